@@ -5,7 +5,10 @@ extern crate core;
 use base64::{engine::general_purpose, Engine as _};
 use clap::Parser;
 use many_error::{ManyError, ManyErrorCode};
+use many_ledger::json::DustConfigJson;
+use many_ledger::storage::dust::{key_for_dust_config, DustConfig};
 use many_ledger::storage::multisig::MultisigTransactionStorage;
+use many_ledger::storage::snapshot::BalancesSnapshot;
 use many_modules::account::features::multisig::{MultisigAccountFeature, MultisigTransactionState};
 use many_modules::account::features::{FeatureSet, TryCreateFeature};
 use many_modules::account::{Account, AddressRoleMap, Role};
@@ -32,6 +35,7 @@ enum Extract {
     Genesis,
     Events,
     Multisig,
+    Snapshot,
 }
 
 // Implement the `FromStr` trait for `Extract`.
@@ -43,6 +47,7 @@ impl FromStr for Extract {
             "genesis" => Ok(Extract::Genesis),
             "events" => Ok(Extract::Events),
             "multisig" => Ok(Extract::Multisig),
+            "snapshot" => Ok(Extract::Snapshot),
             _ => Err(ManyError::unknown("Invalid extract type")),
         }
     }
@@ -69,6 +74,8 @@ pub struct SymbolMeta {
     pub decimals: u64,
     pub owner: Option<Address>,
     pub maximum: Option<TokenAmount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dust: Option<DustConfigJson>,
 }
 
 #[derive(Serialize)]
@@ -156,6 +163,7 @@ fn main() {
         Extract::Genesis => extract_genesis(&merk),
         Extract::Events => extract_events(&merk),
         Extract::Multisig => extract_multisig(&merk),
+        Extract::Snapshot => extract_snapshot(&merk),
     };
 
     println!("{to_print}");
@@ -233,6 +241,15 @@ fn extract_symbols(merk: &merk::Merk) -> SymbolsJsonRoot {
             minicbor::decode::<TokenInfo>(value.as_ref()).expect("Could not decode token info");
         let mut key_string = String::from_utf8(key.to_vec()).expect("Could not decode symbol key");
         key_string.remove_matches(SYMBOLS_ROOT_DASH);
+        let symbol = Address::from_str(&key_string).expect("Could not decode symbol");
+        let dust = merk
+            .get(&key_for_dust_config(&symbol))
+            .expect("Could not read dust config")
+            .map(|x| {
+                minicbor::decode::<DustConfig>(&x)
+                    .expect("Could not decode dust config")
+                    .into()
+            });
 
         symbols.insert(
             key_string,
@@ -241,6 +258,7 @@ fn extract_symbols(merk: &merk::Merk) -> SymbolsJsonRoot {
                 decimals: info.summary.decimals,
                 owner: info.owner,
                 maximum: info.supply.maximum,
+                dust,
             },
         );
     }
@@ -1174,6 +1192,7 @@ pub enum RoleJson {
     CanTokensUpdate,
     CanTokensAddExtendedInfo,
     CanTokensRemoveExtendedInfo,
+    Observer,
 }
 
 // Implement From Role for RoleJson
@@ -1193,6 +1212,7 @@ impl From<Role> for RoleJson {
             Role::CanTokensUpdate => RoleJson::CanTokensUpdate,
             Role::CanTokensAddExtendedInfo => RoleJson::CanTokensAddExtendedInfo,
             Role::CanTokensRemoveExtendedInfo => RoleJson::CanTokensRemoveExtendedInfo,
+            Role::Observer => RoleJson::Observer,
         }
     }
 }
@@ -1337,3 +1357,8 @@ fn extract_multisig(merk: &merk::Merk) -> String {
     }
     serde_json::to_string_pretty(&multisig_logs).expect("Could not serialize")
 }
+
+fn extract_snapshot(merk: &merk::Merk) -> String {
+    let snapshot = BalancesSnapshot::create(merk).expect("Could not create the snapshot");
+    serde_json::to_string_pretty(&snapshot).expect("Could not serialize")
+}
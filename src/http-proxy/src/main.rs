@@ -1,7 +1,7 @@
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
 use many_client::client::blocking::ManyClient;
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::CoseKeyIdentity;
 use many_modules::kvstore::{GetArgs, GetReturns};
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -146,7 +146,7 @@ fn main() {
     let server_id = server_id.unwrap_or_default();
     let key: Box<dyn Identity> = pem.map_or_else(
         || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-        |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
+        |p| Box::new(read_pem(p).unwrap()),
     );
 
     let client = Client::new(ManyClient::new(server, server_id, key).unwrap());
@@ -1,14 +1,15 @@
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::output::{CommandOutput, OutputFlags};
 use many_client::client::blocking::ManyClient;
 use many_error::{ManyError, Reason};
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::CoseKeyIdentity;
-use many_modules::kvstore::list::{ListArgs, ListReturns};
-use many_modules::kvstore::{KeyFilterType, TransferArgs};
+use many_modules::kvstore::list::{ListArgs, ListByOwnerArgs, ListByOwnerReturns, ListReturns};
+use many_modules::kvstore::{AcceptTransferArgs, KeyFilterType, OfferTransferArgs, TransferArgs};
 use many_modules::r#async::{StatusArgs, StatusReturn};
 use many_modules::{kvstore, r#async};
 use many_protocol::ResponseMessage;
-use many_types::{Either, SortOrder};
+use many_types::{Either, SortOrder, Timestamp};
 use std::collections::BTreeMap;
 use std::io::Read;
 use std::path::PathBuf;
@@ -53,6 +54,9 @@ struct Opts {
     #[clap(long, arg_enum, default_value_t = LogStrategy::Terminal)]
     logmode: LogStrategy,
 
+    #[clap(flatten)]
+    output_flags: OutputFlags,
+
     #[clap(subcommand)]
     subcommand: SubCommand,
 }
@@ -68,14 +72,29 @@ enum SubCommand {
     /// Put a value in the store.
     Put(PutOpt),
 
+    /// Put a value at the key derived from its content, and print the key.
+    PutCas(PutCasOpt),
+
     /// Disable a value from the store.
     Disable(DisableOpt),
 
     /// Transfer ownership of a key.
     Transfer(TransferOpt),
 
+    /// Offer the ownership of a key, to be accepted by the new owner.
+    OfferTransfer(OfferTransferOpt),
+
+    /// Accept a transfer of ownership offered to you.
+    AcceptTransfer(AcceptTransferOpt),
+
     /// List key owned by sender
     List(ListOpt),
+
+    /// List the keys owned by an address, a page at a time.
+    ListByOwner(ListByOwnerOpt),
+
+    /// Set who can read a key and put values at it.
+    SetAcl(SetAclOpt),
 }
 
 #[derive(Debug, Parser)]
@@ -120,6 +139,17 @@ struct PutOpt {
     stdin: bool,
 }
 
+#[derive(Debug, Parser)]
+struct PutCasOpt {
+    /// The value to put. Use `--stdin` to read the value from STDIN.
+    #[clap(conflicts_with = "stdin")]
+    value: Option<String>,
+
+    /// Use this flag to use STDIN to get the value.
+    #[clap(long, conflicts_with = "value")]
+    stdin: bool,
+}
+
 #[derive(Debug, Parser)]
 struct DisableOpt {
     /// The key to disable.
@@ -147,6 +177,53 @@ struct TransferOpt {
     new_owner: Address,
 }
 
+#[derive(Debug, Parser)]
+struct OfferTransferOpt {
+    /// The key to offer.
+    key: String,
+
+    /// If the key is passed as an hexadecimal string, pass this key.
+    #[clap(long)]
+    hex_key: bool,
+
+    /// The new owner of the key to offer it to.
+    new_owner: Address,
+
+    /// The number of seconds the offer can be accepted for. By default, the
+    /// offer does not expire.
+    #[clap(long)]
+    expires_in_secs: Option<u64>,
+}
+
+#[derive(Debug, Parser)]
+struct AcceptTransferOpt {
+    /// The key to accept.
+    key: String,
+
+    /// If the key is passed as an hexadecimal string, pass this key.
+    #[clap(long)]
+    hex_key: bool,
+}
+
+#[derive(Debug, Parser)]
+struct SetAclOpt {
+    /// The key to set the ACL of.
+    key: String,
+
+    /// If the key is passed as an hexadecimal string, pass this key.
+    #[clap(long)]
+    hex_key: bool,
+
+    /// Only let the owner and the writers read the key.
+    #[clap(long)]
+    restricted: bool,
+
+    /// An identity that can put values at the key, besides its owner. Can be
+    /// passed multiple times.
+    #[clap(long = "writer")]
+    writers: Vec<Address>,
+}
+
 #[derive(Debug, Parser)]
 struct ListOpt {
     /// The order in which to list the keys
@@ -162,7 +239,29 @@ struct ListOpt {
     hex_key: bool,
 }
 
-fn get(client: ManyClient<impl Identity>, key: &[u8], hex: bool) -> Result<(), ManyError> {
+#[derive(Debug, Parser)]
+struct ListByOwnerOpt {
+    /// The owner of the keys.
+    owner: Address,
+
+    /// The maximum number of keys to list.
+    #[clap(long)]
+    count: Option<u64>,
+
+    /// List the keys after this one, printed at the end of the previous page.
+    #[clap(long)]
+    after: Option<String>,
+
+    /// Use this flag if the keys are hexadecimal
+    #[clap(long)]
+    hex_key: bool,
+}
+
+fn get(
+    client: ManyClient<impl Identity>,
+    key: &[u8],
+    hex: bool,
+) -> Result<CommandOutput, ManyError> {
     let arguments = kvstore::GetArgs {
         key: key.to_vec().into(),
     };
@@ -175,21 +274,20 @@ fn get(client: ManyClient<impl Identity>, key: &[u8], hex: bool) -> Result<(), M
             minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
         let value = result.value;
 
-        if let Some(value) = value {
+        let output = CommandOutput::cbor(payload);
+        Ok(if let Some(value) = value {
             if hex {
-                println!("{}", hex::encode(value.as_slice()));
+                output.with_plain(hex::encode(value.as_slice()))
             } else {
-                std::io::Write::write_all(&mut std::io::stdout(), &value).unwrap();
+                output.with_raw_plain(value.into())
             }
         } else {
-            println!("{value:?}");
-        }
-
-        Ok(())
+            output.with_plain(format!("{value:?}"))
+        })
     }
 }
 
-fn query(client: ManyClient<impl Identity>, key: &[u8]) -> Result<(), ManyError> {
+fn query(client: ManyClient<impl Identity>, key: &[u8]) -> Result<CommandOutput, ManyError> {
     let arguments = kvstore::QueryArgs {
         key: key.to_vec().into(),
     };
@@ -203,13 +301,21 @@ fn query(client: ManyClient<impl Identity>, key: &[u8]) -> Result<(), ManyError>
 
         let owner = result.owner.to_string();
 
-        match result.disabled {
-            Some(Either::Left(true)) => println!("{owner}, disabled"),
-            Some(Either::Right(reason)) => println!("{owner}, disabled ({reason})"),
-            _ => println!("{owner}"),
+        let mut plain = match result.disabled {
+            Some(Either::Left(true)) => format!("{owner}, disabled"),
+            Some(Either::Right(reason)) => format!("{owner}, disabled ({reason})"),
+            _ => owner,
+        };
+        if let Some(acl) = result.acl {
+            if acl.read == kvstore::ReadAccess::Restricted {
+                plain.push_str(", read restricted");
+            }
+            for writer in acl.writers {
+                plain.push_str(&format!(", writer {writer}"));
+            }
         }
 
-        Ok(())
+        Ok(CommandOutput::cbor(payload).with_plain(plain))
     }
 }
 
@@ -218,7 +324,7 @@ fn put(
     alt_owner: Option<Address>,
     key: &[u8],
     value: Vec<u8>,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
     let arguments = kvstore::PutArgs {
         key: key.to_vec().into(),
         value: value.into(),
@@ -227,8 +333,29 @@ fn put(
 
     let response = client.call("kvstore.put", arguments)?;
     let payload = wait_response(client, response)?;
-    println!("{}", minicbor::display(&payload));
-    Ok(())
+    Ok(CommandOutput::cbor(payload))
+}
+
+fn put_cas(
+    client: ManyClient<impl Identity>,
+    alt_owner: Option<Address>,
+    value: Vec<u8>,
+) -> Result<CommandOutput, ManyError> {
+    let arguments = kvstore::PutCasArgs {
+        value: value.into(),
+        alternative_owner: alt_owner,
+    };
+
+    let response = client.call("kvstore.putCas", arguments)?;
+    let payload = wait_response(client, response)?;
+    let result: kvstore::PutCasReturn =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+    let plain = format!(
+        "{} ({} references)",
+        hex::encode(&result.key),
+        result.references
+    );
+    Ok(CommandOutput::cbor(payload).with_plain(plain))
 }
 
 fn disable(
@@ -236,7 +363,7 @@ fn disable(
     alt_owner: Option<Address>,
     key: &[u8],
     reason: Option<Reason<u64>>,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
     let arguments = kvstore::DisableArgs {
         key: key.to_vec().into(),
         alternative_owner: alt_owner,
@@ -245,8 +372,7 @@ fn disable(
 
     let response = client.call("kvstore.disable", arguments)?;
     let payload = wait_response(client, response)?;
-    println!("{}", minicbor::display(&payload));
-    Ok(())
+    Ok(CommandOutput::cbor(payload))
 }
 
 fn transfer(
@@ -254,7 +380,7 @@ fn transfer(
     alt_owner: Option<Address>,
     key: Vec<u8>,
     new_owner: Address,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
     let args = TransferArgs {
         key: key.into(),
         alternative_owner: alt_owner,
@@ -263,8 +389,58 @@ fn transfer(
 
     let response = client.call("kvstore.transfer", args)?;
     let payload = wait_response(client, response)?;
-    println!("{}", minicbor::display(&payload));
-    Ok(())
+    Ok(CommandOutput::cbor(payload))
+}
+
+fn offer_transfer(
+    client: ManyClient<impl Identity>,
+    alt_owner: Option<Address>,
+    key: Vec<u8>,
+    new_owner: Address,
+    expires_in_secs: Option<u64>,
+) -> Result<CommandOutput, ManyError> {
+    let args = OfferTransferArgs {
+        key: key.into(),
+        alternative_owner: alt_owner,
+        new_owner,
+        expiration: expires_in_secs.map(|secs| Timestamp::now() + secs),
+    };
+
+    let response = client.call("kvstore.offerTransfer", args)?;
+    let payload = wait_response(client, response)?;
+    Ok(CommandOutput::cbor(payload))
+}
+
+fn accept_transfer(
+    client: ManyClient<impl Identity>,
+    alt_owner: Option<Address>,
+    key: Vec<u8>,
+) -> Result<CommandOutput, ManyError> {
+    let args = AcceptTransferArgs {
+        key: key.into(),
+        alternative_owner: alt_owner,
+    };
+
+    let response = client.call("kvstore.acceptTransfer", args)?;
+    let payload = wait_response(client, response)?;
+    Ok(CommandOutput::cbor(payload))
+}
+
+fn set_acl(
+    client: ManyClient<impl Identity>,
+    alt_owner: Option<Address>,
+    key: Vec<u8>,
+    acl: kvstore::KeyAcl,
+) -> Result<CommandOutput, ManyError> {
+    let args = kvstore::SetAclArgs {
+        key: key.into(),
+        acl,
+        alternative_owner: alt_owner,
+    };
+
+    let response = client.call("kvstore.setAcl", args)?;
+    let payload = wait_response(client, response)?;
+    Ok(CommandOutput::cbor(payload))
 }
 
 fn list(
@@ -272,7 +448,7 @@ fn list(
     order: Option<SortOrder>,
     filter: Option<Vec<KeyFilterType>>,
     hex_key: bool,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
     let args = ListArgs {
         count: None,
         order,
@@ -286,18 +462,58 @@ fn list(
         let result: ListReturns =
             minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
 
-        let keys = result.keys;
+        let keys = result
+            .keys
+            .into_iter()
+            .map(|key| {
+                if hex_key {
+                    Ok(hex::encode(key.as_slice()))
+                } else {
+                    String::from_utf8(key.into()).map_err(ManyError::unknown)
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CommandOutput::cbor(payload).with_plain(keys.join("\n")))
+    }
+}
+
+fn list_by_owner(
+    client: ManyClient<impl Identity>,
+    owner: Address,
+    count: Option<u64>,
+    after: Option<Vec<u8>>,
+    hex_key: bool,
+) -> Result<CommandOutput, ManyError> {
+    let args = ListByOwnerArgs {
+        owner,
+        count,
+        after: after.map(Into::into),
+    };
+    let payload = client.call_("kvstore.listByOwner", args)?;
+    if payload.is_empty() {
+        Err(ManyError::unexpected_empty_response())
+    } else {
+        let result: ListByOwnerReturns =
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
 
-        for key in keys {
+        let encode = |key: Vec<u8>| {
             if hex_key {
-                println!("{}", hex::encode(key.as_slice()));
+                Ok(hex::encode(key))
             } else {
-                let key = String::from_utf8(key.into()).map_err(ManyError::unknown)?;
-                println!("{key}");
+                String::from_utf8(key).map_err(ManyError::unknown)
             }
+        };
+        let mut lines = result
+            .keys
+            .into_iter()
+            .map(|key| encode(key.into()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(next) = result.next {
+            lines.push(format!("(more keys after {})", encode(next.into())?));
         }
 
-        Ok(())
+        Ok(CommandOutput::cbor(payload).with_plain(lines.join("\n")))
     }
 }
 
@@ -376,6 +592,7 @@ fn main() {
         verbose,
         quiet,
         logmode,
+        output_flags,
     } = Opts::parse();
 
     let verbose_level = 2 + verbose - quiet;
@@ -410,7 +627,7 @@ fn main() {
 
     let key = pem.map_or_else(
         || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-        |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
+        |p| Box::new(read_pem(p).unwrap()),
     );
 
     let client = ManyClient::new(server, server_id, key).unwrap();
@@ -451,6 +668,16 @@ fn main() {
             };
             put(client, alt_owner, &key, value)
         }
+        SubCommand::PutCas(PutCasOpt { value, stdin }) => {
+            let value = if stdin {
+                let mut value = Vec::new();
+                std::io::stdin().read_to_end(&mut value).unwrap();
+                value
+            } else {
+                value.expect("Must pass a value").into_bytes()
+            };
+            put_cas(client, alt_owner, value)
+        }
         SubCommand::Disable(DisableOpt {
             key,
             hex_key,
@@ -476,12 +703,74 @@ fn main() {
             };
             transfer(client, alt_owner, key, new_owner)
         }
+        SubCommand::OfferTransfer(OfferTransferOpt {
+            key,
+            hex_key,
+            new_owner,
+            expires_in_secs,
+        }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            offer_transfer(client, alt_owner, key, new_owner, expires_in_secs)
+        }
+        SubCommand::AcceptTransfer(AcceptTransferOpt { key, hex_key }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            accept_transfer(client, alt_owner, key)
+        }
         SubCommand::List(ListOpt {
             order,
             filter,
             hex_key,
         }) => list(client, order, filter, hex_key),
-    };
+        SubCommand::ListByOwner(ListByOwnerOpt {
+            owner,
+            count,
+            after,
+            hex_key,
+        }) => {
+            let after = after.map(|key| {
+                if hex_key {
+                    hex::decode(&key).unwrap()
+                } else {
+                    key.into_bytes()
+                }
+            });
+            list_by_owner(client, owner, count, after, hex_key)
+        }
+        SubCommand::SetAcl(SetAclOpt {
+            key,
+            hex_key,
+            restricted,
+            writers,
+        }) => {
+            let key = if hex_key {
+                hex::decode(&key).unwrap()
+            } else {
+                key.into_bytes()
+            };
+            let acl = kvstore::KeyAcl {
+                read: if restricted {
+                    kvstore::ReadAccess::Restricted
+                } else {
+                    kvstore::ReadAccess::Public
+                },
+                writers: writers.into_iter().collect(),
+            };
+            set_acl(client, alt_owner, key, acl)
+        }
+    }
+    .and_then(|output| {
+        output
+            .print(output_flags.output)
+            .map_err(ManyError::unknown)
+    });
 
     if let Err(err) = result {
         error!(
@@ -1,14 +1,16 @@
 use anyhow::anyhow;
 use clap::{ArgGroup, Parser};
 use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::identity::{read_pem, IdentityConfig};
+use many_cli_helpers::output::{CommandOutput, OutputFlags};
 use many_client::client::blocking::ManyClient;
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::CoseKeyIdentity;
 use many_identity_hsm::{Hsm, HsmIdentity, HsmMechanismType, HsmSessionType, HsmUserType};
 use many_modules::r#async::{StatusArgs, StatusReturn};
 use many_modules::{ledger, r#async};
 use many_protocol::ResponseMessage;
 use many_types::ledger::{Symbol, TokenAmount};
+use many_types::memo::MIME_JSON;
 use many_types::Memo;
 use minicbor::data::Tag;
 use minicbor::encode::{Error, Write};
@@ -63,6 +65,9 @@ struct Opts {
     #[clap(flatten)]
     common_flags: many_cli_helpers::CommonCliFlags,
 
+    #[clap(flatten)]
+    output_flags: OutputFlags,
+
     /// Many server URL to connect to.
     #[clap(default_value = "http://localhost:8000")]
     server: String,
@@ -88,6 +93,11 @@ struct Opts {
     #[clap(long, conflicts_with("pem"))]
     keyid: Option<String>,
 
+    /// A JSON5 file listing the key storages of the identity, in order of
+    /// precedence (e.g. an HSM, then a PEM file of the same key as a fallback).
+    #[clap(long, conflicts_with_all(&["pem", "hsm"]))]
+    identity_config: Option<PathBuf>,
+
     #[clap(subcommand)]
     subcommand: SubCommand,
 }
@@ -127,8 +137,9 @@ pub(crate) struct TargetCommandOpt {
     #[clap(long)]
     account: Option<Address>,
 
-    /// The account or target identity.
-    identity: Address,
+    /// The account or target identity. This can also be a name registered
+    /// on the server, which will be resolved to an identity.
+    identity: String,
 
     /// The amount of tokens.
     amount: BigUint,
@@ -141,6 +152,33 @@ pub(crate) struct TargetCommandOpt {
     /// Optional memo
     #[clap(long)]
     memo: Option<String>,
+
+    /// A JSON document to attach to the memo, e.g. remittance data. It is
+    /// sent as an `application/json` part, after the text of `--memo`.
+    #[clap(long)]
+    memo_json: Option<String>,
+}
+
+/// The memo of a transaction, from its text and JSON parts.
+pub(crate) fn build_memo(
+    text: Option<String>,
+    json: Option<String>,
+) -> Result<Option<Memo>, anyhow::Error> {
+    let memo = text
+        .map(|text| Memo::try_from(text).map_err(|e| anyhow!("Invalid memo: {e}")))
+        .transpose()?;
+    let Some(json) = json else {
+        return Ok(memo);
+    };
+
+    serde_json::from_str::<serde_json::Value>(&json)
+        .map_err(|e| anyhow!("Invalid JSON memo: {e}"))?;
+    match memo {
+        Some(mut memo) => memo.push_json(json).map(|_| memo),
+        None => Memo::typed(MIME_JSON, json),
+    }
+    .map(Some)
+    .map_err(|e| anyhow!("Invalid JSON memo: {e}"))
 }
 
 pub fn resolve_symbol(
@@ -165,7 +203,7 @@ fn balance(
     client: ManyClient<impl Identity>,
     account: Option<Address>,
     symbols: Vec<String>,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     // Get info.
     let info: ledger::InfoReturns = minicbor::decode(&client.call_("ledger.info", ())?)?;
     let local_names: BTreeMap<String, Symbol> = info
@@ -202,15 +240,25 @@ fn balance(
         Err(anyhow!("Unexpected empty response.").into())
     } else {
         let balance: ledger::BalanceReturns = minicbor::decode(&payload).unwrap();
+        let mut lines = Vec::new();
+        let mut json = Vec::new();
         for (symbol, amount) in balance.balances {
-            if let Some(symbol_name) = info.local_names.get(&symbol) {
-                println!("{amount:>12} {symbol_name} ({symbol})");
+            let symbol_name = info.local_names.get(&symbol);
+            if let Some(symbol_name) = symbol_name {
+                lines.push(format!("{amount:>12} {symbol_name} ({symbol})"));
             } else {
-                println!("{amount:>12} {symbol}");
+                lines.push(format!("{amount:>12} {symbol}"));
             }
+            json.push(serde_json::json!({
+                "symbol": symbol.to_string(),
+                "name": symbol_name,
+                "amount": amount.to_string(),
+            }));
         }
 
-        Ok(())
+        Ok(CommandOutput::cbor(payload)
+            .with_plain(lines.join("\n"))
+            .with_json(json.into()))
     }
 }
 
@@ -276,12 +324,13 @@ pub(crate) fn wait_response(
 fn send(
     client: ManyClient<impl Identity>,
     from: Address,
-    to: Address,
+    to: String,
     amount: BigUint,
     symbol: String,
     memo: Option<Memo>,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let symbol = resolve_symbol(&client, symbol)?;
+    let to = client.resolve_address(&to)?;
 
     if from.is_anonymous() {
         Err(anyhow!("Cannot send tokens from anonymous.").into())
@@ -292,21 +341,23 @@ fn send(
             symbol,
             amount: TokenAmount::from(amount),
             memo,
+            decimal_amount: None,
         };
         let response = client.call("ledger.send", arguments)?;
         let payload = wait_response(client, response)?;
-        println!("{}", minicbor::display(&payload));
-        Ok(())
+        Ok(CommandOutput::cbor(payload))
     }
 }
 
 fn main() {
     let Opts {
         common_flags,
+        output_flags,
         pem,
         module,
         slot,
         keyid,
+        identity_config,
         server,
         server_id,
         subcommand,
@@ -314,9 +365,13 @@ fn main() {
 
     common_flags.init_logging().unwrap();
 
-    let key: Box<dyn Identity> = if let (Some(module), Some(slot), Some(keyid)) =
-        (module, slot, keyid)
-    {
+    let key: Box<dyn Identity> = if let Some(path) = identity_config {
+        Box::new(
+            IdentityConfig::read(path)
+                .and_then(|config| config.load())
+                .expect("Unable to load the identity configuration"),
+        )
+    } else if let (Some(module), Some(slot), Some(keyid)) = (module, slot, keyid) {
         trace!("Getting user PIN");
         let pin = rpassword::prompt_password("Please enter the HSM user PIN: ")
             .expect("I/O error when reading HSM PIN");
@@ -341,7 +396,7 @@ fn main() {
     } else {
         pem.map_or_else(
             || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-            |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
+            |p| Box::new(read_pem(p).unwrap()),
         )
     };
 
@@ -351,12 +406,8 @@ fn main() {
         SubCommand::Balance(BalanceOpt { identity, symbols }) => {
             let identity = identity.map(|identity| {
                 Address::from_str(&identity)
-                    .or_else(|_| {
-                        let bytes = std::fs::read_to_string(PathBuf::from(identity))?;
-
-                        Ok(CoseKeyIdentity::from_pem(bytes).unwrap().address())
-                    })
-                    .map_err(|_: std::io::Error| ())
+                    .or_else(|_| read_pem(PathBuf::from(identity)).map(|key| key.address()))
+                    .map_err(|_: anyhow::Error| ())
                     .expect("Unable to decode identity command-line argument")
             });
 
@@ -368,20 +419,17 @@ fn main() {
             amount,
             symbol,
             memo,
+            memo_json,
         }) => {
             let from = account.unwrap_or(client_address);
-            send(
-                client,
-                from,
-                identity,
-                amount,
-                symbol,
-                memo.map(|m| Memo::try_from(m.as_str()).unwrap()),
-            )
+            build_memo(memo, memo_json)
+                .map_err(Into::into)
+                .and_then(|memo| send(client, from, identity, amount, symbol, memo))
         }
         SubCommand::Multisig(opts) => multisig::multisig(client, opts),
         SubCommand::Token(opts) => tokens::tokens(client, opts),
-    };
+    }
+    .and_then(|output| output.print(output_flags.output).map_err(Into::into));
 
     if let Err(err) = result {
         error!("{err}");
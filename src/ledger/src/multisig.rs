@@ -1,6 +1,7 @@
 use crate::TargetCommandOpt;
 use clap::Parser;
 use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::output::CommandOutput;
 use many_client::client::blocking::ManyClient;
 use many_identity::{Address, Identity};
 use many_modules::account::features::multisig;
@@ -8,9 +9,8 @@ use many_modules::{events, ledger};
 use many_protocol::ResponseMessage;
 use many_types::ledger::TokenAmount;
 use many_types::memo::MemoLegacy;
-use many_types::Memo;
+use many_types::{Memo, Timestamp};
 use minicbor::bytes::ByteVec;
-use tracing::info;
 
 #[derive(Parser)]
 pub struct CommandOpt {
@@ -62,8 +62,20 @@ enum SubcommandOpt {
     /// Show the information of a multisig transaction.
     Info(TransactionOpt),
 
+    /// Comment on a pending transaction.
+    Comment(CommentOpt),
+
     /// Set new defaults for the multisig account.
     SetDefaults(SetDefaultsOpt),
+
+    /// Delegate your approvals for an account to another address.
+    Delegate(DelegateOpt),
+
+    /// Revoke your delegation for an account.
+    RevokeDelegation {
+        /// The account to revoke the delegation for.
+        account: Address,
+    },
 }
 
 #[derive(Parser)]
@@ -86,6 +98,35 @@ struct TransactionOpt {
     token: ByteVec,
 }
 
+#[derive(Parser)]
+struct CommentOpt {
+    #[clap(flatten)]
+    transaction: TransactionOpt,
+
+    /// The text of the comment.
+    memo: String,
+
+    /// The index of the comment to reply to.
+    #[clap(long)]
+    reply_to: Option<u64>,
+}
+
+#[derive(Parser)]
+struct DelegateOpt {
+    /// The account to delegate approvals for.
+    account: Address,
+
+    /// The address to delegate to.
+    delegate: Address,
+
+    /// How long the delegation lasts, from now.
+    duration: humantime::Duration,
+
+    /// Memo to use for the delegation.
+    #[clap(long)]
+    memo: Option<String>,
+}
+
 #[derive(Parser)]
 struct MultisigArgOpt {
     /// The number of approvals needed to execute a transaction.
@@ -109,13 +150,14 @@ fn submit_send(
     opts: TargetCommandOpt,
     memo: Option<String>,
     legacy_memo: Option<String>,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let TargetCommandOpt {
         account: from,
         identity,
         amount,
         symbol,
         memo: send_memo,
+        memo_json,
     } = opts;
     let MultisigArgOpt {
         threshold,
@@ -123,12 +165,14 @@ fn submit_send(
         execute_automatically,
     } = multisig_arg;
     let symbol = crate::resolve_symbol(&client, symbol)?;
+    let to = client.resolve_address(&identity)?;
     let transaction = events::AccountMultisigTransaction::Send(ledger::SendArgs {
         from: from.or(Some(account)),
-        to: identity,
+        to,
         symbol,
         amount: TokenAmount::from(amount),
-        memo: send_memo.map(|m| Memo::try_from(m.as_str()).unwrap()),
+        memo: crate::build_memo(send_memo, memo_json)?,
+        decimal_amount: None,
     });
     let arguments = multisig::SubmitTransactionArgs {
         account,
@@ -145,11 +189,10 @@ fn submit_send(
     let payload = crate::wait_response(client, response)?;
     let result: multisig::SubmitTransactionReturn = minicbor::decode(&payload)?;
 
-    info!(
+    Ok(CommandOutput::cbor(payload).with_plain(format!(
         "Transaction Token: {}",
         hex::encode(result.token.as_slice())
-    );
-    Ok(())
+    )))
 }
 
 fn submit_set_defaults(
@@ -158,7 +201,7 @@ fn submit_set_defaults(
     multisig_arg: MultisigArgOpt,
     target: Address,
     opts: MultisigArgOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let MultisigArgOpt {
         threshold,
         timeout,
@@ -187,11 +230,10 @@ fn submit_set_defaults(
     let payload = crate::wait_response(client, response)?;
     let result: multisig::SubmitTransactionReturn = minicbor::decode(&payload)?;
 
-    info!(
+    Ok(CommandOutput::cbor(payload).with_plain(format!(
         "Transaction Token: {}",
         hex::encode(result.token.as_slice())
-    );
-    Ok(())
+    )))
 }
 
 fn submit(
@@ -201,7 +243,7 @@ fn submit(
     opts: SubmitOpt,
     memo: Option<String>,
     legacy_memo: Option<String>,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     match opts {
         SubmitOpt::Send(target) => {
             submit_send(client, account, multisig_arg, target, memo, legacy_memo)
@@ -216,64 +258,79 @@ fn submit(
 fn approve(
     client: ManyClient<impl Identity>,
     opts: TransactionOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let arguments = multisig::ApproveArgs { token: opts.token };
     let response = client.call("account.multisigApprove", arguments)?;
 
     let payload = crate::wait_response(client, response)?;
     let _result: multisig::ApproveReturn = minicbor::decode(&payload)?;
 
-    info!("Approved.");
-
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain("Approved."))
 }
 
 fn revoke(
     client: ManyClient<impl Identity>,
     opts: TransactionOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let arguments = multisig::RevokeArgs { token: opts.token };
     let response = client.call("account.multisigRevoke", arguments)?;
 
     let payload = crate::wait_response(client, response)?;
     let _result: multisig::RevokeReturn = minicbor::decode(&payload)?;
 
-    info!("Revoked.");
-
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain("Revoked."))
 }
 
 fn execute(
     client: ManyClient<impl Identity>,
     opts: TransactionOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let arguments = multisig::ExecuteArgs { token: opts.token };
     let response = client.call("account.multisigExecute", arguments)?;
 
     let payload = crate::wait_response(client, response)?;
     let result: ResponseMessage = minicbor::decode(&payload)?;
 
-    info!("Executed:");
-    println!("{}", minicbor::display(&result.data?));
-    Ok(())
+    let data = result.data?;
+    let plain = format!("Executed:\n{}", minicbor::display(&data));
+    Ok(CommandOutput::cbor(data).with_plain(plain))
 }
 
-fn info(client: ManyClient<impl Identity>, opts: TransactionOpt) -> Result<(), ClientServerError> {
+fn info(
+    client: ManyClient<impl Identity>,
+    opts: TransactionOpt,
+) -> Result<CommandOutput, ClientServerError> {
     let arguments = multisig::InfoArgs { token: opts.token };
     let response = client.call("account.multisigInfo", arguments)?;
 
     let payload = crate::wait_response(client, response)?;
     let result: multisig::InfoReturn = minicbor::decode(&payload)?;
 
-    println!("{result:#?}");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(format!("{result:#?}")))
+}
+
+fn comment(
+    client: ManyClient<impl Identity>,
+    opts: CommentOpt,
+) -> Result<CommandOutput, ClientServerError> {
+    let arguments = multisig::CommentArgs {
+        token: opts.transaction.token,
+        memo: Memo::try_from(opts.memo)?,
+        reply_to: opts.reply_to,
+    };
+    let response = client.call("account.multisigComment", arguments)?;
+
+    let payload = crate::wait_response(client, response)?;
+    let result: multisig::CommentReturn = minicbor::decode(&payload)?;
+
+    Ok(CommandOutput::cbor(payload).with_plain(format!("Comment {} added.", result.id)))
 }
 
 fn set_defaults(
     client: ManyClient<impl Identity>,
     account: Address,
     opts: MultisigArgOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let arguments = multisig::SetDefaultsArgs {
         account,
         threshold: opts.threshold,
@@ -285,14 +342,53 @@ fn set_defaults(
     let payload = crate::wait_response(client, response)?;
     let _result: multisig::SetDefaultsReturn = minicbor::decode(&payload)?;
 
-    info!("Defaults set.");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain("Defaults set."))
+}
+
+fn delegate(
+    client: ManyClient<impl Identity>,
+    opts: DelegateOpt,
+) -> Result<CommandOutput, ClientServerError> {
+    let end = std::time::SystemTime::now() + *opts.duration;
+    let arguments = multisig::DelegateArgs {
+        account: opts.account,
+        delegate: opts.delegate,
+        start: None,
+        end: Timestamp::from_system_time(end)?,
+        memo: opts.memo.map(Memo::try_from).transpose()?,
+    };
+    let response = client.call("account.delegate", arguments)?;
+
+    let payload = crate::wait_response(client, response)?;
+    let _result: multisig::DelegateReturn = minicbor::decode(&payload)?;
+
+    Ok(CommandOutput::cbor(payload).with_plain(format!(
+        "Delegated to {} until {}.",
+        opts.delegate,
+        humantime::format_rfc3339_seconds(end)
+    )))
+}
+
+fn revoke_delegation(
+    client: ManyClient<impl Identity>,
+    account: Address,
+) -> Result<CommandOutput, ClientServerError> {
+    let arguments = multisig::RevokeDelegationArgs {
+        account,
+        memo: None,
+    };
+    let response = client.call("account.revokeDelegation", arguments)?;
+
+    let payload = crate::wait_response(client, response)?;
+    let _result: multisig::RevokeDelegationReturn = minicbor::decode(&payload)?;
+
+    Ok(CommandOutput::cbor(payload).with_plain("Delegation revoked."))
 }
 
 pub fn multisig(
     client: ManyClient<impl Identity>,
     opts: CommandOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     match opts.subcommand {
         SubcommandOpt::Submit {
             account,
@@ -305,9 +401,12 @@ pub fn multisig(
         SubcommandOpt::Revoke(sub_opts) => revoke(client, sub_opts),
         SubcommandOpt::Execute(sub_opts) => execute(client, sub_opts),
         SubcommandOpt::Info(sub_opts) => info(client, sub_opts),
+        SubcommandOpt::Comment(sub_opts) => comment(client, sub_opts),
         SubcommandOpt::SetDefaults(SetDefaultsOpt {
             target_account,
             opts,
         }) => set_defaults(client, target_account, opts),
+        SubcommandOpt::Delegate(sub_opts) => delegate(client, sub_opts),
+        SubcommandOpt::RevokeDelegation { account } => revoke_delegation(client, account),
     }
 }
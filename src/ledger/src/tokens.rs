@@ -1,15 +1,16 @@
 use anyhow::anyhow;
 use clap::{Args, Parser};
 use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::output::CommandOutput;
 use many_client::client::blocking::ManyClient;
 use many_identity::{Address, Identity};
 use many_modules::ledger::extended_info::visual_logo::VisualTokenLogo;
 use many_modules::ledger::extended_info::TokenExtendedInfo;
 use many_modules::ledger::{
-    TokenAddExtendedInfoArgs, TokenAddExtendedInfoReturns, TokenBurnArgs, TokenBurnReturns,
-    TokenCreateArgs, TokenCreateReturns, TokenInfoArgs, TokenInfoReturns, TokenMintArgs,
-    TokenMintReturns, TokenRemoveExtendedInfoArgs, TokenRemoveExtendedInfoReturns, TokenUpdateArgs,
-    TokenUpdateReturns,
+    DustConfig, DustPolicy, TokenAddExtendedInfoArgs, TokenAddExtendedInfoReturns, TokenBurnArgs,
+    TokenBurnReturns, TokenCreateArgs, TokenCreateReturns, TokenInfoArgs, TokenInfoReturns,
+    TokenMintArgs, TokenMintReturns, TokenRemoveExtendedInfoArgs, TokenRemoveExtendedInfoReturns,
+    TokenUpdateArgs, TokenUpdateReturns,
 };
 use many_types::cbor::CborNull;
 use many_types::ledger::{LedgerTokensAddressMap, TokenAmount, TokenInfoSummary, TokenMaybeOwner};
@@ -91,6 +92,31 @@ struct InitialDistribution {
     amount: u64,
 }
 
+#[derive(Args)]
+struct DustOpt {
+    /// The minimum balance of the token. Zero removes the minimum.
+    #[clap(long)]
+    dust_threshold: Option<u64>,
+
+    /// Send the remainder along with transfers that would leave less than
+    /// the minimum, instead of refusing them.
+    #[clap(long, requires = "dust_threshold")]
+    dust_sweep: bool,
+}
+
+impl DustOpt {
+    fn config(self) -> Option<DustConfig> {
+        self.dust_threshold.map(|threshold| DustConfig {
+            threshold: threshold.into(),
+            policy: if self.dust_sweep {
+                DustPolicy::Sweep
+            } else {
+                DustPolicy::Reject
+            },
+        })
+    }
+}
+
 #[derive(Parser)]
 struct CreateTokenOpt {
     name: String,
@@ -107,6 +133,9 @@ struct CreateTokenOpt {
     #[clap(long)]
     maximum_supply: Option<u64>,
 
+    #[clap(flatten)]
+    dust: DustOpt,
+
     #[clap(subcommand)]
     extended_info: Option<CreateExtInfoOpt>,
 
@@ -132,6 +161,9 @@ struct UpdateTokenOpt {
     #[clap(value_parser = token_maybe_owner)]
     owner: Option<TokenMaybeOwner>,
 
+    #[clap(flatten)]
+    dust: DustOpt,
+
     #[clap(long)]
     #[clap(parse(try_from_str = Memo::try_from))]
     memo: Option<Memo>,
@@ -236,7 +268,7 @@ fn create_ext_info(opts: CreateExtInfoOpt) -> TokenExtendedInfo {
 fn create_token(
     client: ManyClient<impl Identity>,
     opts: CreateTokenOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let extended_info = opts.extended_info.map(create_ext_info);
 
     let args = TokenCreateArgs {
@@ -250,19 +282,19 @@ fn create_token(
         maximum_supply: opts.maximum_supply.map(TokenAmount::from),
         extended_info,
         memo: opts.memo,
+        dust: opts.dust.config(),
     };
     let response = client.call("tokens.create", args)?;
     let payload = crate::wait_response(client, response)?;
     let result: TokenCreateReturns = minicbor::decode(&payload)?;
 
-    println!("{result:#?}");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(format!("{result:#?}")))
 }
 
 fn update_token(
     client: ManyClient<impl Identity>,
     opts: UpdateTokenOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let args = TokenUpdateArgs {
         symbol: opts.symbol,
         name: opts.name,
@@ -270,18 +302,18 @@ fn update_token(
         decimals: opts.decimals,
         owner: opts.owner,
         memo: opts.memo,
+        dust: opts.dust.config(),
     };
     let response = client.call("tokens.update", args)?;
     let payload = crate::wait_response(client, response)?;
     let _result: TokenUpdateReturns = minicbor::decode(&payload)?;
-
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(""))
 }
 
 fn add_ext_info(
     client: ManyClient<impl Identity>,
     opts: AddExtInfoOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let extended_info = create_ext_info(opts.ext_info_type);
 
     let args = TokenAddExtendedInfoArgs {
@@ -292,13 +324,13 @@ fn add_ext_info(
     let response = client.call("tokens.addExtendedInfo", args)?;
     let payload = crate::wait_response(client, response)?;
     let _result: TokenAddExtendedInfoReturns = minicbor::decode(&payload)?;
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(""))
 }
 
 fn remove_ext_info(
     client: ManyClient<impl Identity>,
     opts: RemoveExtInfoOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let args = TokenRemoveExtendedInfoArgs {
         symbol: opts.symbol,
         extended_info: opts.indices,
@@ -307,10 +339,13 @@ fn remove_ext_info(
     let response = client.call("tokens.removeExtendedInfo", args)?;
     let payload = crate::wait_response(client, response)?;
     let _result: TokenRemoveExtendedInfoReturns = minicbor::decode(&payload)?;
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(""))
 }
 
-fn info_token(client: ManyClient<impl Identity>, opts: InfoOpt) -> Result<(), ClientServerError> {
+fn info_token(
+    client: ManyClient<impl Identity>,
+    opts: InfoOpt,
+) -> Result<CommandOutput, ClientServerError> {
     let args = TokenInfoArgs {
         symbol: opts.symbol,
         extended_info: opts.indices,
@@ -319,11 +354,13 @@ fn info_token(client: ManyClient<impl Identity>, opts: InfoOpt) -> Result<(), Cl
     let payload = crate::wait_response(client, response)?;
     let result: TokenInfoReturns = minicbor::decode(&payload)?;
 
-    println!("{result:#?}");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(format!("{result:#?}")))
 }
 
-fn mint_token(client: ManyClient<impl Identity>, opts: MintOpt) -> Result<(), ClientServerError> {
+fn mint_token(
+    client: ManyClient<impl Identity>,
+    opts: MintOpt,
+) -> Result<CommandOutput, ClientServerError> {
     let symbol = Address::try_from(opts.symbol.as_str()).or_else(|_| {
         // Get symbol address from name
         let info: many_modules::ledger::InfoReturns =
@@ -347,11 +384,13 @@ fn mint_token(client: ManyClient<impl Identity>, opts: MintOpt) -> Result<(), Cl
     let payload = crate::wait_response(client, response)?;
     let result: TokenMintReturns = minicbor::decode(&payload)?;
 
-    println!("{result:#?}");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(format!("{result:#?}")))
 }
 
-fn burn_token(client: ManyClient<impl Identity>, opts: BurnOpt) -> Result<(), ClientServerError> {
+fn burn_token(
+    client: ManyClient<impl Identity>,
+    opts: BurnOpt,
+) -> Result<CommandOutput, ClientServerError> {
     let symbol = Address::try_from(opts.symbol.as_str()).or_else(|_| {
         // Get symbol address from name
         let info: many_modules::ledger::InfoReturns =
@@ -376,14 +415,13 @@ fn burn_token(client: ManyClient<impl Identity>, opts: BurnOpt) -> Result<(), Cl
     let payload = crate::wait_response(client, response)?;
     let result: TokenBurnReturns = minicbor::decode(&payload)?;
 
-    println!("{result:#?}");
-    Ok(())
+    Ok(CommandOutput::cbor(payload).with_plain(format!("{result:#?}")))
 }
 
 pub fn tokens(
     client: ManyClient<impl Identity>,
     opts: CommandOpt,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     match opts.subcommand {
         SubcommandOpt::Create(opts) => create_token(client, opts),
         SubcommandOpt::Update(opts) => update_token(client, opts),
@@ -1,18 +1,24 @@
 use crate::migration::error_code::LEGACY_ERROR_CODE_TRIGGER;
+use crate::migration::proposal::PROPOSAL_FILTERING_TRIGGER;
 use crate::migration::{AbciAppMigrations, MIGRATIONS};
+use crate::router::{Backend, Router};
+use crate::tx_events;
 use coset::{CborSerializable, CoseSign1};
-use many_client::client::blocking::{block_on, ManyClient};
+use many_client::client::blocking::block_on;
 use many_error::{ManyError, ManyErrorCode};
 use many_identity::{Address, AnonymousIdentity};
 use many_migration::MigrationConfig;
-use many_modules::abci_backend::{AbciBlock, AbciCommitInfo, AbciInfo};
-use many_protocol::{RequestMessage, ResponseMessage};
-use many_server::RequestValidator;
-use reqwest::{IntoUrl, Url};
+use many_modules::abci_backend::{
+    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, AbciProposal, BlockGasMeter,
+    PrepareProposalReturn, ProcessProposalReturn,
+};
+use many_protocol::{encode_cose_sign1_from_response, RequestMessage, ResponseMessage};
+use many_server::{telemetry, RequestValidator};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::sync::{Arc, RwLock};
 use tendermint_abci::Application;
 use tendermint_proto::abci::*;
-use tracing::{debug, error};
+use tracing::{debug, error, warn};
 
 lazy_static::lazy_static!(
     static ref EPOCH: many_types::Timestamp = many_types::Timestamp::new(0).unwrap();
@@ -35,6 +41,7 @@ enum ManyAbciCheckErrorCodes {
     CannotGetSystemTimeError = 8,
     TimestampOutsideOfRangeError = 9,
     ValidationError = 10,
+    GasLimitError = 11,
 }
 
 enum ManyAbciDeliverErrorCodes {
@@ -44,53 +51,40 @@ enum ManyAbciDeliverErrorCodes {
     CoseDeserializeError = 2,
     TransportResponseError = 3,
     RwLockPoisonedError = 11,
+    BlockGasLimitExceeded = 12,
 }
 
 pub const MANYABCI_DEFAULT_TIMEOUT: u64 = 300;
 
-fn get_abci_info_(client: &ManyClient<AnonymousIdentity>) -> Result<AbciInfo, ManyError> {
-    client
-        .call_("abci.info", ())
-        .and_then(|payload| minicbor::decode(&payload).map_err(ManyError::deserialization_error))
-}
-
 #[derive(Clone)]
 pub struct AbciApp {
     app_name: String,
-    many_client: ManyClient<AnonymousIdentity>,
-    many_url: Url,
+    router: Router,
     cache: Arc<RwLock<dyn RequestValidator + Send + Sync>>,
 
     /// We need interior mutability, safely.
     migrations: Arc<RwLock<AbciAppMigrations>>,
     block_time: Arc<RwLock<Option<u64>>>,
+    gas: Arc<RwLock<BlockGasMeter>>,
 }
 
 impl AbciApp {
-    /// Constructor.
-    pub fn create<U>(
-        many_url: U,
-        server_id: Address,
+    /// Constructor. The name of the application is the name of the default
+    /// application of the router.
+    pub fn create(
+        router: Router,
         migration_config: Option<MigrationConfig>,
-    ) -> Result<Self, String>
-    where
-        U: IntoUrl,
-    {
-        let many_url = many_url.into_url().map_err(|e| e.to_string())?;
-
-        // TODO: Get the server ID from the many server.
-        // let server_id = if server_id.is_anonymous() {
-        //     server_id
-        // } else {
-        //     server_id
-        // };
-
-        let many_client = ManyClient::new(many_url.clone(), server_id, AnonymousIdentity)?;
-        let status = many_client.status().map_err(|x| x.to_string())?;
+    ) -> Result<Self, String> {
+        let status = router
+            .default_backend()
+            .client
+            .status()
+            .map_err(|x| x.to_string())?;
         let app_name = status.name;
 
         let migrations = RwLock::new({
-            let AbciInfo { height, .. } = get_abci_info_(&many_client)
+            let AbciInfo { height, .. } = router
+                .info()
                 .map_err(|e| format!("Unable to call abci.info: {e}"))?;
 
             let migrations = migration_config
@@ -102,13 +96,24 @@ impl AbciApp {
             migrations
         });
 
+        let AbciInit {
+            gas,
+            block_gas_limit,
+            ..
+        } = router
+            .init()
+            .map_err(|e| format!("Unable to call abci.init: {e}"))?;
+
         Ok(Self {
             app_name,
-            many_url,
-            many_client,
+            router,
             cache: Arc::new(RwLock::new(())),
             migrations: Arc::new(migrations),
             block_time: Arc::new(RwLock::new(None)),
+            gas: Arc::new(RwLock::new(BlockGasMeter::new(
+                gas.unwrap_or_default(),
+                block_gas_limit,
+            ))),
         })
     }
 
@@ -118,6 +123,24 @@ impl AbciApp {
     }
 
     fn do_check_tx(&self, tx: impl AsRef<[u8]>) -> Result<(), (ManyAbciCheckErrorCodes, String)> {
+        let time = *self.block_time.read().map_err(|log| {
+            (
+                ManyAbciCheckErrorCodes::RwLockPoisonedError,
+                log.to_string(),
+            )
+        })?;
+        self.validate_tx(tx, time, true)
+    }
+
+    /// Validate an envelope at a block time, or now if the time is unknown.
+    /// If `local_checks` is set, also run the request validator, whose result
+    /// depends on the state of this node and not only on the blockchain.
+    fn validate_tx(
+        &self,
+        tx: impl AsRef<[u8]>,
+        time: Option<u64>,
+        local_checks: bool,
+    ) -> Result<(), (ManyAbciCheckErrorCodes, String)> {
         use many_types::Timestamp;
         let cose = CoseSign1::from_slice(tx.as_ref()).map_err(|log| {
             (
@@ -133,7 +156,7 @@ impl AbciApp {
         })?;
 
         // Run the same validator as the server would.
-        {
+        if local_checks {
             let validator = self.cache.read().map_err(|log| {
                 (
                     ManyAbciCheckErrorCodes::RwLockPoisonedError,
@@ -155,15 +178,8 @@ impl AbciApp {
         }
 
         // Check the time of the transaction.
-        let time = self.block_time.read().map_err(|log| {
-            (
-                ManyAbciCheckErrorCodes::RwLockPoisonedError,
-                log.to_string(),
-            )
-        })?;
         let now = time
-            .as_ref()
-            .map_or_else(|| Ok(Timestamp::now()), |x| Timestamp::new(*x))
+            .map_or_else(|| Ok(Timestamp::now()), Timestamp::new)
             .map_err(|e| (ManyAbciCheckErrorCodes::TimestampError, e.to_string()))?;
 
         let now = now.as_system_time().map_err(|log| {
@@ -183,6 +199,169 @@ impl AbciApp {
             })?;
         Ok(())
     }
+
+    /// The gas a transaction costs, if it can fit in a block.
+    fn check_gas(&self, tx: &[u8]) -> Result<u64, (ManyAbciCheckErrorCodes, String)> {
+        let gas = self.gas.read().map_err(|log| {
+            (
+                ManyAbciCheckErrorCodes::RwLockPoisonedError,
+                log.to_string(),
+            )
+        })?;
+        let cost = gas.cost_of(tx);
+        match gas.limit() {
+            Some(limit) if cost > limit => Err((
+                ManyAbciCheckErrorCodes::GasLimitError,
+                format!("The transaction costs {cost} gas, over the block gas limit of {limit}"),
+            )),
+            _ => Ok(cost),
+        }
+    }
+
+    fn is_proposal_filtering_active(&self) -> bool {
+        self.migrations
+            .read()
+            .map(|m| m.is_active(&PROPOSAL_FILTERING_TRIGGER))
+            .unwrap_or_else(|_| {
+                error!("Migration: Could not acquire migration lock...");
+                false
+            })
+    }
+
+    /// Split the transactions of a proposal by the application they are
+    /// routed to, keeping their order.
+    fn split_by_backend<T: AsRef<[u8]>>(&self, txs: Vec<T>) -> BTreeMap<usize, Vec<T>> {
+        let mut split: BTreeMap<usize, Vec<T>> = BTreeMap::new();
+        for tx in txs {
+            let index = CoseSign1::from_slice(tx.as_ref())
+                .map_or(0, |cose| self.router.route_envelope(&cose));
+            split.entry(index).or_default().push(tx);
+        }
+        split
+    }
+
+    /// Let the MANY applications re-order or remove their transactions from
+    /// a proposal. The transactions of each application follow the ones of
+    /// the applications before it in the router.
+    fn backends_prepare_proposal<T: AsRef<[u8]> + Clone>(
+        &self,
+        txs: Vec<T>,
+        height: u64,
+        time: Option<u64>,
+    ) -> Vec<T> {
+        if !self.router.is_multi_app() {
+            return backend_prepare_proposal(self.router.default_backend(), txs, height, time);
+        }
+        self.split_by_backend(txs)
+            .into_iter()
+            .flat_map(|(index, txs)| {
+                backend_prepare_proposal(&self.router.backends()[index], txs, height, time)
+            })
+            .collect()
+    }
+
+    /// Let every MANY application accept or reject its transactions in a
+    /// proposal. Applications without transactions are asked too, with none.
+    fn backends_process_proposal(
+        &self,
+        txs: &[impl AsRef<[u8]>],
+        height: u64,
+        time: Option<u64>,
+    ) -> bool {
+        let mut split = self.split_by_backend(txs.iter().collect());
+        self.router
+            .backends()
+            .iter()
+            .enumerate()
+            .all(|(index, backend)| {
+                let proposal = AbciProposal {
+                    txs: split
+                        .remove(&index)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|tx| tx.as_ref().to_vec().into())
+                        .collect(),
+                    height,
+                    time,
+                };
+                match backend.call::<ProcessProposalReturn>("abci.processProposal", proposal) {
+                    Ok(ProcessProposalReturn { accept }) => accept,
+                    Err(err) => {
+                        warn!(
+                            "abci.processProposal failed on {}, accepting the proposal: {err}",
+                            backend.url
+                        );
+                        true
+                    }
+                }
+            })
+    }
+
+    /// The merged initialization of the applications, answered by the bridge
+    /// itself since no single application knows every endpoint.
+    fn init_response(&self, envelope: &CoseSign1) -> Result<CoseSign1, ManyError> {
+        let message = RequestMessage::try_from(envelope)?;
+        let init = self.router.init()?;
+        let response = ResponseMessage::from_request(
+            &message,
+            &Address::anonymous(),
+            minicbor::to_vec(init).map_err(ManyError::serialization_error),
+        );
+        encode_cose_sign1_from_response(response, &AnonymousIdentity)
+    }
+}
+
+/// Let a MANY application re-order or remove transactions from a proposal.
+/// Transactions returned by the application that are not part of the
+/// proposal are ignored. If the call fails, the proposal is kept as is.
+fn backend_prepare_proposal<T: AsRef<[u8]> + Clone>(
+    backend: &Backend,
+    txs: Vec<T>,
+    height: u64,
+    time: Option<u64>,
+) -> Vec<T> {
+    let proposal = AbciProposal {
+        txs: txs.iter().map(|tx| tx.as_ref().to_vec().into()).collect(),
+        height,
+        time,
+    };
+    let result = backend.call::<PrepareProposalReturn>("abci.prepareProposal", proposal);
+
+    match result {
+        Ok(PrepareProposalReturn { txs: ordered }) => {
+            let mut available: HashMap<&[u8], &T> =
+                txs.iter().map(|tx| (tx.as_ref(), tx)).collect();
+            ordered
+                .iter()
+                .filter_map(|tx| available.remove(tx.as_slice()).cloned())
+                .collect()
+        }
+        Err(err) => {
+            warn!(
+                "abci.prepareProposal failed on {}, keeping the proposal as is: {err}",
+                backend.url
+            );
+            txs
+        }
+    }
+}
+
+/// Record the request of an envelope on its span, if it can be decoded.
+fn record_request(span: &tracing::Span, cose: &CoseSign1) {
+    if let Ok(message) = RequestMessage::try_from(cose) {
+        telemetry::record_request(span, &message);
+    }
+}
+
+/// Keep the first transactions of a proposal that fit in `max_tx_bytes`.
+fn truncate_txs<T: AsRef<[u8]>>(txs: Vec<T>, max_tx_bytes: i64) -> Vec<T> {
+    let mut total_tx_bytes: i64 = 0;
+    txs.into_iter()
+        .take_while(|tx| {
+            total_tx_bytes += tx.as_ref().len() as i64;
+            total_tx_bytes <= max_tx_bytes
+        })
+        .collect()
 }
 
 impl Application for AbciApp {
@@ -192,7 +371,7 @@ impl Application for AbciApp {
             request.version, request.block_version, request.p2p_version
         );
 
-        let AbciInfo { height, hash } = match get_abci_info_(&self.many_client) {
+        let AbciInfo { height, hash } = match self.router.info() {
             Ok(x) => x,
             Err(err) => {
                 return ResponseInfo {
@@ -224,11 +403,28 @@ impl Application for AbciApp {
                 }
             }
         };
-        let value = match block_on(many_client::client::send_envelope(
-            self.many_url.clone(),
-            cose,
-        )) {
-            Ok(cose_sign) => cose_sign,
+        let span = many_server::request_span!("abci_query");
+        let _enter = span.enter();
+        record_request(&span, &cose);
+
+        let is_init = RequestMessage::try_from(&cose).is_ok_and(|m| m.method == "abci.init");
+        let value = if self.router.is_multi_app() && is_init {
+            self.init_response(&cose)
+        } else {
+            block_on(many_client::client::send_envelope(
+                self.router.backend_for(&cose).url.clone(),
+                cose,
+            ))
+        };
+        let value = match value {
+            Ok(cose_sign) => {
+                if let Ok(response) =
+                    ResponseMessage::from_bytes(cose_sign.payload.as_deref().unwrap_or_default())
+                {
+                    telemetry::record_response(&span, &response);
+                }
+                cose_sign
+            }
 
             Err(err) => {
                 return ResponseQuery {
@@ -253,6 +449,92 @@ impl Application for AbciApp {
         }
     }
 
+    fn prepare_proposal(&self, request: RequestPrepareProposal) -> ResponsePrepareProposal {
+        let RequestPrepareProposal {
+            txs,
+            max_tx_bytes,
+            height,
+            time,
+            ..
+        } = request;
+
+        let time = time.map(|x| x.seconds as u64);
+        let txs = if self.is_proposal_filtering_active() {
+            let mut seen = HashSet::new();
+            txs.into_iter()
+                .filter(|tx| {
+                    if !seen.insert(tx.to_vec()) {
+                        debug!("prepare_proposal: dropped a duplicate envelope");
+                        return false;
+                    }
+                    self.validate_tx(tx, time, true)
+                        .map_err(|(_, log)| debug!("prepare_proposal: dropped an envelope: {log}"))
+                        .is_ok()
+                })
+                .collect()
+        } else {
+            txs
+        };
+
+        // The applications order their transactions whether envelopes are
+        // filtered or not, so their ordering always applies.
+        let txs = self.backends_prepare_proposal(txs, height.max(0) as u64, time);
+
+        let txs = match self.gas.read() {
+            Ok(gas) => gas.fit(txs),
+            Err(_) => {
+                error!("Gas: Could not acquire lock");
+                txs
+            }
+        };
+
+        ResponsePrepareProposal {
+            txs: truncate_txs(txs, max_tx_bytes),
+        }
+    }
+
+    fn process_proposal(&self, request: RequestProcessProposal) -> ResponseProcessProposal {
+        use response_process_proposal::ProposalStatus;
+
+        let reject = |log: String| {
+            warn!("process_proposal: rejected proposal: {log}");
+            ResponseProcessProposal {
+                status: ProposalStatus::Reject as i32,
+            }
+        };
+
+        match self.gas.read() {
+            Ok(gas) if !gas.fits(&request.txs) => {
+                return reject("over the block gas limit".to_string());
+            }
+            Ok(_) => {}
+            Err(_) => error!("Gas: Could not acquire lock"),
+        }
+
+        let time = request.time.map(|x| x.seconds as u64);
+        if self.is_proposal_filtering_active() {
+            // Only checks that give the same result on every node can be used
+            // here, so the request validator (which uses a local cache) is not.
+            let mut seen = HashSet::new();
+            for tx in &request.txs {
+                if !seen.insert(tx.to_vec()) {
+                    return reject("duplicate envelope".to_string());
+                }
+                if let Err((_, log)) = self.validate_tx(tx, time, false) {
+                    return reject(log);
+                }
+            }
+        }
+
+        if !self.backends_process_proposal(&request.txs, request.height.max(0) as u64, time) {
+            return reject("rejected by the MANY application".to_string());
+        }
+
+        ResponseProcessProposal {
+            status: ProposalStatus::Accept as i32,
+        }
+    }
+
     fn begin_block(&self, request: RequestBeginBlock) -> ResponseBeginBlock {
         let (time, height) = request
             .header
@@ -268,7 +550,7 @@ impl Application for AbciApp {
             if let Ok(mut m) = self.migrations.write() {
                 // Since it's impossible to truly handle error here, and
                 // we don't actually want to panic, just ignore any errors.
-                let _ = m.update_at_height(&mut (), height);
+                let _ = m.update_at(&mut (), height, time);
             } else {
                 error!("Migration: Could not acquire migration lock...");
             }
@@ -279,14 +561,20 @@ impl Application for AbciApp {
             .write()
             .map(|mut block_time| *block_time = time)
             .unwrap_or_else(|_| error!("Block time: Could not acquire lock"));
-        let _ = self.many_client.call_("abci.beginBlock", block);
+        self.gas
+            .write()
+            .map(|mut gas| gas.reset())
+            .unwrap_or_else(|_| error!("Gas: Could not acquire lock"));
+        self.router.broadcast("abci.beginBlock", block);
         ResponseBeginBlock { events: vec![] }
     }
 
     fn check_tx(&self, request: RequestCheckTx) -> ResponseCheckTx {
         self.do_check_tx(&request.tx)
-            .map(|_| ResponseCheckTx {
+            .and_then(|_| self.check_gas(&request.tx))
+            .map(|gas_wanted| ResponseCheckTx {
                 code: ManyAbciCheckErrorCodes::Success as u32,
+                gas_wanted: gas_wanted as i64,
                 ..Default::default()
             })
             .unwrap_or_else(|(code, log)| {
@@ -300,6 +588,26 @@ impl Application for AbciApp {
     }
 
     fn deliver_tx(&self, request: RequestDeliverTx) -> ResponseDeliverTx {
+        // Transactions over the block gas limit are not executed. This only
+        // depends on the transactions before it in the block, so every node
+        // skips the same ones.
+        let gas = match self.gas.write().map(|mut gas| gas.consume(&request.tx)) {
+            Ok(Some(gas)) => gas as i64,
+            Ok(None) => {
+                return ResponseDeliverTx {
+                    code: ManyAbciDeliverErrorCodes::BlockGasLimitExceeded as u32,
+                    log: "The block gas limit was reached".to_string(),
+                    ..Default::default()
+                }
+            }
+            Err(_) => {
+                return ResponseDeliverTx {
+                    code: ManyAbciDeliverErrorCodes::RwLockPoisonedError as u32,
+                    ..Default::default()
+                }
+            }
+        };
+
         let cose = match CoseSign1::from_slice(&request.tx) {
             Ok(x) => x,
             Err(err) => {
@@ -310,8 +618,15 @@ impl Application for AbciApp {
                 }
             }
         };
+        let span = many_server::request_span!("abci_deliver_tx");
+        let _enter = span.enter();
+        record_request(&span, &cose);
+        let events = RequestMessage::try_from(&cose)
+            .map(|message| tx_events::request_events(&message))
+            .unwrap_or_default();
+
         match block_on(many_client::client::send_envelope(
-            self.many_url.clone(),
+            self.router.backend_for(&cose).url.clone(),
             cose.clone(),
         )) {
             Ok(cose_sign) => {
@@ -365,10 +680,14 @@ impl Application for AbciApp {
                     }
                 }
 
+                telemetry::record_response(&span, &response);
                 if let Ok(data) = response.to_bytes() {
                     ResponseDeliverTx {
                         code: ManyAbciDeliverErrorCodes::Success as u32,
                         data: data.into(),
+                        gas_wanted: gas,
+                        gas_used: gas,
+                        events,
                         ..Default::default()
                     }
                 } else {
@@ -387,7 +706,7 @@ impl Application for AbciApp {
     }
 
     fn end_block(&self, _request: RequestEndBlock) -> ResponseEndBlock {
-        let _ = self.many_client.call_("abci.endBlock", ());
+        self.router.broadcast("abci.endBlock", ());
         Default::default()
     }
 
@@ -396,17 +715,14 @@ impl Application for AbciApp {
     }
 
     fn commit(&self) -> ResponseCommit {
-        self.many_client.call_("abci.commit", ()).map_or_else(
+        self.router.commit().map_or_else(
             |err| ResponseCommit {
                 data: err.to_string().into_bytes().into(),
                 retain_height: 0,
             },
-            |msg| {
-                let info: AbciCommitInfo = minicbor::decode(&msg).unwrap();
-                ResponseCommit {
-                    data: info.hash.to_vec().into(),
-                    retain_height: info.retain_height as i64,
-                }
+            |info: AbciCommitInfo| ResponseCommit {
+                data: info.hash.to_vec().into(),
+                retain_height: info.retain_height as i64,
             },
         )
     }
@@ -0,0 +1,204 @@
+//! Entropy derived from the commits of the blockchain, served as `random.get`.
+//!
+//! This is not a verifiable random function nor an unbiasable beacon. The
+//! value of a block is a digest of its hash and its commit signatures, all of
+//! which are chosen by validators:
+//! - the proposer picks the transactions, and thus the block hash, and can
+//!   try several blocks before proposing one;
+//! - the last validators to sign see the other signatures, and can withhold
+//!   theirs;
+//! - the next proposer chooses which of the signatures above the quorum are
+//!   part of the canonical commit.
+//!
+//! Every node returns the same value, anyone with the commit can verify it,
+//! and it is not known before the block is committed. Applications should
+//! only use it where no validator gains from biasing it, e.g. to break ties,
+//! or combine it with a commit-reveal scheme of their own.
+use many_client::client::blocking::block_on;
+use many_error::ManyError;
+use many_modules::{abci_frontend, random};
+use sha2::Digest;
+use std::collections::BTreeMap;
+use tendermint::block::CommitSig;
+use tendermint_rpc::{Client, Paging};
+
+/// Prefix of the hashed data, so the value cannot be the hash of anything
+/// else signed on the blockchain. It is part of the values, and stays the
+/// same.
+const DOMAIN: &[u8] = b"many-random-beacon";
+
+fn transport_error(e: tendermint_rpc::Error) -> ManyError {
+    tracing::error!("abci transport: {}", e.to_string());
+    abci_frontend::abci_transport_error(e.to_string())
+}
+
+/// Derive the value of a block from its commit signatures, keyed by the
+/// consensus address of their validator.
+fn commit_entropy(
+    height: u64,
+    block_hash: &[u8],
+    signatures: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Vec<u8> {
+    let mut hasher = sha2::Sha256::new();
+    hasher.update(DOMAIN);
+    hasher.update(height.to_be_bytes());
+    hasher.update(block_hash);
+    for (address, signature) in signatures {
+        hasher.update(address);
+        hasher.update(signature);
+    }
+    hasher.finalize().to_vec()
+}
+
+/// Whether the validators which signed, given with their consensus address
+/// and voting power, hold more than two thirds of the voting power, as
+/// Tendermint requires of a commit.
+fn has_quorum<'a>(
+    validators: impl IntoIterator<Item = (&'a [u8], u64)>,
+    signatures: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> bool {
+    let (total, signed) =
+        validators
+            .into_iter()
+            .fold((0u128, 0u128), |(total, signed), (address, power)| {
+                if signatures.contains_key(address) {
+                    (total + power as u128, signed + power as u128)
+                } else {
+                    (total + power as u128, signed)
+                }
+            });
+    signed * 3 > total * 2
+}
+
+pub struct AbciCommitEntropyImpl<C: Client> {
+    client: C,
+}
+
+impl<C: Client> AbciCommitEntropyImpl<C> {
+    pub fn new(client: C) -> Self {
+        Self { client }
+    }
+}
+
+impl<C: Client + Send + Sync> random::RandomModuleBackend for AbciCommitEntropyImpl<C> {
+    fn get(&self, args: random::GetArgs) -> Result<random::GetReturns, ManyError> {
+        let height = match args.height {
+            Some(height) => height,
+            // The commit of the latest block is only canonical once the next
+            // block includes it.
+            None => block_on(async { self.client.status().await })
+                .map_err(transport_error)?
+                .sync_info
+                .latest_block_height
+                .value()
+                .saturating_sub(1),
+        };
+        let tm_height = tendermint::block::Height::try_from(height)
+            .map_err(|e| ManyError::unknown(e.to_string()))?;
+
+        let commit = block_on(async { self.client.commit(tm_height).await })
+            .map_err(|_| random::height_not_committed(height))?;
+        if !commit.canonical {
+            return Err(random::height_not_committed(height));
+        }
+        let commit = commit.signed_header.commit;
+
+        let signatures: BTreeMap<Vec<u8>, Vec<u8>> = commit
+            .signatures
+            .into_iter()
+            .filter_map(|sig| match sig {
+                CommitSig::BlockIdFlagCommit {
+                    validator_address,
+                    signature: Some(signature),
+                    ..
+                } => Some((
+                    validator_address.as_bytes().to_vec(),
+                    signature.as_bytes().to_vec(),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        // Only use commits signed by more than two thirds of the voting power,
+        // as Tendermint does.
+        let validators = block_on(async { self.client.validators(tm_height, Paging::All).await })
+            .map_err(transport_error)?
+            .validators;
+        if !has_quorum(
+            validators
+                .iter()
+                .map(|v| (v.address.as_bytes(), v.power.value())),
+            &signatures,
+        ) {
+            return Err(random::not_enough_voting_power(height));
+        }
+
+        let block_hash = commit.block_id.hash.as_bytes().to_vec();
+        Ok(random::GetReturns {
+            height,
+            value: commit_entropy(height, &block_hash, &signatures).into(),
+            block_hash: block_hash.into(),
+            signers: signatures.into_keys().map(Into::into).collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn signatures(signers: &[u8]) -> BTreeMap<Vec<u8>, Vec<u8>> {
+        signers
+            .iter()
+            .map(|&i| (vec![i; 20], vec![i + 100; 64]))
+            .collect()
+    }
+
+    #[test]
+    fn entropy() {
+        let value = commit_entropy(5, &[1; 32], &signatures(&[1, 2, 3]));
+        assert_eq!(value.len(), 32);
+        assert_eq!(value, commit_entropy(5, &[1; 32], &signatures(&[3, 1, 2])));
+
+        // Any input changes the value.
+        assert_ne!(value, commit_entropy(6, &[1; 32], &signatures(&[1, 2, 3])));
+        assert_ne!(value, commit_entropy(5, &[2; 32], &signatures(&[1, 2, 3])));
+        assert_ne!(value, commit_entropy(5, &[1; 32], &signatures(&[1, 2])));
+
+        let mut other = signatures(&[1, 2, 3]);
+        other.insert(vec![3; 20], vec![0; 64]);
+        assert_ne!(value, commit_entropy(5, &[1; 32], &other));
+    }
+
+    /// Validators with these voting powers, and the addresses signing in
+    /// `signatures`.
+    fn with_powers<'a>(addresses: &'a [Vec<u8>], powers: &[u64]) -> Vec<(&'a [u8], u64)> {
+        addresses
+            .iter()
+            .map(Vec::as_slice)
+            .zip(powers.iter().copied())
+            .collect()
+    }
+
+    #[test]
+    fn quorum() {
+        let addresses: Vec<Vec<u8>> = (1..=4).map(|i| vec![i; 20]).collect();
+        let validators = |powers: &[u64]| with_powers(&addresses, powers);
+
+        // Exactly two thirds is not enough.
+        assert!(!has_quorum(validators(&[1, 1, 1]), &signatures(&[1, 2])));
+        assert!(has_quorum(validators(&[1, 1, 1]), &signatures(&[1, 2, 3])));
+        assert!(has_quorum(
+            validators(&[1, 1, 1, 1]),
+            &signatures(&[1, 2, 3])
+        ));
+
+        // Voting power counts, not signatures.
+        assert!(has_quorum(validators(&[10, 1, 1, 2]), &signatures(&[1])));
+        assert!(!has_quorum(validators(&[10, 1, 1, 4]), &signatures(&[1])));
+
+        // Signatures of validators outside of the set are ignored.
+        assert!(!has_quorum(validators(&[1, 1, 1]), &signatures(&[1, 2, 9])));
+        assert!(!has_quorum(validators(&[]), &signatures(&[1])));
+    }
+}
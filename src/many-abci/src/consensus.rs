@@ -0,0 +1,256 @@
+use many_client::client::blocking::block_on;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{abci_frontend, consensus};
+use std::collections::BTreeSet;
+use tendermint_rpc::{Client, Paging};
+
+fn transport_error(e: tendermint_rpc::Error) -> ManyError {
+    tracing::error!("abci transport: {}", e.to_string());
+    abci_frontend::abci_transport_error(e.to_string())
+}
+
+/// Proxies selected Tendermint RPC endpoints, so consensus can be monitored
+/// without exposing the RPC port.
+pub struct AbciConsensusModuleImpl<C: Client> {
+    client: C,
+
+    /// The addresses allowed to query consensus data.
+    operators: BTreeSet<Address>,
+}
+
+impl<C: Client> AbciConsensusModuleImpl<C> {
+    pub fn new(client: C, operators: BTreeSet<Address>) -> Self {
+        Self { client, operators }
+    }
+
+    fn verify_operator(&self, sender: &Address) -> Result<(), ManyError> {
+        if self.operators.contains(sender) {
+            Ok(())
+        } else {
+            Err(consensus::not_an_operator(sender))
+        }
+    }
+}
+
+impl<C: Client + Send + Sync> consensus::ConsensusModuleBackend for AbciConsensusModuleImpl<C> {
+    fn net_info(
+        &self,
+        sender: &Address,
+        _args: consensus::NetInfoArgs,
+    ) -> Result<consensus::NetInfoReturns, ManyError> {
+        self.verify_operator(sender)?;
+        let net_info = block_on(async { self.client.net_info().await }).map_err(transport_error)?;
+
+        Ok(consensus::NetInfoReturns {
+            listening: net_info.listening,
+            listeners: net_info.listeners.iter().map(ToString::to_string).collect(),
+            peers: net_info
+                .peers
+                .into_iter()
+                .map(|peer| consensus::PeerInfo {
+                    node_id: peer.node_info.id.to_string(),
+                    moniker: peer.node_info.moniker.to_string(),
+                    remote_ip: peer.remote_ip.to_string(),
+                    is_outbound: peer.is_outbound,
+                })
+                .collect(),
+        })
+    }
+
+    fn validators(
+        &self,
+        sender: &Address,
+        args: consensus::ValidatorsArgs,
+    ) -> Result<consensus::ValidatorsReturns, ManyError> {
+        self.verify_operator(sender)?;
+        let height = match args.height {
+            Some(height) => height,
+            None => block_on(async { self.client.status().await })
+                .map_err(transport_error)?
+                .sync_info
+                .latest_block_height
+                .value(),
+        };
+        let height = tendermint::block::Height::try_from(height)
+            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        let response = block_on(async { self.client.validators(height, Paging::All).await })
+            .map_err(transport_error)?;
+
+        Ok(consensus::ValidatorsReturns {
+            height: response.block_height.value(),
+            validators: response
+                .validators
+                .into_iter()
+                .map(|validator| consensus::ValidatorInfo {
+                    address: validator.address.as_bytes().to_vec().into(),
+                    public_key: validator.pub_key.to_bytes().into(),
+                    voting_power: validator.power.value(),
+                    proposer_priority: validator.proposer_priority.value(),
+                })
+                .collect(),
+        })
+    }
+
+    fn state(
+        &self,
+        sender: &Address,
+        _args: consensus::StateArgs,
+    ) -> Result<consensus::StateReturns, ManyError> {
+        self.verify_operator(sender)?;
+        let round_state = block_on(async { self.client.consensus_state().await })
+            .map_err(transport_error)?
+            .round_state;
+        let step = round_state.height_round_step;
+
+        Ok(consensus::StateReturns {
+            height: step.height.value(),
+            round: step.round.value(),
+            step: step.step,
+            proposer: round_state.proposer.address.as_bytes().to_vec().into(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::{engine::general_purpose, Engine};
+    use consensus::ConsensusModuleBackend;
+    use many_identity::testing::identity;
+    use many_modules::EmptyArg;
+    use std::io::Read;
+
+    /// Start a Tendermint RPC server answering every `method` request with
+    /// `result`, and every other request with an error.
+    fn rpc_server(method: &'static str, result: serde_json::Value) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+
+                let response = if request["method"] == method {
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": result,
+                    })
+                } else {
+                    serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": {
+                            "code": -32601,
+                            "message": "Method not found",
+                        },
+                    })
+                };
+                request
+                    .respond(tiny_http::Response::from_string(response.to_string()))
+                    .unwrap();
+            }
+        });
+        url
+    }
+
+    /// A module with `identity(1)` as the only operator.
+    fn module(url: &str) -> AbciConsensusModuleImpl<tendermint_rpc::HttpClient> {
+        AbciConsensusModuleImpl::new(
+            tendermint_rpc::HttpClient::new(url).unwrap(),
+            BTreeSet::from([identity(1)]),
+        )
+    }
+
+    #[test]
+    fn not_an_operator() {
+        // Nothing listens there; the sender is rejected before any request.
+        let module = module("http://127.0.0.1:1");
+        let code = consensus::not_an_operator(identity(2)).code();
+
+        assert_eq!(
+            module.net_info(&identity(2), EmptyArg).unwrap_err().code(),
+            code
+        );
+        assert_eq!(
+            module
+                .validators(&identity(2), consensus::ValidatorsArgs::default())
+                .unwrap_err()
+                .code(),
+            code
+        );
+        assert_eq!(
+            module.state(&identity(2), EmptyArg).unwrap_err().code(),
+            code
+        );
+    }
+
+    #[test]
+    fn validators() {
+        let url = rpc_server(
+            "validators",
+            serde_json::json!({
+                "block_height": "5",
+                "validators": [{
+                    "address": hex::encode_upper([1; 20]),
+                    "pub_key": {
+                        "type": "tendermint/PubKeyEd25519",
+                        "value": general_purpose::STANDARD.encode([2; 32]),
+                    },
+                    "voting_power": "10",
+                    "proposer_priority": "-3",
+                }],
+                "count": "1",
+                "total": "1",
+            }),
+        );
+
+        let returns = module(&url)
+            .validators(&identity(1), consensus::ValidatorsArgs { height: Some(5) })
+            .unwrap();
+        assert_eq!(
+            returns,
+            consensus::ValidatorsReturns {
+                height: 5,
+                validators: vec![consensus::ValidatorInfo {
+                    address: vec![1; 20].into(),
+                    public_key: vec![2; 32].into(),
+                    voting_power: 10,
+                    proposer_priority: -3,
+                }],
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_height() {
+        let module = module("http://127.0.0.1:1");
+        assert!(module
+            .validators(
+                &identity(1),
+                consensus::ValidatorsArgs {
+                    height: Some(u64::MAX),
+                },
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn rpc_errors() {
+        let url = rpc_server("validators", serde_json::Value::Null);
+        let code = abci_frontend::abci_transport_error("").code();
+
+        let module = module(&url);
+        assert_eq!(
+            module.net_info(&identity(1), EmptyArg).unwrap_err().code(),
+            code
+        );
+        assert_eq!(
+            module.state(&identity(1), EmptyArg).unwrap_err().code(),
+            code
+        );
+    }
+}
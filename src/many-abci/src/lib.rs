@@ -1,6 +1,11 @@
 #![feature(used_with_arg)]
 
 pub mod abci_app;
+pub mod commit_entropy;
+pub mod consensus;
 pub mod many_app;
 pub mod migration;
 pub mod module;
+pub mod query_cache;
+pub mod router;
+pub mod tx_events;
@@ -1,15 +1,19 @@
 #![feature(used_with_arg)]
 
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::read_json5;
 use many_client::ManyClient;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
-use many_identity_webauthn::WebAuthnVerifier;
+use many_identity_dsa::CoseKeyVerifier;
+use many_identity_webauthn::{AttestationPolicy, ChallengeServer, WebAuthnVerifier};
 use many_migration::MigrationConfig;
 use many_modules::{base, blockchain, r#async};
 use many_protocol::ManyUrl;
-use many_server::transport::http::HttpServer;
+use many_server::health::HeightWatcher;
+use many_server::reload::{ConfigReloader, Reloadable};
+use many_server::transport::http::{CorsConfig, HttpServer};
 use many_server::ManyServer;
 use many_server_cache::{RequestCacheValidator, SharedRocksDbCacheBackend};
 use std::collections::BTreeSet;
@@ -20,14 +24,25 @@ use tendermint_rpc::Client;
 use tracing::{debug, error, info, trace};
 
 mod abci_app;
+mod commit_entropy;
+mod consensus;
 mod many_app;
 mod migration;
 mod module;
+mod query_cache;
+mod router;
+mod tx_events;
+mod webauthn;
 
 use abci_app::AbciApp;
+use commit_entropy::AbciCommitEntropyImpl;
+use consensus::AbciConsensusModuleImpl;
 use many_app::AbciModuleMany;
+use many_server::validator::policy::{Policy, PolicyValidator};
 use many_server::validator::ValidateOnlyRequestValidator;
 use module::AbciBlockchainModuleImpl;
+use query_cache::{QueryCache, DEFAULT_CACHED_METHODS};
+use router::{Router, RoutingTable};
 
 #[derive(Debug, Parser)]
 struct Opts {
@@ -43,9 +58,17 @@ struct Opts {
     tendermint: String,
 
     /// URL (including scheme) that has the MANY application running.
+    /// Requests not routed to another application by `--routes` go to it.
     #[clap(long)]
     many_app: String,
 
+    /// Path to a JSON5 file mapping method namespaces to the URL of the MANY
+    /// application serving them, e.g. `{ kvstore: "http://localhost:8001" }`.
+    /// Every application takes part in every block, and the state hash
+    /// combines their hashes.
+    #[clap(long)]
+    routes: Option<PathBuf>,
+
     /// Address and port to bind the MANY server to.
     #[clap(long)]
     many: String,
@@ -64,18 +87,87 @@ struct Opts {
     #[clap(long)]
     allow_origin: Option<Vec<ManyUrl>>,
 
+    /// Origins allowed to call this server from a browser, e.g.
+    /// `https://wallet.example`, or `*` for any origin. Preflight requests are
+    /// then answered with the CORS headers. Multiple occurences of this
+    /// argument can be given.
+    #[clap(long)]
+    cors_origin: Option<Vec<String>>,
+
+    /// Request headers browsers can send from the CORS origins, besides
+    /// `Content-Type`. Multiple occurences of this argument can be given.
+    #[clap(long)]
+    cors_header: Option<Vec<String>>,
+
+    /// How long browsers can cache the answer to a preflight request, in
+    /// seconds.
+    #[clap(long)]
+    cors_max_age: Option<u64>,
+
+    /// How long the responses of idempotent queries are cached by this
+    /// frontend, in milliseconds, so identical queries in a burst reach the
+    /// MANY application once. Responses are not cached if left empty.
+    #[clap(long)]
+    query_cache_ttl_ms: Option<u64>,
+
+    /// A query method whose responses are cached, instead of `ledger.info`,
+    /// `kvstore.info` and `tokens.info`. Commands are never cached, and the
+    /// endpoints of the frontend itself (e.g. `status`) are answered without
+    /// querying the MANY application. Multiple occurences of this argument
+    /// can be given.
+    #[clap(long)]
+    query_cache_method: Option<Vec<String>>,
+
+    /// Path to a PEM file containing the root certificates trusted for WebAuthn
+    /// authenticator attestation. When given, WebAuthn envelopes must carry an
+    /// attestation statement (packed or fido-u2f) chaining to one of these roots.
+    #[clap(long)]
+    webauthn_attestation_roots: Option<PathBuf>,
+
+    /// Only accept the WebAuthn envelopes signed over a challenge issued by
+    /// this frontend with `webauthn.challenge`, once, so WebAuthn signatures
+    /// cannot be replayed. The challenges are for the origins given with
+    /// `--allow-origin`.
+    #[clap(long)]
+    webauthn_challenges: bool,
+
+    /// How long a WebAuthn challenge can be used after it was issued, in
+    /// seconds.
+    #[clap(long, default_value = "120")]
+    webauthn_challenge_ttl: u64,
+
     /// Path to a JSON file containing an array of MANY addresses
     /// Only addresses from this array will be able to execute commands, e.g., send, put, ...
     /// Any addresses will be able to execute queries, e.g., balance, get, ...
+    /// The file is read again on SIGHUP.
     #[clap(long)]
     allow_addrs: Option<PathBuf>,
 
+    /// Path to a JSON5 file containing the policy of the methods allowed per
+    /// sender. See `many_server::validator::policy` for the format. Requests
+    /// are denied unless a rule of the policy allows them. The file is read
+    /// again on SIGHUP.
+    #[clap(long)]
+    policy: Option<PathBuf>,
+
+    /// Path to a JSON file containing an array of MANY addresses allowed to
+    /// query the consensus data of Tendermint (peers, validators, consensus
+    /// state) through the `consensus` endpoints. Those endpoints are not
+    /// available if left empty.
+    #[clap(long)]
+    consensus_operators: Option<PathBuf>,
+
     /// Path to a JSON file containing the configurations for the
     /// migrations. Migrations are DISABLED unless this configuration file
     /// is given.
     #[clap(long, short)]
     migrations_config: Option<PathBuf>,
 
+    /// How long the frontend can go without a new block before it stops
+    /// being ready, in seconds. See `GET /ready`.
+    #[clap(long, default_value = "60")]
+    ready_max_block_age: u64,
+
     /// Database path to the cache. If unspecified, the server will not
     /// verify transactions for duplicate requests.
     #[clap(long)]
@@ -89,16 +181,30 @@ async fn main() {
         abci,
         tendermint,
         many_app,
+        routes,
         many,
         many_pem,
         abci_read_buf_size,
         allow_origin,
+        cors_origin,
+        cors_header,
+        cors_max_age,
+        query_cache_ttl_ms,
+        query_cache_method,
+        webauthn_attestation_roots,
+        webauthn_challenges,
+        webauthn_challenge_ttl,
         allow_addrs,
+        policy,
+        consensus_operators,
         migrations_config,
+        ready_max_block_age,
         cache_db,
     } = Opts::parse();
 
     common_flags.init_logging().unwrap();
+    let mut reloader = ConfigReloader::new();
+    reloader.add("log level", move || common_flags.reload_logging());
 
     debug!("{:?}", Opts::parse());
     info!(
@@ -106,14 +212,21 @@ async fn main() {
         git_sha = env!("VERGEN_GIT_SHA")
     );
 
+    let key = read_pem(many_pem).unwrap();
+    info!(many_address = key.address().to_string().as_str());
+
     info!("Loading migrations from {migrations_config:?}");
     let maybe_migrations = migrations_config.map(|file| {
         let content = std::fs::read_to_string(file)
             .expect("Could not read file passed to --migrations_config");
         let config: MigrationConfig = serde_json::from_str(&content).unwrap();
-        config.strict()
+        config.strict().with_node(key.address())
     });
 
+    let routes: RoutingTable = routes
+        .map(|path| read_json5(&path).expect("Could not read the routing table"))
+        .unwrap_or_default();
+
     // Try to get the status of the backend MANY app.
     let many_client = ManyClient::new(&many_app, Address::anonymous(), AnonymousIdentity).unwrap();
 
@@ -142,11 +255,28 @@ async fn main() {
         std::thread::sleep(std::time::Duration::from_secs(1));
     };
 
+    // The frontend serves the attributes of every routed application.
+    let mut status = status;
+    for url in routes.values().collect::<BTreeSet<_>>() {
+        let routed = ManyClient::new(url, Address::anonymous(), AnonymousIdentity)
+            .unwrap()
+            .status()
+            .await
+            .unwrap_or_else(|e| panic!("Could not get the status of {url}: {e}"));
+        for attribute in routed.attributes.iter() {
+            status.attributes.insert(attribute.clone());
+        }
+        for (id, version) in routed.module_versions.0 {
+            status.module_versions.0.entry(id).or_insert(version);
+        }
+    }
+
     let rocksdb_cache = SharedRocksDbCacheBackend::new(cache_db);
     let abci_app = {
         let rocksdb_cache = rocksdb_cache.clone();
         tokio::task::spawn_blocking(move || {
-            AbciApp::create(many_app, Address::anonymous(), maybe_migrations)
+            let router = Router::new(many_app, Address::anonymous(), routes).unwrap();
+            AbciApp::create(router, maybe_migrations)
                 .unwrap()
                 .with_validator(RequestCacheValidator::new(rocksdb_cache))
         })
@@ -176,28 +306,101 @@ async fn main() {
         std::thread::sleep(std::time::Duration::from_secs(1));
     }
 
-    let key = CoseKeyIdentity::from_pem(std::fs::read_to_string(many_pem).unwrap()).unwrap();
-    info!(many_address = key.address().to_string().as_str());
+    let challenge_server = webauthn_challenges.then(|| {
+        ChallengeServer::new(allow_origin.clone())
+            .with_ttl(std::time::Duration::from_secs(webauthn_challenge_ttl))
+    });
+    let mut webauthn_verifier = WebAuthnVerifier::new(allow_origin);
+    if let Some(path) = webauthn_attestation_roots {
+        info!("Loading WebAuthn attestation roots from {path:?}");
+        let pem = std::fs::read(path).expect("Could not read attestation roots");
+        webauthn_verifier =
+            webauthn_verifier.with_attestation_policy(AttestationPolicy::from_pem(&pem).unwrap());
+    }
+
     let server = ManyServer::new(
         format!("AbciModule({})", &status.name),
         key.clone(),
         (
             AnonymousVerifier,
             CoseKeyVerifier,
-            WebAuthnVerifier::new(allow_origin.clone()),
+            // The backend module verifies the envelopes again, without
+            // the challenges, which can only be used once.
+            match &challenge_server {
+                Some(challenges) => webauthn_verifier
+                    .clone()
+                    .with_challenge_server(challenges.clone()),
+                None => webauthn_verifier.clone(),
+            },
         ),
         key.public_key(),
     );
-    let allowed_addrs: Option<BTreeSet<Address>> =
-        allow_addrs.map(|path| json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap());
+    // The allowed addresses and the policy only filter the requests of this
+    // node before they reach consensus, so they can be reloaded at any time.
+    let allowed_addrs: Option<Reloadable<BTreeSet<Address>>> = allow_addrs.map(|path| {
+        let allowed_addrs = Reloadable::new(read_json5(&path).unwrap());
+        reloader.add_reloadable("allow addrs", &allowed_addrs, move || read_json5(&path));
+        allowed_addrs
+    });
     let backend = AbciModuleMany::new(
         abci_client.clone(),
         status,
         key,
         allowed_addrs,
-        allow_origin,
+        webauthn_verifier,
     )
     .await;
+    let backend = match query_cache_ttl_ms {
+        Some(ttl) => {
+            let methods = query_cache_method.unwrap_or_else(|| {
+                DEFAULT_CACHED_METHODS
+                    .iter()
+                    .map(|method| method.to_string())
+                    .collect()
+            });
+            backend.with_query_cache(QueryCache::new(
+                std::time::Duration::from_millis(ttl),
+                methods,
+            ))
+        }
+        None => backend,
+    };
+    let policy_validator = policy.map(|path| {
+        let commands: Vec<String> = backend.commands().cloned().collect();
+        let read_policy = move || {
+            read_json5::<Policy>(&path)
+                .map(|policy| PolicyValidator::new(policy).with_commands(commands.clone()))
+        };
+        let policy_validator = Reloadable::new(read_policy().unwrap());
+        reloader.add_reloadable("policy", &policy_validator, read_policy);
+        policy_validator
+    });
+    let consensus_operators: Option<BTreeSet<Address>> = consensus_operators
+        .map(|path| json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap());
+    let consensus_impl = consensus_operators.map(|operators| {
+        Arc::new(Mutex::new(AbciConsensusModuleImpl::new(
+            abci_client.clone(),
+            operators,
+        )))
+    });
+    // Poll the height of Tendermint in the background, for the readiness
+    // checks.
+    let height_watcher = HeightWatcher::new(std::time::Duration::from_secs(ready_max_block_age));
+    {
+        let height_watcher = height_watcher.clone();
+        let abci_client = abci_client.clone();
+        tokio::spawn(async move {
+            loop {
+                match abci_client.abci_info().await {
+                    Ok(info) => height_watcher.observe(info.last_block_height.value()),
+                    Err(e) => height_watcher.fail(e),
+                }
+                tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+            }
+        });
+    }
+
+    let entropy_impl = Arc::new(Mutex::new(AbciCommitEntropyImpl::new(abci_client.clone())));
     let blockchain_impl = Arc::new(Mutex::new(AbciBlockchainModuleImpl::new(abci_client)));
 
     {
@@ -205,7 +408,23 @@ async fn main() {
         s.add_module(base::BaseModule::new(server.clone()));
         s.add_module(blockchain::BlockchainModule::new(blockchain_impl.clone()));
         s.add_module(r#async::AsyncModule::new(blockchain_impl));
+        s.add_module(many_modules::random::RandomModule::new(entropy_impl));
+        if let Some(challenges) = challenge_server {
+            s.add_module(many_modules::webauthn::WebAuthnModule::new(Arc::new(
+                Mutex::new(webauthn::WebAuthnChallengeModuleImpl(challenges)),
+            )));
+        }
+        if let Some(consensus_impl) = consensus_impl {
+            s.add_module(many_modules::consensus::ConsensusModule::new(
+                consensus_impl,
+            ));
+        }
         s.set_fallback_module(backend);
+        {
+            let height_watcher = height_watcher.clone();
+            s.add_readiness_check("abci", move || height_watcher.connected());
+        }
+        s.add_readiness_check("height", move || height_watcher.advancing());
 
         // The message is executed by the _server_ itself after it's been
         // added to tendermint.
@@ -215,13 +434,26 @@ async fn main() {
         s.add_validator(ValidateOnlyRequestValidator::new(
             RequestCacheValidator::new(rocksdb_cache.clone()),
         ));
+        if let Some(policy_validator) = policy_validator {
+            s.add_validator(policy_validator);
+        }
     }
 
+    let cors = cors_origin.map(|allowed_origins| CorsConfig {
+        allowed_origins,
+        allowed_headers: cors_header.unwrap_or_default(),
+        max_age: cors_max_age.map(std::time::Duration::from_secs),
+    });
     let mut many_server = HttpServer::new(server);
+    if let Some(cors) = cors {
+        many_server = many_server.with_cors(cors);
+    }
 
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
+    // SIGHUP reloads the configuration instead of stopping the server.
+    reloader
+        .reload_on_sighup()
         .expect("Could not register signal handler");
-    signal_hook::flag::register(signal_hook::consts::SIGHUP, many_server.term_signal())
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
         .expect("Could not register signal handler");
     signal_hook::flag::register(signal_hook::consts::SIGINT, many_server.term_signal())
         .expect("Could not register signal handler");
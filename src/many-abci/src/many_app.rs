@@ -1,3 +1,4 @@
+use crate::query_cache::QueryCache;
 use async_trait::async_trait;
 use coset::{CborSerializable, CoseSign1};
 use many_error::ManyError;
@@ -9,9 +10,10 @@ use many_modules::abci_backend::{AbciInit, EndpointInfo, ABCI_MODULE_ATTRIBUTE};
 use many_modules::base;
 use many_protocol::{
     decode_request_from_cose_sign1, decode_response_from_cose_sign1,
-    encode_cose_sign1_from_request, encode_cose_sign1_from_response, ManyUrl,
+    encode_cose_sign1_from_request, encode_cose_sign1_from_response, ModuleVersions,
     RequestMessageBuilder, ResponseMessage,
 };
+use many_server::reload::Reloadable;
 use many_server::transport::LowLevelManyRequestHandler;
 use many_types::attributes::Attribute;
 use many_types::cbor::CborAny;
@@ -25,8 +27,9 @@ pub struct AbciModuleMany<C: Client> {
     backend_status: base::Status,
     identity: CoseKeyIdentity,
     backend_endpoints: BTreeMap<String, EndpointInfo>,
-    allow_addrs: Option<BTreeSet<Address>>,
-    allow_origin: Option<Vec<ManyUrl>>,
+    allow_addrs: Option<Reloadable<BTreeSet<Address>>>,
+    webauthn_verifier: WebAuthnVerifier,
+    query_cache: Option<QueryCache>,
 }
 
 impl<C: Client + Sync> AbciModuleMany<C> {
@@ -34,8 +37,8 @@ impl<C: Client + Sync> AbciModuleMany<C> {
         client: C,
         backend_status: base::Status,
         identity: CoseKeyIdentity,
-        allow_addrs: Option<BTreeSet<Address>>,
-        allow_origin: Option<Vec<ManyUrl>>,
+        allow_addrs: Option<Reloadable<BTreeSet<Address>>>,
+        webauthn_verifier: WebAuthnVerifier,
     ) -> Self {
         let init_message = RequestMessageBuilder::default()
             .from(identity.address())
@@ -55,7 +58,7 @@ impl<C: Client + Sync> AbciModuleMany<C> {
             &(
                 AnonymousVerifier,
                 CoseKeyVerifier,
-                WebAuthnVerifier::new(allow_origin.clone()),
+                webauthn_verifier.clone(),
             ),
         )
         .unwrap();
@@ -67,17 +70,32 @@ impl<C: Client + Sync> AbciModuleMany<C> {
             identity,
             backend_endpoints: init_message.endpoints,
             allow_addrs,
-            allow_origin,
+            webauthn_verifier,
+            query_cache: None,
         }
     }
 
+    /// Cache the responses of idempotent queries.
+    pub fn with_query_cache(mut self, query_cache: QueryCache) -> Self {
+        self.query_cache = Some(query_cache);
+        self
+    }
+
+    /// The names of the backend endpoints that are commands.
+    pub fn commands(&self) -> impl Iterator<Item = &String> {
+        self.backend_endpoints
+            .iter()
+            .filter(|(_, info)| info.is_command)
+            .map(|(name, _)| name)
+    }
+
     async fn execute_message(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
         let message = decode_request_from_cose_sign1(
             &envelope,
             &(
                 AnonymousVerifier,
                 CoseKeyVerifier,
-                WebAuthnVerifier::new(self.allow_origin.clone()),
+                self.webauthn_verifier.clone(),
             ),
         )?;
         if let Some(info) = self.backend_endpoints.get(&message.method) {
@@ -87,9 +105,10 @@ impl<C: Client + Sync> AbciModuleMany<C> {
                 .map_err(ManyError::unexpected_transport_error)?;
 
             if is_command {
-                // TODO: Refactor this when `is_some_and` and/or `let-chains` are stabilized
-                if self.allow_addrs.is_some()
-                    && !self.allow_addrs.as_ref().unwrap().contains(&message.from())
+                if self
+                    .allow_addrs
+                    .as_ref()
+                    .is_some_and(|addrs| !addrs.get().contains(&message.from()))
                 {
                     return Err(ManyError::invalid_from_identity());
                 }
@@ -111,14 +130,22 @@ impl<C: Client + Sync> AbciModuleMany<C> {
                 encode_cose_sign1_from_response(response, &self.identity)
                     .map_err(ManyError::unexpected_transport_error)
             } else {
-                let response = self
-                    .client
-                    .abci_query(None, data, None, false)
-                    .await
-                    .map_err(ManyError::unexpected_transport_error)?;
-
-                CoseSign1::from_slice(&response.value)
-                    .map_err(ManyError::unexpected_transport_error)
+                let query = move || async move {
+                    let response = self
+                        .client
+                        .abci_query(None, data, None, false)
+                        .await
+                        .map_err(ManyError::unexpected_transport_error)?;
+
+                    CoseSign1::from_slice(&response.value)
+                        .map_err(ManyError::unexpected_transport_error)
+                };
+                match &self.query_cache {
+                    Some(cache) if cache.caches(&message.method) => {
+                        cache.get_or_query(&message, query).await
+                    }
+                    _ => query().await,
+                }
             }
         } else {
             Err(ManyError::invalid_method_name(message.method))
@@ -170,6 +197,15 @@ impl<C: Client + Sync + Send> base::BaseModuleBackend for AbciModuleMany<C> {
             .version(1)
             .identity(self.identity.address())
             .attributes(attributes.into_iter().collect())
+            .module_versions(ModuleVersions(
+                self.backend_status
+                    .module_versions
+                    .0
+                    .iter()
+                    .filter(|(id, _)| **id != ABCI_MODULE_ATTRIBUTE.id)
+                    .map(|(id, version)| (*id, *version))
+                    .collect(),
+            ))
             .server_version(std::env!("CARGO_PKG_VERSION").to_string());
 
         if let Some(pk) = self.identity.public_key() {
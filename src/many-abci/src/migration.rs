@@ -3,6 +3,7 @@ use many_error::ManyError;
 use many_migration::{InnerMigration, MigrationSet};
 
 pub mod error_code;
+pub mod proposal;
 
 pub type AbciAppMigrations = MigrationSet<'static, ()>;
 
@@ -0,0 +1,11 @@
+use crate::migration::MIGRATIONS;
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+
+#[distributed_slice(MIGRATIONS)]
+pub static PROPOSAL_FILTERING_TRIGGER: InnerMigration<(), ManyError> = InnerMigration::new_trigger(
+    false,
+    "ProposalFiltering",
+    "Filter invalid and replayed envelopes out of block proposals.",
+);
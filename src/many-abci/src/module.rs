@@ -5,7 +5,7 @@ use itertools::Itertools;
 use many_client::client::blocking::block_on;
 use many_error::ManyError;
 use many_identity::{Address, AnonymousIdentity};
-use many_modules::r#async::{StatusArgs, StatusReturn};
+use many_modules::r#async::{ResultArgs, ResultReturn, StatusArgs, StatusReturn};
 use many_modules::{abci_frontend, blockchain, r#async};
 use many_protocol::{encode_cose_sign1_from_response, ResponseMessage};
 use many_types::blockchain::{
@@ -149,16 +149,24 @@ impl<C: Client + Sync> AbciBlockchainModuleImpl<C> {
 
                     Ok(Some((tx_request, result_tx)))
                 } else {
-                    Err(ManyError::unknown(format!(
-                        "Invalid transaction hash x'{}'.",
-                        hex::encode(hash)
-                    )))
+                    tracing::debug!("Invalid transaction hash x'{}'.", hex::encode(hash));
+                    Err(blockchain::invalid_hash())
                 }
             }
         }
     }
 }
 
+/// Encode the raw response data stored in the Tendermint transaction index
+/// as a COSE envelope, the format used by every other MANY response.
+fn cose_response_from_tx_result(data: &[u8]) -> Result<Vec<u8>, ManyError> {
+    let response: ResponseMessage =
+        minicbor::decode(data).map_err(ManyError::deserialization_error)?;
+    encode_cose_sign1_from_response(response, &AnonymousIdentity)?
+        .to_vec()
+        .map_err(ManyError::serialization_error)
+}
+
 impl<C: Client> Drop for AbciBlockchainModuleImpl<C> {
     fn drop(&mut self) {
         tracing::info!("ABCI Blockchain Module being dropped.");
@@ -188,6 +196,16 @@ impl<C: Client + Send + Sync> r#async::AsyncModuleBackend for AbciBlockchainModu
             })
         })
     }
+
+    fn result(&self, sender: &Address, args: ResultArgs) -> Result<ResultReturn, ManyError> {
+        let status = self.status(
+            sender,
+            StatusArgs {
+                token: args.token.clone(),
+            },
+        )?;
+        ResultReturn::from_response(status.into_response(&args.token)?)
+    }
 }
 
 impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockchainModuleImpl<C> {
@@ -211,30 +229,17 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         &self,
         args: blockchain::TransactionArgs,
     ) -> Result<blockchain::TransactionReturns, ManyError> {
-        let block = block_on(async {
-            match args.query {
-                SingleTransactionQuery::Hash(hash) => {
-                    if let Ok(hash) = TryInto::<[u8; 32]>::try_into(hash) {
-                        self.client
-                            .tx(tendermint::Hash::Sha256(hash), true)
-                            .await
-                            .map_err(|e| {
-                                tracing::error!("abci transport: {}", e.to_string());
-                                abci_frontend::abci_transport_error(e.to_string())
-                            })
-                    } else {
-                        Err(ManyError::unknown("Invalid transaction hash .".to_string()))
-                    }
-                }
-            }
-        })?;
+        let hash = match &args.query {
+            SingleTransactionQuery::Hash(hash) => hash.clone(),
+        };
+        let (request, response) = block_on(async { self.tx(args.query).await })?
+            .ok_or_else(blockchain::unknown_transaction)?;
 
-        let tx_hash = block.hash.as_bytes().to_vec();
         Ok(blockchain::TransactionReturns {
             txn: Transaction {
-                id: TransactionIdentifier { hash: tx_hash },
-                request: None,
-                response: None,
+                id: TransactionIdentifier { hash },
+                request: Some(request),
+                response: Some(cose_response_from_tx_result(&response)?),
             },
         })
     }
@@ -335,7 +340,7 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         args: blockchain::RequestArgs,
     ) -> Result<blockchain::RequestReturns, ManyError> {
         let (request, _) = block_on(async { self.tx(args.query).await })?
-            .ok_or_else(|| ManyError::unknown("Hash not found."))?;
+            .ok_or_else(blockchain::unknown_transaction)?;
         tracing::debug!("blockchain.request: {}", hex::encode(&request));
         Ok(blockchain::RequestReturns { request })
     }
@@ -345,16 +350,126 @@ impl<C: Client + Send + Sync> blockchain::BlockchainModuleBackend for AbciBlockc
         args: blockchain::ResponseArgs,
     ) -> Result<blockchain::ResponseReturns, ManyError> {
         let (_, response) = block_on(async { self.tx(args.query).await })?
-            .ok_or_else(|| ManyError::unknown("Hash not found."))?;
+            .ok_or_else(blockchain::unknown_transaction)?;
 
         tracing::debug!("blockchain.response: {}", hex::encode(&response));
 
-        let response: ResponseMessage =
-            minicbor::decode(&response).map_err(ManyError::deserialization_error)?;
         Ok(blockchain::ResponseReturns {
-            response: encode_cose_sign1_from_response(response, &AnonymousIdentity)?
-                .to_vec()
-                .map_err(ManyError::serialization_error)?,
+            response: cose_response_from_tx_result(&response)?,
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::CoseSign1;
+    use many_identity::verifiers::AnonymousVerifier;
+    use many_modules::blockchain::BlockchainModuleBackend;
+    use many_protocol::decode_response_from_cose_sign1;
+    use std::io::Read;
+
+    /// Start a Tendermint RPC server answering every `tx` request with
+    /// `result`, or with an error if there is none.
+    fn tx_server(result: Option<serde_json::Value>) -> String {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+                let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+                assert_eq!(request["method"], "tx");
+
+                let response = match &result {
+                    Some(result) => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "result": result,
+                    }),
+                    None => serde_json::json!({
+                        "jsonrpc": "2.0",
+                        "id": request["id"],
+                        "error": {
+                            "code": -32603,
+                            "message": "Internal error",
+                            "data": "tx not found",
+                        },
+                    }),
+                };
+                request
+                    .respond(tiny_http::Response::from_string(response.to_string()))
+                    .unwrap();
+            }
+        });
+        url
+    }
+
+    fn module(url: &str) -> AbciBlockchainModuleImpl<tendermint_rpc::HttpClient> {
+        AbciBlockchainModuleImpl::new(tendermint_rpc::HttpClient::new(url).unwrap())
+    }
+
+    fn transaction(
+        module: &AbciBlockchainModuleImpl<tendermint_rpc::HttpClient>,
+        hash: Vec<u8>,
+    ) -> Result<Transaction, ManyError> {
+        module
+            .transaction(blockchain::TransactionArgs {
+                query: SingleTransactionQuery::Hash(hash),
+            })
+            .map(|returns| returns.txn)
+    }
+
+    #[test]
+    fn transaction_returns_request_and_response() {
+        let request = vec![1, 2, 3];
+        let response = ResponseMessage {
+            data: Ok(vec![4, 5, 6]),
+            ..Default::default()
+        };
+        let url = tx_server(Some(serde_json::json!({
+            "hash": hex::encode_upper([1; 32]),
+            "height": "1",
+            "index": 0,
+            "tx_result": {
+                "code": 0,
+                "data": general_purpose::STANDARD.encode(response.to_bytes().unwrap()),
+                "log": "",
+                "info": "",
+                "gas_wanted": "0",
+                "gas_used": "0",
+                "events": [],
+                "codespace": "",
+            },
+            "tx": general_purpose::STANDARD.encode(&request),
+        })));
+
+        let txn = transaction(&module(&url), vec![1; 32]).unwrap();
+        assert_eq!(txn.id.hash, vec![1; 32]);
+        assert_eq!(txn.request, Some(request));
+
+        // The response is a COSE envelope, like every other MANY response.
+        let envelope = CoseSign1::from_slice(&txn.response.unwrap()).unwrap();
+        let decoded = decode_response_from_cose_sign1(&envelope, None, &AnonymousVerifier).unwrap();
+        assert_eq!(decoded.data, response.data);
+    }
+
+    #[test]
+    fn transaction_unknown() {
+        let url = tx_server(None);
+        assert_eq!(
+            transaction(&module(&url), vec![1; 32]).unwrap_err().code(),
+            blockchain::unknown_transaction().code()
+        );
+    }
+
+    #[test]
+    fn transaction_invalid_hash() {
+        let url = tx_server(None);
+        assert_eq!(
+            transaction(&module(&url), vec![1; 16]).unwrap_err().code(),
+            blockchain::invalid_hash().code()
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! A short-lived cache of the responses of idempotent queries, so bursts of
+//! identical queries (e.g. from dashboards polling `ledger.info`) reach the
+//! backend once.
+//!
+//! Requests are keyed by their encoding without the timestamp and nonce, so
+//! requests of the same sender with the same method, argument, id and
+//! attributes share the envelope returned by the backend until it expires.
+//! Identical requests received while the backend is queried wait for its
+//! response instead of querying it again.
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_protocol::RequestMessage;
+use many_types::Timestamp;
+use std::collections::{BTreeSet, HashMap};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::OnceCell;
+
+/// The methods cached unless others are configured.
+pub const DEFAULT_CACHED_METHODS: &[&str] = &["ledger.info", "kvstore.info", "tokens.info"];
+
+/// The maximum number of responses kept.
+pub const DEFAULT_MAX_ENTRIES: usize = 1024;
+
+type Entry = (Instant, Arc<OnceCell<CoseSign1>>);
+
+pub struct QueryCache {
+    ttl: Duration,
+    methods: BTreeSet<String>,
+    max_entries: usize,
+    entries: Mutex<HashMap<Vec<u8>, Entry>>,
+}
+
+impl QueryCache {
+    pub fn new(ttl: Duration, methods: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            ttl,
+            methods: methods.into_iter().collect(),
+            max_entries: DEFAULT_MAX_ENTRIES,
+            entries: Default::default(),
+        }
+    }
+
+    pub fn with_max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = max_entries;
+        self
+    }
+
+    /// Whether the responses of a method are cached. Only queries are cached,
+    /// whatever the methods configured.
+    pub fn caches(&self, method: &str) -> bool {
+        self.methods.contains(method)
+    }
+
+    /// The response to a request, from the cache or from `query`. Failed
+    /// queries are not cached.
+    pub async fn get_or_query<F, Fut>(
+        &self,
+        message: &RequestMessage,
+        query: F,
+    ) -> Result<CoseSign1, ManyError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<CoseSign1, ManyError>>,
+    {
+        let cell = self.cell(key(message)?);
+        cell.get_or_try_init(query).await.cloned()
+    }
+
+    fn cell(&self, key: Vec<u8>) -> Arc<OnceCell<CoseSign1>> {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if let Some((created, cell)) = entries.get(&key) {
+            if now.duration_since(*created) < self.ttl {
+                return cell.clone();
+            }
+        }
+
+        if entries.len() >= self.max_entries {
+            entries.retain(|_, (created, _)| now.duration_since(*created) < self.ttl);
+        }
+        let cell = Arc::new(OnceCell::new());
+        // When full of fresh responses, the request is not cached.
+        if entries.len() < self.max_entries {
+            entries.insert(key, (now, cell.clone()));
+        }
+        cell
+    }
+}
+
+/// The canonical bytes of a request, without its timestamp and nonce.
+fn key(message: &RequestMessage) -> Result<Vec<u8>, ManyError> {
+    let message = RequestMessage {
+        timestamp: Some(Timestamp::new(0)?),
+        nonce: None,
+        ..message.clone()
+    };
+    message.to_bytes().map_err(ManyError::serialization_error)
+}
@@ -0,0 +1,212 @@
+//! Routing of the requests to several MANY applications behind a single
+//! consensus instance, by the namespace of their method.
+//!
+//! The routing table maps namespaces to the URL of the application serving
+//! them, e.g. `{ "kvstore": "http://localhost:8001" }` sends `kvstore.put` to
+//! the kvstore server. Methods of other namespaces go to the default
+//! application. Every application takes part in every block, and the state
+//! hash of the chain combines their state hashes.
+use coset::CoseSign1;
+use many_client::client::blocking::ManyClient;
+use many_error::ManyError;
+use many_identity::{Address, AnonymousIdentity};
+use many_modules::abci_backend::{AbciCommitInfo, AbciInfo, AbciInit};
+use many_protocol::RequestMessage;
+use reqwest::{IntoUrl, Url};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+/// The URL of the application serving each namespace.
+pub type RoutingTable = BTreeMap<String, String>;
+
+/// A MANY application behind the bridge.
+#[derive(Clone, Debug)]
+pub struct Backend {
+    pub url: Url,
+    pub client: ManyClient<AnonymousIdentity>,
+}
+
+impl Backend {
+    fn new(url: Url, server_id: Address) -> Result<Self, String> {
+        let client = ManyClient::new(url.clone(), server_id, AnonymousIdentity)?;
+        Ok(Self { url, client })
+    }
+
+    pub fn call<T: for<'a> minicbor::Decode<'a, ()>>(
+        &self,
+        method: &str,
+        argument: impl minicbor::Encode<()>,
+    ) -> Result<T, ManyError> {
+        self.client.call_(method, argument).and_then(|payload| {
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Router {
+    /// The applications, the default one first. Each appears once, however
+    /// many namespaces it serves.
+    backends: Vec<Backend>,
+
+    /// The index of the application of each namespace.
+    routes: BTreeMap<String, usize>,
+}
+
+impl Router {
+    pub fn new<U: IntoUrl>(
+        default_url: U,
+        server_id: Address,
+        table: RoutingTable,
+    ) -> Result<Self, String> {
+        let default_url = default_url.into_url().map_err(|e| e.to_string())?;
+        let mut backends = vec![Backend::new(default_url, server_id)?];
+        let mut routes = BTreeMap::new();
+
+        for (namespace, url) in table {
+            let url = url
+                .into_url()
+                .map_err(|e| format!("Invalid URL for namespace {namespace}: {e}"))?;
+            let index = match backends.iter().position(|b| b.url == url) {
+                Some(index) => index,
+                None => {
+                    backends.push(Backend::new(url, server_id)?);
+                    backends.len() - 1
+                }
+            };
+            routes.insert(namespace, index);
+        }
+
+        Ok(Self { backends, routes })
+    }
+
+    pub fn default_backend(&self) -> &Backend {
+        &self.backends[0]
+    }
+
+    pub fn backends(&self) -> &[Backend] {
+        &self.backends
+    }
+
+    /// Whether requests are routed to more than one application.
+    pub fn is_multi_app(&self) -> bool {
+        self.backends.len() > 1
+    }
+
+    /// The index of the application serving a method.
+    pub fn route(&self, method: &str) -> usize {
+        method
+            .split_once('.')
+            .and_then(|(namespace, _)| self.routes.get(namespace))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// The index of the application serving the request of an envelope.
+    /// Envelopes that cannot be decoded go to the default application, which
+    /// returns the error.
+    pub fn route_envelope(&self, envelope: &CoseSign1) -> usize {
+        RequestMessage::try_from(envelope).map_or(0, |message| self.route(&message.method))
+    }
+
+    pub fn backend_for(&self, envelope: &CoseSign1) -> &Backend {
+        &self.backends[self.route_envelope(envelope)]
+    }
+
+    /// The endpoints and gas costs of all the applications. An application
+    /// only contributes the methods routed to it, so the `abci` methods of
+    /// the other applications are not advertised. The block gas limit is
+    /// the one of the default application.
+    pub fn init(&self) -> Result<AbciInit, ManyError> {
+        let mut init = AbciInit {
+            endpoints: BTreeMap::new(),
+            gas: None,
+            block_gas_limit: None,
+        };
+        for (index, backend) in self.backends.iter().enumerate() {
+            let AbciInit {
+                endpoints,
+                gas,
+                block_gas_limit,
+            } = backend.call("abci.init", ())?;
+            if index == 0 {
+                init.block_gas_limit = block_gas_limit;
+            }
+            init.endpoints.extend(
+                endpoints
+                    .into_iter()
+                    .filter(|(method, _)| self.route(method) == index),
+            );
+            if let Some(gas) = gas {
+                init.gas.get_or_insert_with(BTreeMap::new).extend(
+                    gas.into_iter()
+                        .filter(|(method, _)| self.route(method) == index),
+                );
+            }
+        }
+        Ok(init)
+    }
+
+    /// The height of the default application, and the state hash of all the
+    /// applications.
+    pub fn info(&self) -> Result<AbciInfo, ManyError> {
+        let infos = self
+            .backends
+            .iter()
+            .map(|backend| backend.call::<AbciInfo>("abci.info", ()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let height = infos[0].height;
+        for (info, backend) in infos.iter().zip(&self.backends).skip(1) {
+            if info.height != height {
+                warn!(
+                    "The application at {} is at height {}, the default one at {height}",
+                    backend.url, info.height
+                );
+            }
+        }
+
+        Ok(AbciInfo {
+            height,
+            hash: combine_hashes(infos.iter().map(|info| info.hash.as_slice())).into(),
+        })
+    }
+
+    /// Commit every application. Blocks are retained as long as one of them
+    /// needs them.
+    pub fn commit(&self) -> Result<AbciCommitInfo, ManyError> {
+        let infos = self
+            .backends
+            .iter()
+            .map(|backend| backend.call::<AbciCommitInfo>("abci.commit", ()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(AbciCommitInfo {
+            retain_height: infos.iter().map(|i| i.retain_height).min().unwrap_or(0),
+            hash: combine_hashes(infos.iter().map(|info| info.hash.as_slice())).into(),
+        })
+    }
+
+    /// Call a method without a return on every application, e.g. to begin a
+    /// block. Failures are logged.
+    pub fn broadcast(&self, method: &str, argument: impl minicbor::Encode<()> + Clone) {
+        for backend in &self.backends {
+            if let Err(e) = backend.client.call_(method, argument.clone()) {
+                warn!("{method} failed on {}: {e}", backend.url);
+            }
+        }
+    }
+}
+
+/// The state hash of the applications. With a single application, its own
+/// hash, so chains with a single application keep their state hash.
+fn combine_hashes<'a>(mut hashes: impl ExactSizeIterator<Item = &'a [u8]>) -> Vec<u8> {
+    if hashes.len() == 1 {
+        return hashes.next().unwrap_or_default().to_vec();
+    }
+    let mut hasher = Sha256::new();
+    for hash in hashes {
+        hasher.update(hash);
+    }
+    hasher.finalize().to_vec()
+}
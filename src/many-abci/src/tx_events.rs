@@ -0,0 +1,187 @@
+//! Tendermint events of the delivered transactions, so nodes index them and
+//! `tx_search` can find them without decoding envelopes, e.g. with the query
+//! `many.address='maa...'` for all the transactions involving an address.
+use ciborium::value::Value;
+use many_identity::Address;
+use many_modules::ledger::SendArgs;
+use many_protocol::RequestMessage;
+use std::collections::BTreeSet;
+use tendermint_proto::abci::{Event, EventAttribute};
+
+const EVENT_TYPE: &str = "many";
+
+/// The CBOR tag of addresses.
+const ADDRESS_TAG: u64 = 10000;
+
+fn attribute(key: &str, value: impl ToString) -> EventAttribute {
+    EventAttribute {
+        key: key.to_string().into_bytes().into(),
+        value: value.to_string().into_bytes().into(),
+        index: true,
+    }
+}
+
+fn collect_addresses(value: &Value, addresses: &mut BTreeSet<Address>) {
+    match value {
+        Value::Tag(ADDRESS_TAG, inner) => {
+            if let Value::Bytes(bytes) = inner.as_ref() {
+                if let Ok(address) = Address::from_bytes(bytes) {
+                    addresses.insert(address);
+                }
+            }
+        }
+        Value::Tag(_, inner) => collect_addresses(inner, addresses),
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_addresses(value, addresses)),
+        Value::Map(entries) => entries.iter().for_each(|(key, value)| {
+            collect_addresses(key, addresses);
+            collect_addresses(value, addresses);
+        }),
+        _ => {}
+    }
+}
+
+/// The events of a request, with the attributes:
+/// - `method` and `sender`,
+/// - `receiver` and `symbol`, for sends,
+/// - `address`, for the sender and every address in the arguments.
+pub fn request_events(message: &RequestMessage) -> Vec<Event> {
+    let sender = message.from.unwrap_or_default();
+    let mut attributes = vec![
+        attribute("method", &message.method),
+        attribute("sender", sender),
+    ];
+
+    if message.method == "ledger.send" {
+        if let Ok(args) = minicbor::decode::<SendArgs>(&message.data) {
+            attributes.push(attribute("receiver", args.to));
+            attributes.push(attribute("symbol", args.symbol));
+        }
+    }
+
+    let mut addresses = BTreeSet::from([sender]);
+    if let Ok(value) = ciborium::de::from_reader::<Value, _>(message.data.as_slice()) {
+        collect_addresses(&value, &mut addresses);
+    }
+    attributes.extend(
+        addresses
+            .into_iter()
+            .filter(|address| !address.is_anonymous())
+            .map(|address| attribute("address", address)),
+    );
+
+    vec![Event {
+        r#type: EVENT_TYPE.to_string(),
+        attributes,
+    }]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+    use many_protocol::RequestMessageBuilder;
+    use std::collections::BTreeMap;
+
+    fn request(from: Address, method: &str, data: Vec<u8>) -> RequestMessage {
+        RequestMessageBuilder::default()
+            .from(from)
+            .method(method.to_string())
+            .data(data)
+            .build()
+            .unwrap()
+    }
+
+    /// The attributes of the only event of a request, as keys and values.
+    fn attributes(message: &RequestMessage) -> Vec<(String, String)> {
+        let events = request_events(message);
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].r#type, EVENT_TYPE);
+        events[0]
+            .attributes
+            .iter()
+            .map(|a| {
+                assert!(a.index);
+                (
+                    String::from_utf8(a.key.to_vec()).unwrap(),
+                    String::from_utf8(a.value.to_vec()).unwrap(),
+                )
+            })
+            .collect()
+    }
+
+    fn addresses(attributes: &[(String, String)]) -> BTreeSet<String> {
+        attributes
+            .iter()
+            .filter(|(key, _)| key == "address")
+            .map(|(_, value)| value.clone())
+            .collect()
+    }
+
+    #[test]
+    fn send() {
+        let args = SendArgs {
+            from: None,
+            to: identity(2),
+            amount: 10u64.into(),
+            symbol: identity(3),
+            memo: None,
+        };
+        let message = request(identity(1), "ledger.send", minicbor::to_vec(args).unwrap());
+
+        let attributes = attributes(&message);
+        assert_eq!(
+            attributes[..4],
+            [
+                ("method".to_string(), "ledger.send".to_string()),
+                ("sender".to_string(), identity(1).to_string()),
+                ("receiver".to_string(), identity(2).to_string()),
+                ("symbol".to_string(), identity(3).to_string()),
+            ]
+        );
+        assert_eq!(attributes.len(), 7);
+        assert_eq!(
+            addresses(&attributes),
+            BTreeSet::from([1, 2, 3].map(|seed| identity(seed).to_string()))
+        );
+    }
+
+    #[test]
+    fn nested_addresses() {
+        let data = BTreeMap::from([("accounts", vec![identity(4), identity(5)])]);
+        let message = request(
+            Address::anonymous(),
+            "account.create",
+            minicbor::to_vec(data).unwrap(),
+        );
+
+        let attributes = attributes(&message);
+        assert_eq!(
+            attributes[..2],
+            [
+                ("method".to_string(), "account.create".to_string()),
+                ("sender".to_string(), Address::anonymous().to_string()),
+            ]
+        );
+        // The anonymous sender is not indexed.
+        assert_eq!(attributes.len(), 4);
+        assert_eq!(
+            addresses(&attributes),
+            BTreeSet::from([identity(4).to_string(), identity(5).to_string()])
+        );
+    }
+
+    #[test]
+    fn invalid_data() {
+        let message = request(identity(1), "ledger.send", vec![0xff, 0x00]);
+        assert_eq!(
+            attributes(&message),
+            [
+                ("method".to_string(), "ledger.send".to_string()),
+                ("sender".to_string(), identity(1).to_string()),
+                ("address".to_string(), identity(1).to_string()),
+            ]
+        );
+    }
+}
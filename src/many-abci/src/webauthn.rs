@@ -0,0 +1,22 @@
+use many_error::ManyError;
+use many_identity_webauthn::ChallengeServer;
+use many_modules::webauthn;
+use many_protocol::ManyUrl;
+use many_types::Timestamp;
+
+/// Issues the WebAuthn challenges required by the verifier of this frontend.
+pub struct WebAuthnChallengeModuleImpl(pub ChallengeServer);
+
+impl webauthn::WebAuthnModuleBackend for WebAuthnChallengeModuleImpl {
+    fn challenge(
+        &self,
+        args: webauthn::ChallengeArgs,
+    ) -> Result<webauthn::ChallengeReturns, ManyError> {
+        let origin = ManyUrl::parse(&args.origin).map_err(ManyError::unknown)?;
+        let issued = self.0.issue(&origin)?;
+        Ok(webauthn::ChallengeReturns {
+            nonce: issued.nonce.into(),
+            expires: Timestamp::from_system_time(issued.expires)?,
+        })
+    }
+}
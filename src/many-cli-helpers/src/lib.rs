@@ -1,8 +1,20 @@
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 use tracing::metadata::LevelFilter;
 use tracing_subscriber::fmt::Subscriber;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::reload;
+use tracing_subscriber::util::SubscriberInitExt;
 
 pub mod error;
+pub mod identity;
+pub mod output;
+pub mod state_export;
+#[cfg(feature = "opentelemetry")]
+pub mod telemetry;
 
 #[derive(clap::ArgEnum, Clone, Debug)]
 enum LogStrategy {
@@ -53,32 +65,126 @@ pub struct CommonCliFlags {
     /// Use given logging strategy
     #[clap(long, arg_enum, default_value_t = LogStrategy::Terminal)]
     logmode: LogStrategy,
+
+    /// A JSON5 file with the log level, e.g. `{ level: "debug" }`. Overrides
+    /// `--verbose` and `--quiet`, and is read again when the configuration
+    /// of the server is reloaded.
+    #[clap(long)]
+    log_config: Option<PathBuf>,
+
+    /// Export the request spans to this OpenTelemetry collector, over OTLP/gRPC
+    /// (e.g. `http://localhost:4317`).
+    #[cfg(feature = "opentelemetry")]
+    #[clap(long)]
+    otlp_endpoint: Option<String>,
+}
+
+fn process_name() -> Result<std::ffi::OsString, String> {
+    let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    exe_path
+        .file_name()
+        .map(ToOwned::to_owned)
+        .ok_or_else(|| "Could not find the process name.".to_string())
+}
+
+/// Read a JSON5 configuration file.
+pub fn read_json5<T: DeserializeOwned>(path: &Path) -> Result<T, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Could not read {}: {e}", path.display()))?;
+    json5::from_str(&content).map_err(|e| format!("Invalid {}: {e}", path.display()))
+}
+
+#[derive(Deserialize)]
+struct LogConfig {
+    level: String,
+}
+
+type SetLevelFn = Box<dyn Fn(LevelFilter) -> Result<(), String> + Send + Sync>;
+
+/// Changes the level of the global subscriber, once it is initialized.
+static SET_LEVEL: OnceLock<SetLevelFn> = OnceLock::new();
+
+fn set_reload_handle<S: 'static>(handle: reload::Handle<LevelFilter, S>) {
+    let _ = SET_LEVEL.set(Box::new(move |level| {
+        handle.reload(level).map_err(|e| e.to_string())
+    }));
 }
 
 impl CommonCliFlags {
+    fn level(&self) -> Result<LevelFilter, String> {
+        let Some(path) = &self.log_config else {
+            return Ok(self.verbosity.level());
+        };
+        let config: LogConfig = read_json5(path)?;
+        config
+            .level
+            .parse()
+            .map_err(|_| format!("Invalid log level: {}", config.level))
+    }
+
     pub fn init_logging(&self) -> Result<(), String> {
-        let subscriber = Subscriber::builder().with_max_level(self.verbosity.level());
+        let subscriber = Subscriber::builder().with_max_level(self.level()?);
 
         match self.logmode {
             LogStrategy::Terminal => {
-                let subscriber = subscriber.with_writer(std::io::stderr);
-                subscriber.init();
+                let subscriber = subscriber
+                    .with_writer(std::io::stderr)
+                    .with_filter_reloading();
+                set_reload_handle(subscriber.reload_handle());
+                self.init(subscriber.finish())?;
             }
             LogStrategy::Syslog => {
-                let exe_path = std::env::current_exe().map_err(|e| e.to_string())?;
-                let process_name = exe_path.file_name().unwrap();
-                let identity =
-                    std::ffi::CString::new(process_name.as_bytes()).map_err(|e| e.to_string())?;
+                let identity = std::ffi::CString::new(process_name()?.as_bytes())
+                    .map_err(|e| e.to_string())?;
                 let (options, facility) = Default::default();
                 let syslog = syslog_tracing::Syslog::new(identity, options, facility)
                     .ok_or_else(|| "Could not create syslog logger.".to_string())?;
 
-                let subscriber = subscriber.with_ansi(false).with_writer(syslog);
-                subscriber.init();
+                let subscriber = subscriber
+                    .with_ansi(false)
+                    .with_writer(syslog)
+                    .with_filter_reloading();
+                set_reload_handle(subscriber.reload_handle());
+                self.init(subscriber.finish())?;
                 log_panics::init();
             }
         };
 
         Ok(())
     }
+
+    /// Set the global subscriber, exporting its spans if an OTLP endpoint is
+    /// configured.
+    fn init<S>(&self, subscriber: S) -> Result<(), String>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span> + Send + Sync + 'static,
+    {
+        #[cfg(feature = "opentelemetry")]
+        if let Some(endpoint) = &self.otlp_endpoint {
+            use tracing_subscriber::layer::SubscriberExt;
+
+            let service_name = process_name()?.to_string_lossy().into_owned();
+            subscriber
+                .with(telemetry::otlp_layer(endpoint, service_name)?)
+                .init();
+            return Ok(());
+        }
+
+        subscriber.init();
+        Ok(())
+    }
+
+    /// Read the log level from `--log-config` again, if given, and apply it.
+    pub fn reload_logging(&self) -> Result<(), String> {
+        if self.log_config.is_none() {
+            return Ok(());
+        }
+        let level = self.level()?;
+        let set_level = SET_LEVEL
+            .get()
+            .ok_or_else(|| "The logging is not initialized.".to_string())?;
+        set_level(level)?;
+        tracing::info!("Log level set to {level}");
+        Ok(())
+    }
 }
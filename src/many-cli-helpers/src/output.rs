@@ -0,0 +1,309 @@
+//! Output of the results of the CLI commands, in a format selected with the
+//! global `--output` flag, so scripts can parse them instead of the text
+//! meant for humans.
+use many_identity::Address;
+use minicbor::data::{Tag, Type};
+use minicbor::{Decoder, Encode};
+use serde_json::{Map, Number, Value};
+use std::io::Write;
+
+/// CBOR tag of the addresses.
+const ADDRESS_TAG: u64 = 10000;
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OutputFormat {
+    /// Text meant for humans.
+    #[default]
+    #[clap(alias = "table")]
+    Plain,
+
+    /// JSON, with byte strings in hexadecimal and large integers as strings.
+    Json,
+
+    /// The CBOR of the result, in diagnostic notation.
+    CborDiag,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct OutputFlags {
+    /// The format of the results printed.
+    #[clap(long, arg_enum, default_value_t = OutputFormat::Plain, global = true)]
+    pub output: OutputFormat,
+}
+
+#[derive(Clone, Debug)]
+enum Plain {
+    Text(String),
+
+    /// Bytes written as is, e.g. a value that is not UTF-8.
+    Raw(Vec<u8>),
+}
+
+/// The result of a command. It is always available as CBOR, from which its
+/// JSON and plain text are derived unless they are set explicitly.
+#[derive(Clone, Debug)]
+pub struct CommandOutput {
+    cbor: Vec<u8>,
+    json: Option<Value>,
+    plain: Option<Plain>,
+}
+
+impl CommandOutput {
+    /// A result already encoded in CBOR, e.g. the data of a response. An
+    /// empty result has no output.
+    pub fn cbor(cbor: Vec<u8>) -> Self {
+        Self {
+            cbor,
+            json: None,
+            plain: None,
+        }
+    }
+
+    pub fn encode<T: Encode<()>>(value: &T) -> Result<Self, anyhow::Error> {
+        Ok(Self::cbor(minicbor::to_vec(value)?))
+    }
+
+    /// A result that is only text, e.g. an address in hexadecimal.
+    pub fn text(text: impl Into<String>) -> Self {
+        let text = text.into();
+        Self::encode(&text)
+            .expect("Strings always encode")
+            .with_plain(text)
+    }
+
+    pub fn address(address: &Address) -> Self {
+        Self::encode(address)
+            .expect("Addresses always encode")
+            .with_plain(address.to_string())
+    }
+
+    pub fn with_plain(self, plain: impl Into<String>) -> Self {
+        Self {
+            plain: Some(Plain::Text(plain.into())),
+            ..self
+        }
+    }
+
+    /// Write `bytes` as is in the plain format, without a newline.
+    pub fn with_raw_plain(self, bytes: Vec<u8>) -> Self {
+        Self {
+            plain: Some(Plain::Raw(bytes)),
+            ..self
+        }
+    }
+
+    pub fn with_json(self, json: Value) -> Self {
+        Self {
+            json: Some(json),
+            ..self
+        }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> Result<String, anyhow::Error> {
+        Ok(match format {
+            OutputFormat::Plain => match &self.plain {
+                Some(Plain::Text(text)) => text.clone(),
+                Some(Plain::Raw(bytes)) => String::from_utf8_lossy(bytes).into_owned(),
+                None => minicbor::display(&self.cbor).to_string(),
+            },
+            OutputFormat::Json => {
+                let json = match &self.json {
+                    Some(json) => json.clone(),
+                    None => cbor_to_json(&self.cbor)?,
+                };
+                serde_json::to_string_pretty(&json)?
+            }
+            OutputFormat::CborDiag => minicbor::display(&self.cbor).to_string(),
+        })
+    }
+
+    /// Print the result on the standard output. Nothing is printed for an
+    /// empty result, except `null` in JSON.
+    pub fn print(&self, format: OutputFormat) -> Result<(), anyhow::Error> {
+        if let (OutputFormat::Plain, Some(Plain::Raw(bytes))) = (format, &self.plain) {
+            return Ok(std::io::stdout().write_all(bytes)?);
+        }
+
+        let rendered = self.render(format)?;
+        if !rendered.is_empty() {
+            println!("{rendered}");
+        }
+        Ok(())
+    }
+}
+
+/// Convert CBOR to JSON. Map keys are converted to strings, byte strings to
+/// hexadecimal, addresses to their textual format and bignums to decimal
+/// strings. Other tags are kept as `{ "tag": .., "value": .. }`.
+pub fn cbor_to_json(cbor: &[u8]) -> Result<Value, anyhow::Error> {
+    if cbor.is_empty() {
+        return Ok(Value::Null);
+    }
+    Ok(decode_json(&mut Decoder::new(cbor))?)
+}
+
+fn decode_json(d: &mut Decoder) -> Result<Value, minicbor::decode::Error> {
+    Ok(match d.datatype()? {
+        Type::Bool => Value::Bool(d.bool()?),
+        Type::Null | Type::Undefined => {
+            d.skip()?;
+            Value::Null
+        }
+        Type::U8 | Type::U16 | Type::U32 | Type::U64 => Value::from(d.u64()?),
+        Type::I8 | Type::I16 | Type::I32 | Type::I64 => Value::from(d.i64()?),
+        Type::Int => Value::String(i128::from(d.int()?).to_string()),
+        Type::F16 | Type::F32 | Type::F64 => {
+            Number::from_f64(d.f64()?).map_or(Value::Null, Value::Number)
+        }
+        Type::Simple => Value::from(d.simple()?),
+        Type::Bytes | Type::BytesIndef => {
+            let mut bytes = Vec::new();
+            for chunk in d.bytes_iter()? {
+                bytes.extend_from_slice(chunk?);
+            }
+            Value::String(hex::encode(bytes))
+        }
+        Type::String | Type::StringIndef => {
+            let mut string = String::new();
+            for chunk in d.str_iter()? {
+                string.push_str(chunk?);
+            }
+            Value::String(string)
+        }
+        Type::Array | Type::ArrayIndef => {
+            let len = d.array()?;
+            let mut items = Vec::new();
+            while match len {
+                Some(len) => items.len() < len as usize,
+                None => d.datatype()? != Type::Break,
+            } {
+                items.push(decode_json(d)?);
+            }
+            if len.is_none() {
+                skip_break(d);
+            }
+            Value::Array(items)
+        }
+        Type::Map | Type::MapIndef => {
+            let len = d.map()?;
+            let mut map = Map::new();
+            let mut count = 0;
+            while match len {
+                Some(len) => count < len,
+                None => d.datatype()? != Type::Break,
+            } {
+                let key = match decode_json(d)? {
+                    Value::String(key) => key,
+                    key => key.to_string(),
+                };
+                map.insert(key, decode_json(d)?);
+                count += 1;
+            }
+            if len.is_none() {
+                skip_break(d);
+            }
+            Value::Object(map)
+        }
+        Type::Tag => match d.tag()? {
+            Tag::Unassigned(ADDRESS_TAG) => Value::String(
+                Address::from_bytes(d.bytes()?)
+                    .map_err(|e| minicbor::decode::Error::message(e.to_string()))?
+                    .to_string(),
+            ),
+            Tag::PosBignum => {
+                Value::String(num_bigint::BigUint::from_bytes_be(d.bytes()?).to_string())
+            }
+            Tag::Timestamp => decode_json(d)?,
+            tag => {
+                let mut map = Map::new();
+                map.insert("tag".to_string(), Value::from(u64::from(tag)));
+                map.insert("value".to_string(), decode_json(d)?);
+                Value::Object(map)
+            }
+        },
+        _ => return Err(minicbor::decode::Error::message("Unsupported CBOR type.")),
+    })
+}
+
+/// Skip the break ending an indefinite array or map.
+fn skip_break(d: &mut Decoder) {
+    d.set_position(d.position() + 1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn to_json() {
+        let mut e = minicbor::Encoder::new(Vec::new());
+        e.map(4)
+            .unwrap()
+            .u8(0)
+            .unwrap()
+            .encode(Address::illegal())
+            .unwrap()
+            .u8(1)
+            .unwrap()
+            .array(2)
+            .unwrap()
+            .bytes(&[1, 2])
+            .unwrap()
+            .null()
+            .unwrap()
+            .str("amount")
+            .unwrap()
+            .tag(Tag::PosBignum)
+            .unwrap()
+            .bytes(&[1, 0, 0, 0, 0, 0, 0, 0, 0])
+            .unwrap()
+            .u8(3)
+            .unwrap()
+            .tag(Tag::Unassigned(1234))
+            .unwrap()
+            .i8(-1)
+            .unwrap();
+
+        assert_eq!(
+            cbor_to_json(&e.into_writer()).unwrap(),
+            json!({
+                "0": Address::illegal().to_string(),
+                "1": ["0102", null],
+                "amount": "18446744073709551616",
+                "3": { "tag": 1234, "value": -1 },
+            })
+        );
+    }
+
+    #[test]
+    fn empty() {
+        let output = CommandOutput::cbor(vec![]);
+        assert_eq!(output.render(OutputFormat::Plain).unwrap(), "");
+        assert_eq!(output.render(OutputFormat::Json).unwrap(), "null");
+    }
+
+    #[test]
+    fn formats() {
+        let output = CommandOutput::address(&Address::illegal());
+        assert_eq!(
+            output.render(OutputFormat::Plain).unwrap(),
+            Address::illegal().to_string()
+        );
+        assert_eq!(
+            output.render(OutputFormat::Json).unwrap(),
+            format!("\"{}\"", Address::illegal())
+        );
+        assert_eq!(
+            output.render(OutputFormat::CborDiag).unwrap(),
+            minicbor::display(&minicbor::to_vec(Address::illegal()).unwrap()).to_string()
+        );
+
+        let output = CommandOutput::text("abc").with_json(json!({ "a": 1 }));
+        assert_eq!(output.render(OutputFormat::Plain).unwrap(), "abc");
+        assert_eq!(
+            output.render(OutputFormat::Json).unwrap(),
+            "{\n  \"a\": 1\n}"
+        );
+    }
+}
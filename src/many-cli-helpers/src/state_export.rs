@@ -0,0 +1,207 @@
+//! Exports of the whole state of a store at a height, to start another
+//! network from it, e.g. to restart a chain after a hard fork or to spin up
+//! a testnet from a production snapshot.
+//!
+//! An export lists every key and value of the store, sorted by key, so two
+//! exports of the same state are the same bytes. It is written either as
+//! JSON5, with the keys and values in hexadecimal:
+//!
+//! ```json5
+//! {
+//!   height: 1234,
+//!   hash: "7a6b...",
+//!   entries: { "2f686569676874": "00000000000004d2", ... },
+//! }
+//! ```
+//!
+//! or as a CBOR map of the same fields, with byte strings.
+use anyhow::anyhow;
+use minicbor::encode::{Error, Write};
+use minicbor::{Decode, Decoder, Encode, Encoder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(clap::ArgEnum, Clone, Copy, Debug, Eq, PartialEq)]
+pub enum StateFormat {
+    Json5,
+    Cbor,
+}
+
+/// Options of the `export-state` command of the servers.
+#[derive(clap::Args, Clone, Debug)]
+pub struct ExportStateOpt {
+    /// The height to export. The persistent store, or one of its
+    /// checkpoints, needs to be at this height. By default, the height of
+    /// the persistent store.
+    #[clap(long)]
+    pub height: Option<u64>,
+
+    /// The file to write the export to.
+    #[clap(long)]
+    pub output: PathBuf,
+
+    /// The format of the export.
+    #[clap(long, arg_enum, default_value_t = StateFormat::Json5)]
+    pub format: StateFormat,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "StateExportJson", into = "StateExportJson")]
+pub struct StateExport {
+    /// The height of the store.
+    pub height: u64,
+
+    /// The root hash of the store. A store imported from the export has the
+    /// same content, but its tree is built at once rather than block after
+    /// block, so it can have another root hash.
+    pub hash: Vec<u8>,
+
+    /// Every key of the store, and its value.
+    pub entries: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+/// The JSON5 form of a [`StateExport`].
+#[derive(Deserialize, Serialize)]
+struct StateExportJson {
+    height: u64,
+    hash: String,
+    entries: BTreeMap<String, String>,
+}
+
+impl From<StateExport> for StateExportJson {
+    fn from(value: StateExport) -> Self {
+        Self {
+            height: value.height,
+            hash: hex::encode(value.hash),
+            entries: value
+                .entries
+                .into_iter()
+                .map(|(k, v)| (hex::encode(k), hex::encode(v)))
+                .collect(),
+        }
+    }
+}
+
+impl TryFrom<StateExportJson> for StateExport {
+    type Error = hex::FromHexError;
+
+    fn try_from(value: StateExportJson) -> Result<Self, Self::Error> {
+        Ok(Self {
+            height: value.height,
+            hash: hex::decode(value.hash)?,
+            entries: value
+                .entries
+                .into_iter()
+                .map(|(k, v)| Ok((hex::decode(k)?, hex::decode(v)?)))
+                .collect::<Result<_, hex::FromHexError>>()?,
+        })
+    }
+}
+
+impl<C> Encode<C> for StateExport {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>, _: &mut C) -> Result<(), Error<W::Error>> {
+        e.map(3)?
+            .u8(0)?
+            .u64(self.height)?
+            .u8(1)?
+            .bytes(&self.hash)?
+            .u8(2)?
+            .map(self.entries.len() as u64)?;
+        for (key, value) in &self.entries {
+            e.bytes(key)?.bytes(value)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'b, C> Decode<'b, C> for StateExport {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        let mut export = StateExport::default();
+        let len = d
+            .map()?
+            .ok_or_else(|| minicbor::decode::Error::message("Expected a definite map"))?;
+        for _ in 0..len {
+            match d.u8()? {
+                0 => export.height = d.u64()?,
+                1 => export.hash = d.bytes()?.to_vec(),
+                2 => {
+                    let len = d.map()?.ok_or_else(|| {
+                        minicbor::decode::Error::message("Expected a definite map")
+                    })?;
+                    for _ in 0..len {
+                        let key = d.bytes()?.to_vec();
+                        export.entries.insert(key, d.bytes()?.to_vec());
+                    }
+                }
+                _ => d.skip()?,
+            }
+        }
+        Ok(export)
+    }
+}
+
+impl StateExport {
+    pub fn to_bytes(&self, format: StateFormat) -> Result<Vec<u8>, anyhow::Error> {
+        match format {
+            StateFormat::Json5 => Ok(serde_json::to_vec_pretty(self)?),
+            StateFormat::Cbor => Ok(minicbor::to_vec(self)?),
+        }
+    }
+
+    /// Decode an export in either format. JSON5 exports are objects, so
+    /// they start with a `{`, which never starts a CBOR map.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, anyhow::Error> {
+        match bytes.iter().find(|b| !b.is_ascii_whitespace()) {
+            Some(b'{') => Ok(json5::from_str(std::str::from_utf8(bytes)?)?),
+            _ => minicbor::decode(bytes).map_err(|e| anyhow!("Invalid CBOR export: {e}")),
+        }
+    }
+
+    pub fn read(path: &Path) -> Result<Self, anyhow::Error> {
+        let bytes =
+            std::fs::read(path).map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?;
+        Self::from_bytes(&bytes).map_err(|e| anyhow!("Invalid {}: {e}", path.display()))
+    }
+
+    pub fn write(&self, path: &Path, format: StateFormat) -> Result<(), anyhow::Error> {
+        std::fs::write(path, self.to_bytes(format)?)
+            .map_err(|e| anyhow!("Could not write {}: {e}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export() -> StateExport {
+        StateExport {
+            height: 42,
+            hash: vec![1, 2, 3],
+            entries: BTreeMap::from([
+                (b"/height".to_vec(), 42u64.to_be_bytes().to_vec()),
+                (b"/balances/a".to_vec(), vec![0x18, 0x64]),
+                (vec![0xff, 0x00], vec![]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        for format in [StateFormat::Json5, StateFormat::Cbor] {
+            let bytes = export().to_bytes(format).unwrap();
+            assert_eq!(StateExport::from_bytes(&bytes).unwrap(), export());
+        }
+    }
+
+    #[test]
+    fn json5_is_hexadecimal() {
+        let export =
+            StateExport::from_bytes(br#"{ height: 1, hash: "ab", entries: { "2f61": "01", } }"#)
+                .unwrap();
+        assert_eq!(export.hash, vec![0xab]);
+        assert_eq!(export.entries[b"/a".as_slice()], vec![1]);
+
+        assert!(StateExport::from_bytes(br#"{ height: 1, hash: "xyz", entries: {} }"#).is_err());
+    }
+}
@@ -0,0 +1,33 @@
+use opentelemetry::sdk::trace::{config, Tracer};
+use opentelemetry::sdk::Resource;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use tracing::Subscriber;
+use tracing_opentelemetry::OpenTelemetryLayer;
+use tracing_subscriber::registry::LookupSpan;
+
+/// A layer exporting the spans to an OpenTelemetry collector over OTLP/gRPC.
+/// Spans are exported in batches from a thread of their own, so this can be
+/// called outside of a Tokio runtime.
+pub fn otlp_layer<S>(
+    endpoint: &str,
+    service_name: String,
+) -> Result<OpenTelemetryLayer<S, Tracer>, String>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(endpoint),
+        )
+        .with_trace_config(
+            config().with_resource(Resource::new([KeyValue::new("service.name", service_name)])),
+        )
+        .install_batch(opentelemetry::runtime::TokioCurrentThread)
+        .map_err(|e| e.to_string())?;
+
+    Ok(tracing_opentelemetry::layer().with_tracer(tracer))
+}
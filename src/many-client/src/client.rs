@@ -1,9 +1,16 @@
 pub mod base;
 pub mod blockchain;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod blocking;
+pub mod bridge;
+pub mod events;
 pub mod ledger;
+pub mod names;
 
+pub use bridge::BridgeClient;
+pub use events::EventsClient;
 pub use ledger::LedgerClient;
+pub use names::NamesClient;
 
 use coset::{CoseSign1, TaggedCborSerializable};
 use many_error::ManyError;
@@ -11,19 +18,118 @@ use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{verifiers, Address, Identity};
 use many_identity_dsa::CoseKeyVerifier;
 use many_modules::base::Status;
+use many_modules::names;
+use many_modules::r#async::attributes::AsyncAttribute;
+use many_modules::r#async::{StatusArgs, StatusReturn};
+use many_modules::ModuleClientTransport;
 use many_protocol::{
     encode_cose_sign1_from_request, RequestMessage, RequestMessageBuilder, ResponseMessage,
 };
+use many_types::attributes::{Attribute, AttributeSet};
+use many_types::deadline::Deadline;
+use many_types::node_state::NODE_STATE;
+use many_types::trace::TraceContext;
+use many_types::{Timestamp, PROOF};
 use minicbor::Encode;
 use reqwest::{IntoUrl, Url};
 use std::fmt::{Debug, Formatter};
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+#[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The time of new requests. `SystemTime` is not available in browsers, so
+/// WASM builds use the clock of the JavaScript runtime.
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> Timestamp {
+    Timestamp::now()
+}
+
+#[cfg(target_arch = "wasm32")]
+fn now() -> Timestamp {
+    Timestamp::new((js_sys::Date::now() / 1000.0) as u64).expect("Time flew all around")
+}
+
+/// How often [`ManyClient::wait_response`] polls the server.
+#[cfg(not(target_arch = "wasm32"))]
+pub const ASYNC_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The options of the HTTP connections of a client. Browsers manage their
+/// own connections, so WASM builds ignore them.
+#[derive(Clone, Debug)]
+pub struct ConnectionConfig {
+    /// Use HTTP/2 without negotiating it, so requests are multiplexed over
+    /// one connection. The server, or a proxy in front of it, must support
+    /// HTTP/2.
+    pub http2_prior_knowledge: bool,
+
+    /// How long an idle connection is kept open for the next requests.
+    pub pool_idle_timeout: Option<Duration>,
+
+    /// The maximum number of idle connections kept open.
+    pub pool_max_idle: usize,
+
+    /// The interval of the TCP keep-alive probes of idle connections.
+    pub tcp_keepalive: Option<Duration>,
+
+    /// The interval of the HTTP/2 pings keeping idle connections open.
+    pub http2_keep_alive_interval: Option<Duration>,
+
+    /// The maximum number of requests of a batch sent at once.
+    pub max_in_flight: usize,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            http2_prior_knowledge: false,
+            pool_idle_timeout: Some(Duration::from_secs(90)),
+            pool_max_idle: 32,
+            tcp_keepalive: Some(Duration::from_secs(60)),
+            http2_keep_alive_interval: None,
+            max_in_flight: 32,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn build(&self) -> Result<reqwest::Client, String> {
+        let mut builder = reqwest::Client::builder()
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .pool_max_idle_per_host(self.pool_max_idle)
+            .tcp_keepalive(self.tcp_keepalive)
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(self.http2_keep_alive_interval.is_some());
+        if self.http2_prior_knowledge {
+            builder = builder.http2_prior_knowledge();
+        }
+        builder.build().map_err(|e| e.to_string())
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    fn build(&self) -> Result<reqwest::Client, String> {
+        Ok(reqwest::Client::new())
+    }
+}
 
 #[derive(Clone)]
 pub struct ManyClient<I: Identity> {
     identity: I,
     to: Option<Address>,
     url: Url,
+
+    /// Shared by the requests of the client and its clones, so they reuse
+    /// its connections.
+    http: reqwest::Client,
+    max_in_flight: usize,
     verifier: (AnonymousVerifier, CoseKeyVerifier),
+    trace: Option<TraceContext>,
+    deadline: Option<Duration>,
+    node_state: bool,
+    #[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+    light_client: Option<Arc<crate::light_client::LightClient>>,
 }
 
 impl<I: Identity + Debug> Debug for ManyClient<I> {
@@ -37,13 +143,20 @@ impl<I: Identity + Debug> Debug for ManyClient<I> {
 }
 
 pub async fn send_envelope<S: IntoUrl>(url: S, message: CoseSign1) -> Result<CoseSign1, ManyError> {
+    post_envelope(&reqwest::Client::new(), url, message).await
+}
+
+async fn post_envelope<S: IntoUrl>(
+    client: &reqwest::Client,
+    url: S,
+    message: CoseSign1,
+) -> Result<CoseSign1, ManyError> {
     let bytes = message
         .to_tagged_vec()
         .map_err(|_| ManyError::internal_server_error())?;
     let len = bytes.len();
     tracing::debug!("Message length in bytes: {}", len);
 
-    let client = reqwest::Client::new();
     tracing::debug!("request {}", hex::encode(&bytes));
     let response = client
         .post(url)
@@ -78,53 +191,189 @@ pub async fn send_envelope<S: IntoUrl>(url: S, message: CoseSign1) -> Result<Cos
 impl<I: Identity> ManyClient<I> {
     pub fn new<S: IntoUrl>(url: S, to: Address, identity: I) -> Result<Self, String> {
         let verifier = (verifiers::AnonymousVerifier, CoseKeyVerifier);
+        let connection = ConnectionConfig::default();
 
         Ok(Self {
             identity,
             to: Some(to),
             url: url.into_url().map_err(|e| e.to_string())?,
+            http: connection.build()?,
+            max_in_flight: connection.max_in_flight,
             verifier,
+            trace: None,
+            deadline: None,
+            node_state: false,
+            #[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+            light_client: None,
         })
     }
 
+    /// Use new connections with these options.
+    pub fn with_connection(mut self, connection: ConnectionConfig) -> Result<Self, String> {
+        self.http = connection.build()?;
+        self.max_in_flight = connection.max_in_flight.max(1);
+        Ok(self)
+    }
+
+    /// Send the trace context with every request, so servers report their
+    /// spans of the requests in that trace.
+    pub fn with_trace(mut self, trace: TraceContext) -> Self {
+        self.trace = Some(trace);
+        self
+    }
+
+    /// Give every request a deadline `timeout` after it is built, so servers
+    /// do not execute it anymore once the caller stopped waiting for it.
+    pub fn with_deadline(mut self, timeout: Duration) -> Self {
+        self.deadline = Some(timeout);
+        self
+    }
+
+    /// Ask servers for their state in the responses of every request, read
+    /// with `response.attributes.get::<NodeState>()`. Clients can compare the
+    /// heights of the responses to detect a lagging node, or wait for a node
+    /// to reach the height of a transaction before reading its changes.
+    pub fn with_node_state(mut self) -> Self {
+        self.node_state = true;
+        self
+    }
+
+    /// Request a proof with the calls to the methods the light client can
+    /// verify, and verify their results against its trusted blocks.
+    #[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+    pub fn with_light_client(
+        mut self,
+        light_client: Arc<crate::light_client::LightClient>,
+    ) -> Self {
+        self.light_client = Some(light_client);
+        self
+    }
+
+    #[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+    fn verifies(&self, method: &str) -> bool {
+        self.light_client
+            .as_ref()
+            .map_or(false, |light_client| light_client.verifies(method))
+    }
+
+    #[cfg(not(all(feature = "light-client", not(target_arch = "wasm32"))))]
+    fn verifies(&self, _method: &str) -> bool {
+        false
+    }
+
     pub async fn send_message(
         &self,
         message: RequestMessage,
     ) -> Result<ResponseMessage, ManyError> {
+        #[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+        if let Some(light_client) = &self.light_client {
+            if light_client.verifies(&message.method) {
+                let response = self.send_request(message.clone()).await?;
+                light_client.verify_response(&message, &response).await?;
+                return Ok(response);
+            }
+        }
+        self.send_request(message).await
+    }
+
+    async fn send_request(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
         let cose = encode_cose_sign1_from_request(message, &self.identity).unwrap();
-        let cose_sign1 = send_envelope(self.url.clone(), cose).await?;
+        let cose_sign1 = post_envelope(&self.http, self.url.clone(), cose).await?;
 
         ResponseMessage::decode_and_verify(&cose_sign1, &self.verifier)
     }
 
-    pub async fn call_raw<M>(
+    /// Send messages concurrently, up to the `max_in_flight` of the
+    /// connection options at once. Over HTTP/2 they share one connection.
+    /// The responses are in the order of the messages, and each of them can
+    /// fail on its own.
+    pub async fn send_batch(
         &self,
-        method: M,
-        argument: &[u8],
-    ) -> Result<ResponseMessage, ManyError>
+        messages: impl IntoIterator<Item = RequestMessage>,
+    ) -> Vec<Result<ResponseMessage, ManyError>> {
+        use futures::StreamExt;
+
+        futures::stream::iter(messages.into_iter().map(|m| self.send_message(m)))
+            .buffered(self.max_in_flight)
+            .collect()
+            .await
+    }
+
+    /// Build the request message of a call, without signing or sending it.
+    pub fn request<M>(&self, method: M, argument: &[u8]) -> Result<RequestMessage, ManyError>
     where
         M: Into<String>,
     {
         let mut nonce = [0u8; 16];
         rand::RngCore::fill_bytes(&mut rand::thread_rng(), &mut nonce);
 
+        let method = method.into();
         let mut builder = RequestMessageBuilder::default();
 
+        let mut attributes = AttributeSet::new();
+        if self.verifies(&method) {
+            attributes.insert(PROOF);
+        }
         builder
             .version(1)
             .from(self.identity.address())
-            .method(method.into())
+            .method(method)
             .data(argument.to_vec())
+            .timestamp(now())
             .nonce(nonce.to_vec());
+        if let Some(trace) = self.trace {
+            attributes.insert(trace.into());
+        }
+        if let Some(timeout) = self.deadline {
+            let deadline = Timestamp::new(now().secs() + timeout.as_secs())?;
+            attributes.insert(Deadline(deadline).into());
+        }
+        if self.node_state {
+            attributes.insert(NODE_STATE);
+        }
+        if !attributes.is_empty() {
+            builder.attributes(attributes);
+        }
 
-        let message: RequestMessage = if let Some(to) = self.to {
+        if let Some(to) = self.to {
             builder.to(to)
         } else {
             &mut builder
         }
         .build()
-        .map_err(|_| ManyError::internal_server_error())?;
+        .map_err(|_| ManyError::internal_server_error())
+    }
 
+    /// Sign the request message of a call, returning its envelope.
+    pub fn sign<M>(&self, method: M, argument: &[u8]) -> Result<CoseSign1, ManyError>
+    where
+        M: Into<String>,
+    {
+        encode_cose_sign1_from_request(self.request(method, argument)?, &self.identity)
+    }
+
+    /// Decode and verify the envelope of a response from the server.
+    pub fn decode_response(&self, envelope: &CoseSign1) -> Result<ResponseMessage, ManyError> {
+        ResponseMessage::decode_and_verify(envelope, &self.verifier)
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.url
+    }
+
+    pub fn address(&self) -> Address {
+        self.identity.address()
+    }
+
+    pub async fn call_raw<M>(
+        &self,
+        method: M,
+        argument: &[u8],
+    ) -> Result<ResponseMessage, ManyError>
+    where
+        M: Into<String>,
+    {
+        let message = self.request(method, argument)?;
         self.send_message(message).await
     }
 
@@ -147,6 +396,66 @@ impl<I: Identity> ManyClient<I> {
         self.call(method, argument).await?.data
     }
 
+    /// Wait for the response of a deferred request, polling `async.status`
+    /// with its token until `timeout`. Responses that are not deferred are
+    /// returned as they are.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn wait_response(
+        &self,
+        mut response: ResponseMessage,
+        timeout: Duration,
+    ) -> Result<ResponseMessage, ManyError> {
+        let until = std::time::Instant::now() + timeout;
+        loop {
+            let token = match (&response.data, response.attributes.get::<AsyncAttribute>()) {
+                (Ok(data), Ok(attr)) if data.is_empty() => attr.token,
+                _ => return Ok(response),
+            };
+            tracing::debug!("Waiting for async token {}", hex::encode(&token));
+
+            // The response may be deferred again, e.g. by a server forwarding
+            // requests to a blockchain.
+            response = loop {
+                let data = self
+                    .call_(
+                        "async.status",
+                        StatusArgs {
+                            token: token.clone(),
+                        },
+                    )
+                    .await?;
+                let status: StatusReturn = minicbor::decode(&data)
+                    .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
+                match status {
+                    StatusReturn::Done { response } => break self.decode_response(&response)?,
+                    StatusReturn::Queued | StatusReturn::Processing
+                        if std::time::Instant::now() < until =>
+                    {
+                        tokio::time::sleep(ASYNC_POLL_INTERVAL).await
+                    }
+                    status => return status.into_response(&token),
+                }
+            };
+        }
+    }
+
+    /// Call a method and wait up to `timeout` for its response, if the server
+    /// deferred it.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub async fn call_and_wait<M, A>(
+        &self,
+        method: M,
+        argument: A,
+        timeout: Duration,
+    ) -> Result<ResponseMessage, ManyError>
+    where
+        M: Into<String>,
+        A: Encode<()>,
+    {
+        let response = self.call(method, argument).await?;
+        self.wait_response(response, timeout).await
+    }
+
     pub async fn status(&self) -> Result<Status, ManyError> {
         let response = self.call_("status", ()).await?;
 
@@ -154,4 +463,48 @@ impl<I: Identity> ManyClient<I> {
             .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
         Ok(status)
     }
+
+    /// Negotiate the version of the module of an attribute to use with the
+    /// server, given the `supported` versions of this client.
+    pub async fn negotiate_version(
+        &self,
+        attribute: &Attribute,
+        supported: RangeInclusive<u32>,
+    ) -> Result<u32, ManyError> {
+        self.status()
+            .await?
+            .module_versions
+            .negotiate(attribute.id, supported)
+    }
+
+    /// Resolve a textual address or a name registered with the names
+    /// attribute of the server to an address.
+    pub async fn resolve_address(&self, name_or_address: &str) -> Result<Address, ManyError> {
+        if let Ok(address) = Address::from_str(name_or_address) {
+            return Ok(address);
+        }
+
+        let response = self
+            .call_(
+                "names.resolve",
+                names::ResolveArgs {
+                    name: name_or_address.to_string(),
+                },
+            )
+            .await?;
+        let resolved: names::ResolveReturns = minicbor::decode(response.as_slice())
+            .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
+        Ok(resolved.address)
+    }
+}
+
+/// Allows the typed module clients (e.g.
+/// [many_modules::ledger::LedgerCommandsModuleClient]) to use this client.
+/// The transport needs `Send` futures, which browser requests are not.
+#[cfg(not(target_arch = "wasm32"))]
+#[async_trait::async_trait]
+impl<I: Identity> ModuleClientTransport for ManyClient<I> {
+    async fn call_endpoint(&self, method: &str, argument: Vec<u8>) -> Result<Vec<u8>, ManyError> {
+        self.call_raw(method, &argument).await?.data
+    }
 }
@@ -4,7 +4,10 @@ use many_modules::base::Status;
 use many_protocol::{RequestMessage, ResponseMessage};
 use minicbor::Encode;
 use reqwest::IntoUrl;
+use std::sync::OnceLock;
+use std::time::Duration;
 
+use crate::client::ConnectionConfig;
 use crate::ManyClient as AsyncClient;
 
 #[derive(Debug, Clone)]
@@ -12,16 +15,19 @@ pub struct ManyClient<I: Identity> {
     client: AsyncClient<I>,
 }
 
+/// The runtime of the calls made outside of one. It lives as long as the
+/// process, so the connections pooled by a call are still usable by the next.
+static RUNTIME: OnceLock<tokio::runtime::Runtime> = OnceLock::new();
+
 pub fn block_on<F>(future: F) -> F::Output
 where
     F: std::future::Future,
 {
     match tokio::runtime::Handle::try_current() {
         Ok(handle) => tokio::task::block_in_place(|| handle.block_on(future)),
-        Err(_) => {
-            let runtime = tokio::runtime::Runtime::new().unwrap();
-            runtime.block_on(future)
-        }
+        Err(_) => RUNTIME
+            .get_or_init(|| tokio::runtime::Runtime::new().unwrap())
+            .block_on(future),
     }
 }
 
@@ -31,10 +37,31 @@ impl<I: Identity> ManyClient<I> {
         Ok(Self { client })
     }
 
+    pub fn with_connection(self, connection: ConnectionConfig) -> Result<Self, String> {
+        Ok(Self {
+            client: self.client.with_connection(connection)?,
+        })
+    }
+
     pub fn send_message(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
         block_on(self.client.send_message(message))
     }
 
+    pub fn send_batch(
+        &self,
+        messages: impl IntoIterator<Item = RequestMessage>,
+    ) -> Vec<Result<ResponseMessage, ManyError>> {
+        block_on(self.client.send_batch(messages))
+    }
+
+    /// Build the request message of a call, without signing or sending it.
+    pub fn request<M>(&self, method: M, argument: &[u8]) -> Result<RequestMessage, ManyError>
+    where
+        M: Into<String>,
+    {
+        self.client.request(method, argument)
+    }
+
     pub fn call_raw<M>(&self, method: M, argument: &[u8]) -> Result<ResponseMessage, ManyError>
     where
         M: Into<String>,
@@ -58,7 +85,181 @@ impl<I: Identity> ManyClient<I> {
         block_on(self.client.call_(method, argument))
     }
 
+    pub fn wait_response(
+        &self,
+        response: ResponseMessage,
+        timeout: Duration,
+    ) -> Result<ResponseMessage, ManyError> {
+        block_on(self.client.wait_response(response, timeout))
+    }
+
+    pub fn call_and_wait<M, A>(
+        &self,
+        method: M,
+        argument: A,
+        timeout: Duration,
+    ) -> Result<ResponseMessage, ManyError>
+    where
+        M: Into<String>,
+        A: Encode<()>,
+    {
+        block_on(self.client.call_and_wait(method, argument, timeout))
+    }
+
     pub fn status(&self) -> Result<Status, ManyError> {
         block_on(self.client.status())
     }
+
+    pub fn resolve_address(&self, name_or_address: &str) -> Result<Address, ManyError> {
+        block_on(self.client.resolve_address(name_or_address))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::{CoseSign1, TaggedCborSerializable};
+    use many_identity::verifiers::AnonymousVerifier;
+    use many_identity::AnonymousIdentity;
+    use many_protocol::{decode_request_from_cose_sign1, encode_cose_sign1_from_response};
+    use std::io::Read;
+    use std::net::SocketAddr;
+    use std::sync::{Arc, Mutex};
+
+    /// Start a server answering every request with its data, returning its
+    /// URL and the addresses the requests came from.
+    fn echo_server() -> (String, Arc<Mutex<Vec<SocketAddr>>>) {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+        let peers = Arc::new(Mutex::new(Vec::new()));
+
+        let peers_ = peers.clone();
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                peers_.lock().unwrap().push(*request.remote_addr().unwrap());
+
+                let mut body = Vec::new();
+                request.as_reader().read_to_end(&mut body).unwrap();
+                let envelope = CoseSign1::from_tagged_slice(&body).unwrap();
+                let message =
+                    decode_request_from_cose_sign1(&envelope, &AnonymousVerifier).unwrap();
+                let response = ResponseMessage::from_request(
+                    &message,
+                    &Address::anonymous(),
+                    Ok(message.data.clone()),
+                );
+                let envelope =
+                    encode_cose_sign1_from_response(response, &AnonymousIdentity).unwrap();
+                request
+                    .respond(tiny_http::Response::from_data(
+                        envelope.to_tagged_vec().unwrap(),
+                    ))
+                    .unwrap();
+            }
+        });
+        (url, peers)
+    }
+
+    fn client(url: &str) -> ManyClient<AnonymousIdentity> {
+        ManyClient::new(url, Address::anonymous(), AnonymousIdentity).unwrap()
+    }
+
+    fn requests(client: &ManyClient<AnonymousIdentity>, count: u8) -> Vec<RequestMessage> {
+        (0..count)
+            .map(|i| client.request("echo", &[i]).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn calls_reuse_connections() {
+        let (url, peers) = echo_server();
+        let client = client(&url);
+        for i in 0..3u8 {
+            let response = client.call_raw("echo", &[i]).unwrap();
+            assert_eq!(response.data.unwrap(), vec![i]);
+        }
+
+        // Every call is sent over the connection of the first one.
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers.len(), 3);
+        assert!(peers.iter().all(|peer| *peer == peers[0]));
+    }
+
+    #[test]
+    fn send_batch() {
+        let (url, _) = echo_server();
+        let client = client(&url)
+            .with_connection(ConnectionConfig {
+                max_in_flight: 3,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // The responses are in the order of the messages.
+        let responses = client.send_batch(requests(&client, 10));
+        assert_eq!(responses.len(), 10);
+        for (i, response) in responses.into_iter().enumerate() {
+            assert_eq!(response.unwrap().data.unwrap(), vec![i as u8]);
+        }
+    }
+
+    #[test]
+    fn send_batch_fails_each_message() {
+        // Nothing listens on the port of a server that stopped.
+        let url = {
+            let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+            format!("http://{}", server.server_addr().to_ip().unwrap())
+        };
+        let client = client(&url);
+
+        let responses = client.send_batch(requests(&client, 3));
+        assert_eq!(responses.len(), 3);
+        for response in responses {
+            assert_eq!(
+                response.unwrap_err().code(),
+                ManyError::unexpected_transport_error("").code()
+            );
+        }
+    }
+
+    #[test]
+    fn connection_config_in_flight() {
+        let (url, peers) = echo_server();
+
+        // A batch is still sent without messages in flight.
+        let client = client(&url)
+            .with_connection(ConnectionConfig {
+                max_in_flight: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(client
+            .send_batch(requests(&client, 2))
+            .into_iter()
+            .all(|response| response.is_ok()));
+
+        // Sent one at a time, the messages share one connection.
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_eq!(peers[0], peers[1]);
+    }
+
+    #[test]
+    fn connection_config_without_pool() {
+        let (url, peers) = echo_server();
+        let client = client(&url)
+            .with_connection(ConnectionConfig {
+                pool_max_idle: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        for i in 0..2u8 {
+            client.call_raw("echo", &[i]).unwrap();
+        }
+
+        // Without idle connections, every call opens its own.
+        let peers = peers.lock().unwrap();
+        assert_eq!(peers.len(), 2);
+        assert_ne!(peers[0], peers[1]);
+    }
 }
@@ -0,0 +1,64 @@
+use many_client_macros::many_client;
+use many_error::ManyError;
+pub use many_identity::Identity;
+pub use many_modules::bridge::{
+    AnchorArgs, AnchorReturns, BridgeTransfer, BurnArgs, BurnReturns, ClaimArgs, ClaimReturns,
+    Counterparty, InfoArgs, InfoReturns, LockArgs, LockReturns, RegisterArgs, RegisterReturns,
+    TransferArgs, TransferInfo, TransferKind,
+};
+use minicbor::bytes::ByteVec;
+
+use crate::ManyClient;
+
+#[many_client(BridgeClient, "bridge")]
+trait BridgeClientTrait {
+    fn info(&self, args: InfoArgs) -> Result<InfoReturns, ManyError>;
+    fn register(&self, args: RegisterArgs) -> Result<RegisterReturns, ManyError>;
+    fn lock(&self, args: LockArgs) -> Result<LockReturns, ManyError>;
+    fn burn(&self, args: BurnArgs) -> Result<BurnReturns, ManyError>;
+    fn transfer(&self, args: TransferArgs) -> Result<TransferInfo, ManyError>;
+    fn anchor(&self, args: AnchorArgs) -> Result<AnchorReturns, ManyError>;
+    fn claim(&self, args: ClaimArgs) -> Result<ClaimReturns, ManyError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct BridgeClient<I: Identity>(ManyClient<I>);
+
+impl<I: Identity> BridgeClient<I> {
+    /// Relay a transfer out of this ledger to its destination: anchor the
+    /// state hash the proof of the transfer is against, then claim it.
+    ///
+    /// The state hash comes from this ledger's server, which the relayer
+    /// needs to trust (e.g. by checking it against the block at that height)
+    /// since the destination trusts the relayer. The client of the
+    /// destination needs to be one of its relayers for the source.
+    pub async fn relay<J: Identity>(
+        &self,
+        destination: &BridgeClient<J>,
+        id: ByteVec,
+    ) -> Result<(), ManyError> {
+        let TransferInfo {
+            transfer,
+            height,
+            hash,
+            proof,
+        } = self.transfer(TransferArgs { id: id.clone() }).await?;
+
+        destination
+            .anchor(AnchorArgs {
+                chain: transfer.source,
+                height,
+                hash,
+            })
+            .await?;
+        destination
+            .claim(ClaimArgs {
+                source: transfer.source,
+                height,
+                id,
+                proof,
+            })
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,487 @@
+use many_client_macros::many_client;
+use many_error::ManyError;
+pub use many_identity::Identity;
+pub use many_modules::events::{
+    EventFilter, EventId, EventLog, InfoArgs, InfoReturn, ListArgs, ListReturns,
+};
+use many_types::{CborRange, SortOrder};
+use std::collections::{BTreeMap, Bound, VecDeque};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::ManyClient;
+
+#[many_client(EventsClient, "events")]
+trait EventsClientTrait {
+    fn info(&self, args: InfoArgs) -> Result<InfoReturn, ManyError>;
+    fn list(&self, args: ListArgs) -> Result<ListReturns, ManyError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct EventsClient<I: Identity>(ManyClient<I>);
+
+/// Persists the last event acknowledged by each subscriber.
+pub trait CursorStore {
+    fn load(&self, subscriber: &str) -> Result<Option<EventId>, ManyError>;
+    fn store(&self, subscriber: &str, id: &EventId) -> Result<(), ManyError>;
+}
+
+/// Keeps cursors in memory. Subscriptions using it only resume while the
+/// process is alive.
+#[derive(Debug, Default)]
+pub struct MemoryCursorStore(Mutex<BTreeMap<String, EventId>>);
+
+impl CursorStore for MemoryCursorStore {
+    fn load(&self, subscriber: &str) -> Result<Option<EventId>, ManyError> {
+        Ok(self.0.lock().unwrap().get(subscriber).cloned())
+    }
+
+    fn store(&self, subscriber: &str, id: &EventId) -> Result<(), ManyError> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(subscriber.to_string(), id.clone());
+        Ok(())
+    }
+}
+
+/// Keeps the cursors of all subscribers in a single CBOR file. Updates are
+/// written and synced to a temporary file first, then renamed over the
+/// previous one and the directory is synced, so a crash never leaves a
+/// partial file nor loses an acknowledged cursor.
+#[derive(Debug)]
+pub struct FileCursorStore {
+    path: PathBuf,
+}
+
+impl FileCursorStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    fn read(&self) -> Result<BTreeMap<String, EventId>, ManyError> {
+        match std::fs::read(&self.path) {
+            Ok(bytes) => minicbor::decode(&bytes).map_err(ManyError::deserialization_error),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+            Err(e) => Err(ManyError::unknown(e.to_string())),
+        }
+    }
+}
+
+impl CursorStore for FileCursorStore {
+    fn load(&self, subscriber: &str) -> Result<Option<EventId>, ManyError> {
+        Ok(self.read()?.remove(subscriber))
+    }
+
+    fn store(&self, subscriber: &str, id: &EventId) -> Result<(), ManyError> {
+        let mut cursors = self.read()?;
+        cursors.insert(subscriber.to_string(), id.clone());
+        let bytes = minicbor::to_vec(&cursors).map_err(ManyError::serialization_error)?;
+
+        let io_error = |e: std::io::Error| ManyError::unknown(e.to_string());
+        let tmp = self.path.with_extension("tmp");
+        let mut file = std::fs::File::create(&tmp).map_err(io_error)?;
+        file.write_all(&bytes).map_err(io_error)?;
+        file.sync_all().map_err(io_error)?;
+        std::fs::rename(&tmp, &self.path).map_err(io_error)?;
+
+        // The rename is only durable once the directory is.
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        std::fs::File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(io_error)
+    }
+}
+
+/// A resumable stream of events. Events are returned in ascending order,
+/// starting after the last event acknowledged by the subscriber. Events
+/// that were returned but not acknowledged are returned again by the next
+/// poll, including after a restart.
+#[derive(Debug)]
+pub struct EventSubscription<I: Identity, S: CursorStore> {
+    client: EventsClient<I>,
+    subscriber: String,
+    store: S,
+    filter: EventFilter,
+}
+
+impl<I: Identity, S: CursorStore> EventSubscription<I, S> {
+    pub fn new(
+        client: EventsClient<I>,
+        subscriber: impl ToString,
+        store: S,
+        filter: EventFilter,
+    ) -> Self {
+        Self {
+            client,
+            subscriber: subscriber.to_string(),
+            store,
+            filter,
+        }
+    }
+
+    /// The last acknowledged event, if any.
+    pub fn cursor(&self) -> Result<Option<EventId>, ManyError> {
+        self.store.load(&self.subscriber)
+    }
+
+    /// Returns up to `count` events following the cursor. Events the server
+    /// returns again, e.g. if it does not exclude the start of the range,
+    /// are dropped.
+    pub async fn poll(&self, count: Option<u64>) -> Result<Vec<EventLog>, ManyError> {
+        let cursor = self.cursor()?;
+        let start = match &cursor {
+            Some(id) => Bound::Excluded(id.clone()),
+            None => Bound::Unbounded,
+        };
+        let filter = EventFilter {
+            id_range: Some(CborRange {
+                start,
+                end: Bound::Unbounded,
+            }),
+            ..self.filter.clone()
+        };
+
+        let events = self
+            .client
+            .list(ListArgs {
+                count,
+                order: Some(SortOrder::Ascending),
+                filter: Some(filter),
+            })
+            .await?
+            .events;
+        Ok(events
+            .into_iter()
+            .filter(|event| cursor.as_ref().map_or(true, |cursor| &event.id > cursor))
+            .collect())
+    }
+
+    /// Record that all events up to and including `id` were processed.
+    pub fn acknowledge(&self, id: &EventId) -> Result<(), ManyError> {
+        self.store.store(&self.subscriber, id)
+    }
+}
+
+/// How the stream of an [`EventSubscription`] polls the events.
+#[derive(Clone, Debug)]
+pub struct SubscribeOptions {
+    /// The maximum number of events requested at once.
+    pub batch_size: u64,
+
+    /// How long to wait before polling again when there is no new event.
+    pub poll_interval: Duration,
+
+    /// The longest wait after consecutive errors. The wait starts at the
+    /// poll interval and doubles with every error.
+    pub max_backoff: Duration,
+}
+
+impl Default for SubscribeOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 100,
+            poll_interval: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(60),
+        }
+    }
+}
+
+/// The state of the stream of a subscription, between two polls.
+#[cfg(not(target_arch = "wasm32"))]
+struct SubscriptionStream<I: Identity, S: CursorStore> {
+    subscription: EventSubscription<I, S>,
+    options: SubscribeOptions,
+
+    /// The last event returned, acknowledged when the next one is asked for.
+    returned: Option<EventId>,
+
+    /// Events polled but not returned yet.
+    pending: VecDeque<EventLog>,
+
+    /// The wait before the next poll, after an error.
+    backoff: Option<Duration>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<I: Identity, S: CursorStore> SubscriptionStream<I, S> {
+    async fn next(&mut self) -> Result<EventLog, ManyError> {
+        if let Some(id) = &self.returned {
+            self.subscription.acknowledge(id)?;
+            self.returned = None;
+        }
+
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                self.returned = Some(event.id.clone());
+                return Ok(event);
+            }
+
+            if let Some(wait) = self.backoff {
+                tokio::time::sleep(wait).await;
+            }
+            match self.subscription.poll(Some(self.options.batch_size)).await {
+                Ok(events) if events.is_empty() => {
+                    self.backoff = None;
+                    tokio::time::sleep(self.options.poll_interval).await;
+                }
+                Ok(events) => {
+                    self.backoff = None;
+                    self.pending.extend(events);
+                }
+                Err(e) => {
+                    let wait = self.backoff.map_or(self.options.poll_interval, |wait| {
+                        (wait * 2).min(self.options.max_backoff)
+                    });
+                    tracing::warn!("Could not poll the events, retrying in {wait:?}: {e}");
+                    self.backoff = Some(wait);
+                    return Err(e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<I: Identity, S: CursorStore> EventSubscription<I, S> {
+    /// A never ending stream of the events following the cursor, polling
+    /// when all the events returned so far were consumed. An event is
+    /// acknowledged when the next one is asked for, so after a restart the
+    /// stream starts again at the event being processed. Errors are
+    /// returned as they happen, and the next poll waits longer after each
+    /// of them, so the stream can keep being consumed. The stream needs a
+    /// Tokio runtime.
+    pub fn into_stream(
+        self,
+        options: SubscribeOptions,
+    ) -> impl futures::Stream<Item = Result<EventLog, ManyError>> {
+        let stream = SubscriptionStream {
+            subscription: self,
+            options,
+            returned: None,
+            pending: VecDeque::new(),
+            backoff: None,
+        };
+        futures::stream::unfold(stream, |mut stream| async move {
+            let item = stream.next().await;
+            Some((item, stream))
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<I: Identity + Clone> ManyClient<I> {
+    /// A never ending stream of the events matching `filter`, in ascending
+    /// order. Its cursor is kept in memory, see [`EventSubscription`] to
+    /// resume it after a restart.
+    pub fn subscribe_events(
+        &self,
+        filter: EventFilter,
+    ) -> impl futures::Stream<Item = Result<EventLog, ManyError>> {
+        self.subscribe_events_with(
+            "",
+            MemoryCursorStore::default(),
+            filter,
+            SubscribeOptions::default(),
+        )
+    }
+
+    /// A never ending stream of the events matching `filter`, following the
+    /// cursor of `subscriber` in `store`.
+    pub fn subscribe_events_with<S: CursorStore>(
+        &self,
+        subscriber: impl ToString,
+        store: S,
+        filter: EventFilter,
+        options: SubscribeOptions,
+    ) -> impl futures::Stream<Item = Result<EventLog, ManyError>> {
+        EventSubscription::new(EventsClient::new(self.clone()), subscriber, store, filter)
+            .into_stream(options)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::{CoseSign1, TaggedCborSerializable};
+    use futures::StreamExt;
+    use many_identity::verifiers::AnonymousVerifier;
+    use many_identity::{Address, AnonymousIdentity};
+    use many_modules::events::EventInfo;
+    use many_protocol::{
+        decode_request_from_cose_sign1, encode_cose_sign1_from_response, ResponseMessage,
+    };
+    use many_types::Timestamp;
+    use std::io::Read;
+    use std::ops::RangeBounds;
+
+    fn event(id: u64) -> EventLog {
+        EventLog {
+            id: EventId::from(id),
+            time: Timestamp::new(1_000 + id).unwrap(),
+            content: EventInfo::Send {
+                from: Address::anonymous(),
+                to: Address::anonymous(),
+                symbol: Address::anonymous(),
+                amount: id.into(),
+                memo: None,
+            },
+            chain_hash: None,
+        }
+    }
+
+    /// Start a server with events 1 to `count`, answering `events.list` in
+    /// ascending order. It does not exclude the start of the range, so the
+    /// subscriptions have to.
+    fn events_server(count: u64) -> ManyClient<AnonymousIdentity> {
+        let server = tiny_http::Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", server.server_addr().to_ip().unwrap());
+
+        std::thread::spawn(move || {
+            for mut request in server.incoming_requests() {
+                let mut body = Vec::new();
+                request.as_reader().read_to_end(&mut body).unwrap();
+                let envelope = CoseSign1::from_tagged_slice(&body).unwrap();
+                let message =
+                    decode_request_from_cose_sign1(&envelope, &AnonymousVerifier).unwrap();
+                let args: ListArgs = minicbor::decode(&message.data).unwrap();
+
+                let range = args.filter.and_then(|f| f.id_range).unwrap_or_default();
+                let start = match range.start_bound() {
+                    Bound::Included(id) | Bound::Excluded(id) => id.clone(),
+                    Bound::Unbounded => EventId::from(0),
+                };
+                let events: Vec<EventLog> = (1..=count)
+                    .map(event)
+                    .filter(|e| e.id >= start)
+                    .take(args.count.unwrap_or(u64::MAX) as usize)
+                    .collect();
+                let returns = ListReturns {
+                    nb_events: count,
+                    events,
+                };
+
+                let response = ResponseMessage::from_request(
+                    &message,
+                    &Address::anonymous(),
+                    Ok(minicbor::to_vec(returns).unwrap()),
+                );
+                let envelope =
+                    encode_cose_sign1_from_response(response, &AnonymousIdentity).unwrap();
+                request
+                    .respond(tiny_http::Response::from_data(
+                        envelope.to_tagged_vec().unwrap(),
+                    ))
+                    .unwrap();
+            }
+        });
+        ManyClient::new(url, Address::anonymous(), AnonymousIdentity).unwrap()
+    }
+
+    fn ids(events: &[EventLog]) -> Vec<EventId> {
+        events.iter().map(|e| e.id.clone()).collect()
+    }
+
+    #[test]
+    fn memory_cursor_store() {
+        let store = MemoryCursorStore::default();
+        assert_eq!(store.load("a").unwrap(), None);
+
+        store.store("a", &EventId::from(1)).unwrap();
+        store.store("b", &EventId::from(2)).unwrap();
+        store.store("a", &EventId::from(3)).unwrap();
+        assert_eq!(store.load("a").unwrap(), Some(EventId::from(3)));
+        assert_eq!(store.load("b").unwrap(), Some(EventId::from(2)));
+    }
+
+    #[test]
+    fn file_cursor_store() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cursors.cbor");
+
+        let store = FileCursorStore::new(&path);
+        assert_eq!(store.load("a").unwrap(), None);
+        store.store("a", &EventId::from(1)).unwrap();
+        store.store("b", &EventId::from(2)).unwrap();
+        store.store("a", &EventId::from(3)).unwrap();
+
+        // Another store reads the cursors back, e.g. after a restart.
+        let store = FileCursorStore::new(&path);
+        assert_eq!(store.load("a").unwrap(), Some(EventId::from(3)));
+        assert_eq!(store.load("b").unwrap(), Some(EventId::from(2)));
+        assert_eq!(store.load("c").unwrap(), None);
+        assert!(!path.with_extension("tmp").exists());
+
+        // A corrupt file is an error, not an empty set of cursors.
+        std::fs::write(&path, b"not cbor").unwrap();
+        assert!(store.load("a").is_err());
+    }
+
+    #[tokio::test]
+    async fn resume_from_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cursors.cbor");
+        let client = events_server(5);
+        let subscription = || {
+            EventSubscription::new(
+                EventsClient::new(client.clone()),
+                "indexer",
+                FileCursorStore::new(&path),
+                EventFilter::default(),
+            )
+        };
+
+        let first = subscription();
+        assert_eq!(first.cursor().unwrap(), None);
+        let events = first.poll(Some(3)).await.unwrap();
+        assert_eq!(ids(&events), (1..=3).map(EventId::from).collect::<Vec<_>>());
+
+        // Events that were not acknowledged are returned again.
+        first.acknowledge(&EventId::from(2)).unwrap();
+        let events = first.poll(None).await.unwrap();
+        assert_eq!(ids(&events), (3..=5).map(EventId::from).collect::<Vec<_>>());
+
+        // A new subscription resumes after the acknowledged event.
+        drop(first);
+        let second = subscription();
+        assert_eq!(second.cursor().unwrap(), Some(EventId::from(2)));
+        let events = second.poll(None).await.unwrap();
+        assert_eq!(ids(&events), (3..=5).map(EventId::from).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn stream_resumes_from_cursor() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cursors.cbor");
+        let client = events_server(5);
+        let options = SubscribeOptions {
+            batch_size: 2,
+            ..Default::default()
+        };
+
+        let stream = client.subscribe_events_with(
+            "indexer",
+            FileCursorStore::new(&path),
+            EventFilter::default(),
+            options.clone(),
+        );
+        let events: Vec<EventLog> = stream.take(3).map(Result::unwrap).collect().await;
+        assert_eq!(ids(&events), (1..=3).map(EventId::from).collect::<Vec<_>>());
+
+        // Taking the third event acknowledged the second, and the third is
+        // returned again.
+        let stream = client.subscribe_events_with(
+            "indexer",
+            FileCursorStore::new(&path),
+            EventFilter::default(),
+            options,
+        );
+        let events: Vec<EventLog> = stream.take(3).map(Result::unwrap).collect().await;
+        assert_eq!(ids(&events), (3..=5).map(EventId::from).collect::<Vec<_>>());
+    }
+}
@@ -15,3 +15,10 @@ trait LedgerClientTrait {
 
 #[derive(Debug, Clone)]
 pub struct LedgerClient<I: Identity>(ManyClient<I>);
+
+/// Typed clients for the ledger modules, generated from their backend traits.
+pub type LedgerModuleClient<I> = many_modules::ledger::LedgerModuleClient<ManyClient<I>>;
+pub type LedgerCommandsModuleClient<I> =
+    many_modules::ledger::LedgerCommandsModuleClient<ManyClient<I>>;
+pub type LedgerTokensModuleClient<I> =
+    many_modules::ledger::LedgerTokensModuleClient<ManyClient<I>>;
@@ -0,0 +1,26 @@
+use many_client_macros::many_client;
+use many_error::ManyError;
+use many_identity::Address;
+pub use many_identity::Identity;
+pub use many_modules::names::{
+    RegisterArgs, RegisterReturns, ResolveArgs, ResolveReturns, TransferArgs, TransferReturns,
+};
+
+use crate::ManyClient;
+
+#[many_client(NamesClient, "names")]
+trait NamesClientTrait {
+    fn register(&self, args: RegisterArgs) -> Result<RegisterReturns, ManyError>;
+    fn resolve(&self, args: ResolveArgs) -> Result<ResolveReturns, ManyError>;
+    fn transfer(&self, args: TransferArgs) -> Result<TransferReturns, ManyError>;
+}
+
+#[derive(Debug, Clone)]
+pub struct NamesClient<I: Identity>(ManyClient<I>);
+
+impl<I: Identity> NamesClient<I> {
+    /// Resolve a textual address or a registered name to an address.
+    pub async fn resolve_address(&self, name_or_address: &str) -> Result<Address, ManyError> {
+        self.0.resolve_address(name_or_address).await
+    }
+}
@@ -1,3 +1,8 @@
 pub mod client;
+#[cfg(all(feature = "light-client", not(target_arch = "wasm32")))]
+pub mod light_client;
+pub mod verify;
+#[cfg(target_arch = "wasm32")]
+pub mod wasm;
 
 pub use client::ManyClient;
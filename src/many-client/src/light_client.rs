@@ -0,0 +1,262 @@
+//! Verification of query responses against the blocks of a Tendermint
+//! network, so the results of untrusted nodes can be trusted.
+//!
+//! A [`LightClient`] starts from a trusted block, e.g. one whose hash was
+//! compared between several nodes, and verifies later blocks from the
+//! signatures of their validators. Servers prove the results of some queries
+//! with a proof of the keys they read, against the hash of their state; that
+//! hash is the app hash of the next block. A [`ProofVerifier`] checks that a
+//! proof matches the result it comes with, and computes the state hash.
+use many_error::ManyError;
+use many_protocol::RequestMessage;
+use many_protocol::ResponseMessage;
+use many_types::proof::Proof;
+use many_types::{ProofOperation, PROOF};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tendermint::block::Height;
+use tendermint::node::Id;
+use tendermint::{validator, Hash, Time};
+use tendermint_light_client_verifier::options::Options;
+use tendermint_light_client_verifier::types::{LightBlock, TrustThreshold};
+use tendermint_light_client_verifier::{ProdVerifier, Verdict, Verifier};
+use tendermint_rpc::{Client, HttpClient, Paging};
+
+/// Two thirds of the default unbonding period of a Cosmos chain. Blocks
+/// older than this cannot be trusted anymore, as their validators could have
+/// left the network without being slashed.
+pub const DEFAULT_TRUSTING_PERIOD: Duration = Duration::from_secs(14 * 24 * 60 * 60);
+
+pub const DEFAULT_CLOCK_DRIFT: Duration = Duration::from_secs(10);
+
+/// How long to wait for the block with the hash of the state of a response.
+pub const DEFAULT_BLOCK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Checks the proofs of the results of the methods it supports.
+pub trait ProofVerifier: Send + Sync {
+    /// Whether the results of a method can be verified.
+    fn verifies(&self, method: &str) -> bool;
+
+    /// Verify that `proof` proves `data`, the result of a request, and return
+    /// the state hash the proof is against.
+    fn state_hash(
+        &self,
+        request: &RequestMessage,
+        data: &[u8],
+        proof: &[ProofOperation],
+    ) -> Result<Vec<u8>, ManyError>;
+}
+
+fn rpc_error(e: tendermint_rpc::Error) -> ManyError {
+    ManyError::unexpected_transport_error(e.to_string())
+}
+
+fn height(height: u64) -> Result<Height, ManyError> {
+    Height::try_from(height).map_err(|e| ManyError::unknown(e.to_string()))
+}
+
+fn now() -> Result<Time, ManyError> {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| ManyError::unknown(e.to_string()))?;
+    Time::from_unix_timestamp(since_epoch.as_secs() as i64, since_epoch.subsec_nanos())
+        .map_err(|e| ManyError::unknown(e.to_string()))
+}
+
+/// The header of a block, with its validators and the next ones. The block
+/// is not verified.
+async fn fetch(rpc: &HttpClient, provider: Id, at: u64) -> Result<LightBlock, ManyError> {
+    let signed_header = rpc
+        .commit(height(at)?)
+        .await
+        .map_err(rpc_error)?
+        .signed_header;
+    let validators = rpc
+        .validators(height(at)?, Paging::All)
+        .await
+        .map_err(rpc_error)?
+        .validators;
+    let next_validators = rpc
+        .validators(height(at + 1)?, Paging::All)
+        .await
+        .map_err(rpc_error)?
+        .validators;
+
+    Ok(LightBlock::new(
+        signed_header,
+        validator::Set::new(validators, None),
+        validator::Set::new(next_validators, None),
+        provider,
+    ))
+}
+
+pub struct LightClient {
+    rpc: HttpClient,
+    provider: Id,
+    proofs: Arc<dyn ProofVerifier>,
+    verifier: ProdVerifier,
+    options: Options,
+    block_timeout: Duration,
+
+    /// The latest verified block.
+    trusted: Mutex<LightBlock>,
+}
+
+impl LightClient {
+    /// Connect to the Tendermint RPC of a node, trusting its block at
+    /// `trusted_height` if it has the `trusted_hash`.
+    pub async fn new(
+        url: &str,
+        trusted_height: u64,
+        trusted_hash: Hash,
+        proofs: impl ProofVerifier + 'static,
+    ) -> Result<Self, ManyError> {
+        let rpc = HttpClient::new(url).map_err(rpc_error)?;
+        let provider = rpc.status().await.map_err(rpc_error)?.node_info.id;
+        let trusted = fetch(&rpc, provider, trusted_height).await?;
+        if trusted.signed_header.header.hash() != trusted_hash {
+            return Err(ManyError::unverified_response(format!(
+                "The block at height {trusted_height} does not have the trusted hash"
+            )));
+        }
+
+        Ok(Self {
+            rpc,
+            provider,
+            proofs: Arc::new(proofs),
+            verifier: ProdVerifier::default(),
+            options: Options {
+                trust_threshold: TrustThreshold::ONE_THIRD,
+                trusting_period: DEFAULT_TRUSTING_PERIOD,
+                clock_drift: DEFAULT_CLOCK_DRIFT,
+            },
+            block_timeout: DEFAULT_BLOCK_TIMEOUT,
+            trusted: Mutex::new(trusted),
+        })
+    }
+
+    pub fn with_trusting_period(mut self, trusting_period: Duration) -> Self {
+        self.options.trusting_period = trusting_period;
+        self
+    }
+
+    pub fn with_block_timeout(mut self, block_timeout: Duration) -> Self {
+        self.block_timeout = block_timeout;
+        self
+    }
+
+    pub fn verifies(&self, method: &str) -> bool {
+        self.proofs.verifies(method)
+    }
+
+    async fn latest_height(&self) -> Result<u64, ManyError> {
+        Ok(self
+            .rpc
+            .status()
+            .await
+            .map_err(rpc_error)?
+            .sync_info
+            .latest_block_height
+            .value())
+    }
+
+    /// Verify the block at a height from the latest verified block. If the
+    /// validators changed too much in between, the blocks half way are
+    /// verified first.
+    pub async fn verify_block(&self, at: u64) -> Result<LightBlock, ManyError> {
+        let mut trusted = self.trusted.lock().unwrap().clone();
+        let trusted_height = trusted.height().value();
+        if at == trusted_height {
+            return Ok(trusted);
+        } else if at < trusted_height {
+            return Err(ManyError::unverified_response(format!(
+                "Block {at} is before the trusted block {trusted_height}"
+            )));
+        }
+
+        let mut pending = vec![fetch(&self.rpc, self.provider, at).await?];
+        while let Some(untrusted) = pending.pop() {
+            match self.verifier.verify(
+                untrusted.as_untrusted_state(),
+                trusted.as_trusted_state(),
+                &self.options,
+                now()?,
+            ) {
+                Verdict::Success => trusted = untrusted,
+                Verdict::NotEnoughTrust(_) => {
+                    let low = trusted.height().value();
+                    let pivot = low + (untrusted.height().value() - low) / 2;
+                    if pivot == low {
+                        return Err(ManyError::unverified_response(format!(
+                            "The validators of block {} are not trusted",
+                            untrusted.height()
+                        )));
+                    }
+                    pending.push(untrusted);
+                    pending.push(fetch(&self.rpc, self.provider, pivot).await?);
+                }
+                Verdict::Invalid(e) => return Err(ManyError::unverified_response(e)),
+            }
+        }
+
+        let mut latest = self.trusted.lock().unwrap();
+        if trusted.height() > latest.height() {
+            *latest = trusted.clone();
+        }
+        Ok(trusted)
+    }
+
+    async fn wait_for_block(&self, at: u64) -> Result<(), ManyError> {
+        let start = Instant::now();
+        while self.latest_height().await? < at {
+            if start.elapsed() > self.block_timeout {
+                return Err(ManyError::unverified_response(format!(
+                    "Block {at} was not committed in time"
+                )));
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+        Ok(())
+    }
+
+    /// Verify that a state hash is the app hash of the latest block, or of
+    /// the next one, and return the height of the state. Queries read the
+    /// latest committed state, whose hash is in the next block; the state of
+    /// a response cannot be verified if other blocks were committed since.
+    pub async fn verify_state_hash(&self, hash: &[u8]) -> Result<u64, ManyError> {
+        let latest = self.latest_height().await?;
+        for at in [latest, latest + 1] {
+            self.wait_for_block(at).await?;
+            let block = self.verify_block(at).await?;
+            if block.signed_header.header.app_hash.as_bytes() == hash {
+                return Ok(at - 1);
+            }
+        }
+        Err(ManyError::unverified_response(
+            "The state of the response is not the one of the latest blocks",
+        ))
+    }
+
+    /// Verify the result of the response to a request, if it is not an
+    /// error.
+    pub async fn verify_response(
+        &self,
+        request: &RequestMessage,
+        response: &ResponseMessage,
+    ) -> Result<(), ManyError> {
+        let Ok(data) = &response.data else {
+            return Ok(());
+        };
+        let proof = response
+            .attributes
+            .get_attribute(PROOF.id)
+            .and_then(|attr| attr.arguments.first())
+            .ok_or_else(|| ManyError::unverified_response("The response has no proof"))?;
+        let proof: Proof = minicbor::to_vec(proof)
+            .map_err(ManyError::serialization_error)
+            .and_then(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))?;
+
+        let hash = self.proofs.state_hash(request, data, &proof.operations)?;
+        self.verify_state_hash(&hash).await.map(|_| ())
+    }
+}
@@ -0,0 +1,203 @@
+//! Offline verification of stored request envelopes, e.g. from an archive
+//! or exported blocks. No server is needed.
+use coset::{CborSerializable, CoseSign1, TaggedCborSerializable};
+use many_error::ManyError;
+use many_identity::{Address, Verifier};
+use many_protocol::decode_request_from_cose_sign1;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The state of a bulk verification, reported after every envelope.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Progress {
+    pub verified: usize,
+    pub failed: usize,
+    pub total: usize,
+}
+
+impl Progress {
+    pub fn done(&self) -> usize {
+        self.verified + self.failed
+    }
+}
+
+/// Verifies the signatures and senders of many request envelopes, spread
+/// across threads.
+#[derive(Debug)]
+pub struct BulkVerifier<V> {
+    verifier: V,
+    threads: NonZeroUsize,
+}
+
+impl<V: Verifier + Sync> BulkVerifier<V> {
+    /// Create a verifier using as many threads as there are CPUs.
+    pub fn new(verifier: V) -> Self {
+        Self {
+            verifier,
+            threads: std::thread::available_parallelism().unwrap_or(NonZeroUsize::MIN),
+        }
+    }
+
+    pub fn with_threads(mut self, threads: NonZeroUsize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Verify a single CBOR request envelope, tagged or not, and return the
+    /// address of its sender.
+    pub fn verify(&self, envelope: &[u8]) -> Result<Address, ManyError> {
+        let envelope = CoseSign1::from_tagged_slice(envelope)
+            .or_else(|_| CoseSign1::from_slice(envelope))
+            .map_err(ManyError::deserialization_error)?;
+        decode_request_from_cose_sign1(&envelope, &self.verifier)
+            .map(|message| message.from.unwrap_or_default())
+    }
+
+    /// Verify all the envelopes, calling `progress` after each of them.
+    /// The results are in the same order as the envelopes.
+    pub fn verify_all<B: AsRef<[u8]> + Sync>(
+        &self,
+        envelopes: &[B],
+        progress: impl Fn(Progress) + Sync,
+    ) -> Vec<Result<Address, ManyError>> {
+        let total = envelopes.len();
+        if total == 0 {
+            return vec![];
+        }
+
+        let verified = AtomicUsize::new(0);
+        let failed = AtomicUsize::new(0);
+        let verify_one = |envelope: &B| {
+            let r = self.verify(envelope.as_ref());
+            if r.is_ok() {
+                verified.fetch_add(1, Ordering::Relaxed);
+            } else {
+                failed.fetch_add(1, Ordering::Relaxed);
+            }
+
+            progress(Progress {
+                verified: verified.load(Ordering::Relaxed),
+                failed: failed.load(Ordering::Relaxed),
+                total,
+            });
+            r
+        };
+
+        let chunk_size = (total + self.threads.get() - 1) / self.threads.get();
+        // Without threads to spare, or support for them (e.g. in browsers),
+        // verify on the current thread.
+        if chunk_size == total {
+            return envelopes.iter().map(verify_one).collect();
+        }
+
+        let mut results: Vec<Option<Result<Address, ManyError>>> =
+            (0..total).map(|_| None).collect();
+        std::thread::scope(|scope| {
+            for (envelopes, results) in envelopes
+                .chunks(chunk_size)
+                .zip(results.chunks_mut(chunk_size))
+            {
+                let verify_one = &verify_one;
+                scope.spawn(move || {
+                    for (envelope, result) in envelopes.iter().zip(results.iter_mut()) {
+                        *result = Some(verify_one(envelope));
+                    }
+                });
+            }
+        });
+
+        // Every chunk was processed by a thread, so all results are set.
+        results.into_iter().flatten().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::Identity;
+    use many_identity_dsa::ed25519::generate_random_ed25519_identity;
+    use many_identity_dsa::CoseKeyVerifier;
+    use many_protocol::{encode_cose_sign1_from_request, RequestMessageBuilder};
+    use std::sync::Mutex;
+
+    fn envelope(identity: &impl Identity, from: Address) -> CoseSign1 {
+        let request = RequestMessageBuilder::default()
+            .from(from)
+            .method("status".to_string())
+            .data(vec![])
+            .build()
+            .unwrap();
+        encode_cose_sign1_from_request(request, identity).unwrap()
+    }
+
+    fn signed(identity: &impl Identity) -> Vec<u8> {
+        envelope(identity, identity.address())
+            .to_tagged_vec()
+            .unwrap()
+    }
+
+    #[test]
+    fn verify() {
+        let identity = generate_random_ed25519_identity();
+        let verifier = BulkVerifier::new(CoseKeyVerifier);
+        assert_eq!(verifier.verify(&signed(&identity)), Ok(identity.address()));
+
+        // Untagged envelopes too.
+        let untagged = envelope(&identity, identity.address()).to_vec().unwrap();
+        assert_eq!(verifier.verify(&untagged), Ok(identity.address()));
+    }
+
+    #[test]
+    fn verify_tampered() {
+        let identity = generate_random_ed25519_identity();
+        let mut envelope = envelope(&identity, identity.address());
+        envelope.payload.as_mut().unwrap().push(0);
+
+        let verifier = BulkVerifier::new(CoseKeyVerifier);
+        assert!(verifier.verify(&envelope.to_tagged_vec().unwrap()).is_err());
+        assert!(verifier.verify(&[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn verify_wrong_sender() {
+        let identity = generate_random_ed25519_identity();
+        let other = generate_random_ed25519_identity();
+        let envelope = envelope(&identity, other.address());
+
+        let verifier = BulkVerifier::new(CoseKeyVerifier);
+        assert!(verifier.verify(&envelope.to_tagged_vec().unwrap()).is_err());
+    }
+
+    #[test]
+    fn verify_all() {
+        let identities: Vec<_> = (0..5).map(|_| generate_random_ed25519_identity()).collect();
+        let mut envelopes: Vec<_> = identities.iter().map(signed).collect();
+        envelopes[2].pop();
+
+        let last = Mutex::new(None);
+        let results = BulkVerifier::new(CoseKeyVerifier)
+            .with_threads(NonZeroUsize::new(2).unwrap())
+            .verify_all(&envelopes, |p| {
+                let mut last = last.lock().unwrap();
+                if last.map_or(true, |l: Progress| l.done() < p.done()) {
+                    *last = Some(p);
+                }
+            });
+
+        for (i, (result, identity)) in results.iter().zip(&identities).enumerate() {
+            if i == 2 {
+                assert!(result.is_err());
+            } else {
+                assert_eq!(result, &Ok(identity.address()));
+            }
+        }
+        assert_eq!(
+            last.into_inner().unwrap(),
+            Some(Progress {
+                verified: 4,
+                failed: 1,
+                total: 5,
+            })
+        );
+    }
+}
@@ -0,0 +1,93 @@
+//! Bindings for browsers, generated with `wasm-bindgen`, so web wallets use
+//! the same message encoding and signing as the servers.
+//!
+//! Build with `wasm-pack build --target web src/many-client`. Arguments and
+//! results are CBOR bytes (`Uint8Array`), and errors are thrown as `Error`s
+//! with the description of the MANY error.
+use crate::client::send_envelope;
+use crate::ManyClient;
+use coset::{CoseSign1, TaggedCborSerializable};
+use js_sys::{Promise, Uint8Array};
+use many_error::ManyError;
+use many_identity::{Address, AnonymousIdentity, Identity};
+use many_identity_dsa::CoseKeyIdentity;
+use std::str::FromStr;
+use std::sync::Arc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+fn js_error(e: impl ToString) -> JsError {
+    JsError::new(&e.to_string())
+}
+
+/// A client to a MANY server, signing its requests with an Ed25519 or ECDSA
+/// key in PEM, or anonymous without a key.
+#[wasm_bindgen(js_name = ManyClient)]
+#[derive(Clone)]
+pub struct WasmClient {
+    inner: ManyClient<Arc<dyn Identity>>,
+}
+
+#[wasm_bindgen(js_class = ManyClient)]
+impl WasmClient {
+    #[wasm_bindgen(constructor)]
+    pub fn new(url: &str, to: &str, pem: Option<String>) -> Result<WasmClient, JsError> {
+        let identity: Arc<dyn Identity> = match pem {
+            Some(pem) => Arc::new(CoseKeyIdentity::from_pem(pem).map_err(js_error)?),
+            None => Arc::new(AnonymousIdentity),
+        };
+        let to = Address::from_str(to).map_err(js_error)?;
+        let inner = ManyClient::new(url, to, identity).map_err(js_error)?;
+        Ok(Self { inner })
+    }
+
+    /// The address of the identity signing the requests.
+    #[wasm_bindgen(getter)]
+    pub fn address(&self) -> String {
+        self.inner.address().to_string()
+    }
+
+    /// Sign a request to `method` and return its tagged envelope, e.g. to
+    /// send it later with `sendEnvelope`.
+    pub fn sign(&self, method: String, argument: &[u8]) -> Result<Vec<u8>, JsError> {
+        self.inner
+            .sign(method, argument)
+            .and_then(|envelope| {
+                envelope
+                    .to_tagged_vec()
+                    .map_err(ManyError::serialization_error)
+            })
+            .map_err(js_error)
+    }
+
+    /// Call `method` with a CBOR argument. The promise resolves to the CBOR
+    /// data of the response, once it is verified.
+    pub fn call(&self, method: String, argument: Vec<u8>) -> Promise {
+        let client = self.inner.clone();
+        future_to_promise(async move {
+            let response = client
+                .call_raw(method, &argument)
+                .await
+                .and_then(|response| response.data)
+                .map_err(js_error)?;
+            Ok(Uint8Array::from(response.as_slice()).into())
+        })
+    }
+
+    /// Send a tagged request envelope, e.g. from `sign`. The promise resolves
+    /// to the CBOR data of the response, once it is verified.
+    #[wasm_bindgen(js_name = sendEnvelope)]
+    pub fn send_envelope(&self, envelope: Vec<u8>) -> Promise {
+        let client = self.inner.clone();
+        future_to_promise(async move {
+            let envelope = CoseSign1::from_tagged_slice(&envelope)
+                .map_err(|e| js_error(ManyError::deserialization_error(e)))?;
+            let response = send_envelope(client.url().clone(), envelope)
+                .await
+                .and_then(|envelope| client.decode_response(&envelope))
+                .and_then(|response| response.data)
+                .map_err(js_error)?;
+            Ok(Uint8Array::from(response.as_slice()).into())
+        })
+    }
+}
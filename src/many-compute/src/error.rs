@@ -5,6 +5,19 @@ define_attribute_many_error!(
         1: pub fn missing_port() => "RPC node URL is missing port number.",
         2: pub fn invalid_initial_hash(expected, actual)
             => "Invalid initial hash. Expected '{expected}', was '{actual}'.",
+        3: pub fn deployment_not_found(dseq, owner) => "Deployment {dseq} not found for {owner}.",
+        4: pub fn deployment_closed(dseq) => "Deployment {dseq} is closed.",
+        5: pub fn empty_command() => "The command to execute is empty.",
+        6: pub fn template_not_found(name) => "Template {name} not found.",
+        7: pub fn missing_template_parameter(name)
+            => "Missing a value for the parameter {name} of the template.",
+        8: pub fn invalid_template(desc) => "Invalid template: {desc}.",
+        9: pub fn invalid_env_var(name) => "Invalid environment variable name '{name}'.",
+        10: pub fn escrow_disabled() => "Deployments are not funded by an escrow on this server.",
+        11: pub fn insufficient_escrow_funds(needed, balance)
+            => "Insufficient escrow funds. Needed {needed}, the balance is {balance}.",
+        12: pub fn escrow_on_blockchain()
+            => "Deployments cannot be funded by an escrow on a blockchain.",
     }
 );
 
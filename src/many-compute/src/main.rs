@@ -2,28 +2,34 @@
 
 use crate::allow_addrs::AllowAddrsModule;
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::Address;
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity_dsa::CoseKeyVerifier;
 use many_identity_webauthn::WebAuthnVerifier;
-use many_modules::compute;
+use many_modules::{compute, r#async};
 use many_protocol::ManyUrl;
+use many_server::deferred::{DeferredModule, DeferredResponses};
 use many_server::transport::http::HttpServer;
 use many_server::ManyServer;
 use std::collections::BTreeSet;
 use std::net::SocketAddr;
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 mod error;
 mod module;
 mod opt;
 mod storage;
 
-use crate::opt::AkashOpt;
+use crate::opt::{AkashOpt, EscrowOpt};
+use module::escrow::Escrow;
 use module::*;
 
+/// The methods waiting on Akash transactions, whose responses are deferred.
+const DEFERRED_METHODS: [&str; 3] = ["compute.deploy", "compute.deployTemplate", "compute.close"];
+
 #[derive(Debug, Parser)]
 struct Opts {
     #[clap(flatten)]
@@ -66,8 +72,17 @@ struct Opts {
     #[clap(long)]
     allow_addrs: Option<PathBuf>,
 
+    /// A directory of SDL templates for `compute.deployTemplate`, as `.yml`
+    /// files named after their template. They are added to the templates
+    /// bundled with the server.
+    #[clap(long)]
+    templates: Option<PathBuf>,
+
     #[clap(flatten)]
     akash_opt: AkashOpt,
+
+    #[clap(flatten)]
+    escrow_opt: EscrowOpt,
 }
 
 fn main() {
@@ -80,7 +95,9 @@ fn main() {
         clean,
         allow_origin,
         allow_addrs,
+        templates,
         akash_opt,
+        escrow_opt,
         ..
     } = Opts::parse();
 
@@ -105,7 +122,7 @@ fn main() {
         state = None;
     }
 
-    let key = CoseKeyIdentity::from_pem(std::fs::read_to_string(pem).unwrap()).unwrap();
+    let key = read_pem(pem).unwrap();
 
     let state = state.map(|state| {
         let content = std::fs::read_to_string(state).unwrap();
@@ -130,8 +147,37 @@ fn main() {
         panic!("Persistent store or staging file not found.")
     };
 
+    let module = match templates {
+        Some(dir) => module.with_templates(
+            module::template::Templates::default()
+                .load_dir(&dir)
+                .expect("Could not load the templates"),
+        ),
+        None => module,
+    };
+    let escrow =
+        Escrow::new(escrow_opt, key.clone()).expect("Could not connect to the escrow ledger");
+    let module = match escrow.clone() {
+        Some(escrow) => module
+            .with_escrow(escrow)
+            .expect("Could not fund deployments from the escrow"),
+        None => module,
+    };
     let module = Arc::new(Mutex::new(module));
 
+    // Credit new escrow funds, send refunds and top up deployments in the
+    // background.
+    if let Some(escrow) = escrow {
+        let module = module.clone();
+        std::thread::spawn(move || loop {
+            if let Err(e) = escrow.sync(&module) {
+                warn!("Could not sync the escrow: {e}");
+            }
+            std::thread::sleep(escrow.interval());
+        });
+    }
+    let responses = DeferredResponses::new(key.clone());
+
     let many = ManyServer::simple(
         "many-compute",
         key,
@@ -149,13 +195,22 @@ fn main() {
         if let Some(path) = allow_addrs {
             let allow_addrs: BTreeSet<Address> =
                 json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
-            s.add_module(AllowAddrsModule {
-                inner: compute_module,
-                allow_addrs,
-            });
+            s.add_module(DeferredModule::new(
+                AllowAddrsModule {
+                    inner: compute_module,
+                    allow_addrs,
+                },
+                DEFERRED_METHODS,
+                responses.clone(),
+            ));
         } else {
-            s.add_module(compute_module);
+            s.add_module(DeferredModule::new(
+                compute_module,
+                DEFERRED_METHODS,
+                responses.clone(),
+            ));
         }
+        s.add_module(r#async::AsyncModule::new(Arc::new(Mutex::new(responses))));
     }
     let mut many_server = HttpServer::new(many);
 
@@ -4,12 +4,13 @@ use crate::storage::ComputeStorage;
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::abci_backend::{
-    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, InitChainReturn,
-    ManyAbciModuleBackend,
+    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, GasCost,
+    InitChainReturn, ManyAbciModuleBackend,
 };
 use many_modules::compute::{
-    CloseArgs, CloseReturns, ComputeModuleBackend, DeployArgs, DeployReturns, InfoArg, InfoReturns,
-    ListArgs, ListReturns,
+    CloseArgs, CloseReturns, ComputeModuleBackend, DeployArgs, DeployReturns, DeployTemplateArgs,
+    DeployTemplateReturns, EscrowArgs, EscrowReturns, EstimateArgs, EstimateReturns, ExecArgs,
+    ExecReturns, InfoArg, InfoReturns, ListArgs, ListReturns, LogsArgs, LogsReturns,
 };
 use many_types::compute::{
     Bids, ComputeListFilter, ComputeStatus, DeploymentInfo, DeploymentMeta, LeaseStatus,
@@ -26,10 +27,30 @@ use std::time::Duration;
 use tracing::{debug, info};
 
 pub mod allow_addrs;
+pub mod escrow;
+pub mod template;
+
+use escrow::Escrow;
+use template::{Templates, DEFAULT_TEMPLATE};
 
 const AKASH_BIN: &str = "provider-services";
 const DEPLOYMENT_TIMEOUT: u16 = 60 * 2; // 2 minutes
 
+// The maximum price per block of a deployment, in the SDL pricing.
+const PRICING_DENOM: &str = "uakt";
+const PRICING_AMOUNT: f64 = 10000.0;
+
+// Averages used by Akash to convert a price per block to a monthly cost.
+const AKASH_BLOCK_TIME_SECS: f64 = 6.098;
+const AKASH_DAYS_PER_MONTH: f64 = 30.437;
+
+// The name of the service of the deployments, in the SDL.
+const SERVICE_NAME: &str = "app";
+
+// The number of log lines returned by `compute.logs`, by default and at most.
+const DEFAULT_LOG_LINES: u64 = 100;
+const MAX_LOG_LINES: u64 = 1000;
+
 // The initial state schema, loaded from JSON.
 #[derive(serde::Deserialize, Debug, Default)]
 pub struct InitialStateJson {
@@ -41,6 +62,8 @@ pub struct InitialStateJson {
 pub struct ComputeModuleImpl {
     akash_opt: AkashOpt,
     storage: ComputeStorage,
+    templates: Templates,
+    escrow: Option<Escrow>,
 }
 
 impl ComputeModuleImpl {
@@ -52,7 +75,12 @@ impl ComputeModuleImpl {
         let storage =
             ComputeStorage::load(persistent_store_path, blockchain).map_err(ManyError::unknown)?;
 
-        Ok(Self { akash_opt, storage })
+        Ok(Self {
+            akash_opt,
+            storage,
+            templates: Templates::default(),
+            escrow: None,
+        })
     }
 
     pub fn new<P: AsRef<Path>>(
@@ -78,7 +106,29 @@ impl ComputeModuleImpl {
             hash = hex::encode(storage.hash()).as_str()
         );
 
-        Ok(Self { akash_opt, storage })
+        Ok(Self {
+            akash_opt,
+            storage,
+            templates: Templates::default(),
+            escrow: None,
+        })
+    }
+
+    /// Replace the templates of `compute.deployTemplate`.
+    pub fn with_templates(mut self, templates: Templates) -> Self {
+        self.templates = templates;
+        self
+    }
+
+    /// Fund the deployments from an escrow on a MANY ledger. The escrow
+    /// follows the ledger and the clock of this server, so nodes of a
+    /// blockchain would not agree on it.
+    pub fn with_escrow(mut self, escrow: Escrow) -> Result<Self, ManyError> {
+        if self.storage.is_blockchain() {
+            return Err(error::escrow_on_blockchain());
+        }
+        self.escrow = Some(escrow);
+        Ok(self)
     }
 
     fn execute_akash_command(&self, args: &[&str]) -> Result<Output, ManyError> {
@@ -145,63 +195,29 @@ impl ComputeModuleImpl {
         Ok(())
     }
 
+    fn render_sdl(
+        &self,
+        template: &str,
+        deployment: &DeployArgs,
+        env: &BTreeMap<String, String>,
+        parameters: &BTreeMap<String, String>,
+    ) -> Result<String, ManyError> {
+        self.templates.render(
+            template,
+            deployment,
+            env,
+            parameters,
+            (PRICING_DENOM, PRICING_AMOUNT),
+        )
+    }
+
+    /// Create a deployment, with an initial deposit in `uakt` if given. Akash
+    /// uses its default deposit otherwise.
     fn create_deployment(
         &mut self,
-        args: &DeployArgs,
+        sdl: String,
+        deposit: Option<u64>,
     ) -> Result<(u64, u64, u64, String), ManyError> {
-        let DeployArgs {
-            image,
-            port,
-            num_cpu,
-            num_memory,
-            memory_type,
-            num_storage,
-            storage_type,
-            region,
-        } = args;
-
-        let sdl = format!(
-            r#"---
-version: "2.0"
-
-services:
-  app:
-    image: {}
-    expose:
-      - port: {}
-        to:
-          - global: true
-profiles:
-  compute:
-    app:
-      resources:
-        cpu:
-          units: {}
-        memory:
-          size: {}{}
-        storage:
-          size: {}{}
-  placement:
-    region:
-      attributes:
-        host: akash
-        region: {}
-      signedBy:
-        anyOf:
-          - "akash1365yvmc4s7awdyj3n2sav7xfx76adc6dnmlx63"
-          - "akash18qa2a2ltfyvkyj0ggj3hkvuj6twzyumuaru9s4"
-      pricing:
-        app:
-          denom: uakt
-          amount: 10000
-deployment:
-  app:
-    region:
-      profile: app
-      count: 1"#,
-            image, port, num_cpu, num_memory, memory_type, num_storage, storage_type, region
-        );
-
         debug!("{sdl}");
 
         let mut tmpfile = tempfile::Builder::new()
@@ -216,7 +232,8 @@ deployment:
             .ok_or(ManyError::unknown("Unable to get SDL file path"))?;
 
         info!("Creating deployment");
-        let deploy_args = [
+        let deposit = deposit.map(|d| format!("{d}{PRICING_DENOM}"));
+        let mut deploy_args = vec![
             "tx",
             "deployment",
             "create",
@@ -239,6 +256,9 @@ deployment:
             self.akash_opt.akash_keyring_backend.as_str(),
             "--yes",
         ];
+        if let Some(deposit) = &deposit {
+            deploy_args.extend(["--deposit", deposit.as_str()]);
+        }
         let output = self.execute_akash_command(&deploy_args)?;
 
         if !output.status.success() {
@@ -336,6 +356,39 @@ deployment:
         Ok((cheapest_provider, cheapest_price))
     }
 
+    /// The prices per block of the bids currently open on the market.
+    fn open_bid_prices(&self) -> Result<Vec<f64>, ManyError> {
+        info!("Querying open bids");
+        let bid_list_args = [
+            "query",
+            "market",
+            "bid",
+            "list",
+            "--chain-id",
+            self.akash_opt.akash_chain_id.as_str(),
+            "--node",
+            self.akash_opt.akash_rpc.as_str(),
+            "--state",
+            "open",
+        ];
+        let output = self.execute_akash_command(&bid_list_args)?;
+
+        if !output.status.success() {
+            let err = std::str::from_utf8(&output.stderr).map_err(ManyError::unknown)?;
+            return Err(ManyError::unknown(format!(
+                "akash query market bid list failed: {err}"
+            )));
+        }
+
+        let response: Bids = serde_yaml::from_slice(&output.stdout).map_err(ManyError::unknown)?;
+        Ok(response
+            .bids
+            .into_iter()
+            .filter(|bid| bid.bid.price.denom == PRICING_DENOM)
+            .map(|bid| bid.bid.price.amount)
+            .collect())
+    }
+
     fn create_lease(
         &mut self,
         dseq: u64,
@@ -538,6 +591,47 @@ deployment:
         Ok(())
     }
 
+    /// Add `uakt` to the Akash escrow of a deployment, from the wallet.
+    fn deposit_deployment(&self, dseq: u64, uakt: u64) -> Result<(), ManyError> {
+        info!("Depositing to deployment");
+        let amount = format!("{uakt}{PRICING_DENOM}");
+        let deployment_deposit_args = [
+            "tx",
+            "deployment",
+            "deposit",
+            amount.as_str(),
+            "--dseq",
+            &dseq.to_string(),
+            "--chain-id",
+            self.akash_opt.akash_chain_id.as_str(),
+            "--node",
+            self.akash_opt.akash_rpc.as_str(),
+            "--gas",
+            self.akash_opt.akash_gas.as_str(),
+            "--gas-prices",
+            self.akash_opt.akash_gas_price.as_str(),
+            "--gas-adjustment",
+            &self.akash_opt.akash_gas_adjustment.to_string(),
+            "--sign-mode",
+            self.akash_opt.akash_sign_mode.as_str(),
+            "--from",
+            self.akash_opt.akash_wallet.as_str(),
+            "--keyring-backend",
+            self.akash_opt.akash_keyring_backend.as_str(),
+            "--yes",
+        ];
+        let output = self.execute_akash_command(&deployment_deposit_args)?;
+
+        if !output.status.success() {
+            let err = std::str::from_utf8(&output.stderr).map_err(ManyError::unknown)?;
+            return Err(ManyError::unknown(format!(
+                "akash tx deployment deposit failed: {err}"
+            )));
+        }
+
+        Ok(())
+    }
+
     fn send_manifest(
         &mut self,
         dseq: u64,
@@ -591,6 +685,217 @@ deployment:
         Ok(())
     }
 
+    /// The provider of a deployment of `owner`, which needs to be deployed.
+    fn deployment_provider(&self, owner: &Address, dseq: u64) -> Result<String, ManyError> {
+        match self.storage.get_deployment(owner, dseq)? {
+            Some(DeploymentMeta {
+                status: ComputeStatus::Deployed,
+                meta: Some(info),
+                ..
+            }) => Ok(info.provider),
+            Some(_) => Err(error::deployment_closed(dseq)),
+            None => Err(error::deployment_not_found(dseq, owner)),
+        }
+    }
+
+    /// The last `tail` lines of the logs of a service of a deployment.
+    fn lease_logs(
+        &self,
+        dseq: u64,
+        provider: &str,
+        service: &str,
+        tail: u64,
+    ) -> Result<String, ManyError> {
+        info!("Fetching lease logs");
+        let lease_logs_args = [
+            "lease-logs",
+            "--node",
+            self.akash_opt.akash_rpc.as_str(),
+            "--from",
+            self.akash_opt.akash_wallet.as_str(),
+            "--dseq",
+            &dseq.to_string(),
+            "--provider",
+            provider,
+            "--keyring-backend",
+            self.akash_opt.akash_keyring_backend.as_str(),
+            "--service",
+            service,
+            "--tail",
+            &tail.to_string(),
+        ];
+        let output = self.execute_akash_command(&lease_logs_args)?;
+
+        if !output.status.success() {
+            let err = std::str::from_utf8(&output.stderr).map_err(ManyError::unknown)?;
+            return Err(ManyError::unknown(format!(
+                "akash lease-logs failed: {err}"
+            )));
+        }
+        String::from_utf8(output.stdout).map_err(ManyError::unknown)
+    }
+
+    /// Keep `count` lines of the logs, before the `skip` most recent ones.
+    fn page_logs(logs: &str, count: u64, skip: u64) -> LogsReturns {
+        let lines: Vec<&str> = logs.lines().collect();
+        let end = lines.len().saturating_sub(skip as usize);
+        let start = end.saturating_sub(count as usize);
+        LogsReturns {
+            lines: lines[start..end].iter().map(|l| l.to_string()).collect(),
+            more: start > 0,
+        }
+    }
+
+    /// Run a command in a service of a deployment, through the lease shell
+    /// of its provider.
+    fn lease_shell(
+        &self,
+        dseq: u64,
+        provider: &str,
+        service: &str,
+        command: &[String],
+    ) -> Result<Output, ManyError> {
+        info!("Running a command in the lease shell");
+        let lease_shell_args = [
+            "lease-shell",
+            "--node",
+            self.akash_opt.akash_rpc.as_str(),
+            "--from",
+            self.akash_opt.akash_wallet.as_str(),
+            "--dseq",
+            &dseq.to_string(),
+            "--provider",
+            provider,
+            "--keyring-backend",
+            self.akash_opt.akash_keyring_backend.as_str(),
+            // The command is not parsed for flags.
+            "--",
+            service,
+        ];
+        Command::new(AKASH_BIN)
+            .args(lease_shell_args)
+            .args(command)
+            .output()
+            .map_err(|_| ManyError::unknown("Failed to execute command"))
+    }
+
+    /// Estimate the price of a deployment from the prices of the bids open on the
+    /// market. Akash providers only bid on existing deployments, so bids on
+    /// other deployments are used. Prices are capped to the SDL pricing, as
+    /// providers cannot bid over it.
+    fn estimate_from_bids(mut prices: Vec<f64>) -> Result<EstimateReturns, ManyError> {
+        prices.retain(|p| p.is_finite() && *p >= 0.0);
+        for p in prices.iter_mut() {
+            *p = p.min(PRICING_AMOUNT);
+        }
+        prices.sort_by(|a, b| a.partial_cmp(b).unwrap_or(Ordering::Equal));
+
+        let (Some(min_price), Some(max_price)) = (prices.first(), prices.last()) else {
+            return Err(ManyError::unknown("No open bids to estimate a price from"));
+        };
+        let price = prices[prices.len() / 2];
+        let blocks_per_month = AKASH_DAYS_PER_MONTH * 24.0 * 60.0 * 60.0 / AKASH_BLOCK_TIME_SECS;
+
+        Ok(EstimateReturns {
+            denom: PRICING_DENOM.to_string(),
+            price,
+            min_price: *min_price,
+            max_price: *max_price,
+            monthly_cost: price * blocks_per_month,
+            bids: prices.len() as u64,
+        })
+    }
+
+    /// Deploy an SDL, rendered from the arguments of the deployment. With an
+    /// escrow, the initial deposit is charged to the sender first, and given
+    /// back if the deployment fails.
+    fn deploy_sdl(
+        &mut self,
+        sender: &Address,
+        args: DeployArgs,
+        sdl: String,
+    ) -> Result<DeployReturns, ManyError> {
+        let deposit = self.charge_deposit(sender)?;
+        let result = self.deploy_funded_sdl(sender, args, sdl, deposit.as_ref().map(|d| d.0));
+        match (result, deposit) {
+            (Ok(DeployReturns(meta)), Some(deposit)) => {
+                let price = meta.meta.as_ref().map_or(0.0, |info| info.price);
+                self.add_deployment_escrow(sender, meta.dseq, price, deposit)?;
+                Ok(DeployReturns(meta))
+            }
+            (Err(e), Some((_, charged))) => {
+                self.storage.credit_escrow(sender, charged)?;
+                Err(e)
+            }
+            (result, None) => result,
+        }
+    }
+
+    fn deploy_funded_sdl(
+        &mut self,
+        sender: &Address,
+        args: DeployArgs,
+        sdl: String,
+        deposit: Option<u64>,
+    ) -> Result<DeployReturns, ManyError> {
+        // At this point, the sender should already be validated by the WhitelistValidator
+        self.generate_cert()?;
+        let (dseq, gseq, oseq, sdl) = self.create_deployment(sdl, deposit)?;
+        let (provider, price) = self.create_bid(dseq, gseq, oseq)?;
+
+        let DeployArgs { image, port, .. } = args;
+
+        self.create_lease(dseq, gseq, oseq, &provider)?;
+        self.check_lease_status(dseq, gseq, oseq)?;
+        self.send_manifest(dseq, gseq, oseq, &provider, &sdl)?;
+        let lease_status = self.check_manifest_status(dseq, gseq, oseq, &provider)?;
+
+        let uris = lease_status
+            .services
+            .get("app")
+            .and_then(|service_status| service_status.as_ref())
+            .and_then(|boxed_status| boxed_status.uris.as_deref());
+
+        let forwarded_ports = lease_status.forwarded_ports.get("app");
+
+        let meta = match (
+            uris.and_then(|u| u.get(0)),
+            forwarded_ports.and_then(|fp| fp.get(0)),
+        ) {
+            (Some(uri), _) => self.create_deployment_meta(
+                Some(uri.clone()),
+                port,
+                port,
+                ServiceProtocol::TCP,
+                dseq,
+                provider,
+                price,
+                image,
+            ),
+            (_, Some(forwarded_port)) => self.create_deployment_meta(
+                forwarded_port.host.clone(),
+                port,
+                forwarded_port.external_port,
+                forwarded_port.proto,
+                dseq,
+                provider,
+                price,
+                image,
+            ),
+            _ => {
+                return Err(ManyError::unknown(format!(
+                    "No URIs or forwarded ports found for deployment {}",
+                    dseq
+                )))
+            }
+        };
+
+        // Write info to compute storage
+        self.storage.add_deployment(sender, &meta)?;
+
+        Ok(DeployReturns(meta))
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn create_deployment_meta(
         &self,
@@ -630,13 +935,23 @@ impl ManyAbciModuleBackend for ComputeModuleImpl {
             endpoints: BTreeMap::from([
                 ("compute.info".to_string(), EndpointInfo { is_command: false }),
                 ("compute.deploy".to_string(), EndpointInfo { is_command: true }),
+                ("compute.deployTemplate".to_string(), EndpointInfo { is_command: true }),
                 ("compute.close".to_string(), EndpointInfo { is_command: true }),
                 ("compute.list".to_string(), EndpointInfo { is_command: false }),
+                ("compute.estimate".to_string(), EndpointInfo { is_command: false }),
+                ("compute.logs".to_string(), EndpointInfo { is_command: false }),
+                ("compute.exec".to_string(), EndpointInfo { is_command: false }),
+                ("compute.escrow".to_string(), EndpointInfo { is_command: false }),
                 //
                 // Events
                 ("events.info".to_string(), EndpointInfo { is_command: false }),
                 ("events.list".to_string(), EndpointInfo { is_command: false }),
             ]),
+            gas: Some(BTreeMap::from([
+                ("compute.deploy".to_string(), GasCost::new(10_000, 1)),
+                ("compute.deployTemplate".to_string(), GasCost::new(10_000, 1)),
+            ])),
+            block_gas_limit: None,
         })
     }
 
@@ -696,62 +1011,23 @@ impl ComputeModuleBackend for ComputeModuleImpl {
     }
 
     fn deploy(&mut self, sender: &Address, args: DeployArgs) -> Result<DeployReturns, ManyError> {
-        // At this point, the sender should already be validated by the WhitelistValidator
-        self.generate_cert()?;
-        let (dseq, gseq, oseq, sdl) = self.create_deployment(&args)?;
-        let (provider, price) = self.create_bid(dseq, gseq, oseq)?;
-
-        let DeployArgs { image, port, .. } = args;
-
-        self.create_lease(dseq, gseq, oseq, &provider)?;
-        self.check_lease_status(dseq, gseq, oseq)?;
-        self.send_manifest(dseq, gseq, oseq, &provider, &sdl)?;
-        let lease_status = self.check_manifest_status(dseq, gseq, oseq, &provider)?;
-
-        let uris = lease_status
-            .services
-            .get("app")
-            .and_then(|service_status| service_status.as_ref())
-            .and_then(|boxed_status| boxed_status.uris.as_deref());
-
-        let forwarded_ports = lease_status.forwarded_ports.get("app");
-
-        let meta = match (
-            uris.and_then(|u| u.get(0)),
-            forwarded_ports.and_then(|fp| fp.get(0)),
-        ) {
-            (Some(uri), _) => self.create_deployment_meta(
-                Some(uri.clone()),
-                port,
-                port,
-                ServiceProtocol::TCP,
-                dseq,
-                provider,
-                price,
-                image,
-            ),
-            (_, Some(forwarded_port)) => self.create_deployment_meta(
-                forwarded_port.host.clone(),
-                port,
-                forwarded_port.external_port,
-                forwarded_port.proto,
-                dseq,
-                provider,
-                price,
-                image,
-            ),
-            _ => {
-                return Err(ManyError::unknown(format!(
-                    "No URIs or forwarded ports found for deployment {}",
-                    dseq
-                )))
-            }
-        };
-
-        // Write info to compute storage
-        self.storage.add_deployment(sender, &meta)?;
+        let sdl = self.render_sdl(DEFAULT_TEMPLATE, &args, &BTreeMap::new(), &BTreeMap::new())?;
+        self.deploy_sdl(sender, args, sdl)
+    }
 
-        Ok(DeployReturns(meta))
+    fn deploy_template(
+        &mut self,
+        sender: &Address,
+        args: DeployTemplateArgs,
+    ) -> Result<DeployTemplateReturns, ManyError> {
+        let DeployTemplateArgs {
+            template,
+            deployment,
+            env,
+            parameters,
+        } = args;
+        let sdl = self.render_sdl(&template, &deployment, &env, &parameters)?;
+        self.deploy_sdl(sender, deployment, sdl)
     }
 
     fn close(&mut self, sender: &Address, args: CloseArgs) -> Result<CloseReturns, ManyError> {
@@ -764,8 +1040,9 @@ impl ComputeModuleBackend for ComputeModuleImpl {
 
         self.close_deployment(&args)?;
         self.storage.remove_deployment(sender, args.dseq)?;
+        self.refund_deployment_escrow(sender, args.dseq)?;
 
-        Ok(CloseReturns {})
+        Ok(CloseReturns::default())
     }
 
     fn list(&self, _sender: &Address, args: ListArgs) -> Result<ListReturns, ManyError> {
@@ -780,4 +1057,51 @@ impl ComputeModuleBackend for ComputeModuleImpl {
             },
         })
     }
+
+    fn estimate(
+        &self,
+        _sender: &Address,
+        _args: EstimateArgs,
+    ) -> Result<EstimateReturns, ManyError> {
+        // Open bids are not filtered by resources, so the spec isn't used yet.
+        Self::estimate_from_bids(self.open_bid_prices()?)
+    }
+
+    fn logs(&self, sender: &Address, args: LogsArgs) -> Result<LogsReturns, ManyError> {
+        let provider = self.deployment_provider(sender, args.dseq)?;
+        let count = args.count.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES);
+        let skip = args.skip.unwrap_or(0);
+
+        // One more line than needed, to know if there are older ones.
+        let logs = self.lease_logs(
+            args.dseq,
+            &provider,
+            args.service.as_deref().unwrap_or(SERVICE_NAME),
+            skip.saturating_add(count).saturating_add(1),
+        )?;
+        Ok(Self::page_logs(&logs, count, skip))
+    }
+
+    fn exec(&self, sender: &Address, args: ExecArgs) -> Result<ExecReturns, ManyError> {
+        if args.command.is_empty() {
+            return Err(error::empty_command());
+        }
+        let provider = self.deployment_provider(sender, args.dseq)?;
+
+        let output = self.lease_shell(
+            args.dseq,
+            &provider,
+            args.service.as_deref().unwrap_or(SERVICE_NAME),
+            &args.command,
+        )?;
+        Ok(ExecReturns {
+            exit_code: output.status.code(),
+            stdout: output.stdout.into(),
+            stderr: output.stderr.into(),
+        })
+    }
+
+    fn escrow(&self, sender: &Address, args: EscrowArgs) -> Result<EscrowReturns, ManyError> {
+        self.escrow_of(&args.owner.unwrap_or(*sender))
+    }
 }
@@ -0,0 +1,304 @@
+use crate::error;
+use crate::module::{ComputeModuleImpl, AKASH_BLOCK_TIME_SECS, PRICING_DENOM};
+use crate::opt::EscrowOpt;
+use many_client::client::blocking::ManyClient;
+use many_error::ManyError;
+use many_identity::{Address, Identity};
+use many_identity_dsa::CoseKeyIdentity;
+use many_modules::compute::{DeploymentEscrow, EscrowReturns};
+use many_modules::events::{EventFilter, EventId, EventInfo, EventKind, ListArgs, ListReturns};
+use many_modules::ledger::SendArgs;
+use many_types::ledger::TokenAmount;
+use many_types::{CborRange, Memo, SortOrder, Timestamp};
+use std::ops::Bound;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::{info, warn};
+
+// The number of events fetched per call when looking for new funds.
+const EVENTS_PAGE_SIZE: u64 = 100;
+
+// How long to wait for a refund to be executed by the ledger.
+const REFUND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The escrow account of the server on a MANY ledger, which owners fund
+/// deployments from. Its balance is bridged to the Akash wallet: the
+/// deposits of escrow deployments are paid from the wallet, at the rate of
+/// the escrow, and the tokens stay in the escrow account.
+#[derive(Clone, Debug)]
+pub struct Escrow {
+    opt: EscrowOpt,
+    account: Address,
+    symbol: Address,
+    ledger: ManyClient<CoseKeyIdentity>,
+}
+
+impl Escrow {
+    /// The escrow of the options given, if any. The identity of the server
+    /// owns the escrow account.
+    pub fn new(opt: EscrowOpt, identity: CoseKeyIdentity) -> Result<Option<Self>, String> {
+        let (Some(url), Some(symbol)) = (opt.escrow_ledger.clone(), opt.escrow_symbol) else {
+            return Ok(None);
+        };
+        if opt.escrow_rate_uakt == 0 || opt.escrow_rate_tokens == 0 {
+            return Err(format!(
+                "Invalid escrow rate {}uakt for {} tokens",
+                opt.escrow_rate_uakt, opt.escrow_rate_tokens
+            ));
+        }
+        let account = identity.address();
+        let ledger = ManyClient::new(url, Address::anonymous(), identity)?;
+        Ok(Some(Self {
+            opt,
+            account,
+            symbol,
+            ledger,
+        }))
+    }
+
+    /// The number of seconds between checks for new funds and top-ups.
+    pub fn interval(&self) -> Duration {
+        Duration::from_secs(self.opt.escrow_interval)
+    }
+
+    /// The tokens paying for a deposit, rounded up.
+    fn tokens_for(&self, uakt: u64) -> TokenAmount {
+        let rate = self.opt.escrow_rate_uakt as u128;
+        let tokens = uakt as u128 * self.opt.escrow_rate_tokens as u128;
+        ((tokens + rate - 1) / rate).into()
+    }
+
+    /// The tokens a refund of `uakt` is worth, rounded down.
+    fn refund_for(&self, uakt: u64) -> TokenAmount {
+        let tokens = uakt as u128 * self.opt.escrow_rate_tokens as u128;
+        (tokens / self.opt.escrow_rate_uakt as u128).into()
+    }
+
+    /// The transfers of tokens to the escrow account after the event
+    /// `after`, with the last event seen.
+    fn transfers(
+        &self,
+        after: Option<EventId>,
+    ) -> Result<(Vec<(Address, TokenAmount)>, Option<EventId>), ManyError> {
+        let mut transfers = Vec::new();
+        let mut last = after;
+        loop {
+            let filter = EventFilter {
+                account: Some(vec![self.account].into()),
+                kind: Some(vec![EventKind::Send].into()),
+                id_range: Some(CborRange {
+                    start: last.clone().map_or(Bound::Unbounded, Bound::Excluded),
+                    end: Bound::Unbounded,
+                }),
+                ..Default::default()
+            };
+            let data = self.ledger.call_(
+                "events.list",
+                ListArgs {
+                    count: Some(EVENTS_PAGE_SIZE),
+                    order: Some(SortOrder::Ascending),
+                    filter: Some(filter),
+                },
+            )?;
+            let ListReturns { events, .. } =
+                minicbor::decode(&data).map_err(ManyError::deserialization_error)?;
+            let done = (events.len() as u64) < EVENTS_PAGE_SIZE;
+
+            for event in events {
+                if let EventInfo::Send {
+                    from,
+                    to,
+                    symbol,
+                    amount,
+                    ..
+                } = event.content
+                {
+                    if to == self.account && from != self.account && symbol == self.symbol {
+                        transfers.push((from, amount));
+                    }
+                }
+                last = Some(event.id);
+            }
+            if done {
+                return Ok((transfers, last));
+            }
+        }
+    }
+
+    /// Credit new funds, send the refunds of closed deployments, then top up
+    /// the deployments running low. The module is only locked to read and
+    /// update the escrow, not while waiting on the ledger.
+    pub fn sync(&self, module: &Mutex<ComputeModuleImpl>) -> Result<(), ManyError> {
+        let cursor = module.lock().unwrap().storage.escrow_cursor()?;
+        let (transfers, last) = self.transfers(cursor.clone())?;
+        if let Some(last) = last.filter(|last| Some(last) != cursor.as_ref()) {
+            info!("Crediting {} transfers to the escrow", transfers.len());
+            module
+                .lock()
+                .unwrap()
+                .storage
+                .credit_escrow_transfers(transfers, last)?;
+        }
+
+        let refunds = module.lock().unwrap().storage.escrow_refunds()?;
+        for (owner, dseq, refund) in refunds {
+            info!("Refunding {refund} to {owner} for the deployment {dseq}");
+            let credit = self.refund(&owner, refund.clone(), dseq).err().map(|e| {
+                warn!(
+                    "Could not refund {refund} to {owner} on the ledger, crediting the escrow: {e}"
+                );
+                refund
+            });
+            module
+                .lock()
+                .unwrap()
+                .storage
+                .settle_escrow_refund(&owner, dseq, credit)?;
+        }
+
+        module.lock().unwrap().top_up_escrows()
+    }
+
+    /// Send tokens back to an owner on the ledger.
+    fn refund(&self, owner: &Address, amount: TokenAmount, dseq: u64) -> Result<(), ManyError> {
+        let memo = Memo::try_from(format!("Refund of the deployment {dseq}"))?;
+        self.ledger
+            .call_and_wait(
+                "ledger.send",
+                SendArgs {
+                    from: None,
+                    to: *owner,
+                    amount,
+                    symbol: self.symbol,
+                    memo: Some(memo),
+                    decimal_amount: None,
+                },
+                REFUND_TIMEOUT,
+            )?
+            .data
+            .map(|_| ())
+    }
+}
+
+/// The `uakt` left in the Akash escrow of a deployment at `now`, from the
+/// price of its lease. The estimate is that of Akash, which pays the lease
+/// every block.
+fn remaining(deployment: &DeploymentEscrow, now: Timestamp) -> u64 {
+    let blocks = now.secs().saturating_sub(deployment.since.secs()) as f64 / AKASH_BLOCK_TIME_SECS;
+    let spent = (deployment.price * blocks).ceil() as u64;
+    deployment.deposited.saturating_sub(spent)
+}
+
+/// Deployments are funded from the escrow when the server has one. Their
+/// initial deposit is set by the server and paid from the balance of their
+/// owner. Deployments running low are topped up from the same balance, and
+/// what is left in their Akash escrow is refunded to their owner on close.
+impl ComputeModuleImpl {
+    fn enabled_escrow(&self) -> Result<&Escrow, ManyError> {
+        self.escrow.as_ref().ok_or_else(error::escrow_disabled)
+    }
+
+    /// Charge the owner of a new deployment for its initial deposit, if
+    /// deployments are funded from the escrow. Returns the deposit in `uakt`
+    /// and the tokens charged. Funds are credited by [`Escrow::sync`], so
+    /// new transfers can take up to the escrow interval to be usable.
+    pub(crate) fn charge_deposit(
+        &mut self,
+        owner: &Address,
+    ) -> Result<Option<(u64, TokenAmount)>, ManyError> {
+        let Some(escrow) = &self.escrow else {
+            return Ok(None);
+        };
+        let deposit = escrow.opt.escrow_deposit;
+        let tokens = escrow.tokens_for(deposit);
+        self.storage.debit_escrow(owner, &tokens)?;
+        Ok(Some((deposit, tokens)))
+    }
+
+    /// Record the funding of a deployment whose initial deposit was charged.
+    pub(crate) fn add_deployment_escrow(
+        &mut self,
+        owner: &Address,
+        dseq: u64,
+        price: f64,
+        (deposited, charged): (u64, TokenAmount),
+    ) -> Result<(), ManyError> {
+        self.storage.set_deployment_escrow(
+            owner,
+            dseq,
+            &DeploymentEscrow {
+                price,
+                deposited,
+                charged,
+                since: Timestamp::now(),
+            },
+        )
+    }
+
+    /// Queue the refund of what is left of the deposits of a deployment being
+    /// closed, sent by [`Escrow::sync`]. Refunds that could not be sent on
+    /// the ledger go back to the escrow balance of the owner instead.
+    pub(crate) fn refund_deployment_escrow(
+        &mut self,
+        owner: &Address,
+        dseq: u64,
+    ) -> Result<(), ManyError> {
+        let (Some(escrow), Some(deployment)) =
+            (&self.escrow, self.storage.deployment_escrow(owner, dseq)?)
+        else {
+            return Ok(());
+        };
+        let refund = escrow
+            .refund_for(remaining(&deployment, Timestamp::now()))
+            .min(deployment.charged);
+        self.storage.close_deployment_escrow(owner, dseq, refund)
+    }
+
+    /// Top up the deployments running low whose owners have enough funds
+    /// left.
+    fn top_up_escrows(&mut self) -> Result<(), ManyError> {
+        let Some(escrow) = &self.escrow else {
+            return Ok(());
+        };
+        let top_up_blocks = escrow.opt.escrow_top_up_blocks;
+
+        for (owner, dseq, mut deployment) in self.storage.deployment_escrows(None)? {
+            let threshold = (deployment.price * top_up_blocks as f64).ceil() as u64;
+            if remaining(&deployment, Timestamp::now()) >= threshold || threshold == 0 {
+                continue;
+            }
+            let tokens = self.enabled_escrow()?.tokens_for(threshold);
+            if let Err(e) = self.storage.debit_escrow(&owner, &tokens) {
+                warn!("Could not top up the deployment {dseq} of {owner}: {e}");
+                continue;
+            }
+
+            info!("Topping up the deployment {dseq} of {owner} with {threshold}{PRICING_DENOM}");
+            if let Err(e) = self.deposit_deployment(dseq, threshold) {
+                warn!("Could not top up the deployment {dseq} of {owner}: {e}");
+                self.storage.credit_escrow(&owner, tokens)?;
+                continue;
+            }
+            deployment.deposited += threshold;
+            deployment.charged += tokens;
+            self.storage
+                .set_deployment_escrow(&owner, dseq, &deployment)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn escrow_of(&self, owner: &Address) -> Result<EscrowReturns, ManyError> {
+        let escrow = self.enabled_escrow()?;
+        Ok(EscrowReturns {
+            account: escrow.account,
+            symbol: escrow.symbol,
+            balance: self.storage.escrow_balance(owner)?,
+            deployments: self
+                .storage
+                .deployment_escrows(Some(owner))?
+                .into_iter()
+                .map(|(_, dseq, deployment)| (dseq, deployment))
+                .collect(),
+        })
+    }
+}
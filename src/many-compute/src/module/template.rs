@@ -0,0 +1,177 @@
+//! SDL templates of the deployments, rendered by the server so users do not
+//! have to write SDL themselves.
+//!
+//! A template is an SDL file with `{{name}}` placeholders. The parameters of
+//! every deployment are `image`, `port`, `cpu`, `memory`, `storage`,
+//! `region`, `env` (a list of `NAME=value`), `pricing_denom` and
+//! `pricing_amount`. Templates can declare other parameters, with values
+//! given in the `parameters` of `compute.deployTemplate`. String values are
+//! quoted, so they cannot change the structure of the SDL. The service of a
+//! template must be named `app`.
+use crate::error;
+use many_error::ManyError;
+use many_modules::compute::DeployArgs;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tracing::info;
+
+/// The template of `compute.deploy`.
+pub const DEFAULT_TEMPLATE: &str = "default";
+
+const DEFAULT_SDL: &str = r#"---
+version: "2.0"
+
+services:
+  app:
+    image: {{image}}
+    env: {{env}}
+    expose:
+      - port: {{port}}
+        to:
+          - global: true
+profiles:
+  compute:
+    app:
+      resources:
+        cpu:
+          units: {{cpu}}
+        memory:
+          size: {{memory}}
+        storage:
+          size: {{storage}}
+  placement:
+    region:
+      attributes:
+        host: akash
+        region: {{region}}
+      signedBy:
+        anyOf:
+          - "akash1365yvmc4s7awdyj3n2sav7xfx76adc6dnmlx63"
+          - "akash18qa2a2ltfyvkyj0ggj3hkvuj6twzyumuaru9s4"
+      pricing:
+        app:
+          denom: {{pricing_denom}}
+          amount: {{pricing_amount}}
+deployment:
+  app:
+    region:
+      profile: app
+      count: 1"#;
+
+/// The templates of a server, by name.
+#[derive(Clone, Debug)]
+pub struct Templates(BTreeMap<String, String>);
+
+impl Default for Templates {
+    /// The templates bundled with the server.
+    fn default() -> Self {
+        Self(BTreeMap::from([(
+            DEFAULT_TEMPLATE.to_string(),
+            DEFAULT_SDL.to_string(),
+        )]))
+    }
+}
+
+impl Templates {
+    /// Add the `.yml` and `.yaml` files of a directory, named after their
+    /// file stem. They replace the bundled templates of the same name.
+    pub fn load_dir(mut self, dir: &Path) -> Result<Self, String> {
+        let entries = std::fs::read_dir(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        for entry in entries {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let is_yaml = path
+                .extension()
+                .map_or(false, |ext| ext == "yml" || ext == "yaml");
+            let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                continue;
+            };
+            if is_yaml {
+                let sdl = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("{}: {e}", path.display()))?;
+                info!("Loaded template {name} from {}", path.display());
+                self.0.insert(name.to_string(), sdl);
+            }
+        }
+        Ok(self)
+    }
+
+    /// Render the SDL of a deployment from a template.
+    pub fn render(
+        &self,
+        name: &str,
+        deployment: &DeployArgs,
+        env: &BTreeMap<String, String>,
+        parameters: &BTreeMap<String, String>,
+        pricing: (&str, f64),
+    ) -> Result<String, ManyError> {
+        let template = self
+            .0
+            .get(name)
+            .ok_or_else(|| error::template_not_found(name))?;
+
+        if let Some(var) = env
+            .keys()
+            .find(|var| var.is_empty() || var.contains(['=', '\n']))
+        {
+            return Err(error::invalid_env_var(var));
+        }
+        let env = env
+            .iter()
+            .map(|(var, value)| quote(&format!("{var}={value}")))
+            .collect::<Vec<_>>();
+
+        let DeployArgs {
+            image,
+            port,
+            num_cpu,
+            num_memory,
+            memory_type,
+            num_storage,
+            storage_type,
+            region,
+        } = deployment;
+
+        // The parameters of the deployment take precedence over the others.
+        let mut values: BTreeMap<&str, String> = parameters
+            .iter()
+            .map(|(k, v)| (k.as_str(), quote(v)))
+            .collect();
+        values.extend([
+            ("image", quote(image)),
+            ("port", port.to_string()),
+            ("cpu", num_cpu.to_string()),
+            ("memory", format!("{num_memory}{memory_type}")),
+            ("storage", format!("{num_storage}{storage_type}")),
+            ("region", region.to_string()),
+            ("env", format!("[{}]", env.join(", "))),
+            ("pricing_denom", pricing.0.to_string()),
+            ("pricing_amount", pricing.1.to_string()),
+        ]);
+
+        render(template, &values)
+    }
+}
+
+/// A string value, as a YAML string.
+fn quote(value: &str) -> String {
+    serde_json::Value::from(value).to_string()
+}
+
+fn render(template: &str, values: &BTreeMap<&str, String>) -> Result<String, ManyError> {
+    let mut sdl = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let end = rest[start..]
+            .find("}}")
+            .ok_or_else(|| error::invalid_template("unclosed placeholder"))?;
+        let name = rest[start + 2..start + end].trim();
+        let value = values
+            .get(name)
+            .ok_or_else(|| error::missing_template_parameter(name))?;
+        sdl.push_str(&rest[..start]);
+        sdl.push_str(value);
+        rest = &rest[start + end + 2..];
+    }
+    sdl.push_str(rest);
+    Ok(sdl)
+}
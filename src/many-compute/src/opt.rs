@@ -1,4 +1,6 @@
 use clap::Parser;
+use many_identity::Address;
+use many_protocol::ManyUrl;
 
 #[derive(Debug, Parser)]
 pub struct AkashOpt {
@@ -28,3 +30,45 @@ pub struct AkashOpt {
     #[clap(long, default_value = "os")]
     pub akash_keyring_backend: String,
 }
+
+/// Fund deployments from the balances of their owners on a MANY ledger,
+/// instead of the Akash wallet alone. Owners send tokens to the ledger
+/// account of this server, which deposits their equivalent in `uakt` from
+/// the Akash wallet, tops up deployments running low and refunds what is
+/// left on close. The escrow is kept by this server alone, so it cannot be
+/// used on a blockchain.
+#[derive(Clone, Debug, Parser)]
+pub struct EscrowOpt {
+    /// The MANY ledger holding the escrow account, the account of the
+    /// identity of this server. Deployments are not funded by the escrow if
+    /// this is omitted.
+    #[clap(long, requires = "escrow_symbol")]
+    pub escrow_ledger: Option<ManyUrl>,
+
+    /// The symbol of the tokens funding deployments.
+    #[clap(long)]
+    pub escrow_symbol: Option<Address>,
+
+    /// The number of `uakt` that `--escrow-rate-tokens` tokens (in their
+    /// smallest unit) pay for.
+    #[clap(long, default_value = "1")]
+    pub escrow_rate_uakt: u64,
+
+    /// The number of tokens (in their smallest unit) paying for
+    /// `--escrow-rate-uakt` `uakt`.
+    #[clap(long, default_value = "1")]
+    pub escrow_rate_tokens: u64,
+
+    /// The initial deposit of a deployment, in `uakt`.
+    #[clap(long, default_value = "5000000")]
+    pub escrow_deposit: u64,
+
+    /// Top up deployments with less than this many blocks of funds left, by
+    /// depositing as many blocks of funds.
+    #[clap(long, default_value = "14400")]
+    pub escrow_top_up_blocks: u64,
+
+    /// The number of seconds between checks for new funds and top-ups.
+    #[clap(long, default_value = "600")]
+    pub escrow_interval: u64,
+}
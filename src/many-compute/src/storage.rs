@@ -9,6 +9,7 @@ use many_types::{SortOrder, Timestamp};
 use merk::{BatchEntry, Op};
 use std::path::Path;
 
+pub mod escrow;
 pub mod iterator;
 
 pub struct ComputeStorage {
@@ -35,6 +36,10 @@ impl std::fmt::Debug for ComputeStorage {
 }
 
 impl ComputeStorage {
+    pub fn is_blockchain(&self) -> bool {
+        self.blockchain
+    }
+
     #[inline]
     pub fn set_time(&mut self, time: Timestamp) {
         self.current_time = Some(time);
@@ -192,6 +197,18 @@ impl ComputeStorage {
             .is_some())
     }
 
+    pub fn get_deployment(
+        &self,
+        owner: &Address,
+        dseq: u64,
+    ) -> Result<Option<DeploymentMeta>, ManyError> {
+        self.persistent_store
+            .get(format!("/deploy/{owner}/{dseq}").as_bytes())
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
     pub fn remove_deployment(&mut self, sender: &Address, dseq: u64) -> Result<(), ManyError> {
         let mut meta: DeploymentMeta = minicbor::decode(
             &self
@@ -0,0 +1,202 @@
+use crate::error;
+use crate::storage::iterator::ComputeIterator;
+use crate::storage::ComputeStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::compute::DeploymentEscrow;
+use many_modules::events::EventId;
+use many_types::ledger::TokenAmount;
+use merk::{BatchEntry, Op};
+use std::collections::BTreeMap;
+
+const ESCROW_DEPLOY_ROOT: &str = "/escrow/deploy/";
+const ESCROW_REFUND_ROOT: &str = "/escrow/refund/";
+
+fn key_for_balance(owner: &Address) -> Vec<u8> {
+    format!("/escrow/balance/{owner}").into_bytes()
+}
+
+fn key_for_deployment(owner: &Address, dseq: u64) -> Vec<u8> {
+    format!("{ESCROW_DEPLOY_ROOT}{owner}/{dseq}").into_bytes()
+}
+
+fn key_for_refund(owner: &Address, dseq: u64) -> Vec<u8> {
+    format!("{ESCROW_REFUND_ROOT}{owner}/{dseq}").into_bytes()
+}
+
+/// The owner and `dseq` of a key under `root`.
+fn owner_and_dseq(root: &str, key: &[u8]) -> Result<(Address, u64), ManyError> {
+    let key = std::str::from_utf8(&key[root.len()..]).map_err(ManyError::deserialization_error)?;
+    let (owner, dseq) = key
+        .split_once('/')
+        .ok_or_else(|| ManyError::deserialization_error(key))?;
+    Ok((
+        owner.parse()?,
+        dseq.parse().map_err(ManyError::deserialization_error)?,
+    ))
+}
+
+/// The balances of the owners in the escrow, at `/escrow/balance/<owner>`,
+/// the funding of their deployments, at `/escrow/deploy/<owner>/<dseq>`, the
+/// refunds of closed deployments waiting to be sent, at
+/// `/escrow/refund/<owner>/<dseq>`, and the last ledger event credited to
+/// the escrow, at `/escrow/cursor`.
+impl ComputeStorage {
+    fn apply_escrow(&mut self, batch: &[BatchEntry]) -> Result<(), ManyError> {
+        self.persistent_store
+            .apply(batch)
+            .map_err(error::storage_apply_failed)?;
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(())
+    }
+
+    pub fn escrow_balance(&self, owner: &Address) -> Result<TokenAmount, ManyError> {
+        Ok(self
+            .persistent_store
+            .get(&key_for_balance(owner))
+            .map_err(error::storage_get_failed)?
+            .map_or_else(TokenAmount::zero, TokenAmount::from))
+    }
+
+    /// The last ledger event credited to the escrow.
+    pub fn escrow_cursor(&self) -> Result<Option<EventId>, ManyError> {
+        Ok(self
+            .persistent_store
+            .get(b"/escrow/cursor")
+            .map_err(error::storage_get_failed)?
+            .map(EventId::from))
+    }
+
+    /// Credit the transfers to the escrow up to the event `cursor`.
+    pub fn credit_escrow_transfers(
+        &mut self,
+        transfers: impl IntoIterator<Item = (Address, TokenAmount)>,
+        cursor: EventId,
+    ) -> Result<(), ManyError> {
+        let mut balances = BTreeMap::new();
+        for (owner, amount) in transfers {
+            let balance = match balances.remove(&owner) {
+                Some(balance) => balance,
+                None => self.escrow_balance(&owner)?,
+            };
+            balances.insert(owner, balance + amount);
+        }
+
+        let mut batch: Vec<BatchEntry> = balances
+            .into_iter()
+            .map(|(owner, balance)| (key_for_balance(&owner), Op::Put(balance.to_vec())))
+            .collect();
+        batch.push((b"/escrow/cursor".to_vec(), Op::Put(cursor.into())));
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+        self.apply_escrow(&batch)
+    }
+
+    pub fn credit_escrow(&mut self, owner: &Address, amount: TokenAmount) -> Result<(), ManyError> {
+        let balance = self.escrow_balance(owner)? + amount;
+        self.apply_escrow(&[(key_for_balance(owner), Op::Put(balance.to_vec()))])
+    }
+
+    pub fn debit_escrow(&mut self, owner: &Address, amount: &TokenAmount) -> Result<(), ManyError> {
+        let mut balance = self.escrow_balance(owner)?;
+        if balance < *amount {
+            return Err(error::insufficient_escrow_funds(amount, balance));
+        }
+        balance -= amount;
+        self.apply_escrow(&[(key_for_balance(owner), Op::Put(balance.to_vec()))])
+    }
+
+    pub fn deployment_escrow(
+        &self,
+        owner: &Address,
+        dseq: u64,
+    ) -> Result<Option<DeploymentEscrow>, ManyError> {
+        self.persistent_store
+            .get(&key_for_deployment(owner, dseq))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    pub fn set_deployment_escrow(
+        &mut self,
+        owner: &Address,
+        dseq: u64,
+        escrow: &DeploymentEscrow,
+    ) -> Result<(), ManyError> {
+        self.apply_escrow(&[(
+            key_for_deployment(owner, dseq),
+            Op::Put(minicbor::to_vec(escrow).map_err(ManyError::serialization_error)?),
+        )])
+    }
+
+    /// Remove the funding of a closed deployment, queuing the refund of what
+    /// is left of it.
+    pub fn close_deployment_escrow(
+        &mut self,
+        owner: &Address,
+        dseq: u64,
+        refund: TokenAmount,
+    ) -> Result<(), ManyError> {
+        let mut batch = vec![(key_for_deployment(owner, dseq), Op::Delete)];
+        if !refund.is_zero() {
+            batch.push((key_for_refund(owner, dseq), Op::Put(refund.to_vec())));
+        }
+        self.apply_escrow(&batch)
+    }
+
+    /// The refunds waiting to be sent, with their owner and `dseq`.
+    pub fn escrow_refunds(&self) -> Result<Vec<(Address, u64, TokenAmount)>, ManyError> {
+        ComputeIterator::all_with_prefix(
+            &self.persistent_store,
+            ESCROW_REFUND_ROOT.as_bytes().to_vec(),
+        )
+        .map(|item| {
+            let (key, value) = item.map_err(error::storage_get_failed)?;
+            let (owner, dseq) = owner_and_dseq(ESCROW_REFUND_ROOT, &key)?;
+            Ok((owner, dseq, TokenAmount::from(value)))
+        })
+        .collect()
+    }
+
+    /// Remove a refund once sent, or credit it back to the escrow balance of
+    /// its owner if it could not be sent.
+    pub fn settle_escrow_refund(
+        &mut self,
+        owner: &Address,
+        dseq: u64,
+        credit: Option<TokenAmount>,
+    ) -> Result<(), ManyError> {
+        let mut batch = Vec::new();
+        if let Some(credit) = credit {
+            let balance = self.escrow_balance(owner)? + credit;
+            batch.push((key_for_balance(owner), Op::Put(balance.to_vec())));
+        }
+        batch.push((key_for_refund(owner, dseq), Op::Delete));
+        self.apply_escrow(&batch)
+    }
+
+    /// The funded deployments, of an owner or of everyone, with their owner
+    /// and `dseq`.
+    pub fn deployment_escrows(
+        &self,
+        owner: Option<&Address>,
+    ) -> Result<Vec<(Address, u64, DeploymentEscrow)>, ManyError> {
+        let prefix = owner.map_or(ESCROW_DEPLOY_ROOT.to_string(), |owner| {
+            format!("{ESCROW_DEPLOY_ROOT}{owner}/")
+        });
+        ComputeIterator::all_with_prefix(&self.persistent_store, prefix.into_bytes())
+            .map(|item| {
+                let (key, value) = item.map_err(error::storage_get_failed)?;
+                let (owner, dseq) = owner_and_dseq(ESCROW_DEPLOY_ROOT, &key)?;
+                Ok((
+                    owner,
+                    dseq,
+                    minicbor::decode(&value).map_err(ManyError::deserialization_error)?,
+                ))
+            })
+            .collect()
+    }
+}
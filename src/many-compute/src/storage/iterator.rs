@@ -30,6 +30,16 @@ impl<'a> ComputeIterator<'a> {
 
         Self { inner }
     }
+
+    /// Iterate all keys starting with a prefix, in ascending order.
+    pub fn all_with_prefix(merk: &'a merk::Merk, prefix: Vec<u8>) -> Self {
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(prefix));
+
+        let inner = merk.iter_opt(IteratorMode::Start, options);
+
+        Self { inner }
+    }
 }
 
 impl<'a> Iterator for ComputeIterator<'a> {
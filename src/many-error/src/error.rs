@@ -65,6 +65,7 @@ macro_rules! many_error {
         impl ManyError {
             $($(
                 #[doc = $description]
+                #[track_caller]
                 pub fn $snake_name( $($arg: impl ToString,)* ) -> Self {
                     let s = Self::new(
                         ManyErrorCode::$name,
@@ -72,7 +73,8 @@ macro_rules! many_error {
                         BTreeMap::from_iter(vec![
                             $( (stringify!($arg).to_string(), ($arg).to_string()) ),*
                         ]),
-                    );
+                    )
+                    .with_origin(ErrorOrigin::caller(None));
 
                     #[cfg(feature = "trace_error_creation")] {
                         tracing::trace!("{}", s);
@@ -108,6 +110,8 @@ many_error! {
       -10: InvalidAttributeArguments as invalid_attribute_arguments()
             => "Attribute does not have the right arguments.",
       -11: AttributeNotFound as attribute_not_found(id) => "Expected attribute {id} not found.",
+      -12: UnverifiedResponse as unverified_response(details)
+            => "The response could not be verified against a trusted state:\n{details}",
 
      -100: InvalidIdentity as invalid_identity()
             => "Identity is invalid (does not follow the protocol).",
@@ -163,6 +167,12 @@ many_error! {
             => "Non-WebAuthn request denied for endpoint '{endpoint}'.",
     -1009: DuplicatedMessage as duplicated_message()
             => "This message was already processed.",
+    -1010: MethodDeniedByPolicy as method_denied_by_policy(method, from)
+            => "Method '{method}' is not allowed for '{from}' by the server policy.",
+    -1011: IncompatibleModuleVersion as incompatible_module_version(attribute, server, client)
+            => "Module {attribute} of the server has versions {server}, but the client supports {client}.",
+    -1012: DeadlineExceeded as deadline_exceeded()
+            => "The deadline of the request has passed.",
 
     // -2000 - -2999 is for server errors.
     -2000: InternalServerError as internal_server_error()
@@ -181,14 +191,29 @@ macro_rules! define_attribute_many_error {
     ( $( attribute $module_id: literal => { $( $id: literal : $vis: vis fn $name: ident ($( $var_name: ident ),*) => $message: literal ),* $(,)? } );* ) => {
         $(
         $(
+            #[doc = $message]
+            #[track_caller]
             $vis fn $name( $($var_name: impl ToString),* ) -> $crate::ManyError {
                 $crate::ManyError::attribute_specific(
-                    ($module_id as i32) * -10000i32 - ($id as i32),
+                    $name::CODE_VALUE,
                     String::from($message),
                     std::iter::FromIterator::from_iter(vec![
                         $( (stringify!($var_name).to_string(), ($var_name).to_string()) ),*
                     ]),
                 )
+                .with_origin($crate::ErrorOrigin::caller(Some(module_path!())))
+            }
+
+            #[doc = concat!("Error code and message of [`", stringify!($name), "()`].")]
+            #[allow(non_snake_case, dead_code)]
+            $vis mod $name {
+                pub(super) const CODE_VALUE: i32 = ($module_id as i32) * -10000i32 - ($id as i32);
+
+                /// The error code, to compare with `ManyError::code()`.
+                pub const CODE: $crate::ManyErrorCode = $crate::ManyErrorCode::AttributeSpecific(CODE_VALUE);
+
+                /// The message template, with `{argument}` placeholders.
+                pub const MESSAGE: &str = $message;
             }
         )*
         )*
@@ -200,6 +225,8 @@ macro_rules! define_application_many_error {
     ( $( { $( $id: literal : $vis: vis fn $name: ident ($( $var_name: ident ),*) => $message: literal ),* $(,)? } );* ) => {
         $(
         $(
+            #[doc = $message]
+            #[track_caller]
             $vis fn $name ( $($var_name: impl ToString),* ) -> $crate::ManyError {
                 $crate::ManyError::application_specific(
                     $id as u32,
@@ -208,6 +235,17 @@ macro_rules! define_application_many_error {
                         $( (stringify!($var_name).to_string(), ($var_name).to_string()) ),*
                     ]),
                 )
+                .with_origin($crate::ErrorOrigin::caller(Some(module_path!())))
+            }
+
+            #[doc = concat!("Error code and message of [`", stringify!($name), "()`].")]
+            #[allow(non_snake_case, dead_code)]
+            $vis mod $name {
+                /// The error code, to compare with `ManyError::code()`.
+                pub const CODE: $crate::ManyErrorCode = $crate::ManyErrorCode::ApplicationSpecific($id as u32);
+
+                /// The message template, with `{argument}` placeholders.
+                pub const MESSAGE: &str = $message;
             }
         )*
         )*
@@ -225,9 +263,64 @@ impl ManyErrorCode {
     }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
-#[repr(transparent)]
-pub struct ManyError(Reason<ManyErrorCode>);
+/// Where an error was created. This is only kept in memory for debugging
+/// (e.g. in logs); it is not encoded and not compared, so errors created at
+/// different places are the same error and responses do not depend on it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ErrorOrigin {
+    /// The module that defined the error, for attribute and application
+    /// specific errors.
+    pub module: Option<&'static str>,
+    pub file: &'static str,
+    pub line: u32,
+    pub column: u32,
+}
+
+impl ErrorOrigin {
+    /// The location of the caller, following `#[track_caller]` functions.
+    #[track_caller]
+    pub fn caller(module: Option<&'static str>) -> Self {
+        let location = std::panic::Location::caller();
+        Self {
+            module,
+            file: location.file(),
+            line: location.line(),
+            column: location.column(),
+        }
+    }
+}
+
+impl Display for ErrorOrigin {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if let Some(module) = self.module {
+            write!(f, "{module} ")?;
+        }
+        write!(f, "at {}:{}:{}", self.file, self.line, self.column)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ManyError(Reason<ManyErrorCode>, Option<ErrorOrigin>);
+
+impl PartialEq for ManyError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl Eq for ManyError {}
+
+impl PartialOrd for ManyError {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for ManyError {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.0.cmp(&other.0)
+    }
+}
 
 impl ManyError {
     #[inline]
@@ -260,6 +353,16 @@ impl ManyError {
         self.0.add_argument(key, value);
     }
 
+    #[inline]
+    pub const fn origin(&self) -> Option<&ErrorOrigin> {
+        self.1.as_ref()
+    }
+
+    #[inline]
+    pub fn with_origin(self, origin: ErrorOrigin) -> Self {
+        Self(self.0, Some(origin))
+    }
+
     #[inline]
     pub const fn is_attribute_specific(&self) -> bool {
         self.code().is_attribute_specific()
@@ -275,11 +378,11 @@ impl ManyError {
         message: Option<String>,
         arguments: BTreeMap<String, String>,
     ) -> Self {
-        Self(Reason::new(code, message, arguments))
+        Self(Reason::new(code, message, arguments), None)
     }
 
     pub fn with_code(self, code: ManyErrorCode) -> Self {
-        Self(self.0.with_code(code))
+        Self(self.0.with_code(code), self.1)
     }
 
     #[inline]
@@ -398,4 +501,115 @@ mod tests {
 
         assert_eq!(e.to_string(), "/{}{ZERO}{}}{TWO.");
     }
+
+    crate::define_attribute_many_error!(
+        attribute 2 => {
+            1: pub fn insufficient_funds(symbol) => "Insufficient {symbol}.",
+        }
+    );
+
+    crate::define_application_many_error!(
+        {
+            4: fn application_error() => "Application error.",
+        }
+    );
+
+    #[test]
+    fn typed_codes() {
+        assert_eq!(insufficient_funds::CODE, insufficient_funds("MFX").code());
+        assert_eq!(
+            insufficient_funds::CODE,
+            ErrorCode::AttributeSpecific(-20001)
+        );
+        assert_eq!(insufficient_funds::MESSAGE, "Insufficient {symbol}.");
+
+        assert_eq!(application_error::CODE, application_error().code());
+        assert_eq!(application_error::CODE, ErrorCode::ApplicationSpecific(4));
+    }
+
+    #[test]
+    fn origin() {
+        let e = insufficient_funds("MFX");
+        let line = line!() - 1;
+        let origin = e.origin().unwrap();
+        assert_eq!(origin.module, Some(module_path!()));
+        assert_eq!(origin.file, file!());
+        assert_eq!(origin.line, line);
+
+        let e = ManyError::unknown("foo");
+        let origin = e.origin().unwrap();
+        assert_eq!(origin.module, None);
+        assert_eq!(origin.line, line!() - 3);
+
+        // The origin does not change what the error is.
+        assert_eq!(e, ManyError::unknown("foo"));
+        assert_eq!(
+            e.clone().with_code(ErrorCode::Unknown).origin(),
+            Some(origin)
+        );
+    }
+
+    #[cfg(feature = "minicbor")]
+    #[test]
+    fn origin_is_not_encoded() {
+        let e = insufficient_funds("MFX");
+        let decoded: ManyError = minicbor::decode(&minicbor::to_vec(&e).unwrap()).unwrap();
+        assert_eq!(decoded, e);
+        assert_eq!(decoded.origin(), None);
+    }
+
+    #[cfg(feature = "minicbor")]
+    #[test]
+    fn details_roundtrip() {
+        let e = insufficient_funds("MFX").with_details(&BTreeMap::from([("needed", 100u64)]));
+        let bytes = minicbor::to_vec(&e).unwrap();
+        let decoded: ManyError = minicbor::decode(&bytes).unwrap();
+
+        assert_eq!(decoded, e);
+        assert_eq!(decoded.code(), insufficient_funds::CODE);
+        assert_eq!(
+            decoded.details::<BTreeMap<String, u64>>().unwrap().unwrap(),
+            BTreeMap::from([("needed".to_string(), 100)])
+        );
+
+        // Errors without details encode as before.
+        let e = insufficient_funds("MFX");
+        assert_eq!(e.details_bytes(), None);
+        assert_eq!(
+            minicbor::to_vec(&e).unwrap(),
+            minicbor::to_vec(crate::Reason::new(
+                insufficient_funds::CODE,
+                e.message().map(String::from),
+                e.arguments().clone()
+            ))
+            .unwrap()
+        );
+    }
+
+    #[cfg(feature = "minicbor")]
+    #[test]
+    fn decode_skips_unknown_keys() {
+        // { 0: -20001, 99: [1, 2], 1: "Insufficient." }
+        let mut bytes = Vec::new();
+        minicbor::Encoder::new(&mut bytes)
+            .map(3)
+            .unwrap()
+            .u32(0)
+            .unwrap()
+            .i64(-20001)
+            .unwrap()
+            .u32(99)
+            .unwrap()
+            .encode([1u8, 2])
+            .unwrap()
+            .u32(1)
+            .unwrap()
+            .str("Insufficient.")
+            .unwrap();
+
+        let decoded: ManyError = minicbor::decode(&bytes).unwrap();
+        assert_eq!(decoded.code(), insufficient_funds::CODE);
+        assert_eq!(decoded.message(), Some("Insufficient."));
+        assert_eq!(decoded.details_bytes(), None);
+    }
 }
@@ -26,6 +26,28 @@ impl<C> Encode<C> for ManyError {
 
 impl<'b, C> Decode<'b, C> for ManyError {
     fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
-        Ok(Self(d.decode()?))
+        Ok(Self(d.decode()?, None))
+    }
+}
+
+impl ManyError {
+    /// Attach structured details to this error. Details are encoded as CBOR
+    /// and sent alongside the message, so clients can inspect them without
+    /// parsing the message. If the details cannot be encoded, the error is
+    /// returned unchanged.
+    pub fn with_details(mut self, details: &impl Encode<()>) -> Self {
+        self.0.set_details(minicbor::to_vec(details).ok());
+        self
+    }
+
+    /// Decode the structured details of this error, if any.
+    pub fn details<'b, D: Decode<'b, ()>>(&'b self) -> Option<Result<D, minicbor::decode::Error>> {
+        self.0.details().map(minicbor::decode)
+    }
+
+    /// The raw CBOR bytes of the structured details of this error, if any.
+    #[inline]
+    pub fn details_bytes(&self) -> Option<&[u8]> {
+        self.0.details()
     }
 }
@@ -1,5 +1,5 @@
 pub mod error;
-pub use error::{ManyError, ManyErrorCode};
+pub use error::{ErrorOrigin, ManyError, ManyErrorCode};
 
 pub mod reason;
 pub use reason::Reason;
@@ -9,6 +9,7 @@ pub struct Reason<T> {
     code: T,
     message: Option<String>,
     arguments: BTreeMap<String, String>,
+    details: Option<Vec<u8>>,
 }
 
 impl<T> Reason<T> {
@@ -22,6 +23,7 @@ impl<T> Reason<T> {
             code,
             message,
             arguments,
+            details: None,
         }
     }
 
@@ -69,6 +71,17 @@ impl<T> Reason<T> {
     pub fn arguments(&self) -> &BTreeMap<String, String> {
         &self.arguments
     }
+
+    /// The structured details of this reason, as a single encoded CBOR item.
+    #[inline]
+    pub fn details(&self) -> Option<&[u8]> {
+        self.details.as_deref()
+    }
+
+    #[inline]
+    pub fn set_details(&mut self, details: Option<Vec<u8>>) {
+        self.details = details;
+    }
 }
 
 impl<T: Display> Display for Reason<T> {
@@ -10,6 +10,7 @@ enum ReasonCborKey {
     Code = 0,
     Message = 1,
     Arguments = 2,
+    Details = 3,
 }
 
 impl<T: Encode<()>> crate::Reason<T> {
@@ -31,9 +32,13 @@ impl<'b, T: Decode<'b, ()> + Default> crate::Reason<T> {
 impl<T: Encode<C>, C> Encode<C> for crate::Reason<T> {
     #[inline]
     fn encode<W: Write>(&self, e: &mut Encoder<W>, ctx: &mut C) -> Result<(), Error<W::Error>> {
-        e.map(1 + u64::from(self.message.is_some()) + u64::from(!self.arguments.is_empty()))?
-            .u32(ReasonCborKey::Code as u32)?
-            .encode_with(&self.code, ctx)?;
+        e.map(
+            1 + u64::from(self.message.is_some())
+                + u64::from(!self.arguments.is_empty())
+                + u64::from(self.details.is_some()),
+        )?
+        .u32(ReasonCborKey::Code as u32)?
+        .encode_with(&self.code, ctx)?;
 
         if let Some(msg) = &self.message {
             e.u32(ReasonCborKey::Message as u32)?.str(msg.as_str())?;
@@ -42,6 +47,11 @@ impl<T: Encode<C>, C> Encode<C> for crate::Reason<T> {
             e.u32(ReasonCborKey::Arguments as u32)?
                 .encode(&self.arguments)?;
         }
+        if let Some(details) = &self.details {
+            // Details are already encoded as a single CBOR item.
+            e.u32(ReasonCborKey::Details as u32)?;
+            e.writer_mut().write_all(details).map_err(Error::write)?;
+        }
         Ok(())
     }
 }
@@ -53,6 +63,7 @@ impl<'b, T: Decode<'b, C> + Default, C> Decode<'b, C> for crate::Reason<T> {
         let mut code: Option<T> = None;
         let mut message = None;
         let mut arguments: BTreeMap<String, String> = BTreeMap::new();
+        let mut details = None;
 
         let mut i = 0;
         loop {
@@ -65,7 +76,12 @@ impl<'b, T: Decode<'b, C> + Default, C> Decode<'b, C> for crate::Reason<T> {
                 Some(ReasonCborKey::Code) => code = Some(d.decode_with(ctx)?),
                 Some(ReasonCborKey::Message) => message = Some(d.str()?),
                 Some(ReasonCborKey::Arguments) => arguments = d.decode()?,
-                None => {}
+                Some(ReasonCborKey::Details) => {
+                    let start = d.position();
+                    d.skip()?;
+                    details = Some(d.input()[start..d.position()].to_vec());
+                }
+                None => d.skip()?,
             }
 
             i += 1;
@@ -78,6 +94,7 @@ impl<'b, T: Decode<'b, C> + Default, C> Decode<'b, C> for crate::Reason<T> {
             code: code.unwrap_or_default(),
             message: message.map(|s| s.to_string()),
             arguments,
+            details,
         })
     }
 }
@@ -14,7 +14,7 @@ use std::collections::BTreeSet;
 ///
 /// * `x` - Public key
 /// * `d` - Private key
-fn eddsa_cose_key(x: Vec<u8>, d: Option<Vec<u8>>) -> CoseKey {
+pub fn eddsa_cose_key(x: Vec<u8>, d: Option<Vec<u8>>) -> CoseKey {
     let mut params: Vec<(Label, Value)> = Vec::from([
         (
             Label::Int(OkpKeyParameter::Crv.to_i64()),
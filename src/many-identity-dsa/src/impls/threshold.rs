@@ -0,0 +1,320 @@
+//! Threshold Ed25519 identities, whose address is controlled jointly by
+//! several signers using FROST (RFC 9591).
+//!
+//! A group of `max_signers` shares a single Ed25519 key, and any
+//! `min_signers` of them can sign together. The signatures are regular
+//! Ed25519 signatures of the group key, so envelopes signed by a
+//! [`ThresholdIdentity`] are verified by an [`Ed25519Verifier`] like any
+//! other Ed25519 envelope, and servers need no change to accept them.
+//!
+//! [`Ed25519Verifier`]: crate::ed25519::Ed25519Verifier
+use crate::impls::ed25519::eddsa_cose_key;
+use coset::{CoseKey, CoseSign1, CoseSign1Builder};
+use frost_ed25519::keys::{IdentifierList, KeyPackage, PublicKeyPackage};
+use frost_ed25519::round1::{SigningCommitments, SigningNonces};
+use frost_ed25519::round2::SignatureShare;
+use frost_ed25519::{Identifier, SigningPackage};
+use many_error::ManyError;
+use many_identity::{cose, Address, Identity};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+pub use frost_ed25519 as frost;
+
+/// The public key of the group, as an EdDSA COSE key.
+fn group_cose_key(public: &PublicKeyPackage) -> CoseKey {
+    eddsa_cose_key(public.verifying_key().serialize().to_vec(), None)
+}
+
+/// The address of a group, i.e. of its group key.
+pub fn group_address(public: &PublicKeyPackage) -> Result<Address, ManyError> {
+    unsafe { cose::address_unchecked(&group_cose_key(public)) }
+}
+
+/// A signer taking part in threshold signatures, e.g. a share held locally
+/// or a remote participant.
+pub trait ThresholdSigner: Send + Sync {
+    fn identifier(&self) -> Identifier;
+
+    /// Round 1: commit to new nonces. The signer keeps the nonces for the
+    /// next round.
+    fn commit(&self) -> Result<SigningCommitments, ManyError>;
+
+    /// Round 2: sign the package with the nonces of the last commitment.
+    /// The nonces cannot be used again.
+    fn sign(&self, package: &SigningPackage) -> Result<SignatureShare, ManyError>;
+}
+
+/// The share of the group key of a signer.
+pub struct ThresholdShare {
+    key_package: KeyPackage,
+    nonces: Mutex<Option<SigningNonces>>,
+}
+
+impl Debug for ThresholdShare {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ThresholdShare")
+            .field(self.key_package.identifier())
+            .finish()
+    }
+}
+
+impl ThresholdShare {
+    pub fn new(key_package: KeyPackage) -> Self {
+        Self {
+            key_package,
+            nonces: Mutex::new(None),
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ManyError> {
+        KeyPackage::deserialize(bytes)
+            .map(Self::new)
+            .map_err(ManyError::deserialization_error)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, ManyError> {
+        self.key_package
+            .serialize()
+            .map_err(ManyError::serialization_error)
+    }
+
+    pub fn key_package(&self) -> &KeyPackage {
+        &self.key_package
+    }
+}
+
+impl ThresholdSigner for ThresholdShare {
+    fn identifier(&self) -> Identifier {
+        *self.key_package.identifier()
+    }
+
+    fn commit(&self) -> Result<SigningCommitments, ManyError> {
+        let (nonces, commitments) =
+            frost::round1::commit(self.key_package.signing_share(), &mut rand::rngs::OsRng);
+        *self.nonces.lock().map_err(ManyError::unknown)? = Some(nonces);
+        Ok(commitments)
+    }
+
+    fn sign(&self, package: &SigningPackage) -> Result<SignatureShare, ManyError> {
+        let nonces = self
+            .nonces
+            .lock()
+            .map_err(ManyError::unknown)?
+            .take()
+            .ok_or_else(|| ManyError::unknown("No commitment to sign with."))?;
+        frost::round2::sign(package, &nonces, &self.key_package).map_err(ManyError::unknown)
+    }
+}
+
+/// Generate the shares of a new group key, with a trusted dealer. The
+/// dealer must deliver each share to its signer and forget them all.
+pub fn generate_threshold_shares(
+    min_signers: u16,
+    max_signers: u16,
+) -> Result<(Vec<ThresholdShare>, PublicKeyPackage), ManyError> {
+    let (shares, public) = frost::keys::generate_with_dealer(
+        max_signers,
+        min_signers,
+        IdentifierList::Default,
+        &mut rand::rngs::OsRng,
+    )
+    .map_err(ManyError::unknown)?;
+
+    let shares = shares
+        .into_values()
+        .map(|share| KeyPackage::try_from(share).map(ThresholdShare::new))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ManyError::unknown)?;
+    Ok((shares, public))
+}
+
+/// An identity signing with the group key of a threshold of signers. It
+/// coordinates the two rounds of FROST with the first `min_signers` of its
+/// signers to sign every envelope.
+#[derive(Clone)]
+pub struct ThresholdIdentity {
+    address: Address,
+    public_key: CoseKey,
+    public: PublicKeyPackage,
+    min_signers: usize,
+    signers: Vec<Arc<dyn ThresholdSigner>>,
+}
+
+impl Debug for ThresholdIdentity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThresholdIdentity")
+            .field("address", &self.address)
+            .field("min_signers", &self.min_signers)
+            .field("signers", &self.signers.len())
+            .finish()
+    }
+}
+
+impl ThresholdIdentity {
+    /// Fails if there are fewer signers than needed to sign, or signers that
+    /// are not part of the group.
+    pub fn new(
+        public: PublicKeyPackage,
+        min_signers: u16,
+        signers: Vec<Arc<dyn ThresholdSigner>>,
+    ) -> Result<Self, ManyError> {
+        let min_signers = usize::from(min_signers);
+        if signers.len() < min_signers {
+            return Err(ManyError::unknown(format!(
+                "At least {min_signers} signers are needed, got {}.",
+                signers.len()
+            )));
+        }
+        if let Some(signer) = signers
+            .iter()
+            .find(|s| !public.verifying_shares().contains_key(&s.identifier()))
+        {
+            return Err(ManyError::unknown(format!(
+                "Signer {:?} is not part of the group.",
+                signer.identifier()
+            )));
+        }
+
+        let public_key = group_cose_key(&public);
+        let address = group_address(&public)?;
+        Ok(Self {
+            address,
+            public_key,
+            public,
+            min_signers,
+            signers,
+        })
+    }
+
+    /// An identity signing with shares held locally, e.g. to test a group.
+    pub fn from_shares(
+        public: PublicKeyPackage,
+        shares: Vec<ThresholdShare>,
+    ) -> Result<Self, ManyError> {
+        let min_signers = shares
+            .first()
+            .map_or(1, |share| *share.key_package.min_signers());
+        let signers = shares
+            .into_iter()
+            .map(|share| Arc::new(share) as Arc<dyn ThresholdSigner>)
+            .collect();
+        Self::new(public, min_signers, signers)
+    }
+
+    pub fn public_key_package(&self) -> &PublicKeyPackage {
+        &self.public
+    }
+
+    /// Sign `bytes` with the group key, using the first `min_signers`
+    /// signers.
+    fn try_sign(&self, bytes: &[u8]) -> Result<Vec<u8>, ManyError> {
+        let signers = &self.signers[..self.min_signers];
+
+        let mut commitments = BTreeMap::new();
+        for signer in signers {
+            commitments.insert(signer.identifier(), signer.commit()?);
+        }
+        let package = SigningPackage::new(commitments, bytes);
+
+        let mut shares = BTreeMap::new();
+        for signer in signers {
+            shares.insert(signer.identifier(), signer.sign(&package)?);
+        }
+
+        frost::aggregate(&package, &shares, &self.public)
+            .map(|signature| signature.serialize().to_vec())
+            .map_err(ManyError::unknown)
+    }
+}
+
+impl Identity for ThresholdIdentity {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn public_key(&self) -> Option<CoseKey> {
+        Some(self.public_key.clone())
+    }
+
+    fn sign_1(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+        let mut envelope = cose::add_keyset_header(envelope, self)?;
+        envelope.protected.header.alg =
+            Some(coset::Algorithm::Assigned(coset::iana::Algorithm::EdDSA));
+        envelope.protected.header.key_id = self.address.to_vec();
+
+        let builder = CoseSign1Builder::new()
+            .protected(envelope.protected.header)
+            .unprotected(envelope.unprotected);
+        let builder = if let Some(payload) = envelope.payload {
+            builder.payload(payload)
+        } else {
+            builder
+        };
+
+        Ok(builder
+            .try_create_signature(&[], |bytes| self.try_sign(bytes))?
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ed25519::Ed25519Verifier;
+
+    #[test]
+    fn sign_and_verify_request() {
+        let (mut shares, public) = generate_threshold_shares(2, 3).unwrap();
+        // Any two of the three signers can sign.
+        shares.remove(0);
+        let id = ThresholdIdentity::from_shares(public, shares).unwrap();
+
+        let envelope = many_protocol::encode_cose_sign1_from_request(
+            many_protocol::RequestMessageBuilder::default()
+                .from(id.address())
+                .method("status".to_string())
+                .data(b"".to_vec())
+                .build()
+                .unwrap(),
+            &id,
+        )
+        .unwrap();
+
+        let verifier = Ed25519Verifier::from_key(&id.public_key().unwrap()).unwrap();
+        many_protocol::decode_request_from_cose_sign1(&envelope, &verifier).unwrap();
+    }
+
+    #[test]
+    fn not_enough_signers() {
+        let (mut shares, public) = generate_threshold_shares(2, 3).unwrap();
+        shares.truncate(1);
+        assert!(ThresholdIdentity::from_shares(public, shares).is_err());
+    }
+
+    #[test]
+    fn nonces_are_used_once() {
+        let (shares, public) = generate_threshold_shares(2, 2).unwrap();
+        let mut commitments = BTreeMap::new();
+        for share in &shares {
+            commitments.insert(share.identifier(), share.commit().unwrap());
+        }
+        let package = SigningPackage::new(commitments, b"FOOBAR");
+        shares[0].sign(&package).unwrap();
+        assert!(shares[0].sign(&package).is_err());
+
+        let id = ThresholdIdentity::from_shares(public, shares).unwrap();
+        assert!(id.try_sign(b"FOOBAR").is_ok());
+    }
+
+    #[test]
+    fn share_round_trip() {
+        let (shares, public) = generate_threshold_shares(2, 3).unwrap();
+        let shares = shares
+            .iter()
+            .map(|share| ThresholdShare::from_bytes(&share.to_bytes().unwrap()).unwrap())
+            .collect();
+        assert!(ThresholdIdentity::from_shares(public, shares).is_ok());
+    }
+}
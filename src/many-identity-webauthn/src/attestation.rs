@@ -0,0 +1,530 @@
+use coset::cbor::value::Value;
+use coset::{AsCborValue, CoseKey, Label};
+use many_error::ManyError;
+use openssl::hash::MessageDigest;
+use openssl::stack::Stack;
+use openssl::x509::store::X509StoreBuilder;
+use openssl::x509::{X509StoreContext, X509};
+use std::collections::BTreeSet;
+use std::str::FromStr;
+
+/// COSE algorithm identifier for ECDSA w/ SHA-256.
+const COSE_ALG_ES256: i128 = -7;
+
+/// Flag in the authenticator data indicating attested credential data is present.
+const AUTH_DATA_FLAG_AT: u8 = 0x40;
+
+/// Size of the fixed part of the authenticator data (rpIdHash, flags, signCount).
+const AUTH_DATA_FIXED_LEN: usize = 37;
+
+/// Attestation statement formats that can be validated.
+/// See https://www.w3.org/TR/webauthn-2/#sctn-defined-attestation-formats
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd)]
+pub enum AttestationFormat {
+    Packed,
+    FidoU2f,
+}
+
+impl AttestationFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttestationFormat::Packed => "packed",
+            AttestationFormat::FidoU2f => "fido-u2f",
+        }
+    }
+}
+
+impl FromStr for AttestationFormat {
+    type Err = ManyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "packed" => Ok(AttestationFormat::Packed),
+            "fido-u2f" => Ok(AttestationFormat::FidoU2f),
+            x => Err(ManyError::unknown(format!(
+                "Unsupported attestation format: {x}"
+            ))),
+        }
+    }
+}
+
+/// The attested credential data embedded in the authenticator data of an
+/// attestation object.
+struct AttestedCredential<'a> {
+    rp_id_hash: &'a [u8],
+    credential_id: &'a [u8],
+    public_key: CoseKey,
+}
+
+fn parse_auth_data(auth_data: &[u8]) -> Result<AttestedCredential, ManyError> {
+    if auth_data.len() < AUTH_DATA_FIXED_LEN + 18 {
+        return Err(ManyError::unknown("Attestation `authData` is too short"));
+    }
+    if auth_data[32] & AUTH_DATA_FLAG_AT == 0 {
+        return Err(ManyError::unknown(
+            "Attestation `authData` has no attested credential data",
+        ));
+    }
+
+    // AAGUID (16 bytes), then credential ID length (2 bytes, big endian).
+    let rest = &auth_data[AUTH_DATA_FIXED_LEN + 16..];
+    let credential_id_len = u16::from_be_bytes([rest[0], rest[1]]) as usize;
+    let rest = &rest[2..];
+    if rest.len() < credential_id_len {
+        return Err(ManyError::unknown("Attestation `authData` is too short"));
+    }
+    let (credential_id, mut public_key) = rest.split_at(credential_id_len);
+
+    // The public key might be followed by extensions, only read a single value.
+    let public_key: Value =
+        coset::cbor::de::from_reader(&mut public_key).map_err(ManyError::unknown)?;
+    let public_key = CoseKey::from_cbor_value(public_key).map_err(ManyError::unknown)?;
+
+    Ok(AttestedCredential {
+        rp_id_hash: &auth_data[0..32],
+        credential_id,
+        public_key,
+    })
+}
+
+/// Returns the uncompressed (x, y) coordinates of an EC2 COSE key.
+fn ec2_coordinates(key: &CoseKey) -> Option<(&[u8], &[u8])> {
+    let get = |label: i64| {
+        key.params
+            .iter()
+            .find(|(k, _)| k == &Label::Int(label))
+            .and_then(|(_, v)| v.as_bytes())
+            .map(Vec::as_slice)
+    };
+    Some((get(-2)?, get(-3)?))
+}
+
+fn map_get<'a>(map: &'a [(Value, Value)], key: &str) -> Option<&'a Value> {
+    map.iter()
+        .find(|(k, _)| k.as_text() == Some(key))
+        .map(|(_, v)| v)
+}
+
+fn verify_signature(cert: &X509, data: &[u8], signature: &[u8]) -> Result<(), ManyError> {
+    let public_key = cert.public_key().map_err(ManyError::unknown)?;
+    let mut verifier = openssl::sign::Verifier::new(MessageDigest::sha256(), &public_key)
+        .map_err(ManyError::unknown)?;
+    verifier.update(data).map_err(ManyError::unknown)?;
+
+    if verifier.verify(signature).map_err(ManyError::unknown)? {
+        Ok(())
+    } else {
+        Err(ManyError::unknown("Attestation signature is invalid"))
+    }
+}
+
+/// A policy requiring WebAuthn credentials to carry an attestation statement
+/// signed by a certificate chaining to one of the trusted roots. This allows
+/// deployments to only accept hardware-backed authenticators.
+#[derive(Clone, Debug)]
+pub struct AttestationPolicy {
+    roots: Vec<X509>,
+    formats: BTreeSet<AttestationFormat>,
+}
+
+impl AttestationPolicy {
+    /// Create a policy accepting all supported formats.
+    pub fn new(roots: Vec<X509>) -> Self {
+        Self {
+            roots,
+            formats: BTreeSet::from([AttestationFormat::Packed, AttestationFormat::FidoU2f]),
+        }
+    }
+
+    /// Create a policy from a PEM bundle of root certificates.
+    pub fn from_pem(pem: &[u8]) -> Result<Self, ManyError> {
+        let roots = X509::stack_from_pem(pem).map_err(ManyError::unknown)?;
+        if roots.is_empty() {
+            return Err(ManyError::unknown(
+                "No root certificate found for attestation",
+            ));
+        }
+        Ok(Self::new(roots))
+    }
+
+    pub fn with_formats(mut self, formats: impl IntoIterator<Item = AttestationFormat>) -> Self {
+        self.formats = formats.into_iter().collect();
+        self
+    }
+
+    /// Verify an attestation object for the credential `key`.
+    ///
+    /// `client_data_hash` is the SHA-256 of the `clientData` used when the
+    /// credential was created, and `rp_id_hash` the Relying Party ID hash of
+    /// the current assertion.
+    pub fn verify(
+        &self,
+        attestation_object: &[u8],
+        client_data_hash: &[u8],
+        key: &CoseKey,
+        rp_id_hash: &[u8],
+    ) -> Result<(), ManyError> {
+        let mut reader = attestation_object;
+        let object: Value =
+            coset::cbor::de::from_reader(&mut reader).map_err(ManyError::unknown)?;
+        let object = object
+            .as_map()
+            .ok_or_else(|| ManyError::unknown("Attestation object is not a map"))?;
+
+        let format: AttestationFormat = map_get(object, "fmt")
+            .and_then(Value::as_text)
+            .ok_or_else(|| ManyError::unknown("Attestation `fmt` missing or not Text"))?
+            .parse()?;
+        if !self.formats.contains(&format) {
+            return Err(ManyError::unknown(format!(
+                "Attestation format not allowed: {}",
+                format.as_str()
+            )));
+        }
+
+        let statement = map_get(object, "attStmt")
+            .and_then(Value::as_map)
+            .ok_or_else(|| ManyError::unknown("Attestation `attStmt` missing or not a map"))?;
+        let auth_data = map_get(object, "authData")
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| ManyError::unknown("Attestation `authData` missing or not Bytes"))?;
+
+        let credential = parse_auth_data(auth_data)?;
+        if credential.rp_id_hash != rp_id_hash {
+            return Err(ManyError::unknown(
+                "Attestation Relying Party doesn't match",
+            ));
+        }
+        let (x, y) = ec2_coordinates(&credential.public_key)
+            .ok_or_else(|| ManyError::unknown("Attested credential key is not EC2"))?;
+        if ec2_coordinates(key) != Some((x, y)) {
+            return Err(ManyError::unknown(
+                "Attested credential doesn't match the envelope key",
+            ));
+        }
+
+        let signature = map_get(statement, "sig")
+            .and_then(Value::as_bytes)
+            .ok_or_else(|| ManyError::unknown("Attestation `sig` missing or not Bytes"))?;
+        let certificates = map_get(statement, "x5c")
+            .and_then(Value::as_array)
+            .ok_or_else(|| {
+                ManyError::unknown("Attestation `x5c` missing, self attestation is not accepted")
+            })?
+            .iter()
+            .map(|c| {
+                c.as_bytes()
+                    .ok_or_else(|| ManyError::unknown("Attestation certificate is not Bytes"))
+                    .and_then(|c| X509::from_der(c).map_err(ManyError::unknown))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let leaf = certificates
+            .first()
+            .ok_or_else(|| ManyError::unknown("Attestation `x5c` is empty"))?;
+
+        match format {
+            AttestationFormat::Packed => {
+                let alg = map_get(statement, "alg")
+                    .and_then(Value::as_integer)
+                    .map(i128::from);
+                if alg != Some(COSE_ALG_ES256) {
+                    return Err(ManyError::unknown("Attestation algorithm not supported"));
+                }
+
+                let data = [auth_data.as_slice(), client_data_hash].concat();
+                verify_signature(leaf, &data, signature)?;
+            }
+            AttestationFormat::FidoU2f => {
+                if certificates.len() != 1 {
+                    return Err(ManyError::unknown(
+                        "FIDO U2F attestation requires a single certificate",
+                    ));
+                }
+
+                let data = [
+                    &[0x00u8][..],
+                    credential.rp_id_hash,
+                    client_data_hash,
+                    credential.credential_id,
+                    &[0x04u8][..],
+                    x,
+                    y,
+                ]
+                .concat();
+                verify_signature(leaf, &data, signature)?;
+            }
+        }
+
+        self.verify_chain(&certificates)
+    }
+
+    fn verify_chain(&self, certificates: &[X509]) -> Result<(), ManyError> {
+        let mut store = X509StoreBuilder::new().map_err(ManyError::unknown)?;
+        for root in &self.roots {
+            store.add_cert(root.clone()).map_err(ManyError::unknown)?;
+        }
+        let store = store.build();
+
+        let mut chain = Stack::new().map_err(ManyError::unknown)?;
+        for cert in &certificates[1..] {
+            chain.push(cert.clone()).map_err(ManyError::unknown)?;
+        }
+
+        let mut context = X509StoreContext::new().map_err(ManyError::unknown)?;
+        let trusted = context
+            .init(&store, &certificates[0], &chain, |c| c.verify_cert())
+            .map_err(ManyError::unknown)?;
+
+        if trusted {
+            Ok(())
+        } else {
+            Err(ManyError::unknown("Attestation certificate is not trusted"))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::{iana, CborSerializable, CoseKeyBuilder};
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::{BigNum, BigNumContext};
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::{PKey, Private};
+    use openssl::sign::Signer;
+    use openssl::x509::extension::BasicConstraints;
+    use openssl::x509::{X509Builder, X509NameBuilder};
+    use sha2::Digest;
+
+    fn ec_key() -> PKey<Private> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+    }
+
+    fn certificate(key: &PKey<Private>, issuer: Option<(&X509, &PKey<Private>)>) -> X509 {
+        let mut name = X509NameBuilder::new().unwrap();
+        let cn = if issuer.is_none() {
+            "Test Root"
+        } else {
+            "Test Authenticator"
+        };
+        name.append_entry_by_text("CN", cn).unwrap();
+        let name = name.build();
+
+        let mut builder = X509Builder::new().unwrap();
+        builder.set_version(2).unwrap();
+        let serial = BigNum::from_u32(if issuer.is_none() { 1 } else { 2 })
+            .unwrap()
+            .to_asn1_integer()
+            .unwrap();
+        builder.set_serial_number(&serial).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder
+            .set_issuer_name(issuer.map_or(&*name, |(c, _)| c.subject_name()))
+            .unwrap();
+        builder.set_pubkey(key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        if issuer.is_none() {
+            builder
+                .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+                .unwrap();
+        }
+        builder
+            .sign(issuer.map_or(key, |(_, k)| k), MessageDigest::sha256())
+            .unwrap();
+        builder.build()
+    }
+
+    fn cose_key(key: &PKey<Private>) -> CoseKey {
+        let ec = key.ec_key().unwrap();
+        let mut ctx = BigNumContext::new().unwrap();
+        let mut x = BigNum::new().unwrap();
+        let mut y = BigNum::new().unwrap();
+        ec.public_key()
+            .affine_coordinates_gfp(ec.group(), &mut x, &mut y, &mut ctx)
+            .unwrap();
+        CoseKeyBuilder::new_ec2_pub_key(
+            iana::EllipticCurve::P_256,
+            x.to_vec_padded(32).unwrap(),
+            y.to_vec_padded(32).unwrap(),
+        )
+        .build()
+    }
+
+    fn sign(key: &PKey<Private>, data: &[u8]) -> Vec<u8> {
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(data).unwrap();
+        signer.sign_to_vec().unwrap()
+    }
+
+    fn rp_id_hash() -> Vec<u8> {
+        sha2::Sha256::digest(b"localhost").to_vec()
+    }
+
+    fn auth_data(credential: &CoseKey) -> Vec<u8> {
+        let mut data = rp_id_hash();
+        data.push(0x41); // User present and attested credential data.
+        data.extend([0, 0, 0, 1]);
+        data.extend([0u8; 16]);
+        data.extend(16u16.to_be_bytes());
+        data.extend([1u8; 16]);
+        data.extend(credential.clone().to_vec().unwrap());
+        data
+    }
+
+    struct Fixture {
+        root: X509,
+        leaf: X509,
+        leaf_key: PKey<Private>,
+        credential: CoseKey,
+        auth_data: Vec<u8>,
+        client_data_hash: Vec<u8>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let root_key = ec_key();
+            let root = certificate(&root_key, None);
+            let leaf_key = ec_key();
+            let leaf = certificate(&leaf_key, Some((&root, &root_key)));
+            let credential = cose_key(&ec_key());
+
+            Self {
+                root,
+                leaf,
+                leaf_key,
+                auth_data: auth_data(&credential),
+                credential,
+                client_data_hash: sha2::Sha256::digest(b"{}").to_vec(),
+            }
+        }
+
+        fn object(&self, fmt: &str, statement: Vec<(Value, Value)>) -> Vec<u8> {
+            let object = Value::Map(vec![
+                (Value::Text("fmt".to_string()), Value::Text(fmt.to_string())),
+                (Value::Text("attStmt".to_string()), Value::Map(statement)),
+                (
+                    Value::Text("authData".to_string()),
+                    Value::Bytes(self.auth_data.clone()),
+                ),
+            ]);
+            let mut bytes = vec![];
+            coset::cbor::ser::into_writer(&object, &mut bytes).unwrap();
+            bytes
+        }
+
+        fn packed(&self) -> Vec<u8> {
+            let data = [self.auth_data.as_slice(), &self.client_data_hash].concat();
+            self.object(
+                "packed",
+                vec![
+                    (Value::Text("alg".to_string()), Value::Integer((-7).into())),
+                    (
+                        Value::Text("sig".to_string()),
+                        Value::Bytes(sign(&self.leaf_key, &data)),
+                    ),
+                    (
+                        Value::Text("x5c".to_string()),
+                        Value::Array(vec![Value::Bytes(self.leaf.to_der().unwrap())]),
+                    ),
+                ],
+            )
+        }
+
+        fn fido_u2f(&self) -> Vec<u8> {
+            let (x, y) = ec2_coordinates(&self.credential).unwrap();
+            let data = [
+                &[0x00u8][..],
+                rp_id_hash().as_slice(),
+                &self.client_data_hash,
+                &[1u8; 16],
+                &[0x04u8][..],
+                x,
+                y,
+            ]
+            .concat();
+            self.object(
+                "fido-u2f",
+                vec![
+                    (
+                        Value::Text("sig".to_string()),
+                        Value::Bytes(sign(&self.leaf_key, &data)),
+                    ),
+                    (
+                        Value::Text("x5c".to_string()),
+                        Value::Array(vec![Value::Bytes(self.leaf.to_der().unwrap())]),
+                    ),
+                ],
+            )
+        }
+
+        fn verify(&self, policy: &AttestationPolicy, object: &[u8]) -> Result<(), ManyError> {
+            policy.verify(
+                object,
+                &self.client_data_hash,
+                &self.credential,
+                &rp_id_hash(),
+            )
+        }
+    }
+
+    #[test]
+    fn packed_ok() {
+        let f = Fixture::new();
+        let policy = AttestationPolicy::new(vec![f.root.clone()]);
+        assert!(f.verify(&policy, &f.packed()).is_ok());
+    }
+
+    #[test]
+    fn fido_u2f_ok() {
+        let f = Fixture::new();
+        let policy = AttestationPolicy::new(vec![f.root.clone()]);
+        assert!(f.verify(&policy, &f.fido_u2f()).is_ok());
+    }
+
+    #[test]
+    fn untrusted_root() {
+        let f = Fixture::new();
+        let other = Fixture::new();
+        let policy = AttestationPolicy::new(vec![other.root]);
+        assert!(f.verify(&policy, &f.packed()).is_err());
+        assert!(f.verify(&policy, &f.fido_u2f()).is_err());
+    }
+
+    #[test]
+    fn format_not_allowed() {
+        let f = Fixture::new();
+        let policy =
+            AttestationPolicy::new(vec![f.root.clone()]).with_formats([AttestationFormat::Packed]);
+        assert!(f.verify(&policy, &f.packed()).is_ok());
+        assert!(f.verify(&policy, &f.fido_u2f()).is_err());
+    }
+
+    #[test]
+    fn credential_mismatch() {
+        let f = Fixture::new();
+        let policy = AttestationPolicy::new(vec![f.root.clone()]);
+        let result = policy.verify(
+            &f.packed(),
+            &f.client_data_hash,
+            &cose_key(&ec_key()),
+            &rp_id_hash(),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn from_pem() {
+        let f = Fixture::new();
+        let pem = f.root.to_pem().unwrap();
+        let policy = AttestationPolicy::from_pem(&pem).unwrap();
+        assert!(f.verify(&policy, &f.packed()).is_ok());
+        assert!(AttestationPolicy::from_pem(b"").is_err());
+    }
+}
@@ -41,6 +41,11 @@ pub struct Challenge {
 
     #[n(1)]
     payload_sha: Base64Encoder<Vec<u8>>,
+
+    /// A nonce issued by the [`ChallengeServer`](crate::ChallengeServer) of
+    /// the server, if it requires one.
+    #[n(2)]
+    nonce: Option<Base64Encoder<Vec<u8>>>,
 }
 
 impl Challenge {
@@ -51,6 +56,10 @@ impl Challenge {
     pub fn protected_header(&self) -> &ProtectedHeader {
         &self.protected_header
     }
+
+    pub fn nonce(&self) -> Option<&[u8]> {
+        self.nonce.as_ref().map(|nonce| nonce.0.as_slice())
+    }
 }
 
 impl TryInto<Challenge> for &CoseSign1 {
@@ -69,6 +78,7 @@ impl TryInto<Challenge> for &CoseSign1 {
         Ok(Challenge {
             protected_header,
             payload_sha,
+            nonce: None,
         })
     }
 }
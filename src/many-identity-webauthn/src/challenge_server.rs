@@ -0,0 +1,210 @@
+use many_error::ManyError;
+use many_protocol::ManyUrl;
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// How long a challenge can be used after it was issued, by default.
+pub const DEFAULT_CHALLENGE_TTL: Duration = Duration::from_secs(120);
+
+/// How many challenges can be pending for an origin, by default.
+pub const DEFAULT_PENDING_PER_ORIGIN: usize = 1_000;
+
+const NONCE_SIZE: usize = 32;
+
+/// A nonce for a WebAuthn challenge, and the time it expires at.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IssuedChallenge {
+    pub nonce: Vec<u8>,
+    pub expires: SystemTime,
+}
+
+struct Pending {
+    origin: ManyUrl,
+    expires: SystemTime,
+}
+
+#[derive(Default)]
+struct Inner {
+    pending: BTreeMap<Vec<u8>, Pending>,
+    per_origin: BTreeMap<ManyUrl, usize>,
+}
+
+impl Inner {
+    fn remove(&mut self, nonce: &[u8]) -> Option<Pending> {
+        let pending = self.pending.remove(nonce)?;
+        if let Some(count) = self.per_origin.get_mut(&pending.origin) {
+            *count -= 1;
+            if *count == 0 {
+                self.per_origin.remove(&pending.origin);
+            }
+        }
+        Some(pending)
+    }
+
+    fn remove_expired(&mut self, now: SystemTime) {
+        let expired: Vec<Vec<u8>> = self
+            .pending
+            .iter()
+            .filter(|(_, p)| p.expires <= now)
+            .map(|(nonce, _)| nonce.clone())
+            .collect();
+        for nonce in expired {
+            self.remove(&nonce);
+        }
+    }
+}
+
+/// The nonces of the WebAuthn challenges issued by a server. A WebAuthn
+/// envelope verified with a [`WebAuthnVerifier`](crate::WebAuthnVerifier)
+/// using this server must be signed over a challenge containing one of
+/// those nonces, issued for the origin of the envelope. Each nonce is used
+/// once, so WebAuthn signatures cannot be replayed.
+///
+/// Cloning the server shares its nonces, e.g. between the verifier and the
+/// endpoint issuing them. Envelopes must only be verified by the verifier
+/// using it once.
+#[derive(Clone)]
+pub struct ChallengeServer {
+    allowed_origins: Option<Vec<ManyUrl>>,
+    ttl: Duration,
+    pending_per_origin: usize,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Debug for ChallengeServer {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ChallengeServer")
+            .field("allowed_origins", &self.allowed_origins)
+            .field("ttl", &self.ttl)
+            .field("pending_per_origin", &self.pending_per_origin)
+            .finish()
+    }
+}
+
+impl ChallengeServer {
+    /// Issue challenges for these origins, or for any origin if `None`.
+    pub fn new(allowed_origins: Option<Vec<ManyUrl>>) -> Self {
+        Self {
+            allowed_origins,
+            ttl: DEFAULT_CHALLENGE_TTL,
+            pending_per_origin: DEFAULT_PENDING_PER_ORIGIN,
+            inner: Default::default(),
+        }
+    }
+
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Limit the number of challenges pending for an origin. New challenges
+    /// are refused until the pending ones are used or expire.
+    pub fn with_pending_per_origin(mut self, limit: usize) -> Self {
+        self.pending_per_origin = limit;
+        self
+    }
+
+    pub fn issue(&self, origin: &ManyUrl) -> Result<IssuedChallenge, ManyError> {
+        if let Some(urls) = &self.allowed_origins {
+            if !urls.contains(origin) {
+                return Err(ManyError::unknown("Origin not allowed"));
+            }
+        }
+
+        let now = SystemTime::now();
+        let mut inner = self.inner.lock().unwrap();
+        inner.remove_expired(now);
+        if inner.per_origin.get(origin).copied().unwrap_or(0) >= self.pending_per_origin {
+            return Err(ManyError::unknown(format!(
+                "Too many pending challenges for origin {origin}"
+            )));
+        }
+
+        let mut nonce = vec![0; NONCE_SIZE];
+        openssl::rand::rand_bytes(&mut nonce).map_err(ManyError::unknown)?;
+        let expires = now + self.ttl;
+        inner.pending.insert(
+            nonce.clone(),
+            Pending {
+                origin: origin.clone(),
+                expires,
+            },
+        );
+        *inner.per_origin.entry(origin.clone()).or_default() += 1;
+
+        Ok(IssuedChallenge { nonce, expires })
+    }
+
+    /// Use a nonce for the origin of a WebAuthn envelope. The nonce cannot
+    /// be used again, even if it was issued for another origin.
+    pub fn consume(&self, nonce: &[u8], origin: &ManyUrl) -> Result<(), ManyError> {
+        let pending = self.inner.lock().unwrap().remove(nonce).ok_or_else(|| {
+            ManyError::could_not_verify_signature("Unknown or already used challenge")
+        })?;
+
+        if pending.expires <= SystemTime::now() {
+            Err(ManyError::could_not_verify_signature("Expired challenge"))
+        } else if &pending.origin != origin {
+            Err(ManyError::could_not_verify_signature(
+                "Challenge issued for another origin",
+            ))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn url(s: &str) -> ManyUrl {
+        ManyUrl::parse(s).unwrap()
+    }
+
+    #[test]
+    fn single_use() {
+        let server = ChallengeServer::new(None);
+        let origin = url("https://localhost:3000");
+        let challenge = server.issue(&origin).unwrap();
+        assert_eq!(challenge.nonce.len(), NONCE_SIZE);
+
+        assert!(server.consume(&challenge.nonce, &origin).is_ok());
+        assert!(server.consume(&challenge.nonce, &origin).is_err());
+        assert!(server.consume(b"never issued", &origin).is_err());
+    }
+
+    #[test]
+    fn origin_binding() {
+        let origin = url("https://localhost:3000");
+        let server = ChallengeServer::new(Some(vec![origin.clone()]));
+        assert!(server.issue(&url("https://example.com")).is_err());
+
+        let challenge = server.issue(&origin).unwrap();
+        assert!(server
+            .consume(&challenge.nonce, &url("https://example.com"))
+            .is_err());
+        // The nonce was used by the failed attempt.
+        assert!(server.consume(&challenge.nonce, &origin).is_err());
+    }
+
+    #[test]
+    fn expiry_and_limit() {
+        let origin = url("https://localhost:3000");
+        let server = ChallengeServer::new(None).with_pending_per_origin(2);
+        let first = server.issue(&origin).unwrap();
+        server.issue(&origin).unwrap();
+        assert!(server.issue(&origin).is_err());
+        // Other origins have their own limit.
+        assert!(server.issue(&url("https://example.com")).is_ok());
+
+        server.consume(&first.nonce, &origin).unwrap();
+        assert!(server.issue(&origin).is_ok());
+
+        let server = ChallengeServer::new(None).with_ttl(Duration::ZERO);
+        let challenge = server.issue(&origin).unwrap();
+        assert!(server.consume(&challenge.nonce, &origin).is_err());
+    }
+}
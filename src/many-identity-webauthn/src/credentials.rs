@@ -0,0 +1,11 @@
+use many_identity::Address;
+use std::fmt::Debug;
+
+/// The WebAuthn credentials registered for identities, so an identity can
+/// sign with any of its credentials (e.g. a phone and a security key), and
+/// not only with the key its address was derived from.
+pub trait WebAuthnCredentials: Debug + Send + Sync {
+    /// Whether the key with this address is a credential of the identity,
+    /// and was not revoked. Implementations should fail closed.
+    fn can_sign(&self, identity: &Address, key: &Address) -> bool;
+}
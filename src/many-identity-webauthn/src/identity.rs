@@ -45,6 +45,13 @@ impl WebAuthnIdentity {
             rp_id,
         })
     }
+
+    /// Sign for an identity this credential was added to, instead of the
+    /// identity of its key. See `idstore.addCredential`.
+    pub fn for_identity(mut self, address: Address) -> Self {
+        self.address = address;
+        self
+    }
 }
 
 impl Identity for WebAuthnIdentity {
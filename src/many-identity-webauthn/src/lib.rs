@@ -3,6 +3,15 @@ extern crate core;
 // Do not expose this. There's no need to know the internal works.
 mod challenge;
 
+mod attestation;
+pub use attestation::*;
+
+mod challenge_server;
+pub use challenge_server::*;
+
+mod credentials;
+pub use credentials::*;
+
 mod verifier;
 pub use verifier::*;
 
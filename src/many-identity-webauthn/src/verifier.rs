@@ -1,4 +1,6 @@
+use crate::attestation::AttestationPolicy;
 use crate::challenge::Challenge;
+use crate::{ChallengeServer, WebAuthnCredentials};
 use base64::{engine::general_purpose, Engine as _};
 use coset::cbor::value::Value;
 use coset::{CborSerializable, CoseKey, CoseKeySet, CoseSign1, Label};
@@ -8,6 +10,7 @@ use many_protocol::ManyUrl;
 use serde::{Deserialize, Serialize};
 use sha2::digest::Digest;
 use std::collections::BTreeMap;
+use std::sync::Arc;
 
 /// WebAuthn ClientData, in JSON.
 #[derive(Deserialize, Serialize)]
@@ -21,11 +24,40 @@ struct ClientData {
 #[derive(Clone, Debug, Default)]
 pub struct WebAuthnVerifier {
     allowed_origins: Option<Vec<ManyUrl>>,
+    attestation_policy: Option<AttestationPolicy>,
+    credentials: Option<Arc<dyn WebAuthnCredentials>>,
+    challenges: Option<ChallengeServer>,
 }
 
 impl WebAuthnVerifier {
     pub fn new(allowed_origins: Option<Vec<ManyUrl>>) -> Self {
-        Self { allowed_origins }
+        Self {
+            allowed_origins,
+            attestation_policy: None,
+            credentials: None,
+            challenges: None,
+        }
+    }
+
+    /// Require envelopes to carry an attestation statement valid for this policy.
+    pub fn with_attestation_policy(mut self, policy: AttestationPolicy) -> Self {
+        self.attestation_policy = Some(policy);
+        self
+    }
+
+    /// Only accept the envelopes signed by a credential of their identity.
+    /// Without credentials, the key of the envelope is trusted to be the
+    /// one of its identity.
+    pub fn with_credentials(mut self, credentials: impl WebAuthnCredentials + 'static) -> Self {
+        self.credentials = Some(Arc::new(credentials));
+        self
+    }
+
+    /// Only accept the WebAuthn envelopes whose challenge has a nonce issued
+    /// by this server, for their origin. See [`ChallengeServer`].
+    pub fn with_challenge_server(mut self, challenges: ChallengeServer) -> Self {
+        self.challenges = Some(challenges);
+        self
     }
 
     pub fn get_keyset(&self, sign1: &CoseSign1) -> Option<CoseKeySet> {
@@ -140,8 +172,53 @@ impl WebAuthnVerifier {
         let cose_sig = signature;
         tracing::trace!("Verifying WebAuthn signature");
 
-        let key = many_identity_dsa::ecdsa::EcDsaVerifier::from_key(&key)?;
-        key.verify_signature(cose_sig, &msg)?;
+        let verifier = many_identity_dsa::ecdsa::EcDsaVerifier::from_key(&key)?;
+        verifier.verify_signature(cose_sig, &msg)?;
+
+        if let Some(policy) = &self.attestation_policy {
+            tracing::trace!("Getting `attestationObject` from unprotected header");
+            let attestation_object = unprotected
+                .get(&Label::Text("attestationObject".to_string()))
+                .ok_or_else(|| {
+                    ManyError::unknown("`attestationObject` entry missing from unprotected header")
+                })?
+                .as_bytes()
+                .ok_or_else(|| ManyError::unknown("`attestationObject` entry is not Bytes"))?;
+
+            tracing::trace!("Getting `attestationClientDataHash` from unprotected header");
+            let client_data_hash = unprotected
+                .get(&Label::Text("attestationClientDataHash".to_string()))
+                .ok_or_else(|| {
+                    ManyError::unknown(
+                        "`attestationClientDataHash` entry missing from unprotected header",
+                    )
+                })?
+                .as_bytes()
+                .ok_or_else(|| {
+                    ManyError::unknown("`attestationClientDataHash` entry is not Bytes")
+                })?;
+
+            if auth_data.len() < 32 {
+                return Err(ManyError::unknown("`authData` entry is too short"));
+            }
+
+            tracing::trace!("Verifying authenticator attestation");
+            policy.verify(
+                attestation_object,
+                client_data_hash,
+                &key,
+                &auth_data[0..32],
+            )?;
+        }
+
+        // Last, so only valid signatures use their nonce.
+        if let Some(challenges) = &self.challenges {
+            tracing::trace!("Verifying the nonce of `challenge`");
+            let nonce = challenge
+                .nonce()
+                .ok_or_else(|| ManyError::unknown("`challenge` is missing a nonce"))?;
+            challenges.consume(nonce, &origin)?;
+        }
 
         tracing::trace!("WebAuthn verifications succedded!");
         Ok(())
@@ -164,6 +241,14 @@ impl WebAuthnVerifier {
                 let key = self.get_cose_key_for_identity(sign1, &id).ok_or_else(|| {
                     ManyError::unknown("Could not find a public key in the envelope")
                 })?;
+                if let Some(credentials) = &self.credentials {
+                    let key_address = many_identity_dsa::ecdsa::address(&key)?;
+                    if !credentials.can_sign(&id, &key_address) {
+                        return Err(ManyError::could_not_verify_signature(format!(
+                            "The key {key_address} is not a credential of {id}"
+                        )));
+                    }
+                }
                 let protected = BTreeMap::from_iter(sign1.protected.header.rest.clone());
                 if protected.contains_key(&Label::Text("webauthn".to_string())) {
                     let unprotected = BTreeMap::from_iter(sign1.unprotected.rest.clone());
@@ -447,6 +532,58 @@ mod tests {
         );
     }
 
+    #[test]
+    fn webauthn_attestation_missing() {
+        let verifier =
+            WebAuthnVerifier::new(None).with_attestation_policy(AttestationPolicy::new(vec![]));
+        assert_eq!(
+            verifier.verify_1(&ENVELOPE).map_err(|e| e.to_string()),
+            Err(
+                "Unknown error: `attestationObject` entry missing from unprotected header"
+                    .to_string()
+            )
+        );
+    }
+
+    /// Credentials allowing all the keys to sign, or none of them.
+    #[derive(Debug)]
+    struct AllCredentials(bool);
+
+    impl WebAuthnCredentials for AllCredentials {
+        fn can_sign(&self, _identity: &Address, _key: &Address) -> bool {
+            self.0
+        }
+    }
+
+    #[test]
+    fn webauthn_credentials() {
+        let verifier = WebAuthnVerifier::new(None).with_credentials(AllCredentials(true));
+        assert!(verifier.verify_1(&ENVELOPE).is_ok());
+
+        let verifier = WebAuthnVerifier::new(None).with_credentials(AllCredentials(false));
+        let id = Address::from_str("mag7naerft2o3czjj6edvfkm6m3ahhdc3zpaaqjz2j7pvvlyf5").unwrap();
+        let key = many_identity_dsa::ecdsa::address(
+            &verifier.get_cose_key_for_identity(&ENVELOPE, &id).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(
+            verifier.verify_1(&ENVELOPE).map_err(|e| e.to_string()),
+            Err(format!(
+                "Could not verify the signature: The key {key} is not a credential of {id}."
+            ))
+        );
+    }
+
+    #[test]
+    fn webauthn_challenge_server() {
+        let verifier =
+            WebAuthnVerifier::new(None).with_challenge_server(ChallengeServer::new(None));
+        assert_eq!(
+            verifier.verify_1(&ENVELOPE).map_err(|e| e.to_string()),
+            Err("Unknown error: `challenge` is missing a nonce".to_string())
+        );
+    }
+
     #[test]
     fn webauthn_tamper_protected_header() {
         run_error(
@@ -0,0 +1,219 @@
+//! Compose multiple identities or verifiers into a single one, with fallback.
+use crate::{Address, Identity, Verifier};
+use coset::{CoseKey, CoseSign1};
+use many_error::ManyError;
+use tracing::{debug, warn};
+
+/// An identity backed by multiple implementations of the same key, tried in
+/// order when signing. This allows for example to sign with an HSM, and fall
+/// back to a PEM file of the same key if the HSM is unavailable.
+///
+/// All identities in the chain must share the same address.
+pub struct IdentityChain {
+    identities: Vec<Box<dyn Identity>>,
+}
+
+impl IdentityChain {
+    pub fn new(primary: impl Identity + 'static) -> Self {
+        Self {
+            identities: vec![Box::new(primary)],
+        }
+    }
+
+    /// Add an identity to try if all the previous ones failed to sign.
+    pub fn with_fallback(mut self, fallback: impl Identity + 'static) -> Result<Self, ManyError> {
+        let (address, fallback_address) = (self.address(), fallback.address());
+        if address != fallback_address {
+            return Err(ManyError::unknown(format!(
+                "Fallback identity {fallback_address} does not match {address}."
+            )));
+        }
+        self.identities.push(Box::new(fallback));
+        Ok(self)
+    }
+}
+
+impl Identity for IdentityChain {
+    fn address(&self) -> Address {
+        self.identities[0].address()
+    }
+
+    fn public_key(&self) -> Option<CoseKey> {
+        self.identities.iter().find_map(|i| i.public_key())
+    }
+
+    fn sign_1(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+        let mut errs = Vec::new();
+        for (i, identity) in self.identities.iter().enumerate() {
+            match identity.sign_1(envelope.clone()) {
+                Ok(envelope) => {
+                    debug!("Envelope signed by identity #{i} of the chain");
+                    return Ok(envelope);
+                }
+                Err(e) => {
+                    warn!("Identity #{i} of the chain could not sign: {e}");
+                    errs.push(e.to_string());
+                }
+            }
+        }
+
+        Err(ManyError::unknown(format!(
+            "No identity could sign the envelope: {}",
+            errs.join(", ")
+        )))
+    }
+}
+
+/// A list of verifiers tried in order; the first one to accept an envelope
+/// resolves its address. Unlike tuples of verifiers, the list can be built at
+/// runtime, e.g. from a configuration file.
+#[derive(Default)]
+pub struct VerifierChain {
+    verifiers: Vec<Box<dyn Verifier>>,
+}
+
+impl VerifierChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a verifier with a lower precedence than the previous ones.
+    pub fn with(mut self, verifier: impl Verifier + 'static) -> Self {
+        self.verifiers.push(Box::new(verifier));
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.verifiers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.verifiers.is_empty()
+    }
+}
+
+impl Verifier for VerifierChain {
+    fn verify_1(&self, envelope: &CoseSign1) -> Result<Address, ManyError> {
+        let mut errs = Vec::new();
+        for verifier in &self.verifiers {
+            match verifier.verify_1(envelope) {
+                Ok(a) => return Ok(a),
+                Err(e) => errs.push(e.to_string()),
+            }
+        }
+
+        Err(ManyError::could_not_verify_signature(errs.join(", ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+    use crate::verifiers::AnonymousVerifier;
+    use crate::AcceptAllVerifier;
+    use coset::CoseSign1Builder;
+
+    /// An identity that marks the envelopes it signs with its name.
+    struct TestIdentity {
+        address: Address,
+        name: &'static str,
+        fails: bool,
+    }
+
+    impl Identity for TestIdentity {
+        fn address(&self) -> Address {
+            self.address
+        }
+
+        fn public_key(&self) -> Option<CoseKey> {
+            None
+        }
+
+        fn sign_1(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+            if self.fails {
+                Err(ManyError::unknown(format!("{} is unavailable", self.name)))
+            } else {
+                Ok(CoseSign1 {
+                    signature: self.name.as_bytes().to_vec(),
+                    ..envelope
+                })
+            }
+        }
+    }
+
+    fn test_identity(name: &'static str, fails: bool) -> TestIdentity {
+        TestIdentity {
+            address: identity(1),
+            name,
+            fails,
+        }
+    }
+
+    #[test]
+    fn identity_fallback() {
+        let chain = IdentityChain::new(test_identity("hsm", true))
+            .with_fallback(test_identity("pem", false))
+            .unwrap();
+        assert_eq!(chain.address(), identity(1));
+
+        let envelope = chain.sign_1(CoseSign1Builder::new().build()).unwrap();
+        assert_eq!(envelope.signature, b"pem");
+    }
+
+    #[test]
+    fn identity_precedence() {
+        let chain = IdentityChain::new(test_identity("hsm", false))
+            .with_fallback(test_identity("pem", false))
+            .unwrap();
+
+        let envelope = chain.sign_1(CoseSign1Builder::new().build()).unwrap();
+        assert_eq!(envelope.signature, b"hsm");
+    }
+
+    #[test]
+    fn identity_all_fail() {
+        let chain = IdentityChain::new(test_identity("hsm", true))
+            .with_fallback(test_identity("pem", true))
+            .unwrap();
+
+        let err = chain.sign_1(CoseSign1Builder::new().build()).unwrap_err();
+        assert!(err.to_string().contains("hsm is unavailable"));
+        assert!(err.to_string().contains("pem is unavailable"));
+    }
+
+    #[test]
+    fn identity_address_mismatch() {
+        let other = TestIdentity {
+            address: identity(2),
+            name: "other",
+            fails: false,
+        };
+        assert!(IdentityChain::new(test_identity("hsm", false))
+            .with_fallback(other)
+            .is_err());
+    }
+
+    #[test]
+    fn verifier_precedence() {
+        let envelope = CoseSign1Builder::new()
+            .protected(
+                coset::HeaderBuilder::new()
+                    .key_id(identity(1).to_vec())
+                    .build(),
+            )
+            .build();
+
+        // Anonymous refuses envelopes with a key id, so the next one is used.
+        let chain = VerifierChain::new()
+            .with(AnonymousVerifier)
+            .with(AcceptAllVerifier);
+        assert_eq!(chain.verify_1(&envelope).unwrap(), identity(1));
+
+        assert!(VerifierChain::new()
+            .with(AnonymousVerifier)
+            .verify_1(&envelope)
+            .is_err());
+        assert!(VerifierChain::new().verify_1(&envelope).is_err());
+    }
+}
@@ -4,8 +4,14 @@ pub use address::{Address, MAX_SUBRESOURCE_ID};
 mod identity;
 pub use identity::*;
 
+pub mod chain;
+pub use chain::{IdentityChain, VerifierChain};
+
 pub mod cose;
 
+#[cfg(all(feature = "coset", feature = "minicbor"))]
+pub mod rotation;
+
 #[cfg(feature = "testing")]
 pub mod testing {
     use super::Address;
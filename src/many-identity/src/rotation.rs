@@ -0,0 +1,370 @@
+//! Key rotation for identities. An identity can replace its key by a new one
+//! while keeping its address, by publishing a continuity proof signed by both
+//! its current key and the new key. Once rotated, signatures of the previous
+//! keys are rejected.
+use crate::{Address, Identity, Verifier};
+use coset::{CborSerializable, CoseKey, CoseSign1, CoseSign1Builder, TaggedCborSerializable};
+use many_error::ManyError;
+use minicbor::{Decode, Encode};
+
+fn sign_payload(identity: &impl Identity, payload: &[u8]) -> Result<Vec<u8>, ManyError> {
+    identity
+        .sign_1(CoseSign1Builder::new().payload(payload.to_vec()).build())?
+        .to_tagged_vec()
+        .map_err(ManyError::serialization_error)
+}
+
+/// A record replacing the key of an identity by a new key.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct KeyRotation {
+    /// The address kept by the identity.
+    #[n(0)]
+    pub identity: Address,
+
+    /// The number of rotations of the identity, starting at 1.
+    #[n(1)]
+    pub sequence: u64,
+
+    /// The address of the key being replaced. For the first rotation, this
+    /// is the address of the identity itself.
+    #[n(2)]
+    pub previous_key: Address,
+
+    /// The address of the new key.
+    #[n(3)]
+    pub new_key: Address,
+}
+
+impl KeyRotation {
+    /// The first rotation of an identity, away from the key of its address.
+    pub fn first(identity: Address, new_key: Address) -> Self {
+        Self {
+            identity,
+            sequence: 1,
+            previous_key: identity,
+            new_key,
+        }
+    }
+
+    /// The rotation following this one.
+    pub fn next(&self, new_key: Address) -> Self {
+        Self {
+            identity: self.identity,
+            sequence: self.sequence + 1,
+            previous_key: self.new_key,
+            new_key,
+        }
+    }
+
+    /// Sign the rotation with the key being replaced and the new key.
+    pub fn sign(
+        &self,
+        previous: &impl Identity,
+        next: &impl Identity,
+    ) -> Result<ContinuityProof, ManyError> {
+        if previous.address() != self.previous_key || next.address() != self.new_key {
+            return Err(ManyError::unknown(
+                "The keys do not match the ones of the rotation.",
+            ));
+        }
+
+        let payload = minicbor::to_vec(self).map_err(ManyError::serialization_error)?;
+        Ok(ContinuityProof {
+            previous: sign_payload(previous, &payload)?,
+            next: sign_payload(next, &payload)?,
+        })
+    }
+
+    /// Check that this rotation directly follows the latest rotation of the
+    /// identity, if any.
+    pub fn follows(&self, latest: Option<&KeyRotation>) -> Result<(), ManyError> {
+        let expected = match latest {
+            Some(latest) if latest.identity != self.identity => {
+                return Err(ManyError::unknown(format!(
+                    "The rotation is of {}, not {}.",
+                    self.identity, latest.identity
+                )));
+            }
+            Some(latest) => latest.next(self.new_key),
+            None => KeyRotation::first(self.identity, self.new_key),
+        };
+
+        if self.sequence != expected.sequence {
+            Err(ManyError::unknown(format!(
+                "Expected rotation #{} of {}, got #{}.",
+                expected.sequence, self.identity, self.sequence
+            )))
+        } else if self.previous_key != expected.previous_key {
+            Err(ManyError::unknown(format!(
+                "The current key of {} is {}, not {}.",
+                self.identity, expected.previous_key, self.previous_key
+            )))
+        } else if self.new_key == self.previous_key || self.new_key == self.identity {
+            Err(ManyError::unknown("The new key must be a new key."))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// A [`KeyRotation`] signed by both the key being replaced, so only the
+/// identity can rotate its key, and the new key, so an identity cannot claim
+/// the key of someone else.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ContinuityProof {
+    /// The rotation signed by the previous key, as a tagged COSE_Sign1.
+    #[cbor(n(0), with = "minicbor::bytes")]
+    pub previous: Vec<u8>,
+
+    /// The rotation signed by the new key, as a tagged COSE_Sign1.
+    #[cbor(n(1), with = "minicbor::bytes")]
+    pub next: Vec<u8>,
+}
+
+impl ContinuityProof {
+    /// Verify both signatures, and return the rotation they sign.
+    pub fn verify(&self, verifier: &impl Verifier) -> Result<KeyRotation, ManyError> {
+        let decode = |bytes: &[u8]| -> Result<(Address, Vec<u8>), ManyError> {
+            let envelope = CoseSign1::from_tagged_slice(bytes)
+                .or_else(|_| CoseSign1::from_slice(bytes))
+                .map_err(ManyError::deserialization_error)?;
+            let signer = verifier.verify_1(&envelope)?;
+            Ok((signer, envelope.payload.unwrap_or_default()))
+        };
+        let (previous_signer, previous_payload) = decode(&self.previous)?;
+        let (next_signer, next_payload) = decode(&self.next)?;
+
+        if previous_payload != next_payload {
+            return Err(ManyError::unknown(
+                "The signatures are not of the same rotation.",
+            ));
+        }
+        let rotation: KeyRotation =
+            minicbor::decode(&previous_payload).map_err(ManyError::deserialization_error)?;
+
+        if previous_signer != rotation.previous_key {
+            return Err(ManyError::unknown(format!(
+                "The rotation must be signed by {}, not {previous_signer}.",
+                rotation.previous_key
+            )));
+        }
+        if next_signer != rotation.new_key {
+            return Err(ManyError::unknown(format!(
+                "The rotation must be signed by {}, not {next_signer}.",
+                rotation.new_key
+            )));
+        }
+        Ok(rotation)
+    }
+}
+
+/// The rotations known to a verifier.
+pub trait KeyRotations: Send {
+    /// The identity a key currently signs for, if it replaced the key of an
+    /// identity.
+    fn identity_of_key(&self, key: &Address) -> Option<Address>;
+
+    /// Whether a key was replaced, and cannot sign anymore.
+    fn is_retired(&self, key: &Address) -> bool;
+}
+
+/// A verifier resolving the envelopes signed by the current key of a rotated
+/// identity to the address of the identity, and rejecting the envelopes
+/// signed by replaced keys.
+pub struct RotatingVerifier<V, R> {
+    inner: V,
+    rotations: R,
+}
+
+impl<V: Verifier, R: KeyRotations> RotatingVerifier<V, R> {
+    pub fn new(inner: V, rotations: R) -> Self {
+        Self { inner, rotations }
+    }
+}
+
+impl<V: Verifier, R: KeyRotations> Verifier for RotatingVerifier<V, R> {
+    fn verify_1(&self, envelope: &CoseSign1) -> Result<Address, ManyError> {
+        let key = self.inner.verify_1(envelope)?;
+        if key.is_anonymous() {
+            return Ok(key);
+        }
+
+        if let Some(identity) = self.rotations.identity_of_key(&key) {
+            Ok(identity)
+        } else if self.rotations.is_retired(&key) {
+            Err(ManyError::could_not_verify_signature(format!(
+                "The key {key} was rotated and cannot sign anymore."
+            )))
+        } else {
+            Ok(key)
+        }
+    }
+}
+
+/// An identity signing with a key that replaced its original key. Envelopes
+/// are signed by the key, for the address of the identity.
+pub struct RotatedIdentity<I> {
+    identity: Address,
+    key: I,
+}
+
+impl<I: Identity> RotatedIdentity<I> {
+    pub fn new(identity: Address, key: I) -> Self {
+        Self { identity, key }
+    }
+}
+
+impl<I: Identity> Identity for RotatedIdentity<I> {
+    fn address(&self) -> Address {
+        self.identity
+    }
+
+    fn public_key(&self) -> Option<CoseKey> {
+        self.key.public_key()
+    }
+
+    fn sign_1(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+        self.key.sign_1(envelope)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::identity;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    /// Signs with its address as the signature.
+    struct TestKey(Address);
+
+    impl Identity for TestKey {
+        fn address(&self) -> Address {
+            self.0
+        }
+
+        fn public_key(&self) -> Option<CoseKey> {
+            None
+        }
+
+        fn sign_1(&self, mut envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+            envelope.protected.header.key_id = self.0.to_vec();
+            envelope.signature = self.0.to_vec();
+            Ok(envelope)
+        }
+    }
+
+    struct TestVerifier;
+
+    impl Verifier for TestVerifier {
+        fn verify_1(&self, envelope: &CoseSign1) -> Result<Address, ManyError> {
+            if envelope.signature != envelope.protected.header.key_id {
+                return Err(ManyError::could_not_verify_signature("Invalid signature"));
+            }
+            Address::from_bytes(&envelope.signature)
+        }
+    }
+
+    #[derive(Default)]
+    struct TestRotations {
+        keys: BTreeMap<Address, Address>,
+        retired: BTreeSet<Address>,
+    }
+
+    impl TestRotations {
+        fn apply(&mut self, rotation: &KeyRotation) {
+            self.keys.remove(&rotation.previous_key);
+            self.retired.insert(rotation.previous_key);
+            self.keys.insert(rotation.new_key, rotation.identity);
+        }
+    }
+
+    impl KeyRotations for TestRotations {
+        fn identity_of_key(&self, key: &Address) -> Option<Address> {
+            self.keys.get(key).copied()
+        }
+
+        fn is_retired(&self, key: &Address) -> bool {
+            self.retired.contains(key)
+        }
+    }
+
+    fn envelope(identity: &impl Identity) -> CoseSign1 {
+        identity.sign_1(CoseSign1Builder::new().build()).unwrap()
+    }
+
+    #[test]
+    fn continuity() {
+        let first = KeyRotation::first(identity(1), identity(2));
+        let proof = first
+            .sign(&TestKey(identity(1)), &TestKey(identity(2)))
+            .unwrap();
+        let proof: ContinuityProof = minicbor::decode(&minicbor::to_vec(&proof).unwrap()).unwrap();
+        assert_eq!(proof.verify(&TestVerifier).unwrap(), first);
+        assert!(first.follows(None).is_ok());
+
+        let second = first.next(identity(3));
+        assert_eq!(second.sequence, 2);
+        assert_eq!(second.previous_key, identity(2));
+        assert!(second.follows(Some(&first)).is_ok());
+        assert!(second.follows(None).is_err());
+        assert!(first.next(identity(1)).follows(Some(&first)).is_err());
+
+        // Skipping a rotation.
+        assert!(second.next(identity(4)).follows(Some(&first)).is_err());
+    }
+
+    #[test]
+    fn continuity_signatures() {
+        let rotation = KeyRotation::first(identity(1), identity(2));
+        assert!(rotation
+            .sign(&TestKey(identity(3)), &TestKey(identity(2)))
+            .is_err());
+
+        // A proof signed by another key than the previous one.
+        let other = KeyRotation {
+            previous_key: identity(3),
+            ..rotation.clone()
+        };
+        let mut proof = rotation
+            .sign(&TestKey(identity(1)), &TestKey(identity(2)))
+            .unwrap();
+        proof.previous = other
+            .sign(&TestKey(identity(3)), &TestKey(identity(2)))
+            .unwrap()
+            .previous;
+        assert!(proof.verify(&TestVerifier).is_err());
+
+        // A proof missing the signature of the new key.
+        let mut proof = rotation
+            .sign(&TestKey(identity(1)), &TestKey(identity(2)))
+            .unwrap();
+        proof.next = proof.previous.clone();
+        assert!(proof.verify(&TestVerifier).is_err());
+    }
+
+    #[test]
+    fn rotating_verifier() {
+        let mut rotations = TestRotations::default();
+        let first = KeyRotation::first(identity(1), identity(2));
+        rotations.apply(&first);
+
+        let verifier = RotatingVerifier::new(TestVerifier, rotations);
+        let rotated = RotatedIdentity::new(identity(1), TestKey(identity(2)));
+        assert_eq!(rotated.address(), identity(1));
+        assert_eq!(verifier.verify_1(&envelope(&rotated)).unwrap(), identity(1));
+        assert!(verifier.verify_1(&envelope(&TestKey(identity(1)))).is_err());
+        assert_eq!(
+            verifier.verify_1(&envelope(&TestKey(identity(5)))).unwrap(),
+            identity(5)
+        );
+
+        let mut rotations = verifier.rotations;
+        rotations.apply(&first.next(identity(3)));
+        let verifier = RotatingVerifier::new(TestVerifier, rotations);
+        assert!(verifier.verify_1(&envelope(&rotated)).is_err());
+        let rotated = RotatedIdentity::new(identity(1), TestKey(identity(3)));
+        assert_eq!(verifier.verify_1(&envelope(&rotated)).unwrap(), identity(1));
+    }
+}
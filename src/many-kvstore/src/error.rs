@@ -10,12 +10,22 @@ define_attribute_many_error!(
         5: pub fn subres_alt_unsupported() => "Subresource alternative owner unsupported.",
         6: pub fn key_not_found() => "The key was not found.",
         7: pub fn cannot_disable_empty_key() => "Unable to disable an empty key.",
+        8: pub fn transfer_offer_not_found() => "No transfer of this key was offered.",
+        9: pub fn transfer_offer_expired() => "The transfer offer of this key expired.",
+        10: pub fn transfer_offer_denied() => "The transfer of this key was offered to another identity.",
+        11: pub fn batch_too_large(count, limit)
+            => "The batch has {count} entries, the limit is {limit}.",
+        12: pub fn duplicate_batch_key() => "A key appears more than once in the batch.",
+        13: pub fn read_denied() => "You do not have the authorization to read this key.",
+        14: pub fn content_addressed_key() => "Content-addressed keys can only be put with kvstore.putCas.",
+        15: pub fn owner_index_inactive() => "The keys of each owner are not indexed yet.",
     }
 );
 
 define_application_many_error!(
     {
         1: pub fn storage_apply_failed(desc) => "Unable to apply change to persistent storage: {desc}.",
-        2: pub fn storage_get_failed(desc) => "Unable to get data from persistent storage: {desc}."
+        2: pub fn storage_get_failed(desc) => "Unable to get data from persistent storage: {desc}.",
+        3: pub fn unable_to_load_migrations(desc) => "Unable to load migrations: {desc}.",
     }
 );
@@ -1,3 +1,4 @@
 pub mod error;
+pub mod migration;
 pub mod module;
 pub mod storage;
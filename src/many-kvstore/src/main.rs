@@ -1,9 +1,13 @@
+use crate::migration::MIGRATIONS;
 use crate::module::account::AccountFeatureModule;
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::state_export::{ExportStateOpt, StateExport};
 use many_identity::verifiers::AnonymousVerifier;
-use many_identity::Address;
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity::{Address, Identity};
+use many_identity_dsa::CoseKeyVerifier;
 use many_identity_webauthn::WebAuthnVerifier;
+use many_migration::MigrationConfig;
 use many_modules::account::features::Feature;
 use many_modules::{abci_backend, account, events, kvstore};
 use many_protocol::ManyUrl;
@@ -17,19 +21,25 @@ use std::sync::{Arc, Mutex};
 use tracing::{debug, info};
 
 mod error;
+mod migration;
 mod module;
 mod storage;
 
 use module::*;
 
 #[derive(Debug, Parser)]
+#[clap(subcommand_negates_reqs(true))]
 struct Opts {
     #[clap(flatten)]
     common_flags: many_cli_helpers::CommonCliFlags,
 
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// The location of a PEM file for the identity of this server.
-    #[clap(long)]
-    pem: PathBuf,
+    // The field needs to be an Option for the clap derive to work properly.
+    #[clap(long, required = true)]
+    pem: Option<PathBuf>,
 
     /// The address and port to bind to for the MANY Http server.
     #[clap(long, short, default_value = "127.0.0.1:8000")]
@@ -43,9 +53,15 @@ struct Opts {
     #[clap(long)]
     state: Option<PathBuf>,
 
+    /// Path of a state export, as written by `export-state`, to create the
+    /// persistent store from instead of an initial state.
+    #[clap(long, conflicts_with = "state")]
+    import_state: Option<PathBuf>,
+
     /// Path to a persistent store database (rocksdb).
-    #[clap(long)]
-    persistent: PathBuf,
+    // The field needs to be an Option for the clap derive to work properly.
+    #[clap(long, required = true)]
+    persistent: Option<PathBuf>,
 
     /// Delete the persistent storage to start from a clean state.
     /// If this is not specified the initial state will not be used.
@@ -69,20 +85,47 @@ struct Opts {
     /// messages.
     #[clap(long)]
     cache_db: Option<PathBuf>,
+
+    /// The maximum number of entries of a batch request, e.g., batchPut.
+    /// This must be the same on all the nodes of a network.
+    #[clap(long, default_value_t = DEFAULT_BATCH_LIMIT)]
+    batch_limit: usize,
+
+    /// Path to a JSON file containing the configurations for the
+    /// migrations. Migrations are DISABLED unless this configuration file
+    /// is given.
+    #[clap(long, short)]
+    migrations_config: Option<PathBuf>,
+
+    /// List built-in migrations supported by this binary
+    #[clap(long, exclusive = true)]
+    list_migrations: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Export every key of the persistent store, e.g. the values and their
+    /// ACLs, to start another network from it with `--import-state`.
+    ExportState(ExportStateOpt),
 }
 
 fn main() {
     let Opts {
         common_flags,
+        command,
         pem,
         addr,
         abci,
         mut state,
+        mut import_state,
         persistent,
         clean,
         allow_addrs,
         allow_origin,
         cache_db,
+        batch_limit,
+        migrations_config,
+        list_migrations,
     } = Opts::parse();
 
     common_flags.init_logging().unwrap();
@@ -93,22 +136,62 @@ fn main() {
         git_sha = env!("VERGEN_GIT_SHA")
     );
 
+    if list_migrations {
+        for migration in MIGRATIONS {
+            println!("Name: {}", migration.name());
+            println!("Description: {}", migration.description());
+        }
+        return;
+    }
+
+    let persistent = persistent.expect("--persistent is required.");
+
+    if let Some(Command::ExportState(opt)) = command {
+        let module = KvStoreModuleImpl::load(None, &persistent, false)
+            .expect("Could not open the persistent store.");
+        let export = module.export_state().expect("Could not export the state.");
+        if let Some(height) = opt.height.filter(|h| *h != export.height) {
+            panic!("The store is at height {}, not {height}.", export.height);
+        }
+        export
+            .write(&opt.output, opt.format)
+            .expect("Could not write the state export.");
+        println!(
+            "Exported {} key(s) at height {}.",
+            export.entries.len(),
+            export.height
+        );
+        return;
+    }
+
     if clean {
         // Delete the persistent storage.
         let _ = std::fs::remove_dir_all(persistent.as_path());
     } else if persistent.exists() {
         // Initial state is ignored.
         state = None;
+        import_state = None;
     }
 
-    let key = CoseKeyIdentity::from_pem(std::fs::read_to_string(pem).unwrap()).unwrap();
+    // Safe unwrap, `--pem` is required to start the server.
+    let key = read_pem(pem.unwrap()).unwrap();
 
     let state = state.map(|state| {
         let content = std::fs::read_to_string(state).unwrap();
         json5::from_str(&content).unwrap()
     });
+    let import_state = import_state
+        .map(|path| StateExport::read(&path).expect("Could not read the state export."));
+
+    info!("Loading migrations from {migrations_config:?}");
+    let maybe_migrations = migrations_config.map(|file| {
+        let content = std::fs::read_to_string(file)
+            .expect("Could not read file passed to --migrations_config");
+        let config: MigrationConfig = serde_json::from_str(&content).unwrap();
+        config.strict().with_node(key.address())
+    });
 
-    let module = if persistent.exists() {
+    let mut module = if persistent.exists() {
         if state.is_some() {
             tracing::warn!(
                 r#"
@@ -119,12 +202,16 @@ fn main() {
             );
         }
 
-        KvStoreModuleImpl::load(persistent, abci).unwrap()
+        KvStoreModuleImpl::load(maybe_migrations, persistent, abci).unwrap()
+    } else if let Some(export) = import_state {
+        KvStoreModuleImpl::import(&export, maybe_migrations, persistent, abci)
+            .expect("Could not import the state export.")
     } else if let Some(state) = state {
-        KvStoreModuleImpl::new(state, persistent, abci).unwrap()
+        KvStoreModuleImpl::new(state, maybe_migrations, persistent, abci).unwrap()
     } else {
         panic!("Persistent store or staging file not found.")
     };
+    module.set_batch_limit(batch_limit);
 
     let module = Arc::new(Mutex::new(module));
 
@@ -158,10 +245,11 @@ fn main() {
 
         s.add_module(AccountFeatureModule::new(
             account::AccountModule::new(module.clone()),
-            [Feature::with_id(2)],
+            [Feature::with_id(2), Feature::with_id(6)],
         ));
         if abci {
             s.set_timeout(u64::MAX);
+            s.set_enforce_deadlines(false);
             s.add_module(abci_backend::AbciModule::new(module));
         }
 
@@ -0,0 +1,12 @@
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::{InnerMigration, MigrationSet};
+
+pub mod owner_index;
+
+pub type KvStoreMigrations = MigrationSet<'static, merk::Merk>;
+
+// This is the global migration registry
+// Doesn't contain any metadata
+#[distributed_slice]
+pub static MIGRATIONS: [InnerMigration<merk::Merk, ManyError>] = [..];
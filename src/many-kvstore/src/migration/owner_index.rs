@@ -0,0 +1,19 @@
+use crate::migration::MIGRATIONS;
+use crate::storage::index_all_owners;
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn initialize(storage: &mut merk::Merk, _: &HashMap<String, Value>) -> Result<(), ManyError> {
+    index_all_owners(storage)
+}
+
+#[distributed_slice(MIGRATIONS)]
+pub static OWNER_INDEX_MIGRATION: InnerMigration<merk::Merk, ManyError> =
+    InnerMigration::new_initialize(
+        initialize,
+        "Owner Index Migration",
+        "Index the keys of each owner, and keep the index up to date for kvstore.listByOwner",
+    );
@@ -1,22 +1,29 @@
 use crate::{
     error,
-    storage::{AclMap, KvStoreStorage},
+    storage::{AclMap, KvStoreStorage, TransferOffer},
 };
+use many_cli_helpers::state_export::StateExport;
 use many_error::{ManyError, Reason};
 use many_identity::Address;
+use many_migration::MigrationConfig;
 use many_modules::abci_backend::{
-    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, InitChainReturn,
-    ManyAbciModuleBackend,
+    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, GasCost,
+    InitChainReturn, ManyAbciModuleBackend,
 };
 use many_modules::account::Role;
-use many_modules::kvstore::list::{ListArgs, ListReturns};
+use many_modules::kvstore;
+use many_modules::kvstore::list::{ListArgs, ListByOwnerArgs, ListByOwnerReturns, ListReturns};
 use many_modules::kvstore::{
-    DisableArgs, DisableReturn, GetArgs, GetReturns, InfoArg, InfoReturns,
-    KvStoreCommandsModuleBackend, KvStoreModuleBackend, KvStoreTransferModuleBackend, PutArgs,
-    PutReturn, QueryArgs, QueryReturns, TransferArgs, TransferReturn,
+    AcceptTransferArgs, AcceptTransferReturn, BatchDeleteArgs, BatchDeleteReturn, BatchGetArgs,
+    BatchGetReturns, BatchPutArgs, BatchPutReturn, DisableArgs, DisableReturn, GetArgs, GetReturns,
+    InfoArg, InfoReturns, KeyAcl, KvStoreCommandsModuleBackend, KvStoreModuleBackend,
+    KvStoreTransferModuleBackend, OfferTransferArgs, OfferTransferReturn, PutArgs, PutCasArgs,
+    PutCasReturn, PutReturn, QueryArgs, QueryReturns, SetAclArgs, SetAclReturn, TransferArgs,
+    TransferReturn,
 };
+use many_types::clock::Clock;
 use many_types::{Either, Timestamp};
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::Debug;
 use std::path::Path;
 use tracing::info;
@@ -31,12 +38,23 @@ pub struct InitialStateJson {
     acl: AclMap,
     identity: Address,
     hash: Option<String>,
+
+    /// The maximum gas the transactions of a block can use. See
+    /// [`AbciInit::block_gas_limit`].
+    block_gas_limit: Option<u64>,
 }
 
+/// The default maximum number of entries of a batch endpoint.
+pub const DEFAULT_BATCH_LIMIT: usize = 100;
+
+/// The maximum number of keys returned by `kvstore.listByOwner`.
+const MAXIMUM_LIST_BY_OWNER_COUNT: usize = 100;
+
 /// A simple kv-store.
 #[derive(Debug)]
 pub struct KvStoreModuleImpl {
     storage: KvStoreStorage,
+    batch_limit: usize,
 }
 
 /// The KvStoreMetadata mimics the QueryReturns structure but adds serde capabilities
@@ -53,6 +71,10 @@ pub struct KvStoreMetadata {
 
     #[n(2)]
     pub previous_owner: Option<Address>,
+
+    #[n(3)]
+    #[serde(skip_deserializing)]
+    pub acl: Option<KeyAcl>,
 }
 
 #[derive(Debug, serde::Deserialize, minicbor::Encode, minicbor::Decode)]
@@ -61,32 +83,41 @@ pub struct KvStoreMetadata {
 pub struct KvStoreMetadataWrapper(
     #[serde(with = "KvStoreMetadata")]
     #[n(0)]
-    QueryReturns,
+    pub(crate) QueryReturns,
 );
 
 impl KvStoreModuleImpl {
     pub fn load<P: AsRef<Path>>(
+        migration_config: Option<MigrationConfig>,
         persistent_store_path: P,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
-        let storage =
-            KvStoreStorage::load(persistent_store_path, blockchain).map_err(ManyError::unknown)?;
+        let storage = KvStoreStorage::load(persistent_store_path, blockchain, migration_config)
+            .map_err(ManyError::unknown)?;
+        tracing::debug!("Final migrations: {:?}", storage.migrations());
 
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            batch_limit: DEFAULT_BATCH_LIMIT,
+        })
     }
 
     pub fn new<P: AsRef<Path>>(
         initial_state: InitialStateJson,
+        migration_config: Option<MigrationConfig>,
         persistence_store_path: P,
         blockchain: bool,
     ) -> Result<Self, ManyError> {
         let storage = KvStoreStorage::new(
             initial_state.acl,
             initial_state.identity,
+            initial_state.block_gas_limit,
             persistence_store_path,
             blockchain,
         )
-        .map_err(ManyError::unknown)?;
+        .map_err(ManyError::unknown)?
+        .with_migrations(migration_config)?;
+        tracing::debug!("Final migrations: {:?}", storage.migrations());
 
         if let Some(h) = initial_state.hash {
             // Verify the hash.
@@ -101,7 +132,111 @@ impl KvStoreModuleImpl {
             hash = hex::encode(storage.hash()).as_str()
         );
 
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            batch_limit: DEFAULT_BATCH_LIMIT,
+        })
+    }
+
+    /// Create the persistent store from a state export, then load it.
+    pub fn import<P: AsRef<Path>>(
+        export: &StateExport,
+        migration_config: Option<MigrationConfig>,
+        persistence_store_path: P,
+        blockchain: bool,
+    ) -> Result<Self, ManyError> {
+        KvStoreStorage::import_state(&persistence_store_path, export)
+            .map_err(ManyError::unknown)?;
+        let module_impl = Self::load(migration_config, persistence_store_path, blockchain)?;
+
+        info!(
+            height = export.height,
+            exported_hash = hex::encode(&export.hash).as_str(),
+            hash = hex::encode(module_impl.storage.hash()).as_str()
+        );
+        Ok(module_impl)
+    }
+
+    /// Export every key of the committed store.
+    pub fn export_state(&self) -> Result<StateExport, ManyError> {
+        self.storage.export_state().map_err(ManyError::unknown)
+    }
+
+    /// Replace the source of the time used outside of blocks, e.g. by a
+    /// [`many_types::clock::TestClock`] in tests.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.storage.set_clock(clock);
+    }
+
+    /// Set the maximum number of entries of the batch endpoints. This must
+    /// be the same on all the nodes of a network.
+    pub fn set_batch_limit(&mut self, limit: usize) {
+        self.batch_limit = limit;
+    }
+
+    fn metadata(&self, key: &[u8]) -> Result<Option<KvStoreMetadata>, ManyError> {
+        self.storage
+            .get_metadata(key)?
+            .map(|cbor| {
+                minicbor::decode(&cbor).map_err(|e| ManyError::deserialization_error(e.to_string()))
+            })
+            .transpose()
+    }
+
+    /// Verify that `sender` can read the value of a key.
+    fn verify_read(&self, sender: &Address, key: &[u8]) -> Result<(), ManyError> {
+        match self.metadata(key)? {
+            Some(KvStoreMetadata {
+                owner,
+                acl: Some(acl),
+                ..
+            }) if !acl.can_read(&owner, sender) => Err(error::read_denied()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The metadata of a key after `sender` puts a value at it. Writers put
+    /// values on behalf of the owner, so the key keeps its owner and ACL.
+    fn verify_put(&self, sender: &Address, key: &[u8]) -> Result<KvStoreMetadata, ManyError> {
+        if kvstore::is_content_key(key) {
+            return Err(error::content_addressed_key());
+        }
+        let (owner, acl) = match self.metadata(key)? {
+            Some(meta) => {
+                let allowed = match &meta.acl {
+                    Some(acl) => acl.can_write(&meta.owner, sender),
+                    None => &meta.owner == sender,
+                };
+                if !allowed {
+                    return Err(error::permission_denied());
+                }
+                (meta.owner, meta.acl)
+            }
+            None => (*sender, None),
+        };
+        Ok(KvStoreMetadata {
+            owner,
+            disabled: Some(Either::Left(false)),
+            previous_owner: None,
+            acl,
+        })
+    }
+
+    /// Check the size of a batch, and that its keys are unique.
+    fn verify_batch<'a>(
+        &self,
+        keys: impl ExactSizeIterator<Item = &'a [u8]>,
+    ) -> Result<(), ManyError> {
+        if keys.len() > self.batch_limit {
+            return Err(error::batch_too_large(keys.len(), self.batch_limit));
+        }
+        let mut seen = BTreeSet::new();
+        for key in keys {
+            if !seen.insert(key) {
+                return Err(error::duplicate_batch_key());
+            }
+        }
+        Ok(())
     }
 }
 
@@ -116,9 +251,17 @@ impl ManyAbciModuleBackend for KvStoreModuleImpl {
                 ("kvstore.get".to_string(), EndpointInfo { is_command: false }),
                 ("kvstore.query".to_string(), EndpointInfo { is_command: false }),
                 ("kvstore.put".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.putCas".to_string(), EndpointInfo { is_command: true }),
                 ("kvstore.disable".to_string(), EndpointInfo { is_command: true }),
                 ("kvstore.transfer".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.offerTransfer".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.acceptTransfer".to_string(), EndpointInfo { is_command: true }),
                 ("kvstore.list".to_string(), EndpointInfo { is_command: false }),
+                ("kvstore.listByOwner".to_string(), EndpointInfo { is_command: false }),
+                ("kvstore.batchGet".to_string(), EndpointInfo { is_command: false }),
+                ("kvstore.batchPut".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.batchDelete".to_string(), EndpointInfo { is_command: true }),
+                ("kvstore.setAcl".to_string(), EndpointInfo { is_command: true }),
 
                 // Accounts
                 ("account.create".to_string(), EndpointInfo { is_command: true }),
@@ -134,7 +277,15 @@ impl ManyAbciModuleBackend for KvStoreModuleImpl {
                 // Events
                 ("events.info".to_string(), EndpointInfo { is_command: false }),
                 ("events.list".to_string(), EndpointInfo { is_command: false }),
+                ("events.types".to_string(), EndpointInfo { is_command: false }),
             ]),
+            // Storing a value costs more than the size of its envelope.
+            gas: Some(BTreeMap::from([
+                ("kvstore.put".to_string(), GasCost::new(10_000, 10)),
+                ("kvstore.putCas".to_string(), GasCost::new(10_000, 10)),
+                ("kvstore.batchPut".to_string(), GasCost::new(10_000, 10)),
+            ])),
+            block_gas_limit: self.storage.block_gas_limit()?,
         })
     }
 
@@ -193,7 +344,8 @@ impl KvStoreModuleBackend for KvStoreModuleImpl {
         Ok(InfoReturns { hash: hash.into() })
     }
 
-    fn get(&self, _sender: &Address, args: GetArgs) -> Result<GetReturns, ManyError> {
+    fn get(&self, sender: &Address, args: GetArgs) -> Result<GetReturns, ManyError> {
+        self.verify_read(sender, &args.key)?;
         let value = self.storage.get(&args.key)?;
         Ok(GetReturns {
             value: value.map(|x| x.into()),
@@ -219,6 +371,54 @@ impl KvStoreModuleBackend for KvStoreModuleImpl {
                 .collect(),
         })
     }
+
+    fn list_by_owner(
+        &self,
+        _sender: &Address,
+        args: ListByOwnerArgs,
+    ) -> Result<ListByOwnerReturns, ManyError> {
+        if !self.storage.owner_index_active() {
+            return Err(error::owner_index_inactive());
+        }
+        // A page has at least one key, so that `next` always moves forward.
+        let count = args.count.map_or(MAXIMUM_LIST_BY_OWNER_COUNT, |c| {
+            (c as usize).clamp(1, MAXIMUM_LIST_BY_OWNER_COUNT)
+        });
+
+        // Get one more key to know whether there is another page.
+        let mut keys = self
+            .storage
+            .list_by_owner(&args.owner, args.after.map(Into::into))
+            .take(count + 1)
+            .collect::<Result<Vec<_>, _>>()?;
+        let next = (keys.len() > count).then(|| keys[count - 1].clone().into());
+        keys.truncate(count);
+
+        Ok(ListByOwnerReturns {
+            keys: keys.into_iter().map(Into::into).collect(),
+            next,
+        })
+    }
+
+    fn batch_get(
+        &self,
+        sender: &Address,
+        args: BatchGetArgs,
+    ) -> Result<BatchGetReturns, ManyError> {
+        if args.keys.len() > self.batch_limit {
+            return Err(error::batch_too_large(args.keys.len(), self.batch_limit));
+        }
+        Ok(BatchGetReturns {
+            values: args
+                .keys
+                .iter()
+                .map(|key| {
+                    self.verify_read(sender, key)?;
+                    Ok(self.storage.get(key)?.map(|x| x.into()))
+                })
+                .collect::<Result<_, ManyError>>()?,
+        })
+    }
 }
 
 impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
@@ -232,6 +432,7 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             self.validate_alternative_owner(
                 sender,
                 &alternative_owner,
+                "kvstore.put",
                 [Role::CanKvStorePut, Role::Owner],
             )?;
             alternative_owner
@@ -239,15 +440,31 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             *sender
         };
 
-        self.verify_acl(&owner, &key)?;
+        let meta = self.verify_put(&owner, &key)?;
+        self.storage.put(&meta, &key, value.into())?;
+        Ok(PutReturn::default())
+    }
 
-        let meta = KvStoreMetadata {
-            owner,
-            disabled: Some(Either::Left(false)),
-            previous_owner: None,
+    fn put_cas(&mut self, sender: &Address, args: PutCasArgs) -> Result<PutCasReturn, ManyError> {
+        let PutCasArgs {
+            value,
+            alternative_owner,
+        } = args;
+        let owner = if let Some(alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                &alternative_owner,
+                "kvstore.putCas",
+                [Role::CanKvStorePut, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            *sender
         };
-        self.storage.put(&meta, &key, value.into())?;
-        Ok(PutReturn {})
+
+        let key = kvstore::content_key(&value);
+        let references = self.storage.put_cas(&owner, &key, value.into())?;
+        Ok(PutCasReturn { key, references })
     }
 
     fn disable(&mut self, sender: &Address, args: DisableArgs) -> Result<DisableReturn, ManyError> {
@@ -256,13 +473,15 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             alternative_owner,
             reason,
         } = args;
-        if self.storage.get(&key)?.is_none() {
+        let is_content_key = kvstore::is_content_key(&key);
+        if !is_content_key && self.storage.get(&key)?.is_none() {
             return Err(error::cannot_disable_empty_key());
         }
         let owner = if let Some(ref alternative_owner) = alternative_owner {
             self.validate_alternative_owner(
                 sender,
                 alternative_owner,
+                "kvstore.disable",
                 [Role::CanKvStoreDisable, Role::Owner],
             )?;
             alternative_owner
@@ -270,6 +489,13 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             sender
         };
 
+        // Content-addressed values are shared, disabling one only removes the
+        // reference of its owner.
+        if is_content_key {
+            self.storage.release_cas(owner, &key)?;
+            return Ok(DisableReturn::default());
+        }
+
         self.verify_acl(owner, &key)?;
 
         let maybe_reason = if let Some(reason) = reason {
@@ -282,10 +508,128 @@ impl KvStoreCommandsModuleBackend for KvStoreModuleImpl {
             owner: *owner,
             disabled: Some(maybe_reason),
             previous_owner: None,
+            acl: self.metadata(&key)?.and_then(|meta| meta.acl),
         };
 
         self.storage.disable(&meta, &key)?;
-        Ok(DisableReturn {})
+        Ok(DisableReturn::default())
+    }
+
+    fn batch_put(
+        &mut self,
+        sender: &Address,
+        args: BatchPutArgs,
+    ) -> Result<BatchPutReturn, ManyError> {
+        let BatchPutArgs {
+            entries,
+            alternative_owner,
+        } = args;
+        self.verify_batch(entries.iter().map(|entry| entry.key.as_slice()))?;
+        let owner = if let Some(alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                &alternative_owner,
+                "kvstore.batchPut",
+                [Role::CanKvStorePut, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            *sender
+        };
+
+        // Verify all the keys before changing any of them.
+        let metas = entries
+            .iter()
+            .map(|entry| self.verify_put(&owner, &entry.key))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.storage.put_batch(
+            metas
+                .into_iter()
+                .zip(entries)
+                .map(|(meta, entry)| (meta, entry.key.into(), entry.value.into()))
+                .collect(),
+        )?;
+        Ok(BatchPutReturn::default())
+    }
+
+    fn batch_delete(
+        &mut self,
+        sender: &Address,
+        args: BatchDeleteArgs,
+    ) -> Result<BatchDeleteReturn, ManyError> {
+        let BatchDeleteArgs {
+            keys,
+            alternative_owner,
+            reason,
+        } = args;
+        self.verify_batch(keys.iter().map(|key| key.as_slice()))?;
+        let owner = if let Some(ref alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                alternative_owner,
+                "kvstore.batchDelete",
+                [Role::CanKvStoreDisable, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        for key in &keys {
+            if self.storage.get(key)?.is_none() {
+                return Err(error::cannot_disable_empty_key());
+            }
+            self.verify_acl(owner, key)?;
+        }
+
+        // The keys keep their own ACL.
+        let disabled = Some(reason.map_or(Either::Left(true), Either::Right));
+        let entries = keys
+            .into_iter()
+            .map(|key| {
+                let meta = KvStoreMetadata {
+                    owner: *owner,
+                    disabled: disabled.clone(),
+                    previous_owner: None,
+                    acl: self.metadata(&key)?.and_then(|meta| meta.acl),
+                };
+                Ok((meta, key.into()))
+            })
+            .collect::<Result<_, ManyError>>()?;
+        self.storage.disable_batch(entries)?;
+        Ok(BatchDeleteReturn::default())
+    }
+
+    fn set_acl(&mut self, sender: &Address, args: SetAclArgs) -> Result<SetAclReturn, ManyError> {
+        let SetAclArgs {
+            key,
+            acl,
+            alternative_owner,
+        } = args;
+        let metadata = self.metadata(&key)?.ok_or_else(error::key_not_found)?;
+        let owner = if let Some(ref alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                alternative_owner,
+                "kvstore.setAcl",
+                [Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        self.verify_acl(owner, &key)?;
+
+        self.storage.set_acl(
+            &key,
+            KvStoreMetadata {
+                acl: Some(acl),
+                ..metadata
+            },
+        )?;
+        Ok(SetAclReturn::default())
     }
 }
 
@@ -315,6 +659,7 @@ impl KvStoreTransferModuleBackend for KvStoreModuleImpl {
             self.validate_alternative_owner(
                 sender,
                 alternative_owner,
+                "kvstore.transfer",
                 [Role::CanKvStoreTransfer, Role::Owner],
             )?;
             alternative_owner
@@ -325,13 +670,117 @@ impl KvStoreTransferModuleBackend for KvStoreModuleImpl {
         self.verify_acl(owner, &key)?;
 
         // We allow transferring a disabled key, and keep the same reason.
+        // The ACL was set by the previous owner, so it is removed.
         let meta = KvStoreMetadata {
             owner: args.new_owner,
             disabled: metadata.disabled,
             previous_owner: Some(metadata.owner),
+            acl: None,
         };
         self.storage.transfer(&key, *owner, meta)?;
 
-        Ok(TransferReturn {})
+        Ok(TransferReturn::default())
+    }
+
+    fn offer_transfer(
+        &mut self,
+        sender: &Address,
+        args: OfferTransferArgs,
+    ) -> Result<OfferTransferReturn, ManyError> {
+        let OfferTransferArgs {
+            key,
+            alternative_owner,
+            new_owner,
+            expiration,
+        } = args;
+        if self.storage.get(&key)?.is_none() {
+            return Err(error::key_not_found());
+        }
+        if new_owner.is_anonymous() {
+            return Err(error::anon_alt_denied());
+        }
+        if matches!(expiration, Some(expiration) if expiration <= self.storage.now()) {
+            return Err(error::transfer_offer_expired());
+        }
+
+        let owner = if let Some(ref alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                alternative_owner,
+                "kvstore.offerTransfer",
+                [Role::CanKvStoreTransfer, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            sender
+        };
+
+        self.verify_acl(owner, &key)?;
+
+        self.storage.offer_transfer(
+            &key,
+            TransferOffer {
+                owner: *owner,
+                new_owner,
+                expiration,
+            },
+        )?;
+        Ok(OfferTransferReturn::default())
+    }
+
+    fn accept_transfer(
+        &mut self,
+        sender: &Address,
+        args: AcceptTransferArgs,
+    ) -> Result<AcceptTransferReturn, ManyError> {
+        let AcceptTransferArgs {
+            key,
+            alternative_owner,
+        } = args;
+        let key: Vec<u8> = key.into();
+        let metadata: KvStoreMetadata = minicbor::decode(
+            &self
+                .storage
+                .get_metadata(&key)?
+                .ok_or_else(error::key_not_found)?,
+        )
+        .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
+
+        // An offer made by a previous owner cannot be accepted.
+        let offer = self
+            .storage
+            .get_transfer_offer(&key)?
+            .filter(|offer| offer.owner == metadata.owner)
+            .ok_or_else(error::transfer_offer_not_found)?;
+        if matches!(offer.expiration, Some(expiration) if expiration < self.storage.now()) {
+            return Err(error::transfer_offer_expired());
+        }
+
+        let new_owner = if let Some(ref alternative_owner) = alternative_owner {
+            self.validate_alternative_owner(
+                sender,
+                alternative_owner,
+                "kvstore.acceptTransfer",
+                [Role::CanKvStoreTransfer, Role::Owner],
+            )?;
+            alternative_owner
+        } else {
+            sender
+        };
+        if offer.new_owner != *new_owner {
+            return Err(error::transfer_offer_denied());
+        }
+
+        // Like direct transfers, disabled keys keep the same reason and
+        // lose their ACL.
+        let meta = KvStoreMetadata {
+            owner: offer.new_owner,
+            disabled: metadata.disabled,
+            previous_owner: Some(metadata.owner),
+            acl: None,
+        };
+        self.storage.accept_transfer(&key, metadata.owner, meta)?;
+
+        Ok(AcceptTransferReturn::default())
     }
 }
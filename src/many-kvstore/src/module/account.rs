@@ -4,7 +4,7 @@ use many_error::{ManyError, ManyErrorCode};
 use many_identity::Address;
 use many_modules::account::features::{FeatureInfo, TryCreateFeature};
 use many_modules::account::{AccountModuleBackend, Role};
-use many_modules::{account, EmptyReturn, ManyModule, ManyModuleInfo};
+use many_modules::{account, Acknowledgment, ManyModule, ManyModuleInfo};
 use many_protocol::{context::Context, RequestMessage, ResponseMessage};
 use many_types::cbor::CborAny;
 use std::collections::BTreeSet;
@@ -30,6 +30,12 @@ fn validate_features_for_account(account: &account::Account) -> Result<(), ManyE
         }
     }
 
+    if let Err(e) = features.get::<account::features::method_acl::MethodAclAccountFeature>() {
+        if e.code() != ManyErrorCode::AttributeNotFound {
+            return Err(e);
+        }
+    }
+
     Ok(())
 }
 
@@ -137,7 +143,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         &mut self,
         sender: &Address,
         args: account::SetDescriptionArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<Acknowledgment, ManyError> {
         let (account, _) = self.storage.get_account(&args.account);
         let account = account.ok_or_else(|| account::errors::unknown_account(args.account))?;
 
@@ -147,7 +153,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
 
         self.storage
             .set_description(account, args)
-            .map(|_| EmptyReturn)
+            .map(|_| Acknowledgment::default())
     }
 
     fn list_roles(
@@ -194,14 +200,16 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         &mut self,
         sender: &Address,
         args: account::AddRolesArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<Acknowledgment, ManyError> {
         let (account, _) = self.storage.get_account(&args.account);
         let account = account.ok_or_else(|| account::errors::unknown_account(args.account))?;
 
         if !account.has_role(sender, account::Role::Owner) {
             Err(account::errors::user_needs_role("owner"))
         } else {
-            self.storage.add_roles(account, args).map(|_| EmptyReturn)
+            self.storage
+                .add_roles(account, args)
+                .map(|_| Acknowledgment::default())
         }
     }
 
@@ -209,7 +217,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         &mut self,
         sender: &Address,
         args: account::RemoveRolesArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<Acknowledgment, ManyError> {
         let (account, _) = self.storage.get_account(&args.account);
         let account = account.ok_or_else(|| account::errors::unknown_account(args.account))?;
 
@@ -218,7 +226,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         } else {
             self.storage
                 .remove_roles(account, args)
-                .map(|_| EmptyReturn)
+                .map(|_| Acknowledgment::default())
         }
     }
 
@@ -254,7 +262,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         &mut self,
         sender: &Address,
         args: account::DisableArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<Acknowledgment, ManyError> {
         let (account, _) = self.storage.get_account(&args.account);
         let account = account.ok_or_else(|| account::errors::unknown_account(args.account))?;
 
@@ -263,7 +271,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
         } else {
             self.storage
                 .disable_account(&args.account)
-                .map(|_| EmptyReturn)
+                .map(|_| Acknowledgment::default())
         }
     }
 
@@ -281,7 +289,7 @@ impl AccountModuleBackend for KvStoreModuleImpl {
             account.needs_role(sender, [Role::Owner]).and_then(|_| {
                 self.storage
                     .add_features(account, args)
-                    .map(|_| EmptyReturn)
+                    .map(|_| Acknowledgment::default())
             })
         }
     }
@@ -290,14 +298,16 @@ impl AccountModuleBackend for KvStoreModuleImpl {
 impl KvStoreModuleImpl {
     /// Verify the alternative owner is supported
     /// Verify the sender has the rights to use this alternative owner address
+    /// to call `method`
     pub(crate) fn validate_alternative_owner<R: TryInto<Role> + std::fmt::Display + Copy>(
         &self,
         sender: &Address,
         alternative_owner: &Address,
+        method: &str,
         roles: impl IntoIterator<Item = R>,
     ) -> Result<(), ManyError> {
         if let Some(account) = self.storage.get_account(alternative_owner).0 {
-            account.needs_role(sender, roles)
+            account::verify_account_role(&account, sender, method, roles)
         } else if alternative_owner.is_subresource() {
             // TODO: Subresource alternative owner support
             // https://github.com/liftedinit/many-framework/issues/246
@@ -1,8 +1,14 @@
+use crate::migration::owner_index::OWNER_INDEX_MIGRATION;
+use crate::migration::{KvStoreMigrations, MIGRATIONS};
 use crate::module::{KvStoreMetadata, KvStoreMetadataWrapper};
+use many_cli_helpers::state_export::StateExport;
 use many_error::ManyError;
 use many_identity::Address;
+use many_migration::{MigrationConfig, MigrationSet};
 use many_modules::abci_backend::AbciCommitInfo;
 use many_modules::events::EventInfo;
+use many_types::clock::{Clock, SystemClock};
+use many_types::proof::cache::{ProofCache, ProofCacheStats};
 use many_types::{Either, ProofOperation, SortOrder, Timestamp};
 use merk::{
     proofs::{
@@ -27,6 +33,20 @@ use many_modules::kvstore::KeyFilterType;
 
 const KVSTORE_ROOT: &[u8] = b"s";
 const KVSTORE_ACL_ROOT: &[u8] = b"a";
+const KVSTORE_TRANSFER_OFFER_ROOT: &[u8] = b"/transfer_offers/";
+
+/// Where the references to content-addressed values are counted, at
+/// `<root><key>`, with a marker for each owner at `<root><key><owner>`.
+const KVSTORE_CAS_REFS_ROOT: &[u8] = b"/cas_refs/";
+
+/// Where the keys of each owner are indexed once the owner index migration
+/// is active, with a marker at `<root><owner length><owner><key>` that is
+/// set while the owner owns the key. The length keeps the keys of an address
+/// apart from those of its subresources.
+const KVSTORE_OWNERS_ROOT: &[u8] = b"/owners/";
+
+/// The maximum gas the transactions of a block can use, set at genesis.
+const BLOCK_GAS_LIMIT_ROOT: &[u8] = b"/config/block_gas_limit";
 
 #[derive(Serialize, Deserialize, Debug, Eq, Ord, PartialEq, PartialOrd)]
 #[serde(transparent)]
@@ -37,6 +57,22 @@ pub struct Key {
 
 pub type AclMap = BTreeMap<Key, KvStoreMetadataWrapper>;
 
+/// A pending offer to transfer the ownership of a key.
+#[derive(Clone, Debug, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+pub struct TransferOffer {
+    /// The owner of the key when the offer was made. The offer is void if
+    /// the key changed owner since.
+    #[n(0)]
+    pub owner: Address,
+
+    #[n(1)]
+    pub new_owner: Address,
+
+    #[n(2)]
+    pub expiration: Option<Timestamp>,
+}
+
 pub struct KvStoreStorage {
     persistent_store: merk::Merk,
 
@@ -48,8 +84,13 @@ pub struct KvStoreStorage {
     latest_event_id: EventId,
     current_time: Option<Timestamp>,
     current_hash: Option<Vec<u8>>,
+    clock: Box<dyn Clock>,
     next_subresource: u32,
     root_identity: Address,
+    migrations: KvStoreMigrations,
+
+    /// Proofs already computed since the store last changed.
+    proof_cache: ProofCache,
 }
 
 impl std::fmt::Debug for KvStoreStorage {
@@ -75,33 +116,84 @@ fn filter_key(filter: &KeyFilterType, _key: &[u8], meta: &KvStoreMetadata) -> bo
     }
 }
 
+fn owner_index_prefix(owner: &Address) -> Vec<u8> {
+    let owner = owner.to_vec();
+    [KVSTORE_OWNERS_ROOT, &[owner.len() as u8], &owner].concat()
+}
+
+/// Mark whether `owner` owns a key. Like the references to
+/// content-addressed values, markers are never deleted, so that a key can
+/// change owner more than once in a block.
+fn owner_index_entry(owner: &Address, key: &[u8], owned: bool) -> BatchEntry {
+    (
+        [owner_index_prefix(owner).as_slice(), key].concat(),
+        Op::Put(vec![owned as u8]),
+    )
+}
+
+/// Index the owner of every key of the committed store.
+pub(crate) fn index_all_owners(persistent_store: &mut merk::Merk) -> Result<(), ManyError> {
+    let mut batch = KvStoreIterator::all_keys(persistent_store, SortOrder::Ascending)
+        .map(|item| {
+            let (k, v) = item.map_err(error::storage_get_failed)?;
+            let meta: KvStoreMetadata = minicbor::decode(&v)
+                .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
+            Ok(owner_index_entry(
+                &meta.owner,
+                &k[KVSTORE_ACL_ROOT.len()..],
+                true,
+            ))
+        })
+        .collect::<Result<Vec<BatchEntry>, ManyError>>()?;
+    batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    persistent_store
+        .apply(&batch)
+        .map_err(error::storage_apply_failed)
+}
+
 impl KvStoreStorage {
     #[inline]
     pub fn set_time(&mut self, time: Timestamp) {
         self.current_time = Some(time);
     }
     #[inline]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+    #[inline]
     pub fn now(&self) -> Timestamp {
-        self.current_time.unwrap_or_else(Timestamp::now)
+        self.current_time.unwrap_or_else(|| self.clock.now())
+    }
+
+    /// Apply a batch of operations to the persistent store, invalidating the
+    /// cached proofs.
+    fn apply(&mut self, batch: &[BatchEntry]) -> Result<(), ManyError> {
+        self.persistent_store
+            .apply(batch)
+            .map_err(error::storage_apply_failed)?;
+        self.proof_cache.invalidate();
+        Ok(())
     }
 
     pub fn new_subresource_id(&mut self) -> Result<(Address, Vec<u8>), ManyError> {
         let current_id = self.next_subresource;
         self.next_subresource += 1;
         let key = b"/config/subresource_id".to_vec();
-        self.persistent_store
-            .apply(&[(
-                key.clone(),
-                Op::Put(self.next_subresource.to_be_bytes().to_vec()),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            key.clone(),
+            Op::Put(self.next_subresource.to_be_bytes().to_vec()),
+        )])?;
 
         self.root_identity
             .with_subresource_id(current_id)
             .map(|address| (address, key))
     }
 
-    pub fn load<P: AsRef<Path>>(persistent_path: P, blockchain: bool) -> Result<Self, String> {
+    pub fn load<P: AsRef<Path>>(
+        persistent_path: P,
+        blockchain: bool,
+        migration_config: Option<MigrationConfig>,
+    ) -> Result<Self, String> {
         let persistent_store = merk::Merk::open(persistent_path).map_err(|e| e.to_string())?;
 
         let next_subresource = persistent_store
@@ -129,20 +221,33 @@ impl KvStoreStorage {
         )
         .map_err(|e| e.to_string())?;
 
+        let height = persistent_store.get(b"/height").unwrap().map_or(0u64, |x| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(x.as_slice());
+            u64::from_be_bytes(bytes)
+        });
+        let migrations = migration_config.map_or_else(MigrationSet::empty, |config| {
+            KvStoreMigrations::load(&MIGRATIONS, config, height)
+        })?;
+
         Ok(Self {
             persistent_store,
             blockchain,
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             latest_event_id,
             next_subresource,
             root_identity,
+            migrations,
+            proof_cache: ProofCache::default(),
         })
     }
 
     pub fn new<P: AsRef<Path>>(
         acl: AclMap,
         identity: Address,
+        block_gas_limit: Option<u64>,
         persistent_path: P,
         blockchain: bool,
     ) -> Result<Self, String> {
@@ -151,6 +256,12 @@ impl KvStoreStorage {
         let mut batch: Vec<BatchEntry> = Vec::new();
 
         batch.push((b"/config/identity".to_vec(), Op::Put(identity.to_vec())));
+        if let Some(limit) = block_gas_limit {
+            batch.push((
+                BLOCK_GAS_LIMIT_ROOT.to_vec(),
+                Op::Put(limit.to_be_bytes().to_vec()),
+            ));
+        }
 
         // Initialize DB with ACL
         for (k, v) in acl.into_iter() {
@@ -159,6 +270,7 @@ impl KvStoreStorage {
                 Op::Put(minicbor::to_vec(v).map_err(|e| e.to_string())?),
             ));
         }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
         persistent_store
             .apply(batch.as_slice())
@@ -178,24 +290,114 @@ impl KvStoreStorage {
             persistent_store,
             blockchain,
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             latest_event_id,
             next_subresource: 0,
             root_identity: identity,
+            migrations: MigrationSet::empty()?,
+            proof_cache: ProofCache::default(),
+        })
+    }
+
+    /// Set the migrations of a new store. The keys of its initial state are
+    /// indexed if the owner index migration is already active.
+    pub fn with_migrations(
+        mut self,
+        migration_config: Option<MigrationConfig>,
+    ) -> Result<Self, ManyError> {
+        self.migrations = migration_config
+            .map_or_else(MigrationSet::empty, |config| {
+                KvStoreMigrations::load(&MIGRATIONS, config, 0)
+            })
+            .map_err(error::unable_to_load_migrations)?;
+
+        if self.owner_index_active() {
+            index_all_owners(&mut self.persistent_store)?;
+            self.persistent_store
+                .commit(&[])
+                .map_err(error::storage_apply_failed)?;
+        }
+        Ok(self)
+    }
+
+    pub fn migrations(&self) -> &KvStoreMigrations {
+        &self.migrations
+    }
+
+    /// Whether the keys of each owner are indexed.
+    pub fn owner_index_active(&self) -> bool {
+        self.migrations.is_active(&OWNER_INDEX_MIGRATION)
+    }
+
+    /// Export every key of the committed store, including the values, their
+    /// ACLs and the events.
+    pub fn export_state(&self) -> Result<StateExport, String> {
+        let entries = KvStoreIterator::all(&self.persistent_store)
+            .map(|item| item.map(|(key, value)| (key.to_vec(), value)))
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?;
+        Ok(StateExport {
+            height: self.get_height(),
+            hash: self.persistent_store.root_hash().to_vec(),
+            entries,
         })
     }
 
+    /// Create a persistent store holding the state of an export, to load it
+    /// with [`KvStoreStorage::load`].
+    pub fn import_state<P: AsRef<Path>>(
+        persistent_path: P,
+        export: &StateExport,
+    ) -> Result<(), String> {
+        let height = export
+            .entries
+            .get(b"/height".as_slice())
+            .map_or(Ok(0), |h| {
+                <[u8; 8]>::try_from(h.as_slice()).map(u64::from_be_bytes)
+            })
+            .map_err(|_| "The height of the export is not a 64-bit integer.".to_string())?;
+        if height != export.height {
+            return Err(format!(
+                "The export is at height {}, its store at height {height}.",
+                export.height
+            ));
+        }
+
+        let mut persistent_store = merk::Merk::open(persistent_path).map_err(|e| e.to_string())?;
+        // The entries of the export are sorted by key, as Merk needs.
+        let batch: Vec<BatchEntry> = export
+            .entries
+            .iter()
+            .map(|(key, value)| (key.clone(), Op::Put(value.clone())))
+            .collect();
+        persistent_store.apply(&batch).map_err(|e| e.to_string())?;
+        persistent_store.commit(&[]).map_err(|e| e.to_string())
+    }
+
     fn inc_height(&mut self) -> u64 {
         let current_height = self.get_height();
-        self.persistent_store
-            .apply(&[(
-                b"/height".to_vec(),
-                Op::Put((current_height + 1).to_be_bytes().to_vec()),
-            )])
-            .unwrap();
+        self.apply(&[(
+            b"/height".to_vec(),
+            Op::Put((current_height + 1).to_be_bytes().to_vec()),
+        )])
+        .unwrap();
         current_height
     }
 
+    pub fn block_gas_limit(&self) -> Result<Option<u64>, ManyError> {
+        self.persistent_store
+            .get(BLOCK_GAS_LIMIT_ROOT)
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| {
+                bytes
+                    .try_into()
+                    .map(u64::from_be_bytes)
+                    .map_err(|_| ManyError::deserialization_error("Invalid block gas limit"))
+            })
+            .transpose()
+    }
+
     pub fn get_height(&self) -> u64 {
         self.persistent_store
             .get(b"/height")
@@ -209,26 +411,43 @@ impl KvStoreStorage {
 
     pub fn commit(&mut self) -> AbciCommitInfo {
         let _ = self.inc_height();
-        self.persistent_store
-            .apply(&[(
-                b"/latest_event_id".to_vec(),
-                Op::Put(
-                    minicbor::to_vec(&self.latest_event_id).expect("Unable to encode event id"),
-                ),
-            )])
-            .unwrap();
+        self.apply(&[(
+            b"/latest_event_id".to_vec(),
+            Op::Put(minicbor::to_vec(&self.latest_event_id).expect("Unable to encode event id")),
+        )])
+        .unwrap();
+        self.persistent_store.commit(&[]).unwrap();
+
+        // Committing before the migrations so they see the state of the block.
+        let time = self.current_time.map(|t| t.secs());
+        self.migrations
+            .update_at(&mut self.persistent_store, self.get_height(), time)
+            .expect("Unable to run migrations");
         self.persistent_store.commit(&[]).unwrap();
 
         let retain_height = 0;
         let hash = self.persistent_store.root_hash().to_vec();
         self.current_hash = Some(hash.clone());
 
+        let stats = self.proof_cache_stats();
+        tracing::debug!(
+            target: "metrics",
+            proof_cache_hits = stats.hits,
+            proof_cache_misses = stats.misses,
+        );
+
         AbciCommitInfo {
             retain_height,
             hash: hash.into(),
         }
     }
 
+    /// The number of proofs served from the cache and computed since the
+    /// storage was opened.
+    pub fn proof_cache_stats(&self) -> ProofCacheStats {
+        self.proof_cache.stats()
+    }
+
     pub fn hash(&self) -> Vec<u8> {
         self.current_hash
             .as_ref()
@@ -277,6 +496,26 @@ impl KvStoreStorage {
         })
     }
 
+    /// The keys owned by an address in ascending order, after `after` if
+    /// set.
+    pub fn list_by_owner(
+        &self,
+        owner: &Address,
+        after: Option<Vec<u8>>,
+    ) -> impl Iterator<Item = Result<Vec<u8>, ManyError>> + '_ {
+        let prefix = owner_index_prefix(owner);
+        let start = [prefix.as_slice(), after.as_deref().unwrap_or_default()].concat();
+        KvStoreIterator::index(&self.persistent_store, &prefix, &start).filter_map(move |item| {
+            match item {
+                Ok((k, v)) => {
+                    let key = &k[prefix.len()..];
+                    (v == [1] && after.as_deref() != Some(key)).then(|| Ok(key.to_vec()))
+                }
+                Err(e) => Some(Err(ManyError::unknown(e.to_string()))),
+            }
+        })
+    }
+
     pub fn get_metadata(&self, key: &[u8]) -> Result<Option<Vec<u8>>, ManyError> {
         self._get(key, KVSTORE_ACL_ROOT)
     }
@@ -287,27 +526,43 @@ impl KvStoreStorage {
         key: &[u8],
         value: Vec<u8>,
     ) -> Result<(), ManyError> {
-        self.persistent_store
-            .apply(&[
-                (
-                    [KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
-                    Op::Put(
-                        minicbor::to_vec(meta)
-                            .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                    ),
-                ),
-                (
-                    [KVSTORE_ROOT.to_vec(), key.to_vec()].concat(),
-                    Op::Put(value.clone()),
-                ),
-            ])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        self.put_batch(vec![(meta.clone(), key.to_vec(), value)])
+    }
 
-        self.log_event(EventInfo::KvStorePut {
-            key: key.to_vec().into(),
-            value: value.into(),
-            owner: meta.owner,
-        });
+    /// Put the values of multiple keys, each with its metadata, in a single
+    /// change to the persistent store.
+    pub fn put_batch(
+        &mut self,
+        entries: Vec<(KvStoreMetadata, Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), ManyError> {
+        let owner_index = self.owner_index_active();
+        let mut batch: Vec<BatchEntry> = Vec::with_capacity(entries.len() * 3);
+        for (meta, key, value) in &entries {
+            if owner_index {
+                batch.push(owner_index_entry(&meta.owner, key, true));
+            }
+            batch.push((
+                [KVSTORE_ACL_ROOT, key.as_slice()].concat(),
+                Op::Put(
+                    minicbor::to_vec(meta)
+                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+                ),
+            ));
+            batch.push((
+                [KVSTORE_ROOT, key.as_slice()].concat(),
+                Op::Put(value.clone()),
+            ));
+        }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+
+        for (meta, key, value) in entries {
+            self.log_event(EventInfo::KvStorePut {
+                key: key.into(),
+                value: value.into(),
+                owner: meta.owner,
+            });
+        }
 
         if !self.blockchain {
             self.persistent_store.commit(&[]).unwrap();
@@ -315,30 +570,168 @@ impl KvStoreStorage {
         Ok(())
     }
 
-    pub fn disable(&mut self, meta: &KvStoreMetadata, key: &[u8]) -> Result<(), ManyError> {
-        self.persistent_store
-            .apply(&[(
-                [KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
+    /// The number of owners referencing a content-addressed value.
+    pub fn cas_references(&self, key: &[u8]) -> Result<u64, ManyError> {
+        Ok(self._get(key, KVSTORE_CAS_REFS_ROOT)?.map_or(0, |x| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(x.as_slice());
+            u64::from_be_bytes(bytes)
+        }))
+    }
+
+    fn cas_referenced_by(&self, owner: &Address, key: &[u8]) -> Result<bool, ManyError> {
+        let marker = [key, &owner.to_vec()].concat();
+        Ok(self._get(&marker, KVSTORE_CAS_REFS_ROOT)? == Some(vec![1]))
+    }
+
+    /// Change the reference of an owner to a content-addressed value, and its
+    /// count. Markers are never deleted, as Merk ignores a put following a
+    /// delete of the same key before a commit.
+    fn cas_reference_batch(
+        owner: &Address,
+        key: &[u8],
+        references: u64,
+        referenced: bool,
+    ) -> Vec<BatchEntry> {
+        vec![
+            (
+                [KVSTORE_CAS_REFS_ROOT, key].concat(),
+                Op::Put(references.to_be_bytes().to_vec()),
+            ),
+            (
+                [KVSTORE_CAS_REFS_ROOT, key, &owner.to_vec()].concat(),
+                Op::Put(vec![referenced as u8]),
+            ),
+        ]
+    }
+
+    /// Add a reference of an owner to a content-addressed value, storing the
+    /// value if it was not referenced, and return the number of references.
+    /// The value is owned by the server, so only `kvstore.putCas` writes it.
+    pub fn put_cas(
+        &mut self,
+        owner: &Address,
+        key: &[u8],
+        value: Vec<u8>,
+    ) -> Result<u64, ManyError> {
+        let references = self.cas_references(key)?;
+        if self.cas_referenced_by(owner, key)? {
+            return Ok(references);
+        }
+
+        let mut batch = Self::cas_reference_batch(owner, key, references + 1, true);
+        if references == 0 {
+            let meta = KvStoreMetadata {
+                owner: self.root_identity,
+                disabled: Some(Either::Left(false)),
+                previous_owner: None,
+                acl: None,
+            };
+            batch.push((
+                [KVSTORE_ACL_ROOT, key].concat(),
                 Op::Put(
                     minicbor::to_vec(meta)
                         .map_err(|e| ManyError::serialization_error(e.to_string()))?,
                 ),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
-
-        let reason = if let Some(disabled) = &meta.disabled {
-            match disabled {
-                Either::Right(reason) => Some(reason),
-                _ => None,
+            ));
+            batch.push(([KVSTORE_ROOT, key].concat(), Op::Put(value.clone())));
+            if self.owner_index_active() {
+                batch.push(owner_index_entry(&self.root_identity, key, true));
             }
-        } else {
-            None
-        };
+        }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+
+        if references == 0 {
+            self.log_event(EventInfo::KvStorePut {
+                key: key.to_vec().into(),
+                value: value.into(),
+                owner: *owner,
+            });
+        }
 
-        self.log_event(EventInfo::KvStoreDisable {
-            key: key.to_vec().into(),
-            reason: reason.cloned(),
-        });
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(references + 1)
+    }
+
+    /// Remove the reference of an owner to a content-addressed value, and
+    /// return the number of references left. The value is disabled when none
+    /// are left.
+    pub fn release_cas(&mut self, owner: &Address, key: &[u8]) -> Result<u64, ManyError> {
+        if !self.cas_referenced_by(owner, key)? {
+            return Err(error::permission_denied());
+        }
+        let references = self.cas_references(key)? - 1;
+
+        let mut batch = Self::cas_reference_batch(owner, key, references, false);
+        if references == 0 {
+            let meta = KvStoreMetadata {
+                owner: self.root_identity,
+                disabled: Some(Either::Left(true)),
+                previous_owner: None,
+                acl: None,
+            };
+            batch.push((
+                [KVSTORE_ACL_ROOT, key].concat(),
+                Op::Put(
+                    minicbor::to_vec(meta)
+                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+                ),
+            ));
+        }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+
+        if references == 0 {
+            self.log_event(EventInfo::KvStoreDisable {
+                key: key.to_vec().into(),
+                reason: None,
+            });
+        }
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(references)
+    }
+
+    pub fn disable(&mut self, meta: &KvStoreMetadata, key: &[u8]) -> Result<(), ManyError> {
+        self.disable_batch(vec![(meta.clone(), key.to_vec())])
+    }
+
+    /// Disable multiple keys, each with its metadata, in a single change to
+    /// the persistent store.
+    pub fn disable_batch(
+        &mut self,
+        entries: Vec<(KvStoreMetadata, Vec<u8>)>,
+    ) -> Result<(), ManyError> {
+        let mut batch: Vec<BatchEntry> = entries
+            .iter()
+            .map(|(meta, key)| {
+                Ok((
+                    [KVSTORE_ACL_ROOT, key.as_slice()].concat(),
+                    Op::Put(
+                        minicbor::to_vec(meta)
+                            .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+                    ),
+                ))
+            })
+            .collect::<Result<_, ManyError>>()?;
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+
+        for (meta, key) in entries {
+            let reason = match meta.disabled {
+                Some(Either::Right(reason)) => Some(reason),
+                _ => None,
+            };
+            self.log_event(EventInfo::KvStoreDisable {
+                key: key.into(),
+                reason,
+            });
+        }
 
         if !self.blockchain {
             self.persistent_store.commit(&[]).unwrap();
@@ -353,15 +746,21 @@ impl KvStoreStorage {
         meta: KvStoreMetadata,
     ) -> Result<(), ManyError> {
         let new_owner = meta.owner;
-        self.persistent_store
-            .apply(&[(
-                [KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
-                Op::Put(
-                    minicbor::to_vec(meta)
-                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                ),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        let mut batch = vec![(
+            [KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
+            Op::Put(
+                minicbor::to_vec(meta)
+                    .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+            ),
+        )];
+        if self.owner_index_active() {
+            batch.push(owner_index_entry(&new_owner, key, true));
+            if previous_owner != new_owner {
+                batch.push(owner_index_entry(&previous_owner, key, false));
+            }
+        }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
 
         self.log_event(EventInfo::KvStoreTransfer {
             key: key.to_vec().into(),
@@ -375,6 +774,78 @@ impl KvStoreStorage {
         Ok(())
     }
 
+    /// Replace the metadata of a key with one holding its new ACL.
+    pub fn set_acl(&mut self, key: &[u8], meta: KvStoreMetadata) -> Result<(), ManyError> {
+        let owner = meta.owner;
+        let acl = meta.acl.clone().unwrap_or_default();
+        self.apply(&[(
+            [KVSTORE_ACL_ROOT.to_vec(), key.to_vec()].concat(),
+            Op::Put(
+                minicbor::to_vec(meta)
+                    .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+            ),
+        )])?;
+
+        self.log_event(EventInfo::KvStoreSetAcl {
+            key: key.to_vec().into(),
+            owner,
+            acl,
+        });
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(())
+    }
+
+    pub fn get_transfer_offer(&self, key: &[u8]) -> Result<Option<TransferOffer>, ManyError> {
+        self._get(key, KVSTORE_TRANSFER_OFFER_ROOT)?
+            .filter(|cbor| cbor != &[0])
+            .map(|cbor| {
+                minicbor::decode(&cbor).map_err(|e| ManyError::deserialization_error(e.to_string()))
+            })
+            .transpose()
+    }
+
+    pub fn offer_transfer(&mut self, key: &[u8], offer: TransferOffer) -> Result<(), ManyError> {
+        self.apply(&[(
+            [KVSTORE_TRANSFER_OFFER_ROOT.to_vec(), key.to_vec()].concat(),
+            Op::Put(
+                minicbor::to_vec(&offer)
+                    .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+            ),
+        )])?;
+
+        self.log_event(EventInfo::KvStoreTransferOffer {
+            key: key.to_vec().into(),
+            owner: offer.owner,
+            new_owner: offer.new_owner,
+            expiration: offer.expiration,
+        });
+
+        if !self.blockchain {
+            self.persistent_store.commit(&[]).unwrap();
+        }
+        Ok(())
+    }
+
+    /// Transfer a key following an accepted offer, and remove the offer. The
+    /// offer is replaced by a tombstone rather than deleted, as Merk ignores a
+    /// put following a delete of the same key before a commit.
+    pub fn accept_transfer(
+        &mut self,
+        key: &[u8],
+        previous_owner: Address,
+        meta: KvStoreMetadata,
+    ) -> Result<(), ManyError> {
+        self.apply(&[(
+            [KVSTORE_TRANSFER_OFFER_ROOT.to_vec(), key.to_vec()].concat(),
+            Op::Put(vec![0]),
+        )])?;
+
+        self.transfer(key, previous_owner, meta)
+    }
+
     pub fn prove_state(
         &self,
         context: impl AsRef<many_protocol::context::Context>,
@@ -382,30 +853,35 @@ impl KvStoreStorage {
     ) -> Result<(), ManyError> {
         use merk::proofs::Op;
         context.as_ref().prove(|| {
-            self.persistent_store
-                .prove({
-                    let mut query = Query::new();
-                    keys.into_iter().for_each(|key| query.insert_key(key));
-                    query
-                })
-                .and_then(|proof| {
-                    Decoder::new(proof.as_slice())
-                        .map(|fallible_operation| {
-                            fallible_operation.map(|operation| match operation {
-                                Op::Child => ProofOperation::Child,
-                                Op::Parent => ProofOperation::Parent,
-                                Op::Push(Hash(hash)) => ProofOperation::NodeHash(hash.to_vec()),
-                                Op::Push(KV(key, value)) => {
-                                    ProofOperation::KeyValuePair(key.into(), value.into())
-                                }
-                                Op::Push(KVHash(hash)) => {
-                                    ProofOperation::KeyValueHash(hash.to_vec())
-                                }
-                            })
+            self.proof_cache
+                .get_or_prove(self.get_height(), keys, |keys| {
+                    self.persistent_store
+                        .prove({
+                            let mut query = Query::new();
+                            keys.iter().for_each(|key| query.insert_key(key.clone()));
+                            query
+                        })
+                        .and_then(|proof| {
+                            Decoder::new(proof.as_slice())
+                                .map(|fallible_operation| {
+                                    fallible_operation.map(|operation| match operation {
+                                        Op::Child => ProofOperation::Child,
+                                        Op::Parent => ProofOperation::Parent,
+                                        Op::Push(Hash(hash)) => {
+                                            ProofOperation::NodeHash(hash.to_vec())
+                                        }
+                                        Op::Push(KV(key, value)) => {
+                                            ProofOperation::KeyValuePair(key.into(), value.into())
+                                        }
+                                        Op::Push(KVHash(hash)) => {
+                                            ProofOperation::KeyValueHash(hash.to_vec())
+                                        }
+                                    })
+                                })
+                                .collect::<Result<Vec<_>, _>>()
                         })
-                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|error| ManyError::unknown(error.to_string()))
                 })
-                .map_err(|error| ManyError::unknown(error.to_string()))
         })
     }
 }
@@ -166,15 +166,13 @@ impl KvStoreStorage {
     ) -> Result<Vec<u8>, ManyError> {
         tracing::debug!("commit({:?})", account);
         let key = key_for_account(id);
-        self.persistent_store
-            .apply(&[(
-                key.clone(),
-                Op::Put(
-                    minicbor::to_vec(account)
-                        .map_err(|e| ManyError::serialization_error(e.to_string()))?,
-                ),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        self.apply(&[(
+            key.clone(),
+            Op::Put(
+                minicbor::to_vec(account)
+                    .map_err(|e| ManyError::serialization_error(e.to_string()))?,
+            ),
+        )])?;
 
         if !self.blockchain {
             self.persistent_store
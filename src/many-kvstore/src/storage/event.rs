@@ -52,20 +52,20 @@ impl KvStoreStorage {
             id: self.new_event_id(),
             time: self.now(),
             content,
+            chain_hash: None,
         };
 
-        self.persistent_store
-            .apply(&[
-                (
-                    key_for_event(event.id.clone()),
-                    Op::Put(minicbor::to_vec(&event).unwrap()),
-                ),
-                (
-                    b"/events_count".to_vec(),
-                    Op::Put((current_nb_events + 1).to_be_bytes().to_vec()),
-                ),
-            ])
-            .unwrap();
+        self.apply(&[
+            (
+                key_for_event(event.id.clone()),
+                Op::Put(minicbor::to_vec(&event).unwrap()),
+            ),
+            (
+                b"/events_count".to_vec(),
+                Op::Put((current_nb_events + 1).to_be_bytes().to_vec()),
+            ),
+        ])
+        .unwrap();
 
         if !self.blockchain {
             self.persistent_store.commit(&[]).unwrap();
@@ -8,6 +8,13 @@ pub struct KvStoreIterator<'a> {
 }
 
 impl<'a> KvStoreIterator<'a> {
+    /// Every key of the store, in ascending order.
+    pub fn all(merk: &'a merk::Merk) -> Self {
+        let inner = merk.iter_opt(IteratorMode::Start, ReadOptions::default());
+
+        Self { inner }
+    }
+
     pub fn all_keys(merk: &'a merk::Merk, order: SortOrder) -> Self {
         use crate::storage::KVSTORE_ACL_ROOT;
 
@@ -24,6 +31,20 @@ impl<'a> KvStoreIterator<'a> {
 
         Self { inner }
     }
+
+    /// The entries of an index under `prefix`, in ascending order, starting
+    /// at `start`.
+    pub fn index(merk: &'a merk::Merk, prefix: &[u8], start: &[u8]) -> Self {
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(prefix));
+
+        let inner = merk.iter_opt(
+            IteratorMode::From(start, rocksdb::Direction::Forward),
+            options,
+        );
+
+        Self { inner }
+    }
 }
 
 impl<'a> Iterator for KvStoreIterator<'a> {
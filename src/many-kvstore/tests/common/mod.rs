@@ -3,6 +3,7 @@ use many_identity::testing::identity;
 use many_identity::{Address, Identity};
 use many_identity_dsa::ecdsa::generate_random_ecdsa_identity;
 use many_kvstore::module::KvStoreModuleImpl;
+use many_migration::MigrationConfig;
 use many_modules::abci_backend::{AbciBlock, ManyAbciModuleBackend};
 use many_modules::account;
 use many_modules::account::features::FeatureInfo;
@@ -40,14 +41,26 @@ impl Default for Setup {
 
 impl Setup {
     pub fn new(blockchain: bool) -> Self {
+        Self::new_with_migrations(blockchain, None)
+    }
+
+    pub fn new_with_migrations(
+        blockchain: bool,
+        migration_config: Option<MigrationConfig>,
+    ) -> Self {
         let id = generate_random_ecdsa_identity();
         let content = std::fs::read_to_string("../../staging/kvstore_state.json5")
             .or_else(|_| std::fs::read_to_string("staging/kvstore_state.json5"))
             .unwrap();
         let state = json5::from_str(&content).unwrap();
         Self {
-            module_impl: KvStoreModuleImpl::new(state, tempfile::tempdir().unwrap(), blockchain)
-                .unwrap(),
+            module_impl: KvStoreModuleImpl::new(
+                state,
+                migration_config,
+                tempfile::tempdir().unwrap(),
+                blockchain,
+            )
+            .unwrap(),
             id: id.address(),
             time: Some(1_000_000),
         }
@@ -168,6 +181,7 @@ fn create_account_args(account_type: AccountType) -> account::CreateArgs {
         description: Some("Foobar".to_string()),
         roles,
         features,
+        nonce: None,
     }
 }
 
@@ -1,16 +1,21 @@
 pub mod common;
 
-use crate::common::{setup, Setup};
+use crate::common::{assert_many_err, setup, Setup};
 use many_error::Reason;
 use many_identity::testing::identity;
 use many_identity::Address;
 use many_kvstore::error;
+use many_kvstore::migration::owner_index::OWNER_INDEX_MIGRATION;
+use many_migration::{Metadata, MigrationConfig};
+use many_modules::kvstore::list::{ListByOwnerArgs, ListByOwnerReturns};
 use many_modules::kvstore::{
-    InfoArg, KeyFilterType, KvStoreModuleBackend, KvStoreTransferModuleBackend, TransferArgs,
+    BatchDeleteArgs, BatchGetArgs, BatchPutArgs, BatchPutEntry, InfoArg, KeyAcl, KeyFilterType,
+    KvStoreCommandsModuleBackend, KvStoreModuleBackend, KvStoreTransferModuleBackend, PutCasArgs,
+    ReadAccess, SetAclArgs, TransferArgs,
 };
 use many_types::{Either, SortOrder};
 use minicbor::bytes::ByteVec;
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
 
 #[test]
 fn info() {
@@ -488,3 +493,503 @@ fn list_filter_previous_owner() {
         vec![keys[0].clone()]
     );
 }
+
+/// A setup indexing the keys of each owner from a block height.
+fn owner_index_setup(block_height: u64) -> Setup {
+    Setup::new_with_migrations(
+        false,
+        Some(
+            MigrationConfig::default()
+                .with_migration_opts(&OWNER_INDEX_MIGRATION, Metadata::enabled(block_height)),
+        ),
+    )
+}
+
+fn list_by_owner(
+    setup: &Setup,
+    owner: Address,
+    count: Option<u64>,
+    after: Option<Vec<u8>>,
+) -> ListByOwnerReturns {
+    setup
+        .module_impl
+        .list_by_owner(
+            &identity(1),
+            ListByOwnerArgs {
+                owner,
+                count,
+                after: after.map(Into::into),
+            },
+        )
+        .unwrap()
+}
+
+#[test]
+fn list_by_owner_pages() {
+    let mut setup = owner_index_setup(0);
+    let id = setup.id;
+    let subresource = id.with_subresource_id(1).unwrap();
+    for k in 1..=5u8 {
+        setup.put(&id, vec![k], vec![1], None).unwrap();
+        setup
+            .put(&subresource, vec![k + 10], vec![1], None)
+            .unwrap();
+    }
+
+    let page = list_by_owner(&setup, id, Some(2), None);
+    assert_eq!(page.keys, vec![ByteVec::from(vec![1]), vec![2].into()]);
+    assert_eq!(page.next, Some(vec![2].into()));
+
+    let page = list_by_owner(&setup, id, Some(2), Some(vec![2]));
+    assert_eq!(page.keys, vec![ByteVec::from(vec![3]), vec![4].into()]);
+
+    let page = list_by_owner(&setup, id, Some(2), Some(vec![4]));
+    assert_eq!(page.keys, vec![ByteVec::from(vec![5])]);
+    assert_eq!(page.next, None);
+
+    // A page has at least one key.
+    let page = list_by_owner(&setup, id, Some(0), None);
+    assert_eq!(page.keys, vec![ByteVec::from(vec![1])]);
+    assert_eq!(page.next, Some(vec![1].into()));
+
+    // The keys of a subresource are its own.
+    let page = list_by_owner(&setup, subresource, None, None);
+    assert_eq!(page.keys.len(), 5);
+    assert!(page.keys.iter().all(|key| key[0] > 10));
+}
+
+#[test]
+fn list_by_owner_transfer() {
+    let mut setup = owner_index_setup(0);
+    let id = setup.id;
+    for k in 1..=3u8 {
+        setup.put(&id, vec![k], vec![1], None).unwrap();
+    }
+    setup
+        .module_impl
+        .transfer(
+            &id,
+            TransferArgs {
+                key: vec![2].into(),
+                alternative_owner: None,
+                new_owner: identity(2),
+            },
+        )
+        .unwrap();
+    // Disabled keys are still owned.
+    setup.disable(&id, vec![3], None, None).unwrap();
+
+    assert_eq!(
+        list_by_owner(&setup, id, None, None).keys,
+        vec![ByteVec::from(vec![1]), vec![3].into()]
+    );
+    assert_eq!(
+        list_by_owner(&setup, identity(2), None, None).keys,
+        vec![ByteVec::from(vec![2])]
+    );
+
+    // Transferring it back owns it again.
+    setup
+        .module_impl
+        .transfer(
+            &identity(2),
+            TransferArgs {
+                key: vec![2].into(),
+                alternative_owner: None,
+                new_owner: id,
+            },
+        )
+        .unwrap();
+    assert_eq!(list_by_owner(&setup, id, None, None).keys.len(), 3);
+    assert!(list_by_owner(&setup, identity(2), None, None)
+        .keys
+        .is_empty());
+}
+
+#[test]
+fn list_by_owner_transfer_back_block() {
+    let mut setup = Setup::new_with_migrations(
+        true,
+        Some(
+            MigrationConfig::default()
+                .with_migration_opts(&OWNER_INDEX_MIGRATION, Metadata::enabled(0)),
+        ),
+    );
+    let id = setup.id;
+    let (_, put) = setup.block(|setup| setup.put(&id, vec![1], vec![1], None));
+    put.unwrap();
+
+    // Transfer the key and back in the same block.
+    setup.block(|setup| {
+        for (owner, new_owner) in [(id, identity(2)), (identity(2), id)] {
+            setup
+                .module_impl
+                .transfer(
+                    &owner,
+                    TransferArgs {
+                        key: vec![1].into(),
+                        alternative_owner: None,
+                        new_owner,
+                    },
+                )
+                .unwrap();
+        }
+    });
+
+    assert_eq!(
+        list_by_owner(&setup, id, None, None).keys,
+        vec![ByteVec::from(vec![1])]
+    );
+    assert!(list_by_owner(&setup, identity(2), None, None)
+        .keys
+        .is_empty());
+}
+
+#[test]
+fn list_by_owner_migration() {
+    let mut setup = owner_index_setup(2);
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1], None).unwrap();
+    setup.put(&id, vec![2], vec![1], None).unwrap();
+
+    let list = setup.module_impl.list_by_owner(
+        &identity(1),
+        ListByOwnerArgs {
+            owner: id,
+            count: None,
+            after: None,
+        },
+    );
+    assert_eq!(
+        list.unwrap_err().code(),
+        error::owner_index_inactive().code()
+    );
+
+    // The keys put before the migration are indexed when it activates.
+    setup.block(|_| ());
+    let (height, _) = setup.block(|_| ());
+    assert_eq!(height, 2);
+    assert_eq!(
+        list_by_owner(&setup, id, None, None).keys,
+        vec![ByteVec::from(vec![1]), vec![2].into()]
+    );
+
+    // And the index is kept up to date after.
+    setup.put(&id, vec![3], vec![1], None).unwrap();
+    setup
+        .module_impl
+        .transfer(
+            &id,
+            TransferArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+                new_owner: identity(2),
+            },
+        )
+        .unwrap();
+    assert_eq!(
+        list_by_owner(&setup, id, None, None).keys,
+        vec![ByteVec::from(vec![2]), vec![3].into()]
+    );
+    assert_eq!(
+        list_by_owner(&setup, identity(2), None, None).keys,
+        vec![ByteVec::from(vec![1])]
+    );
+}
+
+fn batch_put_args(entries: &[(u8, u8)]) -> BatchPutArgs {
+    BatchPutArgs {
+        entries: entries
+            .iter()
+            .map(|(k, v)| BatchPutEntry {
+                key: vec![*k].into(),
+                value: vec![*v].into(),
+            })
+            .collect(),
+        alternative_owner: None,
+    }
+}
+
+fn batch_get(setup: &Setup, keys: &[u8]) -> Result<Vec<Option<ByteVec>>, many_error::ManyError> {
+    setup
+        .module_impl
+        .batch_get(
+            &setup.id,
+            BatchGetArgs {
+                keys: keys.iter().map(|k| vec![*k].into()).collect(),
+            },
+        )
+        .map(|returns| returns.values)
+}
+
+#[test]
+fn batch_put_get_delete() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (_, put) = setup.block(|setup| {
+        setup
+            .module_impl
+            .batch_put(&id, batch_put_args(&[(1, 10), (2, 20), (3, 30)]))
+    });
+    assert!(put.is_ok());
+    assert_eq!(
+        batch_get(&setup, &[3, 1, 4]).unwrap(),
+        vec![Some(vec![30].into()), Some(vec![10].into()), None]
+    );
+
+    let (_, delete) = setup.block(|setup| {
+        setup.module_impl.batch_delete(
+            &id,
+            BatchDeleteArgs {
+                keys: vec![vec![1].into(), vec![2].into()],
+                alternative_owner: None,
+                reason: None,
+            },
+        )
+    });
+    assert!(delete.is_ok());
+    assert_eq!(
+        setup.query(&id, vec![2]).unwrap().disabled,
+        Some(Either::Left(true))
+    );
+    assert_eq!(
+        setup.get(&id, vec![3]).unwrap().value,
+        Some(vec![30].into())
+    );
+
+    // Like kvstore.get, a disabled key is an error.
+    let get = batch_get(&setup, &[3, 1]);
+    assert_eq!(get.unwrap_err().code(), error::key_disabled().code());
+}
+
+fn put_cas(setup: &mut Setup, sender: &Address, value: Vec<u8>) -> (ByteVec, u64) {
+    let (_, result) = setup.block(|setup| {
+        setup.module_impl.put_cas(
+            sender,
+            PutCasArgs {
+                value: value.into(),
+                alternative_owner: None,
+            },
+        )
+    });
+    let result = result.unwrap();
+    (result.key, result.references)
+}
+
+#[test]
+fn put_cas_dedup() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (key, references) = put_cas(&mut setup, &id, vec![1, 2, 3]);
+    assert_eq!(key, many_modules::kvstore::content_key(&[1, 2, 3]));
+    assert_eq!(references, 1);
+
+    // Putting it again does not add a reference, another owner does.
+    assert_eq!(put_cas(&mut setup, &id, vec![1, 2, 3]), (key.clone(), 1));
+    assert_eq!(
+        put_cas(&mut setup, &identity(5), vec![1, 2, 3]),
+        (key.clone(), 2)
+    );
+    assert_eq!(
+        setup.get(&identity(6), key.to_vec()).unwrap().value,
+        Some(vec![1, 2, 3].into())
+    );
+
+    // Nobody can overwrite it.
+    assert_many_err(
+        setup.put(&id, key.to_vec(), vec![4], None),
+        error::content_addressed_key(),
+    );
+
+    // The value is disabled once all its owners disabled it.
+    assert_many_err(
+        setup.disable(&identity(6), key.to_vec(), None, None),
+        error::permission_denied(),
+    );
+    let (_, disable) = setup.block(|setup| setup.disable(&id, key.to_vec(), None, None));
+    assert!(disable.is_ok());
+    assert!(setup.get(&id, key.to_vec()).is_ok());
+    let (_, disable) = setup.block(|setup| setup.disable(&identity(5), key.to_vec(), None, None));
+    assert!(disable.is_ok());
+    assert_eq!(
+        setup.get(&id, key.to_vec()).unwrap_err().code(),
+        error::key_disabled().code()
+    );
+
+    // And enabled when it is put again.
+    assert_eq!(put_cas(&mut setup, &id, vec![1, 2, 3]), (key.clone(), 1));
+    assert!(setup.get(&id, key.to_vec()).is_ok());
+}
+
+#[test]
+fn batch_put_unauthorized() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&identity(1), vec![2], vec![1], None).unwrap();
+
+    // No key is changed if one of them cannot be.
+    let put = setup
+        .module_impl
+        .batch_put(&id, batch_put_args(&[(1, 10), (2, 20)]));
+    assert_eq!(put.unwrap_err().code(), error::permission_denied().code());
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, None);
+    assert_eq!(setup.get(&id, vec![2]).unwrap().value, Some(vec![1].into()));
+}
+
+#[test]
+fn batch_delete_empty_key() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1], None).unwrap();
+
+    let delete = setup.module_impl.batch_delete(
+        &id,
+        BatchDeleteArgs {
+            keys: vec![vec![1].into(), vec![2].into()],
+            alternative_owner: None,
+            reason: None,
+        },
+    );
+    assert_eq!(
+        delete.unwrap_err().code(),
+        error::cannot_disable_empty_key().code()
+    );
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, Some(vec![1].into()));
+}
+
+#[test]
+fn batch_limit() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.module_impl.set_batch_limit(2);
+
+    let put = setup
+        .module_impl
+        .batch_put(&id, batch_put_args(&[(1, 10), (2, 20), (3, 30)]));
+    assert_eq!(put.unwrap_err().code(), error::batch_too_large(3, 2).code());
+    assert_eq!(
+        batch_get(&setup, &[1, 2, 3]).unwrap_err().code(),
+        error::batch_too_large(3, 2).code()
+    );
+
+    let put = setup
+        .module_impl
+        .batch_put(&id, batch_put_args(&[(1, 10), (1, 20)]));
+    assert_eq!(put.unwrap_err().code(), error::duplicate_batch_key().code());
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, None);
+}
+
+fn set_acl(
+    setup: &mut Setup,
+    sender: &Address,
+    key: u8,
+    acl: KeyAcl,
+) -> Result<(), many_error::ManyError> {
+    setup
+        .module_impl
+        .set_acl(
+            sender,
+            SetAclArgs {
+                key: vec![key].into(),
+                acl,
+                alternative_owner: None,
+            },
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn acl_restricted_read_and_writers() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1], None).unwrap();
+    set_acl(
+        &mut setup,
+        &id,
+        1,
+        KeyAcl {
+            read: ReadAccess::Restricted,
+            writers: BTreeSet::from([identity(2)]),
+        },
+    )
+    .unwrap();
+
+    // Only the owner and the writers can read the key.
+    assert_eq!(setup.get(&id, vec![1]).unwrap().value, Some(vec![1].into()));
+    assert_eq!(
+        setup.get(&identity(3), vec![1]).unwrap_err().code(),
+        error::read_denied().code()
+    );
+
+    // Writers put values for the owner, and the key keeps its ACL.
+    setup.put(&identity(2), vec![1], vec![2], None).unwrap();
+    let query = setup.query(&id, vec![1]).unwrap();
+    assert_eq!(query.owner, id);
+    assert_eq!(query.acl.unwrap().read, ReadAccess::Restricted);
+    assert_eq!(
+        setup.get(&identity(2), vec![1]).unwrap().value,
+        Some(vec![2].into())
+    );
+    assert_eq!(
+        setup
+            .put(&identity(3), vec![1], vec![3], None)
+            .unwrap_err()
+            .code(),
+        error::permission_denied().code()
+    );
+
+    // Writers do not own the key.
+    assert_eq!(
+        setup
+            .disable(&identity(2), vec![1], None, None)
+            .unwrap_err()
+            .code(),
+        error::permission_denied().code()
+    );
+    assert_eq!(
+        set_acl(&mut setup, &identity(2), 1, KeyAcl::default())
+            .unwrap_err()
+            .code(),
+        error::permission_denied().code()
+    );
+}
+
+#[test]
+fn acl_is_removed_on_transfer() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![1], None).unwrap();
+    assert_eq!(
+        set_acl(&mut setup, &id, 2, KeyAcl::default())
+            .unwrap_err()
+            .code(),
+        error::key_not_found().code()
+    );
+    set_acl(
+        &mut setup,
+        &id,
+        1,
+        KeyAcl {
+            read: ReadAccess::Restricted,
+            writers: BTreeSet::new(),
+        },
+    )
+    .unwrap();
+
+    setup
+        .module_impl
+        .transfer(
+            &id,
+            TransferArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+                new_owner: identity(4),
+            },
+        )
+        .unwrap();
+    assert!(setup.query(&id, vec![1]).unwrap().acl.is_none());
+    assert_eq!(
+        setup.get(&identity(3), vec![1]).unwrap().value,
+        Some(vec![1].into())
+    );
+}
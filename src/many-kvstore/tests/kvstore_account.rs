@@ -164,3 +164,43 @@ fn query_disable_reason_as() {
     assert_eq!(query_value.disabled, Some(Either::Right(reason)));
     assert_eq!(query_value.owner, account_id);
 }
+
+#[test]
+fn put_as_alt_user_with_method_acl() {
+    use many_modules::account::features::method_acl::MethodAclAccountFeature;
+    use many_modules::account::features::{FeatureInfo, FeatureSet};
+    use many_modules::account::AccountModuleBackend;
+    use std::collections::BTreeSet;
+
+    let mut setup = setup_with_account(AccountType::KvStore);
+    let account_id = setup.account_id;
+    let id = setup.id();
+
+    let acl = MethodAclAccountFeature::new(BTreeMap::from([(
+        identity(5),
+        BTreeSet::from(["kvstore.put".to_string()]),
+    )]));
+    setup
+        .inner
+        .borrow_mut()
+        .module_impl
+        .add_features(
+            &id,
+            account::AddFeaturesArgs {
+                account: account_id,
+                roles: None,
+                features: FeatureSet::from_iter([acl.as_feature()]),
+            },
+        )
+        .unwrap();
+
+    // The ACL allows putting without any role, but nothing else.
+    setup
+        .put(&identity(5), vec![1], vec![2], Some(account_id))
+        .unwrap();
+    let disable = setup.disable(&identity(5), vec![1], Some(account_id), None);
+    assert_eq!(
+        disable.unwrap_err().code(),
+        account::errors::user_needs_role(Role::CanKvStoreDisable).code()
+    );
+}
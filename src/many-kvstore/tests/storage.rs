@@ -22,7 +22,7 @@ fn load() {
             }
         }"#;
         let state = json5::from_str(init).unwrap();
-        let mut module_impl = KvStoreModuleImpl::new(state, path.clone(), false).unwrap();
+        let mut module_impl = KvStoreModuleImpl::new(state, None, path.clone(), false).unwrap();
 
         // Put some data at some non-used key
         module_impl
@@ -37,7 +37,7 @@ fn load() {
             .expect("Unable to put new data in DB");
     }
 
-    let mut module_impl = KvStoreModuleImpl::load(path, false).unwrap();
+    let mut module_impl = KvStoreModuleImpl::load(None, path, false).unwrap();
 
     // Get the data from the previous put
     let v = module_impl
@@ -90,3 +90,59 @@ fn load() {
         .unwrap();
     assert_eq!(v, vec![0].into());
 }
+
+/// Verify a persistent storage can be exported and imported into another
+#[test]
+fn export_import() {
+    let init = r#"{
+        identity: "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow",
+        acl: {
+          "010203": { owner: "maeaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaiye" }
+        }
+    }"#;
+    let mut source = KvStoreModuleImpl::new(
+        json5::from_str(init).unwrap(),
+        None,
+        tempfile::tempdir().unwrap().into_path(),
+        false,
+    )
+    .unwrap();
+    source
+        .put(
+            &identity(1),
+            PutArgs {
+                key: vec![2, 3, 4].into(),
+                value: vec![0, 1, 2, 3].into(),
+                alternative_owner: None,
+            },
+        )
+        .unwrap();
+    let export = source.export_state().unwrap();
+
+    let path = tempfile::tempdir().unwrap().into_path().join("store");
+    let mut target = KvStoreModuleImpl::import(&export, None, path, false).unwrap();
+    assert_eq!(target.export_state().unwrap().entries, export.entries);
+
+    let v = target
+        .get(
+            &identity(1),
+            GetArgs {
+                key: vec![2, 3, 4].into(),
+            },
+        )
+        .unwrap()
+        .value
+        .unwrap();
+    assert_eq!(v, vec![0, 1, 2, 3].into());
+
+    // The ACL was imported too.
+    let p = target.put(
+        &identity(2),
+        PutArgs {
+            key: vec![1, 2, 3].into(),
+            value: vec![0].into(),
+            alternative_owner: None,
+        },
+    );
+    assert_eq!(p.unwrap_err().code(), error::permission_denied().code());
+}
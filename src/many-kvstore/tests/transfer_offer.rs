@@ -0,0 +1,201 @@
+pub mod common;
+
+use crate::common::{assert_many_err, setup, Setup};
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_kvstore::error;
+use many_modules::kvstore::{
+    AcceptTransferArgs, AcceptTransferReturn, KvStoreTransferModuleBackend, OfferTransferArgs,
+    OfferTransferReturn, TransferArgs,
+};
+use many_types::clock::TestClock;
+use many_types::Timestamp;
+
+fn offer(
+    setup: &mut Setup,
+    sender: &Address,
+    key: Vec<u8>,
+    new_owner: Address,
+    expiration: Option<u64>,
+) -> Result<OfferTransferReturn, many_error::ManyError> {
+    setup.module_impl.offer_transfer(
+        sender,
+        OfferTransferArgs {
+            key: key.into(),
+            alternative_owner: None,
+            new_owner,
+            expiration: expiration.map(|secs| Timestamp::new(secs).unwrap()),
+        },
+    )
+}
+
+fn accept(
+    setup: &mut Setup,
+    sender: &Address,
+    key: Vec<u8>,
+) -> Result<AcceptTransferReturn, many_error::ManyError> {
+    setup.module_impl.accept_transfer(
+        sender,
+        AcceptTransferArgs {
+            key: key.into(),
+            alternative_owner: None,
+        },
+    )
+}
+
+#[test]
+fn offer_accept() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    offer(&mut setup, &id, vec![1], identity(2), None).unwrap();
+    // The key is not transferred until the offer is accepted.
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+
+    accept(&mut setup, &identity(2), vec![1]).unwrap();
+    let query = setup.query(&id, vec![1]).unwrap();
+    assert_eq!(query.owner, identity(2));
+    assert_eq!(query.previous_owner, Some(id));
+
+    // The offer can only be accepted once.
+    assert_many_err(
+        accept(&mut setup, &identity(2), vec![1]),
+        error::transfer_offer_not_found(),
+    );
+}
+
+#[test]
+fn offer_unauthorized() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    assert_many_err(
+        offer(&mut setup, &identity(3), vec![1], identity(3), None),
+        error::permission_denied(),
+    );
+    assert_many_err(
+        offer(&mut setup, &id, vec![2], identity(2), None),
+        error::key_not_found(),
+    );
+}
+
+#[test]
+fn accept_by_another_identity() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    offer(&mut setup, &id, vec![1], identity(2), None).unwrap();
+    assert_many_err(
+        accept(&mut setup, &identity(3), vec![1]),
+        error::transfer_offer_denied(),
+    );
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+}
+
+#[test]
+fn new_offer_replaces_previous() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    offer(&mut setup, &id, vec![1], identity(2), None).unwrap();
+    offer(&mut setup, &id, vec![1], identity(3), None).unwrap();
+    assert_many_err(
+        accept(&mut setup, &identity(2), vec![1]),
+        error::transfer_offer_denied(),
+    );
+    accept(&mut setup, &identity(3), vec![1]).unwrap();
+}
+
+#[test]
+fn offer_back_same_block() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (_, put) = setup.block(|setup| setup.put(&id, vec![1], vec![2], None));
+    put.unwrap();
+
+    // The key is offered back once the first offer is accepted, in the same
+    // block.
+    setup.block(|setup| {
+        offer(setup, &id, vec![1], identity(2), None).unwrap();
+        accept(setup, &identity(2), vec![1]).unwrap();
+        offer(setup, &identity(2), vec![1], id, None).unwrap();
+    });
+    setup.block(|setup| accept(setup, &id, vec![1]).unwrap());
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+    assert_many_err(
+        accept(&mut setup, &id, vec![1]),
+        error::transfer_offer_not_found(),
+    );
+}
+
+#[test]
+fn offer_void_after_owner_change() {
+    let mut setup = setup();
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    offer(&mut setup, &id, vec![1], identity(2), None).unwrap();
+    setup
+        .module_impl
+        .transfer(
+            &id,
+            TransferArgs {
+                key: vec![1].into(),
+                alternative_owner: None,
+                new_owner: identity(3),
+            },
+        )
+        .unwrap();
+
+    assert_many_err(
+        accept(&mut setup, &identity(2), vec![1]),
+        error::transfer_offer_not_found(),
+    );
+}
+
+#[test]
+fn offer_expiration() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    setup.block(|setup| setup.put(&id, vec![1], vec![2], None).unwrap());
+
+    // The time of the next block is 1_000_002, and increases by one every block.
+    assert_many_err(
+        setup
+            .block(|setup| offer(setup, &id, vec![1], identity(2), Some(1_000_002)))
+            .1,
+        error::transfer_offer_expired(),
+    );
+    setup.block(|setup| offer(setup, &id, vec![1], identity(2), Some(1_000_004)).unwrap());
+    setup.block(|_| {});
+    assert_many_err(
+        setup.block(|setup| accept(setup, &identity(2), vec![1])).1,
+        error::transfer_offer_expired(),
+    );
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, id);
+}
+
+#[test]
+fn offer_expiration_test_clock() {
+    let mut setup = setup();
+    let clock = TestClock::new(Timestamp::new(1_000).unwrap());
+    setup.module_impl.set_clock(clock.clone());
+    let id = setup.id;
+    setup.put(&id, vec![1], vec![2], None).unwrap();
+
+    offer(&mut setup, &id, vec![1], identity(2), Some(1_010)).unwrap();
+    clock.advance(11);
+    assert_many_err(
+        accept(&mut setup, &identity(2), vec![1]),
+        error::transfer_offer_expired(),
+    );
+
+    // Travelling back in time makes the offer valid again.
+    clock.set(Timestamp::new(1_009).unwrap());
+    accept(&mut setup, &identity(2), vec![1]).unwrap();
+    assert_eq!(setup.query(&id, vec![1]).unwrap().owner, identity(2));
+}
@@ -17,6 +17,50 @@ define_attribute_many_error!(
         9: pub fn amount_is_zero()
             => "Unable to send zero (0) token.",
         10: pub fn storage_key_not_found(key) => "Key not found in storage: {key:?}.",
+        11: pub fn invalid_schedule()
+            => "A schedule must specify exactly one of a block height or a timestamp.",
+        12: pub fn schedule_in_the_past()
+            => "Unable to schedule a transaction in the past.",
+        13: pub fn scheduled_transaction_not_found(token)
+            => "Scheduled transaction not found: {token}.",
+        14: pub fn reservation_not_found(id)
+            => "Reservation not found: {id}.",
+        15: pub fn amount_too_precise(amount, symbol, decimals)
+            => "The amount {amount} is more precise than {symbol}, which has {decimals} decimals.",
+        16: pub fn decimal_amount_mismatch(decimal_amount, amount)
+            => "The amount {decimal_amount} does not match {amount} in the decimals of the symbol.",
+        17: pub fn pull_not_authorized(account, symbol)
+            => "Not authorized to pull {symbol} from {account}.",
+        18: pub fn pull_limit_exceeded(amount, remaining)
+            => "Unable to pull {amount}, only {remaining} can be pulled in this period.",
+        19: pub fn invalid_pull_period()
+            => "The period of a pull authorization cannot be zero.",
+        20: pub fn funds_locked(locked, symbol)
+            => "Insufficient unlocked funds, {locked} {symbol} are still vesting.",
+        21: pub fn invalid_vesting_schedule()
+            => "A vesting schedule needs a duration, and a cliff no later than its end.",
+        22: pub fn balance_below_dust_threshold(remainder, threshold)
+            => "The transfer would leave {remainder}, below the minimum balance of {threshold}.",
+        23: pub fn amount_below_dust_threshold(balance, threshold)
+            => "A balance of {balance} would be below the minimum balance of {threshold}.",
+        24: pub fn swap_not_found(id) => "Swap not found: {id}.",
+        25: pub fn swap_expired(id) => "Swap {id} has expired.",
+        26: pub fn swap_expiration_in_the_past()
+            => "A swap cannot expire in the past.",
+        27: pub fn transfers_paused(symbol)
+            => "Transfers of {symbol} are paused.",
+        28: pub fn address_not_whitelisted(address, symbol)
+            => "The address {address} is not allowed to hold {symbol}.",
+        29: pub fn amount_over_transfer_limit(amount, limit)
+            => "Unable to send {amount}, transfers are limited to {limit}.",
+    }
+);
+
+define_attribute_many_error!(
+    attribute 5 => {
+        1: pub fn data_index_exists(index) => "A data index is already defined at {index}.",
+        2: pub fn data_metric_not_found(index) => "No data metric is registered at {index}.",
+        3: pub fn invalid_data_metric(reason) => "Invalid data metric: {reason}.",
     }
 );
 
@@ -28,6 +72,7 @@ define_attribute_many_error!(
         4: pub fn ticker_exists(ticker) => "Token ticker already exists on this network: {ticker}.",
         5: pub fn subresource_exhausted(key) => "Subresources are exhausted for: {key}.",
         6: pub fn invalid_ticker_length(ticker) => "Token ticker length is invalid (<3 or >5): {ticker}.",
+        7: pub fn supply_not_recorded(symbol, height) => "No supply of {symbol} was recorded at or before height {height}.",
     }
 );
 
@@ -49,5 +94,11 @@ define_application_many_error!(
         3: pub fn storage_commit_failed(desc) => "Unable to commit data to persistent storage: {desc}.",
         4: pub fn storage_open_failed(desc) => "Unable to open persistent storage: {desc}.",
         5: pub fn unable_to_load_migrations(desc) => "Unable to load migrations: {desc}.",
+        6: pub fn storage_checkpoint_failed(desc) => "Unable to manage storage checkpoints: {desc}.",
+        7: pub fn storage_quarantined(desc) => "The persistent storage is quarantined and read-only: {desc}.",
+        8: pub fn invalid_snapshot(desc) => "Invalid balances snapshot: {desc}.",
+        9: pub fn invalid_command_journal(desc) => "Invalid command journal: {desc}.",
+        10: pub fn journal_hash_mismatch(expected, actual) => "The command journal does not match the persistent storage: expected hash {expected}, got {actual}.",
+        11: pub fn invalid_state_export(desc) => "Invalid state export: {desc}.",
     }
 );
@@ -1,14 +1,18 @@
 use crate::storage::account::AccountMeta;
+use crate::storage::dust::{DustConfig, DustPolicy};
 use crate::storage::ledger_tokens::SymbolMeta;
+use crate::storage::snapshot::BalancesSnapshot;
 use many_error::ManyError;
 use many_identity::Address;
+use many_modules::abci_backend::TxOrdering;
 use many_modules::account;
 use many_modules::account::features;
 use many_modules::account::features::{FeatureInfo, TryCreateFeature};
-use many_types::ledger::{Symbol, TokenAmount};
+use many_types::ledger::{Symbol, TokenAmount, VestingGrant, VestingSchedule};
+use many_types::Timestamp;
 use std::cmp::Ordering;
 use std::collections::{BTreeMap, BTreeSet};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 #[derive(serde::Deserialize, Clone, Debug, Default)]
 pub struct MultisigFeatureArgJson {
@@ -114,6 +118,8 @@ pub struct SymbolMetaJson {
     pub decimals: u64,
     pub owner: Option<Address>,
     pub maximum: Option<TokenAmount>,
+    #[serde(default)]
+    pub dust: Option<DustConfigJson>,
 }
 
 /// Converts the JSON Symbol metadata to our internal representation
@@ -124,10 +130,94 @@ impl From<SymbolMetaJson> for SymbolMeta {
             decimals: value.decimals,
             owner: value.owner,
             maximum: value.maximum,
+            dust: value.dust.map(DustConfig::from),
         }
     }
 }
 
+/// The minimum balance of a symbol, e.g. `{ threshold: 10, policy: "sweep" }`.
+/// The policy defaults to `reject`.
+#[derive(serde::Deserialize, serde::Serialize, Clone, Debug)]
+pub struct DustConfigJson {
+    pub threshold: TokenAmount,
+    #[serde(default)]
+    pub policy: DustPolicyJson,
+}
+
+#[derive(serde::Deserialize, serde::Serialize, Clone, Copy, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DustPolicyJson {
+    #[default]
+    Reject,
+    Sweep,
+}
+
+impl From<DustConfigJson> for DustConfig {
+    fn from(value: DustConfigJson) -> Self {
+        Self {
+            threshold: value.threshold,
+            policy: match value.policy {
+                DustPolicyJson::Reject => DustPolicy::Reject,
+                DustPolicyJson::Sweep => DustPolicy::Sweep,
+            },
+        }
+    }
+}
+
+impl From<DustConfig> for DustConfigJson {
+    fn from(value: DustConfig) -> Self {
+        Self {
+            threshold: value.threshold,
+            policy: match value.policy {
+                DustPolicy::Reject => DustPolicyJson::Reject,
+                DustPolicy::Sweep => DustPolicyJson::Sweep,
+            },
+        }
+    }
+}
+
+/// Balances to import from a snapshot of another network.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct GenesisSnapshotJson {
+    /// Path to the snapshot, as exported by `genesis-from-db <store> snapshot`.
+    pub path: PathBuf,
+
+    /// The state hash of the source network at the height of the snapshot,
+    /// in hexadecimal. The snapshot is rejected if its proof does not match.
+    pub hash: String,
+}
+
+/// The order of execution of the transactions of a block, e.g.
+/// `{ kind: "priority", classes: { "tokens.mint": 0, "ledger.send": 1 } }`.
+#[derive(serde::Deserialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TxOrderingJson {
+    Arrival,
+    Priority { classes: BTreeMap<String, u64> },
+}
+
+impl From<TxOrderingJson> for TxOrdering {
+    fn from(value: TxOrderingJson) -> Self {
+        match value {
+            TxOrderingJson::Arrival => TxOrdering::Arrival,
+            TxOrderingJson::Priority { classes } => TxOrdering::Priority(classes),
+        }
+    }
+}
+
+/// Part of the initial balance of an account that unlocks over a schedule.
+/// `start` is in seconds since the epoch, `cliff` and `duration` in seconds
+/// after it.
+#[derive(serde::Deserialize, Clone, Debug)]
+pub struct VestingJson {
+    pub account: Address,
+    pub symbol: String,
+    pub amount: TokenAmount,
+    pub start: u64,
+    pub cliff: u64,
+    pub duration: u64,
+}
+
 /// The initial state schema, loaded from JSON.
 #[derive(serde::Deserialize, Clone, Debug, Default)]
 pub struct InitialStateJson {
@@ -142,6 +232,10 @@ pub struct InitialStateJson {
     pub id_store_seed: Option<u64>,
     pub id_store_keys: Option<BTreeMap<String, String>>,
     pub hash: Option<String>,
+    pub genesis_snapshot: Option<GenesisSnapshotJson>,
+    pub tx_ordering: Option<TxOrderingJson>,
+    pub block_gas_limit: Option<u64>,
+    pub vesting: Option<Vec<VestingJson>>,
 }
 
 impl InitialStateJson {
@@ -164,26 +258,79 @@ impl InitialStateJson {
         self.symbols.clone()
     }
 
+    /// The initial balances, including the ones imported from the genesis
+    /// snapshot if any. Imported balances need to be in one of the symbols
+    /// of the state, and cannot also be in the initial balances.
     pub fn balances(&self) -> Result<BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>, ManyError> {
+        let mut balances = self.initial_balances()?;
+        let Some(snapshot) = &self.genesis_snapshot else {
+            return Ok(balances);
+        };
+
+        let imported = BalancesSnapshot::read(&snapshot.path)?.verify(Some(&snapshot.hash))?;
+        for (id, b) in imported.balances {
+            let entry = balances.entry(id).or_default();
+            for (symbol, amount) in b {
+                if !self.symbols.contains_key(&symbol) {
+                    return Err(ManyError::unknown(format!(
+                        "Could not resolve imported symbol '{symbol}'"
+                    )));
+                }
+                if entry.insert(symbol, amount).is_some() {
+                    return Err(ManyError::unknown(format!(
+                        "Balance of {id} in '{symbol}' is both initial and imported"
+                    )));
+                }
+            }
+        }
+        Ok(balances)
+    }
+
+    /// The vesting grants locking part of the initial balances, by account
+    /// and symbol.
+    pub fn vesting(&self) -> Result<BTreeMap<(Address, Symbol), Vec<VestingGrant>>, ManyError> {
+        let mut grants: BTreeMap<_, Vec<_>> = BTreeMap::new();
+        for v in self.vesting.iter().flatten() {
+            let symbol = self.resolve_symbol(&v.symbol)?;
+            let start = Timestamp::new(v.start)?;
+            grants
+                .entry((v.account, symbol))
+                .or_default()
+                .push(VestingGrant {
+                    amount: v.amount.clone(),
+                    schedule: VestingSchedule {
+                        start,
+                        cliff: v.cliff,
+                        duration: v.duration,
+                    },
+                });
+        }
+        Ok(grants)
+    }
+
+    /// Find a symbol by its address or its name.
+    fn resolve_symbol(&self, token_name: &str) -> Result<Symbol, ManyError> {
+        self.symbols
+            .iter()
+            .find_map(|(s, n)| {
+                if *s == token_name || n == token_name {
+                    Some(*s)
+                } else {
+                    None
+                }
+            })
+            .ok_or_else(|| ManyError::unknown(format!("Could not resolve symbol '{token_name}'")))
+    }
+
+    fn initial_balances(
+        &self,
+    ) -> Result<BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>, ManyError> {
         self.initial
             .iter()
             .map(|(id, b)| {
                 let mut balances = BTreeMap::new();
                 for (token_name, amount) in b {
-                    let symbol = self
-                        .symbols
-                        .iter()
-                        .find_map(|(s, n)| {
-                            if *s == token_name.as_str() || n == token_name {
-                                Some(*s)
-                            } else {
-                                None
-                            }
-                        })
-                        .ok_or_else(|| {
-                            ManyError::unknown(format!("Could not resolve symbol '{token_name}'"))
-                        })?;
-                    balances.insert(symbol, amount.clone());
+                    balances.insert(self.resolve_symbol(token_name)?, amount.clone());
                 }
                 Ok((*id, balances))
             })
@@ -4,6 +4,8 @@ extern crate core;
 
 pub mod error;
 pub mod json;
+#[cfg(feature = "light-client")]
+pub mod light_client;
 pub mod migration;
 pub mod module;
 pub mod storage;
@@ -0,0 +1,182 @@
+//! Verification of the results of the ledger queries by clients, with the
+//! light client of `many-client`.
+use crate::storage::ledger_tokens::{key_for_ext_info, key_for_symbol, SYMBOLS_ROOT_DASH};
+use crate::storage::snapshot::execute_ops;
+use crate::storage::token_rules::key_for_token_rules;
+use crate::storage::BALANCES_ROOT;
+use many_client::light_client::ProofVerifier;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::ledger;
+use many_protocol::RequestMessage;
+use many_types::ledger::{Symbol, TokenAmount, TokenInfo, TokenInfoSummary};
+use many_types::ProofOperation;
+use merk::proofs::{Node, Op};
+use merk::tree::Hash;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+fn to_hash(hash: &[u8]) -> Result<Hash, ManyError> {
+    hash.try_into()
+        .map_err(|_| ManyError::unverified_response("A hash of the proof is not 32 bytes"))
+}
+
+/// The operations of a proof sent with a response, as Merk operations.
+fn merk_ops(proof: &[ProofOperation]) -> impl Iterator<Item = Result<Op, ManyError>> + '_ {
+    proof.iter().map(|op| {
+        Ok(match op {
+            ProofOperation::Child => Op::Child,
+            ProofOperation::Parent => Op::Parent,
+            ProofOperation::KeyValuePair(key, value) => {
+                Op::Push(Node::KV(key.clone().into(), value.clone().into()))
+            }
+            ProofOperation::KeyValueHash(hash) => Op::Push(Node::KVHash(to_hash(hash)?)),
+            ProofOperation::NodeHash(hash) => Op::Push(Node::Hash(to_hash(hash)?)),
+        })
+    })
+}
+
+/// The balances of an account revealed by a proof.
+fn proven_balances(
+    account: &Address,
+    entries: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> BTreeMap<Symbol, TokenAmount> {
+    let prefix = format!("{BALANCES_ROOT}{account}/").into_bytes();
+    entries
+        .range(prefix.clone()..)
+        .map_while(|(key, value)| Some((key.strip_prefix(prefix.as_slice())?, value)))
+        .filter_map(|(symbol, value)| {
+            let symbol = Symbol::from_str(std::str::from_utf8(symbol).ok()?).ok()?;
+            Some((symbol, TokenAmount::from(value.clone())))
+        })
+        .collect()
+}
+
+/// The summaries of the tokens revealed by a proof.
+fn proven_token_summaries(
+    entries: &BTreeMap<Vec<u8>, Vec<u8>>,
+) -> Result<BTreeMap<Symbol, TokenInfoSummary>, ManyError> {
+    let prefix = SYMBOLS_ROOT_DASH.as_bytes();
+    entries
+        .range(prefix.to_vec()..)
+        .map_while(|(key, value)| Some((key.strip_prefix(prefix)?, value)))
+        .map(|(symbol, value)| {
+            let symbol = std::str::from_utf8(symbol).map_err(ManyError::unverified_response)?;
+            let info: TokenInfo = decode_proven(value)?;
+            Ok((Symbol::from_str(symbol)?, info.summary))
+        })
+        .collect()
+}
+
+fn decode_proven<T: for<'b> minicbor::Decode<'b, ()>>(value: &[u8]) -> Result<T, ManyError> {
+    minicbor::decode(value).map_err(ManyError::unverified_response)
+}
+
+fn unproven(what: &str) -> ManyError {
+    ManyError::unverified_response(format!("The {what} are not the ones proven"))
+}
+
+/// Verifies the results of the ledger queries, that have to be the entries
+/// revealed by the proof:
+///
+/// - `ledger.balance`: the balances. A server can still leave a balance out
+///   of both.
+/// - `ledger.info`: the summaries of the tokens. A server can still leave a
+///   token out of both.
+/// - `tokens.info`: the information, supply included, and the extended
+///   information of the token. Its rules too, if the proof reveals them.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LedgerProofVerifier;
+
+impl LedgerProofVerifier {
+    fn verify_balance(
+        request: &RequestMessage,
+        data: &[u8],
+        entries: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<(), ManyError> {
+        let args: ledger::BalanceArgs =
+            minicbor::decode(&request.data).map_err(ManyError::deserialization_error)?;
+        let result: ledger::BalanceReturns =
+            minicbor::decode(data).map_err(ManyError::deserialization_error)?;
+        let account = args.account.unwrap_or_else(|| request.from());
+
+        let requested = args.symbols.unwrap_or_default().0;
+        let proven: BTreeMap<Symbol, TokenAmount> = proven_balances(&account, entries)
+            .into_iter()
+            .filter(|(symbol, _)| requested.is_empty() || requested.contains(symbol))
+            .collect();
+        if proven != result.balances {
+            return Err(unproven("balances"));
+        }
+        Ok(())
+    }
+
+    fn verify_info(data: &[u8], entries: &BTreeMap<Vec<u8>, Vec<u8>>) -> Result<(), ManyError> {
+        let result: ledger::InfoReturns =
+            minicbor::decode(data).map_err(ManyError::deserialization_error)?;
+        if proven_token_summaries(entries)? != result.tokens {
+            return Err(unproven("tokens"));
+        }
+        Ok(())
+    }
+
+    fn verify_token_info(
+        request: &RequestMessage,
+        data: &[u8],
+        entries: &BTreeMap<Vec<u8>, Vec<u8>>,
+    ) -> Result<(), ManyError> {
+        let args: ledger::TokenInfoArgs =
+            minicbor::decode(&request.data).map_err(ManyError::deserialization_error)?;
+        let result: ledger::TokenInfoReturns =
+            minicbor::decode(data).map_err(ManyError::deserialization_error)?;
+
+        let info: TokenInfo = entries
+            .get(key_for_symbol(&args.symbol).as_bytes())
+            .ok_or_else(|| unproven("token information"))
+            .and_then(|value| decode_proven(value))?;
+        if info != result.info {
+            return Err(unproven("token information"));
+        }
+
+        let mut extended_info: ledger::extended_info::TokenExtendedInfo = entries
+            .get(&key_for_ext_info(&args.symbol))
+            .ok_or_else(|| unproven("extended information"))
+            .and_then(|value| decode_proven(value))?;
+        if let Some(indices) = args.extended_info {
+            extended_info.retain(indices)?;
+        }
+        if extended_info != result.extended_info {
+            return Err(unproven("extended information"));
+        }
+
+        if let Some(rules) = entries.get(&key_for_token_rules(&args.symbol)) {
+            if Some(decode_proven::<ledger::TokenRules>(rules)?) != result.rules {
+                return Err(unproven("rules"));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ProofVerifier for LedgerProofVerifier {
+    fn verifies(&self, method: &str) -> bool {
+        matches!(method, "ledger.balance" | "ledger.info" | "tokens.info")
+    }
+
+    fn state_hash(
+        &self,
+        request: &RequestMessage,
+        data: &[u8],
+        proof: &[ProofOperation],
+    ) -> Result<Vec<u8>, ManyError> {
+        let (hash, entries) =
+            execute_ops(merk_ops(proof)).map_err(ManyError::unverified_response)?;
+        match request.method.as_str() {
+            "ledger.balance" => Self::verify_balance(request, data, &entries)?,
+            "ledger.info" => Self::verify_info(data, &entries)?,
+            "tokens.info" => Self::verify_token_info(request, data, &entries)?,
+            method => return Err(ManyError::invalid_method_name(method.to_string())),
+        }
+        Ok(hash.to_vec())
+    }
+}
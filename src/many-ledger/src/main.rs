@@ -1,18 +1,29 @@
 #![feature(used_with_arg)]
 
 use clap::Parser;
-use many_cli_helpers::CommonCliFlags;
+use many_cli_helpers::identity::{read_pem, IdentityConfig};
+use many_cli_helpers::state_export::{ExportStateOpt, StateExport};
+use many_cli_helpers::{read_json5, CommonCliFlags};
+use many_error::ManyError;
+use many_identity::rotation::RotatingVerifier;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, Identity};
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity_dsa::CoseKeyVerifier;
 use many_identity_webauthn::WebAuthnVerifier;
 use many_migration::MigrationConfig;
+use many_modules::abci_backend::AbciInit;
 use many_modules::account::features::Feature;
-use many_modules::{abci_backend, account, data, events, idstore, ledger};
+use many_modules::{
+    abci_backend, account, bridge, bundle, data, debug, escrow, events, idstore, ledger,
+    migrations, names,
+};
 use many_protocol::ManyUrl;
-use many_server::transport::http::HttpServer;
+use many_server::reload::{ConfigReloader, Reloadable};
+use many_server::transport::http::{CorsConfig, HttpServer};
+use many_server::validator::policy::{Policy, PolicyValidator};
 use many_server::ManyServer;
 use many_server_cache::{RequestCacheValidator, RocksDbCacheBackend};
+use many_types::node_state::NodeState;
 use std::collections::BTreeSet;
 use std::net::SocketAddr;
 use std::path::PathBuf;
@@ -26,6 +37,11 @@ use crate::idstore_webauthn::IdStoreWebAuthnModule;
 use crate::json::InitialStateJson;
 use crate::migration::MIGRATIONS;
 use crate::module::account::AccountFeatureModule;
+use crate::module::idstore::{LedgerKeyRotations, LedgerWebAuthnCredentials};
+use crate::module::observers::ObserverEventsModule;
+use crate::module::query_snapshot::{QuerySnapshotModule, QuerySnapshots};
+use crate::storage::checkpoint::CheckpointConfig;
+use crate::storage::pruning::EventRetention;
 use module::*;
 
 mod error;
@@ -35,20 +51,51 @@ mod module;
 mod storage;
 
 #[derive(Parser, Debug)]
-#[clap(args_override_self(true))]
+#[clap(args_override_self(true), subcommand_negates_reqs(true))]
 struct Opts {
     #[clap(flatten)]
     common_flags: CommonCliFlags,
 
+    #[clap(subcommand)]
+    command: Option<Command>,
+
     /// The location of a PEM file for the identity of this server.
     // The field needs to be an Option for the clap derive to work properly.
-    #[clap(long, required = true)]
+    #[clap(long, required_unless_present_any = ["identity_config", "prune_events"])]
     pem: Option<PathBuf>,
 
+    /// A JSON5 file listing the key storages of the identity of this server,
+    /// in order of precedence. Replaces `--pem`.
+    #[clap(long, conflicts_with = "pem")]
+    identity_config: Option<PathBuf>,
+
     /// The address and port to bind to for the MANY Http server.
     #[clap(long, short, default_value = "127.0.0.1:8000")]
     addr: SocketAddr,
 
+    /// The address and port to bind to for the MANY gRPC server, in addition
+    /// to the Http server. No gRPC server is started if unspecified.
+    /// This requires the feature "grpc" to be enabled.
+    #[cfg(feature = "grpc")]
+    #[clap(long)]
+    grpc_addr: Option<SocketAddr>,
+
+    /// PEM certificate chain of the gRPC server. Enables TLS.
+    #[cfg(feature = "grpc")]
+    #[clap(long, requires_all = &["grpc_addr", "grpc_tls_key"])]
+    grpc_tls_cert: Option<PathBuf>,
+
+    /// PEM private key of the gRPC server.
+    #[cfg(feature = "grpc")]
+    #[clap(long, requires = "grpc_tls_cert")]
+    grpc_tls_key: Option<PathBuf>,
+
+    /// PEM certificate of the CA signing client certificates. Clients without
+    /// a certificate signed by this CA are rejected (mTLS).
+    #[cfg(feature = "grpc")]
+    #[clap(long, requires = "grpc_tls_cert")]
+    grpc_client_ca: Option<PathBuf>,
+
     /// Uses an ABCI application module.
     #[clap(long)]
     abci: bool,
@@ -57,6 +104,13 @@ struct Opts {
     #[clap(long)]
     state: Option<PathBuf>,
 
+    /// Path of a state export, as written by `export-state`, to create the
+    /// persistent store from instead of an initial state. The store keeps
+    /// the height of the export, so the chain needs to start at the next
+    /// height.
+    #[clap(long, conflicts_with = "state")]
+    import_state: Option<PathBuf>,
+
     /// Path to a persistent store database (rocksdb).
     // The field needs to be an Option for the clap derive to work properly.
     #[clap(long, required = true)]
@@ -73,6 +127,23 @@ struct Opts {
     #[clap(long)]
     allow_origin: Option<Vec<ManyUrl>>,
 
+    /// Origins allowed to call this server from a browser, e.g.
+    /// `https://wallet.example`, or `*` for any origin. Preflight requests are
+    /// then answered with the CORS headers. Multiple occurences of this
+    /// argument can be given.
+    #[clap(long)]
+    cors_origin: Option<Vec<String>>,
+
+    /// Request headers browsers can send from the CORS origins, besides
+    /// `Content-Type`. Multiple occurences of this argument can be given.
+    #[clap(long)]
+    cors_header: Option<Vec<String>>,
+
+    /// How long browsers can cache the answer to a preflight request, in
+    /// seconds.
+    #[clap(long)]
+    cors_max_age: Option<u64>,
+
     /// A list of initial balances. This will be in addition to the genesis
     /// state file in --state and should only be used for testing.
     /// Each transaction MUST be of the format:
@@ -103,34 +174,142 @@ struct Opts {
     /// Path to a JSON file containing an array of MANY addresses
     /// Only addresses from this array will be able to execute commands, e.g., send, put, ...
     /// Any addresses will be able to execute queries, e.g., balance, get, ...
+    /// The file is read again on SIGHUP.
     #[clap(long)]
     allow_addrs: Option<PathBuf>,
 
+    /// Path to a JSON5 file containing the policy of the methods allowed per
+    /// sender. See `many_server::validator::policy` for the format. Requests
+    /// are denied unless a rule of the policy allows them. The file is read
+    /// again on SIGHUP.
+    #[clap(long)]
+    policy: Option<PathBuf>,
+
     /// Database path to the request cache to validate duplicate messages.
     /// If unspecified, the server will not verify transactions for duplicate
     /// messages.
     #[clap(long)]
     cache_db: Option<PathBuf>,
+
+    /// Directory where a checkpoint of the persistent store is saved after
+    /// every block. If unspecified, no checkpoints are created.
+    #[clap(long)]
+    checkpoints: Option<PathBuf>,
+
+    /// Number of most recent checkpoints to keep. All checkpoints are kept if
+    /// unspecified.
+    #[clap(long, requires = "checkpoints")]
+    checkpoint_retention: Option<u64>,
+
+    /// Directory where a snapshot of the persistent store is taken after
+    /// every block. The ledger and events queries execute against the
+    /// snapshot of the last committed block, concurrently with the commands.
+    /// If unspecified, queries wait for the commands to execute.
+    #[clap(long, requires = "abci")]
+    query_snapshots: Option<PathBuf>,
+
+    /// Check the integrity of the persistent store before starting the
+    /// server. If any issue is found, the store is quarantined: it stays
+    /// read-only and blocks are not committed.
+    #[clap(long)]
+    check_integrity: bool,
+
+    /// Check the integrity of the persistent store every this number of
+    /// blocks, quarantining it if any issue is found.
+    #[clap(long)]
+    integrity_check_interval: Option<u64>,
+
+    /// Also recompute the supply and data indices when checking the
+    /// integrity of the persistent store, logging the ones that are out of
+    /// date.
+    #[clap(long)]
+    verify_indices: bool,
+
+    /// Check the integrity of the persistent store, print the issues found,
+    /// then exit without starting the server. Exits with a non-zero status if
+    /// any issue is found.
+    #[clap(long, conflicts_with_all = &["clean", "prune_events"])]
+    verify_state: bool,
+
+    /// Check the command journal left over by a crash while committing a
+    /// block, if any, before loading the persistent store. Refuse to start
+    /// if it is corrupt, rather than discarding it.
+    #[clap(long, requires = "abci")]
+    verify_journal: bool,
+
+    /// Add the `debug` endpoints, e.g. `debug.stateHashes` to compare the
+    /// hashes of the subsystems of the store with another node when their
+    /// app hashes differ. They iterate over the whole store, so they should
+    /// not be reachable by the public.
+    #[clap(long)]
+    debug_endpoints: bool,
+
+    /// Prune the events of the persistent store that are outside of the
+    /// retention window, compact the store to reclaim their space, then exit
+    /// without starting the server.
+    /// This changes the state hash, so it must not be used on the store of
+    /// a blockchain node. Blockchain nodes need to use the Event Pruning
+    /// Migration, which prunes at the same height on every node.
+    #[clap(long, conflicts_with_all = &["clean", "state", "import_state"])]
+    prune_events: bool,
+
+    /// Keep the events logged in this number of most recent blocks when
+    /// pruning.
+    #[clap(long, requires = "prune_events")]
+    event_retention_height: Option<u64>,
+
+    /// Keep this number of most recent events when pruning.
+    #[clap(long, requires = "prune_events")]
+    event_retention_count: Option<u64>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum Command {
+    /// Export every key of the persistent store, e.g. balances, accounts and
+    /// tokens, to start another network from it with `--import-state`. Older
+    /// heights are exported from their checkpoint in `--checkpoints`.
+    ExportState(ExportStateOpt),
 }
 
 fn main() {
     let Opts {
         common_flags,
+        command,
         pem,
+        identity_config,
         addr,
         abci,
         mut state,
+        mut import_state,
         persistent,
         clean,
         migrations_config,
         allow_origin,
+        cors_origin,
+        cors_header,
+        cors_max_age,
         allow_addrs,
+        policy,
         list_migrations,
         cache_db,
+        checkpoints,
+        checkpoint_retention,
+        query_snapshots,
+        check_integrity,
+        integrity_check_interval,
+        verify_indices,
+        verify_state,
+        verify_journal,
+        debug_endpoints,
+        prune_events,
+        event_retention_height,
+        event_retention_count,
         ..
     } = Opts::parse();
 
     common_flags.init_logging().unwrap();
+    let mut reloader = ConfigReloader::new();
+    reloader.add("log level", move || common_flags.reload_logging());
 
     debug!("{:?}", Opts::parse());
     info!(
@@ -146,10 +325,55 @@ fn main() {
         return;
     }
 
-    // Safe unwrap.
-    // At this point the Options should contain a value.
-    let pem = pem.unwrap();
-    let persistent = persistent.unwrap();
+    let persistent = persistent.expect("--persistent is required.");
+
+    if let Some(Command::ExportState(opt)) = command {
+        // The checkpoint of the height if there is one, the store otherwise.
+        let path = opt
+            .height
+            .zip(checkpoints)
+            .map(|(height, path)| CheckpointConfig::new(path).path_for_height(height))
+            .filter(|path| path.exists())
+            .unwrap_or(persistent);
+        let storage = storage::LedgerStorage::load(path, false, None)
+            .expect("Could not open the persistent store.");
+        let export = storage.export_state().expect("Could not export the state.");
+        if let Some(height) = opt.height.filter(|h| *h != export.height) {
+            panic!(
+                "No checkpoint at height {height}, and the store is at height {}.",
+                export.height
+            );
+        }
+        export
+            .write(&opt.output, opt.format)
+            .expect("Could not write the state export.");
+        println!(
+            "Exported {} key(s) at height {}.",
+            export.entries.len(),
+            export.height
+        );
+        return;
+    }
+
+    if prune_events {
+        let retention = EventRetention {
+            height: event_retention_height,
+            count: event_retention_count,
+        };
+        if retention.is_empty() {
+            panic!("--prune-events requires --event-retention-height or --event-retention-count");
+        }
+
+        let mut storage = storage::LedgerStorage::load(&persistent, false, None)
+            .expect("Could not open the persistent store.");
+        let pruned = storage
+            .prune_events(&retention)
+            .expect("Could not prune events.");
+        drop(storage);
+        storage::pruning::compact_store(&persistent).expect("Could not compact the store.");
+        println!("Pruned {pruned} event(s).");
+        return;
+    }
 
     if clean {
         // Delete the persistent storage.
@@ -164,24 +388,47 @@ fn main() {
     } else if persistent.exists() {
         // Initial state is ignored.
         state = None;
+        import_state = None;
     }
 
-    let pem = std::fs::read_to_string(pem).expect("Could not read PEM file.");
-    let key = CoseKeyIdentity::from_pem(pem).expect("Could not generate identity from PEM file.");
+    if verify_journal && persistent.exists() {
+        match storage::journal::read_journal(&persistent)
+            .expect("Invalid command journal, refusing to start.")
+        {
+            Some(record) => info!(
+                "Recovering the commit of block {} from the command journal",
+                record.height
+            ),
+            None => info!("No command journal to recover from"),
+        }
+    }
+
+    let key: Box<dyn Identity> = if let Some(path) = identity_config {
+        Box::new(
+            IdentityConfig::read(path)
+                .and_then(|config| config.load())
+                .expect("Could not load the identity configuration."),
+        )
+    } else {
+        // Safe unwrap, one of `--pem` or `--identity-config` is required.
+        Box::new(read_pem(pem.unwrap()).expect("Could not generate identity from PEM file."))
+    };
     info!(address = key.address().to_string().as_str());
 
     let state: Option<InitialStateJson> =
         state.map(|p| InitialStateJson::read(p).expect("Could not read state file."));
+    let import_state: Option<StateExport> =
+        import_state.map(|p| StateExport::read(&p).expect("Could not read the state export."));
 
     info!("Loading migrations from {migrations_config:?}");
     let maybe_migrations = migrations_config.map(|file| {
         let content = std::fs::read_to_string(file)
             .expect("Could not read file passed to --migrations_config");
         let config: MigrationConfig = serde_json::from_str(&content).unwrap();
-        config.strict()
+        config.strict().with_node(key.address())
     });
 
-    let module_impl = if persistent.exists() {
+    let mut module_impl = if persistent.exists() {
         if state.is_some() {
             warn!(
                 r#"
@@ -204,6 +451,9 @@ fn main() {
         }
 
         LedgerModuleImpl::load(maybe_migrations, persistent, abci).unwrap()
+    } else if let Some(export) = import_state {
+        LedgerModuleImpl::import(&export, maybe_migrations, persistent, abci)
+            .expect("Could not import the state export.")
     } else if let Some(state) = state {
         #[cfg(feature = "balance_testing")]
         {
@@ -241,36 +491,101 @@ fn main() {
     } else {
         panic!("Persistent store or staging file not found.")
     };
+
+    module_impl
+        .storage_mut()
+        .set_checkpoint_config(checkpoints.map(|path| CheckpointConfig {
+            path,
+            retention: checkpoint_retention,
+        }));
+    module_impl
+        .storage_mut()
+        .set_integrity_check_interval(integrity_check_interval);
+    module_impl
+        .storage_mut()
+        .set_integrity_check_indices(verify_indices);
+    if verify_state {
+        let report = module_impl
+            .storage()
+            .check_integrity()
+            .expect("Could not check the integrity of the persistent store.");
+        for issue in report.issues.iter().chain(&report.index_issues) {
+            println!("{issue}");
+        }
+        println!("Checked {} node(s): {report}.", report.nodes);
+        std::process::exit(if report.is_ok() { 0 } else { 1 });
+    }
+    if check_integrity {
+        let report = module_impl
+            .storage_mut()
+            .enforce_integrity()
+            .expect("Could not check the integrity of the persistent store.");
+        if !report.is_ok() {
+            warn!("Starting with a quarantined persistent store: {report}");
+        }
+    }
+
+    let query_snapshots = query_snapshots.map(|path| Arc::new(QuerySnapshots::new(path)));
+    module_impl
+        .set_query_snapshots(query_snapshots.clone())
+        .expect("Could not create the query snapshot.");
     let module_impl = Arc::new(Mutex::new(module_impl));
 
     let many = ManyServer::simple(
         "many-ledger",
         key,
-        (
-            AnonymousVerifier,
-            CoseKeyVerifier,
-            WebAuthnVerifier::new(allow_origin),
+        RotatingVerifier::new(
+            (
+                AnonymousVerifier,
+                CoseKeyVerifier,
+                WebAuthnVerifier::new(allow_origin)
+                    .with_credentials(LedgerWebAuthnCredentials(module_impl.clone())),
+            ),
+            LedgerKeyRotations(module_impl.clone()),
         ),
         Some(env!("CARGO_PKG_VERSION").to_string()),
     );
 
     {
         let mut s = many.lock().unwrap();
-        s.add_module(ledger::LedgerModule::new(module_impl.clone()));
+        // Requests to the modules below wait for the bundles executing.
+        let gate = bundle::BundleGate::default();
+        let ledger_module = ledger::LedgerModule::new(module_impl.clone());
+        if let Some(snapshots) = &query_snapshots {
+            s.add_module(gate.module(QuerySnapshotModule::new(
+                ledger_module,
+                snapshots.clone(),
+                ledger::LedgerModule::new,
+            )));
+        } else {
+            s.add_module(gate.module(ledger_module));
+        }
         let ledger_command_module = ledger::LedgerCommandsModule::new(module_impl.clone());
         if let Some(path) = allow_addrs {
-            let allow_addrs: BTreeSet<Address> =
-                json5::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
-            s.add_module(AllowAddrsModule {
+            let allow_addrs: Reloadable<BTreeSet<Address>> =
+                Reloadable::new(read_json5(&path).unwrap());
+            reloader.add_reloadable("allow addrs", &allow_addrs, move || read_json5(&path));
+            s.add_module(gate.module(AllowAddrsModule {
                 inner: ledger_command_module,
                 allow_addrs,
-            });
+            }));
         } else {
-            s.add_module(ledger_command_module);
+            s.add_module(gate.module(ledger_command_module));
         }
-        s.add_module(events::EventsModule::new(module_impl.clone()));
-        s.add_module(ledger::LedgerTokensModule::new(module_impl.clone()));
-        s.add_module(ledger::LedgerMintBurnModule::new(module_impl.clone()));
+        let events_module = ObserverEventsModule::new(module_impl.clone());
+        if let Some(snapshots) = &query_snapshots {
+            s.add_module(gate.module(QuerySnapshotModule::new(
+                events_module,
+                snapshots.clone(),
+                ObserverEventsModule::new,
+            )));
+        } else {
+            s.add_module(gate.module(events_module));
+        }
+        s.add_module(gate.module(events::EventsProofModule::new(module_impl.clone())));
+        s.add_module(gate.module(ledger::LedgerTokensModule::new(module_impl.clone())));
+        s.add_module(gate.module(ledger::LedgerMintBurnModule::new(module_impl.clone())));
+        s.add_module(gate.module(ledger::LedgerCurveModule::new(module_impl.clone())));
 
         let idstore_module = idstore::IdStoreModule::new(module_impl.clone());
         #[cfg(feature = "webauthn_testing")]
@@ -281,28 +596,100 @@ fn main() {
             } = Opts::parse();
 
             if disable_webauthn_only_for_testing {
-                s.add_module(IdStoreWebAuthnModule {
+                s.add_module(gate.module(IdStoreWebAuthnModule {
                     inner: idstore_module,
                     check_webauthn: false,
-                });
+                }));
             } else {
-                s.add_module(idstore_module);
+                s.add_module(gate.module(idstore_module));
             }
         }
         #[cfg(not(feature = "webauthn_testing"))]
-        s.add_module(idstore_module);
+        s.add_module(gate.module(idstore_module));
 
-        s.add_module(AccountFeatureModule::new(
+        s.add_module(gate.module(AccountFeatureModule::new(
             account::AccountModule::new(module_impl.clone()),
-            [Feature::with_id(0), Feature::with_id(1)],
-        ));
-        s.add_module(account::features::multisig::AccountMultisigModule::new(
-            module_impl.clone(),
-        ));
-        s.add_module(data::DataModule::new(module_impl.clone()));
+            [
+                Feature::with_id(0),
+                Feature::with_id(1),
+                Feature::with_id(4),
+                Feature::with_id(5),
+                Feature::with_id(6),
+            ],
+        )));
+        s.add_module(
+            gate.module(account::features::multisig::AccountMultisigModule::new(
+                module_impl.clone(),
+            )),
+        );
+        s.add_module(gate.module(data::DataModule::new(module_impl.clone())));
+        s.add_module(gate.module(names::NamesModule::new(module_impl.clone())));
+        s.add_module(gate.module(escrow::EscrowModule::new(module_impl.clone())));
+        s.add_module(gate.module(bridge::BridgeModule::new(module_impl.clone())));
+        s.add_module(gate.module(migrations::MigrationsModule::new(module_impl.clone())));
+
+        // Bundles can contain messages to any of the modules above.
+        let modules = s.modules();
+        s.add_module(
+            bundle::BundleModule::new(module_impl.clone(), modules).with_gate(gate.clone()),
+        );
+
+        if debug_endpoints {
+            s.add_module(gate.module(debug::DebugModule::new(module_impl.clone())));
+        }
+
+        if let Some(path) = policy {
+            // The commands are the same as the ones given to the ABCI frontend.
+            let AbciInit { endpoints, .. } =
+                abci_backend::ManyAbciModuleBackend::init(&mut *module_impl.lock().unwrap())
+                    .unwrap();
+            let commands: Vec<String> = endpoints
+                .into_iter()
+                .filter(|(_, info)| info.is_command)
+                .map(|(name, _)| name)
+                .collect();
+            let read_policy = move || {
+                read_json5::<Policy>(&path)
+                    .map(|policy| PolicyValidator::new(policy).with_commands(commands.clone()))
+            };
+            let policy_validator = Reloadable::new(read_policy().unwrap());
+            reloader.add_reloadable("policy", &policy_validator, read_policy);
+            s.add_validator(policy_validator);
+        }
+
+        {
+            let module_impl = module_impl.clone();
+            s.add_readiness_check("storage", move || {
+                let module_impl = module_impl
+                    .lock()
+                    .map_err(|_| "A request panicked with the storage locked".to_string())?;
+                module_impl
+                    .storage()
+                    .get_height()
+                    .map(|_| ())
+                    .map_err(|e| e.to_string())
+            });
+        }
+
+        {
+            let module_impl = module_impl.clone();
+            s.set_node_state_fn(move || {
+                let module_impl = module_impl.lock().map_err(|_| {
+                    ManyError::unknown("A request panicked with the storage locked")
+                })?;
+                let storage = module_impl.storage();
+                Ok(NodeState {
+                    height: storage.get_height()?,
+                    time: storage.now(),
+                    app_hash: Some(storage.hash()),
+                })
+            });
+        }
+
         if abci {
             s.set_timeout(u64::MAX);
-            s.add_module(abci_backend::AbciModule::new(module_impl));
+            s.set_enforce_deadlines(false);
+            s.add_module(gate.module(abci_backend::AbciModule::new(module_impl)));
         }
 
         if let Some(p) = cache_db {
@@ -310,15 +697,60 @@ fn main() {
         }
     }
 
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    #[cfg(feature = "grpc")]
+    {
+        use many_server::transport::grpc::GrpcServer;
+
+        let Opts {
+            grpc_addr,
+            grpc_tls_cert,
+            grpc_tls_key,
+            grpc_client_ca,
+            ..
+        } = Opts::parse();
+
+        if let Some(grpc_addr) = grpc_addr {
+            let mut grpc_server = GrpcServer::new(many.clone());
+            if let (Some(cert), Some(key)) = (grpc_tls_cert, grpc_tls_key) {
+                let read = |path: PathBuf| std::fs::read(path).expect("Could not read TLS file.");
+                grpc_server = grpc_server.with_tls(read(cert), read(key), grpc_client_ca.map(read));
+            }
+
+            for signal in [signal_hook::consts::SIGTERM, signal_hook::consts::SIGINT] {
+                signal_hook::flag::register(signal, grpc_server.term_signal())
+                    .expect("Could not register signal handler");
+            }
+
+            // The Http server blocks the main thread, so the gRPC server runs
+            // on the runtime workers.
+            runtime.spawn(async move {
+                if let Err(e) = grpc_server.bind(grpc_addr).await {
+                    warn!("gRPC server stopped: {e}");
+                }
+            });
+        }
+    }
+
+    let cors = cors_origin.map(|allowed_origins| CorsConfig {
+        allowed_origins,
+        allowed_headers: cors_header.unwrap_or_default(),
+        max_age: cors_max_age.map(std::time::Duration::from_secs),
+    });
     let mut many_server = HttpServer::new(many);
+    if let Some(cors) = cors {
+        many_server = many_server.with_cors(cors);
+    }
 
-    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
+    // SIGHUP reloads the configuration instead of stopping the server.
+    reloader
+        .reload_on_sighup()
         .expect("Could not register signal handler");
-    signal_hook::flag::register(signal_hook::consts::SIGHUP, many_server.term_signal())
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, many_server.term_signal())
         .expect("Could not register signal handler");
     signal_hook::flag::register(signal_hook::consts::SIGINT, many_server.term_signal())
         .expect("Could not register signal handler");
 
-    let runtime = tokio::runtime::Runtime::new().unwrap();
     runtime.block_on(many_server.bind(addr)).unwrap();
 }
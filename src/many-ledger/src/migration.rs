@@ -3,14 +3,32 @@ use linkme::distributed_slice;
 use many_error::ManyError;
 use many_migration::{InnerMigration, MigrationSet};
 
+pub mod account_delegation;
+pub mod acknowledgment;
+pub mod batched_writes;
 pub mod block_9400;
+pub mod bonding_curve;
+pub mod bridge;
 pub mod data;
+pub mod data_metrics;
+pub mod decimal_amount;
 pub mod disable_token_create;
 pub mod disable_token_mint;
+pub mod escrow;
+pub mod event_chain;
+pub mod event_index;
+pub mod event_pruning;
+pub mod key_range;
 pub mod legacy_remove_roles;
 pub mod memo;
+pub mod multisig_comments;
+pub mod names;
+pub mod supply;
+pub mod swap;
 pub mod token_create;
+pub mod token_rules;
 pub mod tokens;
+pub mod vesting;
 
 #[cfg(feature = "migration_testing")]
 pub mod dummy_hotfix;
@@ -18,7 +18,8 @@ pub static NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX: DataIndex =
 
 const BALANCES_ROOT_BYTES: &[u8] = b"/balances";
 
-fn get_data_from_db(storage: &InnerStorage) -> Result<(u64, u64), ManyError> {
+/// The number of accounts with a balance, and of those with a non-zero one.
+pub(crate) fn get_data_from_db(storage: &InnerStorage) -> Result<(u64, u64), ManyError> {
     let mut num_unique_accounts: u64 = 0;
     let mut num_non_zero_account: u64 = 0;
 
@@ -0,0 +1,11 @@
+use crate::migration::MIGRATIONS;
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+
+#[distributed_slice(MIGRATIONS)]
+pub static ESCROW_MIGRATION: InnerMigration<merk::Merk, ManyError> = InnerMigration::new_trigger(
+    false,
+    "Escrow Migration",
+    "Enable escrows locking funds until a condition releases them",
+);
@@ -0,0 +1,19 @@
+use crate::migration::MIGRATIONS;
+use crate::storage::event_index::index_all_events;
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn initialize(storage: &mut merk::Merk, _: &HashMap<String, Value>) -> Result<(), ManyError> {
+    index_all_events(storage)
+}
+
+#[distributed_slice(MIGRATIONS)]
+pub static EVENT_INDEX_MIGRATION: InnerMigration<merk::Merk, ManyError> =
+    InnerMigration::new_initialize(
+        initialize,
+        "Event Index Migration",
+        "Index the events by kind, address and symbol, and keep the index up to date for new events",
+    );
@@ -0,0 +1,37 @@
+use crate::migration::MIGRATIONS;
+use crate::storage::pruning::{prune_events, EventRetention};
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn retention_param(extra: &HashMap<String, Value>, name: &str) -> Result<Option<u64>, ManyError> {
+    extra
+        .get(name)
+        .map(|v| serde_json::from_value(v.clone()).map_err(ManyError::deserialization_error))
+        .transpose()
+}
+
+fn prune(storage: &mut merk::Merk, extra: &HashMap<String, Value>) -> Result<(), ManyError> {
+    let retention = EventRetention {
+        height: retention_param(extra, "retention_height")?,
+        count: retention_param(extra, "retention_count")?,
+    };
+    if retention.is_empty() {
+        return Err(ManyError::unknown(
+            "Missing extra parameter 'retention_height' or 'retention_count' for Event Pruning Migration",
+        ));
+    }
+
+    prune_events(storage, &retention).map(|_| ())
+}
+
+#[distributed_slice(MIGRATIONS)]
+pub static EVENT_PRUNING_MIGRATION: InnerMigration<merk::Merk, ManyError> =
+    InnerMigration::new_initialize_update(
+        prune,
+        prune,
+        "Event Pruning Migration",
+        "Prune the events outside of the retention window configured by the `retention_height` and `retention_count` extra parameters, at every block",
+    );
@@ -0,0 +1,82 @@
+use crate::error;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::InnerStorage;
+use many_error::ManyError;
+use many_migration::{KeyRange, KeyRangeProgress, KeyRangeStorage};
+use merk::Op;
+
+/// The progress of a key-range hotfix.
+fn key_for_progress(name: &str) -> Vec<u8> {
+    format!("/config/key_range_hotfix/{name}").into_bytes()
+}
+
+/// The access to the ledger storage of the key-range hotfixes, e.g.
+/// `InnerMigration::new_key_range_hotfix::<MerkKeyRange>(...)`. The progress
+/// of a hotfix is part of the state, like the entries it rewrites.
+pub struct MerkKeyRange;
+
+impl KeyRangeStorage<InnerStorage, ManyError> for MerkKeyRange {
+    fn count_entries(storage: &InnerStorage, range: &KeyRange) -> Result<u64, ManyError> {
+        let mut count = 0;
+        for entry in
+            LedgerIterator::key_range(storage, range.start.to_vec(), range.end.map(<[u8]>::to_vec))
+        {
+            entry.map_err(ManyError::unknown)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    fn entries(
+        storage: &InnerStorage,
+        range: &KeyRange,
+        after: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, ManyError> {
+        // The first key after `after` is `after` followed by a zero.
+        let lower_bound = match after {
+            Some(after) => [after, &[0]].concat(),
+            None => range.start.to_vec(),
+        };
+        LedgerIterator::key_range(storage, lower_bound, range.end.map(<[u8]>::to_vec))
+            .take(limit as usize)
+            .map(|entry| {
+                entry
+                    .map(|(key, value)| (key.into(), value))
+                    .map_err(ManyError::unknown)
+            })
+            .collect()
+    }
+
+    fn put_entries(
+        storage: &mut InnerStorage,
+        entries: Vec<(Vec<u8>, Vec<u8>)>,
+    ) -> Result<(), ManyError> {
+        let batch = entries
+            .into_iter()
+            .map(|(key, value)| (key, Op::Put(value)))
+            .collect::<Vec<_>>();
+        storage.apply(&batch).map_err(error::storage_apply_failed)
+    }
+
+    fn progress(storage: &InnerStorage, name: &str) -> Result<Option<KeyRangeProgress>, ManyError> {
+        storage
+            .get(&key_for_progress(name))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    fn set_progress(
+        storage: &mut InnerStorage,
+        name: &str,
+        progress: &KeyRangeProgress,
+    ) -> Result<(), ManyError> {
+        storage
+            .apply(&[(
+                key_for_progress(name),
+                Op::Put(minicbor::to_vec(progress).map_err(ManyError::serialization_error)?),
+            )])
+            .map_err(error::storage_apply_failed)
+    }
+}
@@ -45,7 +45,15 @@ fn update_multisig_submit_events(storage: &mut InnerStorage) -> Result<(), ManyE
     let mut batch = Vec::new();
 
     for log in iter_through_events(storage) {
-        let (key, EventLog { id, time, content }) = log?;
+        let (
+            key,
+            EventLog {
+                id,
+                time,
+                content,
+                chain_hash,
+            },
+        ) = log?;
 
         if let EventInfo::AccountMultisigSubmit {
             submitter,
@@ -78,6 +86,7 @@ fn update_multisig_submit_events(storage: &mut InnerStorage) -> Result<(), ManyE
                 let new_log = EventLog {
                     id,
                     time,
+                    chain_hash,
                     content: EventInfo::AccountMultisigSubmit {
                         submitter,
                         account,
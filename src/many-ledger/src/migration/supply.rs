@@ -0,0 +1,19 @@
+use crate::migration::MIGRATIONS;
+use crate::storage::supply::record_all_supplies;
+use linkme::distributed_slice;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+use serde_json::Value;
+use std::collections::HashMap;
+
+fn initialize(storage: &mut merk::Merk, _: &HashMap<String, Value>) -> Result<(), ManyError> {
+    record_all_supplies(storage)
+}
+
+#[distributed_slice(MIGRATIONS)]
+pub static SUPPLY_ACCOUNTING_MIGRATION: InnerMigration<merk::Merk, ManyError> =
+    InnerMigration::new_initialize(
+        initialize,
+        "Supply Accounting Migration",
+        "Record the supply of every token at each height it changes, counting the burned tokens from the activation",
+    );
@@ -1,29 +1,46 @@
 use crate::error;
 use crate::json::InitialStateJson;
+use crate::module::query_snapshot::QuerySnapshots;
 use crate::storage::LedgerStorage;
+use many_cli_helpers::state_export::StateExport;
 use many_error::ManyError;
 use many_migration::MigrationConfig;
+use many_modules::Acknowledgment;
 use std::fmt::Debug;
 use std::path::Path;
+use std::sync::Arc;
 use tracing::info;
 
 mod abci;
 pub mod account;
 pub mod allow_addrs;
+mod bonding_curve;
+mod bridge;
+mod bundle;
 mod data;
+mod debug;
+mod escrow;
 mod event;
-mod idstore;
+pub mod idstore;
 pub mod idstore_webauthn;
 mod ledger;
 mod ledger_commands;
 mod ledger_mintburn;
 mod ledger_tokens;
+mod migrations;
 mod multisig;
+mod names;
+pub mod observers;
+pub mod query_snapshot;
 
 /// A simple ledger that keeps transactions in memory.
 #[derive(Debug)]
 pub struct LedgerModuleImpl {
     storage: LedgerStorage,
+
+    /// Snapshots of the committed state to refresh after every commit, if
+    /// queries are served from snapshots.
+    query_snapshots: Option<Arc<QuerySnapshots>>,
 }
 
 impl LedgerModuleImpl {
@@ -35,6 +52,7 @@ impl LedgerModuleImpl {
     ) -> Result<Self, ManyError> {
         let symbols = state.symbols();
         let balances = state.balances()?;
+        let vesting = state.vesting()?;
         let symbols_meta = state
             .symbols_meta
             .map(|b| b.into_iter().map(|(k, v)| (k, v.into())).collect());
@@ -45,6 +63,7 @@ impl LedgerModuleImpl {
         let storage = LedgerStorage::new(persistence_store_path, blockchain)?
             .with_migrations(migration_config)?
             .with_balances(&state.identity, &symbols, &balances)?
+            .with_vesting(vesting)?
             .with_idstore(state.id_store_seed, state.id_store_keys)?
             .with_tokens(
                 &symbols,
@@ -54,6 +73,8 @@ impl LedgerModuleImpl {
                 balances,
             )?
             .with_account(state.account_identity, accounts)?
+            .with_tx_ordering(state.tx_ordering.map(Into::into))?
+            .with_block_gas_limit(state.block_gas_limit)?
             .build()?;
 
         if let Some(h) = state.hash {
@@ -71,7 +92,28 @@ impl LedgerModuleImpl {
 
         tracing::debug!("Final migrations: {:?}", storage.migrations());
 
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            query_snapshots: None,
+        })
+    }
+
+    /// Create the persistent store from a state export, then load it.
+    pub fn import<P: AsRef<Path>>(
+        export: &StateExport,
+        migrations: Option<MigrationConfig>,
+        persistence_store_path: P,
+        blockchain: bool,
+    ) -> Result<Self, ManyError> {
+        LedgerStorage::import_state(&persistence_store_path, export)?;
+        let module_impl = Self::load(migrations, persistence_store_path, blockchain)?;
+
+        info!(
+            height = export.height,
+            exported_hash = hex::encode(&export.hash).as_str(),
+            hash = hex::encode(module_impl.storage.hash()).as_str()
+        );
+        Ok(module_impl)
     }
 
     pub fn load<P: AsRef<Path>>(
@@ -83,7 +125,43 @@ impl LedgerModuleImpl {
 
         tracing::debug!("Final migrations: {:?}", storage.migrations());
 
-        Ok(Self { storage })
+        Ok(Self {
+            storage,
+            query_snapshots: None,
+        })
+    }
+
+    /// Access the underlying storage, e.g. to coordinate transfers using
+    /// reservations.
+    pub fn storage(&self) -> &LedgerStorage {
+        &self.storage
+    }
+
+    pub fn storage_mut(&mut self) -> &mut LedgerStorage {
+        &mut self.storage
+    }
+
+    /// Serve the queries of the ledger from `snapshots`, taking a first one
+    /// of the current state.
+    pub fn set_query_snapshots(
+        &mut self,
+        snapshots: Option<Arc<QuerySnapshots>>,
+    ) -> Result<(), ManyError> {
+        if let Some(snapshots) = &snapshots {
+            snapshots.refresh(self)?;
+        }
+        self.query_snapshots = snapshots;
+        Ok(())
+    }
+
+    /// Execute a command against the storage and acknowledge the events it logged.
+    pub(crate) fn acknowledge<T>(
+        &mut self,
+        command: impl FnOnce(&mut LedgerStorage) -> Result<T, ManyError>,
+    ) -> Result<Acknowledgment, ManyError> {
+        self.storage.clear_logged_events();
+        command(&mut self.storage)?;
+        self.storage.acknowledgment()
     }
 
     #[cfg(feature = "balance_testing")]
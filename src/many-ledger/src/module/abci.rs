@@ -1,12 +1,12 @@
 use crate::module::LedgerModuleImpl;
 use many_error::ManyError;
 use many_modules::abci_backend::{
-    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, InitChainReturn,
-    ManyAbciModuleBackend,
+    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, AbciProposal, BeginBlockReturn, EndpointInfo,
+    GasCost, InitChainReturn, ManyAbciModuleBackend, PrepareProposalReturn, ProcessProposalReturn,
 };
 use many_types::Timestamp;
 use std::collections::BTreeMap;
-use tracing::info;
+use tracing::{info, warn};
 
 // This module is always supported, but will only be added when created using an ABCI
 // flag.
@@ -17,16 +17,35 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
             endpoints: BTreeMap::from([
                 ("ledger.info".to_string(), EndpointInfo { is_command: false }),
                 ("ledger.balance".to_string(), EndpointInfo { is_command: false }),
+                ("ledger.listBalances".to_string(), EndpointInfo { is_command: false }),
+                ("ledger.statement".to_string(), EndpointInfo { is_command: false }),
+                ("ledger.vestingInfo".to_string(), EndpointInfo { is_command: false }),
                 ("ledger.send".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.schedule".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.cancelSchedule".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.authorizePull".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.revokePull".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.pullPayment".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.createSwap".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.acceptSwap".to_string(), EndpointInfo { is_command: true }),
+                ("ledger.cancelSwap".to_string(), EndpointInfo { is_command: true }),
 
                 // Events
                 ("events.info".to_string(), EndpointInfo { is_command: false }),
                 ("events.list".to_string(), EndpointInfo { is_command: false }),
+                ("events.types".to_string(), EndpointInfo { is_command: false }),
+                ("events.prove".to_string(), EndpointInfo { is_command: false }),
 
                 // IdStore
                 ("idstore.store".to_string(), EndpointInfo { is_command: true }),
                 ("idstore.getFromRecallPhrase".to_string(), EndpointInfo { is_command: false }),
                 ("idstore.getFromAddress".to_string(), EndpointInfo { is_command: false }),
+                ("idstore.rotateKey".to_string(), EndpointInfo { is_command: true }),
+                ("idstore.getKeyRotation".to_string(), EndpointInfo { is_command: false }),
+                ("idstore.addCredential".to_string(), EndpointInfo { is_command: true }),
+                ("idstore.revokeCredential".to_string(), EndpointInfo { is_command: true }),
+                ("idstore.listCredentials".to_string(), EndpointInfo { is_command: false }),
+                ("idstore.getFromCredentialId".to_string(), EndpointInfo { is_command: false }),
 
                 // Accounts
                 ("account.create".to_string(), EndpointInfo { is_command: true }),
@@ -47,11 +66,17 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
                 ("account.multisigRevoke".to_string(), EndpointInfo { is_command: true }),
                 ("account.multisigExecute".to_string(), EndpointInfo { is_command: true }),
                 ("account.multisigWithdraw".to_string(), EndpointInfo { is_command: true }),
+                ("account.multisigComment".to_string(), EndpointInfo { is_command: true }),
+                ("account.delegate".to_string(), EndpointInfo { is_command: true }),
+                ("account.revokeDelegation".to_string(), EndpointInfo { is_command: true }),
+                ("account.delegations".to_string(), EndpointInfo { is_command: false }),
 
                 // Data Attributes
                 ("data.info".to_string(), EndpointInfo { is_command: false }),
                 ("data.getInfo".to_string(), EndpointInfo { is_command: false }),
                 ("data.query".to_string(), EndpointInfo { is_command: false }),
+                ("data.register".to_string(), EndpointInfo { is_command: true }),
+                ("data.unregister".to_string(), EndpointInfo { is_command: true }),
 
                 // Token attribute
                 ("tokens.create".to_string(), EndpointInfo { is_command : true }),
@@ -59,9 +84,51 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
                 ("tokens.info".to_string(), EndpointInfo { is_command : false }),
                 ("tokens.addExtendedInfo".to_string(), EndpointInfo { is_command : true }),
                 ("tokens.removeExtendedInfo".to_string(), EndpointInfo { is_command : true }),
+                ("tokens.supplyAt".to_string(), EndpointInfo { is_command : false }),
+                ("tokens.grantVesting".to_string(), EndpointInfo { is_command : true }),
+                ("tokens.updateRules".to_string(), EndpointInfo { is_command : true }),
                 ("tokens.mint".to_string(), EndpointInfo { is_command : true }),
                 ("tokens.burn".to_string(), EndpointInfo { is_command : true }),
+                ("tokens.createWithCurve".to_string(), EndpointInfo { is_command : true }),
+                ("tokens.curveInfo".to_string(), EndpointInfo { is_command : false }),
+                ("tokens.buy".to_string(), EndpointInfo { is_command : true }),
+                ("tokens.sell".to_string(), EndpointInfo { is_command : true }),
+
+                // Names
+                ("names.register".to_string(), EndpointInfo { is_command: true }),
+                ("names.resolve".to_string(), EndpointInfo { is_command: false }),
+                ("names.transfer".to_string(), EndpointInfo { is_command: true }),
+
+                // Escrow
+                ("escrow.create".to_string(), EndpointInfo { is_command: true }),
+                ("escrow.info".to_string(), EndpointInfo { is_command: false }),
+                ("escrow.release".to_string(), EndpointInfo { is_command: true }),
+                ("escrow.refund".to_string(), EndpointInfo { is_command: true }),
+
+                // Bridge
+                ("bridge.info".to_string(), EndpointInfo { is_command: false }),
+                ("bridge.register".to_string(), EndpointInfo { is_command: true }),
+                ("bridge.lock".to_string(), EndpointInfo { is_command: true }),
+                ("bridge.burn".to_string(), EndpointInfo { is_command: true }),
+                ("bridge.transfer".to_string(), EndpointInfo { is_command: false }),
+                ("bridge.anchor".to_string(), EndpointInfo { is_command: true }),
+                ("bridge.claim".to_string(), EndpointInfo { is_command: true }),
+
+                // Migrations
+                ("migrations.list".to_string(), EndpointInfo { is_command: false }),
+                ("migrations.info".to_string(), EndpointInfo { is_command: false }),
+
+                // Bundle
+                ("bundle.execute".to_string(), EndpointInfo { is_command: true }),
+                ("base.composite".to_string(), EndpointInfo { is_command: true }),
             ]),
+            // Bundles and composite messages execute every message they contain.
+            gas: Some(BTreeMap::from([
+                ("bundle.execute".to_string(), GasCost::new(5_000, 2)),
+                ("base.composite".to_string(), GasCost::new(5_000, 2)),
+                ("account.multisigSubmitTransaction".to_string(), GasCost::new(2_000, 2)),
+            ])),
+            block_gas_limit: self.storage.block_gas_limit()?,
         })
     }
 
@@ -70,6 +137,23 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
         Ok(InitChainReturn {})
     }
 
+    fn prepare_proposal(&self, proposal: AbciProposal) -> Result<PrepareProposalReturn, ManyError> {
+        Ok(PrepareProposalReturn {
+            txs: self.storage.tx_ordering()?.order(&proposal.txs),
+        })
+    }
+
+    fn process_proposal(&self, proposal: AbciProposal) -> Result<ProcessProposalReturn, ManyError> {
+        let accept = self.storage.tx_ordering()?.is_ordered(&proposal.txs);
+        if !accept {
+            info!(
+                "abci.process_proposal(): rejected height={}, transactions are not ordered",
+                proposal.height
+            );
+        }
+        Ok(ProcessProposalReturn { accept })
+    }
+
     fn begin_block(&mut self, info: AbciBlock) -> Result<BeginBlockReturn, ManyError> {
         let time = info.time;
         info!(
@@ -104,6 +188,12 @@ impl ManyAbciModuleBackend for LedgerModuleImpl {
     fn commit(&mut self) -> Result<AbciCommitInfo, ManyError> {
         let result = self.storage.commit();
 
+        if let Some(snapshots) = &self.query_snapshots {
+            if let Err(e) = snapshots.refresh(self) {
+                warn!("Unable to refresh the query snapshot: {e}");
+            }
+        }
+
         info!(
             "abci.commit(): retain_height={} hash={}",
             result.retain_height,
@@ -2,9 +2,11 @@ use crate::module::LedgerModuleImpl;
 use coset::CoseSign1;
 use many_error::{ManyError, ManyErrorCode};
 use many_identity::Address;
-use many_modules::account::features::{multisig, FeatureId, FeatureInfo, TryCreateFeature};
+use many_modules::account::features::{
+    method_acl, multisig, observers, FeatureId, FeatureInfo, TryCreateFeature,
+};
 use many_modules::account::{Account, AccountModuleBackend, Role};
-use many_modules::{account, EmptyReturn, ManyModule, ManyModuleInfo};
+use many_modules::{account, ManyModule, ManyModuleInfo};
 use many_protocol::{context::Context, RequestMessage, ResponseMessage};
 use many_types::cbor::CborAny;
 use std::collections::{BTreeMap, BTreeSet};
@@ -25,6 +27,9 @@ fn get_roles_for_account(account: &account::Account) -> BTreeSet<account::Role>
     if features.has_id(account::features::tokens::TokenAccountLedger::ID) {
         roles.append(&mut account::features::tokens::TokenAccountLedger::roles());
     }
+    if features.has_id(observers::ObserversAccountFeature::ID) {
+        roles.append(&mut observers::ObserversAccountFeature::roles());
+    }
 
     roles
 }
@@ -50,6 +55,24 @@ pub(crate) fn validate_features_for_account(account: &account::Account) -> Resul
         }
     }
 
+    if let Err(e) = features.get::<account::features::pull_payments::PullPaymentsAccountFeature>() {
+        if e.code() != ManyErrorCode::AttributeNotFound {
+            return Err(e);
+        }
+    }
+
+    if let Err(e) = features.get::<observers::ObserversAccountFeature>() {
+        if e.code() != ManyErrorCode::AttributeNotFound {
+            return Err(e);
+        }
+    }
+
+    if let Err(e) = features.get::<method_acl::MethodAclAccountFeature>() {
+        if e.code() != ManyErrorCode::AttributeNotFound {
+            return Err(e);
+        }
+    }
+
     Ok(())
 }
 
@@ -78,6 +101,9 @@ pub(crate) fn validate_roles_for_account(account: &account::Account) -> Result<(
     {
         allowed_roles.append(&mut account::features::tokens::TokenAccountLedger::roles());
     }
+    if features.get::<observers::ObserversAccountFeature>().is_ok() {
+        allowed_roles.append(&mut observers::ObserversAccountFeature::roles());
+    }
 
     for r in account_roles {
         if !allowed_roles.contains(&r) {
@@ -98,13 +124,17 @@ pub(crate) fn validate_account(account: &account::Account) -> Result<(), ManyErr
     Ok(())
 }
 
+/// Verify that `sender` can call `method` on behalf of the account, as an
+/// owner, through the method ACL of the account, or with one of the roles of
+/// the feature.
 pub(crate) fn verify_account_role<R: TryInto<Role> + std::fmt::Display + Copy>(
     account: &Account,
     sender: &Address,
+    method: &str,
     feature_id: FeatureId,
     role: impl IntoIterator<Item = R>,
 ) -> Result<(), ManyError> {
-    if !account.has_role(sender, account::Role::Owner) {
+    if !account.has_role(sender, account::Role::Owner) && !account.allows_method(sender, method) {
         if account.features.has_id(feature_id) {
             account.needs_role(sender, role)?;
         } else {
@@ -124,11 +154,27 @@ impl AccountModuleBackend for LedgerModuleImpl {
         if args.features.is_empty() {
             return Err(account::errors::empty_feature());
         }
+        let nonce = args.nonce.clone();
+        if nonce
+            .as_ref()
+            .map_or(false, |nonce| nonce.len() > account::MAX_CREATE_NONCE_SIZE)
+        {
+            return Err(account::errors::nonce_too_long(
+                account::MAX_CREATE_NONCE_SIZE,
+            ));
+        }
         let account = account::Account::create(sender, args);
 
         validate_account(&account)?;
 
-        let (id, _) = self.storage.add_account(account)?;
+        let id = match nonce {
+            Some(nonce) => {
+                self.storage
+                    .add_account_with_nonce(sender, &nonce, account)?
+                    .0
+            }
+            None => self.storage.add_account(account)?.0,
+        };
         Ok(account::CreateReturn { id })
     }
 
@@ -136,16 +182,14 @@ impl AccountModuleBackend for LedgerModuleImpl {
         &mut self,
         sender: &Address,
         args: account::SetDescriptionArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<account::SetDescriptionReturn, ManyError> {
         let (account, _) = self.storage.get_account(&args.account)?;
 
         if !account.has_role(sender, account::Role::Owner) {
             return Err(account::errors::user_needs_role("owner"));
         }
 
-        self.storage
-            .set_description(account, args)
-            .map(|_| EmptyReturn)
+        self.acknowledge(|storage| storage.set_description(account, args))
     }
 
     fn list_roles(
@@ -167,10 +211,11 @@ impl AccountModuleBackend for LedgerModuleImpl {
 
     fn get_roles(
         &self,
-        _: &Address,
+        sender: &Address,
         args: account::GetRolesArgs,
         context: Context,
     ) -> Result<account::GetRolesReturn, ManyError> {
+        self.storage.check_observer(&args.account, sender)?;
         self.storage
             .get_account(&args.account)
             .and_then(|(account, keys)| {
@@ -190,13 +235,13 @@ impl AccountModuleBackend for LedgerModuleImpl {
         &mut self,
         sender: &Address,
         args: account::AddRolesArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<account::AddRolesReturn, ManyError> {
         let (account, _) = self.storage.get_account(&args.account)?;
 
         if !account.has_role(sender, account::Role::Owner) {
             Err(account::errors::user_needs_role("owner"))
         } else {
-            self.storage.add_roles(account, args).map(|_| EmptyReturn)
+            self.acknowledge(|storage| storage.add_roles(account, args))
         }
     }
 
@@ -204,24 +249,23 @@ impl AccountModuleBackend for LedgerModuleImpl {
         &mut self,
         sender: &Address,
         args: account::RemoveRolesArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<account::RemoveRolesReturn, ManyError> {
         let (account, _) = self.storage.get_account(&args.account)?;
 
         if !account.has_role(sender, account::Role::Owner) {
             Err(account::errors::user_needs_role(account::Role::Owner))
         } else {
-            self.storage
-                .remove_roles(account, args)
-                .map(|_| EmptyReturn)
+            self.acknowledge(|storage| storage.remove_roles(account, args))
         }
     }
 
     fn info(
         &self,
-        _: &Address,
+        sender: &Address,
         args: account::InfoArgs,
         context: Context,
     ) -> Result<account::InfoReturn, ManyError> {
+        self.storage.check_observer(&args.account, sender)?;
         self.storage
             .get_account_even_disabled(&args.account)
             .and_then(
@@ -250,15 +294,13 @@ impl AccountModuleBackend for LedgerModuleImpl {
         &mut self,
         sender: &Address,
         args: account::DisableArgs,
-    ) -> Result<EmptyReturn, ManyError> {
+    ) -> Result<account::DisableReturn, ManyError> {
         let (account, _) = self.storage.get_account(&args.account)?;
 
         if !account.has_role(sender, account::Role::Owner) {
             Err(account::errors::user_needs_role(account::Role::Owner))
         } else {
-            self.storage
-                .disable_account(&args.account)
-                .map(|_| EmptyReturn)
+            self.acknowledge(|storage| storage.disable_account(&args.account))
         }
     }
 
@@ -273,9 +315,7 @@ impl AccountModuleBackend for LedgerModuleImpl {
             let (account, _) = self.storage.get_account(&args.account)?;
 
             account.needs_role(sender, [account::Role::Owner])?;
-            self.storage
-                .add_features(account, args)
-                .map(|_| EmptyReturn)
+            self.acknowledge(|storage| storage.add_features(account, args))
         }
     }
 }
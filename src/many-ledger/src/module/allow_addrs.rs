@@ -3,12 +3,13 @@ use many_error::ManyError;
 use many_identity::Address;
 use many_modules::{ledger, ManyModule, ManyModuleInfo};
 use many_protocol::{RequestMessage, ResponseMessage};
+use many_server::reload::Reloadable;
 use std::collections::BTreeSet;
 use std::fmt::{Debug, Formatter};
 
 pub struct AllowAddrsModule<T: ledger::LedgerCommandsModuleBackend> {
     pub inner: ledger::LedgerCommandsModule<T>,
-    pub allow_addrs: BTreeSet<Address>,
+    pub allow_addrs: Reloadable<BTreeSet<Address>>,
 }
 
 impl<T: ledger::LedgerCommandsModuleBackend> Debug for AllowAddrsModule<T> {
@@ -28,7 +29,7 @@ impl<T: ledger::LedgerCommandsModuleBackend> ManyModule for AllowAddrsModule<T>
     }
 
     async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
-        if !self.allow_addrs.contains(&message.from()) {
+        if !self.allow_addrs.get().contains(&message.from()) {
             return Err(ManyError::invalid_from_identity());
         }
 
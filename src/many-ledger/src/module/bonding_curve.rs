@@ -0,0 +1,68 @@
+use crate::migration::bonding_curve::BONDING_CURVE_MIGRATION;
+use crate::migration::tokens::TOKEN_MIGRATION;
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::ledger::{
+    BuyArgs, BuyReturns, CurveInfoArgs, CurveInfoReturns, LedgerCurveModuleBackend, SellArgs,
+    SellReturns, TokenCreateWithCurveArgs, TokenCreateWithCurveReturns,
+};
+
+impl LedgerModuleImpl {
+    fn check_curve_migration(&self, endpoint: &str) -> Result<(), ManyError> {
+        let migrations = self.storage.migrations();
+        if migrations.is_active(&TOKEN_MIGRATION) && migrations.is_active(&BONDING_CURVE_MIGRATION)
+        {
+            Ok(())
+        } else {
+            Err(ManyError::invalid_method_name(endpoint))
+        }
+    }
+}
+
+impl LedgerCurveModuleBackend for LedgerModuleImpl {
+    fn create_with_curve(
+        &mut self,
+        sender: &Address,
+        args: TokenCreateWithCurveArgs,
+    ) -> Result<TokenCreateWithCurveReturns, ManyError> {
+        self.check_curve_migration("tokens.createWithCurve")?;
+        self.verify_token_create(sender, &args.owner, &args.summary.ticker)?;
+        self.storage.create_token_with_curve(sender, args)
+    }
+
+    fn curve_info(&self, args: CurveInfoArgs) -> Result<CurveInfoReturns, ManyError> {
+        self.check_curve_migration("tokens.curveInfo")?;
+        self.storage.curve_info(&args.symbol)
+    }
+
+    fn buy(&mut self, sender: &Address, args: BuyArgs) -> Result<BuyReturns, ManyError> {
+        self.check_curve_migration("tokens.buy")?;
+
+        let BuyArgs {
+            symbol,
+            amount,
+            maximum_cost,
+            memo,
+        } = args;
+        let cost = self
+            .storage
+            .buy_from_curve(sender, symbol, amount, maximum_cost, memo)?;
+        Ok(BuyReturns { cost })
+    }
+
+    fn sell(&mut self, sender: &Address, args: SellArgs) -> Result<SellReturns, ManyError> {
+        self.check_curve_migration("tokens.sell")?;
+
+        let SellArgs {
+            symbol,
+            amount,
+            minimum_proceeds,
+            memo,
+        } = args;
+        let proceeds =
+            self.storage
+                .sell_to_curve(sender, symbol, amount, minimum_proceeds, memo)?;
+        Ok(SellReturns { proceeds })
+    }
+}
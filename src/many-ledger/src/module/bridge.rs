@@ -0,0 +1,81 @@
+use crate::migration::bridge::BRIDGE_MIGRATION;
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::bridge;
+
+impl LedgerModuleImpl {
+    fn check_bridge_migration(&self, endpoint: &str) -> Result<(), ManyError> {
+        if self.storage.migrations().is_active(&BRIDGE_MIGRATION) {
+            Ok(())
+        } else {
+            Err(ManyError::invalid_method_name(endpoint))
+        }
+    }
+}
+
+impl bridge::BridgeModuleBackend for LedgerModuleImpl {
+    fn info(&self, args: bridge::InfoArgs) -> Result<bridge::InfoReturns, ManyError> {
+        self.check_bridge_migration("bridge.info")?;
+
+        self.storage.bridge_info(&args.chain)
+    }
+
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: bridge::RegisterArgs,
+    ) -> Result<bridge::RegisterReturns, ManyError> {
+        self.check_bridge_migration("bridge.register")?;
+
+        self.acknowledge(|storage| storage.register_counterparty(sender, args))
+    }
+
+    fn lock(
+        &mut self,
+        sender: &Address,
+        args: bridge::LockArgs,
+    ) -> Result<bridge::LockReturns, ManyError> {
+        self.check_bridge_migration("bridge.lock")?;
+
+        let id = self.storage.bridge_lock(sender, args)?;
+        Ok(bridge::TransferReturns { id })
+    }
+
+    fn burn(
+        &mut self,
+        sender: &Address,
+        args: bridge::BurnArgs,
+    ) -> Result<bridge::BurnReturns, ManyError> {
+        self.check_bridge_migration("bridge.burn")?;
+
+        let id = self.storage.bridge_burn(sender, args)?;
+        Ok(bridge::TransferReturns { id })
+    }
+
+    fn transfer(&self, args: bridge::TransferArgs) -> Result<bridge::TransferInfo, ManyError> {
+        self.check_bridge_migration("bridge.transfer")?;
+
+        self.storage.bridge_transfer(&args.id)
+    }
+
+    fn anchor(
+        &mut self,
+        sender: &Address,
+        args: bridge::AnchorArgs,
+    ) -> Result<bridge::AnchorReturns, ManyError> {
+        self.check_bridge_migration("bridge.anchor")?;
+
+        self.acknowledge(|storage| storage.bridge_anchor(sender, args))
+    }
+
+    fn claim(
+        &mut self,
+        _sender: &Address,
+        args: bridge::ClaimArgs,
+    ) -> Result<bridge::ClaimReturns, ManyError> {
+        self.check_bridge_migration("bridge.claim")?;
+
+        self.acknowledge(|storage| storage.bridge_claim(args))
+    }
+}
@@ -0,0 +1,17 @@
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_modules::bundle;
+
+impl bundle::BundleModuleBackend for LedgerModuleImpl {
+    fn begin_bundle(&mut self) -> Result<(), ManyError> {
+        self.storage.begin_bundle()
+    }
+
+    fn commit_bundle(&mut self) -> Result<(), ManyError> {
+        self.storage.commit_bundle()
+    }
+
+    fn rollback_bundle(&mut self) -> Result<(), ManyError> {
+        self.storage.rollback_bundle()
+    }
+}
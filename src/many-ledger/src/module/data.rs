@@ -3,7 +3,8 @@ use many_error::ManyError;
 use many_identity::Address;
 use many_modules::data::{
     DataGetInfoArgs, DataGetInfoReturns, DataInfoArgs, DataInfoReturns, DataModuleBackend,
-    DataQueryArgs, DataQueryReturns,
+    DataQueryArgs, DataQueryReturns, DataRegisterArgs, DataRegisterReturns, DataUnregisterArgs,
+    DataUnregisterReturns,
 };
 use many_protocol::context::Context;
 
@@ -66,4 +67,29 @@ impl DataModuleBackend for LedgerModuleImpl {
             )
             .map(|_| filtered)
     }
+
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: DataRegisterArgs,
+    ) -> Result<DataRegisterReturns, ManyError> {
+        if !self.storage.is_data_metrics_active() {
+            return Err(ManyError::invalid_method_name("data.register"));
+        }
+
+        let DataRegisterArgs { index, metric } = args;
+        self.acknowledge(|storage| storage.register_data_metric(sender, index, metric))
+    }
+
+    fn unregister(
+        &mut self,
+        sender: &Address,
+        args: DataUnregisterArgs,
+    ) -> Result<DataUnregisterReturns, ManyError> {
+        if !self.storage.is_data_metrics_active() {
+            return Err(ManyError::invalid_method_name("data.unregister"));
+        }
+
+        self.acknowledge(|storage| storage.unregister_data_metric(sender, &args.index))
+    }
 }
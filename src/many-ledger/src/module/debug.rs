@@ -0,0 +1,13 @@
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_modules::debug;
+
+impl debug::DebugModuleBackend for LedgerModuleImpl {
+    fn state_hashes(
+        &self,
+        args: debug::StateHashesArgs,
+    ) -> Result<debug::StateHashesReturns, ManyError> {
+        let prefix: Vec<u8> = args.prefix.map(Into::into).unwrap_or_default();
+        self.storage.state_hashes(&prefix)
+    }
+}
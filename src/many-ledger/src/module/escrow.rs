@@ -0,0 +1,56 @@
+use crate::migration::escrow::ESCROW_MIGRATION;
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::escrow;
+
+impl LedgerModuleImpl {
+    fn check_escrow_migration(&self, endpoint: &str) -> Result<(), ManyError> {
+        if self.storage.migrations().is_active(&ESCROW_MIGRATION) {
+            Ok(())
+        } else {
+            Err(ManyError::invalid_method_name(endpoint))
+        }
+    }
+}
+
+impl escrow::EscrowModuleBackend for LedgerModuleImpl {
+    fn create(
+        &mut self,
+        sender: &Address,
+        args: escrow::CreateArgs,
+    ) -> Result<escrow::CreateReturns, ManyError> {
+        self.check_escrow_migration("escrow.create")?;
+
+        let id = self.storage.create_escrow(sender, args)?;
+        Ok(escrow::CreateReturns { id })
+    }
+
+    fn info(&self, args: escrow::InfoArgs) -> Result<escrow::InfoReturns, ManyError> {
+        self.check_escrow_migration("escrow.info")?;
+
+        let escrow = self.storage.get_escrow(&args.id)?;
+        Ok(escrow::InfoReturns { escrow })
+    }
+
+    fn release(
+        &mut self,
+        sender: &Address,
+        args: escrow::ReleaseArgs,
+    ) -> Result<escrow::ReleaseReturns, ManyError> {
+        self.check_escrow_migration("escrow.release")?;
+
+        let escrow::ReleaseArgs { id, preimage } = args;
+        self.acknowledge(|storage| storage.release_escrow(sender, id, preimage))
+    }
+
+    fn refund(
+        &mut self,
+        sender: &Address,
+        args: escrow::RefundArgs,
+    ) -> Result<escrow::RefundReturns, ManyError> {
+        self.check_escrow_migration("escrow.refund")?;
+
+        self.acknowledge(|storage| storage.refund_escrow(sender, args.id))
+    }
+}
@@ -1,18 +1,78 @@
 use crate::module::LedgerModuleImpl;
+use crate::storage::event::{EVENT_CHAIN_HEAD_ROOT, HEIGHT_EVENTID_SHIFT};
+use crate::storage::pruning::key_for_pruned_segment;
+use crate::storage::LedgerStorage;
 use many_error::ManyError;
 use many_identity::Address;
+use many_modules::account;
 use many_modules::account::features::multisig::MultisigTransactionState;
 use many_modules::events;
 use many_modules::events::{
-    EventFilterAttributeSpecific, EventFilterAttributeSpecificIndex, EventInfo, EventLog,
+    AddressContainer, EventFilterAttributeSpecific, EventFilterAttributeSpecificIndex, EventInfo,
+    EventLog, EventPredicate,
 };
-use many_types::{CborRange, Timestamp, VecOrSingle};
+use many_protocol::context::Context;
+use many_types::{CborRange, SortOrder, Timestamp, VecOrSingle};
 use std::collections::BTreeMap;
+use std::ops::Bound;
 
 const MAXIMUM_EVENT_COUNT: usize = 100;
 
+/// Proofs are computed over every event of the segment, so they are bounded.
+const MAXIMUM_PROVE_EVENT_COUNT: usize = 1000;
+
 type EventLogResult = Result<events::EventLog, ManyError>;
 
+/// The events a sender can see, i.e. those that are not about an account it
+/// cannot observe.
+struct EventVisibility<'a> {
+    storage: &'a LedgerStorage,
+    sender: &'a Address,
+    accounts: BTreeMap<Address, bool>,
+}
+
+impl<'a> EventVisibility<'a> {
+    fn new(storage: &'a LedgerStorage, sender: &'a Address) -> Self {
+        Self {
+            storage,
+            sender,
+            accounts: BTreeMap::new(),
+        }
+    }
+
+    fn can_see(&mut self, event: &EventLog) -> Result<bool, ManyError> {
+        for id in event.content.addresses() {
+            let visible = match self.accounts.get(&id) {
+                Some(visible) => *visible,
+                None => {
+                    let visible = self.storage.can_observe(&id, self.sender)?;
+                    self.accounts.insert(id, visible);
+                    visible
+                }
+            };
+            if !visible {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+}
+
+fn filter_visible<'a>(
+    it: Box<dyn Iterator<Item = EventLogResult> + 'a>,
+    mut visibility: EventVisibility<'a>,
+) -> Box<dyn Iterator<Item = EventLogResult> + 'a> {
+    Box::new(it.filter_map(move |t| match t {
+        // Propagate the errors.
+        Err(e) => Some(Err(e)),
+        Ok(t) => match visibility.can_see(&t) {
+            Ok(true) => Some(Ok(t)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e)),
+        },
+    }))
+}
+
 fn filter_account<'a>(
     it: Box<dyn Iterator<Item = EventLogResult> + 'a>,
     account: Option<VecOrSingle<Address>>,
@@ -55,6 +115,21 @@ fn filter_date<'a>(
     }))
 }
 
+fn filter_predicate<'a>(
+    it: Box<dyn Iterator<Item = EventLogResult> + 'a>,
+    predicate: Option<EventPredicate>,
+) -> Box<dyn Iterator<Item = EventLogResult> + 'a> {
+    if let Some(predicate) = predicate {
+        Box::new(it.filter(move |t| match t {
+            // Propagate the errors.
+            Err(_) => true,
+            Ok(t) => predicate.matches(t),
+        }))
+    } else {
+        it
+    }
+}
+
 fn filter_attribute_specific<'a>(
     mut it: Box<dyn Iterator<Item = EventLogResult> + 'a>,
     attribute_specific: &'a BTreeMap<
@@ -98,16 +173,16 @@ fn filter_attribute_specific<'a>(
     it
 }
 
-impl events::EventsModuleBackend for LedgerModuleImpl {
-    fn info(&self, _args: events::InfoArgs) -> Result<events::InfoReturn, ManyError> {
-        use strum::IntoEnumIterator;
-        Ok(events::InfoReturn {
-            total: self.storage.nb_events()?,
-            event_types: events::EventKind::iter().collect(),
-        })
-    }
-
-    fn list(&self, args: events::ListArgs) -> Result<events::ListReturns, ManyError> {
+impl LedgerModuleImpl {
+    /// List the events `sender` can see, i.e. those which are not about an
+    /// account it cannot observe. The `events.list` endpoint goes through the
+    /// [ObserverEventsModule](crate::module::observers::ObserverEventsModule),
+    /// which knows the sender of the request.
+    pub fn list_events(
+        &self,
+        sender: &Address,
+        args: events::ListArgs,
+    ) -> Result<events::ListReturns, ManyError> {
         let events::ListArgs {
             count,
             order,
@@ -120,21 +195,42 @@ impl events::EventsModuleBackend for LedgerModuleImpl {
         });
 
         let storage = &self.storage;
+        if let Some(VecOrSingle(accounts)) = &filter.account {
+            for id in accounts {
+                storage.check_observer(id, sender)?;
+            }
+        }
         let nb_events = storage.nb_events()?;
-        let iter = storage.iter_events(
-            filter.id_range.unwrap_or_default(),
-            order.unwrap_or_default(),
-        );
+        let range = filter.id_range.unwrap_or_default();
+        let order = order.unwrap_or_default();
+        let decode = |v: &[u8]| {
+            minicbor::decode::<events::EventLog>(v).map_err(ManyError::deserialization_error)
+        };
 
-        let iter = Box::new(iter.map(|item| {
-            let (_k, v) = item.map_err(ManyError::unknown)?;
-            minicbor::decode::<events::EventLog>(v.as_slice())
-                .map_err(ManyError::deserialization_error)
-        }));
+        // Only go through the events with the terms of the predicate, if it
+        // has any and they are indexed.
+        let terms = filter
+            .predicate
+            .as_ref()
+            .and_then(EventPredicate::terms)
+            .filter(|_| storage.is_event_index_active());
+        let iter: Box<dyn Iterator<Item = EventLogResult>> = match terms {
+            Some(terms) => Box::new(
+                storage
+                    .iter_indexed_events(&terms, range, order)
+                    .map(move |item| decode(&item?)),
+            ),
+            None => Box::new(storage.iter_events(range, order).map(move |item| {
+                let (_k, v) = item.map_err(ManyError::unknown)?;
+                decode(&v)
+            })),
+        };
 
+        let iter = filter_visible(iter, EventVisibility::new(storage, sender));
         let iter = filter_account(iter, filter.account);
         let iter = filter_event_kind(iter, filter.kind);
         let iter = filter_date(iter, filter.date_range.unwrap_or_default());
+        let iter = filter_predicate(iter, filter.predicate);
         let iter = filter_attribute_specific(iter, &filter.events_filter_attribute_specific);
 
         let events: Vec<events::EventLog> = iter.take(count).collect::<Result<_, _>>()?;
@@ -142,3 +238,111 @@ impl events::EventsModuleBackend for LedgerModuleImpl {
         Ok(events::ListReturns { nb_events, events })
     }
 }
+
+impl events::EventsModuleBackend for LedgerModuleImpl {
+    fn info(&self, _args: events::InfoArgs) -> Result<events::InfoReturn, ManyError> {
+        use strum::IntoEnumIterator;
+        Ok(events::InfoReturn {
+            total: self.storage.nb_events()?,
+            event_types: events::EventKind::iter().collect(),
+        })
+    }
+
+    /// The events an anonymous sender can see.
+    fn list(&self, args: events::ListArgs) -> Result<events::ListReturns, ManyError> {
+        self.list_events(&Address::anonymous(), args)
+    }
+}
+
+impl events::EventsProofModuleBackend for LedgerModuleImpl {
+    fn prove(
+        &self,
+        sender: &Address,
+        args: events::ProveArgs,
+        context: Context,
+    ) -> Result<events::ProveReturns, ManyError> {
+        let events::ProveArgs { start, end } = args;
+        if start > end {
+            return Err(ManyError::unknown(
+                "The start height is after the end height.",
+            ));
+        }
+
+        // Event IDs are prefixed by the height before the block they're
+        // logged in.
+        let start_id = events::EventId::from(start.saturating_sub(1) << HEIGHT_EVENTID_SHIFT);
+        let end_id = events::EventId::from(end << HEIGHT_EVENTID_SHIFT);
+        let decode = |v: &[u8]| {
+            minicbor::decode::<events::EventLog>(v).map_err(ManyError::deserialization_error)
+        };
+
+        let storage = &self.storage;
+        let mut visibility = EventVisibility::new(storage, sender);
+        let mut keys = vec![EVENT_CHAIN_HEAD_ROOT.to_vec()];
+
+        // The event right before the segment holds the running hash the
+        // segment starts from. If it isn't chained, the segment starts the
+        // chain. If it was pruned, the head of the pruned segment holds it.
+        let previous = match storage
+            .iter_events(
+                CborRange {
+                    start: Bound::Unbounded,
+                    end: Bound::Excluded(start_id.clone()),
+                },
+                SortOrder::Descending,
+            )
+            .next()
+        {
+            Some(item) => {
+                let (k, v) = item.map_err(ManyError::unknown)?;
+                keys.push(k.to_vec());
+                Some(
+                    decode(&v)?
+                        .chain_hash
+                        .unwrap_or_else(|| events::EVENT_CHAIN_GENESIS.to_vec().into()),
+                )
+            }
+            None => match storage.last_pruned_segment()? {
+                // Segments are pruned from the start of the log, so the events
+                // after the last one are all there.
+                Some((k, _)) if k >= key_for_pruned_segment(start_id.clone()) => {
+                    return Err(ManyError::unknown(
+                        "The events of the start height were pruned.",
+                    ));
+                }
+                Some((k, chain_head)) => {
+                    keys.push(k);
+                    Some(chain_head.into())
+                }
+                None => None,
+            },
+        };
+
+        let mut events = Vec::new();
+        for item in storage.iter_events(
+            CborRange {
+                start: Bound::Included(start_id),
+                end: Bound::Excluded(end_id),
+            },
+            SortOrder::Ascending,
+        ) {
+            if events.len() == MAXIMUM_PROVE_EVENT_COUNT {
+                return Err(ManyError::unknown(format!(
+                    "More than {MAXIMUM_PROVE_EVENT_COUNT} events to prove, use a smaller range."
+                )));
+            }
+            let (k, v) = item.map_err(ManyError::unknown)?;
+            let event = decode(&v)?;
+            // The proof covers the whole segment, so it cannot skip events.
+            if !visibility.can_see(&event)? {
+                return Err(account::errors::user_needs_role(account::Role::Observer));
+            }
+            keys.push(k.to_vec());
+            events.push(event);
+        }
+
+        keys.sort();
+        storage.prove_state(context, keys)?;
+        Ok(events::ProveReturns { previous, events })
+    }
+}
@@ -1,8 +1,12 @@
 use crate::{module::LedgerModuleImpl, storage::idstore::IDSTORE_ROOT};
 use coset::{CborSerializable, CoseKey};
 use many_error::ManyError;
+use many_identity::rotation::KeyRotations;
 use many_identity::Address;
+use many_identity_dsa::{ecdsa, CoseKeyVerifier};
+use many_identity_webauthn::WebAuthnCredentials;
 use many_modules::idstore;
+use std::sync::{Arc, Mutex};
 
 /// Return a recall phrase
 //
@@ -115,6 +119,110 @@ impl idstore::IdStoreModuleBackend for LedgerModuleImpl {
             public_key,
         })
     }
+
+    fn rotate_key(
+        &mut self,
+        args: idstore::RotateKeyArgs,
+    ) -> Result<idstore::RotateKeyReturns, ManyError> {
+        // Both signatures carry their public key, so they are verified in
+        // isolation from the rotations already known.
+        let rotation = args.proof.verify(&CoseKeyVerifier)?;
+        rotation.follows(self.storage.get_key_rotation(&rotation.identity)?.as_ref())?;
+        self.acknowledge(|storage| storage.rotate_key(&rotation))
+    }
+
+    fn get_key_rotation(
+        &self,
+        args: idstore::GetKeyRotationArgs,
+    ) -> Result<idstore::GetKeyRotationReturns, ManyError> {
+        Ok(idstore::GetKeyRotationReturns {
+            rotation: self.storage.get_key_rotation(&args.0)?,
+            identity: self.storage.identity_of_key(&args.0)?,
+        })
+    }
+
+    fn add_credential(
+        &mut self,
+        sender: &Address,
+        idstore::AddCredentialArgs {
+            cred_id,
+            public_key,
+        }: idstore::AddCredentialArgs,
+    ) -> Result<idstore::AddCredentialReturns, ManyError> {
+        if !sender.is_public_key() {
+            return Err(idstore::invalid_address(sender.to_string()));
+        }
+
+        if !(16..=1023).contains(&cred_id.0.len()) {
+            return Err(idstore::invalid_credential_id(hex::encode(&*cred_id.0)));
+        }
+
+        let key: CoseKey =
+            CoseKey::from_slice(&public_key.0).map_err(ManyError::deserialization_error)?;
+        let key = ecdsa::address(&key)?;
+
+        self.acknowledge(|storage| storage.add_credential(sender, &key, cred_id, public_key))
+    }
+
+    fn revoke_credential(
+        &mut self,
+        sender: &Address,
+        args: idstore::RevokeCredentialArgs,
+    ) -> Result<idstore::RevokeCredentialReturns, ManyError> {
+        self.acknowledge(|storage| storage.revoke_credential(sender, &args.cred_id))
+    }
+
+    fn list_credentials(
+        &self,
+        args: idstore::ListCredentialsArgs,
+    ) -> Result<idstore::ListCredentialsReturns, ManyError> {
+        Ok(idstore::ListCredentialsReturns {
+            credentials: self.storage.list_credentials(&args.0)?,
+        })
+    }
+
+    fn get_from_credential_id(
+        &self,
+        args: idstore::GetFromCredentialIdArgs,
+    ) -> Result<idstore::GetFromCredentialIdReturns, ManyError> {
+        let (address, public_key) = self.storage.get_from_credential_id(&args.0)?;
+        Ok(idstore::GetFromCredentialIdReturns {
+            address,
+            public_key,
+        })
+    }
+}
+
+/// The key rotations stored in the ledger, for a
+/// [`many_identity::rotation::RotatingVerifier`]. Storage errors fail closed,
+/// as if the key was retired.
+pub struct LedgerKeyRotations(pub Arc<Mutex<LedgerModuleImpl>>);
+
+impl KeyRotations for LedgerKeyRotations {
+    fn identity_of_key(&self, key: &Address) -> Option<Address> {
+        let module_impl = self.0.lock().ok()?;
+        module_impl.storage.identity_of_key(key).ok().flatten()
+    }
+
+    fn is_retired(&self, key: &Address) -> bool {
+        self.0.lock().map_or(true, |module_impl| {
+            module_impl.storage.is_key_retired(key).unwrap_or(true)
+        })
+    }
+}
+
+/// The WebAuthn credentials stored in the ledger, for a
+/// [`many_identity_webauthn::WebAuthnVerifier`]. Like [`LedgerKeyRotations`],
+/// storage errors fail closed.
+#[derive(Debug)]
+pub struct LedgerWebAuthnCredentials(pub Arc<Mutex<LedgerModuleImpl>>);
+
+impl WebAuthnCredentials for LedgerWebAuthnCredentials {
+    fn can_sign(&self, identity: &Address, key: &Address) -> bool {
+        self.0.lock().map_or(false, |module_impl| {
+            module_impl.storage.can_sign(identity, key).unwrap_or(false)
+        })
+    }
 }
 
 #[cfg(test)]
@@ -1,4 +1,5 @@
-use crate::{module::LedgerModuleImpl, storage::SYMBOLS_ROOT};
+use crate::storage::ledger_tokens::key_for_symbol;
+use crate::{error, module::LedgerModuleImpl, storage::SYMBOLS_ROOT};
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::ledger;
@@ -6,6 +7,16 @@ use many_protocol::context::Context;
 use std::collections::BTreeSet;
 use tracing::info;
 
+/// The number of balances of a page of `ledger.listBalances`, by default and
+/// at most.
+const DEFAULT_BALANCES_PAGE: u64 = 100;
+const MAX_BALANCES_PAGE: u64 = 1000;
+
+/// The number of entries of a page of `ledger.statement`, by default and at
+/// most.
+const DEFAULT_STATEMENT_PAGE: u64 = 100;
+const MAX_STATEMENT_PAGE: u64 = 1000;
+
 impl ledger::LedgerModuleBackend for LedgerModuleImpl {
     fn info(
         &self,
@@ -18,10 +29,17 @@ impl ledger::LedgerModuleBackend for LedgerModuleImpl {
         // Hash the storage.
         let hash = storage.hash();
         let symbols = storage.get_symbols_and_tickers()?;
+        let tokens = storage.get_token_info_summary()?;
 
+        // The summaries of the tokens are read from their information.
+        let keys = tokens
+            .keys()
+            .map(|symbol| key_for_symbol(symbol).into_bytes());
         storage.prove_state(
             context,
-            vec![hash.clone(), SYMBOLS_ROOT.as_bytes().to_vec()],
+            [hash.clone(), SYMBOLS_ROOT.as_bytes().to_vec()]
+                .into_iter()
+                .chain(keys),
         )?;
 
         info!(
@@ -34,7 +52,7 @@ impl ledger::LedgerModuleBackend for LedgerModuleImpl {
             symbols: symbols.keys().copied().collect(),
             hash: hash.into(),
             local_names: symbols,
-            tokens: storage.get_token_info_summary()?,
+            tokens,
         })
     }
 
@@ -47,6 +65,7 @@ impl ledger::LedgerModuleBackend for LedgerModuleImpl {
         let identity = account.as_ref().unwrap_or(sender);
 
         let storage = &self.storage;
+        storage.check_observer(identity, sender)?;
         let symbols = symbols.unwrap_or_default().0;
 
         let (balances, keys) = storage
@@ -55,4 +74,125 @@ impl ledger::LedgerModuleBackend for LedgerModuleImpl {
         info!("balance({}, {:?}): {:?}", identity, &symbols, &balances);
         Ok(ledger::BalanceReturns { balances })
     }
+
+    fn list_balances(
+        &self,
+        sender: &Address,
+        args: ledger::ListBalancesArgs,
+        context: Context,
+    ) -> Result<ledger::ListBalancesReturns, ManyError> {
+        let ledger::ListBalancesArgs {
+            account,
+            count,
+            after,
+            include_zero,
+            include_locked,
+        } = args;
+        let identity = account.as_ref().unwrap_or(sender);
+
+        let storage = &self.storage;
+        storage.check_observer(identity, sender)?;
+        let count = count
+            .unwrap_or(DEFAULT_BALANCES_PAGE)
+            .clamp(1, MAX_BALANCES_PAGE);
+        let (page, next, keys) = storage.list_balances(
+            identity,
+            after.as_ref(),
+            count as usize,
+            include_zero.unwrap_or(false),
+        )?;
+        storage.prove_state(context, keys)?;
+
+        let include_locked = include_locked.unwrap_or(false) && storage.is_vesting_active();
+        let balances = page
+            .into_iter()
+            .map(|(symbol, amount)| {
+                Ok(ledger::SymbolBalance {
+                    symbol,
+                    amount,
+                    locked: include_locked
+                        .then(|| storage.get_locked_balance(identity, &symbol))
+                        .transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ManyError>>()?;
+        info!("listBalances({}, {:?}): {:?}", identity, after, balances);
+        Ok(ledger::ListBalancesReturns { balances, next })
+    }
+
+    fn statement(
+        &self,
+        sender: &Address,
+        args: ledger::StatementArgs,
+    ) -> Result<ledger::StatementReturns, ManyError> {
+        let ledger::StatementArgs {
+            account,
+            symbol,
+            height_range,
+            date_range,
+            count,
+            after,
+        } = args;
+        let identity = account.as_ref().unwrap_or(sender);
+
+        let storage = &self.storage;
+        storage.check_observer(identity, sender)?;
+        if !storage.get_symbols()?.contains(&symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+        let count = count
+            .unwrap_or(DEFAULT_STATEMENT_PAGE)
+            .clamp(1, MAX_STATEMENT_PAGE);
+        let (entries, more, opening_balance) = storage.statement(
+            identity,
+            &symbol,
+            height_range,
+            date_range,
+            after,
+            count as usize,
+        )?;
+
+        let closing_balance = entries
+            .last()
+            .map_or_else(|| opening_balance.clone(), |entry| entry.balance.clone());
+        let next = more
+            .then(|| entries.last().map(|entry| entry.id.clone()))
+            .flatten();
+        info!(
+            "statement({}, {}): {} entries",
+            identity,
+            symbol,
+            entries.len()
+        );
+        Ok(ledger::StatementReturns {
+            account: *identity,
+            symbol,
+            height: storage.get_height()?,
+            opening_balance,
+            closing_balance,
+            entries,
+            next,
+        })
+    }
+
+    fn vesting_info(
+        &self,
+        sender: &Address,
+        ledger::VestingInfoArgs { account, symbols }: ledger::VestingInfoArgs,
+    ) -> Result<ledger::VestingInfoReturns, ManyError> {
+        let storage = &self.storage;
+        if !storage.is_vesting_active() {
+            return Err(ManyError::invalid_method_name("ledger.vestingInfo"));
+        }
+
+        let identity = account.as_ref().unwrap_or(sender);
+        storage.check_observer(identity, sender)?;
+        let symbols = match symbols {
+            Some(symbols) => BTreeSet::from_iter(symbols.0),
+            None => storage.get_symbols()?,
+        };
+
+        let vesting = storage.get_vesting_info(identity, &symbols)?;
+        Ok(ledger::VestingInfoReturns { vesting })
+    }
 }
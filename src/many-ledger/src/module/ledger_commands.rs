@@ -1,46 +1,116 @@
-use crate::error;
-use crate::module::account::verify_account_role;
+use crate::migration::swap::SWAP_MIGRATION;
 use crate::module::LedgerModuleImpl;
+use crate::storage::schedule::verify_can_send;
 use many_error::ManyError;
 use many_identity::Address;
-use many_modules::account::features::TryCreateFeature;
-use many_modules::account::Role;
-use many_modules::{account, ledger, EmptyReturn};
+use many_modules::ledger;
+
+impl LedgerModuleImpl {
+    fn check_swap_migration(&self, endpoint: &str) -> Result<(), ManyError> {
+        if self.storage.migrations().is_active(&SWAP_MIGRATION) {
+            Ok(())
+        } else {
+            Err(ManyError::invalid_method_name(endpoint))
+        }
+    }
+}
 
 impl ledger::LedgerCommandsModuleBackend for LedgerModuleImpl {
-    fn send(&mut self, sender: &Address, args: ledger::SendArgs) -> Result<EmptyReturn, ManyError> {
+    fn send(
+        &mut self,
+        sender: &Address,
+        args: ledger::SendArgs,
+    ) -> Result<ledger::SendReturns, ManyError> {
         let ledger::SendArgs {
             from,
             to,
             amount,
             symbol,
             memo,
+            decimal_amount,
         } = args;
 
         let from = from.as_ref().unwrap_or(sender);
         // We check here to make sure there isn't a code path that might ends up here without
         // proper validation (e.g. multisig or delayed execution). This should normally
         // not be a problem unless you have an instance of the module directly.
-        if from.is_illegal() {
-            return Err(error::unauthorized());
-        }
-        let mut keys_to_prove = vec![];
-        if from != sender {
-            let (account, keys) = self
-                .storage
-                .get_account(from)
-                .map_err(|_| error::unauthorized())?;
-            verify_account_role(
-                &account,
-                sender,
-                account::features::ledger::AccountLedger::ID,
-                [Role::CanLedgerTransact],
-            )?;
-            keys_to_prove.extend(keys);
-        }
+        verify_can_send(&self.storage, sender, from, "ledger.send")?;
+        self.storage
+            .verify_decimal_amount(&symbol, &amount, decimal_amount.as_ref())?;
 
+        self.acknowledge(|storage| storage.send(from, &to, &symbol, amount, memo))
+    }
+
+    fn schedule(
+        &mut self,
+        sender: &Address,
+        args: ledger::ScheduleArgs,
+    ) -> Result<ledger::ScheduleReturns, ManyError> {
         self.storage
-            .send(from, &to, &symbol, amount, memo)
-            .map(|_| EmptyReturn)
+            .schedule_transaction(sender, args)
+            .map(|token| ledger::ScheduleReturns { token })
+    }
+
+    fn cancel_schedule(
+        &mut self,
+        sender: &Address,
+        args: ledger::CancelScheduleArgs,
+    ) -> Result<ledger::CancelScheduleReturns, ManyError> {
+        self.acknowledge(|storage| storage.cancel_scheduled_transaction(sender, &args.token))
+    }
+
+    fn authorize_pull(
+        &mut self,
+        sender: &Address,
+        args: ledger::AuthorizePullArgs,
+    ) -> Result<ledger::AuthorizePullReturns, ManyError> {
+        self.acknowledge(|storage| storage.authorize_pull(sender, args))
+    }
+
+    fn revoke_pull(
+        &mut self,
+        sender: &Address,
+        args: ledger::RevokePullArgs,
+    ) -> Result<ledger::RevokePullReturns, ManyError> {
+        self.acknowledge(|storage| storage.revoke_pull(sender, args))
+    }
+
+    fn pull_payment(
+        &mut self,
+        sender: &Address,
+        args: ledger::PullPaymentArgs,
+    ) -> Result<ledger::PullPaymentReturns, ManyError> {
+        self.acknowledge(|storage| storage.pull_payment(sender, args))
+    }
+
+    fn create_swap(
+        &mut self,
+        sender: &Address,
+        args: ledger::CreateSwapArgs,
+    ) -> Result<ledger::CreateSwapReturns, ManyError> {
+        self.check_swap_migration("ledger.createSwap")?;
+
+        let id = self.storage.create_swap(sender, args)?;
+        Ok(ledger::CreateSwapReturns { id })
+    }
+
+    fn accept_swap(
+        &mut self,
+        sender: &Address,
+        args: ledger::AcceptSwapArgs,
+    ) -> Result<ledger::AcceptSwapReturns, ManyError> {
+        self.check_swap_migration("ledger.acceptSwap")?;
+
+        self.acknowledge(|storage| storage.accept_swap(sender, args.id))
+    }
+
+    fn cancel_swap(
+        &mut self,
+        sender: &Address,
+        args: ledger::CancelSwapArgs,
+    ) -> Result<ledger::CancelSwapReturns, ManyError> {
+        self.check_swap_migration("ledger.cancelSwap")?;
+
+        self.acknowledge(|storage| storage.cancel_swap(sender, args.id))
     }
 }
@@ -49,18 +49,21 @@ impl ledger::LedgerMintBurnModuleBackend for LedgerModuleImpl {
         self.verify_mint_burn_identity(sender, &symbol)?;
 
         check_symbol_exists(&symbol, self.storage.get_symbols()?)?;
+        if self.storage.is_curve_token(&symbol)? {
+            return Err(ledger::supply_follows_curve(symbol));
+        }
 
-        // Mint into storage
-        let _ = self.storage.mint_token(symbol, &distribution)?;
+        self.acknowledge(|storage| {
+            // Mint into storage
+            let _ = storage.mint_token(symbol, &distribution)?;
 
-        // Log event
-        self.storage
-            .log_event(EventInfo::TokenMint {
+            // Log event
+            storage.log_event(EventInfo::TokenMint {
                 symbol,
                 distribution,
                 memo,
             })
-            .map(|_| TokenMintReturns {})
+        })
     }
 
     fn burn(
@@ -82,6 +85,9 @@ impl ledger::LedgerMintBurnModuleBackend for LedgerModuleImpl {
         self.verify_mint_burn_identity(sender, &symbol)?;
 
         check_symbol_exists(&symbol, self.storage.get_symbols()?)?;
+        if self.storage.is_curve_token(&symbol)? {
+            return Err(ledger::supply_follows_curve(symbol));
+        }
 
         // Disable partial burn, for now
         if let Some(error) = error_on_under_burn {
@@ -4,6 +4,7 @@ use crate::migration::token_create::TOKEN_CREATE_MIGRATION;
 use crate::migration::tokens::TOKEN_MIGRATION;
 use crate::module::LedgerModuleImpl;
 use crate::storage::account::verify_acl;
+use crate::storage::ledger_tokens::token_info_keys;
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::account::features::tokens::TokenAccountLedger;
@@ -11,29 +12,30 @@ use many_modules::account::features::TryCreateFeature;
 use many_modules::account::Role;
 use many_modules::ledger::{
     LedgerTokensModuleBackend, TokenAddExtendedInfoArgs, TokenAddExtendedInfoReturns,
-    TokenCreateArgs, TokenCreateReturns, TokenInfoArgs, TokenInfoReturns,
-    TokenRemoveExtendedInfoArgs, TokenRemoveExtendedInfoReturns, TokenUpdateArgs,
-    TokenUpdateReturns,
+    TokenCreateArgs, TokenCreateReturns, TokenGrantVestingArgs, TokenGrantVestingReturns,
+    TokenInfoArgs, TokenInfoReturns, TokenRemoveExtendedInfoArgs, TokenRemoveExtendedInfoReturns,
+    TokenSupplyAtArgs, TokenSupplyAtReturns, TokenUpdateArgs, TokenUpdateReturns,
+    TokenUpdateRulesArgs, TokenUpdateRulesReturns,
 };
+use many_protocol::context::Context;
+use many_types::ledger::TokenMaybeOwner;
 use many_types::Either;
 
-fn check_ticker_length(ticker: &String) -> Result<(), ManyError> {
+fn check_ticker_length(ticker: &str) -> Result<(), ManyError> {
     if !(3..=5).contains(&ticker.len()) {
         return Err(error::invalid_ticker_length(ticker));
     }
     Ok(())
 }
 
-impl LedgerTokensModuleBackend for LedgerModuleImpl {
-    fn create(
-        &mut self,
+impl LedgerModuleImpl {
+    /// Check that the sender can create a token with this owner and ticker.
+    pub(crate) fn verify_token_create(
+        &self,
         sender: &Address,
-        args: TokenCreateArgs,
-    ) -> Result<TokenCreateReturns, ManyError> {
-        if !self.storage.migrations().is_active(&TOKEN_MIGRATION) {
-            return Err(ManyError::invalid_method_name("tokens.create"));
-        }
-
+        owner: &Option<TokenMaybeOwner>,
+        ticker: &str,
+    ) -> Result<(), ManyError> {
         if self
             .storage
             .migrations()
@@ -54,17 +56,17 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
             )?;
         }
 
-        if let Some(Either::Left(addr)) = &args.owner {
+        if let Some(Either::Left(addr)) = owner {
             verify_acl(
                 &self.storage,
                 sender,
                 addr,
+                "tokens.create",
                 [Role::CanTokensCreate],
                 TokenAccountLedger::ID,
             )?;
         }
 
-        let ticker = &args.summary.ticker;
         check_ticker_length(ticker)?;
 
         if self
@@ -77,11 +79,31 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
                 "The ticker {ticker} already exists on this network"
             )));
         }
+        Ok(())
+    }
+}
+
+impl LedgerTokensModuleBackend for LedgerModuleImpl {
+    fn create(
+        &mut self,
+        sender: &Address,
+        args: TokenCreateArgs,
+    ) -> Result<TokenCreateReturns, ManyError> {
+        if !self.storage.migrations().is_active(&TOKEN_MIGRATION) {
+            return Err(ManyError::invalid_method_name("tokens.create"));
+        }
+
+        self.verify_token_create(sender, &args.owner, &args.summary.ticker)?;
         let (result, _) = self.storage.create_token(sender, args)?;
         Ok(result)
     }
 
-    fn info(&self, _sender: &Address, args: TokenInfoArgs) -> Result<TokenInfoReturns, ManyError> {
+    fn info(
+        &self,
+        _sender: &Address,
+        args: TokenInfoArgs,
+        context: Context,
+    ) -> Result<TokenInfoReturns, ManyError> {
         // Check the memory symbol cache for requested symbol
         if !self.storage.migrations().is_active(&TOKEN_MIGRATION) {
             return Err(ManyError::invalid_method_name("tokens.info"));
@@ -93,7 +115,10 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
                 "The symbol {symbol} was not found"
             )));
         }
-        self.storage.info_token(args)
+        let keys = token_info_keys(symbol);
+        let info = self.storage.info_token(args)?;
+        self.storage.prove_state(context, keys)?;
+        Ok(info)
     }
 
     fn update(
@@ -113,6 +138,7 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
                     &self.storage,
                     sender,
                     &addr,
+                    "tokens.update",
                     [Role::CanTokensUpdate],
                     TokenAccountLedger::ID,
                 )?;
@@ -136,8 +162,7 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
             check_ticker_length(ticker)?;
         }
 
-        let (result, _) = self.storage.update_token(sender, args)?;
-        Ok(result)
+        self.acknowledge(|storage| storage.update_token(sender, args))
     }
 
     fn add_extended_info(
@@ -156,6 +181,7 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
                     &self.storage,
                     sender,
                     &addr,
+                    "tokens.addExtendedInfo",
                     [Role::CanTokensAddExtendedInfo],
                     TokenAccountLedger::ID,
                 )?;
@@ -167,8 +193,7 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
             }
         }
 
-        let (result, _) = self.storage.add_extended_info(args)?;
-        Ok(result)
+        self.acknowledge(|storage| storage.add_extended_info(args))
     }
 
     fn remove_extended_info(
@@ -187,6 +212,7 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
                     &self.storage,
                     sender,
                     &addr,
+                    "tokens.removeExtendedInfo",
                     [Role::CanTokensRemoveExtendedInfo],
                     TokenAccountLedger::ID,
                 )?;
@@ -198,7 +224,67 @@ impl LedgerTokensModuleBackend for LedgerModuleImpl {
             }
         }
 
-        let (result, _) = self.storage.remove_extended_info(args)?;
-        Ok(result)
+        self.acknowledge(|storage| storage.remove_extended_info(args))
+    }
+
+    fn supply_at(
+        &self,
+        _sender: &Address,
+        args: TokenSupplyAtArgs,
+    ) -> Result<TokenSupplyAtReturns, ManyError> {
+        if !self.storage.is_supply_accounting_active() {
+            return Err(ManyError::invalid_method_name("tokens.supplyAt"));
+        }
+
+        let symbol = &args.symbol;
+        if !self.storage.get_symbols()?.contains(symbol) {
+            return Err(ManyError::unknown(format!(
+                "The symbol {symbol} was not found"
+            )));
+        }
+        self.storage.supply_at(symbol, args.height)
+    }
+
+    fn grant_vesting(
+        &mut self,
+        sender: &Address,
+        args: TokenGrantVestingArgs,
+    ) -> Result<TokenGrantVestingReturns, ManyError> {
+        if !self.storage.is_vesting_active() {
+            return Err(ManyError::invalid_method_name("tokens.grantVesting"));
+        }
+
+        self.acknowledge(|storage| storage.grant_vesting(sender, args))
+    }
+    fn update_rules(
+        &mut self,
+        sender: &Address,
+        args: TokenUpdateRulesArgs,
+    ) -> Result<TokenUpdateRulesReturns, ManyError> {
+        if !self.storage.is_token_rules_active() {
+            return Err(ManyError::invalid_method_name("tokens.updateRules"));
+        }
+
+        // Only the owner of a token can set its rules.
+        let (current_owner, _) = self.storage.get_owner(&args.symbol)?;
+        match current_owner {
+            Some(addr) => {
+                verify_acl(
+                    &self.storage,
+                    sender,
+                    &addr,
+                    "tokens.updateRules",
+                    [Role::CanTokensUpdate],
+                    TokenAccountLedger::ID,
+                )?;
+            }
+            None => {
+                return Err(ManyError::unknown(
+                    "Unable to update, this token is immutable",
+                ))
+            }
+        }
+
+        self.acknowledge(|storage| storage.update_token_rules(args))
     }
 }
@@ -0,0 +1,73 @@
+use crate::migration::MIGRATIONS;
+use crate::module::LedgerModuleImpl;
+use crate::storage::InnerStorage;
+use many_error::ManyError;
+use many_migration::InnerMigration;
+use many_modules::migrations;
+use many_types::Timestamp;
+
+impl LedgerModuleImpl {
+    fn migration_info(
+        &self,
+        migration: &InnerMigration<InnerStorage, ManyError>,
+    ) -> migrations::MigrationInfo {
+        let set = self.storage.migrations();
+        let mut info = migrations::MigrationInfo {
+            name: migration.name().to_string(),
+            description: migration.description().to_string(),
+            kind: migration.r#type().to_string(),
+            ..Default::default()
+        };
+        if set.contains_key(migration) {
+            let configured = &set[migration];
+            let metadata = configured.metadata();
+            info.configured = true;
+            info.enabled = configured.is_enabled();
+            info.active = configured.is_active();
+            info.block_height = metadata
+                .upgrade_time
+                .is_none()
+                .then_some(metadata.block_height);
+            info.upper_block_height = metadata.upper_block_height;
+            info.upgrade_time = metadata.upgrade_time.and_then(|t| Timestamp::new(t).ok());
+            info.issue = metadata.issue.clone();
+            info.extra = metadata
+                .extra
+                .iter()
+                .map(|(k, v)| (k.clone(), v.to_string()))
+                .collect();
+            info.rollout = (!metadata.rollout.is_network())
+                .then(|| serde_json::to_string(&metadata.rollout).unwrap_or_default());
+        }
+        info
+    }
+}
+
+impl migrations::MigrationsModuleBackend for LedgerModuleImpl {
+    fn list(&self, _args: migrations::ListArgs) -> Result<migrations::ListReturns, ManyError> {
+        let mut registry: Vec<_> = MIGRATIONS.iter().collect();
+        registry.sort_by_key(|m| m.name());
+        let migrations: Vec<_> = registry
+            .into_iter()
+            .map(|m| self.migration_info(m))
+            .collect();
+
+        Ok(migrations::ListReturns {
+            height: self.storage.get_height()?,
+            plan_hash: migrations::plan_hash(&migrations),
+            migrations,
+        })
+    }
+
+    fn info(&self, args: migrations::InfoArgs) -> Result<migrations::InfoReturns, ManyError> {
+        let migration = MIGRATIONS
+            .iter()
+            .find(|m| m.name() == args.name)
+            .ok_or_else(|| migrations::unknown_migration(&args.name))?;
+
+        Ok(migrations::InfoReturns {
+            height: self.storage.get_height()?,
+            migration: self.migration_info(migration),
+        })
+    }
+}
@@ -1,8 +1,8 @@
+use crate::migration::multisig_comments::MULTISIG_COMMENTS_MIGRATION;
 use crate::module::LedgerModuleImpl;
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::account::features::multisig;
-use many_modules::EmptyReturn;
 use many_protocol::ResponseMessage;
 use minicbor::bytes::ByteVec;
 
@@ -20,10 +20,11 @@ impl multisig::AccountMultisigModuleBackend for LedgerModuleImpl {
 
     fn multisig_info(
         &self,
-        _sender: &Address,
+        sender: &Address,
         args: multisig::InfoArgs,
     ) -> Result<multisig::InfoReturn, ManyError> {
         let info = self.storage.get_multisig_info(&args.token)?;
+        self.storage.check_observer(&info.account, sender)?;
         Ok(info.info)
     }
 
@@ -32,29 +33,23 @@ impl multisig::AccountMultisigModuleBackend for LedgerModuleImpl {
         sender: &Address,
         args: multisig::SetDefaultsArgs,
     ) -> Result<multisig::SetDefaultsReturn, ManyError> {
-        self.storage
-            .set_multisig_defaults(sender, args)
-            .map(|_| EmptyReturn)
+        self.acknowledge(|storage| storage.set_multisig_defaults(sender, args))
     }
 
     fn multisig_approve(
         &mut self,
         sender: &Address,
         args: multisig::ApproveArgs,
-    ) -> Result<EmptyReturn, ManyError> {
-        self.storage
-            .approve_multisig(sender, args.token.as_slice())
-            .map(|_| EmptyReturn)
+    ) -> Result<multisig::ApproveReturn, ManyError> {
+        self.acknowledge(|storage| storage.approve_multisig(sender, args.token.as_slice()))
     }
 
     fn multisig_revoke(
         &mut self,
         sender: &Address,
         args: multisig::RevokeArgs,
-    ) -> Result<EmptyReturn, ManyError> {
-        self.storage
-            .revoke_multisig(sender, args.token.as_slice())
-            .map(|_| EmptyReturn)
+    ) -> Result<multisig::RevokeReturn, ManyError> {
+        self.acknowledge(|storage| storage.revoke_multisig(sender, args.token.as_slice()))
     }
 
     fn multisig_execute(
@@ -69,9 +64,58 @@ impl multisig::AccountMultisigModuleBackend for LedgerModuleImpl {
         &mut self,
         sender: &Address,
         args: multisig::WithdrawArgs,
-    ) -> Result<EmptyReturn, ManyError> {
-        self.storage
-            .withdraw_multisig(sender, args.token.as_slice())
-            .map(|_| EmptyReturn)
+    ) -> Result<multisig::WithdrawReturn, ManyError> {
+        self.acknowledge(|storage| storage.withdraw_multisig(sender, args.token.as_slice()))
+    }
+
+    fn multisig_comment(
+        &mut self,
+        sender: &Address,
+        args: multisig::CommentArgs,
+    ) -> Result<multisig::CommentReturn, ManyError> {
+        if !self
+            .storage
+            .migrations()
+            .is_active(&MULTISIG_COMMENTS_MIGRATION)
+        {
+            return Err(ManyError::invalid_method_name("account.multisigComment"));
+        }
+        let id = self.storage.comment_multisig(sender, args)?;
+        Ok(multisig::CommentReturn { id })
+    }
+
+    fn delegate(
+        &mut self,
+        sender: &Address,
+        args: multisig::DelegateArgs,
+    ) -> Result<multisig::DelegateReturn, ManyError> {
+        if !self.storage.is_account_delegation_active() {
+            return Err(ManyError::invalid_method_name("account.delegate"));
+        }
+        self.acknowledge(|storage| storage.delegate(sender, args))
+    }
+
+    fn revoke_delegation(
+        &mut self,
+        sender: &Address,
+        args: multisig::RevokeDelegationArgs,
+    ) -> Result<multisig::RevokeDelegationReturn, ManyError> {
+        if !self.storage.is_account_delegation_active() {
+            return Err(ManyError::invalid_method_name("account.revokeDelegation"));
+        }
+        self.acknowledge(|storage| storage.revoke_delegation(sender, args))
+    }
+
+    fn delegations(
+        &self,
+        _sender: &Address,
+        args: multisig::DelegationsArgs,
+    ) -> Result<multisig::DelegationsReturn, ManyError> {
+        if !self.storage.is_account_delegation_active() {
+            return Err(ManyError::invalid_method_name("account.delegations"));
+        }
+        Ok(multisig::DelegationsReturn {
+            delegations: self.storage.delegations(&args.account)?,
+        })
     }
 }
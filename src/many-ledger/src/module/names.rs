@@ -0,0 +1,53 @@
+use crate::migration::names::NAMES_MIGRATION;
+use crate::module::LedgerModuleImpl;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::names;
+
+impl LedgerModuleImpl {
+    fn check_names_migration(&self, endpoint: &str) -> Result<(), ManyError> {
+        if self.storage.migrations().is_active(&NAMES_MIGRATION) {
+            Ok(())
+        } else {
+            Err(ManyError::invalid_method_name(endpoint))
+        }
+    }
+}
+
+impl names::NamesModuleBackend for LedgerModuleImpl {
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: names::RegisterArgs,
+    ) -> Result<names::RegisterReturns, ManyError> {
+        self.check_names_migration("names.register")?;
+
+        let names::RegisterArgs {
+            name,
+            address,
+            memo,
+        } = args;
+        let address = address.unwrap_or(*sender);
+        self.acknowledge(|storage| storage.register_name(sender, name, address, memo))
+    }
+
+    fn resolve(&self, args: names::ResolveArgs) -> Result<names::ResolveReturns, ManyError> {
+        self.check_names_migration("names.resolve")?;
+        self.storage.resolve_name(&args.name)
+    }
+
+    fn transfer(
+        &mut self,
+        sender: &Address,
+        args: names::TransferArgs,
+    ) -> Result<names::TransferReturns, ManyError> {
+        self.check_names_migration("names.transfer")?;
+
+        let names::TransferArgs {
+            name,
+            new_owner,
+            memo,
+        } = args;
+        self.acknowledge(|storage| storage.transfer_name(sender, name, new_owner, memo))
+    }
+}
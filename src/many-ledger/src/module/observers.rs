@@ -0,0 +1,60 @@
+use crate::module::LedgerModuleImpl;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_modules::{events, ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+
+/// The events module of the ledger. `events.list` only lists the events its
+/// sender can observe, which the backend trait does not know about. The
+/// other endpoints execute in the inner module.
+pub struct ObserverEventsModule {
+    inner: events::EventsModule<LedgerModuleImpl>,
+    module_impl: Arc<Mutex<LedgerModuleImpl>>,
+}
+
+impl ObserverEventsModule {
+    pub fn new(module_impl: Arc<Mutex<LedgerModuleImpl>>) -> Self {
+        Self {
+            inner: events::EventsModule::new(module_impl.clone()),
+            module_impl,
+        }
+    }
+}
+
+impl Debug for ObserverEventsModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ObserverEventsModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl ManyModule for ObserverEventsModule {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        if message.method != "events.list" {
+            return self.inner.execute(message).await;
+        }
+
+        let args = minicbor::decode(&message.data).map_err(ManyError::deserialization_error)?;
+        let returns = self
+            .module_impl
+            .lock()
+            .unwrap()
+            .list_events(&message.from(), args)?;
+        let data = minicbor::to_vec(returns).map_err(ManyError::serialization_error)?;
+        Ok(ResponseMessage::from_request(
+            &message,
+            &message.to,
+            Ok(data),
+        ))
+    }
+}
@@ -0,0 +1,142 @@
+use crate::module::LedgerModuleImpl;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::fmt::{Debug, Formatter};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use tracing::{debug, warn};
+
+type SharedModuleImpl = Arc<Mutex<LedgerModuleImpl>>;
+
+/// A copy of the state of the last committed block, replaced after every
+/// commit. Queries executing against it do not wait for the commands of the
+/// block in flight, which lock the live ledger.
+pub struct QuerySnapshots {
+    /// Directory containing the snapshots, one sub-directory per height.
+    path: PathBuf,
+
+    /// The current snapshot and its directory.
+    current: RwLock<Option<(PathBuf, SharedModuleImpl)>>,
+
+    /// Previous snapshots that may still be in use by a query.
+    retired: Mutex<Vec<(PathBuf, SharedModuleImpl)>>,
+}
+
+impl Debug for QuerySnapshots {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QuerySnapshots")
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl QuerySnapshots {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            current: RwLock::new(None),
+            retired: Mutex::new(vec![]),
+        }
+    }
+
+    /// The snapshot queries should execute against, if one was taken.
+    pub fn current(&self) -> Option<SharedModuleImpl> {
+        self.current
+            .read()
+            .unwrap()
+            .as_ref()
+            .map(|(_, snapshot)| snapshot.clone())
+    }
+
+    /// Replace the current snapshot with the committed state of
+    /// `module_impl`. The directories of the previous snapshots are removed
+    /// once no query uses them anymore.
+    pub fn refresh(&self, module_impl: &LedgerModuleImpl) -> Result<(), ManyError> {
+        let storage = module_impl.storage();
+        let height = storage.get_height()?;
+        let path = self.path.join(height.to_string());
+        if matches!(&*self.current.read().unwrap(), Some((current, _)) if current == &path) {
+            // Nothing was committed since the last snapshot.
+            return Ok(());
+        }
+        if path.exists() {
+            // Left over from a previous run of the server.
+            std::fs::remove_dir_all(&path).map_err(ManyError::unknown)?;
+        }
+
+        debug!("Opening query snapshot at {}", path.display());
+        let snapshot = LedgerModuleImpl {
+            storage: storage.open_query_snapshot(&path)?,
+            query_snapshots: None,
+        };
+        let previous = self
+            .current
+            .write()
+            .unwrap()
+            .replace((path, Arc::new(Mutex::new(snapshot))));
+
+        let mut retired = self.retired.lock().unwrap();
+        retired.extend(previous);
+        retired.retain(|(path, snapshot)| {
+            if Arc::strong_count(snapshot) > 1 {
+                return true;
+            }
+            if let Err(e) = std::fs::remove_dir_all(path) {
+                warn!("Unable to remove query snapshot {}: {e}", path.display());
+            }
+            false
+        });
+        Ok(())
+    }
+}
+
+/// A module whose queries execute against the query snapshots of the
+/// ledger, when available, instead of the live ledger. The module must
+/// only contain queries, e.g. the ledger or events modules.
+pub struct QuerySnapshotModule<M: ManyModule> {
+    inner: M,
+    snapshots: Arc<QuerySnapshots>,
+    snapshot_module: fn(SharedModuleImpl) -> M,
+}
+
+impl<M: ManyModule> QuerySnapshotModule<M> {
+    /// Wrap the `inner` module. `snapshot_module` creates the same module
+    /// over a snapshot.
+    pub fn new(
+        inner: M,
+        snapshots: Arc<QuerySnapshots>,
+        snapshot_module: fn(SharedModuleImpl) -> M,
+    ) -> Self {
+        Self {
+            inner,
+            snapshots,
+            snapshot_module,
+        }
+    }
+}
+
+impl<M: ManyModule> Debug for QuerySnapshotModule<M> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("QuerySnapshotModule")
+    }
+}
+
+#[async_trait::async_trait]
+impl<M: ManyModule> ManyModule for QuerySnapshotModule<M> {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        match self.snapshots.current() {
+            Some(snapshot) => (self.snapshot_module)(snapshot).execute(message).await,
+            None => self.inner.execute(message).await,
+        }
+    }
+}
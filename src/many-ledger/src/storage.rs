@@ -1,37 +1,96 @@
 use crate::error;
+use crate::migration::batched_writes::BATCHED_WRITES_MIGRATION;
 use crate::migration::tokens::TOKEN_MIGRATION;
 use crate::migration::{LedgerMigrations, MIGRATIONS};
 use crate::storage::account::ACCOUNT_SUBRESOURCE_ID_ROOT;
-use crate::storage::event::HEIGHT_EVENTID_SHIFT;
+use crate::storage::event::{EVENT_ID_KEY_SIZE_IN_BYTES, HEIGHT_EVENTID_SHIFT};
 use many_error::ManyError;
 use many_identity::{Address, MAX_SUBRESOURCE_ID};
 use many_migration::{MigrationConfig, MigrationSet};
 use many_modules::events::EventId;
+use many_types::clock::{Clock, SystemClock};
 use many_types::ledger::Symbol;
+use many_types::proof::cache::{ProofCache, ProofCacheStats};
 use many_types::Timestamp;
-use merk::Op;
+use merk::{BatchEntry, Op};
 use std::collections::{BTreeMap, BTreeSet};
 use std::path::Path;
+use tracing::warn;
 
 mod abci;
 pub mod account;
+mod batch;
+pub mod bonding_curve;
+pub mod bridge;
+pub mod bundle;
+pub mod checkpoint;
 pub mod data;
+pub mod delegation;
+pub mod dust;
+pub mod escrow;
 pub mod event;
+pub mod event_index;
 pub(crate) mod idstore;
+pub mod integrity;
 pub mod iterator;
+pub mod journal;
 mod ledger;
 mod ledger_commands;
 pub mod ledger_mintburn;
 pub mod ledger_tokens;
 mod migrations;
 pub mod multisig;
+pub mod names;
+pub mod pruning;
+pub mod pull_payment;
+pub mod query_snapshot;
+pub mod reservation;
+pub mod schedule;
+pub mod snapshot;
+pub mod state_export;
+pub mod state_hashes;
+pub mod statement;
+pub mod supply;
+pub mod swap;
+pub mod token_rules;
+pub mod vesting;
 
 pub const SYMBOLS_ROOT: &str = "/config/symbols";
 pub const IDENTITY_ROOT: &str = "/config/identity";
 pub const HEIGHT_ROOT: &str = "/height";
+pub const BALANCES_ROOT: &str = "/balances/";
+
+/// The key of the root hash of the last commit, in the auxiliary data of the
+/// store. It is not part of the tree, so it does not change the root hash.
+pub const LAST_APP_HASH_KEY: &[u8] = b"/last_app_hash";
+
+/// Commit the store and record its root hash, so integrity checks can
+/// compare the tree with the app hash it was committed with.
+pub(crate) fn commit_store(store: &mut InnerStorage) -> Result<(), ManyError> {
+    let hash = store.root_hash().to_vec();
+    store
+        .commit(&[(LAST_APP_HASH_KEY.to_vec(), Op::Put(hash))])
+        .map_err(error::storage_commit_failed)
+}
 
 pub(super) fn key_for_account_balance(id: &Address, symbol: &Symbol) -> Vec<u8> {
-    format!("/balances/{id}/{symbol}").into_bytes()
+    format!("{BALANCES_ROOT}{id}/{symbol}").into_bytes()
+}
+
+/// Returns a storage key under `root` for a token derived from an event ID.
+/// Tokens smaller than the event ID key size will have `\0` prepended, and
+/// larger tokens will be cut to this number of bytes.
+pub(super) fn key_for_token(root: &[u8], token: &[u8]) -> Vec<u8> {
+    let token = if token.len() > EVENT_ID_KEY_SIZE_IN_BYTES {
+        &token[0..EVENT_ID_KEY_SIZE_IN_BYTES]
+    } else {
+        token
+    };
+
+    let mut exp_token = [0u8; EVENT_ID_KEY_SIZE_IN_BYTES];
+    exp_token[(EVENT_ID_KEY_SIZE_IN_BYTES - token.len())..].copy_from_slice(token);
+
+    [root, &exp_token[..]].concat()
 }
 
 pub(super) fn key_for_subresource_counter(id: &Address, token_migration_active: bool) -> Vec<u8> {
@@ -46,7 +105,7 @@ pub(super) fn key_for_subresource_counter(id: &Address, token_migration_active:
 pub type InnerStorage = merk::Merk;
 
 pub struct LedgerStorage {
-    persistent_store: InnerStorage,
+    persistent_store: batch::BatchedStore,
 
     /// When this is true, we do not commit every transactions as they come,
     /// but wait for a `commit` call before committing the batch to the
@@ -55,10 +114,37 @@ pub struct LedgerStorage {
 
     latest_tid: EventId,
 
+    /// IDs of the events logged by the command being executed.
+    logged_events: Vec<EventId>,
+
     current_time: Option<Timestamp>,
     current_hash: Option<Vec<u8>>,
 
+    /// Source of the time outside of blocks.
+    clock: Box<dyn Clock>,
+
     migrations: LedgerMigrations,
+
+    checkpoints: Option<checkpoint::CheckpointConfig>,
+
+    /// Previous values of the keys modified by the bundle being executed, if any.
+    journal: Option<bundle::BundleJournal>,
+
+    /// Batches applied since the last commit, journaled before committing a
+    /// block. Only in blockchain mode.
+    command_journal: Option<journal::CommandJournal>,
+
+    /// Number of blocks between integrity checks, if checked periodically.
+    integrity_check_interval: Option<u64>,
+
+    /// Whether integrity checks also recompute the indices.
+    integrity_check_indices: bool,
+
+    /// The failed integrity check that made the storage read-only, if any.
+    quarantine: Option<integrity::IntegrityReport>,
+
+    /// Proofs already computed since the store last changed.
+    proof_cache: ProofCache,
 }
 
 impl LedgerStorage {
@@ -76,14 +162,11 @@ impl LedgerStorage {
         let key = key_for_account_balance(&account, &symbol);
         let amount = many_types::ledger::TokenAmount::from(amount);
 
-        self.persistent_store
-            .apply(&[(key, Op::Put(amount.to_vec()))])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(key, Op::Put(amount.to_vec()))])?;
 
         // Always commit to the store. In blockchain mode this will fail.
-        self.persistent_store
-            .commit(&[])
-            .map_err(error::storage_commit_failed)?;
+        self.flush_writes()?;
+        commit_store(self.persistent_store.flushed_mut())?;
         Ok(())
     }
 }
@@ -102,8 +185,12 @@ impl LedgerStorage {
         self.current_time = Some(time);
     }
     #[inline]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+    #[inline]
     pub fn now(&self) -> Timestamp {
-        self.current_time.unwrap_or_else(Timestamp::now)
+        self.current_time.unwrap_or_else(|| self.clock.now())
     }
 
     pub fn migrations(&self) -> &LedgerMigrations {
@@ -119,11 +206,61 @@ impl LedgerStorage {
         }
     }
 
+    /// Apply a batch of operations to the persistent store. The previous values
+    /// are recorded if a bundle is being executed, so they can be restored.
+    ///
+    /// Once the Batched Writes Migration is active, the operations are only
+    /// staged until the block is committed.
+    fn apply(&mut self, batch: &[BatchEntry]) -> Result<(), ManyError> {
+        self.check_quarantine()?;
+        if let Some(journal) = self.journal.as_mut() {
+            for (key, _) in batch {
+                if !journal.previous.contains_key(key) {
+                    let value = self
+                        .persistent_store
+                        .get(key)
+                        .map_err(error::storage_get_failed)?;
+                    journal.previous.insert(key.clone(), value);
+                }
+            }
+        }
+
+        self.persistent_store.stage(batch)?;
+        if self.migrations.is_active(&BATCHED_WRITES_MIGRATION) {
+            Ok(())
+        } else {
+            self.flush_writes()
+        }
+    }
+
+    /// Apply the staged operations to the persistent store, in one batch.
+    fn flush_writes(&mut self) -> Result<(), ManyError> {
+        let batch = self.persistent_store.flush()?;
+        if !batch.is_empty() {
+            self.proof_cache.invalidate();
+            if let Some(command_journal) = self.command_journal.as_mut() {
+                command_journal.record(&batch);
+            }
+        }
+        Ok(())
+    }
+
     #[inline]
     fn commit_storage(&mut self) -> Result<(), ManyError> {
-        self.persistent_store
-            .commit(&[])
-            .map_err(error::storage_commit_failed)
+        self.check_quarantine()?;
+        self.flush_writes()?;
+        commit_store(self.persistent_store.flushed_mut())?;
+        self.proof_cache.invalidate();
+        if let Some(command_journal) = self.command_journal.as_mut() {
+            command_journal.clear();
+        }
+        Ok(())
+    }
+
+    /// The number of proofs served from the cache and computed since the
+    /// storage was opened.
+    pub fn proof_cache_stats(&self) -> ProofCacheStats {
+        self.proof_cache.stats()
     }
 
     pub fn load<P: AsRef<Path>>(
@@ -131,10 +268,11 @@ impl LedgerStorage {
         blockchain: bool,
         migration_config: Option<MigrationConfig>,
     ) -> Result<Self, ManyError> {
-        let persistent_store =
+        let persistent_path = persistent_path.as_ref();
+        let mut persistent_store =
             InnerStorage::open(persistent_path).map_err(error::storage_open_failed)?;
 
-        let height = persistent_store
+        let mut height = persistent_store
             .get(HEIGHT_ROOT.as_bytes())
             .map_err(error::storage_get_failed)?
             .map_or(0u64, |x| {
@@ -143,6 +281,35 @@ impl LedgerStorage {
                 u64::from_be_bytes(bytes)
             });
 
+        // A journal left over by a crash while committing a block. The
+        // migrations are those of the block before it, so the recovery runs
+        // the ones of the block.
+        let record = if blockchain {
+            journal::read_journal(persistent_path).unwrap_or_else(|e| {
+                warn!("Discarding the command journal: {e}");
+                None
+            })
+        } else {
+            None
+        };
+        let migrations_height = match &record {
+            Some(record) if record.height == height || record.height == height + 1 => {
+                record.height - 1
+            }
+            _ => height,
+        };
+
+        let mut migrations = migration_config
+            .map_or_else(MigrationSet::empty, |config| {
+                LedgerMigrations::load(&MIGRATIONS, config, migrations_height)
+            })
+            .map_err(error::unable_to_load_migrations)?;
+
+        if let Some(record) = record {
+            height = journal::recover(&mut persistent_store, &mut migrations, &record, height)?;
+            journal::clear_journal(persistent_path);
+        }
+
         // The call to `saturating_sub()` is required to fix
         // https://github.com/liftedinit/many-framework/issues/289
         //
@@ -152,40 +319,57 @@ impl LedgerStorage {
         // The discrepancy will lead to an application hash mismatch if the block following the `load()` contains
         // a transaction.
         let latest_tid = EventId::from(height.saturating_sub(1) << HEIGHT_EVENTID_SHIFT);
-        let migrations = migration_config
-            .map_or_else(MigrationSet::empty, |config| {
-                LedgerMigrations::load(&MIGRATIONS, config, height)
-            })
-            .map_err(error::unable_to_load_migrations)?;
 
         Ok(Self {
-            persistent_store,
+            persistent_store: batch::BatchedStore::new(persistent_store),
             blockchain,
             latest_tid,
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             migrations,
+            checkpoints: None,
+            journal: None,
+            command_journal: blockchain.then(|| journal::CommandJournal::new(persistent_path)),
+            logged_events: vec![],
+            integrity_check_interval: None,
+            integrity_check_indices: false,
+            quarantine: None,
+            proof_cache: ProofCache::default(),
         })
     }
 
     pub fn new<P: AsRef<Path>>(persistent_path: P, blockchain: bool) -> Result<Self, ManyError> {
+        let persistent_path = persistent_path.as_ref();
         let persistent_store = InnerStorage::open(persistent_path).map_err(ManyError::unknown)?; // TODO: Custom error
 
         Ok(Self {
-            persistent_store,
+            persistent_store: batch::BatchedStore::new(persistent_store),
             blockchain,
             latest_tid: EventId::from(vec![0]),
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             migrations: MigrationSet::empty().map_err(ManyError::unknown)?, // TODO: Custom error
+            checkpoints: None,
+            journal: None,
+            command_journal: blockchain.then(|| journal::CommandJournal::new(persistent_path)),
+            logged_events: vec![],
+            integrity_check_interval: None,
+            integrity_check_indices: false,
+            quarantine: None,
+            proof_cache: ProofCache::default(),
         })
     }
 
     pub fn build(mut self) -> Result<Self, ManyError> {
-        self.persistent_store
-            .commit(&[])
-            .map_err(error::storage_commit_failed)
-            .map(|_| self)
+        self.flush_writes()?;
+        commit_store(self.persistent_store.flushed_mut())?;
+        // The initial state is not part of a block.
+        if let Some(command_journal) = self.command_journal.as_mut() {
+            command_journal.clear();
+        }
+        Ok(self)
     }
 
     /// Kept for backward compatibility
@@ -213,12 +397,10 @@ impl LedgerStorage {
 
     fn inc_height(&mut self) -> Result<u64, ManyError> {
         let current_height = self.get_height()?;
-        self.persistent_store
-            .apply(&[(
-                HEIGHT_ROOT.as_bytes().to_vec(),
-                Op::Put((current_height + 1).to_be_bytes().to_vec()),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            HEIGHT_ROOT.as_bytes().to_vec(),
+            Op::Put((current_height + 1).to_be_bytes().to_vec()),
+        )])?;
         Ok(current_height)
     }
 
@@ -289,12 +471,10 @@ impl LedgerStorage {
             self.migrations.is_active(&TOKEN_MIGRATION),
         );
 
-        self.persistent_store
-            .apply(&[(
-                key_for_subresource.clone(),
-                Op::Put((current_id + 1).to_be_bytes().to_vec()),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            key_for_subresource.clone(),
+            Op::Put((current_id + 1).to_be_bytes().to_vec()),
+        )])?;
         let mut keys = vec![key_for_subresource];
 
         self.persistent_store
@@ -1,34 +1,146 @@
+use crate::error;
 use crate::storage::event::HEIGHT_EVENTID_SHIFT;
 use crate::storage::LedgerStorage;
-use many_modules::abci_backend::AbciCommitInfo;
+use many_error::ManyError;
+use many_modules::abci_backend::{AbciCommitInfo, TxOrdering};
 use many_modules::events::EventId;
+use merk::Op;
+use tracing::{debug, error, warn};
+
+pub(crate) const TX_ORDERING_ROOT: &[u8] = b"/config/tx_ordering";
+pub(crate) const BLOCK_GAS_LIMIT_ROOT: &[u8] = b"/config/block_gas_limit";
 
 impl LedgerStorage {
+    /// Set the order of execution of the transactions of a block. The policy
+    /// is part of the state, so it is the same on every node. Without it,
+    /// transactions are executed in the order of the proposals.
+    pub fn with_tx_ordering(mut self, ordering: Option<TxOrdering>) -> Result<Self, ManyError> {
+        if let Some(ordering) = ordering {
+            self.apply(&[(
+                TX_ORDERING_ROOT.to_vec(),
+                Op::Put(minicbor::to_vec(ordering).map_err(ManyError::serialization_error)?),
+            )])?;
+        }
+        Ok(self)
+    }
+
+    pub fn tx_ordering(&self) -> Result<TxOrdering, ManyError> {
+        self.persistent_store
+            .get(TX_ORDERING_ROOT)
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(TxOrdering::Arrival), |bytes| {
+                minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+            })
+    }
+
+    /// Set the maximum gas the transactions of a block can use, metered by
+    /// the ABCI frontend. Like the ordering policy, the limit is part of the
+    /// state so every validator enforces the same one.
+    pub fn with_block_gas_limit(mut self, limit: Option<u64>) -> Result<Self, ManyError> {
+        if let Some(limit) = limit {
+            self.apply(&[(
+                BLOCK_GAS_LIMIT_ROOT.to_vec(),
+                Op::Put(limit.to_be_bytes().to_vec()),
+            )])?;
+        }
+        Ok(self)
+    }
+
+    pub fn block_gas_limit(&self) -> Result<Option<u64>, ManyError> {
+        self.persistent_store
+            .get(BLOCK_GAS_LIMIT_ROOT)
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| {
+                bytes
+                    .try_into()
+                    .map(u64::from_be_bytes)
+                    .map_err(|_| ManyError::deserialization_error("Invalid block gas limit"))
+            })
+            .transpose()
+    }
+
     pub fn commit(&mut self) -> AbciCommitInfo {
+        // A quarantined storage is not changed, so the node stops following
+        // the chain instead of committing on top of a corrupt state.
+        if let Some(report) = &self.quarantine {
+            error!("Not committing the block, the storage is quarantined: {report}");
+            return AbciCommitInfo {
+                retain_height: 0,
+                hash: self.hash().into(),
+            };
+        }
+
         // First check if there's any need to clean up multisig transactions. Ignore
         // errors.
         let _ = self.check_timed_out_multisig_transactions();
 
+        // Execute the scheduled transactions that are due at this block. Failures
+        // of the transactions are recorded in the execution events.
+        if let Err(e) = self.process_scheduled_transactions() {
+            error!("Unable to process the scheduled transactions: {e}");
+        }
+
+        // Update the registered data metrics with the changes of this block.
+        if self.is_data_metrics_active() {
+            if let Err(e) = self.update_data_metrics() {
+                warn!("Unable to update the data metrics: {e}");
+            }
+        }
+
         let height = self.inc_height().expect("Unable to increment height.");
         let retain_height = 0;
 
+        // The root hash journaled needs the staged changes of the block.
+        self.flush_writes()
+            .expect("Unable to apply the changes of the block.");
+
+        // Journal the block, so a crash between the two commits below can be
+        // recovered from on startup.
+        let time = self.current_time.map(|t| t.secs());
+        if let Some(command_journal) = self.command_journal.as_mut() {
+            let hash = self.persistent_store.root_hash();
+            if let Err(e) = command_journal.seal(height + 1, time, hash) {
+                warn!("Unable to write the command journal: {e}");
+            }
+        }
+
         // Committing before the migration so that the migration has
         // the actual state of the database when setting its
         // attributes.
         self.commit_storage().expect("Unable to commit to storage.");
 
-        // Initialize/update migrations at current height, if any
+        // Initialize/update migrations at current height and block time, if any
         self.migrations
-            .update_at_height(&mut self.persistent_store, height + 1)
+            .update_at(self.persistent_store.flushed_mut(), height + 1, time)
             .expect("Unable to run migrations");
 
         self.commit_storage().expect("Unable to commit to storage.");
 
+        if let Some(Err(e)) = self.command_journal.as_ref().map(|j| j.remove()) {
+            warn!("Unable to remove the command journal: {e}");
+        }
+
         let hash = self.persistent_store.root_hash().to_vec();
         self.current_hash = Some(hash.clone());
 
         self.latest_tid = EventId::from(height << HEIGHT_EVENTID_SHIFT);
 
+        // Checkpoints are local to this node and do not affect the state hash.
+        if let Err(e) = self.create_checkpoint(height + 1) {
+            warn!("Unable to create checkpoint: {e}");
+        }
+
+        if let Err(e) = self.maybe_check_integrity(height + 1) {
+            warn!("Unable to check the integrity of the storage: {e}");
+        }
+
+        let stats = self.proof_cache_stats();
+        debug!(
+            target: "metrics",
+            proof_cache_hits = stats.hits,
+            proof_cache_misses = stats.misses,
+        );
+
         AbciCommitInfo {
             retain_height,
             hash: hash.into(),
@@ -9,7 +9,8 @@ use crate::storage::multisig::{
 use crate::storage::{LedgerStorage, IDENTITY_ROOT};
 use many_error::ManyError;
 use many_identity::Address;
-use many_modules::account::features::{FeatureId, FeatureInfo, FeatureSet};
+use many_modules::account::features::observers::ObserversAccountFeature;
+use many_modules::account::features::{FeatureId, FeatureInfo, FeatureSet, TryCreateFeature};
 use many_modules::account::Role;
 use many_modules::{account, events};
 use many_types::Either;
@@ -33,10 +34,16 @@ pub(super) fn key_for_account(id: &Address) -> Vec<u8> {
     format!("/accounts/{id}").into_bytes()
 }
 
+/// The account created by a sender with a nonce.
+pub(super) fn key_for_account_nonce(sender: &Address, nonce: &[u8]) -> Vec<u8> {
+    format!("/account_nonces/{sender}/{}", hex::encode(nonce)).into_bytes()
+}
+
 pub fn verify_acl(
     storage: &LedgerStorage,
     sender: &Address,
     addr: &Address,
+    method: &str,
     roles: impl IntoIterator<Item = Role>,
     feature_id: FeatureId,
 ) -> Result<Vec<Vec<u8>>, ManyError> {
@@ -44,7 +51,8 @@ pub fn verify_acl(
         let (account, keys) = storage
             .get_account(addr)
             .map_err(|_| error::unauthorized())?;
-        verify_account_role(&account, sender, feature_id, roles).map(|_| keys.into_iter().collect())
+        verify_account_role(&account, sender, method, feature_id, roles)
+            .map(|_| keys.into_iter().collect())
     } else {
         Ok(Vec::<Vec<u8>>::new())
     }
@@ -59,12 +67,10 @@ impl LedgerStorage {
     ) -> Result<Self, ManyError> {
         if self.migrations.is_active(&TOKEN_MIGRATION) {
             let identity = identity.unwrap_or(self.get_identity(IDENTITY_ROOT)?);
-            self.persistent_store
-                .apply(&[(
-                    ACCOUNT_IDENTITY_ROOT.as_bytes().to_vec(),
-                    Op::Put(identity.to_vec()),
-                )])
-                .map_err(error::storage_apply_failed)?;
+            self.apply(&[(
+                ACCOUNT_IDENTITY_ROOT.as_bytes().to_vec(),
+                Op::Put(identity.to_vec()),
+            )])?;
         }
 
         if let Some(accounts) = accounts {
@@ -169,6 +175,28 @@ impl LedgerStorage {
         self._add_account(account, true)
     }
 
+    /// Add an account, unless `sender` already added one with `nonce`. Returns
+    /// the address of the account, and whether it was added.
+    pub fn add_account_with_nonce(
+        &mut self,
+        sender: &Address,
+        nonce: &[u8],
+        account: account::Account,
+    ) -> Result<(Address, bool), ManyError> {
+        let key = key_for_account_nonce(sender, nonce);
+        if let Some(bytes) = self
+            .persistent_store
+            .get(&key)
+            .map_err(error::storage_get_failed)?
+        {
+            return Ok((Address::from_bytes(&bytes)?, false));
+        }
+
+        let (id, _) = self.add_account(account)?;
+        self.apply(&[(key, Op::Put(id.to_vec()))])?;
+        self.maybe_commit().map(|_| (id, true))
+    }
+
     pub fn disable_account(
         &mut self,
         id: &Address,
@@ -314,6 +342,36 @@ impl LedgerStorage {
             .unwrap_or_else(|| Err(account::errors::unknown_account(id)))
     }
 
+    /// Whether `sender` can query the account `id`. Accounts with the
+    /// observers feature can only be queried by the identities with a role on
+    /// them. Other accounts, and addresses that are not accounts, are public.
+    pub fn can_observe(&self, id: &Address, sender: &Address) -> Result<bool, ManyError> {
+        if id == sender
+            || self
+                .persistent_store
+                .get(&key_for_account(id))
+                .unwrap_or_default()
+                .is_none()
+        {
+            return Ok(true);
+        }
+        let (account, _) = self.get_account_even_disabled(id)?;
+        Ok(!account.features.has_id(ObserversAccountFeature::ID)
+            || account
+                .roles
+                .get(sender)
+                .map_or(false, |roles| !roles.is_empty()))
+    }
+
+    /// Fail unless `sender` can query the account `id`.
+    pub fn check_observer(&self, id: &Address, sender: &Address) -> Result<(), ManyError> {
+        if self.can_observe(id, sender)? {
+            Ok(())
+        } else {
+            Err(account::errors::user_needs_role(Role::Observer))
+        }
+    }
+
     pub fn commit_account(
         &mut self,
         id: &Address,
@@ -322,12 +380,10 @@ impl LedgerStorage {
         tracing::debug!("commit({:?})", account);
         let key = key_for_account(id);
 
-        self.persistent_store
-            .apply(&[(
-                key.clone(),
-                Op::Put(minicbor::to_vec(account).map_err(ManyError::serialization_error)?),
-            )])
-            .map_err(|e| ManyError::unknown(e.to_string()))?;
+        self.apply(&[(
+            key.clone(),
+            Op::Put(minicbor::to_vec(account).map_err(ManyError::serialization_error)?),
+        )])?;
 
         self.maybe_commit().map(|_| key)
     }
@@ -0,0 +1,153 @@
+//! Writes staged until they are applied to the Merk store in one batch.
+//!
+//! Each `apply` of Merk rebalances the tree and hashes the nodes along the
+//! paths of its keys, so a block of thousands of sends applied one command
+//! at a time hashes the top of the tree thousands of times. Once the Batched
+//! Writes Migration is active, the changes of a block are staged here and
+//! applied in one sorted batch when the block is committed.
+//!
+//! The shape of the tree depends on the order its keys are applied in, so
+//! the root hash is not the same as applying each command on its own. This
+//! is why batching is enabled by a migration.
+use crate::error;
+use crate::storage::InnerStorage;
+use many_error::ManyError;
+use merk::{BatchEntry, Op};
+use std::collections::BTreeMap;
+use std::ops::Deref;
+
+/// The Merk store, and the writes staged since they were last applied to it.
+///
+/// Reads of single keys with [`get`](Self::get) see the staged writes. The
+/// rest of the store (iterators, proofs, root hash) is the one of the Merk
+/// store, without them. Iterators only see committed keys in any case.
+pub(crate) struct BatchedStore {
+    store: InnerStorage,
+
+    /// The value of each key changed since the last flush, or `None` if it
+    /// was deleted.
+    staged: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl BatchedStore {
+    pub fn new(store: InnerStorage) -> Self {
+        Self {
+            store,
+            staged: BTreeMap::new(),
+        }
+    }
+
+    pub fn get(&self, key: &[u8]) -> merk::Result<Option<Vec<u8>>> {
+        match self.staged.get(key) {
+            Some(value) => Ok(value.clone()),
+            None => self.store.get(key),
+        }
+    }
+
+    /// Stage the operations of a batch. Deleting a key that does not exist
+    /// is an error, as it is for Merk.
+    pub fn stage(&mut self, batch: &[BatchEntry]) -> Result<(), ManyError> {
+        for (key, op) in batch {
+            match op {
+                Op::Put(value) => {
+                    self.staged.insert(key.clone(), Some(value.clone()));
+                }
+                Op::Delete => {
+                    // Whether the key has a staged value, or a staged deletion.
+                    let staged = self.staged.get(key).map(Option::is_some);
+                    let in_store = self
+                        .store
+                        .get(key)
+                        .map_err(error::storage_get_failed)?
+                        .is_some();
+                    if !staged.unwrap_or(in_store) {
+                        return Err(error::storage_apply_failed(format!(
+                            "Tried to delete non-existent key {}",
+                            hex::encode(key)
+                        )));
+                    }
+
+                    // A key created since the last flush is only staged.
+                    if in_store {
+                        self.staged.insert(key.clone(), None);
+                    } else {
+                        self.staged.remove(key);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the staged writes to the Merk store, sorted by key. Returns the
+    /// batch applied, empty if nothing was staged.
+    pub fn flush(&mut self) -> Result<Vec<BatchEntry>, ManyError> {
+        let batch: Vec<BatchEntry> = std::mem::take(&mut self.staged)
+            .into_iter()
+            .map(|(key, value)| (key, value.map_or(Op::Delete, Op::Put)))
+            .collect();
+        if !batch.is_empty() {
+            self.store
+                .apply(&batch)
+                .map_err(error::storage_apply_failed)?;
+        }
+        Ok(batch)
+    }
+
+    /// The Merk store, to change it directly. The staged writes need to be
+    /// flushed first.
+    pub fn flushed_mut(&mut self) -> &mut InnerStorage {
+        debug_assert!(self.staged.is_empty(), "Staged writes were not flushed");
+        &mut self.store
+    }
+}
+
+impl Deref for BatchedStore {
+    type Target = InnerStorage;
+
+    fn deref(&self) -> &Self::Target {
+        &self.store
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn staged() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = BatchedStore::new(InnerStorage::open(dir.path()).unwrap());
+        store.stage(&[(b"/a".to_vec(), Op::Put(vec![1]))]).unwrap();
+        store.flush().unwrap();
+
+        store
+            .stage(&[
+                (b"/a".to_vec(), Op::Delete),
+                (b"/b".to_vec(), Op::Put(vec![2])),
+            ])
+            .unwrap();
+        store.stage(&[(b"/c".to_vec(), Op::Put(vec![3]))]).unwrap();
+        store.stage(&[(b"/c".to_vec(), Op::Delete)]).unwrap();
+        assert_eq!(store.get(b"/a").unwrap(), None);
+        assert_eq!(store.get(b"/b").unwrap(), Some(vec![2]));
+        assert_eq!(store.get(b"/c").unwrap(), None);
+        // The Merk store does not have the staged writes.
+        assert_eq!(store.store.get(b"/a").unwrap(), Some(vec![1]));
+        assert!(store.stage(&[(b"/c".to_vec(), Op::Delete)]).is_err());
+        assert!(store.stage(&[(b"/d".to_vec(), Op::Delete)]).is_err());
+
+        // The key created then deleted is not in the batch.
+        let batch = store.flush().unwrap();
+        assert_eq!(
+            batch
+                .iter()
+                .map(|(key, _)| key.as_slice())
+                .collect::<Vec<_>>(),
+            vec![b"/a".as_slice(), b"/b".as_slice()]
+        );
+        assert_eq!(store.store.get(b"/a").unwrap(), None);
+        assert_eq!(store.store.get(b"/b").unwrap(), Some(vec![2]));
+        assert!(store.flush().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,241 @@
+use crate::error;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::events::EventInfo;
+use many_modules::ledger::{
+    self, BondingCurve, CurveInfoReturns, TokenCreateArgs, TokenCreateWithCurveArgs,
+    TokenCreateWithCurveReturns,
+};
+use many_types::ledger::{LedgerTokensAddressMap, Symbol, TokenAmount};
+use many_types::Memo;
+use merk::{BatchEntry, Op};
+
+pub(crate) const CURVES_ROOT: &[u8] = b"/curves/";
+
+pub(super) fn key_for_curve(symbol: &Symbol) -> Vec<u8> {
+    [CURVES_ROOT, symbol.to_string().as_bytes()].concat()
+}
+
+/// The curve of a token and its reserve pool.
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug)]
+#[cbor(map)]
+struct CurveStorage {
+    #[n(0)]
+    reserve: Symbol,
+
+    #[n(1)]
+    curve: BondingCurve,
+
+    /// The reserve tokens paid by buyers and not yet returned to sellers.
+    #[n(2)]
+    pool: TokenAmount,
+}
+
+impl LedgerStorage {
+    fn get_curve(&self, symbol: &Symbol) -> Result<Option<CurveStorage>, ManyError> {
+        self.persistent_store
+            .get(&key_for_curve(symbol))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    pub fn is_curve_token(&self, symbol: &Symbol) -> Result<bool, ManyError> {
+        Ok(self.get_curve(symbol)?.is_some())
+    }
+
+    /// The symbol paid to buy a curve token, if the symbol is one.
+    pub(crate) fn curve_reserve(&self, symbol: &Symbol) -> Result<Option<Symbol>, ManyError> {
+        Ok(self.get_curve(symbol)?.map(|curve| curve.reserve))
+    }
+
+    /// Set the reserve balance of an account and the curve record in a single batch.
+    fn settle_curve(
+        &mut self,
+        symbol: &Symbol,
+        record: &CurveStorage,
+        account: &Address,
+        reserve_balance: TokenAmount,
+    ) -> Result<(), ManyError> {
+        let mut batch: Vec<BatchEntry> = vec![
+            self.balance_entry(account, &record.reserve, &reserve_balance)?,
+            (
+                key_for_curve(symbol),
+                Op::Put(minicbor::to_vec(record).map_err(ManyError::serialization_error)?),
+            ),
+        ];
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)
+    }
+
+    pub fn create_token_with_curve(
+        &mut self,
+        sender: &Address,
+        args: TokenCreateWithCurveArgs,
+    ) -> Result<TokenCreateWithCurveReturns, ManyError> {
+        let TokenCreateWithCurveArgs {
+            summary,
+            owner,
+            maximum_supply,
+            extended_info,
+            memo,
+            reserve,
+            curve,
+        } = args;
+
+        curve.validate()?;
+        // Curve tokens cannot be reserves, so pools cannot be nested.
+        if !self.get_symbols()?.contains(&reserve) || self.is_curve_token(&reserve)? {
+            return Err(ledger::invalid_reserve(reserve));
+        }
+
+        let (result, _) = self.create_token(
+            sender,
+            TokenCreateArgs {
+                summary,
+                owner,
+                initial_distribution: None,
+                maximum_supply,
+                extended_info,
+                memo,
+                dust: None,
+            },
+        )?;
+
+        let record = CurveStorage {
+            reserve,
+            curve,
+            pool: TokenAmount::zero(),
+        };
+        self.apply(&[(
+            key_for_curve(&result.info.symbol),
+            Op::Put(minicbor::to_vec(&record).map_err(ManyError::serialization_error)?),
+        )])?;
+
+        self.maybe_commit().map(|_| result)
+    }
+
+    pub fn curve_info(&self, symbol: &Symbol) -> Result<CurveInfoReturns, ManyError> {
+        let CurveStorage {
+            reserve,
+            curve,
+            pool,
+        } = self
+            .get_curve(symbol)?
+            .ok_or_else(|| ledger::not_a_curve_token(symbol))?;
+        let supply = self.get_token_supply(symbol)?.circulating;
+        let price = curve.spot_price(&supply);
+
+        Ok(CurveInfoReturns {
+            reserve,
+            curve,
+            pool,
+            supply,
+            price,
+        })
+    }
+
+    /// Mint `amount` tokens to the buyer, in exchange for reserve tokens
+    /// following the curve. Returns the cost.
+    pub fn buy_from_curve(
+        &mut self,
+        buyer: &Address,
+        symbol: Symbol,
+        amount: TokenAmount,
+        maximum_cost: Option<TokenAmount>,
+        memo: Option<Memo>,
+    ) -> Result<TokenAmount, ManyError> {
+        if amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        let mut record = self
+            .get_curve(&symbol)?
+            .ok_or_else(|| ledger::not_a_curve_token(symbol))?;
+
+        let supply = self.get_token_supply(&symbol)?.circulating;
+        let cost = record.curve.buy_cost(&supply, &amount);
+        if let Some(maximum) = maximum_cost {
+            if cost > maximum {
+                return Err(ledger::cost_over_maximum(cost, maximum));
+            }
+        }
+
+        let balance = self.get_balance(buyer, &record.reserve)?;
+        if balance < cost {
+            return Err(error::insufficient_funds());
+        }
+
+        // Minting first checks the maximum supply, if any.
+        let _ = self.mint_token(
+            symbol,
+            &LedgerTokensAddressMap::from([(*buyer, amount.clone())]),
+        )?;
+
+        record.pool += &cost;
+        self.settle_curve(&symbol, &record, buyer, &balance - &cost)?;
+
+        self.log_event(EventInfo::TokenBuy {
+            symbol,
+            buyer: *buyer,
+            amount,
+            cost: cost.clone(),
+            memo,
+        })?;
+        Ok(cost)
+    }
+
+    /// Burn `amount` tokens from the seller, and return them reserve tokens
+    /// following the curve. Returns the proceeds.
+    pub fn sell_to_curve(
+        &mut self,
+        seller: &Address,
+        symbol: Symbol,
+        amount: TokenAmount,
+        minimum_proceeds: Option<TokenAmount>,
+        memo: Option<Memo>,
+    ) -> Result<TokenAmount, ManyError> {
+        if amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        let mut record = self
+            .get_curve(&symbol)?
+            .ok_or_else(|| ledger::not_a_curve_token(symbol))?;
+
+        // The seller's balance is part of the supply, so this also makes
+        // sure the supply covers the amount.
+        let balance = self.get_balance(seller, &symbol)?;
+        if balance < amount {
+            return Err(error::insufficient_funds());
+        }
+
+        let supply = self.get_token_supply(&symbol)?.circulating;
+        let proceeds = record.curve.sell_proceeds(&supply, &amount);
+        if let Some(minimum) = minimum_proceeds {
+            if proceeds < minimum {
+                return Err(ledger::proceeds_under_minimum(proceeds, minimum));
+            }
+        }
+        if proceeds > record.pool {
+            return Err(error::insufficient_funds());
+        }
+
+        let _ = self.burn_token(
+            symbol,
+            &LedgerTokensAddressMap::from([(*seller, amount.clone())]),
+        )?;
+
+        record.pool -= &proceeds;
+        let reserve_balance = self.get_balance(seller, &record.reserve)? + proceeds.clone();
+        self.settle_curve(&symbol, &record, seller, reserve_balance)?;
+
+        self.log_event(EventInfo::TokenSell {
+            symbol,
+            seller: *seller,
+            amount,
+            proceeds: proceeds.clone(),
+            memo,
+        })?;
+        Ok(proceeds)
+    }
+}
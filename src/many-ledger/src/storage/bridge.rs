@@ -0,0 +1,401 @@
+use crate::error;
+use crate::storage::ledger_tokens::{verify_tokens_sender, TOKEN_IDENTITY_ROOT};
+use crate::storage::snapshot::execute_proof;
+use crate::storage::{key_for_token, LedgerStorage, IDENTITY_ROOT};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::bridge::{self, BridgeTransfer, Counterparty, TransferKind};
+use many_modules::events;
+use many_types::ledger::{LedgerTokensAddressMap, Symbol, TokenAmount};
+use merk::proofs::query::QueryItem;
+use merk::rocksdb::{self, IteratorMode, ReadOptions};
+use merk::{BatchEntry, Op};
+use minicbor::bytes::ByteVec;
+use std::collections::BTreeSet;
+
+pub(crate) const BRIDGE_COUNTERPARTIES_ROOT: &str = "/bridge/counterparties/";
+pub(crate) const BRIDGE_ANCHORS_ROOT: &str = "/bridge/anchors/";
+pub(crate) const BRIDGE_CLAIMS_ROOT: &str = "/bridge/claims/";
+pub(crate) const BRIDGE_TRANSFERS_ROOT: &[u8] = b"/bridge/transfers/";
+
+fn key_for_counterparty(chain: &Address) -> Vec<u8> {
+    format!("{BRIDGE_COUNTERPARTIES_ROOT}{chain}").into_bytes()
+}
+
+fn key_for_anchor(chain: &Address, height: u64) -> Vec<u8> {
+    let mut key = format!("{BRIDGE_ANCHORS_ROOT}{chain}/").into_bytes();
+    key.extend(height.to_be_bytes());
+    key
+}
+
+fn key_for_claim(chain: &Address, id: &[u8]) -> Vec<u8> {
+    let mut key = format!("{BRIDGE_CLAIMS_ROOT}{chain}/").into_bytes();
+    key.extend(key_for_transfer(id));
+    key
+}
+
+/// The key of a transfer record, on its source ledger.
+pub fn key_for_transfer(id: &[u8]) -> Vec<u8> {
+    key_for_token(BRIDGE_TRANSFERS_ROOT, id)
+}
+
+/// Bridges send the native tokens locked for a counterparty to its address,
+/// and release them from it, like any other transfer. They mint or burn the
+/// wrapped tokens of the counterparty like any other token.
+impl LedgerStorage {
+    fn get_counterparty(&self, chain: &Address) -> Result<Counterparty, ManyError> {
+        let bytes = self
+            .persistent_store
+            .get(&key_for_counterparty(chain))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| bridge::unknown_counterparty(chain))?;
+
+        minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+    }
+
+    fn counterparty_entry(
+        chain: &Address,
+        counterparty: &Counterparty,
+    ) -> Result<BatchEntry, ManyError> {
+        Ok((
+            key_for_counterparty(chain),
+            Op::Put(minicbor::to_vec(counterparty).map_err(ManyError::serialization_error)?),
+        ))
+    }
+
+    fn get_anchor(&self, chain: &Address, height: u64) -> Result<Option<Vec<u8>>, ManyError> {
+        self.persistent_store
+            .get(&key_for_anchor(chain, height))
+            .map_err(error::storage_get_failed)
+    }
+
+    /// The last height of a counterparty with an anchored state hash.
+    fn last_anchored_height(&self, chain: &Address) -> Result<Option<u64>, ManyError> {
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(
+            format!("{BRIDGE_ANCHORS_ROOT}{chain}/").into_bytes(),
+        ));
+        match self
+            .persistent_store
+            .iter_opt(IteratorMode::End, options)
+            .next()
+        {
+            Some(item) => {
+                let (key, _) = item.map_err(error::storage_get_failed)?;
+                let height = <[u8; 8]>::try_from(&key[key.len() - 8..])
+                    .map_err(ManyError::deserialization_error)?;
+                Ok(Some(u64::from_be_bytes(height)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn bridge_info(&self, chain: &Address) -> Result<bridge::InfoReturns, ManyError> {
+        let (locked, _) = self.get_multiple_balances(chain, &BTreeSet::new())?;
+        Ok(bridge::InfoReturns {
+            counterparty: self.get_counterparty(chain)?,
+            anchored_height: self.last_anchored_height(chain)?,
+            locked: locked
+                .into_iter()
+                .filter(|(_, amount)| !amount.is_zero())
+                .collect(),
+        })
+    }
+
+    /// Add or update a counterparty. Only the token identity can, as it
+    /// controls the wrapped tokens.
+    pub fn register_counterparty(
+        &mut self,
+        sender: &Address,
+        args: bridge::RegisterArgs,
+    ) -> Result<(), ManyError> {
+        let bridge::RegisterArgs {
+            chain,
+            relayers,
+            wrapped,
+        } = args;
+        verify_tokens_sender(sender, self.get_identity(TOKEN_IDENTITY_ROOT)?)?;
+
+        if chain == self.get_identity(IDENTITY_ROOT)? {
+            return Err(bridge::unknown_counterparty(chain));
+        }
+        let symbols = self.get_symbols()?;
+        for symbol in wrapped.values() {
+            if !symbols.contains(symbol) {
+                return Err(error::unknown_symbol(symbol.to_string()));
+            }
+        }
+
+        let counterparty = Counterparty { relayers, wrapped };
+        self.apply(&[Self::counterparty_entry(&chain, &counterparty)?])?;
+        self.maybe_commit()
+    }
+
+    fn put_transfer(
+        &mut self,
+        kind: TransferKind,
+        sender: &Address,
+        destination: Address,
+        recipient: Address,
+        native_symbol: Symbol,
+        amount: TokenAmount,
+        mut batch: Vec<BatchEntry>,
+    ) -> Result<ByteVec, ManyError> {
+        let id: ByteVec = self.new_event_id().into();
+        let transfer = BridgeTransfer {
+            kind,
+            source: self.get_identity(IDENTITY_ROOT)?,
+            destination,
+            sender: *sender,
+            recipient,
+            native_symbol,
+            amount,
+            created: self.now(),
+        };
+        batch.push((
+            key_for_transfer(&id),
+            Op::Put(minicbor::to_vec(&transfer).map_err(ManyError::serialization_error)?),
+        ));
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+        Ok(id)
+    }
+
+    /// Lock native tokens of `sender` for a counterparty.
+    pub fn bridge_lock(
+        &mut self,
+        sender: &Address,
+        args: bridge::LockArgs,
+    ) -> Result<ByteVec, ManyError> {
+        let bridge::LockArgs {
+            destination,
+            recipient,
+            symbol,
+            amount,
+            memo,
+        } = args;
+        let counterparty = self.get_counterparty(&destination)?;
+
+        if !self.get_symbols()?.contains(&symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+        if counterparty.native_symbol(&symbol).is_some() {
+            return Err(bridge::unsupported_symbol(
+                symbol,
+                "it is wrapped from the destination, burn it instead",
+            ));
+        }
+        if recipient.is_anonymous() {
+            return Err(error::anonymous_cannot_hold_funds());
+        }
+
+        let (reservation, _) =
+            self.prepare_transfer(sender, &destination, &symbol, amount.clone(), memo.clone())?;
+        self.commit_transfer(&reservation)?;
+
+        let id = self.put_transfer(
+            TransferKind::Lock,
+            sender,
+            destination,
+            recipient,
+            symbol,
+            amount.clone(),
+            vec![],
+        )?;
+
+        self.log_event(events::EventInfo::BridgeLock {
+            id: id.clone(),
+            sender: *sender,
+            destination,
+            recipient,
+            symbol,
+            amount,
+            memo,
+        })?;
+        self.maybe_commit()?;
+        Ok(id)
+    }
+
+    /// Burn wrapped tokens of `sender`, to release their native tokens on the
+    /// counterparty.
+    pub fn bridge_burn(
+        &mut self,
+        sender: &Address,
+        args: bridge::BurnArgs,
+    ) -> Result<ByteVec, ManyError> {
+        let bridge::BurnArgs {
+            destination,
+            recipient,
+            symbol,
+            amount,
+            memo,
+        } = args;
+        let counterparty = self.get_counterparty(&destination)?;
+        let native_symbol = counterparty.native_symbol(&symbol).ok_or_else(|| {
+            bridge::unsupported_symbol(symbol, "it is not wrapped from the destination")
+        })?;
+        if recipient.is_anonymous() {
+            return Err(error::anonymous_cannot_hold_funds());
+        }
+
+        self.burn_token(
+            symbol,
+            &LedgerTokensAddressMap::from([(*sender, amount.clone())]),
+        )?;
+        let id = self.put_transfer(
+            TransferKind::Burn,
+            sender,
+            destination,
+            recipient,
+            native_symbol,
+            amount.clone(),
+            vec![],
+        )?;
+
+        self.log_event(events::EventInfo::BridgeBurn {
+            id: id.clone(),
+            sender: *sender,
+            destination,
+            recipient,
+            symbol,
+            amount,
+            memo,
+        })?;
+        self.maybe_commit()?;
+        Ok(id)
+    }
+
+    /// A transfer out of this ledger, with the proof of its record against
+    /// the current state hash.
+    pub fn bridge_transfer(&self, id: &[u8]) -> Result<bridge::TransferInfo, ManyError> {
+        let key = key_for_transfer(id);
+        let transfer = self
+            .persistent_store
+            .get(&key)
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| bridge::transfer_not_found(hex::encode(id)))?;
+        let proof = self
+            .persistent_store
+            .prove(vec![QueryItem::Key(key)].into())
+            .map_err(error::storage_get_failed)?;
+
+        Ok(bridge::TransferInfo {
+            transfer: minicbor::decode(&transfer).map_err(ManyError::deserialization_error)?,
+            height: self.get_height()?,
+            hash: self.persistent_store.root_hash().to_vec().into(),
+            proof: proof.into(),
+        })
+    }
+
+    /// Anchor a state hash of a counterparty. An anchor cannot be changed
+    /// once set.
+    pub fn bridge_anchor(
+        &mut self,
+        sender: &Address,
+        args: bridge::AnchorArgs,
+    ) -> Result<(), ManyError> {
+        let bridge::AnchorArgs {
+            chain,
+            height,
+            hash,
+        } = args;
+        let counterparty = self.get_counterparty(&chain)?;
+        if !counterparty.relayers.contains(sender) {
+            return Err(bridge::not_a_relayer(sender));
+        }
+
+        match self.get_anchor(&chain, height)? {
+            Some(anchored) if anchored == hash.as_slice() => Ok(()),
+            Some(_) => Err(bridge::conflicting_anchor(chain, height)),
+            None => {
+                self.apply(&[(key_for_anchor(&chain, height), Op::Put(hash.to_vec()))])?;
+                self.maybe_commit()
+            }
+        }
+    }
+
+    /// Verify the proof of a transfer to this ledger, and credit its
+    /// recipient: wrapped tokens are minted for a lock, and native tokens are
+    /// released for a burn. Anyone can claim a transfer, but only once.
+    pub fn bridge_claim(&mut self, args: bridge::ClaimArgs) -> Result<(), ManyError> {
+        let bridge::ClaimArgs {
+            source,
+            height,
+            id,
+            proof,
+        } = args;
+        let counterparty = self.get_counterparty(&source)?;
+        let anchored = self
+            .get_anchor(&source, height)?
+            .ok_or_else(|| bridge::unknown_anchor(source, height))?;
+
+        let (root_hash, entries) =
+            execute_proof(&proof).map_err(|e| bridge::invalid_bridge_proof(e.to_string()))?;
+        if root_hash.as_slice() != anchored.as_slice() {
+            return Err(bridge::invalid_bridge_proof(
+                "it does not match the anchored state hash",
+            ));
+        }
+        let transfer: BridgeTransfer = entries
+            .get(&key_for_transfer(&id))
+            .ok_or_else(|| bridge::invalid_bridge_proof("it does not contain the transfer"))
+            .and_then(|bytes| minicbor::decode(bytes).map_err(ManyError::deserialization_error))?;
+        if transfer.source != source || transfer.destination != self.get_identity(IDENTITY_ROOT)? {
+            return Err(bridge::invalid_bridge_proof(
+                "the transfer is not from the source to this ledger",
+            ));
+        }
+
+        let claim_key = key_for_claim(&source, &id);
+        if self
+            .persistent_store
+            .get(&claim_key)
+            .map_err(error::storage_get_failed)?
+            .is_some()
+        {
+            return Err(bridge::transfer_already_claimed(hex::encode(id.as_slice())));
+        }
+
+        let BridgeTransfer {
+            kind,
+            recipient,
+            native_symbol,
+            amount,
+            ..
+        } = transfer;
+        let symbol = match kind {
+            TransferKind::Lock => {
+                let wrapped = *counterparty.wrapped.get(&native_symbol).ok_or_else(|| {
+                    bridge::unsupported_symbol(native_symbol, "it has no wrapped symbol")
+                })?;
+                self.mint_token(
+                    wrapped,
+                    &LedgerTokensAddressMap::from([(recipient, amount.clone())]),
+                )?;
+                self.apply(&[(claim_key, Op::Put(vec![]))])?;
+                wrapped
+            }
+            TransferKind::Burn => {
+                let (reservation, _) = self.prepare_transfer(
+                    &source,
+                    &recipient,
+                    &native_symbol,
+                    amount.clone(),
+                    None,
+                )?;
+                self.commit_transfer(&reservation)?;
+                self.apply(&[(claim_key, Op::Put(vec![]))])?;
+                native_symbol
+            }
+        };
+
+        self.log_event(events::EventInfo::BridgeClaim {
+            source,
+            id,
+            recipient,
+            symbol,
+            amount,
+        })?;
+        self.maybe_commit()
+    }
+}
@@ -0,0 +1,69 @@
+use crate::error;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_modules::bundle;
+use many_modules::events::EventId;
+use merk::{BatchEntry, Op};
+use std::collections::BTreeMap;
+
+/// The state needed to revert the changes made by a bundle.
+#[derive(Debug)]
+pub(crate) struct BundleJournal {
+    latest_tid: EventId,
+
+    /// The value of each key before its first change in the bundle, or `None`
+    /// if the key did not exist.
+    pub(super) previous: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl LedgerStorage {
+    pub fn begin_bundle(&mut self) -> Result<(), ManyError> {
+        if self.journal.is_some() {
+            return Err(bundle::nested_bundle());
+        }
+
+        self.journal = Some(BundleJournal {
+            latest_tid: self.latest_tid.clone(),
+            previous: BTreeMap::new(),
+        });
+        Ok(())
+    }
+
+    pub fn commit_bundle(&mut self) -> Result<(), ManyError> {
+        self.journal = None;
+        Ok(())
+    }
+
+    /// Restore all keys modified since `begin_bundle`, including the events
+    /// that were logged.
+    pub fn rollback_bundle(&mut self) -> Result<(), ManyError> {
+        let Some(journal) = self.journal.take() else {
+            return Ok(());
+        };
+
+        let mut batch: Vec<BatchEntry> = Vec::with_capacity(journal.previous.len());
+        for (key, value) in journal.previous {
+            match value {
+                Some(value) => batch.push((key, Op::Put(value))),
+                None => {
+                    // Keys created then deleted within the bundle are already gone.
+                    if self
+                        .persistent_store
+                        .get(&key)
+                        .map_err(error::storage_get_failed)?
+                        .is_some()
+                    {
+                        batch.push((key, Op::Delete));
+                    }
+                }
+            }
+        }
+
+        // Keys of a BTreeMap are sorted, as required by the batch.
+        if !batch.is_empty() {
+            self.apply(&batch)?;
+        }
+        self.latest_tid = journal.latest_tid;
+        self.maybe_commit()
+    }
+}
@@ -0,0 +1,104 @@
+use crate::error;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+/// Configuration of the Merk checkpoints kept by the ledger. A checkpoint of
+/// the store is taken after every block, so proofs can be generated against
+/// past states.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CheckpointConfig {
+    /// Directory containing the checkpoints, one sub-directory per height.
+    pub path: PathBuf,
+
+    /// Number of most recent checkpoints to keep. All checkpoints are kept
+    /// if this is `None`.
+    pub retention: Option<u64>,
+}
+
+impl CheckpointConfig {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            retention: None,
+        }
+    }
+
+    pub fn with_retention(mut self, retention: u64) -> Self {
+        self.retention = Some(retention);
+        self
+    }
+
+    pub(crate) fn path_for_height(&self, height: u64) -> PathBuf {
+        self.path.join(height.to_string())
+    }
+}
+
+impl LedgerStorage {
+    pub fn set_checkpoint_config(&mut self, config: Option<CheckpointConfig>) {
+        self.checkpoints = config;
+    }
+
+    /// Returns the heights of all the checkpoints available on disk.
+    pub fn checkpoint_heights(&self) -> Result<BTreeSet<u64>, ManyError> {
+        let Some(config) = &self.checkpoints else {
+            return Ok(BTreeSet::new());
+        };
+        if !config.path.exists() {
+            return Ok(BTreeSet::new());
+        }
+
+        Ok(std::fs::read_dir(&config.path)
+            .map_err(error::storage_checkpoint_failed)?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse::<u64>().ok())
+            .collect())
+    }
+
+    /// Create a checkpoint of the committed store at `height`, then prune
+    /// checkpoints outside of the retention window.
+    pub(crate) fn create_checkpoint(&mut self, height: u64) -> Result<(), ManyError> {
+        let Some(config) = &self.checkpoints else {
+            return Ok(());
+        };
+
+        let path = config.path_for_height(height);
+        if !path.exists() {
+            debug!("Creating checkpoint at {}", path.display());
+            // Opening the checkpoint is not needed, so it is dropped right away.
+            let _ = self
+                .persistent_store
+                .checkpoint(&path)
+                .map_err(error::storage_checkpoint_failed)?;
+        }
+
+        // Checkpoints are local to the node, the retention does not change
+        // the state and applies as soon as it is configured.
+        self.prune_checkpoints(height).map(|_| ())
+    }
+
+    /// Delete the checkpoints that are older than the retention window at
+    /// `height`. Returns the number of checkpoints deleted.
+    pub fn prune_checkpoints(&self, height: u64) -> Result<usize, ManyError> {
+        let Some(config) = &self.checkpoints else {
+            return Ok(0);
+        };
+        let Some(retention) = config.retention else {
+            return Ok(0);
+        };
+
+        let oldest = height.saturating_sub(retention.saturating_sub(1));
+        let mut count = 0;
+        for h in self.checkpoint_heights()?.range(..oldest) {
+            std::fs::remove_dir_all(config.path_for_height(*h))
+                .map_err(error::storage_checkpoint_failed)?;
+            count += 1;
+        }
+
+        if count > 0 {
+            info!("Pruned {count} checkpoint(s) older than height {oldest}");
+        }
+        Ok(count)
+    }
+}
@@ -1,15 +1,53 @@
 use crate::error;
 use crate::migration::data::{ACCOUNT_TOTAL_COUNT_INDEX, NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX};
-use crate::storage::{key_for_account_balance, LedgerStorage};
+use crate::migration::data_metrics::DATA_METRICS_MIGRATION;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::{key_for_account_balance, LedgerStorage, BALANCES_ROOT, IDENTITY_ROOT};
 use many_error::ManyError;
 use many_identity::Address;
-use many_modules::data::{DataIndex, DataInfo, DataValue};
-use many_types::ledger::TokenAmount;
-use merk::Op;
-use std::collections::BTreeMap;
+use many_modules::data::{
+    DataAggregation, DataIndex, DataInfo, DataMetric, DataSource, DataType, DataValue,
+    DataValueTypeGauge,
+};
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::SortOrder;
+use merk::{BatchEntry, Op};
+use num_bigint::{BigInt, BigUint};
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 
 pub const DATA_ATTRIBUTES_KEY: &[u8] = b"/data/attributes";
 pub const DATA_INFO_KEY: &[u8] = b"/data/info";
+pub const DATA_METRICS_KEY: &[u8] = b"/data/metrics";
+
+pub(crate) fn display_index(index: &DataIndex) -> String {
+    index
+        .flattened()
+        .iter()
+        .map(u32::to_string)
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+fn validate_metric(metric: &DataMetric, symbols: &BTreeSet<Symbol>) -> Result<(), ManyError> {
+    match &metric.source {
+        DataSource::Balances(symbol) if !symbols.contains(symbol) => {
+            Err(error::unknown_symbol(symbol.to_string()))
+        }
+        DataSource::Events if metric.aggregation != DataAggregation::Count => {
+            Err(error::invalid_data_metric("events can only be counted"))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Counters saturate at `u64::MAX`.
+fn data_value(r#type: &DataType, value: BigUint) -> DataValue {
+    match r#type {
+        DataType::Counter => DataValue::Counter(u64::try_from(&value).unwrap_or(u64::MAX)),
+        DataType::Gauge => DataValue::Gauge(DataValueTypeGauge::BigInt(BigInt::from(value))),
+    }
+}
 
 impl LedgerStorage {
     pub(crate) fn data_info(&self) -> Result<Option<BTreeMap<DataIndex, DataInfo>>, ManyError> {
@@ -36,6 +74,7 @@ impl LedgerStorage {
         to: &Address,
         amount: TokenAmount,
         symbol: &Address,
+        remove_origin: bool,
     ) -> Result<(), ManyError> {
         if let Some(mut attributes) = self.data_attributes()? {
             let destination_key = key_for_account_balance(to, symbol);
@@ -79,13 +118,156 @@ impl LedgerStorage {
                         }
                     });
             }
-            self.persistent_store
-                .apply(&[(
-                    DATA_ATTRIBUTES_KEY.to_vec(),
-                    Op::Put(minicbor::to_vec(attributes).unwrap()),
-                )])
-                .map_err(error::storage_apply_failed)?
+            // An empty origin account removed from the storage no longer
+            // counts as an account
+            if remove_origin {
+                attributes.entry(ACCOUNT_TOTAL_COUNT_INDEX).and_modify(|x| {
+                    if let DataValue::Counter(count) = x {
+                        *count -= 1;
+                    }
+                });
+            }
+            self.apply(&[(
+                DATA_ATTRIBUTES_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(attributes).unwrap()),
+            )])?
         }
         Ok(())
     }
+
+    pub fn is_data_metrics_active(&self) -> bool {
+        self.migrations.is_active(&DATA_METRICS_MIGRATION)
+    }
+
+    pub(crate) fn data_metrics(&self) -> Result<BTreeMap<DataIndex, DataMetric>, ManyError> {
+        self.persistent_store
+            .get(DATA_METRICS_KEY)
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(BTreeMap::new()), |bytes| {
+                minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+            })
+    }
+
+    /// The current value of a metric, aggregated over the whole storage.
+    pub(crate) fn compute_data_metric(&self, metric: &DataMetric) -> Result<DataValue, ManyError> {
+        let symbol = match &metric.source {
+            DataSource::Balances(symbol) => symbol,
+            DataSource::Events => {
+                return Ok(data_value(&metric.r#type, self.nb_events()?.into()));
+            }
+        };
+
+        let mut value = BigUint::default();
+        for item in LedgerIterator::all_balances(&self.persistent_store, SortOrder::Ascending) {
+            let (key, amount) = item.map_err(error::storage_get_failed)?;
+            let key_symbol = std::str::from_utf8(&key[BALANCES_ROOT.len()..])
+                .ok()
+                .and_then(|rest| rest.rsplit_once('/'))
+                .and_then(|(_, symbol)| Address::from_str(symbol).ok());
+            if key_symbol.as_ref() != Some(symbol) {
+                continue;
+            }
+
+            let amount = TokenAmount::from(amount);
+            match metric.aggregation {
+                DataAggregation::Count => value += 1u32,
+                DataAggregation::NonZeroCount if !amount.is_zero() => value += 1u32,
+                DataAggregation::NonZeroCount => {}
+                DataAggregation::Sum => value += BigUint::from(amount),
+                DataAggregation::Max => value = value.max(amount.into()),
+            }
+        }
+        Ok(data_value(&metric.r#type, value))
+    }
+
+    /// Write the data attributes and info along with the registered metrics.
+    fn apply_data_metrics(
+        &mut self,
+        attributes: BTreeMap<DataIndex, DataValue>,
+        info: BTreeMap<DataIndex, DataInfo>,
+        metrics: BTreeMap<DataIndex, DataMetric>,
+    ) -> Result<(), ManyError> {
+        let batch: Vec<BatchEntry> = vec![
+            (
+                DATA_ATTRIBUTES_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(attributes).map_err(ManyError::serialization_error)?),
+            ),
+            (
+                DATA_INFO_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(info).map_err(ManyError::serialization_error)?),
+            ),
+            (
+                DATA_METRICS_KEY.to_vec(),
+                Op::Put(minicbor::to_vec(metrics).map_err(ManyError::serialization_error)?),
+            ),
+        ];
+        self.apply(&batch)
+    }
+
+    /// Register a metric, computing its first value. Only the identity of the
+    /// ledger can register metrics.
+    pub fn register_data_metric(
+        &mut self,
+        sender: &Address,
+        index: DataIndex,
+        metric: DataMetric,
+    ) -> Result<(), ManyError> {
+        if *sender != self.get_identity(IDENTITY_ROOT)? {
+            return Err(error::unauthorized());
+        }
+        validate_metric(&metric, &self.get_symbols()?)?;
+
+        let mut attributes = self.data_attributes()?.unwrap_or_default();
+        let mut info = self.data_info()?.unwrap_or_default();
+        if attributes.contains_key(&index) || info.contains_key(&index) {
+            return Err(error::data_index_exists(display_index(&index)));
+        }
+
+        let mut metrics = self.data_metrics()?;
+        attributes.insert(index, self.compute_data_metric(&metric)?);
+        info.insert(index, metric.info());
+        metrics.insert(index, metric);
+        self.apply_data_metrics(attributes, info, metrics)?;
+        self.maybe_commit()
+    }
+
+    /// Remove a registered metric, along with its value. Built-in data
+    /// attributes cannot be removed.
+    pub fn unregister_data_metric(
+        &mut self,
+        sender: &Address,
+        index: &DataIndex,
+    ) -> Result<(), ManyError> {
+        if *sender != self.get_identity(IDENTITY_ROOT)? {
+            return Err(error::unauthorized());
+        }
+
+        let mut metrics = self.data_metrics()?;
+        if metrics.remove(index).is_none() {
+            return Err(error::data_metric_not_found(display_index(index)));
+        }
+        let mut attributes = self.data_attributes()?.unwrap_or_default();
+        let mut info = self.data_info()?.unwrap_or_default();
+        attributes.remove(index);
+        info.remove(index);
+        self.apply_data_metrics(attributes, info, metrics)?;
+        self.maybe_commit()
+    }
+
+    /// Compute the registered metrics again, at the end of a block.
+    pub(crate) fn update_data_metrics(&mut self) -> Result<(), ManyError> {
+        let metrics = self.data_metrics()?;
+        if metrics.is_empty() {
+            return Ok(());
+        }
+
+        let mut attributes = self.data_attributes()?.unwrap_or_default();
+        for (index, metric) in &metrics {
+            attributes.insert(*index, self.compute_data_metric(metric)?);
+        }
+        self.apply(&[(
+            DATA_ATTRIBUTES_KEY.to_vec(),
+            Op::Put(minicbor::to_vec(attributes).map_err(ManyError::serialization_error)?),
+        )])
+    }
 }
@@ -0,0 +1,161 @@
+use crate::error;
+use crate::migration::account_delegation::ACCOUNT_DELEGATION_MIGRATION;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::features::multisig::{
+    errors, DelegateArgs, Delegation, RevokeDelegationArgs,
+};
+use many_modules::account::{Account, Role};
+use many_modules::events::EventInfo;
+use merk::Op;
+
+pub(crate) const DELEGATIONS_ROOT: &str = "/delegations/";
+
+fn key_for_account_delegations(account: &Address) -> Vec<u8> {
+    format!("{DELEGATIONS_ROOT}{account}/").into_bytes()
+}
+
+fn key_for_delegation(account: &Address, delegator: &Address) -> Vec<u8> {
+    format!("{DELEGATIONS_ROOT}{account}/{delegator}").into_bytes()
+}
+
+/// Whether an address can approve the multisig transactions of an account.
+pub(crate) fn is_approver(account: &Account, id: &Address) -> bool {
+    account.has_role(id, Role::CanMultisigApprove)
+        || account.has_role(id, Role::CanMultisigSubmit)
+        || account.has_role(id, Role::Owner)
+}
+
+/// Approvers of accounts can delegate their approval power for a time
+/// window, once the Account Delegation Migration is active. There is one
+/// delegation per delegator and account, at
+/// `/delegations/<account>/<delegator>`.
+impl LedgerStorage {
+    pub fn is_account_delegation_active(&self) -> bool {
+        self.migrations.is_active(&ACCOUNT_DELEGATION_MIGRATION)
+    }
+
+    fn get_delegation(
+        &self,
+        account: &Address,
+        delegator: &Address,
+    ) -> Result<Option<Delegation>, ManyError> {
+        self.persistent_store
+            .get(&key_for_delegation(account, delegator))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    pub fn delegate(&mut self, sender: &Address, args: DelegateArgs) -> Result<(), ManyError> {
+        let DelegateArgs {
+            account: account_id,
+            delegate,
+            start,
+            end,
+            memo,
+        } = args;
+        let (account, _) = self.get_account(&account_id)?;
+        if !is_approver(&account, sender) {
+            return Err(errors::user_cannot_approve_transaction());
+        }
+        if delegate == *sender {
+            return Err(errors::cannot_delegate_to_self());
+        }
+        if delegate.is_anonymous() {
+            return Err(ManyError::invalid_identity());
+        }
+        if end <= self.now() || matches!(start, Some(start) if start >= end) {
+            return Err(errors::invalid_delegation_window());
+        }
+
+        let delegation = Delegation {
+            delegator: *sender,
+            delegate,
+            start,
+            end,
+        };
+        self.apply(&[(
+            key_for_delegation(&account_id, sender),
+            Op::Put(minicbor::to_vec(&delegation).map_err(ManyError::serialization_error)?),
+        )])?;
+
+        self.log_event(EventInfo::AccountDelegate {
+            account: account_id,
+            delegator: *sender,
+            delegate,
+            start,
+            end,
+            memo,
+        })?;
+        self.maybe_commit()
+    }
+
+    pub fn revoke_delegation(
+        &mut self,
+        sender: &Address,
+        args: RevokeDelegationArgs,
+    ) -> Result<(), ManyError> {
+        let RevokeDelegationArgs { account, memo } = args;
+        let delegation = self
+            .get_delegation(&account, sender)?
+            .ok_or_else(errors::delegation_cannot_be_found)?;
+        self.apply(&[(key_for_delegation(&account, sender), Op::Delete)])?;
+
+        self.log_event(EventInfo::AccountRevokeDelegation {
+            account,
+            delegator: *sender,
+            delegate: delegation.delegate,
+            memo,
+        })?;
+        self.maybe_commit()
+    }
+
+    /// The delegations of an account that did not end, sorted by delegator.
+    pub fn delegations(&self, account: &Address) -> Result<Vec<Delegation>, ManyError> {
+        let now = self.now();
+        let mut delegations = Vec::new();
+        for item in LedgerIterator::all_with_prefix(
+            &self.persistent_store,
+            key_for_account_delegations(account),
+        ) {
+            let (_, value) = item.map_err(error::storage_get_failed)?;
+            let delegation: Delegation =
+                minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+
+            // Iterators only see committed keys, so get the delegation again
+            // in case it was revoked or replaced in this block.
+            if let Some(delegation) = self.get_delegation(account, &delegation.delegator)? {
+                if now < delegation.end {
+                    delegations.push(delegation);
+                }
+            }
+        }
+        Ok(delegations)
+    }
+
+    /// The approvers of an account whose approval power is delegated to
+    /// `delegate` right now. Delegations of addresses that lost their roles
+    /// are void.
+    pub(crate) fn active_delegators(
+        &self,
+        account_id: &Address,
+        account: &Account,
+        delegate: &Address,
+    ) -> Result<Vec<Address>, ManyError> {
+        if !self.is_account_delegation_active() {
+            return Ok(vec![]);
+        }
+        let now = self.now();
+        Ok(self
+            .delegations(account_id)?
+            .into_iter()
+            .filter(|d| {
+                &d.delegate == delegate && d.is_active(now) && is_approver(account, &d.delegator)
+            })
+            .map(|d| d.delegator)
+            .collect())
+    }
+}
@@ -0,0 +1,80 @@
+use crate::error;
+use crate::storage::{key_for_account_balance, LedgerStorage};
+use many_error::ManyError;
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use merk::{BatchEntry, Op};
+
+pub use many_modules::ledger::{DustConfig, DustPolicy};
+
+pub fn key_for_dust_config(symbol: &Symbol) -> Vec<u8> {
+    format!("/config/dust/{symbol}").into_bytes()
+}
+
+/// The amount to transfer out of `balance` for a transfer of `amount`, following
+/// the policy of the dust config.
+pub(crate) fn adjust(
+    dust: &DustConfig,
+    balance: &TokenAmount,
+    amount: TokenAmount,
+) -> Result<TokenAmount, ManyError> {
+    let remainder = balance.clone() - amount.clone();
+    if remainder.is_zero() || remainder >= dust.threshold {
+        Ok(amount)
+    } else {
+        match dust.policy {
+            DustPolicy::Reject => Err(error::balance_below_dust_threshold(
+                remainder,
+                &dust.threshold,
+            )),
+            DustPolicy::Sweep => Ok(amount + remainder),
+        }
+    }
+}
+
+impl LedgerStorage {
+    pub fn dust_config(&self, symbol: &Symbol) -> Result<Option<DustConfig>, ManyError> {
+        self.persistent_store
+            .get(&key_for_dust_config(symbol))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// The batch entry setting the dust config of a symbol, if any. A zero
+    /// threshold removes it.
+    pub(crate) fn dust_config_entry(
+        &self,
+        symbol: &Symbol,
+        dust: &DustConfig,
+    ) -> Result<Option<BatchEntry>, ManyError> {
+        let key = key_for_dust_config(symbol);
+        if !dust.threshold.is_zero() {
+            let bytes = minicbor::to_vec(dust).map_err(ManyError::serialization_error)?;
+            Ok(Some((key, Op::Put(bytes))))
+        } else if self.dust_config(symbol)?.is_some() {
+            Ok(Some((key, Op::Delete)))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// The batch entry storing a new balance of an account. Every command
+    /// writes balances through it, so balances of a symbol with a dust config
+    /// are either removed when empty or at least the threshold.
+    pub(crate) fn balance_entry(
+        &self,
+        address: &Address,
+        symbol: &Symbol,
+        balance: &TokenAmount,
+    ) -> Result<BatchEntry, ManyError> {
+        let key = key_for_account_balance(address, symbol);
+        match self.dust_config(symbol)? {
+            Some(_) if balance.is_zero() => Ok((key, Op::Delete)),
+            Some(dust) if balance < &dust.threshold => {
+                Err(error::amount_below_dust_threshold(balance, &dust.threshold))
+            }
+            _ => Ok((key, Op::Put(balance.to_vec()))),
+        }
+    }
+}
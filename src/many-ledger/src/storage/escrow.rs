@@ -0,0 +1,111 @@
+use crate::error;
+use crate::storage::{key_for_token, LedgerStorage};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{escrow, events};
+use merk::Op;
+use minicbor::bytes::ByteVec;
+
+pub(crate) const ESCROWS_ROOT: &[u8] = b"/escrows/";
+
+pub(super) fn key_for_escrow(id: &[u8]) -> Vec<u8> {
+    key_for_token(ESCROWS_ROOT, id)
+}
+
+/// Escrows hold their funds in a reservation of the same ID, which is
+/// committed to the recipient on release and aborted on refund.
+impl LedgerStorage {
+    pub fn get_escrow(&self, id: &[u8]) -> Result<escrow::EscrowInfo, ManyError> {
+        let bytes = self
+            .persistent_store
+            .get(&key_for_escrow(id))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| escrow::escrow_not_found(hex::encode(id)))?;
+
+        minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+    }
+
+    pub fn create_escrow(
+        &mut self,
+        sender: &Address,
+        args: escrow::CreateArgs,
+    ) -> Result<ByteVec, ManyError> {
+        let escrow::CreateArgs {
+            recipient,
+            symbol,
+            amount,
+            condition,
+            refund_after,
+            memo,
+        } = args;
+
+        condition.validate(sender, &recipient)?;
+        if !self.get_symbols()?.contains(&symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+
+        let (id, _) =
+            self.prepare_transfer(sender, &recipient, &symbol, amount.clone(), memo.clone())?;
+        let info = escrow::EscrowInfo {
+            depositor: *sender,
+            recipient,
+            symbol,
+            amount: amount.clone(),
+            condition: condition.clone(),
+            refund_after,
+            created: self.now(),
+        };
+        self.apply(&[(
+            key_for_escrow(&id),
+            Op::Put(minicbor::to_vec(&info).map_err(ManyError::serialization_error)?),
+        )])?;
+
+        self.log_event(events::EventInfo::EscrowCreate {
+            id: id.clone(),
+            depositor: *sender,
+            recipient,
+            symbol,
+            amount,
+            condition,
+            refund_after,
+            memo,
+        })?;
+        self.maybe_commit()?;
+        Ok(id)
+    }
+
+    pub fn release_escrow(
+        &mut self,
+        sender: &Address,
+        id: ByteVec,
+        preimage: Option<ByteVec>,
+    ) -> Result<(), ManyError> {
+        let info = self.get_escrow(&id)?;
+        info.can_release(sender, self.now(), preimage.as_ref().map(|p| p.as_slice()))?;
+
+        self.commit_transfer(&id)?;
+        self.apply(&[(key_for_escrow(&id), Op::Delete)])?;
+
+        self.log_event(events::EventInfo::EscrowRelease {
+            id,
+            recipient: info.recipient,
+            releaser: *sender,
+        })?;
+        self.maybe_commit()
+    }
+
+    pub fn refund_escrow(&mut self, sender: &Address, id: ByteVec) -> Result<(), ManyError> {
+        let info = self.get_escrow(&id)?;
+        info.can_refund(sender, self.now())?;
+
+        self.abort_transfer(&id)?;
+        self.apply(&[(key_for_escrow(&id), Op::Delete)])?;
+
+        self.log_event(events::EventInfo::EscrowRefund {
+            id,
+            depositor: info.depositor,
+            refunder: *sender,
+        })?;
+        self.maybe_commit()
+    }
+}
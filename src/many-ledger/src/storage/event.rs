@@ -1,14 +1,20 @@
 use crate::error;
+use crate::migration::acknowledgment::ACKNOWLEDGMENT_MIGRATION;
+use crate::migration::event_chain::EVENT_CHAIN_MIGRATION;
+use crate::migration::event_index::EVENT_INDEX_MIGRATION;
+use crate::storage::event_index::index_keys_for_event;
 use crate::storage::iterator::LedgerIterator;
 use crate::storage::LedgerStorage;
 use many_error::ManyError;
 use many_modules::events;
 use many_modules::events::EventId;
+use many_modules::Acknowledgment;
 use many_types::{CborRange, SortOrder};
 use merk::Op;
 
 pub(crate) const EVENTS_ROOT: &[u8] = b"/events/";
 pub(crate) const EVENT_COUNT_ROOT: &[u8] = b"/events_count";
+pub(crate) const EVENT_CHAIN_HEAD_ROOT: &[u8] = b"/events_chain_head";
 
 // Left-shift the height by this amount of bits
 pub(crate) const HEIGHT_EVENTID_SHIFT: u64 = 32;
@@ -20,6 +26,12 @@ pub(crate) const EVENT_ID_KEY_SIZE_IN_BYTES: usize = 32;
 
 /// Returns the storage key for an event in the kv-store.
 pub(super) fn key_for_event(id: events::EventId) -> Vec<u8> {
+    key_for_event_id(EVENTS_ROOT, id)
+}
+
+/// Returns a key made of a root and a fixed-size event ID, so keys under the
+/// same root sort like the IDs.
+pub(super) fn key_for_event_id(root: &[u8], id: events::EventId) -> Vec<u8> {
     let id = id.as_ref();
     let id = if id.len() > EVENT_ID_KEY_SIZE_IN_BYTES {
         &id[0..EVENT_ID_KEY_SIZE_IN_BYTES]
@@ -29,7 +41,7 @@ pub(super) fn key_for_event(id: events::EventId) -> Vec<u8> {
 
     let mut exp_id = [0u8; EVENT_ID_KEY_SIZE_IN_BYTES];
     exp_id[(EVENT_ID_KEY_SIZE_IN_BYTES - id.len())..].copy_from_slice(id);
-    [EVENTS_ROOT.to_vec(), exp_id.to_vec()].concat()
+    [root, &exp_id].concat()
 }
 
 impl LedgerStorage {
@@ -49,30 +61,78 @@ impl LedgerStorage {
             })
     }
 
+    /// The running hash of the event log after the last chained event.
+    pub fn events_chain_head(&self) -> Result<Option<Vec<u8>>, ManyError> {
+        self.persistent_store
+            .get(EVENT_CHAIN_HEAD_ROOT)
+            .map_err(error::storage_get_failed)
+    }
+
     pub(crate) fn log_event(&mut self, content: events::EventInfo) -> Result<(), ManyError> {
         let current_nb_events = self.nb_events()?;
-        let event = events::EventLog {
+        let mut event = events::EventLog {
             id: self.new_event_id(),
             time: self.now(),
             content,
+            chain_hash: None,
         };
 
-        self.persistent_store
-            .apply(&[
-                (
-                    key_for_event(event.id.clone()),
-                    Op::Put(minicbor::to_vec(&event).map_err(ManyError::serialization_error)?),
-                ),
-                (
-                    EVENT_COUNT_ROOT.to_vec(),
-                    Op::Put((current_nb_events + 1).to_be_bytes().to_vec()),
-                ),
-            ])
-            .map_err(error::storage_apply_failed)?;
+        let mut batch = Vec::with_capacity(3);
+        if self.migrations.is_active(&EVENT_CHAIN_MIGRATION) {
+            let previous = self
+                .events_chain_head()?
+                .unwrap_or_else(|| events::EVENT_CHAIN_GENESIS.to_vec());
+            let chain_hash = event.chain(&previous)?;
+            batch.push((EVENT_CHAIN_HEAD_ROOT.to_vec(), Op::Put(chain_hash.to_vec())));
+            event.chain_hash = Some(chain_hash);
+        }
+
+        self.logged_events.push(event.id.clone());
+        batch.extend([
+            (
+                key_for_event(event.id.clone()),
+                Op::Put(minicbor::to_vec(&event).map_err(ManyError::serialization_error)?),
+            ),
+            (
+                EVENT_COUNT_ROOT.to_vec(),
+                Op::Put((current_nb_events + 1).to_be_bytes().to_vec()),
+            ),
+        ]);
+        if self.migrations.is_active(&EVENT_INDEX_MIGRATION) {
+            batch.extend(
+                index_keys_for_event(&event)
+                    .into_iter()
+                    .map(|key| (key, Op::Put(vec![]))),
+            );
+        }
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
 
         self.maybe_commit()
     }
 
+    pub(crate) fn clear_logged_events(&mut self) {
+        self.logged_events.clear();
+    }
+
+    /// Acknowledge the events logged since the last call to
+    /// `clear_logged_events`. The acknowledgment is empty until the
+    /// Acknowledgment Migration is active.
+    pub(crate) fn acknowledgment(&mut self) -> Result<Acknowledgment, ManyError> {
+        let events = std::mem::take(&mut self.logged_events);
+        if !self.migrations.is_active(&ACKNOWLEDGMENT_MIGRATION) {
+            return Ok(Acknowledgment::default());
+        }
+
+        let ack = Acknowledgment::new().with_events(events);
+        Ok(if self.blockchain {
+            // The block being executed is at `get_height() + 1`.
+            ack.with_height(self.get_height()? + 1)
+        } else {
+            ack
+        })
+    }
+
     pub fn iter_multisig(&self, order: SortOrder) -> LedgerIterator {
         LedgerIterator::all_multisig(&self.persistent_store, order)
     }
@@ -0,0 +1,161 @@
+use crate::error;
+use crate::migration::event_index::EVENT_INDEX_MIGRATION;
+use crate::storage::event::{key_for_event_id, EVENTS_ROOT};
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::{InnerStorage, LedgerStorage};
+use many_error::ManyError;
+use many_modules::events::{EventId, EventLog, EventTerm};
+use many_types::{CborRange, SortOrder};
+use merk::{BatchEntry, Op};
+use std::collections::BTreeSet;
+use std::iter::Peekable;
+
+pub(crate) const EVENT_INDEX_ROOT: &str = "/event_index/";
+
+/// The root of the index keys of a term. Index keys are this root followed
+/// by the event ID, and have no value.
+fn key_for_term(term: &EventTerm) -> Vec<u8> {
+    match term {
+        EventTerm::Kind(kind) => format!("{EVENT_INDEX_ROOT}kind/{kind}/"),
+        EventTerm::Address(address) => format!("{EVENT_INDEX_ROOT}address/{address}/"),
+        EventTerm::Symbol(symbol) => format!("{EVENT_INDEX_ROOT}symbol/{symbol}/"),
+    }
+    .into_bytes()
+}
+
+/// The keys indexing an event by each of its terms.
+pub(super) fn index_keys_for_event(event: &EventLog) -> Vec<Vec<u8>> {
+    event
+        .terms()
+        .iter()
+        .map(|term| key_for_event_id(&key_for_term(term), event.id.clone()))
+        .collect()
+}
+
+/// Index all the events of the store. The changes are applied but not
+/// committed.
+pub(crate) fn index_all_events(store: &mut InnerStorage) -> Result<(), ManyError> {
+    let mut batch: Vec<BatchEntry> = Vec::new();
+    for item in LedgerIterator::all_events(store) {
+        let (_, value) = item.map_err(error::storage_get_failed)?;
+        let event: EventLog = minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+        batch.extend(
+            index_keys_for_event(&event)
+                .into_iter()
+                .map(|key| (key, Op::Put(vec![]))),
+        );
+    }
+
+    batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    store.apply(&batch).map_err(error::storage_apply_failed)
+}
+
+type EventIdIterator<'a> = Box<dyn Iterator<Item = Result<Vec<u8>, ManyError>> + 'a>;
+
+/// The events with any of a set of terms, merged from the index of each
+/// term. Yields the encoded events, in order and without duplicates.
+pub struct IndexedEventIterator<'a> {
+    store: &'a InnerStorage,
+    descending: bool,
+    ids: Vec<Peekable<EventIdIterator<'a>>>,
+}
+
+impl<'a> IndexedEventIterator<'a> {
+    fn new(
+        store: &'a InnerStorage,
+        terms: &BTreeSet<EventTerm>,
+        range: CborRange<EventId>,
+        order: SortOrder,
+    ) -> Self {
+        let ids = terms
+            .iter()
+            .map(|term| {
+                let root = key_for_term(term);
+                let len = root.len();
+                let it: EventIdIterator<'a> = Box::new(
+                    LedgerIterator::scoped_by_event_id(store, &root, range.clone(), order.clone())
+                        .map(move |item| {
+                            item.map(|(key, _)| key[len..].to_vec())
+                                .map_err(error::storage_get_failed)
+                        }),
+                );
+                it.peekable()
+            })
+            .collect();
+
+        Self {
+            store,
+            descending: order == SortOrder::Descending,
+            ids,
+        }
+    }
+
+    /// The next event ID over all the terms.
+    fn next_id(&mut self) -> Option<Result<Vec<u8>, ManyError>> {
+        let mut next: Option<Vec<u8>> = None;
+        for it in self.ids.iter_mut() {
+            match it.peek() {
+                None => {}
+                Some(Err(_)) => return it.next(),
+                Some(Ok(id)) => {
+                    let before = next.as_ref().map_or(true, |next| {
+                        if self.descending {
+                            id > next
+                        } else {
+                            id < next
+                        }
+                    });
+                    if before {
+                        next = Some(id.clone());
+                    }
+                }
+            }
+        }
+
+        // Skip the ID in the index of every term the event has.
+        let next = next?;
+        for it in self.ids.iter_mut() {
+            if matches!(it.peek(), Some(Ok(id)) if id == &next) {
+                it.next();
+            }
+        }
+        Some(Ok(next))
+    }
+}
+
+impl<'a> Iterator for IndexedEventIterator<'a> {
+    type Item = Result<Vec<u8>, ManyError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let id = match self.next_id()? {
+                Ok(id) => id,
+                Err(e) => return Some(Err(e)),
+            };
+            match self.store.get(&[EVENTS_ROOT, &id].concat()) {
+                Ok(Some(event)) => return Some(Ok(event)),
+                // Pruning removes the index of the events, but don't fail
+                // on a stale entry.
+                Ok(None) => continue,
+                Err(e) => return Some(Err(error::storage_get_failed(e))),
+            }
+        }
+    }
+}
+
+impl LedgerStorage {
+    pub fn is_event_index_active(&self) -> bool {
+        self.migrations.is_active(&EVENT_INDEX_MIGRATION)
+    }
+
+    /// Iterate the events with any of the terms, using the event index. This
+    /// needs the Event Index Migration to be active.
+    pub fn iter_indexed_events(
+        &self,
+        terms: &BTreeSet<EventTerm>,
+        range: CborRange<EventId>,
+        order: SortOrder,
+    ) -> IndexedEventIterator {
+        IndexedEventIterator::new(&self.persistent_store, terms, range, order)
+    }
+}
@@ -1,7 +1,9 @@
 use crate::error;
+use crate::storage::iterator::LedgerIterator;
 use crate::storage::LedgerStorage;
 use base64::{engine::general_purpose, Engine as _};
 use many_error::ManyError;
+use many_identity::rotation::KeyRotation;
 use many_identity::Address;
 use many_modules::idstore;
 use merk::Op;
@@ -20,9 +22,52 @@ struct CredentialStorage {
     public_key: idstore::PublicKey,
 }
 
+/// The identity a rotated key belongs to.
+#[derive(Clone, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+struct RotatedKeyStorage {
+    #[n(0)]
+    identity: Address,
+
+    /// Whether the key was replaced by another key.
+    #[n(1)]
+    retired: bool,
+}
+
+/// A WebAuthn credential of an identity, other than the one it was stored
+/// with, or the revocation of the latter.
+#[derive(Clone, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+struct IdentityCredentialStorage {
+    #[n(0)]
+    cred_id: idstore::CredentialId,
+
+    #[n(1)]
+    public_key: idstore::PublicKey,
+
+    #[n(2)]
+    revoked: bool,
+}
+
+/// The identity a credential ID was added to.
+#[derive(Clone, minicbor::Encode, minicbor::Decode)]
+#[cbor(map)]
+struct CredentialOwnerStorage {
+    #[n(0)]
+    identity: Address,
+
+    /// The address of the public key of the credential.
+    #[n(1)]
+    key: Address,
+}
+
 enum IdStoreRootSeparator {
     RecallPhrase,
     Address,
+    KeyRotation,
+    RotatedKey,
+    Credential,
+    CredentialId,
 }
 
 impl IdStoreRootSeparator {
@@ -30,6 +75,10 @@ impl IdStoreRootSeparator {
         match *self {
             IdStoreRootSeparator::RecallPhrase => b"00",
             IdStoreRootSeparator::Address => b"01",
+            IdStoreRootSeparator::KeyRotation => b"02",
+            IdStoreRootSeparator::RotatedKey => b"03",
+            IdStoreRootSeparator::Credential => b"04",
+            IdStoreRootSeparator::CredentialId => b"05",
         }
     }
 }
@@ -56,18 +105,14 @@ impl LedgerStorage {
 
         // Apply keys and seed.
         if let Some(seed) = maybe_seed {
-            self.persistent_store
-                .apply(&[(
-                    IDSTORE_SEED_ROOT.to_vec(),
-                    Op::Put(seed.to_be_bytes().to_vec()),
-                )])
-                .map_err(error::storage_apply_failed)?;
+            self.apply(&[(
+                IDSTORE_SEED_ROOT.to_vec(),
+                Op::Put(seed.to_be_bytes().to_vec()),
+            )])?;
         }
         if let Some(keys) = maybe_keys {
             for (k, v) in keys {
-                self.persistent_store
-                    .apply(&[(k, Op::Put(v))])
-                    .map_err(error::storage_apply_failed)?;
+                self.apply(&[(k, Op::Put(v))])?;
             }
         }
 
@@ -85,12 +130,10 @@ impl LedgerStorage {
                 u64::from_be_bytes(bytes)
             });
 
-        self.persistent_store
-            .apply(&[(
-                IDSTORE_SEED_ROOT.to_vec(),
-                Op::Put((idstore_seed + 1).to_be_bytes().to_vec()),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            IDSTORE_SEED_ROOT.to_vec(),
+            Op::Put((idstore_seed + 1).to_be_bytes().to_vec()),
+        )])?;
 
         self.maybe_commit().map(|_| idstore_seed)
     }
@@ -140,9 +183,7 @@ impl LedgerStorage {
             ),
         ];
 
-        self.persistent_store
-            .apply(&batch)
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&batch)?;
 
         self.maybe_commit().map(|_| {
             vec![
@@ -206,6 +247,293 @@ impl LedgerStorage {
             Err(idstore::entry_not_found(address.to_string()))
         }
     }
+
+    /// The latest rotation of the key of an identity, if any.
+    pub fn get_key_rotation(&self, identity: &Address) -> Result<Option<KeyRotation>, ManyError> {
+        self.get_from_storage(&identity.to_vec(), IdStoreRootSeparator::KeyRotation)?
+            .0
+            .map(|value| minicbor::decode(&value).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    fn get_rotated_key(&self, key: &Address) -> Result<Option<RotatedKeyStorage>, ManyError> {
+        self.get_from_storage(&key.to_vec(), IdStoreRootSeparator::RotatedKey)?
+            .0
+            .map(|value| minicbor::decode(&value).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// The identity a key currently signs for, if it replaced the key of an
+    /// identity.
+    pub fn identity_of_key(&self, key: &Address) -> Result<Option<Address>, ManyError> {
+        Ok(self
+            .get_rotated_key(key)?
+            .filter(|k| !k.retired)
+            .map(|k| k.identity))
+    }
+
+    /// Whether a key was replaced by another key, either as the original key
+    /// of an identity or as a key from a previous rotation.
+    pub fn is_key_retired(&self, key: &Address) -> Result<bool, ManyError> {
+        Ok(self.get_rotated_key(key)?.map_or(false, |k| k.retired))
+    }
+
+    /// Record a rotation, which must already be verified and follow the
+    /// latest rotation of the identity.
+    pub fn rotate_key(&mut self, rotation: &KeyRotation) -> Result<(), ManyError> {
+        if self.get_rotated_key(&rotation.new_key)?.is_some() {
+            return Err(idstore::key_already_used(rotation.new_key));
+        }
+
+        let rotated_key = |key: &Address, retired: bool| {
+            minicbor::to_vec(RotatedKeyStorage {
+                identity: rotation.identity,
+                retired,
+            })
+            .map(|value| {
+                (
+                    [
+                        IDSTORE_ROOT,
+                        IdStoreRootSeparator::RotatedKey.value(),
+                        &key.to_vec(),
+                    ]
+                    .concat(),
+                    Op::Put(value),
+                )
+            })
+            .map_err(ManyError::serialization_error)
+        };
+
+        let mut batch = vec![
+            (
+                [
+                    IDSTORE_ROOT,
+                    IdStoreRootSeparator::KeyRotation.value(),
+                    &rotation.identity.to_vec(),
+                ]
+                .concat(),
+                Op::Put(minicbor::to_vec(rotation).map_err(ManyError::serialization_error)?),
+            ),
+            rotated_key(&rotation.previous_key, true)?,
+            rotated_key(&rotation.new_key, false)?,
+        ];
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        self.apply(&batch)?;
+        self.maybe_commit()
+    }
+}
+
+/// The root of the keys of the credentials of an identity.
+fn credentials_root(identity: &Address) -> Vec<u8> {
+    [
+        IDSTORE_ROOT,
+        IdStoreRootSeparator::Credential.value(),
+        &identity.to_vec(),
+    ]
+    .concat()
+}
+
+/// The key of a credential of an identity. Credentials are keyed by the
+/// address of their public key, which is what verifiers know of them.
+fn key_for_credential(identity: &Address, key: &Address) -> Vec<u8> {
+    [credentials_root(identity), key.to_vec()].concat()
+}
+
+impl LedgerStorage {
+    fn get_credential(
+        &self,
+        identity: &Address,
+        key: &Address,
+    ) -> Result<Option<IdentityCredentialStorage>, ManyError> {
+        self.persistent_store
+            .get(&key_for_credential(identity, key))
+            .map_err(error::storage_get_failed)?
+            .map(|value| minicbor::decode(&value).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    fn get_credential_owner(
+        &self,
+        cred_id: &idstore::CredentialId,
+    ) -> Result<Option<CredentialOwnerStorage>, ManyError> {
+        self.get_from_storage(&cred_id.0.to_vec(), IdStoreRootSeparator::CredentialId)?
+            .0
+            .map(|value| minicbor::decode(&value).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// The credential an identity was stored with, if any.
+    fn get_stored_credential(
+        &self,
+        identity: &Address,
+    ) -> Result<Option<CredentialStorage>, ManyError> {
+        self.get_from_storage(&identity.to_vec(), IdStoreRootSeparator::Address)?
+            .0
+            .map(|value| minicbor::decode(&value).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// Add a credential to an identity. Credential IDs are unique, and a
+    /// credential cannot be added again after being revoked.
+    pub fn add_credential(
+        &mut self,
+        identity: &Address,
+        key: &Address,
+        cred_id: idstore::CredentialId,
+        public_key: idstore::PublicKey,
+    ) -> Result<(), ManyError> {
+        if self.get_credential_owner(&cred_id)?.is_some()
+            || matches!(self.get_stored_credential(identity)?, Some(c) if c.cred_id == cred_id)
+        {
+            return Err(idstore::existing_entry());
+        }
+        if key == identity || self.get_credential(identity, key)?.is_some() {
+            return Err(idstore::key_already_used(key));
+        }
+
+        let mut batch = vec![
+            (
+                key_for_credential(identity, key),
+                Op::Put(
+                    minicbor::to_vec(IdentityCredentialStorage {
+                        cred_id: cred_id.clone(),
+                        public_key,
+                        revoked: false,
+                    })
+                    .map_err(ManyError::serialization_error)?,
+                ),
+            ),
+            (
+                [
+                    IDSTORE_ROOT,
+                    IdStoreRootSeparator::CredentialId.value(),
+                    cred_id.0.as_slice(),
+                ]
+                .concat(),
+                Op::Put(
+                    minicbor::to_vec(CredentialOwnerStorage {
+                        identity: *identity,
+                        key: *key,
+                    })
+                    .map_err(ManyError::serialization_error)?,
+                ),
+            ),
+        ];
+        batch.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+        self.apply(&batch)?;
+        self.maybe_commit()
+    }
+
+    /// Revoke a credential of an identity, either one added to it or the one
+    /// it was stored with.
+    pub fn revoke_credential(
+        &mut self,
+        identity: &Address,
+        cred_id: &idstore::CredentialId,
+    ) -> Result<(), ManyError> {
+        let not_found = || idstore::credential_not_found(hex::encode(&*cred_id.0));
+        let (key, public_key) = match self.get_credential_owner(cred_id)? {
+            Some(owner) if &owner.identity == identity => {
+                let credential = self
+                    .get_credential(identity, &owner.key)?
+                    .ok_or_else(not_found)?;
+                (owner.key, credential.public_key)
+            }
+            Some(_) => return Err(not_found()),
+            None => match self.get_stored_credential(identity)? {
+                Some(stored) if &stored.cred_id == cred_id => (*identity, stored.public_key),
+                _ => return Err(not_found()),
+            },
+        };
+
+        if !self
+            .list_credentials(identity)?
+            .iter()
+            .any(|c| !c.revoked && &c.cred_id != cred_id)
+        {
+            return Err(idstore::last_credential());
+        }
+
+        self.apply(&[(
+            key_for_credential(identity, &key),
+            Op::Put(
+                minicbor::to_vec(IdentityCredentialStorage {
+                    cred_id: cred_id.clone(),
+                    public_key,
+                    revoked: true,
+                })
+                .map_err(ManyError::serialization_error)?,
+            ),
+        )])?;
+        self.maybe_commit()
+    }
+
+    /// The credentials of an identity, starting with the one it was stored
+    /// with.
+    pub fn list_credentials(
+        &self,
+        identity: &Address,
+    ) -> Result<Vec<idstore::CredentialInfo>, ManyError> {
+        let mut credentials = Vec::new();
+        if let Some(stored) = self.get_stored_credential(identity)? {
+            credentials.push(idstore::CredentialInfo {
+                cred_id: stored.cred_id,
+                public_key: stored.public_key,
+                revoked: self
+                    .get_credential(identity, identity)?
+                    .map_or(false, |c| c.revoked),
+            });
+        }
+
+        let own_key = key_for_credential(identity, identity);
+        for item in
+            LedgerIterator::all_with_prefix(&self.persistent_store, credentials_root(identity))
+        {
+            let (key, value) = item.map_err(error::storage_get_failed)?;
+            // The revocation of the stored credential was listed above.
+            if *key == *own_key {
+                continue;
+            }
+            let credential: IdentityCredentialStorage =
+                minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+            credentials.push(idstore::CredentialInfo {
+                cred_id: credential.cred_id,
+                public_key: credential.public_key,
+                revoked: credential.revoked,
+            });
+        }
+        Ok(credentials)
+    }
+
+    /// The identity and public key of a credential added to an identity,
+    /// unless it was revoked.
+    pub fn get_from_credential_id(
+        &self,
+        cred_id: &idstore::CredentialId,
+    ) -> Result<(Address, idstore::PublicKey), ManyError> {
+        self.get_credential_owner(cred_id)?
+            .map(|owner| {
+                self.get_credential(&owner.identity, &owner.key).map(|c| {
+                    c.filter(|c| !c.revoked)
+                        .map(|c| (owner.identity, c.public_key))
+                })
+            })
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| idstore::entry_not_found(hex::encode(&*cred_id.0)))
+    }
+
+    /// Whether the key with this address can sign for the identity, i.e. it
+    /// is the key of the identity or one of its credentials, and was not
+    /// revoked.
+    pub fn can_sign(&self, identity: &Address, key: &Address) -> Result<bool, ManyError> {
+        Ok(match self.get_credential(identity, key)? {
+            Some(credential) => !credential.revoked,
+            None => identity == key,
+        })
+    }
 }
 
 #[cfg(test)]
@@ -214,16 +542,13 @@ pub mod tests {
 
     impl LedgerStorage {
         pub fn set_idstore_seed(&mut self, seed: u64) -> Result<(), ManyError> {
-            self.persistent_store
-                .apply(&[(
-                    IDSTORE_SEED_ROOT.to_vec(),
-                    Op::Put(seed.to_be_bytes().to_vec()),
-                )])
-                .map_err(error::storage_apply_failed)?;
-
-            self.persistent_store
-                .commit(&[])
-                .map_err(error::storage_commit_failed)?;
+            self.apply(&[(
+                IDSTORE_SEED_ROOT.to_vec(),
+                Op::Put(seed.to_be_bytes().to_vec()),
+            )])?;
+
+            self.flush_writes()?;
+            crate::storage::commit_store(self.persistent_store.flushed_mut())?;
             Ok(())
         }
     }
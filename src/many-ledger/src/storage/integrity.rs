@@ -0,0 +1,458 @@
+use crate::error;
+use crate::migration::data::{
+    get_data_from_db, ACCOUNT_TOTAL_COUNT_INDEX, NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX,
+};
+use crate::storage::data::display_index;
+use crate::storage::event::{key_for_event, EVENT_CHAIN_HEAD_ROOT, EVENT_COUNT_ROOT};
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::ledger_tokens::SYMBOLS_ROOT_DASH;
+use crate::storage::pruning::last_pruned_segment;
+use crate::storage::{InnerStorage, LedgerStorage, BALANCES_ROOT, HEIGHT_ROOT, LAST_APP_HASH_KEY};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::data::{DataIndex, DataValue};
+use many_modules::events;
+use many_types::ledger::TokenInfo;
+use many_types::SortOrder;
+use merk::rocksdb::{IteratorMode, ReadOptions};
+use merk::tree::{kv_hash, Hash, Tree, NULL_HASH};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use tracing::{debug, error, info, warn};
+
+/// A problem found while checking the integrity of the storage.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct IntegrityIssue {
+    /// The key of the storage where the problem was found, if any.
+    pub key: Option<Vec<u8>>,
+    pub description: String,
+}
+
+impl IntegrityIssue {
+    fn new(key: Option<&[u8]>, description: impl ToString) -> Self {
+        Self {
+            key: key.map(<[u8]>::to_vec),
+            description: description.to_string(),
+        }
+    }
+}
+
+impl Display for IntegrityIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.key {
+            Some(key) => write!(
+                f,
+                "{} (key {:?})",
+                self.description,
+                String::from_utf8_lossy(key)
+            ),
+            None => f.write_str(&self.description),
+        }
+    }
+}
+
+/// The result of an integrity check of the storage.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct IntegrityReport {
+    /// The height of the storage when it was checked.
+    pub height: u64,
+
+    /// The number of nodes of the Merk tree that were checked.
+    pub nodes: u64,
+
+    pub issues: Vec<IntegrityIssue>,
+
+    /// Indices that differ from their value recomputed from the store, if
+    /// the indices were checked. The indices are derived from the rest of
+    /// the store, so they do not quarantine it.
+    pub index_issues: Vec<IntegrityIssue>,
+}
+
+impl IntegrityReport {
+    pub fn is_ok(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+impl Display for IntegrityReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self.issues.as_slice() {
+            [] => write!(f, "no issue found at height {}", self.height)?,
+            [first, rest @ ..] => write!(
+                f,
+                "{} issue(s) found at height {}, the first being: {first}",
+                rest.len() + 1,
+                self.height
+            )?,
+        }
+        if !self.index_issues.is_empty() {
+            write!(f, ", {} index(es) out of date", self.index_issues.len())?;
+        }
+        Ok(())
+    }
+}
+
+fn get_u64(
+    store: &InnerStorage,
+    key: &[u8],
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<Option<u64>, ManyError> {
+    let Some(value) = store.get(key).map_err(error::storage_get_failed)? else {
+        return Ok(None);
+    };
+    match <[u8; 8]>::try_from(value.as_slice()) {
+        Ok(bytes) => Ok(Some(u64::from_be_bytes(bytes))),
+        Err(_) => {
+            issues.push(IntegrityIssue::new(Some(key), "Invalid counter."));
+            Ok(None)
+        }
+    }
+}
+
+/// Verify that the hash of every node of the Merk tree matches its content
+/// and children, up to the root hash. Returns the number of nodes.
+fn check_tree(store: &InnerStorage, issues: &mut Vec<IntegrityIssue>) -> Result<u64, ManyError> {
+    // The hash of every node, and the hash every parent expects of its children.
+    let mut hashes: BTreeMap<Vec<u8>, Hash> = BTreeMap::new();
+    let mut links: BTreeMap<Vec<u8>, Hash> = BTreeMap::new();
+
+    for item in store.iter_opt(IteratorMode::Start, ReadOptions::default()) {
+        let (key, bytes) = item.map_err(error::storage_get_failed)?;
+        let tree = Tree::decode(key.to_vec(), bytes.as_ref());
+
+        if &kv_hash(tree.key(), tree.value()) != tree.kv_hash() {
+            issues.push(IntegrityIssue::new(
+                Some(&key),
+                "The value does not match its hash.",
+            ));
+        }
+        for left in [true, false] {
+            if let Some(link) = tree.link(left) {
+                links.insert(link.key().to_vec(), *link.hash());
+            }
+        }
+        hashes.insert(key.to_vec(), tree.hash());
+    }
+
+    for (key, expected) in &links {
+        match hashes.get(key) {
+            None => issues.push(IntegrityIssue::new(
+                Some(key),
+                "A node refers to a missing child.",
+            )),
+            Some(hash) if hash != expected => issues.push(IntegrityIssue::new(
+                Some(key),
+                "The hash of the node does not match the one of its parent.",
+            )),
+            Some(_) => {}
+        }
+    }
+
+    // The only node that is not a child is the root.
+    let roots: Vec<&Hash> = hashes
+        .iter()
+        .filter(|(key, _)| !links.contains_key(*key))
+        .map(|(_, hash)| hash)
+        .collect();
+    let root_hash = store.root_hash();
+    match roots.as_slice() {
+        [] if hashes.is_empty() && root_hash == NULL_HASH => {}
+        [root] if **root == root_hash => {}
+        [_] | [] => issues.push(IntegrityIssue::new(
+            None,
+            "The root hash does not match the tree.",
+        )),
+        _ => issues.push(IntegrityIssue::new(
+            None,
+            format!("The tree has {} roots.", roots.len()),
+        )),
+    }
+
+    Ok(hashes.len() as u64)
+}
+
+/// Verify that the root hash of the tree is the one recorded by the last
+/// commit. Stores committed before the root hash was recorded are not
+/// checked. Changes applied but not committed yet change the root hash, so
+/// this must run between blocks.
+fn check_app_hash(store: &InnerStorage, issues: &mut Vec<IntegrityIssue>) -> Result<(), ManyError> {
+    let last = store
+        .get_aux(LAST_APP_HASH_KEY)
+        .map_err(error::storage_get_failed)?;
+    if let Some(last) = last {
+        if last.as_slice() != store.root_hash().as_slice() {
+            issues.push(IntegrityIssue::new(
+                None,
+                format!(
+                    "The root hash of the tree does not match the last committed app hash {}.",
+                    hex::encode(last)
+                ),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_index(
+    index: &DataIndex,
+    stored: Option<&DataValue>,
+    computed: &DataValue,
+    issues: &mut Vec<IntegrityIssue>,
+) {
+    if stored != Some(computed) {
+        issues.push(IntegrityIssue::new(
+            None,
+            format!(
+                "The data attribute {} is {stored:?}, but the store holds {computed:?}.",
+                display_index(index)
+            ),
+        ));
+    }
+}
+
+impl LedgerStorage {
+    /// Recompute the indices that are maintained incrementally, and compare
+    /// them with their stored values: the latest supply of every symbol, the
+    /// account counts and the registered data metrics.
+    pub fn check_indices(&self) -> Result<Vec<IntegrityIssue>, ManyError> {
+        let mut issues = Vec::new();
+
+        if self.is_supply_accounting_active() {
+            for item in LedgerIterator::all_symbols(&self.persistent_store, SortOrder::Ascending) {
+                let (key, value) = item.map_err(error::storage_get_failed)?;
+                let info: TokenInfo =
+                    minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+                let symbol = Address::from_str(
+                    std::str::from_utf8(&key[SYMBOLS_ROOT_DASH.len()..])
+                        .map_err(ManyError::deserialization_error)?,
+                )?;
+                match self.latest_supply(&symbol)? {
+                    Some(supply)
+                        if supply.total == info.supply.total
+                            && supply.circulating == info.supply.circulating => {}
+                    Some(_) => issues.push(IntegrityIssue::new(
+                        Some(&key),
+                        format!("The latest supply of {symbol} is not the supply of the token."),
+                    )),
+                    None => issues.push(IntegrityIssue::new(
+                        Some(&key),
+                        format!("No supply is recorded for {symbol}."),
+                    )),
+                }
+            }
+        }
+
+        if let Some(attributes) = self.data_attributes()? {
+            if attributes.contains_key(&ACCOUNT_TOTAL_COUNT_INDEX) {
+                let (accounts, non_zero) = get_data_from_db(&self.persistent_store)?;
+                check_index(
+                    &ACCOUNT_TOTAL_COUNT_INDEX,
+                    attributes.get(&ACCOUNT_TOTAL_COUNT_INDEX),
+                    &DataValue::Counter(accounts),
+                    &mut issues,
+                );
+                check_index(
+                    &NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX,
+                    attributes.get(&NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX),
+                    &DataValue::Counter(non_zero),
+                    &mut issues,
+                );
+            }
+
+            // Outside of a blockchain, the metrics are only computed when
+            // registered.
+            if self.blockchain {
+                for (index, metric) in &self.data_metrics()? {
+                    check_index(
+                        index,
+                        attributes.get(index),
+                        &self.compute_data_metric(metric)?,
+                        &mut issues,
+                    );
+                }
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Check that the events match the event count and their running
+    /// hashes, and that the head of the chain is the last event.
+    fn check_events(&self, issues: &mut Vec<IntegrityIssue>) -> Result<(), ManyError> {
+        let store = &self.persistent_store;
+        let mut count = 0u64;
+        // The running hash before the event, the head of the last pruned
+        // segment for the first event if the log was pruned.
+        let mut previous: Option<Vec<u8>> = last_pruned_segment(store)?.map(|(_, head)| head);
+
+        for item in LedgerIterator::all_events(store) {
+            let (key, value) = item.map_err(error::storage_get_failed)?;
+            count += 1;
+            let event = match minicbor::decode::<events::EventLog>(&value) {
+                Ok(event) => event,
+                Err(e) => {
+                    issues.push(IntegrityIssue::new(
+                        Some(&key),
+                        format!("Invalid event: {e}"),
+                    ));
+                    previous = None;
+                    continue;
+                }
+            };
+            if key_for_event(event.id.clone()).as_slice() != key.as_ref() {
+                issues.push(IntegrityIssue::new(
+                    Some(&key),
+                    "The event is stored under the key of another event.",
+                ));
+            }
+
+            match &event.chain_hash {
+                Some(chain_hash) => {
+                    if let Some(p) = &previous {
+                        if &event.chain(p)? != chain_hash {
+                            issues.push(IntegrityIssue::new(
+                                Some(&key),
+                                "The event does not follow the previous event.",
+                            ));
+                        }
+                    }
+                    previous = Some(chain_hash.to_vec());
+                }
+                None => previous = Some(events::EVENT_CHAIN_GENESIS.to_vec()),
+            }
+        }
+
+        let nb_events = get_u64(store, EVENT_COUNT_ROOT, issues)?.unwrap_or_default();
+        if nb_events != count {
+            issues.push(IntegrityIssue::new(
+                Some(EVENT_COUNT_ROOT),
+                format!("The event count is {nb_events}, but {count} events are stored."),
+            ));
+        }
+
+        let head = store
+            .get(EVENT_CHAIN_HEAD_ROOT)
+            .map_err(error::storage_get_failed)?;
+        let last = previous.filter(|p| p.as_slice() != events::EVENT_CHAIN_GENESIS);
+        if head.is_some() && last.is_some() && head != last {
+            issues.push(IntegrityIssue::new(
+                Some(EVENT_CHAIN_HEAD_ROOT),
+                "The head of the event chain is not the last event.",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Check that every balance is held in a known symbol.
+    fn check_balances(&self, issues: &mut Vec<IntegrityIssue>) -> Result<(), ManyError> {
+        let symbols = match self.get_symbols() {
+            Ok(symbols) => symbols,
+            Err(e) => {
+                issues.push(IntegrityIssue::new(None, format!("Invalid symbols: {e}")));
+                return Ok(());
+            }
+        };
+
+        for item in LedgerIterator::all_balances(&self.persistent_store, SortOrder::Ascending) {
+            let (key, _) = item.map_err(error::storage_get_failed)?;
+            let symbol = std::str::from_utf8(&key[BALANCES_ROOT.len()..])
+                .ok()
+                .and_then(|rest| rest.rsplit_once('/'))
+                .and_then(|(_, symbol)| Address::from_str(symbol).ok());
+            match symbol {
+                Some(symbol) if symbols.contains(&symbol) => {}
+                Some(symbol) => issues.push(IntegrityIssue::new(
+                    Some(&key),
+                    format!("Balance of unknown symbol {symbol}."),
+                )),
+                None => issues.push(IntegrityIssue::new(Some(&key), "Invalid balance key.")),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check the consistency of the Merk tree and of the ledger indices,
+    /// without changing the storage.
+    pub fn check_integrity(&self) -> Result<IntegrityReport, ManyError> {
+        let mut issues = Vec::new();
+        let height = get_u64(&self.persistent_store, HEIGHT_ROOT.as_bytes(), &mut issues)?
+            .unwrap_or_default();
+        let nodes = check_tree(&self.persistent_store, &mut issues)?;
+        check_app_hash(&self.persistent_store, &mut issues)?;
+        self.check_events(&mut issues)?;
+        self.check_balances(&mut issues)?;
+        let index_issues = if self.integrity_check_indices {
+            self.check_indices()?
+        } else {
+            vec![]
+        };
+
+        Ok(IntegrityReport {
+            height,
+            nodes,
+            issues,
+            index_issues,
+        })
+    }
+
+    /// Check the integrity of the storage, and quarantine it if any issue is
+    /// found. A quarantined storage is read-only: commands fail and blocks are
+    /// not committed, until the node is restarted on a repaired store.
+    pub fn enforce_integrity(&mut self) -> Result<IntegrityReport, ManyError> {
+        let report = self.check_integrity()?;
+        debug!(
+            target: "metrics",
+            integrity_nodes = report.nodes,
+            integrity_issues = report.issues.len(),
+            integrity_index_issues = report.index_issues.len(),
+        );
+        for issue in &report.index_issues {
+            warn!("Storage index issue: {issue}");
+        }
+        if report.is_ok() {
+            info!(
+                "Storage integrity checked at height {} ({} nodes)",
+                report.height, report.nodes
+            );
+        } else {
+            for issue in &report.issues {
+                error!("Storage integrity issue: {issue}");
+            }
+            error!("Quarantining the storage, {report}");
+            self.quarantine = Some(report.clone());
+        }
+        Ok(report)
+    }
+
+    /// Check the integrity of the storage every `interval` blocks when
+    /// committing. Disabled if `None`.
+    pub fn set_integrity_check_interval(&mut self, interval: Option<u64>) {
+        self.integrity_check_interval = interval.filter(|i| *i > 0);
+    }
+
+    /// Also recompute the indices when checking the integrity of the
+    /// storage. This reads every balance, and every symbol.
+    pub fn set_integrity_check_indices(&mut self, check: bool) {
+        self.integrity_check_indices = check;
+    }
+
+    /// The report of the integrity check that quarantined the storage, if any.
+    pub fn quarantine(&self) -> Option<&IntegrityReport> {
+        self.quarantine.as_ref()
+    }
+
+    pub(crate) fn check_quarantine(&self) -> Result<(), ManyError> {
+        match &self.quarantine {
+            Some(report) => Err(error::storage_quarantined(report)),
+            None => Ok(()),
+        }
+    }
+
+    pub(crate) fn maybe_check_integrity(&mut self, height: u64) -> Result<(), ManyError> {
+        match self.integrity_check_interval {
+            Some(interval) if height % interval == 0 => self.enforce_integrity().map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+}
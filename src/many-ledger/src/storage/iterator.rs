@@ -1,4 +1,4 @@
-use crate::storage::event::{key_for_event, EVENTS_ROOT};
+use crate::storage::event::{key_for_event_id, EVENTS_ROOT};
 use crate::storage::InnerStorage;
 use many_modules::events::EventId;
 use many_types::{CborRange, SortOrder};
@@ -31,6 +31,38 @@ impl<'a> LedgerIterator<'a> {
         Self { inner }
     }
 
+    pub fn all_scheduled(merk: &'a InnerStorage, order: SortOrder) -> Self {
+        use crate::storage::schedule::SCHEDULED_TRANSACTIONS_ROOT;
+
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(SCHEDULED_TRANSACTIONS_ROOT));
+
+        let it_mode = match order {
+            SortOrder::Indeterminate | SortOrder::Ascending => IteratorMode::Start,
+            SortOrder::Descending => IteratorMode::End,
+        };
+
+        let inner = merk.iter_opt(it_mode, options);
+
+        Self { inner }
+    }
+
+    pub fn all_balances(merk: &'a InnerStorage, order: SortOrder) -> Self {
+        use crate::storage::BALANCES_ROOT;
+
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(BALANCES_ROOT.as_bytes()));
+
+        let it_mode = match order {
+            SortOrder::Indeterminate | SortOrder::Ascending => IteratorMode::Start,
+            SortOrder::Descending => IteratorMode::End,
+        };
+
+        let inner = merk.iter_opt(it_mode, options);
+
+        Self { inner }
+    }
+
     pub fn all_symbols(merk: &'a InnerStorage, order: SortOrder) -> Self {
         use crate::storage::ledger_tokens::SYMBOLS_ROOT_DASH;
 
@@ -47,6 +79,56 @@ impl<'a> LedgerIterator<'a> {
         Self { inner }
     }
 
+    /// All the keys starting with a prefix, in ascending order.
+    pub fn all_with_prefix(merk: &'a InnerStorage, prefix: Vec<u8>) -> Self {
+        let mut options = ReadOptions::default();
+        options.set_iterate_range(rocksdb::PrefixRange(prefix));
+
+        let inner = merk.iter_opt(IteratorMode::Start, options);
+
+        Self { inner }
+    }
+
+    /// The keys between a lower bound (included) and an upper bound
+    /// (excluded).
+    pub fn scoped_by_key(
+        merk: &'a InnerStorage,
+        lower_bound: Vec<u8>,
+        upper_bound: Vec<u8>,
+        order: SortOrder,
+    ) -> Self {
+        let mut options = ReadOptions::default();
+        options.set_iterate_lower_bound(lower_bound);
+        options.set_iterate_upper_bound(upper_bound);
+
+        let it_mode = match order {
+            SortOrder::Indeterminate | SortOrder::Ascending => IteratorMode::Start,
+            SortOrder::Descending => IteratorMode::End,
+        };
+
+        let inner = merk.iter_opt(it_mode, options);
+
+        Self { inner }
+    }
+
+    /// The keys from a lower bound (included) to an upper bound (excluded),
+    /// or to the last key, in ascending order.
+    pub fn key_range(
+        merk: &'a InnerStorage,
+        lower_bound: Vec<u8>,
+        upper_bound: Option<Vec<u8>>,
+    ) -> Self {
+        let mut options = ReadOptions::default();
+        options.set_iterate_lower_bound(lower_bound);
+        if let Some(upper_bound) = upper_bound {
+            options.set_iterate_upper_bound(upper_bound);
+        }
+
+        let inner = merk.iter_opt(IteratorMode::Start, options);
+
+        Self { inner }
+    }
+
     pub fn all_events(merk: &'a InnerStorage) -> Self {
         Self::events_scoped_by_id(merk, CborRange::default(), SortOrder::Indeterminate)
     }
@@ -55,20 +137,35 @@ impl<'a> LedgerIterator<'a> {
         merk: &'a InnerStorage,
         range: CborRange<EventId>,
         order: SortOrder,
+    ) -> Self {
+        Self::scoped_by_event_id(merk, EVENTS_ROOT, range, order)
+    }
+
+    /// Iterate the keys under `root` made of an event ID, within a range of
+    /// IDs. The root needs to end with a `/`.
+    pub fn scoped_by_event_id(
+        merk: &'a InnerStorage,
+        root: &[u8],
+        range: CborRange<EventId>,
+        order: SortOrder,
     ) -> Self {
         let mut opts = ReadOptions::default();
 
         match range.start_bound() {
-            Bound::Included(x) => opts.set_iterate_lower_bound(key_for_event(x.clone())),
-            Bound::Excluded(x) => opts.set_iterate_lower_bound(key_for_event(x.clone() + 1)),
-            Bound::Unbounded => opts.set_iterate_lower_bound(EVENTS_ROOT),
+            Bound::Included(x) => opts.set_iterate_lower_bound(key_for_event_id(root, x.clone())),
+            Bound::Excluded(x) => {
+                opts.set_iterate_lower_bound(key_for_event_id(root, x.clone() + 1))
+            }
+            Bound::Unbounded => opts.set_iterate_lower_bound(root),
         }
         match range.end_bound() {
-            Bound::Included(x) => opts.set_iterate_upper_bound(key_for_event(x.clone() + 1)),
-            Bound::Excluded(x) => opts.set_iterate_upper_bound(key_for_event(x.clone())),
+            Bound::Included(x) => {
+                opts.set_iterate_upper_bound(key_for_event_id(root, x.clone() + 1))
+            }
+            Bound::Excluded(x) => opts.set_iterate_upper_bound(key_for_event_id(root, x.clone())),
             Bound::Unbounded => {
-                let mut bound = EVENTS_ROOT.to_vec();
-                bound[EVENTS_ROOT.len() - 1] += 1;
+                let mut bound = root.to_vec();
+                bound[root.len() - 1] += 1;
                 opts.set_iterate_upper_bound(bound);
             }
         }
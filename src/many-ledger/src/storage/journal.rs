@@ -0,0 +1,280 @@
+//! Write-ahead journal of the block being committed.
+//!
+//! Committing a block takes two Merk commits: one of the changes made by its
+//! commands, then one of the migrations at its height. A node killed between
+//! them would restart at the new height without the migrations, so its state
+//! hash would diverge from the rest of the network. Before committing, the
+//! changes of the block are written to the journal, which is removed once
+//! both commits are done. On startup, a journal left over is replayed to
+//! finish the commit.
+use crate::error;
+use crate::migration::LedgerMigrations;
+use crate::storage::{commit_store, InnerStorage};
+use many_error::ManyError;
+use merk::{BatchEntry, Op};
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use sha3::{Digest, Sha3_256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Name of the journal file, in the directory of the persistent store.
+pub const JOURNAL_FILE_NAME: &str = "COMMAND_JOURNAL";
+
+const CHECKSUM_SIZE: usize = 32;
+
+/// A change of a key, or its deletion if there is no value.
+type JournalEntry = (ByteVec, Option<ByteVec>);
+
+/// The changes of a block about to be committed.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct JournalRecord {
+    /// The height of the block.
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub time: Option<u64>,
+
+    /// The root hash of the store once the batches are applied, before the
+    /// migrations.
+    #[n(2)]
+    pub hash: ByteVec,
+
+    #[n(3)]
+    batches: Vec<Vec<JournalEntry>>,
+}
+
+impl JournalRecord {
+    fn apply(&self, store: &mut InnerStorage) -> Result<(), ManyError> {
+        for batch in &self.batches {
+            let batch: Vec<BatchEntry> = batch
+                .iter()
+                .map(|(key, value)| {
+                    let op = match value {
+                        Some(value) => Op::Put(value.to_vec()),
+                        None => Op::Delete,
+                    };
+                    (key.to_vec(), op)
+                })
+                .collect();
+            store.apply(&batch).map_err(error::storage_apply_failed)?;
+        }
+        Ok(())
+    }
+}
+
+/// The batches applied to the store since the last commit, and where to
+/// journal them when the block is committed.
+#[derive(Debug)]
+pub(crate) struct CommandJournal {
+    path: PathBuf,
+    batches: Vec<Vec<JournalEntry>>,
+}
+
+impl CommandJournal {
+    pub fn new(store_path: &Path) -> Self {
+        Self {
+            path: journal_path(store_path),
+            batches: vec![],
+        }
+    }
+
+    pub fn record(&mut self, batch: &[BatchEntry]) {
+        self.batches.push(
+            batch
+                .iter()
+                .map(|(key, op)| {
+                    let value = match op {
+                        Op::Put(value) => Some(value.clone().into()),
+                        Op::Delete => None,
+                    };
+                    (key.clone().into(), value)
+                })
+                .collect(),
+        );
+    }
+
+    /// Forget the batches, e.g. once they are committed.
+    pub fn clear(&mut self) {
+        self.batches.clear();
+    }
+
+    /// Write the batches recorded for the block at `height` to the journal,
+    /// so the commit can be finished after a crash.
+    pub fn seal(&mut self, height: u64, time: Option<u64>, hash: &[u8]) -> Result<(), ManyError> {
+        let record = JournalRecord {
+            height,
+            time,
+            hash: hash.to_vec().into(),
+            batches: std::mem::take(&mut self.batches),
+        };
+        write_record(&self.path, &record)
+    }
+
+    /// Remove the journal, once the block is fully committed.
+    pub fn remove(&self) -> Result<(), ManyError> {
+        remove_journal(&self.path)
+    }
+}
+
+fn journal_path(store_path: &Path) -> PathBuf {
+    store_path.join(JOURNAL_FILE_NAME)
+}
+
+fn remove_journal(path: &Path) -> Result<(), ManyError> {
+    match std::fs::remove_file(path) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+            Err(error::invalid_command_journal(e))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Write the record with its checksum to a temporary file renamed over the
+/// journal, so the journal is never partially written.
+fn write_record(path: &Path, record: &JournalRecord) -> Result<(), ManyError> {
+    let payload = minicbor::to_vec(record).map_err(ManyError::serialization_error)?;
+    let tmp_path = path.with_extension("tmp");
+
+    let mut file = std::fs::File::create(&tmp_path).map_err(error::invalid_command_journal)?;
+    file.write_all(&Sha3_256::digest(&payload))
+        .and_then(|_| file.write_all(&payload))
+        .and_then(|_| file.sync_all())
+        .map_err(error::invalid_command_journal)?;
+    std::fs::rename(&tmp_path, path).map_err(error::invalid_command_journal)
+}
+
+/// Read the journal of a persistent store, if any. Fails if the journal is
+/// corrupt.
+pub fn read_journal(store_path: &Path) -> Result<Option<JournalRecord>, ManyError> {
+    let bytes = match std::fs::read(journal_path(store_path)) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(error::invalid_command_journal(e)),
+    };
+    if bytes.len() < CHECKSUM_SIZE {
+        return Err(error::invalid_command_journal("the journal is truncated"));
+    }
+
+    let (checksum, payload) = bytes.split_at(CHECKSUM_SIZE);
+    if Sha3_256::digest(payload).as_slice() != checksum {
+        return Err(error::invalid_command_journal(
+            "the checksum does not match",
+        ));
+    }
+    minicbor::decode(payload)
+        .map(Some)
+        .map_err(error::invalid_command_journal)
+}
+
+/// Finish the commit of the block journaled in `record`, if the store at
+/// `height` does not have it yet. `migrations` need to be the migrations of
+/// the previous block. Returns the height of the store afterward.
+pub(super) fn recover(
+    store: &mut InnerStorage,
+    migrations: &mut LedgerMigrations,
+    record: &JournalRecord,
+    height: u64,
+) -> Result<u64, ManyError> {
+    if height + 1 == record.height {
+        // The commands of the block were not committed.
+        info!("Replaying the journal of block {}", record.height);
+        record.apply(store)?;
+    } else if height != record.height || store.root_hash() != record.hash.as_slice() {
+        // The block was fully committed, or the journal is stale.
+        return Ok(height);
+    }
+
+    // The hash does not depend on the node, so a mismatch means the journal
+    // is not the one of this store.
+    let hash = store.root_hash();
+    if hash != record.hash.as_slice() {
+        return Err(error::journal_hash_mismatch(
+            hex::encode(record.hash.as_slice()),
+            hex::encode(hash),
+        ));
+    }
+    commit_store(store)?;
+
+    info!("Running the migrations of block {}", record.height);
+    migrations.update_at(store, record.height, record.time)?;
+    commit_store(store)?;
+    Ok(record.height)
+}
+
+/// Remove the journal of a persistent store, e.g. after recovering from it.
+pub(super) fn clear_journal(store_path: &Path) {
+    if let Err(e) = remove_journal(&journal_path(store_path)) {
+        warn!("Unable to remove the command journal: {e}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_migration::MigrationSet;
+
+    fn journal_block(path: &Path, height: u64) -> Vec<u8> {
+        let mut store = InnerStorage::open(path).unwrap();
+        let mut journal = CommandJournal::new(path);
+        let batches: [Vec<BatchEntry>; 2] = [
+            vec![(b"/a".to_vec(), Op::Put(vec![1]))],
+            vec![
+                (b"/a".to_vec(), Op::Delete),
+                (b"/b".to_vec(), Op::Put(vec![2])),
+            ],
+        ];
+        for batch in &batches {
+            store.apply(batch).unwrap();
+            journal.record(batch);
+        }
+        let hash = store.root_hash().to_vec();
+        journal.seal(height, Some(1), &hash).unwrap();
+        hash
+    }
+
+    #[test]
+    fn replay() {
+        let path = tempfile::tempdir().unwrap().into_path();
+        // The store is dropped without committing, as in a crash.
+        let hash = journal_block(&path, 1);
+
+        let record = read_journal(&path).unwrap().unwrap();
+        let mut store = InnerStorage::open(&path).unwrap();
+        let mut migrations = MigrationSet::empty().unwrap();
+        assert_eq!(recover(&mut store, &mut migrations, &record, 0), Ok(1));
+        assert_eq!(store.root_hash().to_vec(), hash);
+        assert_eq!(store.get(b"/a").unwrap(), None);
+        assert_eq!(store.get(b"/b").unwrap(), Some(vec![2]));
+    }
+
+    #[test]
+    fn stale() {
+        let path = tempfile::tempdir().unwrap().into_path();
+        journal_block(&path, 5);
+
+        let record = read_journal(&path).unwrap().unwrap();
+        let mut store = InnerStorage::open(&path).unwrap();
+        let mut migrations = MigrationSet::empty().unwrap();
+        assert_eq!(recover(&mut store, &mut migrations, &record, 0), Ok(0));
+        assert_eq!(store.get(b"/b").unwrap(), None);
+    }
+
+    #[test]
+    fn corrupt() {
+        let path = tempfile::tempdir().unwrap().into_path();
+        journal_block(&path, 1);
+
+        let journal_path = journal_path(&path);
+        let mut bytes = std::fs::read(&journal_path).unwrap();
+        *bytes.last_mut().unwrap() ^= 1;
+        std::fs::write(&journal_path, bytes).unwrap();
+        assert!(read_journal(&path).is_err());
+
+        clear_journal(&path);
+        assert_eq!(read_journal(&path), Ok(None));
+    }
+}
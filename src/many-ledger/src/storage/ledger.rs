@@ -50,9 +50,7 @@ impl LedgerStorage {
 
         batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
-        self.persistent_store
-            .apply(batch.as_slice())
-            .map_err(error::storage_apply_failed)?;
+        self.apply(batch.as_slice())?;
 
         Ok(self)
     }
@@ -117,35 +115,73 @@ impl LedgerStorage {
         })
     }
 
+    /// A page of up to `count` balances of an account, ordered by symbol,
+    /// with the symbol to list the next page after if there are more, and
+    /// keys to prove the page.
+    #[allow(clippy::type_complexity)]
+    pub fn list_balances(
+        &self,
+        identity: &Address,
+        after: Option<&Symbol>,
+        count: usize,
+        include_zero: bool,
+    ) -> Result<(Vec<(Symbol, TokenAmount)>, Option<Symbol>, Vec<Vec<u8>>), ManyError> {
+        let (mut balances, _) = self.get_all_balances(identity)?;
+        let mut symbols = self
+            .get_symbols()?
+            .into_iter()
+            .filter(|symbol| after.map_or(true, |after| symbol > after))
+            .filter_map(|symbol| {
+                let amount = balances.remove(&symbol).unwrap_or_default();
+                (include_zero || !amount.is_zero()).then_some((symbol, amount))
+            });
+
+        let page: Vec<_> = symbols.by_ref().take(count).collect();
+        let next = symbols
+            .next()
+            .and_then(|_| page.last().map(|(symbol, _)| *symbol));
+        let keys = page
+            .iter()
+            .map(|(symbol, _)| key_for_account_balance(identity, symbol))
+            .collect();
+        Ok((page, next, keys))
+    }
+
     pub fn prove_state(
         &self,
         context: impl AsRef<Context>,
         keys: impl IntoIterator<Item = Vec<u8>>,
     ) -> Result<(), ManyError> {
         context.as_ref().prove(|| {
-            self.persistent_store
-                .prove(
-                    keys.into_iter()
-                        .map(QueryItem::Key)
-                        .collect::<Vec<_>>()
-                        .into(),
-                )
-                .and_then(|proof| {
-                    Decoder::new(proof.as_slice())
-                        .map(|fallible_operation| {
-                            fallible_operation.map(|operation| match operation {
-                                Child => ProofOperation::Child,
-                                Parent => ProofOperation::Parent,
-                                Push(Hash(hash)) => ProofOperation::NodeHash(hash.to_vec()),
-                                Push(KV(key, value)) => {
-                                    ProofOperation::KeyValuePair(key.into(), value.into())
-                                }
-                                Push(KVHash(hash)) => ProofOperation::KeyValueHash(hash.to_vec()),
-                            })
+            self.proof_cache
+                .get_or_prove(self.get_height()?, keys, |keys| {
+                    self.persistent_store
+                        .prove(
+                            keys.iter()
+                                .cloned()
+                                .map(QueryItem::Key)
+                                .collect::<Vec<_>>()
+                                .into(),
+                        )
+                        .and_then(|proof| {
+                            Decoder::new(proof.as_slice())
+                                .map(|fallible_operation| {
+                                    fallible_operation.map(|operation| match operation {
+                                        Child => ProofOperation::Child,
+                                        Parent => ProofOperation::Parent,
+                                        Push(Hash(hash)) => ProofOperation::NodeHash(hash.to_vec()),
+                                        Push(KV(key, value)) => {
+                                            ProofOperation::KeyValuePair(key.into(), value.into())
+                                        }
+                                        Push(KVHash(hash)) => {
+                                            ProofOperation::KeyValueHash(hash.to_vec())
+                                        }
+                                    })
+                                })
+                                .collect::<Result<Vec<_>, _>>()
                         })
-                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(|error| ManyError::unknown(error.to_string()))
                 })
-                .map_err(|error| ManyError::unknown(error.to_string()))
         })
     }
 }
@@ -1,12 +1,15 @@
 use crate::error;
+use crate::migration::decimal_amount::DECIMAL_AMOUNT_MIGRATION;
+use crate::migration::tokens::TOKEN_MIGRATION;
+use crate::storage::dust::adjust;
 use crate::storage::{key_for_account_balance, LedgerStorage};
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::events::EventInfo;
-use many_types::ledger::{Symbol, TokenAmount};
+use many_modules::ledger::TokenInfoArgs;
+use many_types::ledger::{DecimalAmount, Symbol, TokenAmount};
 use many_types::Memo;
-use merk::{BatchEntry, Op};
-use std::cmp::Ordering;
+use merk::BatchEntry;
 use tracing::info;
 
 impl LedgerStorage {
@@ -32,6 +35,42 @@ impl LedgerStorage {
         }
     }
 
+    /// Verify that a decimal amount sent alongside `amount` represents exactly
+    /// the same value in the decimals of the symbol. Amounts more precise than
+    /// the symbol are rejected instead of being truncated.
+    pub fn verify_decimal_amount(
+        &self,
+        symbol: &Symbol,
+        amount: &TokenAmount,
+        decimal_amount: Option<&DecimalAmount>,
+    ) -> Result<(), ManyError> {
+        let decimal_amount = match decimal_amount {
+            Some(d) => d,
+            None => return Ok(()),
+        };
+        if !self.migrations.is_active(&DECIMAL_AMOUNT_MIGRATION)
+            || !self.migrations.is_active(&TOKEN_MIGRATION)
+        {
+            return Ok(());
+        }
+
+        let decimals = self
+            .info_token(TokenInfoArgs {
+                symbol: *symbol,
+                extended_info: None,
+            })?
+            .info
+            .summary
+            .decimals;
+        match decimal_amount.to_token_amount(decimals) {
+            None => Err(error::amount_too_precise(decimal_amount, symbol, decimals)),
+            Some(converted) if &converted != amount => {
+                Err(error::decimal_amount_mismatch(decimal_amount, amount))
+            }
+            Some(_) => Ok(()),
+        }
+    }
+
     pub fn send(
         &mut self,
         from: &Address,
@@ -57,32 +96,47 @@ impl LedgerStorage {
             return Err(error::insufficient_funds());
         }
 
+        // With a dust threshold, the remainder below it is either refused or
+        // swept along with the transfer.
+        let dust = self.dust_config(symbol)?;
+        let amount = match &dust {
+            Some(dust) => adjust(dust, &amount_from, amount)?,
+            None => amount,
+        };
+        self.verify_unlocked(from, symbol, &amount, &amount_from)?;
+        self.verify_token_rules(from, to, symbol, &amount)?;
+
         info!("send({} => {}, {} {})", from, to, &amount, symbol);
 
         let mut amount_to = self.get_balance(to, symbol)?;
         amount_to += amount.clone();
         amount_from -= amount.clone();
 
-        // Keys in batch must be sorted.
+        // Empty balances of symbols with a threshold are removed, so dust
+        // accounts do not pile up in the storage.
+        let remove_from = dust.is_some() && amount_from.is_zero();
+
         let key_from = key_for_account_balance(from, symbol);
         let key_to = key_for_account_balance(to, symbol);
 
-        let batch: Vec<BatchEntry> = match key_from.cmp(&key_to) {
-            Ordering::Less | Ordering::Equal => vec![
-                (key_from.clone(), Op::Put(amount_from.to_vec())),
-                (key_to.clone(), Op::Put(amount_to.to_vec())),
-            ],
-            _ => vec![
-                (key_to.clone(), Op::Put(amount_to.to_vec())),
-                (key_from.clone(), Op::Put(amount_from.to_vec())),
-            ],
-        };
+        // Keys in batch must be sorted.
+        let mut batch: Vec<BatchEntry> = vec![
+            self.balance_entry(from, symbol, &amount_from)?,
+            self.balance_entry(to, symbol, &amount_to)?,
+        ];
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+
+        // Tokens sent to the illegal address can never be spent, so they
+        // count as burned in the supply records.
+        if to.is_illegal() && self.is_supply_accounting_active() {
+            let supply = self.get_token_supply(symbol)?;
+            batch.extend(self.supply_change_entries(symbol, &supply, &amount)?);
+            batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        }
 
-        self.update_account_count(from, to, amount.clone(), symbol)?;
+        self.update_account_count(from, to, amount.clone(), symbol, remove_from)?;
 
-        self.persistent_store
-            .apply(&batch)
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&batch)?;
 
         self.log_event(EventInfo::Send {
             from: *from,
@@ -48,9 +48,8 @@ impl LedgerStorage {
                 self.get_multiple_balances(address, &BTreeSet::from([symbol]))?;
             keys.extend(balance_keys);
             let new_balance = balances.get(&symbol).map_or(amount.clone(), |b| b + amount);
-            let key = key_for_account_balance(address, &symbol);
-            keys.push(key.clone());
-            batch.push((key, Op::Put(new_balance.to_vec())));
+            keys.push(key_for_account_balance(address, &symbol));
+            batch.push(self.balance_entry(address, &symbol, &new_balance)?);
         }
 
         // Update circulating supply
@@ -68,14 +67,13 @@ impl LedgerStorage {
             symbol_key.into(),
             Op::Put(minicbor::to_vec(&info).map_err(ManyError::serialization_error)?),
         ));
+        batch.extend(self.supply_change_entries(&symbol, &info.supply, &TokenAmount::zero())?);
 
         // We need to sort here because `distribution` is sorted by Address (bytes)
         // while the `merk` Ops are sorted by String
         batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
-        self.persistent_store
-            .apply(batch.as_slice())
-            .map_err(error::storage_apply_failed)?;
+        self.apply(batch.as_slice())?;
 
         self.maybe_commit().map(|_| keys)
     }
@@ -106,9 +104,8 @@ impl LedgerStorage {
 
             // Store new balance in DB
             let new_balance = &balance_amount - amount;
-            let key = key_for_account_balance(address, &symbol);
-            keys.push(key.clone());
-            batch.push((key, Op::Put(new_balance.to_vec())));
+            keys.push(key_for_account_balance(address, &symbol));
+            batch.push(self.balance_entry(address, &symbol, &new_balance)?);
             circulating += amount;
         }
 
@@ -120,7 +117,7 @@ impl LedgerStorage {
             })?
             .info;
         info.supply.circulating -= &circulating;
-        info.supply.total -= circulating;
+        info.supply.total -= &circulating;
 
         let symbol_key = key_for_symbol(&symbol);
         keys.push(symbol_key.clone().into_bytes());
@@ -129,14 +126,13 @@ impl LedgerStorage {
             symbol_key.into(),
             Op::Put(minicbor::to_vec(&info).map_err(ManyError::serialization_error)?),
         ));
+        batch.extend(self.supply_change_entries(&symbol, &info.supply, &circulating)?);
 
         // We need to sort here because `distribution` is sorted by Address (bytes)
         // while the `merk` Ops are sorted by String
         batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
-        self.persistent_store
-            .apply(batch.as_slice())
-            .map_err(error::storage_apply_failed)?;
+        self.apply(batch.as_slice())?;
 
         self.maybe_commit().map(|_| keys)
     }
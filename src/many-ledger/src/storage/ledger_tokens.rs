@@ -1,6 +1,9 @@
 use crate::error;
 use crate::migration::tokens::TOKEN_MIGRATION;
+use crate::storage::dust::DustConfig;
 use crate::storage::iterator::LedgerIterator;
+use crate::storage::supply::record_all_supplies;
+use crate::storage::token_rules::key_for_token_rules;
 use crate::storage::{
     key_for_account_balance, key_for_subresource_counter, LedgerStorage, IDENTITY_ROOT,
     SYMBOLS_ROOT,
@@ -32,11 +35,21 @@ pub fn key_for_ext_info(symbol: &Symbol) -> Vec<u8> {
     format!("/config/ext_info/{symbol}").into_bytes()
 }
 
+/// The keys of the information returned by `tokens.info`, to prove it.
+pub fn token_info_keys(symbol: &Symbol) -> Vec<Vec<u8>> {
+    vec![
+        key_for_symbol(symbol).into_bytes(),
+        key_for_ext_info(symbol),
+        key_for_token_rules(symbol),
+    ]
+}
+
 pub struct SymbolMeta {
     pub name: String,
     pub decimals: u64,
     pub owner: Option<Address>,
     pub maximum: Option<TokenAmount>,
+    pub dust: Option<DustConfig>,
 }
 
 pub fn verify_tokens_sender(sender: &Address, token_identity: Address) -> Result<(), ManyError> {
@@ -115,6 +128,9 @@ impl LedgerStorage {
 
             let mut batch: Vec<BatchEntry> = Vec::new();
             for (k, meta) in symbols_meta.into_iter() {
+                if let Some(dust) = &meta.dust {
+                    batch.extend(self.dust_config_entry(&k, dust)?);
+                }
                 let total_supply = total_supply[&k].clone(); // Safe
                 let ticker = symbols[&k].clone(); // Safe
                 let info = LedgerStorage::_token_info(k, ticker, meta, total_supply.clone());
@@ -132,9 +148,11 @@ impl LedgerStorage {
                 ));
             }
             batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
-            self.persistent_store
-                .apply(batch.as_slice())
-                .map_err(error::storage_apply_failed)?;
+            self.apply(batch.as_slice())?;
+            if self.is_supply_accounting_active() {
+                self.flush_writes()?;
+                record_all_supplies(self.persistent_store.flushed_mut())?;
+            }
 
             let token_identity = token_identity.unwrap_or(self.get_identity(IDENTITY_ROOT)?);
             let batch: Vec<BatchEntry> = vec![
@@ -150,9 +168,7 @@ impl LedgerStorage {
                     Op::Put(token_identity.to_vec()),
                 ),
             ];
-            self.persistent_store
-                .apply(batch.as_slice())
-                .map_err(error::storage_apply_failed)?;
+            self.apply(batch.as_slice())?;
 
             self.commit_storage()?;
         }
@@ -222,13 +238,11 @@ impl LedgerStorage {
         let mut symbols = self.get_symbols_and_tickers()?;
         symbols.insert(symbol, ticker);
         let symbols_key = b"/config/symbols".to_vec();
-        self.persistent_store
-            .apply(&[(
-                symbols_key.clone(),
-                Op::Put(minicbor::to_vec(&symbols).map_err(ManyError::serialization_error)?),
-            )])
-            .map_err(error::storage_apply_failed)
-            .map(|_| vec![symbols_key])
+        self.apply(&[(
+            symbols_key.clone(),
+            Op::Put(minicbor::to_vec(&symbols).map_err(ManyError::serialization_error)?),
+        )])
+        .map(|_| vec![symbols_key])
     }
 
     pub fn create_token(
@@ -243,8 +257,17 @@ impl LedgerStorage {
             maximum_supply,
             extended_info,
             memo,
+            dust,
         } = args;
 
+        // The dust config is stored along with the initial balances, so they
+        // are checked against it here.
+        if let (Some(dust), Some(distribution)) = (&dust, &initial_distribution) {
+            if let Some(amount) = distribution.values().find(|v| *v < &dust.threshold) {
+                return Err(error::amount_below_dust_threshold(amount, &dust.threshold));
+            }
+        }
+
         let mut keys: Vec<Vec<u8>> = vec![SYMBOLS_ROOT.into()];
 
         // Create a new token symbol and store in memory and in the persistent store
@@ -268,11 +291,19 @@ impl LedgerStorage {
             TokenAmount::zero()
         };
 
+        if let Some(dust) = &dust {
+            if let Some((key, op)) = self.dust_config_entry(&symbol, dust)? {
+                keys.push(key.clone());
+                batch.push((key, op));
+            }
+        }
+
         let supply = TokenInfoSupply {
             total: total_supply.clone(),
             circulating: total_supply,
             maximum: maximum_supply.clone(),
         };
+        batch.extend(self.supply_change_entries(&symbol, &supply, &TokenAmount::zero())?);
 
         // Create the token information and store it in the persistent storage
         let maybe_owner = owner.clone().map_or(Some(*sender), Either::left);
@@ -313,9 +344,7 @@ impl LedgerStorage {
         // while the `merk` Ops are sorted by String
         batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
 
-        self.persistent_store
-            .apply(batch.as_slice())
-            .map_err(error::storage_apply_failed)?;
+        self.apply(batch.as_slice())?;
 
         self.maybe_commit()
             .map(|_| (TokenCreateReturns { info }, keys))
@@ -356,6 +385,7 @@ impl LedgerStorage {
         Ok(TokenInfoReturns {
             info,
             extended_info: ext_info,
+            rules: self.token_rules(&symbol)?,
         })
     }
 
@@ -371,6 +401,7 @@ impl LedgerStorage {
             decimals,
             owner,
             memo,
+            dust,
         } = args;
         let mut keys: Vec<Vec<u8>> = vec![SYMBOLS_ROOT.into()];
 
@@ -405,12 +436,20 @@ impl LedgerStorage {
                 },
             };
 
-            self.persistent_store
-                .apply(&[(
-                    symbol_key.into(),
-                    Op::Put(minicbor::to_vec(&info).map_err(ManyError::serialization_error)?),
-                )])
-                .map_err(error::storage_apply_failed)?;
+            // Balances already below a new threshold are only checked when
+            // they next change.
+            let mut batch: Vec<BatchEntry> = vec![(
+                symbol_key.into(),
+                Op::Put(minicbor::to_vec(&info).map_err(ManyError::serialization_error)?),
+            )];
+            if let Some(dust) = &dust {
+                if let Some((key, op)) = self.dust_config_entry(&symbol, dust)? {
+                    keys.push(key.clone());
+                    batch.push((key, op));
+                }
+            }
+            batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+            self.apply(&batch)?;
 
             self.log_event(EventInfo::TokenUpdate {
                 symbol,
@@ -421,7 +460,8 @@ impl LedgerStorage {
                 memo,
             })?;
 
-            self.maybe_commit().map(|_| (TokenUpdateReturns {}, keys))
+            self.maybe_commit()
+                .map(|_| (TokenUpdateReturns::default(), keys))
         } else {
             Err(ManyError::unknown(format!(
                 "Symbol {symbol} not found in persistent storage"
@@ -460,12 +500,10 @@ impl LedgerStorage {
             indices.push(AttributeRelatedIndex::from(ExtendedInfoKey::VisualLogo));
         }
 
-        self.persistent_store
-            .apply(&[(
-                ext_info_key.clone(),
-                Op::Put(minicbor::to_vec(&ext_info).map_err(ManyError::serialization_error)?),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            ext_info_key.clone(),
+            Op::Put(minicbor::to_vec(&ext_info).map_err(ManyError::serialization_error)?),
+        )])?;
 
         self.log_event(EventInfo::TokenAddExtendedInfo {
             symbol,
@@ -474,7 +512,7 @@ impl LedgerStorage {
         })?;
 
         self.maybe_commit()
-            .map(|_| (TokenAddExtendedInfoReturns {}, ext_info_key))
+            .map(|_| (TokenAddExtendedInfoReturns::default(), ext_info_key))
     }
 
     pub fn remove_extended_info(
@@ -503,12 +541,10 @@ impl LedgerStorage {
             }
         }
 
-        self.persistent_store
-            .apply(&[(
-                ext_info_key.clone(),
-                Op::Put(minicbor::to_vec(&ext_info).map_err(ManyError::serialization_error)?),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            ext_info_key.clone(),
+            Op::Put(minicbor::to_vec(&ext_info).map_err(ManyError::serialization_error)?),
+        )])?;
 
         self.log_event(EventInfo::TokenRemoveExtendedInfo {
             symbol,
@@ -517,6 +553,6 @@ impl LedgerStorage {
         })?;
 
         self.maybe_commit()
-            .map(|_| (TokenRemoveExtendedInfoReturns {}, ext_info_key))
+            .map(|_| (TokenRemoveExtendedInfoReturns::default(), ext_info_key))
     }
 }
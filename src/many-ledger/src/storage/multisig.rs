@@ -2,6 +2,7 @@ use crate::error;
 use crate::migration::block_9400::Block9400Tx;
 use crate::migration::memo::MEMO_MIGRATION;
 use crate::module::account::validate_account;
+use crate::storage::delegation::is_approver;
 use crate::storage::event::EVENT_ID_KEY_SIZE_IN_BYTES;
 use crate::storage::LedgerStorage;
 use many_error::ManyError;
@@ -45,6 +46,7 @@ fn _execute_multisig_tx(
             symbol,
             amount,
             memo,
+            decimal_amount,
         }) => {
             // Use the `from` field to resolve the account sending the funds
             let from = from.ok_or_else(ManyError::invalid_from_identity)?;
@@ -56,6 +58,7 @@ fn _execute_multisig_tx(
                 [account::Role::CanLedgerTransact, account::Role::Owner],
             )?;
 
+            ledger.verify_decimal_amount(symbol, amount, decimal_amount.as_ref())?;
             ledger.send(&from, to, symbol, amount.clone(), memo.clone())?;
             minicbor::to_vec(EmptyReturn)
         }
@@ -209,9 +212,7 @@ impl LedgerStorage {
         if !batch.is_empty() {
             // Reverse the batch so keys are in sorted order.
             batch.reverse();
-            self.persistent_store
-                .apply(&batch)
-                .map_err(error::storage_apply_failed)?;
+            self.apply(&batch)?;
         }
 
         self.maybe_commit()
@@ -264,12 +265,10 @@ impl LedgerStorage {
         tx: &MultisigTransactionStorage,
     ) -> Result<(), ManyError> {
         debug!("{:?}", tx);
-        self.persistent_store
-            .apply(&[(
-                key_for_multisig_transaction(tx_id),
-                Op::Put(minicbor::to_vec(tx).map_err(ManyError::serialization_error)?),
-            )])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(
+            key_for_multisig_transaction(tx_id),
+            Op::Put(minicbor::to_vec(tx).map_err(ManyError::serialization_error)?),
+        )])?;
 
         self.maybe_commit()
     }
@@ -355,6 +354,7 @@ impl LedgerStorage {
                 timeout,
                 data_: data_.clone(),
                 state: account::features::multisig::MultisigTransactionState::Pending,
+                comments: None,
             },
             creation: self.now().as_system_time()?,
             disabled: false,
@@ -395,23 +395,34 @@ impl LedgerStorage {
 
         let (account, _) = self.get_account(&storage.account)?;
 
-        // Validate the right.
-        if !account.has_role(sender, account::Role::CanMultisigApprove)
-            && !account.has_role(sender, account::Role::CanMultisigSubmit)
-            && !account.has_role(sender, account::Role::Owner)
-        {
+        // Validate the right. The sender approves for itself if it can, and
+        // for the approvers who delegated to it.
+        let mut approvers = self.active_delegators(&storage.account, &account, sender)?;
+        if is_approver(&account, sender) {
+            approvers.insert(0, *sender);
+        }
+        if approvers.is_empty() {
             return Err(account::features::multisig::errors::user_cannot_approve_transaction());
         }
 
         // Update the entry.
-        storage.info.approvers.entry(*sender).or_default().approved = true;
+        for approver in &approvers {
+            storage
+                .info
+                .approvers
+                .entry(*approver)
+                .or_default()
+                .approved = true;
+        }
 
         self.commit_multisig_transaction(tx_id, &storage)?;
-        self.log_event(events::EventInfo::AccountMultisigApprove {
-            account: storage.account,
-            token: tx_id.to_vec().into(),
-            approver: *sender,
-        })?;
+        for approver in approvers {
+            self.log_event(events::EventInfo::AccountMultisigApprove {
+                account: storage.account,
+                token: tx_id.to_vec().into(),
+                approver,
+            })?;
+        }
 
         // If the transaction executes automatically, calculate number of approvers.
         if storage.info.execute_automatically && storage.should_execute() {
@@ -437,23 +448,25 @@ impl LedgerStorage {
         let (account, _) = self.get_account(&storage.account)?;
 
         // We make an exception here for people who already approved.
-        if let Some(info) = storage.info.approvers.get_mut(sender) {
-            info.approved = false;
-        } else if account.has_role(sender, account::Role::CanMultisigSubmit)
-            || account.has_role(sender, account::Role::CanMultisigApprove)
-            || account.has_role(sender, account::Role::Owner)
-        {
-            storage.info.approvers.entry(*sender).or_default().approved = false;
-        } else {
+        let mut revokers = self.active_delegators(&storage.account, &account, sender)?;
+        if storage.info.approvers.contains_key(sender) || is_approver(&account, sender) {
+            revokers.insert(0, *sender);
+        }
+        if revokers.is_empty() {
             return Err(account::features::multisig::errors::user_cannot_approve_transaction());
         }
+        for revoker in &revokers {
+            storage.info.approvers.entry(*revoker).or_default().approved = false;
+        }
 
         self.commit_multisig_transaction(tx_id, &storage)?;
-        self.log_event(events::EventInfo::AccountMultisigRevoke {
-            account: storage.account,
-            token: tx_id.to_vec().into(),
-            revoker: *sender,
-        })?;
+        for revoker in revokers {
+            self.log_event(events::EventInfo::AccountMultisigRevoke {
+                account: storage.account,
+                token: tx_id.to_vec().into(),
+                revoker,
+            })?;
+        }
         Ok(false)
     }
 
@@ -471,7 +484,13 @@ impl LedgerStorage {
         let (account, _) = self.get_account(&storage.account)?;
 
         // TODO: Better error message
-        if !(account.has_role(sender, account::Role::Owner) || storage.info.submitter == *sender) {
+        if !(account.has_role(sender, account::Role::Owner)
+            || storage.info.submitter == *sender
+            || self
+                .active_delegators(&storage.account, &account, sender)?
+                .iter()
+                .any(|delegator| account.has_role(delegator, account::Role::Owner)))
+        {
             return Err(account::features::multisig::errors::cannot_execute_transaction());
         }
 
@@ -514,6 +533,50 @@ impl LedgerStorage {
         Ok(())
     }
 
+    /// Add a comment to a pending transaction, returning its index.
+    pub fn comment_multisig(
+        &mut self,
+        sender: &Address,
+        args: account::features::multisig::CommentArgs,
+    ) -> Result<u64, ManyError> {
+        let tx_id = args.token.as_slice();
+        let mut storage = self.get_multisig_info(tx_id)?;
+        if storage.disabled {
+            return Err(account::features::multisig::errors::transaction_expired_or_withdrawn());
+        }
+
+        // Only those who can approve the transaction can comment on it.
+        let (account, _) = self.get_account(&storage.account)?;
+        if !account.has_role(sender, account::Role::CanMultisigApprove)
+            && !account.has_role(sender, account::Role::CanMultisigSubmit)
+            && !account.has_role(sender, account::Role::Owner)
+        {
+            return Err(account::features::multisig::errors::user_cannot_approve_transaction());
+        }
+
+        let comments = storage.info.comments.get_or_insert_with(Vec::new);
+        let id = comments.len() as u64;
+        if matches!(args.reply_to, Some(reply_to) if reply_to >= id) {
+            return Err(account::features::multisig::errors::comment_cannot_be_found());
+        }
+        comments.push(account::features::multisig::MultisigComment {
+            author: *sender,
+            timestamp: self.now(),
+            memo: args.memo.clone(),
+            reply_to: args.reply_to,
+        });
+
+        self.commit_multisig_transaction(tx_id, &storage)?;
+        self.log_event(events::EventInfo::AccountMultisigComment {
+            account: storage.account,
+            token: args.token,
+            author: *sender,
+            comment: id,
+            memo: Some(args.memo),
+        })?;
+        Ok(id)
+    }
+
     fn disable_multisig_transaction(
         &mut self,
         tx_id: &[u8],
@@ -528,9 +591,7 @@ impl LedgerStorage {
         let v =
             minicbor::to_vec(storage).map_err(|e| ManyError::serialization_error(e.to_string()))?;
 
-        self.persistent_store
-            .apply(&[(key_for_multisig_transaction(tx_id), Op::Put(v))])
-            .map_err(error::storage_apply_failed)?;
+        self.apply(&[(key_for_multisig_transaction(tx_id), Op::Put(v))])?;
 
         self.maybe_commit()
     }
@@ -0,0 +1,114 @@
+use crate::error;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{events, names};
+use many_types::Memo;
+use merk::Op;
+
+pub(crate) const NAMES_ROOT: &[u8] = b"/names/";
+
+pub(super) fn key_for_name(name: &str) -> Vec<u8> {
+    [NAMES_ROOT, name.as_bytes()].concat()
+}
+
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug)]
+#[cbor(map)]
+struct NameStorage {
+    #[n(0)]
+    owner: Address,
+
+    #[n(1)]
+    address: Address,
+}
+
+impl LedgerStorage {
+    fn get_name(&self, name: &str) -> Result<Option<NameStorage>, ManyError> {
+        self.persistent_store
+            .get(&key_for_name(name))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    fn put_name(&mut self, name: &str, record: &NameStorage) -> Result<(), ManyError> {
+        self.apply(&[(
+            key_for_name(name),
+            Op::Put(minicbor::to_vec(record).map_err(ManyError::serialization_error)?),
+        )])
+    }
+
+    pub fn register_name(
+        &mut self,
+        owner: &Address,
+        name: String,
+        address: Address,
+        memo: Option<Memo>,
+    ) -> Result<(), ManyError> {
+        names::validate_name(&name)?;
+        if address.is_illegal() {
+            return Err(ManyError::unknown(format!(
+                "Names cannot resolve to {address}."
+            )));
+        }
+        if self.get_name(&name)?.is_some() {
+            return Err(names::name_already_registered(name));
+        }
+
+        self.put_name(
+            &name,
+            &NameStorage {
+                owner: *owner,
+                address,
+            },
+        )?;
+        self.log_event(events::EventInfo::NameRegister {
+            name,
+            owner: *owner,
+            address,
+            memo,
+        })
+    }
+
+    pub fn resolve_name(&self, name: &str) -> Result<names::ResolveReturns, ManyError> {
+        let NameStorage { owner, address } = self
+            .get_name(name)?
+            .ok_or_else(|| names::name_not_found(name))?;
+        Ok(names::ResolveReturns { address, owner })
+    }
+
+    /// Transfer a name to a new owner. The name then resolves to the new owner.
+    pub fn transfer_name(
+        &mut self,
+        sender: &Address,
+        name: String,
+        new_owner: Address,
+        memo: Option<Memo>,
+    ) -> Result<(), ManyError> {
+        let record = self
+            .get_name(&name)?
+            .ok_or_else(|| names::name_not_found(&name))?;
+        if &record.owner != sender {
+            return Err(names::not_name_owner(name));
+        }
+        if new_owner.is_anonymous() || new_owner.is_illegal() {
+            return Err(ManyError::unknown(format!(
+                "Names cannot be owned by {new_owner}."
+            )));
+        }
+
+        self.put_name(
+            &name,
+            &NameStorage {
+                owner: new_owner,
+                address: new_owner,
+            },
+        )?;
+        self.log_event(events::EventInfo::NameTransfer {
+            name,
+            owner: record.owner,
+            new_owner,
+            memo,
+        })
+    }
+}
@@ -0,0 +1,191 @@
+use crate::error;
+use crate::storage::event::{
+    key_for_event, key_for_event_id, EVENT_COUNT_ROOT, HEIGHT_EVENTID_SHIFT,
+};
+use crate::storage::event_index::index_keys_for_event;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::{InnerStorage, LedgerStorage, HEIGHT_ROOT};
+use many_error::ManyError;
+use many_modules::events::{EventId, EventLog, EVENT_CHAIN_GENESIS};
+use many_types::{CborRange, SortOrder};
+use merk::rocksdb;
+use merk::{BatchEntry, Op};
+use std::path::Path;
+use tracing::info;
+
+/// The running hash of the event log after the last event of each pruned
+/// segment, keyed by the ID of that event. Proofs of the events following a
+/// segment start from it, so pruning does not break the event chain.
+pub(crate) const EVENTS_PRUNED_ROOT: &[u8] = b"/events_pruned/";
+
+/// Returns the storage key for the chain head of a pruned segment ending with
+/// an event.
+pub(crate) fn key_for_pruned_segment(id: EventId) -> Vec<u8> {
+    key_for_event_id(EVENTS_PRUNED_ROOT, id)
+}
+
+/// The key and chain head of the last pruned segment, if any.
+pub(crate) fn last_pruned_segment(
+    store: &InnerStorage,
+) -> Result<Option<(Vec<u8>, Vec<u8>)>, ManyError> {
+    LedgerIterator::scoped_by_event_id(
+        store,
+        EVENTS_PRUNED_ROOT,
+        CborRange::default(),
+        SortOrder::Descending,
+    )
+    .next()
+    .transpose()
+    .map(|item| item.map(|(k, v)| (k.to_vec(), v)))
+    .map_err(error::storage_get_failed)
+}
+
+/// Which events to keep when pruning. An event is pruned if it falls outside
+/// of any of the configured windows. Nothing is pruned if both are `None`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventRetention {
+    /// Keep the events logged in the last `height` blocks.
+    pub height: Option<u64>,
+
+    /// Keep the last `count` events.
+    pub count: Option<u64>,
+}
+
+impl EventRetention {
+    pub fn is_empty(&self) -> bool {
+        self.height.is_none() && self.count.is_none()
+    }
+}
+
+fn get_u64(store: &InnerStorage, key: &[u8]) -> Result<u64, ManyError> {
+    Ok(store
+        .get(key)
+        .map_err(error::storage_get_failed)?
+        .map_or(0u64, |x| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(x.as_slice());
+            u64::from_be_bytes(bytes)
+        }))
+}
+
+/// Delete the events of the store outside of the retention window, and update
+/// the event count accordingly. The running hash after the last deleted event
+/// is kept as the head of the pruned segment. The changes are applied but not
+/// committed. Returns the number of events deleted.
+///
+/// This only depends on the content of the store, so every node pruning with
+/// the same retention at the same height ends up with the same state hash.
+pub(crate) fn prune_events(
+    store: &mut InnerStorage,
+    retention: &EventRetention,
+) -> Result<u64, ManyError> {
+    if retention.is_empty() {
+        return Ok(0);
+    }
+
+    let height = get_u64(store, HEIGHT_ROOT.as_bytes())?;
+    let nb_events = get_u64(store, EVENT_COUNT_ROOT)?;
+
+    // Events logged before this height are out of the height window. Event
+    // IDs are prefixed by the height, so their keys sort the same way.
+    let height_bound = retention.height.map(|h| {
+        key_for_event(EventId::from(
+            height.saturating_sub(h) << HEIGHT_EVENTID_SHIFT,
+        ))
+    });
+    let count_bound = retention.count.map(|c| nb_events.saturating_sub(c));
+
+    let mut batch: Vec<BatchEntry> = Vec::new();
+    let mut index_keys = Vec::new();
+    let mut last = None;
+    for (i, item) in LedgerIterator::all_events(store).enumerate() {
+        let (key, value) = item.map_err(error::storage_get_failed)?;
+        let below_height = height_bound.as_ref().map_or(false, |b| key.as_ref() < b);
+        let below_count = count_bound.map_or(false, |c| (i as u64) < c);
+        if !below_height && !below_count {
+            // Keys are iterated in ascending order, so all remaining events
+            // are retained.
+            break;
+        }
+        batch.push((key.to_vec(), Op::Delete));
+
+        let event: EventLog = minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+        index_keys.extend(index_keys_for_event(&event));
+        last = Some(event);
+    }
+
+    let pruned = batch.len() as u64;
+    let last = match last {
+        Some(last) => last,
+        None => return Ok(0),
+    };
+
+    // Unchained events are followed by the start of the chain.
+    let chain_head = last
+        .chain_hash
+        .map_or_else(|| EVENT_CHAIN_GENESIS.to_vec(), |hash| hash.to_vec());
+    batch.push((key_for_pruned_segment(last.id), Op::Put(chain_head)));
+
+    // The events are only indexed once the Event Index Migration is active,
+    // so only delete the index keys that exist.
+    for key in index_keys {
+        if store
+            .get(&key)
+            .map_err(error::storage_get_failed)?
+            .is_some()
+        {
+            batch.push((key, Op::Delete));
+        }
+    }
+
+    batch.push((
+        EVENT_COUNT_ROOT.to_vec(),
+        Op::Put(nb_events.saturating_sub(pruned).to_be_bytes().to_vec()),
+    ));
+    batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    store.apply(&batch).map_err(error::storage_apply_failed)?;
+
+    info!("Pruned {pruned} event(s) at height {height}");
+    Ok(pruned)
+}
+
+/// Compact the RocksDB database of a persistent store, so the space of pruned
+/// keys is reclaimed now rather than by the background compactions. The store
+/// must not be open. This does not change the state hash.
+pub fn compact_store<P: AsRef<Path>>(path: P) -> Result<(), ManyError> {
+    let options = rocksdb::Options::default();
+    let cfs = rocksdb::DB::list_cf(&options, &path).map_err(error::storage_open_failed)?;
+    let db = rocksdb::DB::open_cf(&options, &path, &cfs).map_err(error::storage_open_failed)?;
+    for name in &cfs {
+        if let Some(cf) = db.cf_handle(name) {
+            db.compact_range_cf(cf, None::<&[u8]>, None::<&[u8]>);
+        }
+    }
+    info!(
+        "Compacted the persistent store at {}",
+        path.as_ref().display()
+    );
+    Ok(())
+}
+
+impl LedgerStorage {
+    /// The key and chain head of the last pruned segment of the event log, if
+    /// any.
+    pub fn last_pruned_segment(&self) -> Result<Option<(Vec<u8>, Vec<u8>)>, ManyError> {
+        last_pruned_segment(&self.persistent_store)
+    }
+
+    /// Prune the events outside of the retention window, then commit.
+    ///
+    /// Blockchain nodes must not call this directly, as the state hash would
+    /// diverge from the other nodes. They should use the Event Pruning
+    /// Migration instead, which prunes at the same height on all nodes.
+    pub fn prune_events(&mut self, retention: &EventRetention) -> Result<u64, ManyError> {
+        self.flush_writes()?;
+        let pruned = prune_events(self.persistent_store.flushed_mut(), retention)?;
+        if pruned > 0 {
+            self.commit_storage()?;
+        }
+        Ok(pruned)
+    }
+}
@@ -0,0 +1,226 @@
+use crate::error;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::features::pull_payments::PullPaymentsAccountFeature;
+use many_modules::account::Role;
+use many_modules::{events, ledger};
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Timestamp;
+use merk::Op;
+
+pub(crate) const PULL_AUTHORIZATIONS_ROOT: &str = "/pull_authorizations/";
+
+/// Returns the storage key for the authorization of `payee` to pull `symbol`
+/// from `account`.
+pub(super) fn key_for_pull_authorization(
+    account: &Address,
+    payee: &Address,
+    symbol: &Symbol,
+) -> Vec<u8> {
+    format!("{PULL_AUTHORIZATIONS_ROOT}{account}/{payee}/{symbol}").into_bytes()
+}
+
+/// An authorization to pull tokens from an account, with the amount already
+/// pulled in the current period.
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PullAuthorization {
+    /// The maximum amount that can be pulled per period.
+    #[n(0)]
+    pub amount: TokenAmount,
+
+    #[n(1)]
+    pub period_in_secs: u64,
+
+    /// When the first period started. Periods follow each other from there.
+    #[n(2)]
+    pub start: Timestamp,
+
+    /// The index of the period of the last pull, counting from `start`.
+    #[n(3)]
+    pub period: u64,
+
+    /// The amount pulled during `period`.
+    #[n(4)]
+    pub pulled: TokenAmount,
+}
+
+impl PullAuthorization {
+    /// The index of the period at `now`.
+    pub fn period_at(&self, now: Timestamp) -> u64 {
+        now.secs().saturating_sub(self.start.secs()) / self.period_in_secs
+    }
+
+    /// The amount that can still be pulled at `now`.
+    pub fn remaining_at(&self, now: Timestamp) -> TokenAmount {
+        if self.period_at(now) == self.period {
+            &self.amount - &self.pulled
+        } else {
+            self.amount.clone()
+        }
+    }
+}
+
+impl LedgerStorage {
+    pub fn get_pull_authorization(
+        &self,
+        account: &Address,
+        payee: &Address,
+        symbol: &Symbol,
+    ) -> Result<Option<PullAuthorization>, ManyError> {
+        self.persistent_store
+            .get(&key_for_pull_authorization(account, payee, symbol))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    fn put_pull_authorization(
+        &mut self,
+        account: &Address,
+        payee: &Address,
+        symbol: &Symbol,
+        authorization: &PullAuthorization,
+    ) -> Result<(), ManyError> {
+        self.apply(&[(
+            key_for_pull_authorization(account, payee, symbol),
+            Op::Put(minicbor::to_vec(authorization).map_err(ManyError::serialization_error)?),
+        )])
+    }
+
+    pub fn authorize_pull(
+        &mut self,
+        sender: &Address,
+        args: ledger::AuthorizePullArgs,
+    ) -> Result<(), ManyError> {
+        let ledger::AuthorizePullArgs {
+            account,
+            payee,
+            symbol,
+            amount,
+            period_in_secs,
+        } = args;
+
+        let (account_info, _) = self.get_account(&account)?;
+        account_info.features.get::<PullPaymentsAccountFeature>()?;
+        account_info.needs_role(sender, [Role::Owner])?;
+
+        if payee == account {
+            return Err(error::destination_is_source());
+        }
+        if payee.is_anonymous() {
+            return Err(error::anonymous_cannot_hold_funds());
+        }
+        if amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        if period_in_secs == 0 {
+            return Err(error::invalid_pull_period());
+        }
+        if !self.get_symbols()?.contains(&symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+
+        let authorization = PullAuthorization {
+            amount: amount.clone(),
+            period_in_secs,
+            start: self.now(),
+            period: 0,
+            pulled: TokenAmount::zero(),
+        };
+        self.put_pull_authorization(&account, &payee, &symbol, &authorization)?;
+
+        self.log_event(events::EventInfo::PullAuthorized {
+            account,
+            payee,
+            symbol,
+            amount,
+            period_in_secs,
+        })?;
+        self.maybe_commit()
+    }
+
+    pub fn revoke_pull(
+        &mut self,
+        sender: &Address,
+        args: ledger::RevokePullArgs,
+    ) -> Result<(), ManyError> {
+        let ledger::RevokePullArgs {
+            account,
+            payee,
+            symbol,
+        } = args;
+
+        if *sender != payee {
+            let (account_info, _) = self.get_account_even_disabled(&account)?;
+            account_info.needs_role(sender, [Role::Owner])?;
+        }
+        if self
+            .get_pull_authorization(&account, &payee, &symbol)?
+            .is_none()
+        {
+            return Err(error::pull_not_authorized(account, symbol));
+        }
+
+        self.apply(&[(
+            key_for_pull_authorization(&account, &payee, &symbol),
+            Op::Delete,
+        )])?;
+
+        self.log_event(events::EventInfo::PullRevoked {
+            account,
+            payee,
+            symbol,
+            revoker: *sender,
+        })?;
+        self.maybe_commit()
+    }
+
+    /// Send tokens from an account to the sender, within the authorization
+    /// of the sender for the current period.
+    pub fn pull_payment(
+        &mut self,
+        sender: &Address,
+        args: ledger::PullPaymentArgs,
+    ) -> Result<(), ManyError> {
+        let ledger::PullPaymentArgs {
+            account,
+            symbol,
+            amount,
+            memo,
+        } = args;
+
+        let mut authorization = self
+            .get_pull_authorization(&account, sender, &symbol)?
+            .ok_or_else(|| error::pull_not_authorized(account, symbol))?;
+        // The feature cannot be removed, but the account can be disabled.
+        self.get_account(&account)?;
+
+        let now = self.now();
+        let remaining = authorization.remaining_at(now);
+        if amount > remaining {
+            return Err(error::pull_limit_exceeded(amount, remaining));
+        }
+
+        self.send(&account, sender, &symbol, amount.clone(), memo.clone())?;
+
+        let period = authorization.period_at(now);
+        if period != authorization.period {
+            authorization.period = period;
+            authorization.pulled = TokenAmount::zero();
+        }
+        authorization.pulled += &amount;
+        self.put_pull_authorization(&account, sender, &symbol, &authorization)?;
+
+        self.log_event(events::EventInfo::PullPayment {
+            account,
+            payee: *sender,
+            symbol,
+            amount,
+            period,
+            memo,
+        })?;
+        self.maybe_commit()
+    }
+}
@@ -0,0 +1,39 @@
+use crate::error;
+use crate::storage::batch::BatchedStore;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_types::clock::SystemClock;
+use many_types::proof::cache::ProofCache;
+use std::path::Path;
+
+impl LedgerStorage {
+    /// Open a copy of the committed state of the storage, checkpointed in
+    /// `path`. The copy does not see the changes of the block in flight, or
+    /// any later block, so it can serve queries while commands execute on
+    /// this storage. It must only be used for queries.
+    pub fn open_query_snapshot(&self, path: &Path) -> Result<Self, ManyError> {
+        let persistent_store = self
+            .persistent_store
+            .checkpoint(path)
+            .map_err(error::storage_checkpoint_failed)?;
+
+        Ok(Self {
+            persistent_store: BatchedStore::new(persistent_store),
+            // Never commit anything to the copy outside of a block.
+            blockchain: true,
+            latest_tid: self.latest_tid.clone(),
+            logged_events: vec![],
+            current_time: self.current_time,
+            current_hash: self.current_hash.clone(),
+            clock: Box::new(SystemClock),
+            migrations: self.migrations.clone(),
+            checkpoints: None,
+            journal: None,
+            command_journal: None,
+            integrity_check_interval: None,
+            integrity_check_indices: false,
+            quarantine: self.quarantine.clone(),
+            proof_cache: ProofCache::default(),
+        })
+    }
+}
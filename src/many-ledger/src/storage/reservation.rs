@@ -0,0 +1,196 @@
+use crate::error;
+use crate::storage::{key_for_account_balance, key_for_token, LedgerStorage};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::events::EventInfo;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Memo;
+use merk::{BatchEntry, Op};
+use minicbor::bytes::ByteVec;
+use tracing::info;
+
+pub(crate) const RESERVATIONS_ROOT: &[u8] = b"/reservations/";
+
+/// Returns the storage key for a reservation.
+pub(super) fn key_for_reservation(id: &[u8]) -> Vec<u8> {
+    key_for_token(RESERVATIONS_ROOT, id)
+}
+
+/// Funds that were taken out of the `from` balance, pending a commit (which
+/// credits `to`) or an abort (which credits `from` back).
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug, Eq, PartialEq)]
+#[cbor(map)]
+pub struct Reservation {
+    #[n(0)]
+    pub from: Address,
+
+    #[n(1)]
+    pub to: Address,
+
+    #[n(2)]
+    pub symbol: Symbol,
+
+    #[n(3)]
+    pub amount: TokenAmount,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+}
+
+/// Two-phase transfers. Coordinators (e.g. swaps or escrows) first prepare a
+/// transfer, which reserves the funds so they cannot be spent elsewhere, then
+/// either commit or abort it. Each step logs an event, and a reservation takes
+/// the ID of the event preparing it.
+///
+/// Authorization is the responsibility of the caller.
+impl LedgerStorage {
+    pub fn get_reservation(&self, id: &[u8]) -> Result<Reservation, ManyError> {
+        let bytes = self
+            .persistent_store
+            .get(&key_for_reservation(id))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| error::reservation_not_found(hex::encode(id)))?;
+
+        minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+    }
+
+    /// Reserve `amount` of `symbol` from `from`, to be sent to `to` on commit.
+    /// Returns the reservation ID and the keys that were modified.
+    pub fn prepare_transfer(
+        &mut self,
+        from: &Address,
+        to: &Address,
+        symbol: &Symbol,
+        amount: TokenAmount,
+        memo: Option<Memo>,
+    ) -> Result<(ByteVec, Vec<Vec<u8>>), ManyError> {
+        if from == to {
+            return Err(error::destination_is_source());
+        }
+        if amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        if to.is_anonymous() || from.is_anonymous() {
+            return Err(error::anonymous_cannot_hold_funds());
+        }
+        if !self.get_symbols()?.contains(symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+
+        let mut amount_from = self.get_balance(from, symbol)?;
+        if amount > amount_from {
+            return Err(error::insufficient_funds());
+        }
+        self.verify_unlocked(from, symbol, &amount, &amount_from)?;
+        self.verify_token_rules(from, to, symbol, &amount)?;
+        amount_from -= amount.clone();
+
+        info!(
+            "prepare_transfer({} => {}, {} {})",
+            from, to, &amount, symbol
+        );
+
+        self.log_event(EventInfo::TransferPrepared {
+            from: *from,
+            to: *to,
+            symbol: *symbol,
+            amount: amount.clone(),
+            memo: memo.clone(),
+        })?;
+        let id: ByteVec = self.latest_tid.clone().into();
+        let reservation = Reservation {
+            from: *from,
+            to: *to,
+            symbol: *symbol,
+            amount,
+            memo,
+        };
+
+        // Keys in batch must be sorted, and balances are before reservations.
+        // Reserved amounts are exact, so a remainder below the dust threshold
+        // is refused whatever the policy of the symbol.
+        let key_from = key_for_account_balance(from, symbol);
+        let key_reservation = key_for_reservation(&id);
+        self.apply(&[
+            self.balance_entry(from, symbol, &amount_from)?,
+            (
+                key_reservation.clone(),
+                Op::Put(minicbor::to_vec(&reservation).map_err(ManyError::serialization_error)?),
+            ),
+        ])?;
+
+        self.maybe_commit()?;
+        Ok((id, vec![key_from, key_reservation]))
+    }
+
+    /// Release the funds of a reservation to `address`, removing it.
+    fn release_reservation(
+        &mut self,
+        id: &[u8],
+        address: &Address,
+        reservation: &Reservation,
+    ) -> Result<Vec<Vec<u8>>, ManyError> {
+        let mut amount = self.get_balance(address, &reservation.symbol)?;
+        amount += reservation.amount.clone();
+
+        let key_balance = key_for_account_balance(address, &reservation.symbol);
+        let key_reservation = key_for_reservation(id);
+        let batch: Vec<BatchEntry> = vec![
+            self.balance_entry(address, &reservation.symbol, &amount)?,
+            (key_reservation.clone(), Op::Delete),
+        ];
+        self.apply(&batch)?;
+
+        Ok(vec![key_balance, key_reservation])
+    }
+
+    /// Commit a prepared transfer, crediting its destination.
+    pub fn commit_transfer(&mut self, id: &[u8]) -> Result<Vec<Vec<u8>>, ManyError> {
+        let reservation = self.get_reservation(id)?;
+        // The rules of the symbol can change while the transfer is prepared.
+        self.verify_token_rules(
+            &reservation.from,
+            &reservation.to,
+            &reservation.symbol,
+            &reservation.amount,
+        )?;
+        info!(
+            "commit_transfer({} => {}, {} {})",
+            reservation.from, reservation.to, &reservation.amount, reservation.symbol
+        );
+
+        let keys = self.release_reservation(id, &reservation.to, &reservation)?;
+        // The send comes last, right before the event of the coordinator.
+        self.log_event(EventInfo::TransferCommitted {
+            id: id.to_vec().into(),
+            from: reservation.from,
+            to: reservation.to,
+        })?;
+        self.log_event(EventInfo::Send {
+            from: reservation.from,
+            to: reservation.to,
+            symbol: reservation.symbol,
+            amount: reservation.amount,
+            memo: reservation.memo,
+        })?;
+
+        self.maybe_commit().map(|_| keys)
+    }
+
+    /// Abort a prepared transfer, returning the funds to their source.
+    pub fn abort_transfer(&mut self, id: &[u8]) -> Result<Vec<Vec<u8>>, ManyError> {
+        let reservation = self.get_reservation(id)?;
+        info!(
+            "abort_transfer({} => {}, {} {})",
+            reservation.from, reservation.to, &reservation.amount, reservation.symbol
+        );
+
+        let keys = self.release_reservation(id, &reservation.from, &reservation)?;
+        self.log_event(EventInfo::TransferAborted {
+            id: id.to_vec().into(),
+            from: reservation.from,
+            to: reservation.to,
+        })?;
+        self.maybe_commit().map(|_| keys)
+    }
+}
@@ -0,0 +1,266 @@
+use crate::error;
+use crate::module::account::verify_account_role;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::{key_for_token, LedgerStorage};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::account::features::TryCreateFeature;
+use many_modules::account::Role;
+use many_modules::{account, events, ledger};
+use many_types::SortOrder;
+use merk::Op;
+use minicbor::bytes::ByteVec;
+
+pub(crate) const SCHEDULED_TRANSACTIONS_ROOT: &[u8] = b"/scheduled/";
+
+pub(crate) const SCHEDULED_BY_HEIGHT_ROOT: &[u8] = b"/scheduled_due/height/";
+pub(crate) const SCHEDULED_BY_TIME_ROOT: &[u8] = b"/scheduled_due/time/";
+
+/// Returns the storage key for a scheduled transaction.
+pub(super) fn key_for_scheduled_transaction(token: &[u8]) -> Vec<u8> {
+    key_for_token(SCHEDULED_TRANSACTIONS_ROOT, token)
+}
+
+/// Returns the key of a scheduled transaction in the index of the transactions
+/// due at a height or a time. The big endian height or time keeps the index in
+/// due order, so a block only reads the transactions due at it.
+fn key_for_due_transaction(root: &[u8], due: u64, token: &[u8]) -> Vec<u8> {
+    [root, &due.to_be_bytes(), token].concat()
+}
+
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug)]
+#[cbor(map)]
+pub struct ScheduledTransactionStorage {
+    #[n(0)]
+    pub token: ByteVec,
+
+    /// The identity that scheduled the transaction. Authorization is checked
+    /// against this identity when the transaction executes.
+    #[n(1)]
+    pub sender: Address,
+
+    #[n(2)]
+    pub args: ledger::ScheduleArgs,
+}
+
+impl ScheduledTransactionStorage {
+    pub fn from(&self) -> Address {
+        self.args.send.from.unwrap_or(self.sender)
+    }
+
+    /// The key of this transaction in the index of due transactions.
+    fn due_key(&self) -> Vec<u8> {
+        match (self.args.height, self.args.time) {
+            (Some(h), _) => key_for_due_transaction(SCHEDULED_BY_HEIGHT_ROOT, h, &self.token),
+            (_, Some(t)) => key_for_due_transaction(SCHEDULED_BY_TIME_ROOT, t.secs(), &self.token),
+            (None, None) => key_for_due_transaction(SCHEDULED_BY_HEIGHT_ROOT, 0, &self.token),
+        }
+    }
+}
+
+/// Verify that `sender` can send funds on behalf of `from` with `method`.
+pub(crate) fn verify_can_send(
+    storage: &LedgerStorage,
+    sender: &Address,
+    from: &Address,
+    method: &str,
+) -> Result<(), ManyError> {
+    if from.is_illegal() {
+        return Err(error::unauthorized());
+    }
+    if from != sender {
+        let (account, _) = storage
+            .get_account(from)
+            .map_err(|_| error::unauthorized())?;
+        verify_account_role(
+            &account,
+            sender,
+            method,
+            account::features::ledger::AccountLedger::ID,
+            [Role::CanLedgerTransact],
+        )?;
+    }
+    Ok(())
+}
+
+impl LedgerStorage {
+    pub fn iter_scheduled(&self, order: SortOrder) -> LedgerIterator {
+        LedgerIterator::all_scheduled(&self.persistent_store, order)
+    }
+
+    pub fn get_scheduled_transaction(
+        &self,
+        token: &[u8],
+    ) -> Result<ScheduledTransactionStorage, ManyError> {
+        let bytes = self
+            .persistent_store
+            .get(&key_for_scheduled_transaction(token))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| error::scheduled_transaction_not_found(hex::encode(token)))?;
+
+        minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+    }
+
+    pub fn schedule_transaction(
+        &mut self,
+        sender: &Address,
+        args: ledger::ScheduleArgs,
+    ) -> Result<ByteVec, ManyError> {
+        match (args.height, args.time) {
+            (Some(height), None) => {
+                // The block being executed is at `get_height() + 1`.
+                if height <= self.get_height()? {
+                    return Err(error::schedule_in_the_past());
+                }
+            }
+            (None, Some(time)) => {
+                if time < self.now() {
+                    return Err(error::schedule_in_the_past());
+                }
+            }
+            _ => return Err(error::invalid_schedule()),
+        }
+
+        let ledger::SendArgs {
+            from,
+            to,
+            amount,
+            symbol,
+            memo,
+            decimal_amount,
+        } = &args.send;
+        let from = from.unwrap_or(*sender);
+        verify_can_send(self, sender, &from, "ledger.schedule")?;
+        self.verify_decimal_amount(symbol, amount, decimal_amount.as_ref())?;
+
+        if from == *to {
+            return Err(error::destination_is_source());
+        }
+        if amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        if to.is_anonymous() || from.is_anonymous() {
+            return Err(error::anonymous_cannot_hold_funds());
+        }
+        if !self.get_symbols()?.contains(symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+
+        let token: ByteVec = self.new_event_id().into();
+        let event = events::EventInfo::SendScheduled {
+            token: token.clone(),
+            from,
+            to: *to,
+            symbol: *symbol,
+            amount: amount.clone(),
+            height: args.height,
+            time: args.time,
+            memo: memo.clone(),
+        };
+
+        let storage = ScheduledTransactionStorage {
+            token: token.clone(),
+            sender: *sender,
+            args,
+        };
+        // The transaction sorts before its index entry.
+        self.apply(&[
+            (
+                key_for_scheduled_transaction(&token),
+                Op::Put(minicbor::to_vec(&storage).map_err(ManyError::serialization_error)?),
+            ),
+            (storage.due_key(), Op::Put(token.to_vec())),
+        ])?;
+
+        self.log_event(event)?;
+        self.maybe_commit()?;
+        Ok(token)
+    }
+
+    pub fn cancel_scheduled_transaction(
+        &mut self,
+        sender: &Address,
+        token: &[u8],
+    ) -> Result<(), ManyError> {
+        let storage = self.get_scheduled_transaction(token)?;
+        if storage.sender != *sender {
+            return Err(error::unauthorized());
+        }
+
+        self.apply(&[
+            (key_for_scheduled_transaction(token), Op::Delete),
+            (storage.due_key(), Op::Delete),
+        ])?;
+
+        self.log_event(events::EventInfo::ScheduledSendCancelled {
+            token: storage.token.clone(),
+            from: storage.from(),
+            canceller: *sender,
+        })?;
+        self.maybe_commit()
+    }
+
+    fn execute_scheduled_transaction(
+        &mut self,
+        storage: &ScheduledTransactionStorage,
+    ) -> Result<(), ManyError> {
+        let from = storage.from();
+        // Roles might have changed since the transaction was scheduled.
+        verify_can_send(self, &storage.sender, &from, "ledger.schedule")?;
+
+        let ledger::SendArgs {
+            to,
+            amount,
+            symbol,
+            memo,
+            ..
+        } = &storage.args.send;
+        self.send(&from, to, symbol, amount.clone(), memo.clone())
+            .map(|_| ())
+    }
+
+    /// Execute all scheduled transactions that are due at the block being
+    /// committed, in the order they were scheduled. Each execution is removed
+    /// from the queue whether it succeeded or not, and its result is recorded
+    /// in an event.
+    pub fn process_scheduled_transactions(&mut self) -> Result<(), ManyError> {
+        let height = self.get_height()? + 1;
+        let now = self.now();
+
+        let mut tokens = vec![];
+        for (root, due) in [
+            (SCHEDULED_BY_HEIGHT_ROOT, height),
+            (SCHEDULED_BY_TIME_ROOT, now.secs()),
+        ] {
+            let it = LedgerIterator::key_range(
+                &self.persistent_store,
+                root.to_vec(),
+                Some(key_for_due_transaction(root, due + 1, &[])),
+            );
+            for item in it {
+                let (_, token) = item.map_err(ManyError::unknown)?;
+                tokens.push(token);
+            }
+        }
+        tokens.sort_by_key(|token| key_for_scheduled_transaction(token));
+
+        for token in tokens {
+            let storage = self.get_scheduled_transaction(&token)?;
+            let result = self.execute_scheduled_transaction(&storage);
+
+            self.apply(&[
+                (key_for_scheduled_transaction(&token), Op::Delete),
+                (storage.due_key(), Op::Delete),
+            ])?;
+
+            self.log_event(events::EventInfo::ScheduledSendExecuted {
+                token: storage.token.clone(),
+                from: storage.from(),
+                to: storage.args.send.to,
+                error: result.err(),
+            })?;
+        }
+
+        self.maybe_commit()
+    }
+}
@@ -0,0 +1,267 @@
+use crate::error;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::{InnerStorage, LedgerStorage, BALANCES_ROOT, HEIGHT_ROOT, SYMBOLS_ROOT};
+use many_error::ManyError;
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::SortOrder;
+use merk::proofs::query::QueryItem;
+use merk::proofs::{Decoder, Node, Op};
+use merk::tree::{kv_hash, node_hash, Hash, NULL_HASH};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// An export of the balances of a ledger at a given height, with a Merk
+/// proof of the balances against the state hash of the ledger at that height.
+/// It is used to seed the genesis balances of another network, e.g. when
+/// relaunching or forking a chain, without trusting the file itself.
+///
+/// The proof shows that every balance of the snapshot is part of the source
+/// state, but not that no balance was left out; compare the totals with the
+/// supply of the tokens on the source network for that.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Eq, PartialEq)]
+pub struct BalancesSnapshot {
+    /// The height of the source ledger.
+    pub height: u64,
+
+    /// The state hash of the source ledger, in hexadecimal.
+    pub hash: String,
+
+    /// The Merk proof of the height, symbols and balances, in hexadecimal.
+    pub proof: String,
+}
+
+/// The content of a verified [`BalancesSnapshot`].
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SnapshotBalances {
+    pub height: u64,
+    pub symbols: BTreeMap<Symbol, String>,
+    pub balances: BTreeMap<Address, BTreeMap<Symbol, TokenAmount>>,
+}
+
+/// A node of the tree rebuilt from a proof. Pruned subtrees are only known by
+/// their hash.
+enum ProofNode {
+    Pruned(Hash),
+    Node {
+        kv_hash: Hash,
+        left: Option<Hash>,
+        right: Option<Hash>,
+    },
+}
+
+impl ProofNode {
+    fn hash(&self) -> Hash {
+        match self {
+            ProofNode::Pruned(hash) => *hash,
+            ProofNode::Node {
+                kv_hash,
+                left,
+                right,
+            } => node_hash(
+                kv_hash,
+                left.as_ref().unwrap_or(&NULL_HASH),
+                right.as_ref().unwrap_or(&NULL_HASH),
+            ),
+        }
+    }
+
+    fn attach(&mut self, is_left: bool, child: ProofNode) -> Result<(), ManyError> {
+        let ProofNode::Node { left, right, .. } = self else {
+            return Err(error::invalid_snapshot(
+                "A pruned node cannot have children",
+            ));
+        };
+        let slot = if is_left { left } else { right };
+        if slot.is_some() {
+            return Err(error::invalid_snapshot("A node has two children on a side"));
+        }
+        *slot = Some(child.hash());
+        Ok(())
+    }
+}
+
+fn pop(stack: &mut Vec<ProofNode>) -> Result<ProofNode, ManyError> {
+    stack
+        .pop()
+        .ok_or_else(|| error::invalid_snapshot("The proof is truncated"))
+}
+
+/// Execute a Merk proof, returning the root hash of the tree and the key
+/// value pairs it reveals.
+pub(crate) fn execute_proof(proof: &[u8]) -> Result<(Hash, BTreeMap<Vec<u8>, Vec<u8>>), ManyError> {
+    execute_ops(Decoder::new(proof).map(|op| op.map_err(error::invalid_snapshot)))
+}
+
+/// Execute the decoded operations of a Merk proof.
+pub(crate) fn execute_ops(
+    ops: impl IntoIterator<Item = Result<Op, ManyError>>,
+) -> Result<(Hash, BTreeMap<Vec<u8>, Vec<u8>>), ManyError> {
+    let mut stack: Vec<ProofNode> = Vec::new();
+    let mut entries = BTreeMap::new();
+    let mut last_key: Option<Vec<u8>> = None;
+
+    for op in ops {
+        match op? {
+            Op::Push(Node::Hash(hash)) => stack.push(ProofNode::Pruned(hash)),
+            Op::Push(Node::KVHash(hash)) => stack.push(ProofNode::Node {
+                kv_hash: hash,
+                left: None,
+                right: None,
+            }),
+            Op::Push(Node::KV(key, value)) => {
+                if last_key.as_ref().map_or(false, |last| *last >= key) {
+                    return Err(error::invalid_snapshot(
+                        "The keys of the proof are not sorted",
+                    ));
+                }
+                stack.push(ProofNode::Node {
+                    kv_hash: kv_hash(&key, &value),
+                    left: None,
+                    right: None,
+                });
+                last_key = Some(key.clone());
+                entries.insert(key, value);
+            }
+            Op::Parent => {
+                let mut parent = pop(&mut stack)?;
+                let child = pop(&mut stack)?;
+                parent.attach(true, child)?;
+                stack.push(parent);
+            }
+            Op::Child => {
+                let child = pop(&mut stack)?;
+                let mut parent = pop(&mut stack)?;
+                parent.attach(false, child)?;
+                stack.push(parent);
+            }
+        }
+    }
+
+    match stack.as_slice() {
+        [root] => Ok((root.hash(), entries)),
+        _ => Err(error::invalid_snapshot(
+            "The proof does not have a single root",
+        )),
+    }
+}
+
+impl BalancesSnapshot {
+    /// Export the balances of a committed store.
+    pub fn create(store: &InnerStorage) -> Result<Self, ManyError> {
+        let mut keys = vec![
+            HEIGHT_ROOT.as_bytes().to_vec(),
+            SYMBOLS_ROOT.as_bytes().to_vec(),
+        ];
+        for item in LedgerIterator::all_balances(store, SortOrder::Ascending) {
+            let (key, _) = item.map_err(error::storage_get_failed)?;
+            keys.push(key.to_vec());
+        }
+        keys.sort();
+
+        let proof = store
+            .prove(
+                keys.into_iter()
+                    .map(QueryItem::Key)
+                    .collect::<Vec<_>>()
+                    .into(),
+            )
+            .map_err(error::storage_get_failed)?;
+        let height = store
+            .get(HEIGHT_ROOT.as_bytes())
+            .map_err(error::storage_get_failed)?
+            .and_then(|x| <[u8; 8]>::try_from(x.as_slice()).ok())
+            .map_or(0, u64::from_be_bytes);
+
+        Ok(Self {
+            height,
+            hash: hex::encode(store.root_hash()),
+            proof: hex::encode(proof),
+        })
+    }
+
+    pub fn read(path: impl AsRef<std::path::Path>) -> Result<Self, ManyError> {
+        let content = std::fs::read_to_string(path).map_err(error::invalid_snapshot)?;
+        serde_json::from_str(&content).map_err(error::invalid_snapshot)
+    }
+
+    /// Verify the proof of the snapshot against the state hash of the source
+    /// ledger, and return its balances. The expected hash must come from a
+    /// trusted source, e.g. the block at that height of the source network;
+    /// the hash in the snapshot is only used if none is given.
+    pub fn verify(&self, expected_hash: Option<&str>) -> Result<SnapshotBalances, ManyError> {
+        let expected_hash = expected_hash.unwrap_or(&self.hash);
+        if !expected_hash.eq_ignore_ascii_case(&self.hash) {
+            return Err(error::invalid_snapshot(format!(
+                "Expected the hash '{expected_hash}', the snapshot is of '{}'",
+                self.hash
+            )));
+        }
+
+        let proof = hex::decode(&self.proof).map_err(error::invalid_snapshot)?;
+        let (root_hash, entries) = execute_proof(&proof)?;
+        if hex::encode(root_hash) != self.hash.to_ascii_lowercase() {
+            return Err(error::invalid_snapshot(
+                "The proof does not match the hash of the snapshot",
+            ));
+        }
+
+        let height = entries
+            .get(HEIGHT_ROOT.as_bytes())
+            .and_then(|x| <[u8; 8]>::try_from(x.as_slice()).ok())
+            .map(u64::from_be_bytes)
+            .ok_or_else(|| error::invalid_snapshot("The height is not proven"))?;
+        if height != self.height {
+            return Err(error::invalid_snapshot(format!(
+                "The snapshot is of height {height}, not {}",
+                self.height
+            )));
+        }
+
+        let symbols: BTreeMap<Symbol, String> = entries
+            .get(SYMBOLS_ROOT.as_bytes())
+            .ok_or_else(|| error::invalid_snapshot("The symbols are not proven"))
+            .and_then(|x| minicbor::decode(x).map_err(error::invalid_snapshot))?;
+
+        let mut balances: BTreeMap<Address, BTreeMap<Symbol, TokenAmount>> = BTreeMap::new();
+        for (key, value) in entries.range(BALANCES_ROOT.as_bytes().to_vec()..) {
+            let Some(rest) = key.strip_prefix(BALANCES_ROOT.as_bytes()) else {
+                break;
+            };
+            let (id, symbol) = std::str::from_utf8(rest)
+                .ok()
+                .and_then(|rest| rest.split_once('/'))
+                .and_then(|(id, symbol)| {
+                    Some((Address::from_str(id).ok()?, Address::from_str(symbol).ok()?))
+                })
+                .ok_or_else(|| {
+                    error::invalid_snapshot(format!(
+                        "Invalid balance key {:?}",
+                        String::from_utf8_lossy(key)
+                    ))
+                })?;
+            if !symbols.contains_key(&symbol) {
+                return Err(error::invalid_snapshot(format!(
+                    "Balance of unknown symbol {symbol}"
+                )));
+            }
+            balances
+                .entry(id)
+                .or_default()
+                .insert(symbol, TokenAmount::from(value.clone()));
+        }
+
+        Ok(SnapshotBalances {
+            height,
+            symbols,
+            balances,
+        })
+    }
+}
+
+impl LedgerStorage {
+    /// Export the balances of the ledger, as of the last commit.
+    pub fn balances_snapshot(&self) -> Result<BalancesSnapshot, ManyError> {
+        BalancesSnapshot::create(&self.persistent_store)
+    }
+}
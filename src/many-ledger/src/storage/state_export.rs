@@ -0,0 +1,111 @@
+use crate::error;
+use crate::storage::{commit_store, InnerStorage, LedgerStorage, HEIGHT_ROOT};
+use many_cli_helpers::state_export::StateExport;
+use many_error::ManyError;
+use merk::rocksdb::{IteratorMode, ReadOptions};
+use merk::tree::Tree;
+use merk::Op;
+use std::path::Path;
+
+impl LedgerStorage {
+    /// Export every key of the committed store, including the balances,
+    /// accounts, tokens and events, at its current height.
+    pub fn export_state(&self) -> Result<StateExport, ManyError> {
+        let mut export = StateExport {
+            height: self.get_height()?,
+            hash: self.persistent_store.root_hash().to_vec(),
+            ..Default::default()
+        };
+        for item in self
+            .persistent_store
+            .iter_opt(IteratorMode::Start, ReadOptions::default())
+        {
+            let (key, bytes) = item.map_err(error::storage_get_failed)?;
+            let tree = Tree::decode(key.to_vec(), bytes.as_ref());
+            export
+                .entries
+                .insert(tree.key().to_vec(), tree.value().to_vec());
+        }
+        Ok(export)
+    }
+
+    /// Create a persistent store holding the state of an export, to load it
+    /// with [`LedgerStorage::load`]. The store keeps the height of the
+    /// export, so the chain started from it needs to resume at the next
+    /// height.
+    pub fn import_state<P: AsRef<Path>>(
+        persistent_path: P,
+        export: &StateExport,
+    ) -> Result<(), ManyError> {
+        let height = export
+            .entries
+            .get(HEIGHT_ROOT.as_bytes())
+            .map_or(Ok(0), |h| {
+                <[u8; 8]>::try_from(h.as_slice()).map(u64::from_be_bytes)
+            })
+            .map_err(|_| error::invalid_state_export("The height is not a 64-bit integer"))?;
+        if height != export.height {
+            return Err(error::invalid_state_export(format!(
+                "The export is at height {}, its store at height {height}",
+                export.height
+            )));
+        }
+
+        let mut store = InnerStorage::open(persistent_path).map_err(error::storage_open_failed)?;
+        // Merk needs the batch sorted by key, which the export already is.
+        let batch: Vec<_> = export
+            .entries
+            .iter()
+            .map(|(key, value)| (key.clone(), Op::Put(value.clone())))
+            .collect();
+        store.apply(&batch).map_err(error::storage_apply_failed)?;
+        commit_store(&mut store)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+    use many_types::ledger::TokenAmount;
+    use std::collections::{BTreeMap, BTreeSet};
+
+    #[test]
+    fn export_and_import() {
+        let symbol = identity(100);
+        let symbols = BTreeMap::from([(symbol, "MFX".to_string())]);
+        let balances = BTreeMap::from([(
+            identity(1),
+            BTreeMap::from([(symbol, TokenAmount::from(1000u64))]),
+        )]);
+        let source_path = tempfile::tempdir().unwrap().into_path();
+        let source = LedgerStorage::new(&source_path, false)
+            .unwrap()
+            .with_balances(&identity(0), &symbols, &balances)
+            .unwrap()
+            .build()
+            .unwrap();
+        let export = source.export_state().unwrap();
+        assert!(!export.entries.is_empty());
+
+        let target_path = tempfile::tempdir().unwrap().into_path().join("store");
+        LedgerStorage::import_state(&target_path, &export).unwrap();
+        let target = LedgerStorage::load(&target_path, false, None).unwrap();
+        assert_eq!(target.export_state().unwrap().entries, export.entries);
+
+        let (imported, _) = target
+            .get_multiple_balances(&identity(1), &BTreeSet::from([symbol]))
+            .unwrap();
+        assert_eq!(imported, balances[&identity(1)]);
+    }
+
+    #[test]
+    fn height_mismatch() {
+        let export = StateExport {
+            height: 3,
+            ..Default::default()
+        };
+        let path = tempfile::tempdir().unwrap().into_path().join("store");
+        assert!(LedgerStorage::import_state(path, &export).is_err());
+    }
+}
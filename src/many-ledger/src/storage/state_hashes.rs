@@ -0,0 +1,73 @@
+use crate::error;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_modules::debug::{StateHashesReturns, SubtreeHash};
+use merk::tree::kv_hash;
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+
+/// The prefix of the subtree of a key under `prefix`: up to and including
+/// the next `/` of the key after the prefix, ignoring a `/` right after it.
+fn subtree_of<'a>(prefix: &[u8], key: &'a [u8]) -> &'a [u8] {
+    let rest = &key[prefix.len()..];
+    match rest.iter().skip(1).position(|b| *b == b'/') {
+        Some(i) => &key[..prefix.len() + i + 2],
+        None => key,
+    }
+}
+
+impl LedgerStorage {
+    /// Break down the committed store under a prefix in the hashes of its
+    /// subtrees. See [`many_modules::debug::DebugModuleBackend`].
+    pub fn state_hashes(&self, prefix: &[u8]) -> Result<StateHashesReturns, ManyError> {
+        let mut hashers: BTreeMap<Vec<u8>, (Sha3_256, u64)> = BTreeMap::new();
+        for item in LedgerIterator::all_with_prefix(&self.persistent_store, prefix.to_vec()) {
+            let (key, value) = item.map_err(error::storage_get_failed)?;
+            let (hasher, keys) = hashers
+                .entry(subtree_of(prefix, &key).to_vec())
+                .or_default();
+            hasher.update(kv_hash(&key, &value));
+            *keys += 1;
+        }
+
+        Ok(StateHashesReturns {
+            height: self.get_height()?,
+            app_hash: self.hash().into(),
+            subtrees: hashers
+                .into_iter()
+                .map(|(subtree, (hasher, keys))| {
+                    (
+                        subtree.into(),
+                        SubtreeHash {
+                            hash: hasher.finalize().to_vec().into(),
+                            keys,
+                        },
+                    )
+                })
+                .collect(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn subtrees() {
+        assert_eq!(
+            subtree_of(b"", b"/balances/abc/MFX"),
+            b"/balances/".as_slice()
+        );
+        assert_eq!(subtree_of(b"", b"/height"), b"/height".as_slice());
+        assert_eq!(
+            subtree_of(b"/balances/", b"/balances/abc/MFX"),
+            b"/balances/abc/".as_slice()
+        );
+        assert_eq!(
+            subtree_of(b"/config/", b"/config/identity"),
+            b"/config/identity".as_slice()
+        );
+    }
+}
@@ -0,0 +1,369 @@
+use crate::error;
+use crate::storage::event::{key_for_event, HEIGHT_EVENTID_SHIFT};
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::events::{EventId, EventInfo, EventKind, EventLog, EventTerm};
+use many_modules::ledger::StatementEntry;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{CborRange, Memo, SortOrder, Timestamp};
+use std::collections::{BTreeSet, VecDeque};
+use std::ops::Bound;
+
+/// How an event changed the balance of an account in a symbol.
+struct BalanceChange {
+    credit: TokenAmount,
+    debit: TokenAmount,
+    counterparty: Option<Address>,
+    memo: Option<Memo>,
+}
+
+impl BalanceChange {
+    fn credit(amount: &TokenAmount, counterparty: Option<Address>, memo: &Option<Memo>) -> Self {
+        Self {
+            credit: amount.clone(),
+            debit: TokenAmount::zero(),
+            counterparty,
+            memo: memo.clone(),
+        }
+    }
+
+    fn debit(amount: &TokenAmount, counterparty: Option<Address>, memo: &Option<Memo>) -> Self {
+        Self {
+            credit: TokenAmount::zero(),
+            debit: amount.clone(),
+            counterparty,
+            memo: memo.clone(),
+        }
+    }
+}
+
+/// The range of the event IDs of the blocks at a range of heights. Events
+/// are prefixed by the height before the block they're logged in.
+fn id_range_of_heights(range: &CborRange<u64>) -> CborRange<EventId> {
+    let first_of = |height: u64| EventId::from(height.saturating_sub(1) << HEIGHT_EVENTID_SHIFT);
+    CborRange {
+        start: match range.start {
+            Bound::Included(h) => Bound::Included(first_of(h)),
+            Bound::Excluded(h) => Bound::Included(first_of(h + 1)),
+            Bound::Unbounded => Bound::Unbounded,
+        },
+        end: match range.end {
+            Bound::Included(h) => Bound::Excluded(first_of(h + 1)),
+            Bound::Excluded(h) => Bound::Excluded(first_of(h)),
+            Bound::Unbounded => Bound::Unbounded,
+        },
+    }
+}
+
+/// Event IDs don't have the same size, they are compared by their keys.
+fn key_range(range: CborRange<EventId>, after: Option<EventId>) -> CborRange<Vec<u8>> {
+    let key = |bound: Bound<EventId>| match bound {
+        Bound::Included(id) => Bound::Included(key_for_event(id)),
+        Bound::Excluded(id) => Bound::Excluded(key_for_event(id)),
+        Bound::Unbounded => Bound::Unbounded,
+    };
+    let start = match (key(range.start), after.map(key_for_event)) {
+        (Bound::Included(start), Some(after)) if start > after => Bound::Included(start),
+        (Bound::Excluded(start), Some(after)) if start > after => Bound::Excluded(start),
+        (_, Some(after)) => Bound::Excluded(after),
+        (start, None) => start,
+    };
+    CborRange {
+        start,
+        end: key(range.end),
+    }
+}
+
+/// Whether a key is before the start of a range.
+fn is_before(range: &CborRange<Vec<u8>>, key: &[u8]) -> bool {
+    match &range.start {
+        Bound::Included(start) => key < start.as_slice(),
+        Bound::Excluded(start) => key <= start.as_slice(),
+        Bound::Unbounded => false,
+    }
+}
+
+impl LedgerStorage {
+    fn event_at(&self, id: EventId) -> Result<Option<EventLog>, ManyError> {
+        self.persistent_store
+            .get(&key_for_event(id))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// The event creating a swap or an escrow. The reservation of its funds
+    /// takes the ID right before it.
+    fn reservation_event(&self, id: &[u8]) -> Result<EventInfo, ManyError> {
+        self.event_at(EventId::from(id.to_vec()) + 1u32)?
+            .map(|event| event.content)
+            .ok_or_else(|| {
+                ManyError::unknown(format!(
+                    "The event creating reservation {} was pruned.",
+                    hex::encode(id)
+                ))
+            })
+    }
+
+    /// Whether a send credits the funds of a reservation, which were debited
+    /// when it was created. Those are logged right before the event of the
+    /// swap or escrow they complete.
+    fn is_reservation_commit(&self, id: &EventId) -> Result<bool, ManyError> {
+        Ok(matches!(
+            self.event_at(id.clone() + 1u32)?.map(|event| event.kind()),
+            Some(EventKind::SwapAccept) | Some(EventKind::EscrowRelease)
+        ))
+    }
+
+    fn balance_change(
+        &self,
+        event: &EventLog,
+        account: &Address,
+        symbol: &Symbol,
+    ) -> Result<Option<BalanceChange>, ManyError> {
+        let change = match &event.content {
+            EventInfo::Send {
+                from,
+                to,
+                symbol: s,
+                amount,
+                memo,
+            } if s == symbol => {
+                if to == account {
+                    Some(BalanceChange::credit(amount, Some(*from), memo))
+                } else if from == account && !self.is_reservation_commit(&event.id)? {
+                    Some(BalanceChange::debit(amount, Some(*to), memo))
+                } else {
+                    None
+                }
+            }
+            EventInfo::SwapCreate {
+                creator,
+                counterparty,
+                give_symbol,
+                give_amount,
+                memo,
+                ..
+            } if creator == account && give_symbol == symbol => {
+                Some(BalanceChange::debit(give_amount, Some(*counterparty), memo))
+            }
+            EventInfo::SwapCancel { id, creator, .. } if creator == account => {
+                match self.reservation_event(id)? {
+                    EventInfo::SwapCreate {
+                        counterparty,
+                        give_symbol,
+                        give_amount,
+                        ..
+                    } if &give_symbol == symbol => Some(BalanceChange::credit(
+                        &give_amount,
+                        Some(counterparty),
+                        &None,
+                    )),
+                    _ => None,
+                }
+            }
+            EventInfo::EscrowCreate {
+                depositor,
+                recipient,
+                symbol: s,
+                amount,
+                memo,
+                ..
+            } if depositor == account && s == symbol => {
+                Some(BalanceChange::debit(amount, Some(*recipient), memo))
+            }
+            EventInfo::EscrowRefund { id, depositor, .. } if depositor == account => {
+                match self.reservation_event(id)? {
+                    EventInfo::EscrowCreate {
+                        recipient,
+                        symbol: s,
+                        amount,
+                        ..
+                    } if &s == symbol => {
+                        Some(BalanceChange::credit(&amount, Some(recipient), &None))
+                    }
+                    _ => None,
+                }
+            }
+            EventInfo::TokenMint {
+                symbol: s,
+                distribution,
+                memo,
+            } if s == symbol => distribution
+                .get(account)
+                .map(|amount| BalanceChange::credit(amount, None, memo)),
+            EventInfo::TokenBurn {
+                symbol: s,
+                distribution,
+                memo,
+            } if s == symbol => distribution
+                .get(account)
+                .map(|amount| BalanceChange::debit(amount, None, memo)),
+            EventInfo::TokenBuy {
+                symbol: s,
+                buyer,
+                amount,
+                cost,
+                memo,
+            } if buyer == account => {
+                if s == symbol {
+                    Some(BalanceChange::credit(amount, None, memo))
+                } else if self.curve_reserve(s)?.as_ref() == Some(symbol) {
+                    Some(BalanceChange::debit(cost, None, memo))
+                } else {
+                    None
+                }
+            }
+            EventInfo::TokenSell {
+                symbol: s,
+                seller,
+                amount,
+                proceeds,
+                memo,
+            } if seller == account => {
+                if s == symbol {
+                    Some(BalanceChange::debit(amount, None, memo))
+                } else if self.curve_reserve(s)?.as_ref() == Some(symbol) {
+                    Some(BalanceChange::credit(proceeds, None, memo))
+                } else {
+                    None
+                }
+            }
+            EventInfo::BridgeLock {
+                sender,
+                destination,
+                symbol: s,
+                amount,
+                memo,
+                ..
+            }
+            | EventInfo::BridgeBurn {
+                sender,
+                destination,
+                symbol: s,
+                amount,
+                memo,
+                ..
+            } if sender == account && s == symbol => {
+                Some(BalanceChange::debit(amount, Some(*destination), memo))
+            }
+            EventInfo::BridgeClaim {
+                source,
+                recipient,
+                symbol: s,
+                amount,
+                ..
+            } if recipient == account && s == symbol => {
+                Some(BalanceChange::credit(amount, Some(*source), &None))
+            }
+            _ => None,
+        };
+        Ok(change)
+    }
+
+    /// The events of an account, newest first.
+    fn iter_account_events<'a>(
+        &'a self,
+        account: &'a Address,
+    ) -> Box<dyn Iterator<Item = Result<EventLog, ManyError>> + 'a> {
+        let decode =
+            |v: &[u8]| minicbor::decode::<EventLog>(v).map_err(ManyError::deserialization_error);
+        if self.is_event_index_active() {
+            let terms = BTreeSet::from([EventTerm::Address(*account)]);
+            Box::new(
+                self.iter_indexed_events(&terms, CborRange::default(), SortOrder::Descending)
+                    .map(move |item| decode(&item?)),
+            )
+        } else {
+            Box::new(
+                self.iter_events(CborRange::default(), SortOrder::Descending)
+                    .map(move |item| {
+                        let (_, v) = item.map_err(ManyError::unknown)?;
+                        decode(&v)
+                    })
+                    .filter(|event| !matches!(event, Ok(event) if !event.is_about(*account))),
+            )
+        }
+    }
+
+    /// The first `count` balance changes of an account in the ranges, after
+    /// the `after` event, whether there are more, and the balance before the
+    /// first of them.
+    ///
+    /// The balance after each change is derived from the current balance,
+    /// undoing the events back from the latest one. Changes that are not
+    /// from events, like the initial balances, are only part of the
+    /// balances.
+    #[allow(clippy::too_many_arguments)]
+    pub fn statement(
+        &self,
+        account: &Address,
+        symbol: &Symbol,
+        height_range: Option<CborRange<u64>>,
+        date_range: Option<CborRange<Timestamp>>,
+        after: Option<EventId>,
+        count: usize,
+    ) -> Result<(Vec<StatementEntry>, bool, TokenAmount), ManyError> {
+        let range = key_range(
+            height_range
+                .as_ref()
+                .map(id_range_of_heights)
+                .unwrap_or_default(),
+            after,
+        );
+        let date_range = date_range.unwrap_or_default();
+
+        let mut balance = self.get_balance(account, symbol)?;
+        let mut entries = VecDeque::with_capacity(count + 1);
+        let mut more = false;
+        for event in self.iter_account_events(account) {
+            let event = event?;
+            let key = key_for_event(event.id.clone());
+            let before_dates = match &date_range.start {
+                Bound::Included(start) => &event.time < start,
+                Bound::Excluded(start) => &event.time <= start,
+                Bound::Unbounded => false,
+            };
+            if is_before(&range, &key) || before_dates {
+                break;
+            }
+
+            let Some(change) = self.balance_change(&event, account, symbol)? else {
+                continue;
+            };
+            let after_change = balance.clone();
+            balance += &change.debit;
+            if balance < change.credit {
+                return Err(ManyError::unknown(format!(
+                    "The balance of {account} cannot be derived before event {}.",
+                    hex::encode(&event.id)
+                )));
+            }
+            balance -= &change.credit;
+
+            if range.contains(&key) && date_range.contains(&event.time) {
+                entries.push_front(StatementEntry {
+                    id: event.id.clone(),
+                    time: event.time,
+                    kind: event.kind(),
+                    credit: change.credit,
+                    debit: change.debit,
+                    counterparty: change.counterparty,
+                    balance: after_change,
+                    memo: change.memo,
+                });
+                if entries.len() > count {
+                    entries.pop_back();
+                    more = true;
+                }
+            }
+        }
+
+        let opening = match entries.front() {
+            Some(first) => &(&first.balance + &first.debit) - &first.credit,
+            None => balance,
+        };
+        Ok((entries.into(), more, opening))
+    }
+}
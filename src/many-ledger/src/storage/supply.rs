@@ -0,0 +1,156 @@
+use crate::error;
+use crate::migration::supply::SUPPLY_ACCOUNTING_MIGRATION;
+use crate::storage::iterator::LedgerIterator;
+use crate::storage::ledger_tokens::SYMBOLS_ROOT_DASH;
+use crate::storage::{InnerStorage, LedgerStorage, HEIGHT_ROOT};
+use many_error::ManyError;
+use many_modules::ledger::TokenSupplyAtReturns;
+use many_types::ledger::{Symbol, TokenAmount, TokenInfo, TokenInfoSupply};
+use many_types::SortOrder;
+use merk::{BatchEntry, Op};
+use std::str::FromStr;
+
+pub(crate) const SUPPLY_ROOT: &str = "/supply/";
+
+/// The key of the latest supply of a symbol.
+fn key_for_supply(symbol: &Symbol) -> Vec<u8> {
+    format!("{SUPPLY_ROOT}{symbol}").into_bytes()
+}
+
+/// The root of the supply records of a symbol, one per height at which the
+/// supply changed.
+fn key_for_supply_history(symbol: &Symbol) -> Vec<u8> {
+    format!("{SUPPLY_ROOT}{symbol}/").into_bytes()
+}
+
+fn key_for_supply_at(symbol: &Symbol, height: u64) -> Vec<u8> {
+    [
+        key_for_supply_history(symbol).as_slice(),
+        &height.to_be_bytes(),
+    ]
+    .concat()
+}
+
+/// The entries storing a supply as the latest one of a symbol and as the one
+/// at a height.
+fn supply_entries(
+    symbol: &Symbol,
+    height: u64,
+    supply: &TokenSupplyAtReturns,
+) -> Result<Vec<BatchEntry>, ManyError> {
+    let value = minicbor::to_vec(supply).map_err(ManyError::serialization_error)?;
+    Ok(vec![
+        (key_for_supply(symbol), Op::Put(value.clone())),
+        (key_for_supply_at(symbol, height), Op::Put(value)),
+    ])
+}
+
+/// Record the current supply of all the symbols, with nothing burned, at the
+/// current height. The changes are applied but not committed.
+pub(crate) fn record_all_supplies(store: &mut InnerStorage) -> Result<(), ManyError> {
+    let height = store
+        .get(HEIGHT_ROOT.as_bytes())
+        .map_err(error::storage_get_failed)?
+        .map_or(0u64, |x| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(x.as_slice());
+            u64::from_be_bytes(bytes)
+        });
+
+    let mut batch: Vec<BatchEntry> = Vec::new();
+    for item in LedgerIterator::all_symbols(store, SortOrder::Indeterminate) {
+        let (key, value) = item.map_err(error::storage_get_failed)?;
+        let symbol = Symbol::from_str(
+            std::str::from_utf8(&key[SYMBOLS_ROOT_DASH.len()..])
+                .map_err(ManyError::deserialization_error)?,
+        )?;
+        let info: TokenInfo = minicbor::decode(&value).map_err(ManyError::deserialization_error)?;
+        batch.extend(supply_entries(
+            &symbol,
+            height,
+            &TokenSupplyAtReturns {
+                total: info.supply.total,
+                circulating: info.supply.circulating,
+                burned: TokenAmount::zero(),
+            },
+        )?);
+    }
+
+    batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    store.apply(&batch).map_err(error::storage_apply_failed)
+}
+
+impl LedgerStorage {
+    pub fn is_supply_accounting_active(&self) -> bool {
+        self.migrations.is_active(&SUPPLY_ACCOUNTING_MIGRATION)
+    }
+
+    pub(crate) fn latest_supply(
+        &self,
+        symbol: &Symbol,
+    ) -> Result<Option<TokenSupplyAtReturns>, ManyError> {
+        self.persistent_store
+            .get(&key_for_supply(symbol))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    /// The entries recording a new supply of a symbol in the block being
+    /// executed, with `burned` more tokens burned. Empty if the Supply
+    /// Accounting Migration is not active.
+    pub(crate) fn supply_change_entries(
+        &self,
+        symbol: &Symbol,
+        supply: &TokenInfoSupply,
+        burned: &TokenAmount,
+    ) -> Result<Vec<BatchEntry>, ManyError> {
+        if !self.is_supply_accounting_active() {
+            return Ok(vec![]);
+        }
+
+        let previous = self.latest_supply(symbol)?;
+        let record = TokenSupplyAtReturns {
+            total: supply.total.clone(),
+            circulating: supply.circulating.clone(),
+            burned: &previous.map_or_else(TokenAmount::zero, |p| p.burned) + burned,
+        };
+        // Changes made in a block are part of the state at its height, once
+        // committed.
+        supply_entries(symbol, self.get_height()? + 1, &record)
+    }
+
+    /// The supply of a symbol at the end of the block of a height, or the
+    /// latest supply if `height` is `None`.
+    pub fn supply_at(
+        &self,
+        symbol: &Symbol,
+        height: Option<u64>,
+    ) -> Result<TokenSupplyAtReturns, ManyError> {
+        let Some(height) = height else {
+            return match self.latest_supply(symbol)? {
+                Some(supply) => Ok(supply),
+                None => Err(error::supply_not_recorded(symbol, self.get_height()?)),
+            };
+        };
+
+        // The first record at or before the height. History keys are the
+        // root followed by the height, so this upper bound excludes the
+        // records after it.
+        let mut upper_bound = key_for_supply_at(symbol, height);
+        upper_bound.push(0);
+        let mut it = LedgerIterator::scoped_by_key(
+            &self.persistent_store,
+            key_for_supply_history(symbol),
+            upper_bound,
+            SortOrder::Descending,
+        );
+        match it.next() {
+            Some(item) => {
+                let (_, value) = item.map_err(error::storage_get_failed)?;
+                minicbor::decode(&value).map_err(ManyError::deserialization_error)
+            }
+            None => Err(error::supply_not_recorded(symbol, height)),
+        }
+    }
+}
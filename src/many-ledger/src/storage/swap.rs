@@ -0,0 +1,156 @@
+use crate::error;
+use crate::storage::{key_for_token, LedgerStorage};
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::{events, ledger};
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Timestamp;
+use merk::Op;
+use minicbor::bytes::ByteVec;
+
+pub(crate) const SWAPS_ROOT: &[u8] = b"/swaps/";
+
+pub(super) fn key_for_swap(id: &[u8]) -> Vec<u8> {
+    key_for_token(SWAPS_ROOT, id)
+}
+
+/// An open swap. What the creator gives is held in the reservation of the
+/// same ID, to the counterparty.
+#[derive(minicbor::Encode, minicbor::Decode, Clone, Debug, Eq, PartialEq)]
+#[cbor(map)]
+pub struct Swap {
+    #[n(0)]
+    pub creator: Address,
+
+    #[n(1)]
+    pub counterparty: Address,
+
+    #[n(2)]
+    pub want_symbol: Symbol,
+
+    #[n(3)]
+    pub want_amount: TokenAmount,
+
+    #[n(4)]
+    pub expiration: Timestamp,
+}
+
+impl LedgerStorage {
+    pub fn get_swap(&self, id: &[u8]) -> Result<Swap, ManyError> {
+        let bytes = self
+            .persistent_store
+            .get(&key_for_swap(id))
+            .map_err(error::storage_get_failed)?
+            .ok_or_else(|| error::swap_not_found(hex::encode(id)))?;
+
+        minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+    }
+
+    pub fn create_swap(
+        &mut self,
+        sender: &Address,
+        args: ledger::CreateSwapArgs,
+    ) -> Result<ByteVec, ManyError> {
+        let ledger::CreateSwapArgs {
+            counterparty,
+            give_symbol,
+            give_amount,
+            want_symbol,
+            want_amount,
+            expiration,
+            memo,
+        } = args;
+
+        if expiration <= self.now() {
+            return Err(error::swap_expiration_in_the_past());
+        }
+        if want_amount.is_zero() {
+            return Err(error::amount_is_zero());
+        }
+        let symbols = self.get_symbols()?;
+        for symbol in [&give_symbol, &want_symbol] {
+            if !symbols.contains(symbol) {
+                return Err(error::unknown_symbol(symbol.to_string()));
+            }
+        }
+
+        let (id, _) = self.prepare_transfer(
+            sender,
+            &counterparty,
+            &give_symbol,
+            give_amount.clone(),
+            memo.clone(),
+        )?;
+        let swap = Swap {
+            creator: *sender,
+            counterparty,
+            want_symbol,
+            want_amount: want_amount.clone(),
+            expiration,
+        };
+        self.apply(&[(
+            key_for_swap(&id),
+            Op::Put(minicbor::to_vec(&swap).map_err(ManyError::serialization_error)?),
+        )])?;
+
+        self.log_event(events::EventInfo::SwapCreate {
+            id: id.clone(),
+            creator: *sender,
+            counterparty,
+            give_symbol,
+            give_amount,
+            want_symbol,
+            want_amount,
+            expiration,
+            memo,
+        })?;
+        self.maybe_commit()?;
+        Ok(id)
+    }
+
+    /// Send what the creator wants from the counterparty, then what the
+    /// creator gives from the reservation. Both sends fail together.
+    pub fn accept_swap(&mut self, sender: &Address, id: ByteVec) -> Result<(), ManyError> {
+        let swap = self.get_swap(&id)?;
+        if sender != &swap.counterparty {
+            return Err(error::unauthorized());
+        }
+        if self.now() >= swap.expiration {
+            return Err(error::swap_expired(hex::encode(&id)));
+        }
+
+        self.send(
+            sender,
+            &swap.creator,
+            &swap.want_symbol,
+            swap.want_amount.clone(),
+            None,
+        )?;
+        self.commit_transfer(&id)?;
+        self.apply(&[(key_for_swap(&id), Op::Delete)])?;
+
+        self.log_event(events::EventInfo::SwapAccept {
+            id,
+            creator: swap.creator,
+            counterparty: swap.counterparty,
+        })?;
+        self.maybe_commit()
+    }
+
+    pub fn cancel_swap(&mut self, sender: &Address, id: ByteVec) -> Result<(), ManyError> {
+        let swap = self.get_swap(&id)?;
+        if sender != &swap.creator && sender != &swap.counterparty {
+            return Err(error::unauthorized());
+        }
+
+        self.abort_transfer(&id)?;
+        self.apply(&[(key_for_swap(&id), Op::Delete)])?;
+
+        self.log_event(events::EventInfo::SwapCancel {
+            id,
+            creator: swap.creator,
+            canceller: *sender,
+        })?;
+        self.maybe_commit()
+    }
+}
@@ -0,0 +1,84 @@
+use crate::error;
+use crate::migration::token_rules::TOKEN_RULES_MIGRATION;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::events::EventInfo;
+use many_modules::ledger::{TokenRules, TokenUpdateRulesArgs};
+use many_types::ledger::{Symbol, TokenAmount};
+use merk::Op;
+
+pub fn key_for_token_rules(symbol: &Symbol) -> Vec<u8> {
+    format!("/config/rules/{symbol}").into_bytes()
+}
+
+/// The transfer rules of tokens, set by their owner. Every transfer of a
+/// token checks its rules once the Token Rules Migration is active: sends,
+/// prepared and committed transfers (e.g. of swaps and escrows), and the
+/// tokens locked and released by the bridge.
+impl LedgerStorage {
+    pub fn is_token_rules_active(&self) -> bool {
+        self.migrations.is_active(&TOKEN_RULES_MIGRATION)
+    }
+
+    pub fn token_rules(&self, symbol: &Symbol) -> Result<Option<TokenRules>, ManyError> {
+        self.persistent_store
+            .get(&key_for_token_rules(symbol))
+            .map_err(error::storage_get_failed)?
+            .map(|bytes| minicbor::decode(&bytes).map_err(ManyError::deserialization_error))
+            .transpose()
+    }
+
+    pub fn update_token_rules(&mut self, args: TokenUpdateRulesArgs) -> Result<(), ManyError> {
+        let TokenUpdateRulesArgs {
+            symbol,
+            rules,
+            memo,
+        } = args;
+        let key = key_for_token_rules(&symbol);
+        if !rules.is_empty() {
+            self.apply(&[(
+                key,
+                Op::Put(minicbor::to_vec(&rules).map_err(ManyError::serialization_error)?),
+            )])?;
+        } else if self.token_rules(&symbol)?.is_some() {
+            self.apply(&[(key, Op::Delete)])?;
+        }
+
+        self.log_event(EventInfo::TokenUpdateRules {
+            symbol,
+            rules,
+            memo,
+        })?;
+        self.maybe_commit()
+    }
+
+    /// Verify that the rules of the symbol allow a transfer.
+    pub(crate) fn verify_token_rules(
+        &self,
+        from: &Address,
+        to: &Address,
+        symbol: &Symbol,
+        amount: &TokenAmount,
+    ) -> Result<(), ManyError> {
+        if !self.is_token_rules_active() {
+            return Ok(());
+        }
+        let Some(rules) = self.token_rules(symbol)? else {
+            return Ok(());
+        };
+
+        if rules.paused {
+            return Err(error::transfers_paused(symbol));
+        }
+        if let Some(whitelist) = &rules.whitelist {
+            if let Some(address) = [from, to].into_iter().find(|a| !whitelist.contains(a)) {
+                return Err(error::address_not_whitelisted(address, symbol));
+            }
+        }
+        match &rules.max_per_transfer {
+            Some(limit) if amount > limit => Err(error::amount_over_transfer_limit(amount, limit)),
+            _ => Ok(()),
+        }
+    }
+}
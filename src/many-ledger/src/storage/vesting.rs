@@ -0,0 +1,146 @@
+use crate::error;
+use crate::migration::vesting::VESTING_MIGRATION;
+use crate::storage::LedgerStorage;
+use many_error::ManyError;
+use many_identity::Address;
+use many_modules::ledger::{TokenGrantVestingArgs, VestingInfo};
+use many_types::ledger::{Symbol, TokenAmount, VestingGrant};
+use merk::{BatchEntry, Op};
+use std::collections::{BTreeMap, BTreeSet};
+
+pub(crate) const VESTING_ROOT: &str = "/vesting/";
+
+fn key_for_vesting(id: &Address, symbol: &Symbol) -> Vec<u8> {
+    format!("{VESTING_ROOT}{id}/{symbol}").into_bytes()
+}
+
+/// Vesting grants lock part of the balance of their account, which can only
+/// be spent as the grants unlock. Grants are kept until a new grant to the
+/// same account and symbol finds them fully unlocked.
+impl LedgerStorage {
+    pub fn is_vesting_active(&self) -> bool {
+        self.migrations.is_active(&VESTING_MIGRATION)
+    }
+
+    /// Lock the initial balances of the grants, by account and symbol.
+    pub fn with_vesting(
+        mut self,
+        grants: BTreeMap<(Address, Symbol), Vec<VestingGrant>>,
+    ) -> Result<Self, ManyError> {
+        let mut batch: Vec<BatchEntry> = Vec::new();
+        for ((id, symbol), grants) in grants {
+            if grants.iter().any(|g| !g.schedule.is_valid()) {
+                return Err(error::invalid_vesting_schedule());
+            }
+            batch.push((
+                key_for_vesting(&id, &symbol),
+                Op::Put(minicbor::to_vec(grants).map_err(ManyError::serialization_error)?),
+            ));
+        }
+
+        batch.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+        self.apply(&batch)?;
+        Ok(self)
+    }
+
+    pub fn get_vesting_grants(
+        &self,
+        id: &Address,
+        symbol: &Symbol,
+    ) -> Result<Vec<VestingGrant>, ManyError> {
+        self.persistent_store
+            .get(&key_for_vesting(id, symbol))
+            .map_err(error::storage_get_failed)?
+            .map_or(Ok(vec![]), |bytes| {
+                minicbor::decode(&bytes).map_err(ManyError::deserialization_error)
+            })
+    }
+
+    /// The amount of the balance of an account that is still vesting.
+    pub fn get_locked_balance(
+        &self,
+        id: &Address,
+        symbol: &Symbol,
+    ) -> Result<TokenAmount, ManyError> {
+        let now = self.now();
+        let mut locked = TokenAmount::zero();
+        for grant in self.get_vesting_grants(id, symbol)? {
+            locked += grant.locked_at(now);
+        }
+        Ok(locked)
+    }
+
+    /// Verify that `amount` can be spent out of the `balance` of an account
+    /// without spending its locked tokens.
+    pub(crate) fn verify_unlocked(
+        &self,
+        id: &Address,
+        symbol: &Symbol,
+        amount: &TokenAmount,
+        balance: &TokenAmount,
+    ) -> Result<(), ManyError> {
+        let locked = self.get_locked_balance(id, symbol)?;
+        if &(amount + &locked) > balance {
+            return Err(error::funds_locked(locked, symbol));
+        }
+        Ok(())
+    }
+
+    pub fn grant_vesting(
+        &mut self,
+        sender: &Address,
+        args: TokenGrantVestingArgs,
+    ) -> Result<(), ManyError> {
+        let TokenGrantVestingArgs {
+            symbol,
+            to,
+            amount,
+            schedule,
+            memo,
+        } = args;
+
+        if !schedule.is_valid() {
+            return Err(error::invalid_vesting_schedule());
+        }
+        if !self.get_symbols()?.contains(&symbol) {
+            return Err(error::unknown_symbol(symbol.to_string()));
+        }
+
+        self.send(sender, &to, &symbol, amount.clone(), memo)?;
+
+        let now = self.now();
+        let mut grants = self.get_vesting_grants(&to, &symbol)?;
+        grants.retain(|g| !g.locked_at(now).is_zero());
+        grants.push(VestingGrant { amount, schedule });
+
+        self.apply(&[(
+            key_for_vesting(&to, &symbol),
+            Op::Put(minicbor::to_vec(grants).map_err(ManyError::serialization_error)?),
+        )])?;
+        self.maybe_commit()
+    }
+
+    /// The grants of an account in the symbols that have any, among
+    /// `symbols`.
+    pub fn get_vesting_info(
+        &self,
+        id: &Address,
+        symbols: &BTreeSet<Symbol>,
+    ) -> Result<BTreeMap<Symbol, VestingInfo>, ManyError> {
+        let now = self.now();
+        let mut vesting = BTreeMap::new();
+        for symbol in symbols {
+            let grants = self.get_vesting_grants(id, symbol)?;
+            if grants.is_empty() {
+                continue;
+            }
+
+            let mut locked = TokenAmount::zero();
+            for grant in &grants {
+                locked += grant.locked_at(now);
+            }
+            vesting.insert(*symbol, VestingInfo { grants, locked });
+        }
+        Ok(vesting)
+    }
+}
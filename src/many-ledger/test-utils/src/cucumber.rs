@@ -1,3 +1,4 @@
+use async_channel::unbounded;
 use cucumber::Parameter;
 use many_error::{ManyError, ManyErrorCode};
 use many_identity::testing::identity;
@@ -12,6 +13,7 @@ use many_modules::account::{
 };
 use many_modules::ledger::extended_info::TokenExtendedInfo;
 use many_modules::ledger::{LedgerTokensModuleBackend, TokenInfoArgs};
+use many_protocol::{context::Context, RequestMessage};
 use many_types::cbor::CborNull;
 use many_types::ledger::{TokenAmount, TokenInfo, TokenMaybeOwner};
 use std::collections::{BTreeMap, BTreeSet};
@@ -208,6 +210,7 @@ pub fn given_token_account<T: LedgerWorld + AccountWorld>(w: &mut T) {
             features: FeatureSet::from_iter([
                 account::features::tokens::TokenAccountLedger.as_feature()
             ]),
+            nonce: None,
         },
     )
     .expect("Unable to create account");
@@ -331,6 +334,7 @@ pub fn refresh_token_info<T: LedgerWorld + TokenWorld>(w: &mut T) {
             symbol: w.info().symbol,
             ..Default::default()
         },
+        Context::new(RequestMessage::default(), unbounded().0),
     )
     .expect("Unable to query token info");
     *w.info_mut() = result.info;
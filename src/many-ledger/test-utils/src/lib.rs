@@ -64,6 +64,7 @@ pub fn default_token_create_args(
                 .unwrap(),
         ),
         memo: None,
+        dust: None,
     }
 }
 
@@ -163,9 +164,23 @@ pub fn create_account_args(account_type: AccountType) -> account::CreateArgs {
         description: Some("Foobar".to_string()),
         roles,
         features,
+        nonce: None,
     }
 }
 
+fn migration_config(
+    migrations: impl IntoIterator<Item = impl Into<MigrationHarness>>,
+) -> MigrationConfig {
+    let migrations = format!(
+        r#"{{ "migrations": [{}] }}"#,
+        migrations
+            .into_iter()
+            .map(|x| x.into().to_json_str())
+            .join(",")
+    );
+    serde_json::from_str(&migrations).unwrap()
+}
+
 #[derive(Debug)]
 pub struct Setup {
     pub module_impl: LedgerModuleImpl,
@@ -187,6 +202,7 @@ impl Setup {
         blockchain: bool,
         migration_config: Option<MigrationConfig>,
         skip_hash_check: bool, // If true, skip the staging file hash check
+        update_state: impl FnOnce(&mut InitialStateJson),
     ) -> Self {
         let id = generate_random_ed25519_identity();
         let public_key = PublicKey(id.public_key().to_vec().unwrap().into());
@@ -200,6 +216,7 @@ impl Setup {
         if skip_hash_check {
             state.hash = None;
         }
+        update_state(&mut state);
 
         Self {
             module_impl: LedgerModuleImpl::new(state, migration_config, store_path, blockchain)
@@ -212,7 +229,7 @@ impl Setup {
     }
 
     pub fn new(blockchain: bool) -> Self {
-        Setup::_new(blockchain, None, false)
+        Setup::_new(blockchain, None, false, |_| {})
     }
 
     pub fn new_with_migrations(
@@ -220,18 +237,27 @@ impl Setup {
         migrations: impl IntoIterator<Item = impl Into<MigrationHarness>>,
         skip_hash_check: bool,
     ) -> Self {
-        let migrations = format!(
-            r#"{{ "migrations": [{}] }}"#,
-            migrations
-                .into_iter()
-                .map(|x| x.into().to_json_str())
-                .join(",")
-        );
-
         Setup::_new(
             blockchain,
-            Some(serde_json::from_str(&migrations).unwrap()),
+            Some(migration_config(migrations)),
             skip_hash_check,
+            |_| {},
+        )
+    }
+
+    /// Like [`Setup::new_with_migrations`], with changes to the initial state,
+    /// e.g. to set up a second ledger with another identity. The hash of the
+    /// staging file is not checked.
+    pub fn new_with_state(
+        blockchain: bool,
+        migrations: impl IntoIterator<Item = impl Into<MigrationHarness>>,
+        update_state: impl FnOnce(&mut InitialStateJson),
+    ) -> Self {
+        Setup::_new(
+            blockchain,
+            Some(migration_config(migrations)),
+            true,
+            update_state,
         )
     }
 
@@ -289,6 +315,7 @@ impl Setup {
                     amount: amount.into(),
                     symbol,
                     memo: None,
+                    decimal_amount: None,
                 },
             )
             .map(|_| ())
@@ -426,6 +453,7 @@ impl Setup {
                 symbol,
                 amount: amount.into(),
                 memo: None,
+                decimal_amount: None,
             }),
         )
     }
@@ -560,6 +588,7 @@ fn event_from_kind(
         symbol: *MFX_SYMBOL,
         amount: TokenAmount::from(10u16),
         memo: None,
+        decimal_amount: None,
     });
 
     match event {
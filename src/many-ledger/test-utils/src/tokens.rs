@@ -1,6 +1,8 @@
 use crate::Setup;
+use async_channel::unbounded;
 use many_error::ManyError;
 use many_modules::ledger::{LedgerTokensModuleBackend, TokenInfoArgs, TokenInfoReturns};
+use many_protocol::{context::Context, RequestMessage};
 use many_types::ledger::Symbol;
 
 pub fn info(h: &Setup, symbol: Symbol) -> Result<TokenInfoReturns, ManyError> {
@@ -11,5 +13,6 @@ pub fn info(h: &Setup, symbol: Symbol) -> Result<TokenInfoReturns, ManyError> {
             symbol,
             extended_info: None,
         },
+        Context::new(RequestMessage::default(), unbounded().0),
     )
 }
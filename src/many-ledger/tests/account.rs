@@ -49,6 +49,38 @@ fn create() {
         .contains(&account::Role::Owner));
 }
 
+#[test]
+/// Verify creating an account again with the same nonce returns the same account
+fn create_with_nonce() {
+    let SetupWithArgs {
+        mut module_impl,
+        id,
+        mut args,
+    } = setup_with_args(AccountType::Multisig);
+    args.nonce = Some(b"wallet-1".to_vec().into());
+
+    let first = module_impl.create(&id, args.clone()).unwrap().id;
+    assert_eq!(module_impl.create(&id, args.clone()).unwrap().id, first);
+
+    // Nonces are scoped to the sender.
+    let other = module_impl.create(&identity(4), args.clone()).unwrap().id;
+    assert_ne!(other, first);
+
+    args.nonce = Some(b"wallet-2".to_vec().into());
+    let second = module_impl.create(&id, args.clone()).unwrap().id;
+    assert_ne!(second, first);
+    assert_eq!(
+        second.subresource_id(),
+        other.subresource_id().map(|id| id + 1)
+    );
+
+    args.nonce = Some(vec![0; account::MAX_CREATE_NONCE_SIZE + 1].into());
+    assert_many_err(
+        module_impl.create(&id, args),
+        account::errors::nonce_too_long(account::MAX_CREATE_NONCE_SIZE),
+    );
+}
+
 #[test]
 /// Verify we can't create an account with roles unsupported by feature
 fn create_invalid_role() {
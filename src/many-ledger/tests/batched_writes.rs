@@ -0,0 +1,88 @@
+use many_identity::testing::identity;
+use many_ledger::migration::batched_writes::BATCHED_WRITES_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::abci_backend::ManyAbciModuleBackend;
+use many_modules::events::{EventsModuleBackend, ListArgs};
+use std::time::Instant;
+
+fn setup(batched: bool) -> Setup {
+    let mut harness = if batched {
+        Setup::new_with_migrations(true, [(0, &BATCHED_WRITES_MIGRATION)], true)
+    } else {
+        Setup::new(true)
+    };
+    harness.set_balance(identity(1), 1_000_000, *MFX_SYMBOL);
+    harness
+}
+
+/// Send from identity 1 to identities 2 and 3, which pass some of it on
+/// within the same block.
+fn sends(harness: &mut Setup) {
+    harness.block(|h| {
+        h.send_(identity(1), identity(2), 100u32);
+        h.send_(identity(2), identity(3), 60u32);
+        h.send_(identity(3), identity(2), 10u32);
+        h.send_(identity(1), identity(3), 5u32);
+    });
+    harness.block(|h| {
+        h.send_(identity(2), identity(1), 50u32);
+        assert!(h
+            .send(identity(3), identity(1), 1_000u32, *MFX_SYMBOL)
+            .is_err());
+    });
+}
+
+#[test]
+fn same_balances() {
+    let mut harness = setup(false);
+    let mut batched = setup(true);
+    sends(&mut harness);
+    sends(&mut batched);
+
+    for i in 1..=3 {
+        assert_eq!(harness.balance_(identity(i)), batched.balance_(identity(i)));
+    }
+    assert_eq!(batched.balance_(identity(2)), 0u32);
+    assert_eq!(batched.balance_(identity(3)), 55u32);
+
+    let events = |h: &Setup| {
+        let events = h.module_impl.list(ListArgs::default()).unwrap().events;
+        minicbor::to_vec(events).unwrap()
+    };
+    assert_eq!(events(&harness), events(&batched));
+}
+
+#[test]
+fn same_hash_on_every_node() {
+    let mut first = setup(true);
+    let mut second = setup(true);
+    sends(&mut first);
+    sends(&mut second);
+
+    let hash = |h: &Setup| h.module_impl.info().unwrap().hash;
+    assert_eq!(hash(&first), hash(&second));
+}
+
+/// Compare the time to execute blocks of sends to many accounts, with and
+/// without batched writes. Run with
+/// `cargo test -p many-ledger --release --test batched_writes -- --ignored --nocapture`.
+#[ignore]
+#[test]
+fn block_benchmark() {
+    for count in [1_000u32, 5_000] {
+        for batched in [false, true] {
+            let mut harness = setup(batched);
+            let start = Instant::now();
+            harness.block(|h| {
+                for i in 0..count {
+                    h.send_(identity(1), identity(2 + i), 1u32);
+                }
+            });
+            let elapsed = start.elapsed();
+            println!(
+                "batched {batched:>5}: {count} sends in {elapsed:?} ({:.0} sends/s)",
+                f64::from(count) / elapsed.as_secs_f64()
+            );
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_ledger::error;
+use many_ledger::migration::bonding_curve::BONDING_CURVE_MIGRATION;
+use many_ledger::migration::token_create::TOKEN_CREATE_MIGRATION;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{
+    self, BondingCurve, BuyArgs, CurveInfoArgs, LedgerCurveModuleBackend,
+    LedgerMintBurnModuleBackend, LedgerTokensModuleBackend, LinearCurve, SellArgs,
+    TokenCreateWithCurveArgs, TokenMintArgs,
+};
+use many_types::ledger::{LedgerTokensAddressMap, Symbol, TokenAmount, TokenInfoSummary};
+
+/// Setup with a reserve token, of which identity(1) holds 1,000,000.
+fn curve_setup() -> (Setup, Symbol) {
+    let mut harness = Setup::new_with_migrations(
+        false,
+        [
+            (0, &TOKEN_MIGRATION),
+            (0, &TOKEN_CREATE_MIGRATION),
+            (0, &BONDING_CURVE_MIGRATION),
+        ],
+        true,
+    );
+    let mut args = default_token_create_args(None, None);
+    args.summary.ticker = "RES".to_string();
+    args.initial_distribution = Some(LedgerTokensAddressMap::from([(
+        identity(1),
+        TokenAmount::from(1_000_000u64),
+    )]));
+    let reserve = harness
+        .module_impl
+        .create(&identity(1), args)
+        .unwrap()
+        .info
+        .symbol;
+    (harness, reserve)
+}
+
+fn curve_args(reserve: Symbol) -> TokenCreateWithCurveArgs {
+    TokenCreateWithCurveArgs {
+        summary: TokenInfoSummary {
+            name: "Curve Token".to_string(),
+            ticker: "CRV".to_string(),
+            decimals: 9,
+        },
+        owner: None,
+        maximum_supply: None,
+        extended_info: None,
+        memo: None,
+        reserve,
+        // price(s) = 10 + s / 2
+        curve: BondingCurve::Linear(LinearCurve {
+            base_price: 10u64.into(),
+            slope: 1u64.into(),
+            scale: 2u64.into(),
+        }),
+    }
+}
+
+fn create_curve_token(h: &mut Setup, reserve: Symbol) -> Symbol {
+    h.module_impl
+        .create_with_curve(&identity(1), curve_args(reserve))
+        .unwrap()
+        .info
+        .symbol
+}
+
+fn buy(
+    h: &mut Setup,
+    sender: u32,
+    symbol: Symbol,
+    amount: u64,
+    maximum_cost: Option<u64>,
+) -> Result<TokenAmount, ManyError> {
+    h.module_impl
+        .buy(
+            &identity(sender),
+            BuyArgs {
+                symbol,
+                amount: amount.into(),
+                maximum_cost: maximum_cost.map(TokenAmount::from),
+                memo: None,
+            },
+        )
+        .map(|r| r.cost)
+}
+
+fn sell(
+    h: &mut Setup,
+    sender: u32,
+    symbol: Symbol,
+    amount: u64,
+    minimum_proceeds: Option<u64>,
+) -> Result<TokenAmount, ManyError> {
+    h.module_impl
+        .sell(
+            &identity(sender),
+            SellArgs {
+                symbol,
+                amount: amount.into(),
+                minimum_proceeds: minimum_proceeds.map(TokenAmount::from),
+                memo: None,
+            },
+        )
+        .map(|r| r.proceeds)
+}
+
+#[test]
+fn buy_and_sell() {
+    let (mut harness, reserve) = curve_setup();
+    let symbol = create_curve_token(&mut harness, reserve);
+
+    // 10 * 100 + 100^2 / 4
+    assert_eq!(buy(&mut harness, 1, symbol, 100, None).unwrap(), 3500u64);
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 100u64);
+    assert_eq!(harness.balance(identity(1), reserve).unwrap(), 996_500u64);
+
+    let info = harness
+        .module_impl
+        .curve_info(CurveInfoArgs { symbol })
+        .unwrap();
+    assert_eq!(info.reserve, reserve);
+    assert_eq!(info.pool, 3500u64);
+    assert_eq!(info.supply, 100u64);
+    assert_eq!(info.price, 60u64);
+
+    // Selling half returns the upper part of the area under the curve.
+    assert_eq!(sell(&mut harness, 1, symbol, 50, None).unwrap(), 2375u64);
+    assert_eq!(sell(&mut harness, 1, symbol, 50, None).unwrap(), 1125u64);
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 0u64);
+    assert_eq!(harness.balance(identity(1), reserve).unwrap(), 1_000_000u64);
+
+    let info = harness
+        .module_impl
+        .curve_info(CurveInfoArgs { symbol })
+        .unwrap();
+    assert_eq!(info.pool, 0u64);
+    assert_eq!(info.supply, 0u64);
+}
+
+#[test]
+fn slippage() {
+    let (mut harness, reserve) = curve_setup();
+    let symbol = create_curve_token(&mut harness, reserve);
+
+    assert_many_err(
+        buy(&mut harness, 1, symbol, 100, Some(3499)),
+        ledger::cost_over_maximum(3500, 3499),
+    );
+    buy(&mut harness, 1, symbol, 100, Some(3500)).unwrap();
+
+    assert_many_err(
+        sell(&mut harness, 1, symbol, 100, Some(3501)),
+        ledger::proceeds_under_minimum(3500, 3501),
+    );
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 100u64);
+}
+
+#[test]
+fn insufficient_funds() {
+    let (mut harness, reserve) = curve_setup();
+    let symbol = create_curve_token(&mut harness, reserve);
+
+    assert_many_err(
+        buy(&mut harness, 2, symbol, 1, None),
+        error::insufficient_funds(),
+    );
+    buy(&mut harness, 1, symbol, 10, None).unwrap();
+    assert_many_err(
+        sell(&mut harness, 1, symbol, 11, None),
+        error::insufficient_funds(),
+    );
+    assert_many_err(
+        sell(&mut harness, 2, symbol, 1, None),
+        error::insufficient_funds(),
+    );
+}
+
+#[test]
+fn not_a_curve_token() {
+    let (mut harness, reserve) = curve_setup();
+
+    assert_many_err(
+        buy(&mut harness, 1, reserve, 1, None),
+        ledger::not_a_curve_token(reserve),
+    );
+}
+
+#[test]
+fn invalid_reserve() {
+    let (mut harness, reserve) = curve_setup();
+    let symbol = create_curve_token(&mut harness, reserve);
+
+    let mut args = curve_args(symbol);
+    args.summary.ticker = "CRV2".to_string();
+    assert_many_err(
+        harness.module_impl.create_with_curve(&identity(1), args),
+        ledger::invalid_reserve(symbol),
+    );
+
+    let mut args = curve_args(identity(100));
+    args.summary.ticker = "CRV2".to_string();
+    assert_many_err(
+        harness.module_impl.create_with_curve(&identity(1), args),
+        ledger::invalid_reserve(identity(100)),
+    );
+}
+
+#[test]
+fn mint_disabled() {
+    let (mut harness, reserve) = curve_setup();
+    let symbol = create_curve_token(&mut harness, reserve);
+
+    assert_many_err(
+        harness.module_impl.mint(
+            &identity(1),
+            TokenMintArgs {
+                symbol,
+                distribution: LedgerTokensAddressMap::from([(
+                    identity(1),
+                    TokenAmount::from(1u64),
+                )]),
+                memo: None,
+            },
+        ),
+        ledger::supply_follows_curve(symbol),
+    );
+}
+
+#[test]
+fn curve_migration_inactive() {
+    let mut harness = Setup::new_with_migrations(
+        false,
+        [(0, &TOKEN_MIGRATION), (0, &TOKEN_CREATE_MIGRATION)],
+        true,
+    );
+    assert_many_err(
+        harness
+            .module_impl
+            .create_with_curve(&identity(1), curve_args(identity(100))),
+        ManyError::invalid_method_name("tokens.createWithCurve"),
+    );
+    assert_many_err(
+        buy(&mut harness, 1, identity(100), 1, None),
+        ManyError::invalid_method_name("tokens.buy"),
+    );
+}
@@ -0,0 +1,340 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::bridge::BRIDGE_MIGRATION;
+use many_ledger::migration::token_create::TOKEN_CREATE_MIGRATION;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::bridge::{self, BridgeModuleBackend, TransferKind};
+use many_modules::ledger::LedgerTokensModuleBackend;
+use many_types::ledger::{Symbol, TokenAmount};
+use minicbor::bytes::ByteVec;
+use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
+
+/// The identity and token identity of the staging ledger state.
+const LEDGER_IDENTITY: &str = "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow";
+const TOKEN_IDENTITY: &str = "maffbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wijp";
+
+fn token_identity() -> Address {
+    Address::from_str(TOKEN_IDENTITY).unwrap()
+}
+
+fn relayer() -> Address {
+    identity(9)
+}
+
+/// Two ledgers: `a` is native to MFX, and `b` mints it as a wrapped token.
+struct Bridge {
+    a: Setup,
+    a_id: Address,
+    b: Setup,
+    b_id: Address,
+    wrapped: Symbol,
+}
+
+fn bridge_setup() -> Bridge {
+    let migrations = [
+        (0, &TOKEN_MIGRATION),
+        (0, &TOKEN_CREATE_MIGRATION),
+        (0, &BRIDGE_MIGRATION),
+    ];
+    let mut a = Setup::new_with_migrations(true, migrations, true);
+    let a_id = Address::from_str(LEDGER_IDENTITY).unwrap();
+    let b_id = identity(50);
+    let mut b = Setup::new_with_state(true, migrations, |state| state.identity = b_id);
+
+    let mut args = default_token_create_args(None, None);
+    args.summary.ticker = "WMFX".to_string();
+    args.initial_distribution = None;
+    let wrapped = b
+        .module_impl
+        .create(&identity(1), args)
+        .unwrap()
+        .info
+        .symbol;
+
+    register(&mut a, b_id, BTreeMap::new()).unwrap();
+    register(&mut b, a_id, BTreeMap::from([(*MFX_SYMBOL, wrapped)])).unwrap();
+    a.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+
+    Bridge {
+        a,
+        a_id,
+        b,
+        b_id,
+        wrapped,
+    }
+}
+
+fn register(
+    harness: &mut Setup,
+    chain: Address,
+    wrapped: BTreeMap<Symbol, Symbol>,
+) -> Result<(), ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl.register(
+            &token_identity(),
+            bridge::RegisterArgs {
+                chain,
+                relayers: BTreeSet::from([relayer()]),
+                wrapped,
+            },
+        )
+    });
+    result.map(|_| ())
+}
+
+fn locked(harness: &Setup, chain: Address) -> TokenAmount {
+    BridgeModuleBackend::info(&harness.module_impl, bridge::InfoArgs { chain })
+        .unwrap()
+        .locked
+        .get(&MFX_SYMBOL)
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn lock(bridge: &mut Bridge, amount: u32) -> Result<ByteVec, ManyError> {
+    let destination = bridge.b_id;
+    let (_, result) = bridge.a.block(|h| {
+        h.module_impl.lock(
+            &identity(1),
+            bridge::LockArgs {
+                destination,
+                recipient: identity(2),
+                symbol: *MFX_SYMBOL,
+                amount: amount.into(),
+                memo: None,
+            },
+        )
+    });
+    result.map(|r| r.id)
+}
+
+/// Relay a transfer from `source` to `destination`, anchoring the current
+/// state hash of the source.
+fn relay(
+    source: &Setup,
+    source_id: Address,
+    destination: &mut Setup,
+    id: &ByteVec,
+) -> Result<(), ManyError> {
+    let info = source
+        .module_impl
+        .transfer(bridge::TransferArgs { id: id.clone() })?;
+    let (_, result) = destination.block(|h| {
+        h.module_impl.anchor(
+            &relayer(),
+            bridge::AnchorArgs {
+                chain: source_id,
+                height: info.height,
+                hash: info.hash.clone(),
+            },
+        )?;
+        h.module_impl.claim(
+            &identity(7),
+            bridge::ClaimArgs {
+                source: source_id,
+                height: info.height,
+                id: id.clone(),
+                proof: info.proof,
+            },
+        )
+    });
+    result.map(|_| ())
+}
+
+#[test]
+fn lock_and_mint() {
+    let mut bridge = bridge_setup();
+    let id = lock(&mut bridge, 100).unwrap();
+
+    assert_eq!(bridge.a.balance_(identity(1)), 900u32);
+    assert_eq!(locked(&bridge.a, bridge.b_id), 100u32);
+    // The locked tokens are held by the address of the counterparty.
+    assert_eq!(bridge.a.balance_(bridge.b_id), 100u32);
+    let info = bridge
+        .a
+        .module_impl
+        .transfer(bridge::TransferArgs { id: id.clone() })
+        .unwrap();
+    assert_eq!(info.transfer.kind, TransferKind::Lock);
+    assert_eq!(info.transfer.source, bridge.a_id);
+    assert_eq!(info.transfer.destination, bridge.b_id);
+
+    relay(&bridge.a, bridge.a_id, &mut bridge.b, &id).unwrap();
+    assert_eq!(
+        bridge.b.balance(identity(2), bridge.wrapped).unwrap(),
+        100u32
+    );
+
+    // A transfer can only be claimed once.
+    assert_eq!(
+        relay(&bridge.a, bridge.a_id, &mut bridge.b, &id)
+            .unwrap_err()
+            .code(),
+        bridge::transfer_already_claimed("").code()
+    );
+}
+
+#[test]
+fn burn_and_release() {
+    let mut bridge = bridge_setup();
+    let id = lock(&mut bridge, 100).unwrap();
+    relay(&bridge.a, bridge.a_id, &mut bridge.b, &id).unwrap();
+
+    let (destination, wrapped) = (bridge.a_id, bridge.wrapped);
+    let (_, result) = bridge.b.block(|h| {
+        h.module_impl.burn(
+            &identity(2),
+            bridge::BurnArgs {
+                destination,
+                recipient: identity(3),
+                symbol: wrapped,
+                amount: 40u32.into(),
+                memo: None,
+            },
+        )
+    });
+    let id = result.unwrap().id;
+    assert_eq!(
+        bridge.b.balance(identity(2), bridge.wrapped).unwrap(),
+        60u32
+    );
+
+    relay(&bridge.b, bridge.b_id, &mut bridge.a, &id).unwrap();
+    assert_eq!(bridge.a.balance_(identity(3)), 40u32);
+    assert_eq!(locked(&bridge.a, bridge.b_id), 60u32);
+}
+
+#[test]
+fn anchors() {
+    let mut bridge = bridge_setup();
+    let chain = bridge.a_id;
+    let anchor = |h: &mut Setup, sender: Address, hash: Vec<u8>| {
+        h.block(|h| {
+            h.module_impl.anchor(
+                &sender,
+                bridge::AnchorArgs {
+                    chain,
+                    height: 1,
+                    hash: hash.into(),
+                },
+            )
+        })
+        .1
+    };
+
+    assert_many_err(
+        anchor(&mut bridge.b, identity(1), vec![1; 32]),
+        bridge::not_a_relayer(identity(1)),
+    );
+    anchor(&mut bridge.b, relayer(), vec![1; 32]).unwrap();
+    anchor(&mut bridge.b, relayer(), vec![1; 32]).unwrap();
+    assert_many_err(
+        anchor(&mut bridge.b, relayer(), vec![2; 32]),
+        bridge::conflicting_anchor(chain, 1),
+    );
+}
+
+#[test]
+fn invalid_proofs() {
+    let mut bridge = bridge_setup();
+    let id = lock(&mut bridge, 100).unwrap();
+    let info = bridge
+        .a
+        .module_impl
+        .transfer(bridge::TransferArgs { id: id.clone() })
+        .unwrap();
+
+    // The proof has to be against an anchored state hash.
+    let (chain, height) = (bridge.a_id, info.height);
+    let claim = |h: &mut Setup, proof: &ByteVec| {
+        h.block(|h| {
+            h.module_impl.claim(
+                &identity(7),
+                bridge::ClaimArgs {
+                    source: chain,
+                    height,
+                    id: id.clone(),
+                    proof: proof.clone(),
+                },
+            )
+        })
+        .1
+    };
+    assert_many_err(
+        claim(&mut bridge.b, &info.proof),
+        bridge::unknown_anchor(chain, height),
+    );
+
+    let (_, result) = bridge.b.block(|h| {
+        h.module_impl.anchor(
+            &relayer(),
+            bridge::AnchorArgs {
+                chain,
+                height,
+                hash: vec![0; 32].into(),
+            },
+        )
+    });
+    result.unwrap();
+    assert_eq!(
+        claim(&mut bridge.b, &info.proof).unwrap_err().code(),
+        bridge::invalid_bridge_proof("").code()
+    );
+    assert_eq!(bridge.b.balance(identity(2), bridge.wrapped).unwrap(), 0u32);
+}
+
+#[test]
+fn lock_errors() {
+    let mut bridge = bridge_setup();
+    assert_many_err(lock(&mut bridge, 2_000), error::insufficient_funds());
+
+    let (_, result) = bridge.a.block(|h| {
+        h.module_impl.lock(
+            &identity(1),
+            bridge::LockArgs {
+                destination: identity(60),
+                recipient: identity(2),
+                symbol: *MFX_SYMBOL,
+                amount: 10u32.into(),
+                memo: None,
+            },
+        )
+    });
+    assert_many_err(result, bridge::unknown_counterparty(identity(60)));
+
+    // Only the token identity can register counterparties.
+    let (_, result) = bridge.a.block(|h| {
+        h.module_impl.register(
+            &identity(1),
+            bridge::RegisterArgs {
+                chain: identity(60),
+                relayers: BTreeSet::new(),
+                wrapped: BTreeMap::new(),
+            },
+        )
+    });
+    assert_many_err(result, error::invalid_sender());
+}
+
+#[test]
+fn bridge_migration_inactive() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+    assert_many_err(
+        harness.module_impl.lock(
+            &identity(1),
+            bridge::LockArgs {
+                destination: identity(50),
+                recipient: identity(2),
+                symbol: *MFX_SYMBOL,
+                amount: 10u32.into(),
+                memo: None,
+            },
+        ),
+        ManyError::invalid_method_name("bridge.lock"),
+    );
+}
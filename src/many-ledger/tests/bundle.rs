@@ -0,0 +1,240 @@
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::module::LedgerModuleImpl;
+use many_ledger_test_utils::*;
+use many_modules::account::features::ledger::AccountLedger;
+use many_modules::account::features::{FeatureInfo, FeatureSet};
+use many_modules::account::{self, Role};
+use many_modules::bundle::{
+    BundleMessage, BundleModule, CompositeArgs, CompositeReturns, ExecuteArgs, ExecuteReturns,
+};
+use many_modules::{events, ledger, ManyModule};
+use many_protocol::RequestMessage;
+use many_types::ledger::TokenAmount;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+fn send_message(to: Address, amount: u32) -> BundleMessage {
+    BundleMessage {
+        method: "ledger.send".to_string(),
+        data: minicbor::to_vec(ledger::SendArgs {
+            from: None,
+            to,
+            amount: TokenAmount::from(amount),
+            symbol: *MFX_SYMBOL,
+            memo: None,
+            decimal_amount: None,
+        })
+        .unwrap()
+        .into(),
+    }
+}
+
+fn create_account_message() -> BundleMessage {
+    BundleMessage {
+        method: "account.create".to_string(),
+        data: minicbor::to_vec(account::CreateArgs {
+            description: Some("Composite".to_string()),
+            roles: Some(BTreeMap::from_iter([(
+                identity(2),
+                BTreeSet::from_iter([Role::CanLedgerTransact]),
+            )])),
+            features: FeatureSet::from_iter([AccountLedger.as_feature()]),
+            nonce: None,
+        })
+        .unwrap()
+        .into(),
+    }
+}
+
+fn balance(module_impl: &Arc<Mutex<LedgerModuleImpl>>, id: Address) -> TokenAmount {
+    module_impl
+        .lock()
+        .unwrap()
+        .storage()
+        .get_balance(&id, &MFX_SYMBOL)
+        .unwrap()
+}
+
+fn events_total(module_impl: &Arc<Mutex<LedgerModuleImpl>>) -> u64 {
+    events::EventsModuleBackend::info(&*module_impl.lock().unwrap(), events::InfoArgs {})
+        .unwrap()
+        .total
+}
+
+fn bundle_setup() -> (
+    Arc<Mutex<LedgerModuleImpl>>,
+    BundleModule<LedgerModuleImpl>,
+    Address,
+) {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let module_impl = Arc::new(Mutex::new(harness.module_impl));
+    let module = BundleModule::new(
+        module_impl.clone(),
+        vec![
+            Arc::new(ledger::LedgerCommandsModule::new(module_impl.clone())),
+            Arc::new(account::AccountModule::new(module_impl.clone())),
+        ],
+    );
+    (module_impl, module, id)
+}
+
+async fn call(
+    module: &BundleModule<LedgerModuleImpl>,
+    method: &str,
+    id: Address,
+    args: ExecuteArgs,
+) -> Result<ExecuteReturns, many_error::ManyError> {
+    let message = RequestMessage::default()
+        .with_method(method.to_string())
+        .with_data(minicbor::to_vec(args).unwrap())
+        .with_from(id);
+    let data = module.execute(message).await?.data?;
+    Ok(minicbor::decode(&data).unwrap())
+}
+
+async fn execute(
+    module: &BundleModule<LedgerModuleImpl>,
+    id: Address,
+    args: ExecuteArgs,
+) -> Result<ExecuteReturns, many_error::ManyError> {
+    call(module, "bundle.execute", id, args).await
+}
+
+async fn composite(
+    module: &BundleModule<LedgerModuleImpl>,
+    id: Address,
+    args: CompositeArgs,
+) -> Result<CompositeReturns, many_error::ManyError> {
+    call(module, "base.composite", id, args).await
+}
+
+#[tokio::test]
+async fn bundle_execute() {
+    let (module_impl, module, id) = bundle_setup();
+
+    let result = execute(
+        &module,
+        id,
+        ExecuteArgs {
+            messages: vec![
+                send_message(identity(1), 100),
+                send_message(identity(2), 200),
+            ],
+        },
+    )
+    .await;
+    assert_eq!(result.unwrap().results.len(), 2);
+
+    assert_eq!(balance(&module_impl, id), 700u32);
+    assert_eq!(balance(&module_impl, identity(1)), 100u32);
+    assert_eq!(balance(&module_impl, identity(2)), 200u32);
+    assert_eq!(events_total(&module_impl), 2);
+}
+
+#[tokio::test]
+async fn bundle_execute_all_or_nothing() {
+    let (module_impl, module, id) = bundle_setup();
+
+    // The last message fails for insufficient funds, so the first two are reverted.
+    let result = execute(
+        &module,
+        id,
+        ExecuteArgs {
+            messages: vec![
+                send_message(identity(1), 100),
+                send_message(identity(2), 200),
+                send_message(identity(3), 10_000),
+            ],
+        },
+    )
+    .await;
+    assert!(result.is_err());
+
+    assert_eq!(balance(&module_impl, id), 1_000u32);
+    assert_eq!(balance(&module_impl, identity(1)), 0u32);
+    assert_eq!(balance(&module_impl, identity(2)), 0u32);
+    assert_eq!(events_total(&module_impl), 0);
+
+    // The ledger is still usable afterward.
+    let result = execute(
+        &module,
+        id,
+        ExecuteArgs {
+            messages: vec![send_message(identity(1), 100)],
+        },
+    )
+    .await;
+    assert!(result.is_ok());
+    assert_eq!(balance(&module_impl, identity(1)), 100u32);
+    assert_eq!(events_total(&module_impl), 1);
+}
+
+#[tokio::test]
+async fn composite_across_modules() {
+    let (module_impl, module, id) = bundle_setup();
+
+    let result = composite(
+        &module,
+        id,
+        CompositeArgs {
+            messages: vec![create_account_message(), send_message(identity(1), 100)],
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(result.results.len(), 2);
+
+    // Each step returns its own result.
+    let created: account::CreateReturn = minicbor::decode(&result.results[0]).unwrap();
+    let (account, _) = module_impl
+        .lock()
+        .unwrap()
+        .storage()
+        .get_account(&created.id)
+        .unwrap();
+    assert!(account.has_role(&identity(2), Role::CanLedgerTransact));
+    assert_eq!(balance(&module_impl, identity(1)), 100u32);
+
+    // On a fresh ledger, the same account is not created if a later step fails.
+    let (module_impl, module, id) = bundle_setup();
+    let result = composite(
+        &module,
+        id,
+        CompositeArgs {
+            messages: vec![create_account_message(), send_message(identity(1), 10_000)],
+        },
+    )
+    .await;
+    assert!(result.is_err());
+    assert!(module_impl
+        .lock()
+        .unwrap()
+        .storage()
+        .get_account(&created.id)
+        .is_err());
+}
+
+#[test]
+fn rollback_bundle_in_block() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    harness.block(|h| {
+        h.send_(id, identity(1), 100u32);
+
+        h.module_impl.storage_mut().begin_bundle().unwrap();
+        h.send_(id, identity(1), 200u32);
+        h.send_(identity(1), identity(2), 300u32);
+        h.module_impl.storage_mut().rollback_bundle().unwrap();
+    });
+
+    // Only the send made before the bundle remains.
+    assert_eq!(harness.balance_(id), 900u32);
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+    assert_eq!(harness.balance_(identity(2)), 0u32);
+}
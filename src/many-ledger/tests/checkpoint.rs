@@ -0,0 +1,53 @@
+use many_ledger::storage::checkpoint::CheckpointConfig;
+use many_ledger_test_utils::*;
+use std::collections::BTreeSet;
+
+fn checkpoints(harness: &Setup) -> BTreeSet<u64> {
+    harness.module_impl.storage().checkpoint_heights().unwrap()
+}
+
+#[test]
+fn checkpoint_retention() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = Setup::new(true);
+    harness
+        .module_impl
+        .storage_mut()
+        .set_checkpoint_config(Some(CheckpointConfig::new(dir.path()).with_retention(2)));
+
+    // Only the checkpoints in the retention window are kept.
+    let (h, _) = harness.block(|_| {});
+    assert_eq!(h, 1);
+    assert_eq!(checkpoints(&harness), BTreeSet::from([1]));
+
+    for _ in 0..3 {
+        harness.block(|_| {});
+    }
+    assert_eq!(checkpoints(&harness), BTreeSet::from([3, 4]));
+
+    harness.block(|_| {});
+    assert_eq!(checkpoints(&harness), BTreeSet::from([4, 5]));
+    assert!(!dir.path().join("3").exists());
+}
+
+#[test]
+fn checkpoints_without_retention() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut harness = Setup::new(true);
+    harness
+        .module_impl
+        .storage_mut()
+        .set_checkpoint_config(Some(CheckpointConfig::new(dir.path())));
+
+    for _ in 0..3 {
+        harness.block(|_| {});
+    }
+    assert_eq!(checkpoints(&harness), BTreeSet::from([1, 2, 3]));
+}
+
+#[test]
+fn checkpoints_disabled() {
+    let mut harness = Setup::new(true);
+    harness.block(|_| {});
+    assert!(checkpoints(&harness).is_empty());
+}
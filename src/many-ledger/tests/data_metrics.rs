@@ -0,0 +1,188 @@
+use async_channel::unbounded;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::data_metrics::DATA_METRICS_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::data::{
+    DataAggregation, DataGetInfoArgs, DataIndex, DataMetric, DataModuleBackend, DataQueryArgs,
+    DataRegisterArgs, DataSource, DataType, DataUnregisterArgs, DataValue,
+};
+use many_protocol::{context::Context, RequestMessage};
+use many_types::VecOrSingle;
+use std::str::FromStr;
+
+/// The identity of the ledger in the staging state, allowed to register
+/// metrics.
+const LEDGER_IDENTITY: &str = "mahukzwuwgt3porn6q4vq4xu3mwy5gyskhouryzbscq7wb2iow";
+
+static INDEX: DataIndex = DataIndex::new(0).with_index(3).with_index(0);
+
+fn ledger() -> Address {
+    Address::from_str(LEDGER_IDENTITY).unwrap()
+}
+
+fn metric(source: DataSource, aggregation: DataAggregation) -> DataMetric {
+    DataMetric {
+        shortname: "metric".to_string(),
+        r#type: DataType::Counter,
+        source,
+        aggregation,
+    }
+}
+
+fn metrics_setup(migration_height: u64) -> Setup {
+    let mut harness =
+        Setup::new_with_migrations(true, [(migration_height, &DATA_METRICS_MIGRATION)], true);
+    harness.set_balance(identity(1), 1000, *MFX_SYMBOL);
+    harness.set_balance(identity(2), 500, *MFX_SYMBOL);
+    harness
+}
+
+fn register(harness: &mut Setup, sender: Address, metric: DataMetric) -> Result<(), ManyError> {
+    harness
+        .block(|h| {
+            h.module_impl.register(
+                &sender,
+                DataRegisterArgs {
+                    index: INDEX,
+                    metric,
+                },
+            )
+        })
+        .1
+        .map(|_| ())
+}
+
+fn query(harness: &Setup) -> Option<u64> {
+    harness
+        .module_impl
+        .query(
+            &harness.id,
+            DataQueryArgs {
+                indices: VecOrSingle(vec![INDEX]),
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap()
+        .remove(&INDEX)
+        .map(|value| match value {
+            DataValue::Counter(count) => count,
+            value => panic!("Unexpected value {value:?}"),
+        })
+}
+
+#[test]
+fn computed_every_block() {
+    let mut harness = metrics_setup(0);
+    register(
+        &mut harness,
+        ledger(),
+        metric(DataSource::Balances(*MFX_SYMBOL), DataAggregation::Sum),
+    )
+    .unwrap();
+    assert_eq!(query(&harness), Some(1500));
+
+    let info = harness
+        .module_impl
+        .get_info(
+            &harness.id,
+            DataGetInfoArgs {
+                indices: VecOrSingle(vec![INDEX]),
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap();
+    assert_eq!(info[&INDEX].shortname, "metric");
+
+    harness.set_balance(identity(3), 250, *MFX_SYMBOL);
+    harness.block(|_| {});
+    assert_eq!(query(&harness), Some(1750));
+}
+
+#[test]
+fn count() {
+    let mut harness = metrics_setup(0);
+    register(
+        &mut harness,
+        ledger(),
+        metric(DataSource::Balances(*MFX_SYMBOL), DataAggregation::Count),
+    )
+    .unwrap();
+    assert_eq!(query(&harness), Some(2));
+}
+
+#[test]
+fn unauthorized() {
+    let mut harness = metrics_setup(0);
+    assert_many_err(
+        register(
+            &mut harness,
+            identity(1),
+            metric(DataSource::Events, DataAggregation::Count),
+        ),
+        error::unauthorized(),
+    );
+    assert_eq!(query(&harness), None);
+}
+
+#[test]
+fn index_exists() {
+    let mut harness = metrics_setup(0);
+    let metric = metric(DataSource::Events, DataAggregation::Count);
+    register(&mut harness, ledger(), metric.clone()).unwrap();
+    assert_many_err(
+        register(&mut harness, ledger(), metric),
+        error::data_index_exists("0.3.0"),
+    );
+}
+
+#[test]
+fn invalid_metric() {
+    let mut harness = metrics_setup(0);
+    assert!(register(
+        &mut harness,
+        ledger(),
+        metric(DataSource::Events, DataAggregation::Sum),
+    )
+    .is_err());
+}
+
+#[test]
+fn unregister() {
+    let mut harness = metrics_setup(0);
+    register(
+        &mut harness,
+        ledger(),
+        metric(DataSource::Events, DataAggregation::Count),
+    )
+    .unwrap();
+    assert!(query(&harness).is_some());
+
+    let (_, result) = harness.block(|h| {
+        h.module_impl
+            .unregister(&ledger(), DataUnregisterArgs { index: INDEX })
+    });
+    result.unwrap();
+    assert_eq!(query(&harness), None);
+
+    let (_, result) = harness.block(|h| {
+        h.module_impl
+            .unregister(&ledger(), DataUnregisterArgs { index: INDEX })
+    });
+    assert_many_err(result, error::data_metric_not_found("0.3.0"));
+}
+
+#[test]
+fn migration_inactive() {
+    let mut harness = metrics_setup(10);
+    assert_many_err(
+        register(
+            &mut harness,
+            ledger(),
+            metric(DataSource::Events, DataAggregation::Count),
+        ),
+        ManyError::invalid_method_name("data.register"),
+    );
+}
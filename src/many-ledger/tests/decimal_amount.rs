@@ -0,0 +1,100 @@
+use many_identity::testing::identity;
+use many_ledger::error;
+use many_ledger::migration::decimal_amount::DECIMAL_AMOUNT_MIGRATION;
+use many_ledger::migration::token_create::TOKEN_CREATE_MIGRATION;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{self, LedgerCommandsModuleBackend, LedgerTokensModuleBackend};
+use many_types::ledger::{DecimalAmount, Symbol};
+
+/// Setup with a 9 decimals token, of which identity(1) holds 123 units.
+fn decimal_setup(decimal_amount_migration: bool) -> (Setup, Symbol) {
+    let mut harness = if decimal_amount_migration {
+        Setup::new_with_migrations(
+            false,
+            [
+                (0, &TOKEN_MIGRATION),
+                (0, &TOKEN_CREATE_MIGRATION),
+                (0, &DECIMAL_AMOUNT_MIGRATION),
+            ],
+            true,
+        )
+    } else {
+        Setup::new_with_migrations(
+            false,
+            [(0, &TOKEN_MIGRATION), (0, &TOKEN_CREATE_MIGRATION)],
+            true,
+        )
+    };
+    let symbol = harness
+        .module_impl
+        .create(&identity(1), default_token_create_args(None, None))
+        .unwrap()
+        .info
+        .symbol;
+    (harness, symbol)
+}
+
+fn send_args(symbol: Symbol, amount: u64, decimal_amount: &str) -> ledger::SendArgs {
+    ledger::SendArgs {
+        from: None,
+        to: identity(2),
+        amount: amount.into(),
+        symbol,
+        memo: None,
+        decimal_amount: Some(decimal_amount.parse().unwrap()),
+    }
+}
+
+#[test]
+fn send_decimal_amount() {
+    let (mut harness, symbol) = decimal_setup(true);
+
+    // Trailing zeros are not part of the precision.
+    for decimal_amount in ["0.00000010", "0.000000010", "0.0000000100"] {
+        harness
+            .module_impl
+            .send(&identity(1), send_args(symbol, 10, decimal_amount))
+            .unwrap();
+    }
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 93u64);
+    assert_eq!(harness.balance(identity(2), symbol).unwrap(), 486u64);
+}
+
+#[test]
+fn send_too_precise() {
+    let (mut harness, symbol) = decimal_setup(true);
+
+    assert_many_err(
+        harness
+            .module_impl
+            .send(&identity(1), send_args(symbol, 1, "0.0000000011")),
+        error::amount_too_precise(DecimalAmount::new(11u32, 10), symbol, 9),
+    );
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 123u64);
+}
+
+#[test]
+fn send_mismatch() {
+    let (mut harness, symbol) = decimal_setup(true);
+
+    assert_many_err(
+        harness
+            .module_impl
+            .send(&identity(1), send_args(symbol, 10, "0.000000001")),
+        error::decimal_amount_mismatch(DecimalAmount::new(1u32, 9), 10u64),
+    );
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 123u64);
+}
+
+#[test]
+fn migration_inactive() {
+    let (mut harness, symbol) = decimal_setup(false);
+
+    // The decimal amount is ignored.
+    harness
+        .module_impl
+        .send(&identity(1), send_args(symbol, 10, "0.0000000011"))
+        .unwrap();
+    assert_eq!(harness.balance(identity(1), symbol).unwrap(), 113u64);
+}
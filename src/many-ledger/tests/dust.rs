@@ -0,0 +1,194 @@
+use many_identity::testing::identity;
+use many_ledger::error;
+use many_ledger::json::{DustConfigJson, DustPolicyJson};
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger::storage::dust::{DustConfig, DustPolicy};
+use many_ledger_test_utils::*;
+use many_modules::ledger::{TokenCreateArgs, TokenUpdateArgs};
+use many_types::ledger::LedgerTokensAddressMap;
+
+/// Balances of MFX are either empty or at least 10.
+fn dust_setup(policy: DustPolicyJson) -> Setup {
+    let mut harness = Setup::new_with_state(true, [(0, &TOKEN_MIGRATION)], |state| {
+        for meta in state.symbols_meta.iter_mut().flat_map(|m| m.values_mut()) {
+            meta.dust = Some(DustConfigJson {
+                threshold: 10u32.into(),
+                policy,
+            });
+        }
+    });
+    harness.set_balance(identity(1), 100, *MFX_SYMBOL);
+    harness
+}
+
+#[test]
+fn reject() {
+    let mut harness = dust_setup(DustPolicyJson::Reject);
+    let (_, result) = harness.block(|h| h.send(identity(1), identity(2), 95u32, *MFX_SYMBOL));
+    assert_many_err(result, error::balance_below_dust_threshold(5, 10));
+
+    let (_, result) = harness.block(|h| h.send(identity(1), identity(2), 5u32, *MFX_SYMBOL));
+    assert_many_err(result, error::amount_below_dust_threshold(5, 10));
+
+    harness.block(|h| {
+        h.send(identity(1), identity(2), 90u32, *MFX_SYMBOL)
+            .unwrap();
+        h.send(identity(1), identity(2), 10u32, *MFX_SYMBOL)
+            .unwrap();
+    });
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+}
+
+#[test]
+fn sweep() {
+    let mut harness = dust_setup(DustPolicyJson::Sweep);
+    harness.block(|h| {
+        h.send(identity(1), identity(2), 95u32, *MFX_SYMBOL)
+            .unwrap()
+    });
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+
+    // Amounts leaving enough are sent as is.
+    harness.block(|h| {
+        h.send(identity(2), identity(3), 50u32, *MFX_SYMBOL)
+            .unwrap()
+    });
+    assert_eq!(harness.balance_(identity(2)), 50u32);
+    assert_eq!(harness.balance_(identity(3)), 50u32);
+}
+
+#[test]
+fn burn() {
+    let mut harness = dust_setup(DustPolicyJson::Sweep);
+    let burn = |harness: &mut Setup, amount: u32| {
+        harness
+            .block(|h| {
+                h.module_impl.storage_mut().burn_token(
+                    *MFX_SYMBOL,
+                    &LedgerTokensAddressMap::from([(identity(1), amount.into())]),
+                )
+            })
+            .1
+            .map(|_| ())
+    };
+
+    // Burns are exact, so the remainder cannot be swept.
+    assert_many_err(
+        burn(&mut harness, 95),
+        error::amount_below_dust_threshold(5, 10),
+    );
+    burn(&mut harness, 100).unwrap();
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+}
+
+#[test]
+fn reservation() {
+    let mut harness = dust_setup(DustPolicyJson::Sweep);
+    let prepare = |harness: &mut Setup, amount: u32| {
+        harness
+            .block(|h| {
+                h.module_impl.storage_mut().prepare_transfer(
+                    &identity(1),
+                    &identity(2),
+                    &MFX_SYMBOL,
+                    amount.into(),
+                    None,
+                )
+            })
+            .1
+    };
+    assert_many_err(
+        prepare(&mut harness, 95).map(|_| ()),
+        error::amount_below_dust_threshold(5, 10),
+    );
+
+    // Committing a transfer below the threshold of its destination fails,
+    // aborting it gives the funds back.
+    let (id, _) = prepare(&mut harness, 5).unwrap();
+    assert_many_err(
+        harness
+            .block(|h| h.module_impl.storage_mut().commit_transfer(&id))
+            .1,
+        error::amount_below_dust_threshold(5, 10),
+    );
+    harness.block(|h| h.module_impl.storage_mut().abort_transfer(&id).unwrap());
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+}
+
+#[test]
+fn create() {
+    let mut harness = Setup::new_with_migrations(true, [(0, &TOKEN_MIGRATION)], true);
+    let create = |harness: &mut Setup, threshold: u32| {
+        let args = TokenCreateArgs {
+            dust: Some(DustConfig {
+                threshold: threshold.into(),
+                policy: DustPolicy::Reject,
+            }),
+            ..default_token_create_args(None, None)
+        };
+        harness
+            .block(|h| h.module_impl.storage_mut().create_token(&identity(1), args))
+            .1
+            .map(|(result, _)| result.info.symbol)
+    };
+
+    // The initial distribution gives 123 to identity(1).
+    assert_many_err(
+        create(&mut harness, 124),
+        error::amount_below_dust_threshold(123, 124),
+    );
+    let symbol = create(&mut harness, 100).unwrap();
+    assert_eq!(
+        harness.module_impl.storage().dust_config(&symbol).unwrap(),
+        Some(DustConfig {
+            threshold: 100u32.into(),
+            policy: DustPolicy::Reject,
+        })
+    );
+}
+
+#[test]
+fn update() {
+    let mut harness = Setup::new_with_migrations(true, [(0, &TOKEN_MIGRATION)], true);
+    harness.set_balance(identity(1), 100, *MFX_SYMBOL);
+    let update = |harness: &mut Setup, threshold: u32| {
+        let args = TokenUpdateArgs {
+            symbol: *MFX_SYMBOL,
+            name: None,
+            ticker: None,
+            decimals: None,
+            owner: None,
+            memo: None,
+            dust: Some(DustConfig {
+                threshold: threshold.into(),
+                policy: DustPolicy::Reject,
+            }),
+        };
+        harness
+            .block(|h| h.module_impl.storage_mut().update_token(&identity(1), args))
+            .1
+            .unwrap();
+    };
+
+    update(&mut harness, 10);
+    let (_, result) = harness.block(|h| h.send(identity(1), identity(2), 95u32, *MFX_SYMBOL));
+    assert_many_err(result, error::balance_below_dust_threshold(5, 10));
+
+    // A zero threshold removes the minimum.
+    update(&mut harness, 0);
+    assert_eq!(
+        harness
+            .module_impl
+            .storage()
+            .dust_config(&MFX_SYMBOL)
+            .unwrap(),
+        None
+    );
+    harness.block(|h| {
+        h.send(identity(1), identity(2), 95u32, *MFX_SYMBOL)
+            .unwrap()
+    });
+    assert_eq!(harness.balance_(identity(1)), 5u32);
+}
@@ -0,0 +1,248 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::escrow::ESCROW_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::escrow::{self, EscrowCondition, EscrowModuleBackend};
+use many_modules::events::{EventInfo, EventsModuleBackend, ListArgs};
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+
+fn escrow_setup() -> Setup {
+    let mut harness = Setup::new_with_migrations(true, [(0, &ESCROW_MIGRATION)], true);
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+    harness
+}
+
+/// Put `amount` MFX of `depositor` in an escrow for `recipient`.
+fn create(
+    harness: &mut Setup,
+    depositor: Address,
+    recipient: Address,
+    amount: u32,
+    condition: EscrowCondition,
+    refund_after: Option<Timestamp>,
+) -> Result<ByteVec, ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl.create(
+            &depositor,
+            escrow::CreateArgs {
+                recipient,
+                symbol: *MFX_SYMBOL,
+                amount: amount.into(),
+                condition,
+                refund_after,
+                memo: None,
+            },
+        )
+    });
+    result.map(|r| r.id)
+}
+
+fn release(
+    harness: &mut Setup,
+    sender: Address,
+    id: &ByteVec,
+    preimage: Option<&[u8]>,
+) -> Result<(), ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl.release(
+            &sender,
+            escrow::ReleaseArgs {
+                id: id.clone(),
+                preimage: preimage.map(|p| p.to_vec().into()),
+            },
+        )
+    });
+    result.map(|_| ())
+}
+
+fn refund(harness: &mut Setup, sender: Address, id: &ByteVec) -> Result<(), ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl
+            .refund(&sender, escrow::RefundArgs { id: id.clone() })
+    });
+    result.map(|_| ())
+}
+
+#[test]
+fn arbiter_release() {
+    let mut harness = escrow_setup();
+    let id = create(
+        &mut harness,
+        identity(1),
+        identity(2),
+        100,
+        EscrowCondition::Arbiter(identity(3)),
+        None,
+    )
+    .unwrap();
+
+    // The funds are locked.
+    assert_eq!(harness.balance_(identity(1)), 900u32);
+    assert_eq!(harness.balance_(identity(2)), 0u32);
+    let info = harness
+        .module_impl
+        .info(escrow::InfoArgs { id: id.clone() })
+        .unwrap();
+    assert_eq!(info.escrow.depositor, identity(1));
+    assert_eq!(info.escrow.amount, 100u32);
+
+    assert_eq!(
+        release(&mut harness, identity(2), &id, None)
+            .unwrap_err()
+            .code(),
+        escrow::cannot_release("").code()
+    );
+    release(&mut harness, identity(3), &id, None).unwrap();
+    assert_eq!(harness.balance_(identity(1)), 900u32);
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+
+    // An escrow can only be settled once.
+    assert_many_err(
+        refund(&mut harness, identity(3), &id),
+        escrow::escrow_not_found(hex::encode(id.as_slice())),
+    );
+
+    let events = harness.module_impl.list(ListArgs::default()).unwrap();
+    assert!(events.events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::EscrowCreate { condition: EscrowCondition::Arbiter(a), .. } if a == &identity(3)
+    )));
+    assert!(events.events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::EscrowRelease { releaser, .. } if releaser == &identity(3)
+    )));
+}
+
+#[test]
+fn time_release() {
+    let mut harness = escrow_setup();
+    // The harness time starts at 1_000_000 and moves one second per block.
+    let id = create(
+        &mut harness,
+        identity(1),
+        identity(2),
+        100,
+        EscrowCondition::Time(Timestamp::new(1_000_100).unwrap()),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        release(&mut harness, identity(2), &id, None)
+            .unwrap_err()
+            .code(),
+        escrow::cannot_release("").code()
+    );
+    harness.inc_time(100);
+    release(&mut harness, identity(2), &id, None).unwrap();
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+}
+
+#[test]
+fn hash_preimage_release() {
+    let mut harness = escrow_setup();
+    let id = create(
+        &mut harness,
+        identity(1),
+        identity(2),
+        100,
+        EscrowCondition::HashPreimage(escrow::hash_preimage(b"secret")),
+        None,
+    )
+    .unwrap();
+
+    assert_eq!(
+        release(&mut harness, identity(4), &id, Some(b"guess"))
+            .unwrap_err()
+            .code(),
+        escrow::cannot_release("").code()
+    );
+    release(&mut harness, identity(4), &id, Some(b"secret")).unwrap();
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+}
+
+#[test]
+fn refunds() {
+    let mut harness = escrow_setup();
+    let id = create(
+        &mut harness,
+        identity(1),
+        identity(2),
+        100,
+        EscrowCondition::Arbiter(identity(3)),
+        Some(Timestamp::new(1_000_100).unwrap()),
+    )
+    .unwrap();
+
+    // The depositor has to wait for the refund time.
+    assert_eq!(
+        refund(&mut harness, identity(1), &id).unwrap_err().code(),
+        escrow::cannot_refund("").code()
+    );
+    harness.inc_time(100);
+    refund(&mut harness, identity(1), &id).unwrap();
+    assert_eq!(harness.balance_(identity(1)), 1_000u32);
+    assert_eq!(harness.balance_(identity(2)), 0u32);
+
+    // The recipient can refuse the funds at any time.
+    let id = create(
+        &mut harness,
+        identity(1),
+        identity(2),
+        100,
+        EscrowCondition::Arbiter(identity(3)),
+        None,
+    )
+    .unwrap();
+    refund(&mut harness, identity(2), &id).unwrap();
+    assert_eq!(harness.balance_(identity(1)), 1_000u32);
+}
+
+#[test]
+fn create_errors() {
+    let mut harness = escrow_setup();
+    assert_many_err(
+        create(
+            &mut harness,
+            identity(1),
+            identity(2),
+            100,
+            EscrowCondition::Arbiter(identity(2)),
+            None,
+        ),
+        escrow::invalid_escrow_condition("the arbiter cannot be a party of the escrow"),
+    );
+
+    harness.set_balance(identity(1), 10, *MFX_SYMBOL);
+    assert_many_err(
+        create(
+            &mut harness,
+            identity(1),
+            identity(2),
+            100,
+            EscrowCondition::Arbiter(identity(3)),
+            None,
+        ),
+        error::insufficient_funds(),
+    );
+}
+
+#[test]
+fn escrow_migration_inactive() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+    assert_many_err(
+        create(
+            &mut harness,
+            identity(1),
+            identity(2),
+            100,
+            EscrowCondition::Arbiter(identity(3)),
+            None,
+        ),
+        ManyError::invalid_method_name("escrow.create"),
+    );
+}
@@ -0,0 +1,155 @@
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::migration::event_index::EVENT_INDEX_MIGRATION;
+use many_ledger::storage::pruning::EventRetention;
+use many_ledger_test_utils::*;
+use many_modules::events::{self, EventKind, EventPredicate, EventsModuleBackend};
+use many_modules::ledger::{self, LedgerCommandsModuleBackend};
+use many_types::ledger::TokenAmount;
+use many_types::{CborRange, SortOrder, VecOrSingle};
+use std::ops::Bound;
+
+type Transfer = (Address, Address, TokenAmount);
+
+fn sent(from: u32, to: u32, amount: u32) -> Transfer {
+    (identity(from), identity(to), amount.into())
+}
+
+/// Execute a block with a send of `amount` from `from` to `to`.
+fn send(harness: &mut Setup, from: Address, to: Address, amount: u32) {
+    harness.set_balance(from, 1000, *MFX_SYMBOL);
+    harness.block(|h| {
+        h.module_impl
+            .send(
+                &from,
+                ledger::SendArgs {
+                    from: Some(from),
+                    to,
+                    amount: amount.into(),
+                    symbol: *MFX_SYMBOL,
+                    memo: None,
+                    decimal_amount: None,
+                },
+            )
+            .unwrap()
+    });
+}
+
+/// Send from identity 1 to 2, 2 to 3 and 3 to 1, with amounts 10, 20 and 30.
+fn send_around(harness: &mut Setup) {
+    send(harness, identity(1), identity(2), 10);
+    send(harness, identity(2), identity(3), 20);
+    send(harness, identity(3), identity(1), 30);
+}
+
+fn list(harness: &Setup, predicate: EventPredicate, order: SortOrder) -> Vec<Transfer> {
+    harness
+        .module_impl
+        .list(events::ListArgs {
+            count: None,
+            order: Some(order),
+            filter: Some(events::EventFilter {
+                predicate: Some(predicate),
+                ..Default::default()
+            }),
+        })
+        .unwrap()
+        .events
+        .into_iter()
+        .map(|event| match event.content {
+            events::EventInfo::Send {
+                from, to, amount, ..
+            } => (from, to, amount),
+            _ => unreachable!(),
+        })
+        .collect()
+}
+
+fn about(i: u32) -> EventPredicate {
+    EventPredicate::Address(VecOrSingle(vec![identity(i)]))
+}
+
+fn amount_above(amount: u32) -> EventPredicate {
+    EventPredicate::Amount(CborRange {
+        start: Bound::Excluded(amount.into()),
+        end: Bound::Unbounded,
+    })
+}
+
+/// The predicates are evaluated the same way with and without the index.
+#[test]
+fn predicates() {
+    for migrations in [vec![], vec![(0, &EVENT_INDEX_MIGRATION)]] {
+        let mut harness = Setup::new_with_migrations(true, migrations, true);
+        send_around(&mut harness);
+        assert_eq!(
+            list(&harness, about(2), SortOrder::Ascending),
+            vec![sent(1, 2, 10), sent(2, 3, 20)]
+        );
+        assert_eq!(
+            list(
+                &harness,
+                EventPredicate::Any(vec![about(1), about(2)]),
+                SortOrder::Descending
+            ),
+            vec![sent(3, 1, 30), sent(2, 3, 20), sent(1, 2, 10)]
+        );
+        assert_eq!(
+            list(
+                &harness,
+                EventPredicate::All(vec![about(3), amount_above(20)]),
+                SortOrder::Ascending
+            ),
+            vec![sent(3, 1, 30)]
+        );
+        assert_eq!(
+            list(&harness, amount_above(10), SortOrder::Ascending),
+            vec![sent(2, 3, 20), sent(3, 1, 30)]
+        );
+        assert_eq!(
+            list(
+                &harness,
+                EventPredicate::All(vec![
+                    EventPredicate::Kind(VecOrSingle(vec![EventKind::Send])),
+                    EventPredicate::Symbol(VecOrSingle(vec![identity(100)])),
+                ]),
+                SortOrder::Ascending
+            ),
+            vec![]
+        );
+    }
+}
+
+/// Events logged before the migration are indexed when it activates.
+#[test]
+fn backfill() {
+    let mut harness = Setup::new_with_migrations(true, [(3, &EVENT_INDEX_MIGRATION)], true);
+    send_around(&mut harness);
+    send(&mut harness, identity(4), identity(2), 40);
+    assert!(harness.module_impl.storage().is_event_index_active());
+
+    assert_eq!(
+        list(&harness, about(2), SortOrder::Ascending),
+        vec![sent(1, 2, 10), sent(2, 3, 20), sent(4, 2, 40)]
+    );
+}
+
+#[test]
+fn pruning() {
+    let mut harness = Setup::new_with_migrations(true, [(0, &EVENT_INDEX_MIGRATION)], true);
+    send_around(&mut harness);
+    harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&EventRetention {
+            height: None,
+            count: Some(1),
+        })
+        .unwrap();
+
+    assert_eq!(
+        list(&harness, about(1), SortOrder::Ascending),
+        vec![sent(3, 1, 30)]
+    );
+    assert_eq!(list(&harness, about(2), SortOrder::Ascending), vec![]);
+}
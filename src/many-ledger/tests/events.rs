@@ -10,6 +10,7 @@ use many_modules::events::{
 };
 use many_modules::ledger;
 use many_modules::ledger::LedgerCommandsModuleBackend;
+use many_types::clock::TestClock;
 use many_types::{CborRange, Memo, Timestamp};
 use proptest::prelude::*;
 use proptest::test_runner::Config;
@@ -32,6 +33,7 @@ fn send_(module_impl: &mut LedgerModuleImpl, from: Address, to: Address) {
             amount: 10u16.into(),
             symbol: *MFX_SYMBOL,
             memo: None,
+            decimal_amount: None,
         },
     );
     assert!(result.is_ok());
@@ -341,3 +343,44 @@ proptest! {
         assert!(result.events.is_empty());
     }
 }
+
+#[test]
+fn list_filter_date_test_clock() {
+    let Setup {
+        mut module_impl,
+        id,
+        ..
+    } = setup();
+    let clock = TestClock::new(Timestamp::new(1_000).unwrap());
+    module_impl.storage_mut().set_clock(clock.clone());
+
+    send(&mut module_impl, id, identity(1));
+    clock.advance(10);
+    send(&mut module_impl, id, identity(2));
+
+    let list = |module_impl: &LedgerModuleImpl, start: u64, end: u64| {
+        module_impl
+            .list(events::ListArgs {
+                count: None,
+                order: None,
+                filter: Some(events::EventFilter {
+                    date_range: Some(CborRange {
+                        start: Bound::Included(Timestamp::new(start).unwrap()),
+                        end: Bound::Included(Timestamp::new(end).unwrap()),
+                    }),
+                    ..events::EventFilter::default()
+                }),
+            })
+            .unwrap()
+            .events
+    };
+
+    let events = list(&module_impl, 1_000, 1_009);
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].time, Timestamp::new(1_000).unwrap());
+    assert!(events[0].is_about(identity(1)));
+
+    let events = list(&module_impl, 1_001, 1_010);
+    assert_eq!(events.len(), 1);
+    assert!(events[0].is_about(identity(2)));
+}
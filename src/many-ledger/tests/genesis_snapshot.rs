@@ -0,0 +1,100 @@
+use async_channel::unbounded;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::json::{GenesisSnapshotJson, InitialStateJson};
+use many_ledger::module::LedgerModuleImpl;
+use many_ledger::storage::snapshot::BalancesSnapshot;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{BalanceArgs, LedgerModuleBackend};
+use many_protocol::{context::Context, RequestMessage};
+use many_types::ledger::TokenAmount;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+fn source() -> (Setup, BalancesSnapshot) {
+    let mut harness = Setup::new(true);
+    let id = harness.id;
+    harness.set_balance(id, 1000, *MFX_SYMBOL);
+    harness.block(|h| h.send_(id, identity(1), 100u32));
+
+    let snapshot = harness.module_impl.storage().balances_snapshot().unwrap();
+    (harness, snapshot)
+}
+
+fn import(dir: &Path, snapshot: &BalancesSnapshot, hash: &str) -> Result<LedgerModuleImpl, String> {
+    let path = dir.join("snapshot.json");
+    std::fs::write(&path, serde_json::to_string(snapshot).unwrap()).unwrap();
+
+    let mut state = InitialStateJson::read("../../staging/ledger_state.json5")
+        .or_else(|_| InitialStateJson::read("staging/ledger_state.json5"))
+        .unwrap();
+    state.initial = BTreeMap::new();
+    state.hash = None;
+    state.genesis_snapshot = Some(GenesisSnapshotJson {
+        path,
+        hash: hash.to_string(),
+    });
+    LedgerModuleImpl::new(state, None, dir.join("store"), false).map_err(|e| e.to_string())
+}
+
+fn balance(module_impl: &LedgerModuleImpl, account: Address) -> TokenAmount {
+    module_impl
+        .balance(
+            &account,
+            BalanceArgs {
+                account: None,
+                symbols: Some(vec![*MFX_SYMBOL].into()),
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap()
+        .balances
+        .get(&*MFX_SYMBOL)
+        .cloned()
+        .unwrap_or_default()
+}
+
+#[test]
+fn verify() {
+    let (harness, snapshot) = source();
+    let balances = snapshot.verify(None).unwrap();
+    assert_eq!(balances.height, 1);
+    assert_eq!(balances.balances[&harness.id][&*MFX_SYMBOL], 900u32);
+    assert_eq!(balances.balances[&identity(1)][&*MFX_SYMBOL], 100u32);
+
+    // Another source hash.
+    assert!(snapshot.verify(Some(&hex::encode([1u8; 32]))).is_err());
+
+    // A proof of another state.
+    let mut forged = snapshot.clone();
+    forged.hash = hex::encode([1u8; 32]);
+    assert!(forged.verify(None).is_err());
+
+    // A modified proof.
+    let mut forged = snapshot;
+    let mut proof = hex::decode(&forged.proof).unwrap();
+    let last = proof.len() - 1;
+    proof[last] ^= 1;
+    forged.proof = hex::encode(proof);
+    assert!(forged.verify(None).is_err());
+}
+
+#[test]
+fn import_genesis() {
+    let (harness, snapshot) = source();
+    let dir = tempfile::tempdir().unwrap();
+
+    let module_impl = import(dir.path(), &snapshot, &snapshot.hash).unwrap();
+    assert_eq!(balance(&module_impl, harness.id), 900u32);
+    assert_eq!(balance(&module_impl, identity(1)), 100u32);
+    assert_eq!(module_impl.storage().get_height().unwrap(), 0);
+}
+
+#[test]
+fn import_genesis_wrong_hash() {
+    let (_, snapshot) = source();
+    let dir = tempfile::tempdir().unwrap();
+
+    let err = import(dir.path(), &snapshot, &hex::encode([1u8; 32])).unwrap_err();
+    assert!(err.contains("snapshot"), "{err}");
+}
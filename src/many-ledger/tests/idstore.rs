@@ -1,5 +1,8 @@
 use many_error::ManyError;
-use many_identity::Address;
+use many_identity::rotation::KeyRotation;
+use many_identity::{Address, Identity};
+use many_identity_dsa::ecdsa::generate_random_ecdsa_identity;
+use many_identity_dsa::ed25519::generate_random_ed25519_identity;
 use many_ledger::module::LedgerModuleImpl;
 use many_ledger_test_utils::*;
 use many_modules::idstore;
@@ -176,3 +179,184 @@ fn get_from_invalid_address() {
         idstore::entry_not_found("".to_string()).code()
     );
 }
+
+#[test]
+/// Verify an identity can rotate its key, and only with a continuity proof
+/// following its latest rotation
+fn rotate_key() {
+    let Setup {
+        mut module_impl, ..
+    } = setup();
+    let original = generate_random_ed25519_identity();
+    let first_key = generate_random_ed25519_identity();
+    let second_key = generate_random_ed25519_identity();
+    let identity = original.address();
+
+    let first = KeyRotation::first(identity, first_key.address());
+    let proof = first.sign(&original, &first_key).unwrap();
+    assert!(module_impl
+        .rotate_key(idstore::RotateKeyArgs { proof })
+        .is_ok());
+
+    let result = module_impl
+        .get_key_rotation(idstore::GetKeyRotationArgs(identity))
+        .unwrap();
+    assert_eq!(result.rotation, Some(first.clone()));
+    assert_eq!(result.identity, None);
+    let result = module_impl
+        .get_key_rotation(idstore::GetKeyRotationArgs(first_key.address()))
+        .unwrap();
+    assert_eq!(result.identity, Some(identity));
+
+    // The original key cannot rotate the key anymore.
+    let proof = first
+        .next(second_key.address())
+        .sign(&first_key, &second_key)
+        .unwrap();
+    let replay = KeyRotation::first(identity, second_key.address())
+        .sign(&original, &second_key)
+        .unwrap();
+    assert!(module_impl
+        .rotate_key(idstore::RotateKeyArgs { proof: replay })
+        .is_err());
+
+    assert!(module_impl
+        .rotate_key(idstore::RotateKeyArgs { proof })
+        .is_ok());
+    let result = module_impl
+        .get_key_rotation(idstore::GetKeyRotationArgs(first_key.address()))
+        .unwrap();
+    assert_eq!(result.identity, None);
+    let result = module_impl
+        .get_key_rotation(idstore::GetKeyRotationArgs(second_key.address()))
+        .unwrap();
+    assert_eq!(result.identity, Some(identity));
+}
+
+#[test]
+/// Verify a key cannot be claimed by two identities
+fn rotate_key_already_used() {
+    let Setup {
+        mut module_impl, ..
+    } = setup();
+    let alice = generate_random_ed25519_identity();
+    let bob = generate_random_ed25519_identity();
+    let key = generate_random_ed25519_identity();
+
+    let proof = KeyRotation::first(alice.address(), key.address())
+        .sign(&alice, &key)
+        .unwrap();
+    assert!(module_impl
+        .rotate_key(idstore::RotateKeyArgs { proof })
+        .is_ok());
+
+    let proof = KeyRotation::first(bob.address(), key.address())
+        .sign(&bob, &key)
+        .unwrap();
+    let result = module_impl.rotate_key(idstore::RotateKeyArgs { proof });
+    assert_eq!(
+        result.unwrap_err().code(),
+        idstore::key_already_used("").code()
+    );
+}
+
+/// Add a new ECDSA credential to the identity of the setup.
+fn add_credential(setup: &mut SetupWithStore, cred_id: u8) -> Result<Address, ManyError> {
+    let key = generate_random_ecdsa_identity();
+    setup
+        .module_impl
+        .add_credential(
+            &setup.id,
+            idstore::AddCredentialArgs {
+                cred_id: CredentialId(vec![cred_id; 16].into()),
+                public_key: PublicKey(key.public_key().unwrap().to_vec().unwrap().into()),
+            },
+        )
+        .map(|_| key.address())
+}
+
+fn revoke_credential(setup: &mut SetupWithStore, cred_id: u8) -> Result<(), ManyError> {
+    setup
+        .module_impl
+        .revoke_credential(
+            &setup.id,
+            idstore::RevokeCredentialArgs {
+                cred_id: CredentialId(vec![cred_id; 16].into()),
+            },
+        )
+        .map(|_| ())
+}
+
+fn revoked_credentials(setup: &SetupWithStore) -> Vec<bool> {
+    setup
+        .module_impl
+        .list_credentials(idstore::ListCredentialsArgs(setup.id))
+        .unwrap()
+        .credentials
+        .into_iter()
+        .map(|c| c.revoked)
+        .collect()
+}
+
+#[test]
+/// Verify any credential of an identity can sign for it until revoked, and
+/// that the last credential cannot be revoked
+fn credentials() {
+    let mut setup = setup_with_store();
+    let id = setup.id;
+    let key = add_credential(&mut setup, 2).unwrap();
+    assert_eq!(revoked_credentials(&setup), vec![false, false]);
+    assert!(setup.module_impl.storage().can_sign(&id, &key).unwrap());
+    assert!(setup.module_impl.storage().can_sign(&id, &id).unwrap());
+
+    let result = setup
+        .module_impl
+        .get_from_credential_id(idstore::GetFromCredentialIdArgs(CredentialId(
+            vec![2; 16].into(),
+        )))
+        .unwrap();
+    assert_eq!(result.address, id);
+
+    // Revoke the credential the identity was stored with.
+    assert!(revoke_credential(&mut setup, 1).is_ok());
+    assert_eq!(revoked_credentials(&setup), vec![true, false]);
+    assert!(!setup.module_impl.storage().can_sign(&id, &id).unwrap());
+    assert!(setup.module_impl.storage().can_sign(&id, &key).unwrap());
+
+    assert_eq!(
+        revoke_credential(&mut setup, 2).unwrap_err().code(),
+        idstore::last_credential().code()
+    );
+}
+
+#[test]
+/// Verify credential IDs are unique, and only revocable by their identity
+fn credentials_invalid() {
+    let mut setup = setup_with_store();
+    assert!(add_credential(&mut setup, 2).is_ok());
+    assert_eq!(
+        add_credential(&mut setup, 1).unwrap_err().code(),
+        idstore::existing_entry().code()
+    );
+    assert_eq!(
+        add_credential(&mut setup, 2).unwrap_err().code(),
+        idstore::existing_entry().code()
+    );
+
+    let other = generate_random_ed25519_identity().address();
+    assert!(!setup
+        .module_impl
+        .storage()
+        .can_sign(&other, &setup.id)
+        .unwrap());
+    let result = setup.module_impl.revoke_credential(
+        &other,
+        idstore::RevokeCredentialArgs {
+            cred_id: CredentialId(vec![2; 16].into()),
+        },
+    );
+    assert_eq!(
+        result.unwrap_err().code(),
+        idstore::credential_not_found("").code()
+    );
+}
@@ -0,0 +1,169 @@
+use many_identity::testing::identity;
+use many_ledger::module::LedgerModuleImpl;
+use many_ledger::storage::{LedgerStorage, LAST_APP_HASH_KEY};
+use many_ledger_test_utils::*;
+use many_modules::data::{DataIndex, DataValue};
+use many_modules::ledger::{LedgerCommandsModuleBackend, SendArgs};
+use merk::Op;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+fn send(module_impl: &mut LedgerModuleImpl) -> Result<(), many_error::ManyError> {
+    module_impl
+        .send(
+            &identity(5),
+            SendArgs {
+                from: None,
+                to: identity(6),
+                amount: 10u32.into(),
+                symbol: identity(1000),
+                memo: None,
+                decimal_amount: None,
+            },
+        )
+        .map(|_| ())
+}
+
+fn new_storage(path: &Path) {
+    let symbols = BTreeMap::from([(identity(1000), "MF0".to_string())]);
+    let balances = BTreeMap::from([(
+        identity(5),
+        BTreeMap::from([(identity(1000), 1000u32.into())]),
+    )]);
+    let _ = LedgerStorage::new(path, false)
+        .unwrap()
+        .with_balances(&identity(666), &symbols, &balances)
+        .unwrap()
+        .build()
+        .unwrap();
+}
+
+#[test]
+fn healthy() {
+    let mut harness = Setup::new(true);
+    let id = harness.id;
+    harness.set_balance(id, 1000, *MFX_SYMBOL);
+    for _ in 0..3 {
+        harness.block(|h| h.send_(id, identity(1), 10u32));
+    }
+
+    let report = harness.module_impl.storage().check_integrity().unwrap();
+    assert!(report.is_ok(), "{report}");
+    assert_eq!(report.height, 3);
+    assert!(report.nodes > 0);
+
+    let report = harness
+        .module_impl
+        .storage_mut()
+        .enforce_integrity()
+        .unwrap();
+    assert!(report.is_ok());
+    assert!(harness.module_impl.storage().quarantine().is_none());
+}
+
+#[test]
+fn quarantine() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    new_storage(&path);
+    {
+        let mut module_impl = LedgerModuleImpl::load(None, &path, false).unwrap();
+        send(&mut module_impl).unwrap();
+        assert!(module_impl.storage().check_integrity().unwrap().is_ok());
+    }
+
+    // Break the indices behind the back of the ledger. The Merk tree itself
+    // stays consistent.
+    {
+        let mut merk = merk::Merk::open(&path).unwrap();
+        let balance = format!("/balances/{}/{}", identity(5), identity(1001));
+        merk.apply(&[
+            (balance.into_bytes(), Op::Put(vec![1])),
+            (
+                b"/events_count".to_vec(),
+                Op::Put(5u64.to_be_bytes().to_vec()),
+            ),
+        ])
+        .unwrap();
+        let hash = merk.root_hash().to_vec();
+        merk.commit(&[(LAST_APP_HASH_KEY.to_vec(), Op::Put(hash))])
+            .unwrap();
+    }
+
+    let mut module_impl = LedgerModuleImpl::load(None, &path, false).unwrap();
+    let report = module_impl.storage().check_integrity().unwrap();
+    assert_eq!(report.issues.len(), 2, "{:?}", report.issues);
+    assert!(report.issues[0].description.contains("5, but 1 events"));
+    assert!(report.issues[1].description.contains("unknown symbol"));
+
+    // The storage is read-only once quarantined.
+    module_impl.storage_mut().enforce_integrity().unwrap();
+    assert!(module_impl.storage().quarantine().is_some());
+    let err = send(&mut module_impl).unwrap_err();
+    assert!(err.to_string().contains("quarantined"), "{err}");
+}
+
+#[test]
+fn last_app_hash() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    new_storage(&path);
+
+    // A commit that does not record its root hash, e.g. by another tool.
+    {
+        let mut merk = merk::Merk::open(&path).unwrap();
+        merk.apply(&[(b"/foo".to_vec(), Op::Put(vec![1]))]).unwrap();
+        merk.commit(&[]).unwrap();
+    }
+
+    let module_impl = LedgerModuleImpl::load(None, &path, false).unwrap();
+    let report = module_impl.storage().check_integrity().unwrap();
+    assert_eq!(report.issues.len(), 1, "{:?}", report.issues);
+    assert!(report.issues[0]
+        .description
+        .contains("last committed app hash"));
+}
+
+#[test]
+fn indices() {
+    let path = tempfile::tempdir().unwrap().into_path();
+    new_storage(&path);
+
+    // The account counts, off by one.
+    {
+        let mut merk = merk::Merk::open(&path).unwrap();
+        let attributes = BTreeMap::from([
+            (
+                DataIndex::new(0).with_index(2).with_index(0),
+                DataValue::Counter(2),
+            ),
+            (
+                DataIndex::new(0).with_index(2).with_index(1),
+                DataValue::Counter(1),
+            ),
+        ]);
+        merk.apply(&[(
+            b"/data/attributes".to_vec(),
+            Op::Put(minicbor::to_vec(attributes).unwrap()),
+        )])
+        .unwrap();
+        let hash = merk.root_hash().to_vec();
+        merk.commit(&[(LAST_APP_HASH_KEY.to_vec(), Op::Put(hash))])
+            .unwrap();
+    }
+
+    let mut module_impl = LedgerModuleImpl::load(None, &path, false).unwrap();
+    assert!(module_impl
+        .storage()
+        .check_integrity()
+        .unwrap()
+        .index_issues
+        .is_empty());
+
+    module_impl.storage_mut().set_integrity_check_indices(true);
+    let report = module_impl.storage_mut().enforce_integrity().unwrap();
+    assert_eq!(report.index_issues.len(), 1, "{:?}", report.index_issues);
+    assert!(report.index_issues[0].description.contains("0.2.0"));
+
+    // Out of date indices do not quarantine the storage.
+    assert!(report.is_ok());
+    assert!(module_impl.storage().quarantine().is_none());
+}
@@ -53,6 +53,7 @@ fn illegal_address() {
                 amount: TokenAmount::from(1_000u32),
                 symbol: *MFX_SYMBOL,
                 memo: None,
+                decimal_amount: None,
             },
         )
         .unwrap();
@@ -75,6 +76,7 @@ fn illegal_address() {
                 amount: TokenAmount::from(100u32),
                 symbol: *MFX_SYMBOL,
                 memo: None,
+                decimal_amount: None,
             },
         )
         .is_err());
@@ -87,3 +89,66 @@ fn illegal_address() {
         1_000u32.into(),
     );
 }
+
+#[test]
+fn list_balances() {
+    let other = many_identity::testing::identity(1000);
+    let mut harness = Setup::new_with_state(
+        false,
+        [(0, &many_ledger::migration::vesting::VESTING_MIGRATION)],
+        |state| {
+            state.symbols.insert(other, "OTHER".to_string());
+        },
+    );
+    let id = harness.id;
+    harness.set_balance(id, 100, *MFX_SYMBOL);
+    let mut symbols = [*MFX_SYMBOL, other];
+    symbols.sort();
+
+    let list = |args: ledger::ListBalancesArgs| {
+        harness
+            .module_impl
+            .list_balances(
+                &id,
+                args,
+                Context::new(RequestMessage::default(), unbounded().0),
+            )
+            .unwrap()
+    };
+
+    let page = list(ledger::ListBalancesArgs::default());
+    assert_eq!(
+        page.balances,
+        vec![ledger::SymbolBalance {
+            symbol: *MFX_SYMBOL,
+            amount: 100u32.into(),
+            locked: None,
+        }]
+    );
+    assert_eq!(page.next, None);
+
+    let page = list(ledger::ListBalancesArgs {
+        count: Some(1),
+        include_zero: Some(true),
+        include_locked: Some(true),
+        ..Default::default()
+    });
+    assert_eq!(page.balances.len(), 1);
+    assert_eq!(page.balances[0].symbol, symbols[0]);
+    assert_eq!(page.balances[0].locked, Some(TokenAmount::zero()));
+    assert_eq!(page.next, Some(symbols[0]));
+
+    let page = list(ledger::ListBalancesArgs {
+        after: page.next,
+        include_zero: Some(true),
+        ..Default::default()
+    });
+    assert_eq!(
+        page.balances
+            .iter()
+            .map(|balance| balance.symbol)
+            .collect::<Vec<_>>(),
+        vec![symbols[1]]
+    );
+    assert_eq!(page.next, None);
+}
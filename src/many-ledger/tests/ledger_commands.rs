@@ -19,6 +19,7 @@ proptest! {
             amount: half.into(),
             symbol: *MFX_SYMBOL,
             memo: None,
+            decimal_amount: None,
         });
         assert!(result.is_ok());
         verify_balance(&module_impl, id, *MFX_SYMBOL, (amount - half).into());
@@ -40,6 +41,7 @@ proptest! {
             amount: half.into(),
             symbol: *MFX_SYMBOL,
             memo: None,
+            decimal_amount: None,
         });
         assert!(result.is_ok());
         verify_balance(&module_impl, account_id, *MFX_SYMBOL, (amount - half).into());
@@ -62,6 +64,7 @@ fn send_account_missing_feature() {
             amount: 10u16.into(),
             symbol: *MFX_SYMBOL,
             memo: None,
+            decimal_amount: None,
         },
     );
     assert!(result.is_err());
@@ -83,8 +86,62 @@ fn send_invalid_account() {
             amount: 10u16.into(),
             symbol: *MFX_SYMBOL,
             memo: None,
+            decimal_amount: None,
         },
     );
     assert!(result.is_err());
     assert_eq!(result.unwrap_err().code(), error::unauthorized().code());
 }
+
+#[test]
+fn send_account_method_acl() {
+    use many_modules::account::features::method_acl::MethodAclAccountFeature;
+    use many_modules::account::features::{FeatureInfo, FeatureSet};
+    use many_modules::account::{self, AccountModuleBackend};
+    use std::collections::{BTreeMap, BTreeSet};
+
+    let SetupWithAccount {
+        mut module_impl,
+        account_id,
+        id,
+    } = setup_with_account(AccountType::Multisig);
+    module_impl
+        .set_balance_only_for_testing(account_id, 100, *MFX_SYMBOL)
+        .unwrap();
+    let send = |module_impl: &mut many_ledger::module::LedgerModuleImpl, sender| {
+        module_impl.send(
+            &sender,
+            ledger::SendArgs {
+                from: Some(account_id),
+                to: identity(1),
+                amount: 10u16.into(),
+                symbol: *MFX_SYMBOL,
+                memo: None,
+                decimal_amount: None,
+            },
+        )
+    };
+
+    let acl = MethodAclAccountFeature::new(BTreeMap::from([(
+        identity(5),
+        BTreeSet::from(["ledger.send".to_string()]),
+    )]));
+    module_impl
+        .add_features(
+            &id,
+            account::AddFeaturesArgs {
+                account: account_id,
+                roles: None,
+                features: FeatureSet::from_iter([acl.as_feature()]),
+            },
+        )
+        .unwrap();
+
+    // The account has no ledger feature, but the ACL allows sending.
+    send(&mut module_impl, identity(5)).unwrap();
+    verify_balance(&module_impl, identity(1), *MFX_SYMBOL, 10u16.into());
+    assert_eq!(
+        send(&mut module_impl, identity(6)).unwrap_err().code(),
+        error::unauthorized().code()
+    );
+}
@@ -5,6 +5,7 @@ use many_ledger_test_utils::cucumber::{
 };
 use many_ledger_test_utils::Setup;
 
+use async_channel::unbounded;
 use cucumber::{given, then, when, World};
 use many_error::ManyError;
 use many_identity::Address;
@@ -14,6 +15,7 @@ use many_ledger::module::LedgerModuleImpl;
 use many_modules::events::{EventFilter, EventKind, EventsModuleBackend, ListArgs};
 use many_modules::ledger::extended_info::TokenExtendedInfo;
 use many_modules::ledger::{LedgerTokensModuleBackend, TokenInfoArgs, TokenUpdateArgs};
+use many_protocol::{context::Context, RequestMessage};
 use many_types::cbor::CborNull;
 use many_types::ledger::{TokenInfo, TokenMaybeOwner};
 use many_types::Memo;
@@ -116,6 +118,7 @@ fn when_update_ticker(w: &mut UpdateWorld, id: SomeId) {
             symbol: w.info.symbol,
             ..Default::default()
         },
+        Context::new(RequestMessage::default(), unbounded().0),
     )
     .expect("Unable to fetch token info");
     w.info = res.info;
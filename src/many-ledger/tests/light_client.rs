@@ -0,0 +1,149 @@
+#![cfg(feature = "light-client")]
+use async_channel::unbounded;
+use many_client::light_client::ProofVerifier;
+use many_identity::testing::identity;
+use many_ledger::light_client::LedgerProofVerifier;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::abci_backend::ManyAbciModuleBackend;
+use many_modules::ledger::{
+    BalanceArgs, BalanceReturns, InfoArgs, LedgerModuleBackend, LedgerTokensModuleBackend,
+    TokenInfoArgs,
+};
+use many_protocol::context::{Context, ProofResult};
+use many_protocol::RequestMessage;
+use many_types::{ProofOperation, PROOF};
+
+/// The balances of identity 1 with the request and proof of them.
+fn balance(harness: &Setup) -> (RequestMessage, BalanceReturns, Vec<ProofOperation>) {
+    let args = BalanceArgs {
+        account: None,
+        symbols: None,
+    };
+    let request = RequestMessage::default()
+        .with_method("ledger.balance".to_string())
+        .with_from(identity(1))
+        .with_data(minicbor::to_vec(&args).unwrap())
+        .with_attribute(PROOF);
+
+    let (tx, rx) = unbounded();
+    let result = harness
+        .module_impl
+        .balance(&identity(1), args, Context::new(request.clone(), tx))
+        .unwrap();
+    let ProofResult::Proof(proof) = rx.try_recv().unwrap() else {
+        panic!("The balance is not proven");
+    };
+    (request, result, proof)
+}
+
+/// A request to the harness with a proof, returning the proof.
+fn prove(
+    method: &str,
+    args: &impl minicbor::Encode<()>,
+    f: impl FnOnce(Context),
+) -> (RequestMessage, Vec<ProofOperation>) {
+    let request = RequestMessage::default()
+        .with_method(method.to_string())
+        .with_from(identity(1))
+        .with_data(minicbor::to_vec(args).unwrap())
+        .with_attribute(PROOF);
+    let (tx, rx) = unbounded();
+    f(Context::new(request.clone(), tx));
+    let ProofResult::Proof(proof) = rx.try_recv().unwrap() else {
+        panic!("The result of {method} is not proven");
+    };
+    (request, proof)
+}
+
+#[test]
+fn verify_balance() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+    harness.block(|_| {});
+    let hash = ManyAbciModuleBackend::info(&harness.module_impl)
+        .unwrap()
+        .hash;
+
+    let (request, result, proof) = balance(&harness);
+    let data = minicbor::to_vec(&result).unwrap();
+    assert_eq!(
+        LedgerProofVerifier
+            .state_hash(&request, &data, &proof)
+            .unwrap(),
+        hash.to_vec()
+    );
+
+    // A different balance than the one proven.
+    let mut forged = result.clone();
+    forged.balances.insert(*MFX_SYMBOL, 2_000u32.into());
+    let data = minicbor::to_vec(&forged).unwrap();
+    assert!(LedgerProofVerifier
+        .state_hash(&request, &data, &proof)
+        .is_err());
+
+    // A balance left out of the result.
+    let data = minicbor::to_vec(BalanceReturns {
+        balances: Default::default(),
+    })
+    .unwrap();
+    assert!(LedgerProofVerifier
+        .state_hash(&request, &data, &proof)
+        .is_err());
+}
+
+#[test]
+fn verify_tokens() {
+    let mut harness = Setup::new_with_migrations(true, [(0, &TOKEN_MIGRATION)], true);
+    harness.block(|_| {});
+    let hash = ManyAbciModuleBackend::info(&harness.module_impl)
+        .unwrap()
+        .hash;
+    let verify = |request: &RequestMessage, data: &[u8], proof: &[ProofOperation]| {
+        LedgerProofVerifier.state_hash(request, data, proof)
+    };
+
+    let args = TokenInfoArgs {
+        symbol: *MFX_SYMBOL,
+        extended_info: None,
+    };
+    let mut result = None;
+    let (request, proof) = prove("tokens.info", &args, |context| {
+        result = Some(
+            LedgerTokensModuleBackend::info(
+                &harness.module_impl,
+                &identity(1),
+                args.clone(),
+                context,
+            )
+            .unwrap(),
+        );
+    });
+    let result = result.unwrap();
+    let data = minicbor::to_vec(&result).unwrap();
+    assert_eq!(verify(&request, &data, &proof).unwrap(), hash.to_vec());
+
+    // A different supply than the one proven.
+    let mut forged = result.clone();
+    forged.info.supply.total = 1u64.into();
+    let data = minicbor::to_vec(&forged).unwrap();
+    assert!(verify(&request, &data, &proof).is_err());
+
+    // The tokens listed by ledger.info.
+    let mut result = None;
+    let (request, proof) = prove("ledger.info", &InfoArgs {}, |context| {
+        result = Some(
+            LedgerModuleBackend::info(&harness.module_impl, &identity(1), InfoArgs {}, context)
+                .unwrap(),
+        );
+    });
+    let result = result.unwrap();
+    assert!(result.tokens.contains_key(&*MFX_SYMBOL));
+    let data = minicbor::to_vec(&result).unwrap();
+    assert_eq!(verify(&request, &data, &proof).unwrap(), hash.to_vec());
+
+    let mut forged = result.clone();
+    forged.tokens.get_mut(&*MFX_SYMBOL).unwrap().decimals += 1;
+    let data = minicbor::to_vec(&forged).unwrap();
+    assert!(verify(&request, &data, &proof).is_err());
+}
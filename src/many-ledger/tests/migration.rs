@@ -5,9 +5,11 @@ use many_identity::testing::identity;
 use many_ledger::migration::data::{
     ACCOUNT_COUNT_DATA_ATTRIBUTE, ACCOUNT_TOTAL_COUNT_INDEX, NON_ZERO_ACCOUNT_TOTAL_COUNT_INDEX,
 };
+use many_ledger::migration::names::NAMES_MIGRATION;
 use many_ledger_test_utils::*;
 use many_modules::{
     data::{DataGetInfoArgs, DataModuleBackend, DataQueryArgs},
+    migrations::{self, MigrationsModuleBackend},
     EmptyArg,
 };
 use many_protocol::{context::Context, RequestMessage};
@@ -163,3 +165,57 @@ fn migration_stress() {
     assert_eq!(balance3, 0u32);
     assert_metrics(&harness, 5, 3);
 }
+
+#[test]
+fn migrations_info() {
+    let mut harness = Setup::new_with_migrations(
+        true,
+        [
+            (2, &ACCOUNT_COUNT_DATA_ATTRIBUTE, true),
+            (1, &NAMES_MIGRATION, false),
+        ],
+        false,
+    );
+    harness.block(|_| {});
+
+    let list = harness.module_impl.list(EmptyArg).unwrap();
+    assert_eq!(list.height, 1);
+    assert_eq!(list.plan_hash, migrations::plan_hash(&list.migrations));
+    let names: Vec<_> = list.migrations.iter().map(|m| m.name.as_str()).collect();
+    let mut sorted = names.clone();
+    sorted.sort();
+    assert_eq!(names, sorted);
+    assert!(list
+        .migrations
+        .iter()
+        .filter(
+            |m| m.name != ACCOUNT_COUNT_DATA_ATTRIBUTE.name() && m.name != NAMES_MIGRATION.name()
+        )
+        .all(|m| !m.configured && !m.active));
+
+    let info = |name: &str| {
+        harness
+            .module_impl
+            .info(migrations::InfoArgs {
+                name: name.to_string(),
+            })
+            .map(|info| info.migration)
+    };
+    let data = info(ACCOUNT_COUNT_DATA_ATTRIBUTE.name()).unwrap();
+    assert!(data.configured && data.enabled && !data.active);
+    assert_eq!(data.block_height, Some(2));
+    assert_eq!(data.kind, "Regular");
+    let names = info(NAMES_MIGRATION.name()).unwrap();
+    assert!(names.configured && !names.enabled && !names.active);
+
+    // The plan doesn't change with the migrations activating.
+    harness.block(|_| {});
+    let later = harness.module_impl.list(EmptyArg).unwrap();
+    assert!(info(ACCOUNT_COUNT_DATA_ATTRIBUTE.name()).unwrap().active);
+    assert_eq!(later.plan_hash, list.plan_hash);
+
+    assert_eq!(
+        info("Unknown").unwrap_err().code(),
+        migrations::unknown_migration("").code()
+    );
+}
@@ -0,0 +1,46 @@
+use many_identity::testing::identity;
+use many_ledger::migration::acknowledgment::ACKNOWLEDGMENT_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::events::{EventsModuleBackend, ListArgs};
+use many_modules::ledger::{LedgerCommandsModuleBackend, SendArgs};
+use many_modules::Acknowledgment;
+use many_types::ledger::TokenAmount;
+
+#[test]
+fn acknowledgment_migration() {
+    let mut harness = Setup::new_with_migrations(true, [(3, &ACKNOWLEDGMENT_MIGRATION)], false);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let send = |h: &mut Setup| {
+        h.module_impl
+            .send(
+                &id,
+                SendArgs {
+                    from: None,
+                    to: identity(1),
+                    amount: TokenAmount::from(10u16),
+                    symbol: *MFX_SYMBOL,
+                    memo: None,
+                    decimal_amount: None,
+                },
+            )
+            .unwrap()
+    };
+
+    // Before the migration, commands return an empty acknowledgment.
+    let (_, ack) = harness.block(send);
+    assert_eq!(ack, Acknowledgment::default());
+    let (_, ack) = harness.block(send);
+    assert_eq!(ack, Acknowledgment::default());
+
+    let (height, ack) = harness.block(send);
+    assert_eq!(height, 3);
+
+    let events = harness.module_impl.list(ListArgs::default()).unwrap();
+    let last = events.events.into_iter().map(|e| e.id).max().unwrap();
+    assert_eq!(
+        ack,
+        Acknowledgment::new().with_height(3).with_events([last])
+    );
+}
@@ -0,0 +1,59 @@
+use async_channel::unbounded;
+use many_identity::testing::identity;
+use many_ledger::migration::event_chain::EVENT_CHAIN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::events::{EventsProofModuleBackend, ProveArgs, ProveReturns};
+use many_protocol::context::Context;
+use many_protocol::RequestMessage;
+
+fn prove(harness: &Setup, start: u64, end: u64) -> ProveReturns {
+    harness
+        .module_impl
+        .prove(
+            &harness.id,
+            ProveArgs { start, end },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap()
+}
+
+#[test]
+fn event_chain_migration() {
+    let mut harness = Setup::new_with_migrations(true, [(2, &EVENT_CHAIN_MIGRATION)], false);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    for _ in 0..4 {
+        harness.block(|h| {
+            h.send_(id, identity(1), 10u32);
+            h.send_(id, identity(2), 10u32);
+        });
+    }
+
+    // Events before the migration are not chained.
+    let segment = prove(&harness, 1, 1);
+    assert_eq!(segment.events.len(), 2);
+    assert!(segment.events.iter().all(|e| e.chain_hash.is_none()));
+    assert!(segment.verify().is_err());
+
+    // The chain starts after the unchained events, and ends at the head.
+    let mut segment = prove(&harness, 2, 4);
+    assert_eq!(segment.events.len(), 6);
+    let head = segment.verify().unwrap().map(|h| h.to_vec());
+    assert_eq!(
+        head,
+        harness.module_impl.storage().events_chain_head().unwrap()
+    );
+
+    // A segment in the middle of the chain starts from the previous event.
+    let middle = prove(&harness, 3, 3);
+    assert_eq!(middle.previous, segment.events[1].chain_hash);
+    assert!(middle.verify().is_ok());
+
+    // Removing or reordering events breaks the chain.
+    segment.events.remove(2);
+    assert!(segment.verify().is_err());
+    let mut segment = prove(&harness, 2, 4);
+    segment.events.swap(0, 1);
+    assert!(segment.verify().is_err());
+}
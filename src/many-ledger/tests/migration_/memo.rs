@@ -25,6 +25,7 @@ fn memo_migration_works() {
             symbol: *MFX_SYMBOL,
             amount: TokenAmount::from(10_000u16),
             memo: None,
+            decimal_amount: None,
         });
 
         let memo = match (memo_str, memo_data) {
@@ -1 +1,3 @@
+mod acknowledgment;
+mod event_chain;
 mod memo;
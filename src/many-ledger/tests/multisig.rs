@@ -3,6 +3,8 @@ use {
     many_error::ManyError,
     many_identity::testing::identity,
     many_identity::Address,
+    many_ledger::migration::account_delegation::ACCOUNT_DELEGATION_MIGRATION,
+    many_ledger::migration::multisig_comments::MULTISIG_COMMENTS_MIGRATION,
     many_ledger::module::LedgerModuleImpl,
     many_ledger_test_utils::*,
     many_modules::account::features::multisig::AccountMultisigModuleBackend,
@@ -10,6 +12,7 @@ use {
     many_modules::{account, events, ledger},
     many_protocol::{context::Context, RequestMessage},
     many_types::ledger::TokenAmount,
+    many_types::{Memo, Timestamp},
     proptest::prelude::*,
     proptest::test_runner::Config,
     std::collections::{BTreeMap, BTreeSet},
@@ -556,6 +559,7 @@ fn multisig_send_from_another_identity_owner() {
         symbol: *MFX_SYMBOL,
         amount: TokenAmount::from(10u16),
         memo: None,
+        decimal_amount: None,
     });
 
     // Create a multisig tx on acc1 which sends funds from acc2 to some Address
@@ -617,6 +621,7 @@ fn multisig_send_from_another_identity_with_perm() {
         symbol: *MFX_SYMBOL,
         amount: TokenAmount::from(10u16),
         memo: None,
+        decimal_amount: None,
     });
 
     // Create a multisig tx on acc1 which sends funds from acc2 to some Address
@@ -673,6 +678,7 @@ fn recursive_multisig() {
         symbol: *MFX_SYMBOL,
         amount: TokenAmount::from(10u16),
         memo: None,
+        decimal_amount: None,
     });
 
     let multisig_tx = events::AccountMultisigTransaction::AccountMultisigSubmit(
@@ -734,6 +740,7 @@ fn recursive_multisig() {
         symbol: *MFX_SYMBOL,
         amount: TokenAmount::from(10u16),
         memo: None,
+        decimal_amount: None,
     });
 
     let multisig_tx = events::AccountMultisigTransaction::AccountMultisigSubmit(
@@ -808,3 +815,255 @@ fn approve_executed_tx() {
     let result = setup.multisig_approve(identity(6), &token);
     assert_many_err(result, multisig::errors::transaction_expired_or_withdrawn());
 }
+
+fn comment(
+    setup: &mut Setup,
+    id: Address,
+    token: &minicbor::bytes::ByteVec,
+    text: &str,
+    reply_to: Option<u64>,
+) -> Result<u64, ManyError> {
+    setup
+        .module_impl
+        .multisig_comment(
+            &id,
+            multisig::CommentArgs {
+                token: token.clone(),
+                memo: Memo::try_from(text).unwrap(),
+                reply_to,
+            },
+        )
+        .map(|r| r.id)
+}
+
+#[test]
+/// Verify approvers can comment on a pending transaction, and reply to comments.
+fn comments() {
+    let mut setup = Setup::new_with_migrations(false, [(0, &MULTISIG_COMMENTS_MIGRATION)], true);
+    let account_id = setup.create_account_(AccountType::Multisig);
+    let token = setup.multisig_send_(account_id, identity(1234), 10u16);
+    setup.assert_multisig_info(&token, |i| assert_eq!(i.comments, None));
+
+    assert_eq!(
+        comment(&mut setup, identity(2), &token, "Why?", None),
+        Ok(0)
+    );
+    let id = setup.id;
+    assert_eq!(comment(&mut setup, id, &token, "Rent", Some(0)), Ok(1));
+
+    // Only approvers can comment, and only reply to existing comments.
+    assert_many_err(
+        comment(&mut setup, identity(6), &token, "Hi", None),
+        multisig::errors::user_cannot_approve_transaction(),
+    );
+    assert_many_err(
+        comment(&mut setup, identity(3), &token, "Hi", Some(2)),
+        multisig::errors::comment_cannot_be_found(),
+    );
+
+    setup.assert_multisig_info(&token, |i| {
+        let comments = i.comments.unwrap();
+        assert_eq!(comments.len(), 2);
+        assert_eq!(comments[0].author, identity(2));
+        assert_eq!(comments[0].memo, Memo::try_from("Why?").unwrap());
+        assert_eq!(comments[0].reply_to, None);
+        assert_eq!(comments[1].author, id);
+        assert_eq!(comments[1].reply_to, Some(0));
+    });
+
+    // The transaction is not pending anymore.
+    setup
+        .module_impl
+        .multisig_withdraw(
+            &id,
+            multisig::WithdrawArgs {
+                token: token.clone(),
+            },
+        )
+        .unwrap();
+    assert_many_err(
+        comment(&mut setup, id, &token, "Done", None),
+        multisig::errors::transaction_expired_or_withdrawn(),
+    );
+}
+
+#[test]
+fn comments_need_migration() {
+    let mut setup = Setup::new(false);
+    let account_id = setup.create_account_(AccountType::Multisig);
+    let token = setup.multisig_send_(account_id, identity(1234), 10u16);
+    let id = setup.id;
+    assert_many_err(
+        comment(&mut setup, id, &token, "Why?", None),
+        ManyError::invalid_method_name("account.multisigComment"),
+    );
+}
+
+fn delegate(
+    setup: &mut Setup,
+    id: Address,
+    account: Address,
+    delegate: Address,
+    start: Option<u64>,
+    end: u64,
+) -> Result<(), ManyError> {
+    setup
+        .block(|h| {
+            h.module_impl.delegate(
+                &id,
+                multisig::DelegateArgs {
+                    account,
+                    delegate,
+                    start: start.map(|s| Timestamp::new(s).unwrap()),
+                    end: Timestamp::new(end).unwrap(),
+                    memo: None,
+                },
+            )
+        })
+        .1
+        .map(|_| ())
+}
+
+fn revoke_delegation(setup: &mut Setup, id: Address, account: Address) -> Result<(), ManyError> {
+    setup
+        .block(|h| {
+            h.module_impl.revoke_delegation(
+                &id,
+                multisig::RevokeDelegationArgs {
+                    account,
+                    memo: None,
+                },
+            )
+        })
+        .1
+        .map(|_| ())
+}
+
+#[test]
+/// Verify a delegate approves for its delegator, until the delegation is revoked.
+fn delegation() {
+    let mut setup = Setup::new_with_migrations(true, [(0, &ACCOUNT_DELEGATION_MIGRATION)], true);
+    let id = setup.id;
+    let (_, account_id) = setup.block(|h| h.create_account_(AccountType::Multisig));
+    let (_, token) = setup.block(|h| h.multisig_send_(account_id, identity(1234), 10u16));
+    delegate(&mut setup, id, account_id, identity(5), None, 2_000_000).unwrap();
+
+    let delegations = setup
+        .module_impl
+        .delegations(
+            &id,
+            multisig::DelegationsArgs {
+                account: account_id,
+            },
+        )
+        .unwrap()
+        .delegations;
+    assert_eq!(delegations.len(), 1);
+    assert_eq!(delegations[0].delegator, id);
+    assert_eq!(delegations[0].delegate, identity(5));
+
+    setup
+        .block(|h| h.multisig_approve(identity(5), &token))
+        .1
+        .unwrap();
+    setup.assert_multisig_info(&token, |i| {
+        assert!(i.approvers[&id].approved);
+        assert!(!i.approvers.contains_key(&identity(5)));
+    });
+
+    revoke_delegation(&mut setup, id, account_id).unwrap();
+    assert_many_err(
+        setup.block(|h| h.multisig_approve(identity(5), &token)).1,
+        multisig::errors::user_cannot_approve_transaction(),
+    );
+    assert_many_err(
+        revoke_delegation(&mut setup, id, account_id),
+        multisig::errors::delegation_cannot_be_found(),
+    );
+}
+
+#[test]
+/// Verify delegations only apply in their time window.
+fn delegation_window() {
+    let mut setup = Setup::new_with_migrations(true, [(0, &ACCOUNT_DELEGATION_MIGRATION)], true);
+    let (_, account_id) = setup.block(|h| h.create_account_(AccountType::Multisig));
+    let (_, token) = setup.block(|h| h.multisig_send_(account_id, identity(1234), 10u16));
+    delegate(
+        &mut setup,
+        identity(2),
+        account_id,
+        identity(5),
+        Some(1_000_100),
+        1_000_200,
+    )
+    .unwrap();
+
+    // Not started yet.
+    assert_many_err(
+        setup.block(|h| h.multisig_approve(identity(5), &token)).1,
+        multisig::errors::user_cannot_approve_transaction(),
+    );
+
+    setup.inc_time(100);
+    setup
+        .block(|h| h.multisig_approve(identity(5), &token))
+        .1
+        .unwrap();
+    setup.assert_multisig_info(&token, |i| assert!(i.approvers[&identity(2)].approved));
+
+    // Ended.
+    setup.inc_time(100);
+    assert_many_err(
+        setup.block(|h| h.multisig_approve(identity(5), &token)).1,
+        multisig::errors::user_cannot_approve_transaction(),
+    );
+}
+
+#[test]
+fn delegation_invalid() {
+    let mut setup = Setup::new_with_migrations(true, [(0, &ACCOUNT_DELEGATION_MIGRATION)], true);
+    let id = setup.id;
+    let (_, account_id) = setup.block(|h| h.create_account_(AccountType::Multisig));
+
+    assert_many_err(
+        delegate(&mut setup, id, account_id, id, None, 2_000_000),
+        multisig::errors::cannot_delegate_to_self(),
+    );
+    assert_many_err(
+        delegate(&mut setup, id, account_id, identity(5), None, 1_000),
+        multisig::errors::invalid_delegation_window(),
+    );
+    assert_many_err(
+        delegate(
+            &mut setup,
+            id,
+            account_id,
+            identity(5),
+            Some(2_000_000),
+            2_000_000,
+        ),
+        multisig::errors::invalid_delegation_window(),
+    );
+    assert_many_err(
+        delegate(
+            &mut setup,
+            identity(6),
+            account_id,
+            identity(5),
+            None,
+            2_000_000,
+        ),
+        multisig::errors::user_cannot_approve_transaction(),
+    );
+}
+
+#[test]
+fn delegation_needs_migration() {
+    let mut setup = Setup::new(true);
+    let id = setup.id;
+    let (_, account_id) = setup.block(|h| h.create_account_(AccountType::Multisig));
+    assert_many_err(
+        delegate(&mut setup, id, account_id, identity(5), None, 2_000_000),
+        ManyError::invalid_method_name("account.delegate"),
+    );
+}
@@ -0,0 +1,133 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_ledger::migration::names::NAMES_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::events::{EventInfo, EventsModuleBackend, ListArgs};
+use many_modules::names::{self, NamesModuleBackend, RegisterArgs, ResolveArgs, TransferArgs};
+
+fn names_setup() -> Setup {
+    Setup::new_with_migrations(false, [(0, &NAMES_MIGRATION)], true)
+}
+
+fn register(h: &mut Setup, sender: u32, name: &str) -> Result<(), ManyError> {
+    h.module_impl
+        .register(
+            &identity(sender),
+            RegisterArgs {
+                name: name.to_string(),
+                address: None,
+                memo: None,
+            },
+        )
+        .map(|_| ())
+}
+
+fn resolve(h: &Setup, name: &str) -> Result<names::ResolveReturns, ManyError> {
+    h.module_impl.resolve(ResolveArgs {
+        name: name.to_string(),
+    })
+}
+
+fn transfer(h: &mut Setup, sender: u32, name: &str, new_owner: u32) -> Result<(), ManyError> {
+    h.module_impl
+        .transfer(
+            &identity(sender),
+            TransferArgs {
+                name: name.to_string(),
+                new_owner: identity(new_owner),
+                memo: None,
+            },
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn register_and_resolve() {
+    let mut harness = names_setup();
+    register(&mut harness, 1, "alice").unwrap();
+
+    let resolved = resolve(&harness, "alice").unwrap();
+    assert_eq!(resolved.address, identity(1));
+    assert_eq!(resolved.owner, identity(1));
+
+    let events = harness.module_impl.list(ListArgs::default()).unwrap();
+    assert!(events.events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::NameRegister { name, owner, .. } if name == "alice" && owner == &identity(1)
+    )));
+}
+
+#[test]
+fn register_other_address() {
+    let mut harness = names_setup();
+    harness
+        .module_impl
+        .register(
+            &identity(1),
+            RegisterArgs {
+                name: "treasury".to_string(),
+                address: Some(identity(5)),
+                memo: None,
+            },
+        )
+        .unwrap();
+
+    let resolved = resolve(&harness, "treasury").unwrap();
+    assert_eq!(resolved.address, identity(5));
+    assert_eq!(resolved.owner, identity(1));
+}
+
+#[test]
+fn register_errors() {
+    let mut harness = names_setup();
+    register(&mut harness, 1, "alice").unwrap();
+
+    assert_many_err(
+        register(&mut harness, 2, "alice"),
+        names::name_already_registered("alice"),
+    );
+    assert_many_err(
+        register(&mut harness, 2, "Not A Name"),
+        names::invalid_name("Not A Name"),
+    );
+    assert_many_err(resolve(&harness, "bob"), names::name_not_found("bob"));
+}
+
+#[test]
+fn transfer_name() {
+    let mut harness = names_setup();
+    register(&mut harness, 1, "alice").unwrap();
+
+    assert_many_err(
+        transfer(&mut harness, 2, "alice", 2),
+        names::not_name_owner("alice"),
+    );
+    assert_many_err(
+        transfer(&mut harness, 1, "bob", 2),
+        names::name_not_found("bob"),
+    );
+
+    transfer(&mut harness, 1, "alice", 2).unwrap();
+    let resolved = resolve(&harness, "alice").unwrap();
+    assert_eq!(resolved.address, identity(2));
+    assert_eq!(resolved.owner, identity(2));
+
+    // The previous owner cannot transfer it anymore.
+    assert_many_err(
+        transfer(&mut harness, 1, "alice", 1),
+        names::not_name_owner("alice"),
+    );
+}
+
+#[test]
+fn names_migration_inactive() {
+    let mut harness = Setup::new(false);
+    assert_many_err(
+        register(&mut harness, 1, "alice"),
+        ManyError::invalid_method_name("names.register"),
+    );
+    assert_many_err(
+        resolve(&harness, "alice"),
+        ManyError::invalid_method_name("names.resolve"),
+    );
+}
@@ -0,0 +1,222 @@
+use async_channel::unbounded;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::module::observers::ObserverEventsModule;
+use many_ledger_test_utils::*;
+use many_modules::account::features::ledger::AccountLedger;
+use many_modules::account::features::multisig::{self, AccountMultisigModuleBackend};
+use many_modules::account::features::observers::ObserversAccountFeature;
+use many_modules::account::features::{FeatureInfo, FeatureSet};
+use many_modules::account::{self, AccountModuleBackend, Role};
+use many_modules::events;
+use many_modules::ledger::{self, LedgerModuleBackend};
+use many_modules::ManyModule;
+use many_protocol::{context::Context, RequestMessage};
+use minicbor::bytes::ByteVec;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::{Arc, Mutex};
+
+fn context() -> Context {
+    Context::new(RequestMessage::default(), unbounded().0)
+}
+
+/// An account with the observers feature, where `identity(1)` is an observer.
+fn setup_with_account() -> (Setup, Address) {
+    let mut harness = Setup::new(true);
+    let id = harness.id;
+    let (_, account) = harness.block(|harness| {
+        AccountModuleBackend::create(
+            &mut harness.module_impl,
+            &id,
+            account::CreateArgs {
+                description: None,
+                roles: Some(BTreeMap::from([(
+                    identity(1),
+                    BTreeSet::from([Role::Observer]),
+                )])),
+                features: FeatureSet::from_iter([
+                    AccountLedger.as_feature(),
+                    multisig::MultisigAccountFeature::default().as_feature(),
+                    ObserversAccountFeature.as_feature(),
+                ]),
+                nonce: None,
+            },
+        )
+        .unwrap()
+        .id
+    });
+    harness.set_balance(account, 1_000, *MFX_SYMBOL);
+    (harness, account)
+}
+
+fn info(harness: &Setup, sender: Address, account: Address) -> Result<(), ManyError> {
+    harness
+        .module_impl
+        .info(&sender, account::InfoArgs { account }, context())
+        .map(|_| ())
+}
+
+fn balance(harness: &Setup, sender: Address, account: Address) -> Result<(), ManyError> {
+    harness
+        .module_impl
+        .balance(
+            &sender,
+            ledger::BalanceArgs {
+                account: Some(account),
+                symbols: None,
+            },
+            context(),
+        )
+        .map(|_| ())
+}
+
+fn multisig_info(harness: &Setup, sender: Address, token: &ByteVec) -> Result<(), ManyError> {
+    harness
+        .module_impl
+        .multisig_info(
+            &sender,
+            multisig::InfoArgs {
+                token: token.clone(),
+            },
+        )
+        .map(|_| ())
+}
+
+fn events_of(harness: &Setup, sender: Address) -> Vec<events::EventLog> {
+    harness
+        .module_impl
+        .list_events(&sender, events::ListArgs::default())
+        .unwrap()
+        .events
+}
+
+fn account_events_args(account: Address) -> events::ListArgs {
+    events::ListArgs {
+        filter: Some(events::EventFilter {
+            account: Some(vec![account].into()),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn observer_can_query() {
+    let (harness, account) = setup_with_account();
+    let owner = harness.id;
+
+    for sender in [owner, identity(1)] {
+        assert!(info(&harness, sender, account).is_ok());
+        assert!(balance(&harness, sender, account).is_ok());
+    }
+    for sender in [identity(2), Address::anonymous()] {
+        assert_many_err(
+            info(&harness, sender, account),
+            account::errors::user_needs_role(Role::Observer),
+        );
+        assert_many_err(
+            balance(&harness, sender, account),
+            account::errors::user_needs_role(Role::Observer),
+        );
+    }
+}
+
+#[test]
+fn observer_cannot_send() {
+    let (mut harness, account) = setup_with_account();
+    let (_, result) = harness
+        .block(|harness| harness.send_as(identity(1), account, identity(1), 10u32, *MFX_SYMBOL));
+    assert!(result.is_err());
+    assert_eq!(harness.balance_(account), 1_000u32);
+}
+
+#[test]
+fn events_are_hidden() {
+    let (mut harness, account) = setup_with_account();
+    let owner = harness.id;
+    harness.set_balance(owner, 1_000, *MFX_SYMBOL);
+    harness.block(|harness| harness.send_(owner, identity(3), 10u32));
+
+    let is_about_account = |events: &[events::EventLog]| events.iter().any(|e| e.is_about(account));
+    assert!(is_about_account(&events_of(&harness, owner)));
+    assert!(is_about_account(&events_of(&harness, identity(1))));
+
+    // Other senders still see the events that do not concern the account.
+    let events = events_of(&harness, identity(2));
+    assert!(!is_about_account(&events));
+    assert!(events.iter().any(|e| e.is_about(identity(3))));
+
+    let filtered = harness
+        .module_impl
+        .list_events(&identity(2), account_events_args(account));
+    assert_many_err(filtered, account::errors::user_needs_role(Role::Observer));
+}
+
+#[test]
+fn observer_sees_multisig_transactions() {
+    let (mut harness, account) = setup_with_account();
+    let owner = harness.id;
+    let (_, token) = harness.block(|harness| harness.multisig_send_(account, identity(3), 10u32));
+
+    for sender in [owner, identity(1)] {
+        assert!(multisig_info(&harness, sender, &token).is_ok());
+    }
+    for sender in [identity(2), Address::anonymous()] {
+        assert_many_err(
+            multisig_info(&harness, sender, &token),
+            account::errors::user_needs_role(Role::Observer),
+        );
+    }
+}
+
+#[tokio::test]
+async fn events_list_uses_the_sender() {
+    let (harness, account) = setup_with_account();
+    let module = ObserverEventsModule::new(Arc::new(Mutex::new(harness.module_impl)));
+    let message = |sender: Address| {
+        RequestMessage::default()
+            .with_method("events.list".to_string())
+            .with_data(minicbor::to_vec(account_events_args(account)).unwrap())
+            .with_from(sender)
+    };
+
+    let data = module
+        .execute(message(identity(1)))
+        .await
+        .unwrap()
+        .data
+        .unwrap();
+    let events::ListReturns { events, .. } = minicbor::decode(&data).unwrap();
+    assert!(events.iter().any(|e| e.is_about(account)));
+
+    assert_many_err(
+        module.execute(message(identity(2))).await,
+        account::errors::user_needs_role(Role::Observer),
+    );
+}
+
+#[test]
+fn observer_role_needs_feature() {
+    let mut harness = Setup::new(true);
+    let id = harness.id;
+    let (_, result) = harness.block(|harness| {
+        AccountModuleBackend::create(
+            &mut harness.module_impl,
+            &id,
+            account::CreateArgs {
+                description: None,
+                roles: Some(BTreeMap::from([(
+                    identity(1),
+                    BTreeSet::from([Role::Observer]),
+                )])),
+                features: FeatureSet::from_iter([AccountLedger.as_feature()]),
+                nonce: None,
+            },
+        )
+    });
+    assert_many_err(
+        result,
+        account::errors::unknown_role(Role::Observer.to_string()),
+    );
+}
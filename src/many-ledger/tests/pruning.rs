@@ -0,0 +1,222 @@
+use async_channel::unbounded;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_ledger::json::InitialStateJson;
+use many_ledger::migration::event_chain::EVENT_CHAIN_MIGRATION;
+use many_ledger::migration::event_pruning::EVENT_PRUNING_MIGRATION;
+use many_ledger::module::LedgerModuleImpl;
+use many_ledger::storage::pruning::{compact_store, EventRetention};
+use many_ledger::storage::LedgerStorage;
+use many_ledger_test_utils::*;
+use many_migration::MigrationConfig;
+use many_modules::events::{self, EventsModuleBackend, EventsProofModuleBackend};
+use many_modules::ledger::{self, LedgerCommandsModuleBackend};
+use many_protocol::context::Context;
+use many_protocol::RequestMessage;
+
+/// Execute `blocks` blocks containing a single send each.
+fn send_blocks(harness: &mut Setup, blocks: u64) {
+    let id = harness.id;
+    harness.set_balance(id, 1000, *MFX_SYMBOL);
+    for _ in 0..blocks {
+        harness.block(|h| {
+            h.module_impl
+                .send(
+                    &id,
+                    ledger::SendArgs {
+                        from: Some(id),
+                        to: identity(1),
+                        amount: 10u16.into(),
+                        symbol: *MFX_SYMBOL,
+                        memo: None,
+                        decimal_amount: None,
+                    },
+                )
+                .unwrap()
+        });
+    }
+}
+
+fn events(harness: &Setup) -> Vec<events::EventLog> {
+    harness
+        .module_impl
+        .list(events::ListArgs {
+            count: None,
+            order: None,
+            filter: None,
+        })
+        .unwrap()
+        .events
+}
+
+fn prove(harness: &Setup, start: u64, end: u64) -> Result<events::ProveReturns, ManyError> {
+    harness.module_impl.prove(
+        &harness.id,
+        events::ProveArgs { start, end },
+        Context::new(RequestMessage::default(), unbounded().0),
+    )
+}
+
+fn state() -> InitialStateJson {
+    InitialStateJson::read("../../staging/ledger_state.json5")
+        .or_else(|_| InitialStateJson::read("staging/ledger_state.json5"))
+        .unwrap()
+}
+
+fn total(harness: &Setup) -> u64 {
+    harness.module_impl.info(events::InfoArgs {}).unwrap().total
+}
+
+#[test]
+fn retention_height() {
+    let mut harness = Setup::new(true);
+    send_blocks(&mut harness, 5);
+    let before = events(&harness);
+    assert_eq!(before.len(), 5);
+
+    let pruned = harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&EventRetention {
+            height: Some(2),
+            count: None,
+        })
+        .unwrap();
+    assert_eq!(pruned, 3);
+    assert_eq!(total(&harness), 2);
+
+    let after = events(&harness);
+    let ids = after.iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+    let expected = before[3..].iter().map(|e| e.id.clone()).collect::<Vec<_>>();
+    assert_eq!(ids, expected);
+}
+
+#[test]
+fn retention_count() {
+    let mut harness = Setup::new(true);
+    send_blocks(&mut harness, 5);
+
+    let retention = EventRetention {
+        height: None,
+        count: Some(4),
+    };
+    let storage = harness.module_impl.storage_mut();
+    assert_eq!(storage.prune_events(&retention).unwrap(), 1);
+    // Pruning again within the window is a no-op.
+    assert_eq!(storage.prune_events(&retention).unwrap(), 0);
+    assert_eq!(total(&harness), 4);
+    assert_eq!(events(&harness).len(), 4);
+}
+
+#[test]
+fn no_retention() {
+    let mut harness = Setup::new(true);
+    send_blocks(&mut harness, 2);
+    let hash = harness.module_impl.storage().hash();
+
+    let pruned = harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&EventRetention::default())
+        .unwrap();
+    assert_eq!(pruned, 0);
+    assert_eq!(harness.module_impl.storage().hash(), hash);
+    assert_eq!(total(&harness), 2);
+}
+
+#[test]
+fn migration() {
+    let config: MigrationConfig = serde_json::from_str(&format!(
+        r#"{{ "migrations": [{{
+            "name": "{}",
+            "block_height": 4,
+            "retention_count": 2
+        }}] }}"#,
+        EVENT_PRUNING_MIGRATION.name()
+    ))
+    .unwrap();
+    let mut harness = Setup::new(true);
+    harness.module_impl = LedgerModuleImpl::new(
+        state(),
+        Some(config),
+        tempfile::tempdir().unwrap().into_path(),
+        true,
+    )
+    .unwrap();
+
+    // The migration is not active yet.
+    send_blocks(&mut harness, 2);
+    assert_eq!(total(&harness), 2);
+
+    // Events are pruned from the activation block onward.
+    send_blocks(&mut harness, 3);
+    assert_eq!(total(&harness), 2);
+    assert_eq!(events(&harness).len(), 2);
+}
+
+#[test]
+fn prove_after_pruning() {
+    let mut harness = Setup::new_with_migrations(true, [(1, &EVENT_CHAIN_MIGRATION)], false);
+    send_blocks(&mut harness, 5);
+    let before = events(&harness);
+
+    let retention = EventRetention {
+        height: None,
+        count: Some(2),
+    };
+    harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&retention)
+        .unwrap();
+
+    // The segment after the pruned events starts from the running hash of
+    // the last pruned event, and still ends at the head of the chain.
+    let segment = prove(&harness, 4, 5).unwrap();
+    assert_eq!(segment.events.len(), 2);
+    assert_eq!(segment.previous, before[2].chain_hash);
+    assert_eq!(
+        segment.verify().unwrap().map(|h| h.to_vec()),
+        harness.module_impl.storage().events_chain_head().unwrap()
+    );
+
+    // Segments starting with pruned events cannot be proven.
+    assert!(prove(&harness, 3, 5).is_err());
+
+    // Pruning again keeps the head of each segment.
+    harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&EventRetention {
+            height: None,
+            count: Some(1),
+        })
+        .unwrap();
+    let segment = prove(&harness, 5, 5).unwrap();
+    assert_eq!(segment.previous, before[3].chain_hash);
+    assert!(segment.verify().is_ok());
+}
+
+#[test]
+fn compaction() {
+    let path = tempfile::tempdir().unwrap();
+    let mut harness = Setup::new(true);
+    harness.module_impl = LedgerModuleImpl::new(state(), None, path.path(), true).unwrap();
+    send_blocks(&mut harness, 5);
+    harness
+        .module_impl
+        .storage_mut()
+        .prune_events(&EventRetention {
+            height: Some(2),
+            count: None,
+        })
+        .unwrap();
+    let hash = harness.module_impl.storage().hash();
+    drop(harness);
+
+    // Compacting the store reclaims space without changing the state.
+    compact_store(path.path()).unwrap();
+    let storage = LedgerStorage::load(path.path(), true, None).unwrap();
+    assert_eq!(storage.hash(), hash);
+    assert_eq!(storage.nb_events().unwrap(), 2);
+}
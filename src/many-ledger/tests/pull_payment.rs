@@ -0,0 +1,153 @@
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger_test_utils::*;
+use many_modules::account::features::pull_payments::PullPaymentsAccountFeature;
+use many_modules::account::features::FeatureInfo;
+use many_modules::account::{self, AccountModuleBackend};
+use many_modules::events::{self, EventsModuleBackend};
+use many_modules::ledger;
+use many_modules::ledger::LedgerCommandsModuleBackend;
+use many_types::SortOrder;
+
+fn setup_with_account() -> (Setup, Address) {
+    let mut harness = Setup::new(true);
+    let id = harness.id;
+    let (_, account) = harness.block(|harness| {
+        AccountModuleBackend::create(
+            &mut harness.module_impl,
+            &id,
+            account::CreateArgs {
+                description: None,
+                roles: None,
+                features: account::features::FeatureSet::from_iter([
+                    PullPaymentsAccountFeature.as_feature()
+                ]),
+                nonce: None,
+            },
+        )
+        .unwrap()
+        .id
+    });
+    harness.set_balance(account, 1_000, *MFX_SYMBOL);
+    (harness, account)
+}
+
+fn authorize_args(account: Address) -> ledger::AuthorizePullArgs {
+    ledger::AuthorizePullArgs {
+        account,
+        payee: identity(1),
+        symbol: *MFX_SYMBOL,
+        amount: 100u32.into(),
+        period_in_secs: 60,
+    }
+}
+
+fn pull(harness: &mut Setup, account: Address, amount: u32) -> Result<(), many_error::ManyError> {
+    let (_, result) = harness.block(|harness| {
+        harness.module_impl.pull_payment(
+            &identity(1),
+            ledger::PullPaymentArgs {
+                account,
+                symbol: *MFX_SYMBOL,
+                amount: amount.into(),
+                memo: None,
+            },
+        )
+    });
+    result.map(|_| ())
+}
+
+#[test]
+fn pull_per_period() {
+    let (mut harness, account) = setup_with_account();
+    let id = harness.id;
+    let (_, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .authorize_pull(&id, authorize_args(account))
+    });
+    assert!(result.is_ok());
+
+    assert!(pull(&mut harness, account, 60).is_ok());
+    assert_eq!(
+        pull(&mut harness, account, 50).unwrap_err().code(),
+        error::pull_limit_exceeded("", "").code()
+    );
+    assert!(pull(&mut harness, account, 40).is_ok());
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+    assert_eq!(harness.balance_(account), 900u32);
+
+    // The limit is reset in the next period.
+    harness.inc_time(60);
+    assert!(pull(&mut harness, account, 100).is_ok());
+    assert_eq!(harness.balance_(identity(1)), 200u32);
+
+    let list = harness
+        .module_impl
+        .list(events::ListArgs {
+            count: Some(1),
+            order: Some(SortOrder::Descending),
+            filter: Some(events::EventFilter {
+                kind: Some(vec![events::EventKind::PullPayment].into()),
+                ..events::EventFilter::default()
+            }),
+        })
+        .unwrap();
+    assert!(matches!(
+        list.events[0].content,
+        events::EventInfo::PullPayment { period: 1, .. }
+    ));
+}
+
+#[test]
+fn pull_unauthorized() {
+    let (mut harness, account) = setup_with_account();
+
+    // Only an owner of the account can authorize a payee.
+    let (_, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .authorize_pull(&identity(1), authorize_args(account))
+    });
+    assert!(result.is_err());
+    assert_eq!(
+        pull(&mut harness, account, 10).unwrap_err().code(),
+        error::pull_not_authorized("", "").code()
+    );
+
+    // The account needs the feature.
+    let id = harness.id;
+    let (_, result) =
+        harness.block(|harness| harness.module_impl.authorize_pull(&id, authorize_args(id)));
+    assert!(result.is_err());
+}
+
+#[test]
+fn pull_revoked() {
+    let (mut harness, account) = setup_with_account();
+    let id = harness.id;
+    harness.block(|harness| {
+        harness
+            .module_impl
+            .authorize_pull(&id, authorize_args(account))
+            .unwrap()
+    });
+    assert!(pull(&mut harness, account, 10).is_ok());
+
+    let (_, result) = harness.block(|harness| {
+        harness.module_impl.revoke_pull(
+            &identity(1),
+            ledger::RevokePullArgs {
+                account,
+                payee: identity(1),
+                symbol: *MFX_SYMBOL,
+            },
+        )
+    });
+    assert!(result.is_ok());
+    assert_eq!(
+        pull(&mut harness, account, 10).unwrap_err().code(),
+        error::pull_not_authorized("", "").code()
+    );
+}
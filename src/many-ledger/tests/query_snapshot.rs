@@ -0,0 +1,90 @@
+use async_channel::unbounded;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::module::query_snapshot::{QuerySnapshotModule, QuerySnapshots};
+use many_ledger::module::LedgerModuleImpl;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{self, BalanceArgs, BalanceReturns, LedgerModuleBackend};
+use many_modules::ManyModule;
+use many_protocol::{context::Context, RequestMessage};
+use many_types::ledger::TokenAmount;
+use std::sync::{Arc, Mutex};
+
+fn snapshot_balance(snapshots: &QuerySnapshots, id: Address) -> TokenAmount {
+    let snapshot = snapshots.current().expect("No query snapshot.");
+    let snapshot = snapshot.lock().unwrap();
+    let BalanceReturns { mut balances } = snapshot
+        .balance(
+            &id,
+            BalanceArgs {
+                account: None,
+                symbols: None,
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap();
+    balances.remove(&*MFX_SYMBOL).unwrap_or_default()
+}
+
+fn snapshot_setup(harness: &mut Setup) -> (tempfile::TempDir, Arc<QuerySnapshots>) {
+    let dir = tempfile::tempdir().unwrap();
+    let snapshots = Arc::new(QuerySnapshots::new(dir.path()));
+    harness
+        .module_impl
+        .set_query_snapshots(Some(snapshots.clone()))
+        .unwrap();
+    (dir, snapshots)
+}
+
+#[test]
+fn snapshot_is_last_committed_block() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let (dir, snapshots) = snapshot_setup(&mut harness);
+    let id = harness.id;
+
+    harness.block(|h| {
+        h.send_(id, identity(1), 100u32);
+        // The live ledger sees the block in flight, not the snapshot.
+        assert_eq!(h.balance_(id), 900u32);
+        assert_eq!(snapshot_balance(&snapshots, id), 1_000u32);
+    });
+    assert_eq!(snapshot_balance(&snapshots, id), 900u32);
+    assert_eq!(snapshot_balance(&snapshots, identity(1)), 100u32);
+
+    // Unused snapshots are removed.
+    harness.block(|_| {});
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[tokio::test]
+// Holding the lock of the live ledger simulates a command in flight.
+#[allow(clippy::await_holding_lock)]
+async fn queries_do_not_lock_the_ledger() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let (_dir, snapshots) = snapshot_setup(&mut harness);
+    let id = harness.id;
+
+    let module_impl = Arc::new(Mutex::new(harness.module_impl));
+    let module = QuerySnapshotModule::new(
+        ledger::LedgerModule::new(module_impl.clone()),
+        snapshots,
+        ledger::LedgerModule::<LedgerModuleImpl>::new,
+    );
+
+    let _command = module_impl.lock().unwrap();
+    let message = RequestMessage::default()
+        .with_method("ledger.balance".to_string())
+        .with_data(
+            minicbor::to_vec(BalanceArgs {
+                account: None,
+                symbols: None,
+            })
+            .unwrap(),
+        )
+        .with_from(id);
+    let data = module.execute(message).await.unwrap().data.unwrap();
+    let BalanceReturns { balances } = minicbor::decode(&data).unwrap();
+    assert_eq!(balances[&*MFX_SYMBOL], 1_000u32);
+}
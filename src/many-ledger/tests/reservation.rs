@@ -0,0 +1,162 @@
+use many_identity::testing::identity;
+use many_ledger::error;
+use many_ledger_test_utils::*;
+use many_modules::events::{EventId, EventInfo, EventLog, EventsModuleBackend, ListArgs};
+use many_types::ledger::TokenAmount;
+
+fn events(harness: &Setup) -> Vec<EventLog> {
+    harness
+        .module_impl
+        .list(ListArgs::default())
+        .unwrap()
+        .events
+}
+
+#[test]
+fn prepare_commit() {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let (reservation, _) = harness
+        .module_impl
+        .storage_mut()
+        .prepare_transfer(&id, &identity(1), &MFX_SYMBOL, 100u32.into(), None)
+        .unwrap();
+
+    // Funds are held, not yet credited.
+    assert_eq!(harness.balance_(id), 900u32);
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+
+    harness
+        .module_impl
+        .storage_mut()
+        .commit_transfer(&reservation)
+        .unwrap();
+    assert_eq!(harness.balance_(id), 900u32);
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+
+    // A reservation can only be settled once.
+    assert_many_err(
+        harness
+            .module_impl
+            .storage_mut()
+            .commit_transfer(&reservation),
+        error::reservation_not_found(hex::encode(reservation.as_slice())),
+    );
+    assert!(harness
+        .module_impl
+        .storage_mut()
+        .abort_transfer(&reservation)
+        .is_err());
+}
+
+#[test]
+fn prepare_abort() {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let (reservation, _) = harness
+        .module_impl
+        .storage_mut()
+        .prepare_transfer(&id, &identity(1), &MFX_SYMBOL, 1_000u32.into(), None)
+        .unwrap();
+    assert_eq!(harness.balance_(id), 0u32);
+
+    // Reserved funds cannot be spent.
+    assert_many_err(
+        harness.send(id, identity(2), 1u32, *MFX_SYMBOL),
+        error::insufficient_funds(),
+    );
+
+    harness
+        .module_impl
+        .storage_mut()
+        .abort_transfer(&reservation)
+        .unwrap();
+    assert_eq!(harness.balance_(id), 1_000u32);
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+}
+
+#[test]
+fn prepare_insufficient_funds() {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    assert_many_err(
+        harness.module_impl.storage_mut().prepare_transfer(
+            &id,
+            &identity(1),
+            &MFX_SYMBOL,
+            1_001u32.into(),
+            None,
+        ),
+        error::insufficient_funds(),
+    );
+    assert_eq!(harness.balance_(id), 1_000u32);
+}
+
+#[test]
+fn prepare_unknown_symbol() {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+    let count = events(&harness).len();
+
+    assert_many_err(
+        harness.module_impl.storage_mut().prepare_transfer(
+            &id,
+            &identity(1),
+            &identity(100),
+            1u32.into(),
+            None,
+        ),
+        error::unknown_symbol(identity(100).to_string()),
+    );
+    assert_eq!(events(&harness).len(), count);
+}
+
+#[test]
+fn reservation_events() {
+    let mut harness = setup();
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let storage = harness.module_impl.storage_mut();
+    let (committed, _) = storage
+        .prepare_transfer(&id, &identity(1), &MFX_SYMBOL, 100u32.into(), None)
+        .unwrap();
+    storage.commit_transfer(&committed).unwrap();
+    let (aborted, _) = storage
+        .prepare_transfer(&id, &identity(2), &MFX_SYMBOL, 200u32.into(), None)
+        .unwrap();
+    storage.abort_transfer(&aborted).unwrap();
+
+    // A reservation takes the ID of the event preparing it.
+    let events = events(&harness);
+    let event = |id: &[u8]| events.iter().find(|e| e.id == EventId::from(id.to_vec()));
+    assert!(matches!(
+        event(&committed).map(|e| &e.content),
+        Some(EventInfo::TransferPrepared { to, amount, .. })
+            if to == &identity(1) && amount == &TokenAmount::from(100u32)
+    ));
+    assert!(matches!(
+        event(&aborted).map(|e| &e.content),
+        Some(EventInfo::TransferPrepared { to, amount, .. })
+            if to == &identity(2) && amount == &TokenAmount::from(200u32)
+    ));
+    assert!(events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::TransferCommitted { id, to, .. }
+            if id == &committed && to == &identity(1)
+    )));
+    assert!(events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::TransferAborted { id, to, .. } if id == &aborted && to == &identity(2)
+    )));
+    assert!(events
+        .iter()
+        .any(|e| matches!(&e.content, EventInfo::Send { to, .. } if to == &identity(1))));
+}
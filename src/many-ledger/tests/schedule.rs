@@ -0,0 +1,222 @@
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger_test_utils::*;
+use many_modules::ledger;
+use many_modules::ledger::LedgerCommandsModuleBackend;
+use many_types::Timestamp;
+
+fn schedule_args(
+    from: Address,
+    to: Address,
+    height: Option<u64>,
+    time: Option<Timestamp>,
+) -> ledger::ScheduleArgs {
+    ledger::ScheduleArgs {
+        send: ledger::SendArgs {
+            from: Some(from),
+            to,
+            amount: 100u32.into(),
+            symbol: *MFX_SYMBOL,
+            memo: None,
+            decimal_amount: None,
+        },
+        height,
+        time,
+    }
+}
+
+#[test]
+fn schedule_at_height() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let (h, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(1), Some(3), None))
+    });
+    assert_eq!(h, 1);
+    assert!(result.is_ok());
+
+    let (h, _) = harness.block(|_| {});
+    assert_eq!(h, 2);
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+
+    let (h, _) = harness.block(|_| {});
+    assert_eq!(h, 3);
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+    assert_eq!(harness.balance_(id), 900u32);
+
+    // The transaction was removed from the queue.
+    harness.block(|_| {});
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+}
+
+#[test]
+fn schedule_at_time() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    // Blocks are 1 second apart, starting at 1_000_001.
+    let time = Timestamp::new(1_000_003).unwrap();
+    let (_, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(1), None, Some(time)))
+    });
+    assert!(result.is_ok());
+
+    harness.block(|_| {});
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+
+    harness.block(|_| {});
+    assert_eq!(harness.balance_(identity(1)), 100u32);
+}
+
+#[test]
+fn schedule_insufficient_funds() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    harness.block(|harness| {
+        harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(1), Some(2), None))
+            .unwrap();
+        harness.send_(id, identity(2), 1_000u32);
+    });
+
+    // The scheduled transaction fails but does not fail the block.
+    let (h, _) = harness.block(|_| {});
+    assert_eq!(h, 2);
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+    assert_eq!(harness.balance_(identity(2)), 1_000u32);
+}
+
+#[test]
+fn cancel_schedule() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    let (_, token) = harness.block(|harness| {
+        harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(1), Some(3), None))
+            .unwrap()
+            .token
+    });
+
+    harness.block(|harness| {
+        let args = ledger::CancelScheduleArgs {
+            token: token.clone(),
+        };
+        assert_many_err(
+            harness
+                .module_impl
+                .cancel_schedule(&identity(1), args.clone()),
+            error::unauthorized(),
+        );
+        assert!(harness.module_impl.cancel_schedule(&id, args).is_ok());
+    });
+
+    harness.block(|_| {});
+    harness.block(|_| {});
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+    assert_eq!(harness.balance_(id), 1_000u32);
+
+    let (_, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .cancel_schedule(&id, ledger::CancelScheduleArgs { token })
+    });
+    assert!(result.is_err());
+}
+
+#[test]
+fn schedule_invalid() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    harness.block(|_| {});
+    harness.block(|harness| {
+        assert_many_err(
+            harness
+                .module_impl
+                .schedule(&id, schedule_args(id, identity(1), None, None)),
+            error::invalid_schedule(),
+        );
+        assert_many_err(
+            harness.module_impl.schedule(
+                &id,
+                schedule_args(id, identity(1), Some(2), Some(Timestamp::now())),
+            ),
+            error::invalid_schedule(),
+        );
+        assert_many_err(
+            harness
+                .module_impl
+                .schedule(&id, schedule_args(id, identity(1), Some(1), None)),
+            error::schedule_in_the_past(),
+        );
+        assert_many_err(
+            harness
+                .module_impl
+                .schedule(&id, schedule_args(identity(2), identity(1), Some(5), None)),
+            error::unauthorized(),
+        );
+    });
+}
+
+#[test]
+fn schedule_in_due_order() {
+    let mut harness = Setup::new(true);
+    harness.set_balance(harness.id, 1_000, *MFX_SYMBOL);
+    let id = harness.id;
+
+    // Blocks are 1 second apart, starting at 1_000_001.
+    let (_, later) = harness.block(|harness| {
+        let later = harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(1), Some(10), None))
+            .unwrap()
+            .token;
+        harness
+            .module_impl
+            .schedule(&id, schedule_args(id, identity(2), Some(3), None))
+            .unwrap();
+        harness
+            .module_impl
+            .schedule(
+                &id,
+                schedule_args(
+                    id,
+                    identity(3),
+                    None,
+                    Some(Timestamp::new(1_000_003).unwrap()),
+                ),
+            )
+            .unwrap();
+        later
+    });
+
+    harness.block(|_| {});
+    harness.block(|_| {});
+    assert_eq!(harness.balance_(identity(1)), 0u32);
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+    assert_eq!(harness.balance_(identity(3)), 100u32);
+
+    // The transaction due later is still in the queue.
+    let (_, result) = harness.block(|harness| {
+        harness
+            .module_impl
+            .cancel_schedule(&id, ledger::CancelScheduleArgs { token: later })
+    });
+    assert!(result.is_ok());
+    assert_eq!(harness.balance_(id), 800u32);
+}
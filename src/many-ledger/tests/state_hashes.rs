@@ -0,0 +1,67 @@
+use many_identity::testing::identity;
+use many_ledger_test_utils::*;
+use many_modules::debug::{DebugModuleBackend, StateHashesArgs, StateHashesReturns};
+use std::collections::BTreeSet;
+
+fn setup_with_send(amount: u32) -> Setup {
+    let mut harness = Setup::new(true);
+    harness.set_balance(identity(1), 1000, *MFX_SYMBOL);
+    harness
+        .block(|h| h.send(identity(1), identity(2), amount, *MFX_SYMBOL))
+        .1
+        .unwrap();
+    harness
+}
+
+fn state_hashes(harness: &Setup, prefix: Option<&str>) -> StateHashesReturns {
+    harness
+        .module_impl
+        .state_hashes(StateHashesArgs {
+            prefix: prefix.map(|p| p.as_bytes().to_vec().into()),
+        })
+        .unwrap()
+}
+
+/// The subtrees whose hash differs between two breakdowns.
+fn diverged(a: &StateHashesReturns, b: &StateHashesReturns) -> BTreeSet<String> {
+    a.subtrees
+        .keys()
+        .chain(b.subtrees.keys())
+        .filter(|subtree| a.subtrees.get(*subtree) != b.subtrees.get(*subtree))
+        .map(|subtree| String::from_utf8_lossy(subtree).to_string())
+        .collect()
+}
+
+#[test]
+fn same_state() {
+    let a = state_hashes(&setup_with_send(100), None);
+    let b = state_hashes(&setup_with_send(100), None);
+    assert_eq!(a, b);
+    assert!(a.subtrees.contains_key(b"/balances/".as_slice()));
+    assert!(a.subtrees.contains_key(b"/height".as_slice()));
+}
+
+#[test]
+fn diverged_balances() {
+    let (a, b) = (setup_with_send(100), setup_with_send(200));
+    let (top_a, top_b) = (state_hashes(&a, None), state_hashes(&b, None));
+    assert_eq!(top_a.height, top_b.height);
+    assert_ne!(top_a.app_hash, top_b.app_hash);
+
+    let top = diverged(&top_a, &top_b);
+    assert!(top.contains("/balances/"));
+    assert!(!top.contains("/config/"));
+    assert!(!top.contains("/height"));
+
+    let balances = diverged(
+        &state_hashes(&a, Some("/balances/")),
+        &state_hashes(&b, Some("/balances/")),
+    );
+    assert_eq!(
+        balances,
+        BTreeSet::from([
+            format!("/balances/{}/", identity(1)),
+            format!("/balances/{}/", identity(2)),
+        ])
+    );
+}
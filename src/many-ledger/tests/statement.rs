@@ -0,0 +1,154 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::migration::escrow::ESCROW_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::escrow::{self, EscrowCondition, EscrowModuleBackend};
+use many_modules::events::EventKind;
+use many_modules::ledger::{LedgerModuleBackend, StatementArgs, StatementReturns};
+use many_types::ledger::TokenAmount;
+use many_types::{CborRange, Timestamp};
+use minicbor::bytes::ByteVec;
+use std::ops::Bound;
+
+/// Identity 1 sends 100 MFX to identity 2 and gets 30 back, then puts 200 in
+/// an escrow released to identity 2, and 50 in an escrow refunded to it.
+/// Each transaction is in its own block, from height 1.
+fn statement_setup() -> Setup {
+    let mut harness = Setup::new_with_migrations(true, [(0, &ESCROW_MIGRATION)], true);
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+
+    harness.block(|h| h.send_(identity(1), identity(2), 100u32));
+    harness.block(|h| h.send_(identity(2), identity(1), 30u32));
+    let released = create_escrow(&mut harness, 200, None);
+    harness.block(|h| {
+        h.module_impl
+            .release(
+                &identity(1),
+                escrow::ReleaseArgs {
+                    id: released,
+                    preimage: None,
+                },
+            )
+            .unwrap()
+    });
+    let refunded = create_escrow(&mut harness, 50, Some(Timestamp::new(1_000_100).unwrap()));
+    harness.inc_time(200);
+    harness.block(|h| {
+        h.module_impl
+            .refund(&identity(1), escrow::RefundArgs { id: refunded })
+            .unwrap()
+    });
+    harness
+}
+
+fn create_escrow(harness: &mut Setup, amount: u32, refund_after: Option<Timestamp>) -> ByteVec {
+    let (_, result) = harness.block(|h| {
+        h.module_impl.create(
+            &identity(1),
+            escrow::CreateArgs {
+                recipient: identity(2),
+                symbol: *MFX_SYMBOL,
+                amount: amount.into(),
+                condition: EscrowCondition::Arbiter(identity(3)),
+                refund_after,
+                memo: None,
+            },
+        )
+    });
+    result.unwrap().id
+}
+
+fn statement(
+    harness: &Setup,
+    account: Address,
+    height_range: Option<CborRange<u64>>,
+    count: Option<u64>,
+    after: Option<many_modules::events::EventId>,
+) -> Result<StatementReturns, ManyError> {
+    harness.module_impl.statement(
+        &account,
+        StatementArgs {
+            account: None,
+            symbol: *MFX_SYMBOL,
+            height_range,
+            date_range: None,
+            count,
+            after,
+        },
+    )
+}
+
+/// The credit, debit and balance of each entry.
+fn amounts(statement: &StatementReturns) -> Vec<(u32, u32, u32)> {
+    let amount = |a: &TokenAmount| -> u32 { a.to_string().parse().unwrap() };
+    statement
+        .entries
+        .iter()
+        .map(|e| (amount(&e.credit), amount(&e.debit), amount(&e.balance)))
+        .collect()
+}
+
+#[test]
+fn running_balance() {
+    let harness = statement_setup();
+    let first = statement(&harness, identity(1), None, None, None).unwrap();
+    assert_eq!(first.opening_balance, 1_000u32);
+    assert_eq!(
+        amounts(&first),
+        vec![
+            (0, 100, 900),
+            (30, 0, 930),
+            (0, 200, 730),
+            (0, 50, 680),
+            (50, 0, 730),
+        ]
+    );
+    assert_eq!(first.closing_balance, harness.balance_(identity(1)));
+    assert_eq!(first.entries[2].kind, EventKind::EscrowCreate);
+    assert_eq!(first.entries[2].counterparty, Some(identity(2)));
+    assert_eq!(first.next, None);
+
+    // The release is a credit for the recipient only.
+    let second = statement(&harness, identity(2), None, None, None).unwrap();
+    assert_eq!(
+        amounts(&second),
+        vec![(100, 0, 100), (0, 30, 70), (200, 0, 270)]
+    );
+    assert_eq!(second.closing_balance, harness.balance_(identity(2)));
+}
+
+#[test]
+fn pages() {
+    let harness = statement_setup();
+    let first = statement(&harness, identity(1), None, Some(2), None).unwrap();
+    assert_eq!(amounts(&first), vec![(0, 100, 900), (30, 0, 930)]);
+    assert_eq!(first.next, Some(first.entries[1].id.clone()));
+
+    let second = statement(&harness, identity(1), None, Some(2), first.next).unwrap();
+    assert_eq!(second.opening_balance, 930u32);
+    assert_eq!(amounts(&second), vec![(0, 200, 730), (0, 50, 680)]);
+
+    let last = statement(&harness, identity(1), None, Some(2), second.next).unwrap();
+    assert_eq!(amounts(&last), vec![(50, 0, 730)]);
+    assert_eq!(last.next, None);
+}
+
+#[test]
+fn height_range() {
+    let harness = statement_setup();
+    let returns = statement(
+        &harness,
+        identity(1),
+        Some(CborRange {
+            start: Bound::Included(2),
+            end: Bound::Excluded(4),
+        }),
+        None,
+        None,
+    )
+    .unwrap();
+    assert_eq!(returns.opening_balance, 900u32);
+    assert_eq!(amounts(&returns), vec![(30, 0, 930), (0, 200, 730)]);
+    assert_eq!(returns.closing_balance, 730u32);
+}
@@ -45,6 +45,7 @@ fn load() {
                 features: account::features::FeatureSet::from_iter([
                     account::features::ledger::AccountLedger.as_feature(),
                 ]),
+                nonce: None,
             },
         )
         .unwrap()
@@ -95,8 +96,10 @@ fn load_symbol_meta() {
         Metadata {
             block_height: 0,
             upper_block_height: None,
+            upgrade_time: None,
             disabled: false,
             issue: None,
+            rollout: Default::default(),
             extra: Default::default(),
         },
     ));
@@ -110,6 +113,7 @@ fn load_symbol_meta() {
                 decimals: 9,
                 owner: None,
                 maximum: None,
+                dust: None,
             },
         )]);
         let initial_balance = BTreeMap::from([(
@@ -141,6 +145,7 @@ fn load_symbol_meta() {
                 features: account::features::FeatureSet::from_iter([
                     account::features::ledger::AccountLedger.as_feature(),
                 ]),
+                nonce: None,
             },
         )
         .unwrap()
@@ -186,6 +191,7 @@ fn load_symbol_meta() {
             symbol: identity(1000),
             extended_info: None,
         },
+        Context::new(RequestMessage::default(), unbounded().0),
     )
     .unwrap()
     .info;
@@ -0,0 +1,189 @@
+use async_channel::unbounded;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::supply::SUPPLY_ACCOUNTING_MIGRATION;
+use many_ledger::migration::token_create::TOKEN_CREATE_MIGRATION;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{
+    LedgerMintBurnModuleBackend, LedgerTokensModuleBackend, TokenBurnArgs, TokenInfoArgs,
+    TokenMintArgs, TokenSupplyAtArgs, TokenSupplyAtReturns,
+};
+use many_protocol::{context::Context, RequestMessage};
+use many_types::ledger::{LedgerTokensAddressMap, Symbol, TokenAmount};
+
+fn supply_setup(supply_height: u64) -> Setup {
+    Setup::new_with_migrations(
+        true,
+        [
+            (0, &TOKEN_MIGRATION),
+            (0, &TOKEN_CREATE_MIGRATION),
+            (supply_height, &SUPPLY_ACCOUNTING_MIGRATION),
+        ],
+        true,
+    )
+}
+
+/// Create a token with 1000 tokens held by identity(1), its owner.
+fn create_token(harness: &mut Setup) -> Symbol {
+    let mut args = default_token_create_args(None, None);
+    args.initial_distribution = Some(LedgerTokensAddressMap::from([(
+        identity(1),
+        TokenAmount::from(1000u64),
+    )]));
+    harness
+        .block(|h| {
+            h.module_impl
+                .create(&identity(1), args)
+                .unwrap()
+                .info
+                .symbol
+        })
+        .1
+}
+
+fn distribution(amount: u64) -> LedgerTokensAddressMap {
+    LedgerTokensAddressMap::from([(identity(1), TokenAmount::from(amount))])
+}
+
+fn supply_at(
+    harness: &Setup,
+    symbol: Symbol,
+    height: Option<u64>,
+) -> Result<TokenSupplyAtReturns, ManyError> {
+    harness
+        .module_impl
+        .supply_at(&identity(1), TokenSupplyAtArgs { symbol, height })
+}
+
+fn supply(total: u64, circulating: u64, burned: u64) -> TokenSupplyAtReturns {
+    TokenSupplyAtReturns {
+        total: total.into(),
+        circulating: circulating.into(),
+        burned: burned.into(),
+    }
+}
+
+#[test]
+fn history() {
+    let mut harness = supply_setup(0);
+    let symbol = create_token(&mut harness);
+    harness.block(|h| {
+        h.module_impl
+            .mint(
+                &identity(1),
+                TokenMintArgs {
+                    symbol,
+                    distribution: distribution(500),
+                    memo: None,
+                },
+            )
+            .unwrap()
+    });
+    harness.block(|h| {
+        h.module_impl
+            .burn(
+                &identity(1),
+                TokenBurnArgs {
+                    symbol,
+                    distribution: distribution(200),
+                    memo: None,
+                    error_on_under_burn: None,
+                },
+            )
+            .unwrap()
+    });
+    harness.block(|h| {
+        h.send(identity(1), Address::illegal(), 100u64, symbol)
+            .unwrap()
+    });
+    // A block without changes to the supply.
+    harness.block(|h| h.send(identity(1), identity(2), 100u64, symbol).unwrap());
+
+    assert_many_err(
+        supply_at(&harness, symbol, Some(0)),
+        error::supply_not_recorded(symbol, 0),
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(1)),
+        Ok(supply(1000, 1000, 0))
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(2)),
+        Ok(supply(1500, 1500, 0))
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(3)),
+        Ok(supply(1300, 1300, 200))
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(4)),
+        Ok(supply(1300, 1300, 300))
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(5)),
+        Ok(supply(1300, 1300, 300))
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, None),
+        Ok(supply(1300, 1300, 300))
+    );
+}
+
+/// The supply of the tokens of the initial state is recorded at height 0.
+#[test]
+fn genesis() {
+    let harness = supply_setup(0);
+    let info = harness
+        .module_impl
+        .info(
+            &identity(1),
+            TokenInfoArgs {
+                symbol: *MFX_SYMBOL,
+                extended_info: None,
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap()
+        .info;
+    let genesis = TokenSupplyAtReturns {
+        total: info.supply.total,
+        circulating: info.supply.circulating,
+        burned: TokenAmount::zero(),
+    };
+    assert_eq!(
+        supply_at(&harness, *MFX_SYMBOL, Some(0)),
+        Ok(genesis.clone())
+    );
+    assert_eq!(supply_at(&harness, *MFX_SYMBOL, None), Ok(genesis));
+}
+
+/// Tokens created before the migration have their supply recorded when it
+/// activates.
+#[test]
+fn backfill() {
+    let mut harness = supply_setup(2);
+    let symbol = create_token(&mut harness);
+    assert_many_err(
+        supply_at(&harness, symbol, None),
+        ManyError::invalid_method_name("tokens.supplyAt"),
+    );
+
+    harness.block(|_| {});
+    assert_many_err(
+        supply_at(&harness, symbol, Some(1)),
+        error::supply_not_recorded(symbol, 1),
+    );
+    assert_eq!(
+        supply_at(&harness, symbol, Some(2)),
+        Ok(supply(1000, 1000, 0))
+    );
+}
+
+#[test]
+fn unknown_symbol() {
+    let harness = supply_setup(0);
+    assert!(supply_at(&harness, identity(1000), None).is_err());
+}
@@ -0,0 +1,171 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::swap::SWAP_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::events::{EventInfo, EventsModuleBackend, ListArgs};
+use many_modules::ledger::{self, LedgerCommandsModuleBackend};
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use once_cell::sync::Lazy;
+
+static ABC_SYMBOL: Lazy<Symbol> = Lazy::new(|| identity(100));
+
+/// Identity 1 has 1000 MFX, and identity 2 has 1000 ABC.
+fn swap_setup() -> Setup {
+    let mut harness = Setup::new_with_state(true, [(0, &SWAP_MIGRATION)], |state| {
+        state.symbols.insert(*ABC_SYMBOL, "ABC".to_string());
+    });
+    harness.set_balance(identity(1), 1_000, *MFX_SYMBOL);
+    harness.set_balance(identity(2), 1_000, *ABC_SYMBOL);
+    harness
+}
+
+/// A time after the genesis of the harness.
+fn expiration(secs: u64) -> Timestamp {
+    Timestamp::new(1_000_000 + secs).unwrap()
+}
+
+/// Identity 1 offers 100 MFX for 50 ABC of identity 2.
+fn create(harness: &mut Setup, expiration: Timestamp) -> Result<ByteVec, ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl.create_swap(
+            &identity(1),
+            ledger::CreateSwapArgs {
+                counterparty: identity(2),
+                give_symbol: *MFX_SYMBOL,
+                give_amount: 100u32.into(),
+                want_symbol: *ABC_SYMBOL,
+                want_amount: 50u32.into(),
+                expiration,
+                memo: None,
+            },
+        )
+    });
+    result.map(|r| r.id)
+}
+
+fn accept(harness: &mut Setup, sender: u32, id: &ByteVec) -> Result<(), ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl
+            .accept_swap(&identity(sender), ledger::AcceptSwapArgs { id: id.clone() })
+    });
+    result.map(|_| ())
+}
+
+fn cancel(harness: &mut Setup, sender: u32, id: &ByteVec) -> Result<(), ManyError> {
+    let (_, result) = harness.block(|h| {
+        h.module_impl
+            .cancel_swap(&identity(sender), ledger::CancelSwapArgs { id: id.clone() })
+    });
+    result.map(|_| ())
+}
+
+fn balances(harness: &Setup, id: Address) -> (TokenAmount, TokenAmount) {
+    (
+        harness.balance(id, *MFX_SYMBOL).unwrap(),
+        harness.balance(id, *ABC_SYMBOL).unwrap(),
+    )
+}
+
+#[test]
+fn accept_swap() {
+    let mut harness = swap_setup();
+    let id = create(&mut harness, expiration(100)).unwrap();
+
+    // The funds given are locked.
+    assert_eq!(
+        balances(&harness, identity(1)),
+        (900u32.into(), 0u32.into())
+    );
+
+    assert_many_err(accept(&mut harness, 3, &id), error::unauthorized());
+    accept(&mut harness, 2, &id).unwrap();
+    assert_eq!(
+        balances(&harness, identity(1)),
+        (900u32.into(), 50u32.into())
+    );
+    assert_eq!(
+        balances(&harness, identity(2)),
+        (100u32.into(), 950u32.into())
+    );
+
+    assert_many_err(
+        accept(&mut harness, 2, &id),
+        error::swap_not_found(hex::encode(&id)),
+    );
+
+    let events = harness
+        .module_impl
+        .list(ListArgs::default())
+        .unwrap()
+        .events;
+    assert!(events.iter().any(|e| matches!(
+        &e.content,
+        EventInfo::SwapCreate { counterparty, .. } if counterparty == &identity(2)
+    )));
+    assert!(events
+        .iter()
+        .any(|e| matches!(&e.content, EventInfo::SwapAccept { .. })));
+}
+
+#[test]
+fn insufficient_funds() {
+    let mut harness = swap_setup();
+    harness.set_balance(identity(2), 10, *ABC_SYMBOL);
+    let id = create(&mut harness, expiration(100)).unwrap();
+
+    // Neither side moves.
+    assert_many_err(accept(&mut harness, 2, &id), error::insufficient_funds());
+    assert_eq!(
+        balances(&harness, identity(1)),
+        (900u32.into(), 0u32.into())
+    );
+    assert_eq!(balances(&harness, identity(2)), (0u32.into(), 10u32.into()));
+}
+
+#[test]
+fn expired() {
+    let mut harness = swap_setup();
+    assert_many_err(
+        create(&mut harness, expiration(0)),
+        error::swap_expiration_in_the_past(),
+    );
+
+    let id = create(&mut harness, expiration(5)).unwrap();
+    harness.inc_time(10);
+    assert_many_err(
+        accept(&mut harness, 2, &id),
+        error::swap_expired(hex::encode(&id)),
+    );
+
+    cancel(&mut harness, 1, &id).unwrap();
+    assert_eq!(
+        balances(&harness, identity(1)),
+        (1_000u32.into(), 0u32.into())
+    );
+}
+
+#[test]
+fn cancel_swap() {
+    let mut harness = swap_setup();
+    let id = create(&mut harness, expiration(100)).unwrap();
+    assert_many_err(cancel(&mut harness, 3, &id), error::unauthorized());
+
+    // The counterparty can decline the swap.
+    cancel(&mut harness, 2, &id).unwrap();
+    assert_eq!(
+        balances(&harness, identity(1)),
+        (1_000u32.into(), 0u32.into())
+    );
+    assert_eq!(
+        balances(&harness, identity(2)),
+        (0u32.into(), 1_000u32.into())
+    );
+    assert_many_err(
+        accept(&mut harness, 2, &id),
+        error::swap_not_found(hex::encode(&id)),
+    );
+}
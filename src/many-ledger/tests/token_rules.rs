@@ -0,0 +1,176 @@
+use async_channel::unbounded;
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_identity::Address;
+use many_ledger::error;
+use many_ledger::migration::token_rules::TOKEN_RULES_MIGRATION;
+use many_ledger::migration::tokens::TOKEN_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{
+    LedgerTokensModuleBackend, TokenInfoArgs, TokenRules, TokenUpdateRulesArgs,
+};
+use many_protocol::{context::Context, RequestMessage};
+use std::collections::BTreeSet;
+
+/// MFX is owned by identity 1.
+fn rules_setup() -> Setup {
+    let mut harness = Setup::new_with_state(
+        true,
+        [(0, &TOKEN_MIGRATION), (0, &TOKEN_RULES_MIGRATION)],
+        |state| {
+            for meta in state.symbols_meta.iter_mut().flat_map(|m| m.values_mut()) {
+                meta.owner = Some(identity(1));
+            }
+        },
+    );
+    harness.set_balance(identity(1), 1000, *MFX_SYMBOL);
+    harness
+}
+
+fn update_rules(harness: &mut Setup, sender: Address, rules: TokenRules) -> Result<(), ManyError> {
+    harness
+        .block(|h| {
+            h.module_impl.update_rules(
+                &sender,
+                TokenUpdateRulesArgs {
+                    symbol: *MFX_SYMBOL,
+                    rules,
+                    memo: None,
+                },
+            )
+        })
+        .1
+        .map(|_| ())
+}
+
+fn send(harness: &mut Setup, from: Address, to: Address, amount: u32) -> Result<(), ManyError> {
+    harness.block(|h| h.send(from, to, amount, *MFX_SYMBOL)).1
+}
+
+#[test]
+fn whitelist() {
+    let mut harness = rules_setup();
+    let rules = TokenRules {
+        whitelist: Some(BTreeSet::from([identity(1), identity(2)])),
+        ..Default::default()
+    };
+    update_rules(&mut harness, identity(1), rules.clone()).unwrap();
+
+    send(&mut harness, identity(1), identity(2), 100).unwrap();
+    assert_many_err(
+        send(&mut harness, identity(2), identity(3), 10),
+        error::address_not_whitelisted(identity(3), *MFX_SYMBOL),
+    );
+    assert_eq!(harness.balance_(identity(2)), 100u32);
+
+    let info = harness
+        .module_impl
+        .info(
+            &identity(1),
+            TokenInfoArgs {
+                symbol: *MFX_SYMBOL,
+                extended_info: None,
+            },
+            Context::new(RequestMessage::default(), unbounded().0),
+        )
+        .unwrap();
+    assert_eq!(info.rules, Some(rules));
+}
+
+#[test]
+fn max_per_transfer() {
+    let mut harness = rules_setup();
+    let rules = TokenRules {
+        max_per_transfer: Some(100u32.into()),
+        ..Default::default()
+    };
+    update_rules(&mut harness, identity(1), rules).unwrap();
+
+    send(&mut harness, identity(1), identity(2), 100).unwrap();
+    assert_many_err(
+        send(&mut harness, identity(1), identity(2), 101),
+        error::amount_over_transfer_limit(101, 100),
+    );
+}
+
+#[test]
+fn pause() {
+    let mut harness = rules_setup();
+    let paused = TokenRules {
+        paused: true,
+        ..Default::default()
+    };
+    update_rules(&mut harness, identity(1), paused).unwrap();
+    assert_many_err(
+        send(&mut harness, identity(1), identity(2), 1),
+        error::transfers_paused(*MFX_SYMBOL),
+    );
+
+    // Empty rules remove them.
+    update_rules(&mut harness, identity(1), TokenRules::default()).unwrap();
+    send(&mut harness, identity(1), identity(2), 1).unwrap();
+}
+
+#[test]
+fn owner_only() {
+    let mut harness = rules_setup();
+    let paused = TokenRules {
+        paused: true,
+        ..Default::default()
+    };
+    assert!(update_rules(&mut harness, identity(2), paused).is_err());
+    send(&mut harness, identity(1), identity(2), 1).unwrap();
+}
+
+#[test]
+fn needs_migration() {
+    let mut harness = Setup::new_with_migrations(true, [(0, &TOKEN_MIGRATION)], true);
+    assert_many_err(
+        update_rules(&mut harness, identity(1), TokenRules::default()),
+        ManyError::invalid_method_name("tokens.updateRules"),
+    );
+}
+
+#[test]
+fn reservation() {
+    let mut harness = rules_setup();
+    let prepare = |harness: &mut Setup, amount: u32| {
+        harness
+            .block(|h| {
+                h.module_impl.storage_mut().prepare_transfer(
+                    &identity(1),
+                    &identity(2),
+                    &MFX_SYMBOL,
+                    amount.into(),
+                    None,
+                )
+            })
+            .1
+    };
+    let (id, _) = prepare(&mut harness, 100).unwrap();
+
+    let paused = TokenRules {
+        paused: true,
+        ..Default::default()
+    };
+    update_rules(&mut harness, identity(1), paused).unwrap();
+    assert_many_err(
+        prepare(&mut harness, 1),
+        error::transfers_paused(*MFX_SYMBOL),
+    );
+
+    // A prepared transfer cannot be committed while the token is paused, but
+    // it can be aborted.
+    assert_many_err(
+        harness
+            .block(|h| h.module_impl.storage_mut().commit_transfer(&id))
+            .1,
+        error::transfers_paused(*MFX_SYMBOL),
+    );
+    harness
+        .block(|h| h.module_impl.storage_mut().abort_transfer(&id))
+        .1
+        .unwrap();
+    assert_eq!(harness.balance_(identity(1)), 1000u32);
+    assert_eq!(harness.balance_(identity(2)), 0u32);
+}
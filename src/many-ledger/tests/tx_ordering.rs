@@ -0,0 +1,113 @@
+use coset::{CborSerializable, CoseSign1Builder};
+use many_identity::testing::identity;
+use many_ledger::json::{InitialStateJson, TxOrderingJson};
+use many_ledger::module::LedgerModuleImpl;
+use many_modules::abci_backend::{AbciProposal, ManyAbciModuleBackend, TxOrdering};
+use many_protocol::RequestMessage;
+use minicbor::bytes::ByteVec;
+use std::collections::BTreeMap;
+
+fn state() -> InitialStateJson {
+    let mut state = InitialStateJson::read("../../staging/ledger_state.json5")
+        .or_else(|_| InitialStateJson::read("staging/ledger_state.json5"))
+        .unwrap();
+    state.hash = None;
+    state
+}
+
+fn module_impl(ordering: Option<TxOrderingJson>) -> LedgerModuleImpl {
+    let mut state = state();
+    state.tx_ordering = ordering;
+    LedgerModuleImpl::new(state, None, tempfile::tempdir().unwrap(), true).unwrap()
+}
+
+fn tx(from: u32, method: &str) -> ByteVec {
+    let message = RequestMessage {
+        from: Some(identity(from)),
+        method: method.to_string(),
+        ..Default::default()
+    };
+    CoseSign1Builder::new()
+        .payload(message.to_bytes().unwrap())
+        .build()
+        .to_vec()
+        .unwrap()
+        .into()
+}
+
+fn proposal(txs: Vec<ByteVec>) -> AbciProposal {
+    AbciProposal {
+        txs,
+        height: 1,
+        time: None,
+    }
+}
+
+#[test]
+fn arrival_by_default() {
+    let module_impl = module_impl(None);
+    assert_eq!(
+        module_impl.storage().tx_ordering().unwrap(),
+        TxOrdering::Arrival
+    );
+
+    let txs = vec![tx(2, "ledger.send"), tx(1, "ledger.send")];
+    let prepared = module_impl.prepare_proposal(proposal(txs.clone())).unwrap();
+    assert_eq!(prepared.txs, txs);
+    assert!(module_impl.process_proposal(proposal(txs)).unwrap().accept);
+}
+
+fn priority() -> TxOrderingJson {
+    TxOrderingJson::Priority {
+        classes: BTreeMap::from([
+            ("tokens.mint".to_string(), 0),
+            ("ledger.send".to_string(), 1),
+        ]),
+    }
+}
+
+#[test]
+fn priority_classes() {
+    let module_impl = module_impl(Some(priority()));
+    assert!(matches!(
+        module_impl.storage().tx_ordering().unwrap(),
+        TxOrdering::Priority(_)
+    ));
+
+    let txs = vec![
+        tx(1, "kvstore.put"),
+        tx(1, "ledger.send"),
+        tx(2, "tokens.mint"),
+    ];
+    let prepared = module_impl.prepare_proposal(proposal(txs.clone())).unwrap();
+    assert_eq!(
+        prepared.txs,
+        vec![txs[2].clone(), txs[1].clone(), txs[0].clone()]
+    );
+    assert!(
+        module_impl
+            .process_proposal(proposal(prepared.txs))
+            .unwrap()
+            .accept
+    );
+    assert!(!module_impl.process_proposal(proposal(txs)).unwrap().accept);
+}
+
+#[test]
+fn ordering_is_part_of_the_state() {
+    let arrival = module_impl(None);
+    let priority = module_impl(Some(priority()));
+    assert_ne!(arrival.storage().hash(), priority.storage().hash());
+}
+
+#[test]
+fn block_gas_limit() {
+    let mut module_impl = module_impl(None);
+    assert_eq!(module_impl.init().unwrap().block_gas_limit, None);
+
+    let mut state = state();
+    state.block_gas_limit = Some(1_000_000);
+    let mut module_impl =
+        LedgerModuleImpl::new(state, None, tempfile::tempdir().unwrap(), true).unwrap();
+    assert_eq!(module_impl.init().unwrap().block_gas_limit, Some(1_000_000));
+}
@@ -0,0 +1,179 @@
+use many_error::ManyError;
+use many_identity::testing::identity;
+use many_ledger::error;
+use many_ledger::json::VestingJson;
+use many_ledger::migration::vesting::VESTING_MIGRATION;
+use many_ledger_test_utils::*;
+use many_modules::ledger::{
+    LedgerModuleBackend, LedgerTokensModuleBackend, TokenGrantVestingArgs, VestingInfoArgs,
+};
+use many_types::ledger::{TokenAmount, VestingGrant, VestingSchedule};
+use many_types::Timestamp;
+use std::collections::BTreeMap;
+
+/// Nothing unlocks before a cliff of 100 seconds, then everything unlocks
+/// over 1000 seconds from the first block.
+fn schedule() -> VestingSchedule {
+    VestingSchedule {
+        start: Timestamp::new(1_000_000).unwrap(),
+        cliff: 100,
+        duration: 1000,
+    }
+}
+
+fn vesting_setup(migration_height: u64) -> Setup {
+    let mut harness =
+        Setup::new_with_migrations(true, [(migration_height, &VESTING_MIGRATION)], true);
+    harness.set_balance(identity(1), 1000, *MFX_SYMBOL);
+    harness
+}
+
+fn grant_vesting(harness: &mut Setup, schedule: VestingSchedule) -> Result<(), ManyError> {
+    harness
+        .block(|h| {
+            h.module_impl.grant_vesting(
+                &identity(1),
+                TokenGrantVestingArgs {
+                    symbol: *MFX_SYMBOL,
+                    to: identity(2),
+                    amount: 1000u32.into(),
+                    schedule,
+                    memo: None,
+                },
+            )
+        })
+        .1
+        .map(|_| ())
+}
+
+#[test]
+fn locked_until_cliff() {
+    let mut harness = vesting_setup(0);
+    grant_vesting(&mut harness, schedule()).unwrap();
+    assert_eq!(
+        harness.balance(identity(2), *MFX_SYMBOL),
+        Ok(1000u32.into())
+    );
+
+    let (_, result) = harness.block(|h| h.send(identity(2), identity(3), 1u32, *MFX_SYMBOL));
+    assert_many_err(result, error::funds_locked(1000, *MFX_SYMBOL));
+}
+
+#[test]
+fn linear_release() {
+    let mut harness = vesting_setup(0);
+    grant_vesting(&mut harness, schedule()).unwrap();
+
+    // Half of the grant is unlocked in the next block.
+    harness.inc_time(498);
+    harness.block(|h| {
+        h.send(identity(2), identity(3), 500u32, *MFX_SYMBOL)
+            .unwrap();
+        assert_many_err(
+            h.send(identity(2), identity(3), 1u32, *MFX_SYMBOL),
+            error::funds_locked(500, *MFX_SYMBOL),
+        );
+    });
+
+    harness.inc_time(1000);
+    harness.block(|h| {
+        h.send(identity(2), identity(3), 500u32, *MFX_SYMBOL)
+            .unwrap()
+    });
+    assert_eq!(
+        harness.balance(identity(3), *MFX_SYMBOL),
+        Ok(1000u32.into())
+    );
+}
+
+#[test]
+fn vesting_info() {
+    let mut harness = vesting_setup(0);
+    grant_vesting(&mut harness, schedule()).unwrap();
+    harness.inc_time(248);
+    harness.block(|_| {});
+
+    let info = harness
+        .module_impl
+        .vesting_info(
+            &identity(2),
+            VestingInfoArgs {
+                account: None,
+                symbols: None,
+            },
+        )
+        .unwrap();
+    let mfx = &info.vesting[&*MFX_SYMBOL];
+    assert_eq!(
+        mfx.grants,
+        vec![VestingGrant {
+            amount: 1000u32.into(),
+            schedule: schedule(),
+        }]
+    );
+    assert_eq!(mfx.locked, TokenAmount::from(750u32));
+
+    // Accounts without grants have no vesting info.
+    let info = harness.module_impl.vesting_info(
+        &identity(1),
+        VestingInfoArgs {
+            account: None,
+            symbols: Some(vec![*MFX_SYMBOL].into()),
+        },
+    );
+    assert!(info.unwrap().vesting.is_empty());
+}
+
+#[test]
+fn invalid_schedule() {
+    let mut harness = vesting_setup(0);
+    let schedule = VestingSchedule {
+        cliff: 2000,
+        ..schedule()
+    };
+    assert_many_err(
+        grant_vesting(&mut harness, schedule),
+        error::invalid_vesting_schedule(),
+    );
+    assert_eq!(
+        harness.balance(identity(1), *MFX_SYMBOL),
+        Ok(1000u32.into())
+    );
+}
+
+#[test]
+fn migration_inactive() {
+    let mut harness = vesting_setup(10);
+    assert_many_err(
+        grant_vesting(&mut harness, schedule()),
+        ManyError::invalid_method_name("tokens.grantVesting"),
+    );
+}
+
+/// Initial balances can vest, whether or not the migration is active.
+#[test]
+fn genesis() {
+    let mut harness = Setup::new_with_state(true, [(10, &VESTING_MIGRATION)], |state| {
+        state.initial.insert(
+            identity(2),
+            BTreeMap::from([("MFX".to_string(), 1000u32.into())]),
+        );
+        state.vesting = Some(vec![VestingJson {
+            account: identity(2),
+            symbol: "MFX".to_string(),
+            amount: 600u32.into(),
+            start: 1_000_000,
+            cliff: 100,
+            duration: 1000,
+        }]);
+    });
+
+    harness.block(|h| {
+        h.send(identity(2), identity(3), 400u32, *MFX_SYMBOL)
+            .unwrap();
+        assert_many_err(
+            h.send(identity(2), identity(3), 1u32, *MFX_SYMBOL),
+            error::funds_locked(600, *MFX_SYMBOL),
+        );
+    });
+}
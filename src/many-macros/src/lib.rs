@@ -14,6 +14,12 @@ struct ManyModuleAttributes {
     pub name: Option<String>,
     pub namespace: Option<String>,
     pub many_modules_crate: Option<String>,
+    pub client: Option<bool>,
+    /// The latest version of the endpoints, 1 by default.
+    pub version: Option<u32>,
+    /// The oldest version of the endpoints still supported, `version` by
+    /// default.
+    pub oldest_version: Option<u32>,
 }
 
 #[derive(Debug, Default, Deserialize)]
@@ -409,6 +415,37 @@ impl Endpoint {
             }
         }
     }
+
+    /// Returns the method of the typed client calling this endpoint. The
+    /// sender is the identity of the client, and the context is server-side
+    /// only, so only the argument is kept.
+    pub fn client_method(&self, namespace: &Option<String>, many_modules: &Ident) -> TokenStream {
+        let span = self.span;
+        let name = self.name.as_str().to_camel_case();
+        let ep = match namespace {
+            Some(ref namespace) => format!("{namespace}.{name}"),
+            None => name,
+        };
+        let func = &self.func;
+        let ret_type = &self.ret_type;
+        let docs = self.attributes.iter().filter(|a| a.path().is_ident("doc"));
+
+        let (arg_decl, arg) = match &self.arg {
+            Some((_, ty)) => (quote! { , args: #ty }, quote! { args }),
+            None => (quote! {}, quote! { () }),
+        };
+
+        quote_spanned! { span =>
+            #(#docs)*
+            pub async fn #func(&self #arg_decl) -> #ret_type {
+                let argument = minicbor::to_vec(#arg)
+                    .map_err(|e| many_error::ManyError::serialization_error(e.to_string()))?;
+                let response = #many_modules ::ModuleClientTransport::call_endpoint(&self.0, #ep, argument).await?;
+                minicbor::decode(&response)
+                    .map_err(|e| many_error::ManyError::deserialization_error(e.to_string()))
+            }
+        }
+    }
 }
 
 impl quote::ToTokens for Endpoint {
@@ -556,6 +593,40 @@ fn many_module_impl(attr: &TokenStream, item: TokenStream) -> Result<TokenStream
         }
     };
 
+    let client = if attrs.client == Some(true) {
+        let client_name = format!("{struct_name}Client");
+        let client_ident = Ident::new(&client_name, attr.span());
+        let client_methods = endpoints
+            .iter()
+            .map(|e| e.client_method(&namespace, &many_modules));
+        let doc = format!(" A typed client for the endpoints of [`{struct_name}`].");
+
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Debug)]
+            #vis struct #client_ident<C: #many_modules ::ModuleClientTransport>(pub C);
+
+            impl<C: #many_modules ::ModuleClientTransport> #client_ident<C> {
+                pub fn new(client: C) -> Self {
+                    Self(client)
+                }
+
+                #( #client_methods )*
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let version = attrs.version.unwrap_or(1);
+    let oldest_version = attrs.oldest_version.unwrap_or(version);
+    if oldest_version > version {
+        return Err(syn::Error::new(
+            attr.span(),
+            "`oldest_version` cannot be higher than `version`.",
+        ));
+    }
+
     let attribute = if attrs.id.is_some() {
         quote! { Some(#attr_ident) }
     } else {
@@ -579,6 +650,7 @@ fn many_module_impl(attr: &TokenStream, item: TokenStream) -> Result<TokenStream
                         name: #struct_name .to_string(),
                         attribute: #attribute,
                         endpoints: vec![ #( #endpoint_strings .to_string() ),* ],
+                        version: many_protocol::ModuleVersion::new(#version).with_oldest(#oldest_version),
                     })));
                     &*VALUE
                 }
@@ -614,6 +686,8 @@ fn many_module_impl(attr: &TokenStream, item: TokenStream) -> Result<TokenStream
 
             #execute
         }
+
+        #client
     })
 }
 
@@ -0,0 +1,134 @@
+//! Key-range hotfixes, which rewrite the values of a range of keys of the
+//! storage, a batch of entries per block.
+//!
+//! The progress of a hotfix is kept in the storage, so a rewrite resumes
+//! where it stopped after a restart. It is logged with every batch.
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use serde_json::Value;
+use std::collections::HashMap;
+use tracing::info;
+
+/// Transform the value of an entry, returning its new value, or `None` to
+/// keep it.
+pub type FnEntry = fn(&[u8], &[u8]) -> Option<Vec<u8>>;
+
+/// The number of entries rewritten per block, unless the `batch_size` of
+/// the metadata of the migration is set.
+pub const DEFAULT_BATCH_SIZE: u64 = 1000;
+
+/// A range of keys, from `start` (included) to `end` (excluded, or the last
+/// key of the storage).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct KeyRange {
+    pub start: &'static [u8],
+    pub end: Option<&'static [u8]>,
+}
+
+/// How far a key-range hotfix went.
+#[derive(Clone, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct KeyRangeProgress {
+    /// The last key rewritten.
+    #[n(0)]
+    pub last_key: Option<ByteVec>,
+
+    /// The number of entries rewritten.
+    #[n(1)]
+    pub processed: u64,
+
+    /// The number of entries in the range when the hotfix started.
+    #[n(2)]
+    pub total: u64,
+
+    #[n(3)]
+    pub done: bool,
+}
+
+impl KeyRangeProgress {
+    pub fn percent(&self) -> f64 {
+        if self.total == 0 {
+            100.
+        } else {
+            (self.processed.min(self.total) as f64) * 100. / self.total as f64
+        }
+    }
+}
+
+/// Access to the key ranges of a storage `T`, for key-range hotfixes. The
+/// access is a separate type from the storage, so it can be implemented by
+/// the crate of the migrations for a storage of another crate.
+pub trait KeyRangeStorage<T, E> {
+    /// The number of entries in a range.
+    fn count_entries(storage: &T, range: &KeyRange) -> Result<u64, E>;
+
+    /// Up to `limit` entries of a range, in key order, after the key `after`
+    /// if set.
+    fn entries(
+        storage: &T,
+        range: &KeyRange,
+        after: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, E>;
+
+    /// Put new values, sorted by key.
+    fn put_entries(storage: &mut T, entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), E>;
+
+    fn progress(storage: &T, name: &str) -> Result<Option<KeyRangeProgress>, E>;
+
+    fn set_progress(storage: &mut T, name: &str, progress: &KeyRangeProgress) -> Result<(), E>;
+}
+
+/// Rewrite the next batch of entries of a range.
+pub(crate) fn key_range_step<S: KeyRangeStorage<T, E>, T, E>(
+    storage: &mut T,
+    name: &str,
+    range: &KeyRange,
+    transform: FnEntry,
+    extra: &HashMap<String, Value>,
+) -> Result<(), E> {
+    let mut progress = match S::progress(storage, name)? {
+        Some(progress) => progress,
+        None => KeyRangeProgress {
+            total: S::count_entries(storage, range)?,
+            ..Default::default()
+        },
+    };
+    if progress.done {
+        return Ok(());
+    }
+
+    let batch_size = extra
+        .get("batch_size")
+        .and_then(Value::as_u64)
+        .unwrap_or(DEFAULT_BATCH_SIZE)
+        .max(1);
+    let entries = S::entries(
+        storage,
+        range,
+        progress.last_key.as_ref().map(|key| key.as_slice()),
+        batch_size,
+    )?;
+    let len = entries.len() as u64;
+    let last_key = entries.last().map(|(key, _)| ByteVec::from(key.clone()));
+
+    let changes = entries
+        .into_iter()
+        .filter_map(|(key, value)| transform(&key, &value).map(|value| (key, value)))
+        .collect::<Vec<_>>();
+    if !changes.is_empty() {
+        S::put_entries(storage, changes)?;
+    }
+
+    progress.processed += len;
+    progress.last_key = last_key.or(progress.last_key);
+    progress.done = len < batch_size;
+    info!(
+        "Hotfix {name}: {}/{} entries ({:.1}%){}",
+        progress.processed,
+        progress.total,
+        progress.percent(),
+        if progress.done { ", done" } else { "" }
+    );
+    S::set_progress(storage, name, &progress)
+}
@@ -2,19 +2,24 @@
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fmt;
 use std::fmt::Formatter;
 use std::ops::Index;
 use strum::Display;
 use tracing::trace;
 
+pub mod key_range;
+
+pub use key_range::{FnEntry, KeyRange, KeyRangeProgress, KeyRangeStorage};
+
 // Initialize and update functions receive the `metadata.extra` fields.
 // The `metadata.extra` field can be used to provide custom parameters to migrations.
 pub type FnPtr<T, E> = fn(&mut T, &HashMap<String, Value>) -> Result<(), E>;
 pub type FnByte = fn(&[u8]) -> Option<Vec<u8>>;
 
 #[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(try_from = "MetadataConfig", into = "MetadataConfig")]
 pub struct Metadata {
     pub block_height: u64,
 
@@ -22,22 +27,176 @@ pub struct Metadata {
     /// TODO: use this in other migrations type too, maybe.
     pub upper_block_height: Option<u64>,
 
-    #[serde(default)]
+    /// Activate the migration at the first block whose time is this or
+    /// later, in seconds since the UNIX epoch, instead of at `block_height`.
+    pub upgrade_time: Option<u64>,
+
     pub disabled: bool,
 
     pub issue: Option<String>,
 
-    #[serde(flatten)]
+    /// The nodes the migration is enabled on.
+    pub rollout: Rollout,
+
     pub extra: HashMap<String, Value>,
 }
 
+/// How a migration is rolled out over the nodes of a network.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum Rollout {
+    /// The migration is enabled on all nodes.
+    #[default]
+    Network,
+
+    /// The migration is only enabled on canary nodes, to validate it on a
+    /// subset of the nodes before enabling it network-wide. Nodes which are
+    /// not canaries, or whose identity is unknown, consider it disabled.
+    ///
+    /// Canary nodes compute a different app hash once the migration changes
+    /// the state, so they should not be validators.
+    Canary(Canary),
+}
+
+impl Rollout {
+    pub fn is_network(&self) -> bool {
+        matches!(self, Rollout::Network)
+    }
+
+    /// Whether the migration `name` is enabled on the node of the identity
+    /// given, if known.
+    pub fn includes(&self, name: &str, node: Option<&str>) -> bool {
+        match (self, node) {
+            (Rollout::Network, _) => true,
+            (Rollout::Canary(canary), Some(node)) => canary.includes(name, node),
+            (Rollout::Canary(_), None) => false,
+        }
+    }
+}
+
+/// The canary nodes of a migration: the nodes listed, and a percentage of
+/// all nodes, picked by a hash of their identity.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Canary {
+    /// The identities of the canary nodes.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub nodes: BTreeSet<String>,
+
+    /// The percentage of nodes which are canaries, from 0 to 100.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percentage: Option<u8>,
+}
+
+impl Canary {
+    /// The bucket of a node for a migration, from 0 to 99. Buckets are
+    /// hashed with FNV-1a so they are the same on every build, and differ
+    /// between migrations so the same nodes are not always canaries.
+    pub fn bucket(name: &str, node: &str) -> u8 {
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in name.bytes().chain([0]).chain(node.bytes()) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        (hash % 100) as u8
+    }
+
+    pub fn includes(&self, name: &str, node: &str) -> bool {
+        self.nodes.contains(node)
+            || self
+                .percentage
+                .map_or(false, |percentage| Self::bucket(name, node) < percentage)
+    }
+}
+
+/// The serialized form of the metadata of a migration, which has exactly
+/// one of a block height or an upgrade time.
+#[derive(Clone, Deserialize, Serialize)]
+struct MetadataConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    block_height: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upper_block_height: Option<u64>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    upgrade_time: Option<u64>,
+
+    #[serde(default)]
+    disabled: bool,
+
+    issue: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Rollout::is_network")]
+    rollout: Rollout,
+
+    #[serde(flatten)]
+    extra: HashMap<String, Value>,
+}
+
+impl TryFrom<MetadataConfig> for Metadata {
+    type Error = String;
+
+    fn try_from(config: MetadataConfig) -> Result<Self, Self::Error> {
+        let block_height = match (config.block_height, config.upgrade_time) {
+            (Some(height), None) => height,
+            (None, Some(_)) if config.upper_block_height.is_some() => {
+                return Err("`upper_block_height` requires a `block_height`".to_string());
+            }
+            (None, Some(_)) => 0,
+            _ => {
+                return Err(
+                    "exactly one of `block_height` or `upgrade_time` must be set".to_string(),
+                );
+            }
+        };
+        if let Rollout::Canary(Canary {
+            percentage: Some(percentage),
+            ..
+        }) = config.rollout
+        {
+            if percentage > 100 {
+                return Err(format!("invalid canary percentage {percentage}"));
+            }
+        }
+
+        Ok(Self {
+            block_height,
+            upper_block_height: config.upper_block_height,
+            upgrade_time: config.upgrade_time,
+            disabled: config.disabled,
+            issue: config.issue,
+            rollout: config.rollout,
+            extra: config.extra,
+        })
+    }
+}
+
+impl From<Metadata> for MetadataConfig {
+    fn from(metadata: Metadata) -> Self {
+        Self {
+            block_height: metadata
+                .upgrade_time
+                .is_none()
+                .then_some(metadata.block_height),
+            upper_block_height: metadata.upper_block_height,
+            upgrade_time: metadata.upgrade_time,
+            disabled: metadata.disabled,
+            issue: metadata.issue,
+            rollout: metadata.rollout,
+            extra: metadata.extra,
+        }
+    }
+}
+
 impl Metadata {
     pub fn enabled(block_height: u64) -> Self {
         Self {
             block_height,
             upper_block_height: None,
+            upgrade_time: None,
             disabled: false,
             issue: None,
+            rollout: Rollout::Network,
             extra: Default::default(),
         }
     }
@@ -46,11 +205,22 @@ impl Metadata {
         Self {
             block_height,
             upper_block_height: None,
+            upgrade_time: None,
             disabled: true,
             issue: None,
+            rollout: Rollout::Network,
             extra: Default::default(),
         }
     }
+
+    /// Metadata of an enabled migration activating at `upgrade_time`, in
+    /// seconds since the UNIX epoch.
+    pub fn enabled_at_time(upgrade_time: u64) -> Self {
+        Self {
+            upgrade_time: Some(upgrade_time),
+            ..Self::enabled(0)
+        }
+    }
 }
 
 #[derive(Copy, Clone, Display)]
@@ -58,6 +228,7 @@ impl Metadata {
 pub enum MigrationType<T, E> {
     Regular(RegularMigration<T, E>),
     Hotfix(HotfixMigration),
+    KeyRangeHotfix(KeyRangeHotfixMigration<T, E>),
     Trigger(TriggerMigration),
 
     #[non_exhaustive]
@@ -81,6 +252,26 @@ pub struct HotfixMigration {
     hotfix_fn: FnByte,
 }
 
+/// A hotfix migration rewriting the values of a range of keys, a batch per
+/// block from its activation until the whole range was rewritten.
+#[derive(Copy, Clone)]
+pub struct KeyRangeHotfixMigration<T, E> {
+    range: KeyRange,
+    transform_fn: FnEntry,
+    step_fn: fn(&mut T, &str, &KeyRange, FnEntry, &HashMap<String, Value>) -> Result<(), E>,
+}
+
+impl<T, E> KeyRangeHotfixMigration<T, E> {
+    #[inline]
+    pub const fn range(&self) -> &KeyRange {
+        &self.range
+    }
+
+    fn step(&self, storage: &mut T, name: &str, extra: &HashMap<String, Value>) -> Result<(), E> {
+        (self.step_fn)(storage, name, &self.range, self.transform_fn, extra)
+    }
+}
+
 /// A trigger migration is simply a migration that is active in a range of
 /// blocks, but has no other behaviour associated. There is no storage
 /// associated with this migration.
@@ -137,6 +328,27 @@ impl<T, E> InnerMigration<T, E> {
         }
     }
 
+    /// A hotfix rewriting the values of `range` with `transform_fn`, through
+    /// the storage access `S`. It starts at the block height of the
+    /// migration, and goes on at the next blocks with the `batch_size` next
+    /// entries until the end of the range.
+    pub const fn new_key_range_hotfix<S: KeyRangeStorage<T, E>>(
+        range: KeyRange,
+        transform_fn: FnEntry,
+        name: &'static str,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            r#type: MigrationType::KeyRangeHotfix(KeyRangeHotfixMigration {
+                range,
+                transform_fn,
+                step_fn: key_range::key_range_step::<S, T, E>,
+            }),
+            name,
+            description,
+        }
+    }
+
     pub const fn new_initialize_update(
         initialize_fn: FnPtr<T, E>,
         update_fn: FnPtr<T, E>,
@@ -215,10 +427,16 @@ impl<T, E> InnerMigration<T, E> {
         matches!(self.r#type, MigrationType::Trigger(_))
     }
 
+    #[inline]
+    pub const fn is_hotfix(&self) -> bool {
+        matches!(self.r#type, MigrationType::Hotfix(_))
+    }
+
     /// This function gets executed when the storage block height == the migration block height
     fn initialize(&self, storage: &mut T, extra: &HashMap<String, Value>) -> Result<(), E> {
         match &self.r#type {
             MigrationType::Regular(migration) => (migration.initialize_fn)(storage, extra),
+            MigrationType::KeyRangeHotfix(migration) => migration.step(storage, self.name, extra),
             MigrationType::Hotfix(_) | MigrationType::Trigger(_) => Ok(()),
             x => {
                 trace!("Migration {} has unknown type {}", self.name(), x);
@@ -231,6 +449,7 @@ impl<T, E> InnerMigration<T, E> {
     fn update(&self, storage: &mut T, extra: &HashMap<String, Value>) -> Result<(), E> {
         match &self.r#type {
             MigrationType::Regular(migration) => (migration.update_fn)(storage, extra),
+            MigrationType::KeyRangeHotfix(migration) => migration.step(storage, self.name, extra),
             MigrationType::Hotfix(_) | MigrationType::Trigger(_) => Ok(()),
             x => {
                 trace!("Migration {} has unknown type {}", self.name(), x);
@@ -243,7 +462,9 @@ impl<T, E> InnerMigration<T, E> {
     fn hotfix<'b>(&'b self, b: &'b [u8]) -> Option<Vec<u8>> {
         match &self.r#type {
             MigrationType::Hotfix(migration) => (migration.hotfix_fn)(b),
-            MigrationType::Regular(_) | MigrationType::Trigger(_) => None,
+            MigrationType::Regular(_)
+            | MigrationType::KeyRangeHotfix(_)
+            | MigrationType::Trigger(_) => None,
             x => {
                 trace!("Migration {} has unknown type {}", self.name(), x);
                 None
@@ -269,6 +490,9 @@ pub struct Migration<'a, T, E> {
 
     /// Whether the block height has been reached.
     active: bool,
+
+    /// The time of the last block seen, if known.
+    last_time: Option<u64>,
 }
 
 // The Debug derive requires that _all_ parametric types also implement Debug,
@@ -280,10 +504,24 @@ impl<'a, T, E> fmt::Debug for Migration<'a, T, E> {
             .field("metadata", &self.metadata)
             .field("enabled", &self.enabled)
             .field("active", &self.active)
+            .field("last_time", &self.last_time)
             .finish()
     }
 }
 
+// The Clone derive has the same issue as Debug.
+impl<'a, T, E> Clone for Migration<'a, T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            migration: self.migration,
+            metadata: self.metadata.clone(),
+            enabled: self.enabled,
+            active: self.active,
+            last_time: self.last_time,
+        }
+    }
+}
+
 impl<'a, T, E> fmt::Display for Migration<'a, T, E> {
     fn fmt(&self, formatter: &mut Formatter) -> fmt::Result {
         formatter.write_fmt(format_args!(
@@ -303,6 +541,41 @@ impl<'a, T, E> Migration<'a, T, E> {
             metadata,
             enabled,
             active: false,
+            last_time: None,
+        }
+    }
+
+    /// Activate this migration at a block of a certain height and time,
+    /// returning whether initialize, update or no follow-up step should be
+    /// taken.
+    fn activate_at(&mut self, height: u64, time: Option<u64>) -> Activated {
+        let Some(upgrade_time) = self.metadata.upgrade_time else {
+            return self.activate_at_height(height);
+        };
+        // Blocks without a time do not move time-based migrations.
+        let Some(time) = time else {
+            return Activated::None;
+        };
+        let last_time = self.last_time.replace(time);
+
+        if self.migration.is_trigger() {
+            self.active = time >= upgrade_time;
+            trace!("... Trigger at time {time} is {}", self.active);
+            Activated::None
+        } else if time < upgrade_time {
+            Activated::None
+        } else if self.active {
+            Activated::Update
+        } else if last_time.is_none() {
+            // Without the time of the previous block, e.g. right after
+            // loading the set, the upgrade time was already reached.
+            trace!("... Already active at time {time}");
+            self.active = true;
+            Activated::Update
+        } else {
+            trace!("... Activating at time {time}");
+            self.active = true;
+            Activated::Initialize
         }
     }
 
@@ -327,7 +600,17 @@ impl<'a, T, E> Migration<'a, T, E> {
     }
 
     /// Activate this migration, during initialization. This does not initialize
-    /// or call any side effects.
+    /// or call any side effects. `time` is the time of the block at `height`,
+    /// if known. Nothing is active before the first block.
+    fn set_active_at(&mut self, height: u64, time: Option<u64>) {
+        if let Some(upgrade_time) = self.metadata.upgrade_time {
+            self.last_time = if height == 0 { Some(0) } else { time };
+            self.active = self.last_time.map_or(false, |time| time >= upgrade_time);
+        } else {
+            self.set_active_at_height(height);
+        }
+    }
+
     fn set_active_at_height(&mut self, height: u64) {
         if self.migration.is_trigger() {
             self.active = (self.metadata.block_height
@@ -343,9 +626,20 @@ impl<'a, T, E> Migration<'a, T, E> {
         &mut self,
         storage: &mut T,
         block_height: u64,
+    ) -> Result<(), E> {
+        self.maybe_initialize_update_at(storage, block_height, None)
+    }
+
+    /// Check the height and time of a block, and call the inner migration's
+    /// methods.
+    pub fn maybe_initialize_update_at(
+        &mut self,
+        storage: &mut T,
+        block_height: u64,
+        block_time: Option<u64>,
     ) -> Result<(), E> {
         if self.is_enabled() {
-            match self.activate_at_height(block_height) {
+            match self.activate_at(block_height, block_time) {
                 Activated::Initialize => {
                     self.migration.initialize(storage, &self.metadata.extra)?
                 }
@@ -459,6 +753,11 @@ impl<T, E> From<(&InnerMigration<T, E>, Metadata)> for SingleMigrationConfig {
 pub struct MigrationConfig {
     #[serde(skip)]
     strict: Option<bool>,
+
+    /// The identity of this node, for canary migrations.
+    #[serde(skip)]
+    node: Option<String>,
+
     migrations: Vec<SingleMigrationConfig>,
 }
 
@@ -472,6 +771,13 @@ impl MigrationConfig {
         self
     }
 
+    /// Set the identity of this node. Canary migrations are only enabled
+    /// if it is one of their canaries.
+    pub fn with_node(mut self, node: impl ToString) -> Self {
+        self.node = Some(node.to_string());
+        self
+    }
+
     pub fn with_migration<T, E>(self, migration: &InnerMigration<T, E>) -> Self {
         self.with_migration_opts(migration, Metadata::default())
     }
@@ -493,6 +799,7 @@ impl<T: IntoIterator<Item = impl Into<SingleMigrationConfig>>> From<T> for Migra
     fn from(value: T) -> Self {
         Self {
             strict: None,
+            node: None,
             migrations: value.into_iter().map(Into::into).collect(),
         }
     }
@@ -510,6 +817,14 @@ impl<'a, T, E: fmt::Debug> fmt::Debug for MigrationSet<'a, T, E> {
     }
 }
 
+impl<'a, T, E> Clone for MigrationSet<'a, T, E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
 impl<'a, T, E> MigrationSet<'a, T, E> {
     pub fn empty() -> Result<Self, String> {
         Ok(Self {
@@ -525,8 +840,22 @@ impl<'a, T, E> MigrationSet<'a, T, E> {
         registry: &'a [InnerMigration<T, E>],
         config: MigrationConfig,
         height: u64,
+    ) -> Result<Self, String> {
+        Self::load_at(registry, config, height, None)
+    }
+
+    /// Load the migrations at a height, where the block at that height has
+    /// the given time, if known. Time-based migrations are considered
+    /// active at the first block after loading if its time is past their
+    /// upgrade time, without being initialized.
+    pub fn load_at(
+        registry: &'a [InnerMigration<T, E>],
+        config: MigrationConfig,
+        height: u64,
+        time: Option<u64>,
     ) -> Result<Self, String> {
         let is_strict = config.is_strict();
+        let node = config.node;
 
         // Build a BTreeMap from the linear registry
         let registry = registry
@@ -542,7 +871,22 @@ impl<'a, T, E> MigrationSet<'a, T, E> {
                     .get(config.name.as_str())
                     .ok_or_else(|| format!("Unsupported migration '{}'", config.name))?;
 
-                Ok((config.name, Migration::new(v, config.metadata)))
+                if v.is_hotfix() && config.metadata.upgrade_time.is_some() {
+                    return Err(format!(
+                        "Hotfix migration '{}' can only activate at a block height",
+                        config.name
+                    ));
+                }
+
+                let mut migration = Migration::new(v, config.metadata);
+                if !migration.metadata.rollout.includes(v.name, node.as_deref()) {
+                    trace!(
+                        "Migration {} is disabled, this node is not a canary",
+                        v.name
+                    );
+                    migration.disable();
+                }
+                Ok((config.name, migration))
             })
             .collect::<Result<BTreeMap<_, _>, String>>()?
             .into_iter()
@@ -564,7 +908,7 @@ impl<'a, T, E> MigrationSet<'a, T, E> {
 
         // Activate all already active migrations. Do not call initialize though.
         for v in inner.values_mut().filter(|m| m.is_enabled()) {
-            v.set_active_at_height(height);
+            v.set_active_at(height, time);
         }
 
         Ok(Self { inner })
@@ -572,8 +916,21 @@ impl<'a, T, E> MigrationSet<'a, T, E> {
 
     #[inline]
     pub fn update_at_height(&mut self, storage: &mut T, block_height: u64) -> Result<(), E> {
+        self.update_at(storage, block_height, None)
+    }
+
+    /// Initialize or update the migrations at a block of a certain height
+    /// and time. Time-based migrations only move with blocks that have a
+    /// time.
+    #[inline]
+    pub fn update_at(
+        &mut self,
+        storage: &mut T,
+        block_height: u64,
+        block_time: Option<u64>,
+    ) -> Result<(), E> {
         for migration in self.inner.values_mut() {
-            migration.maybe_initialize_update_at_height(storage, block_height)?;
+            migration.maybe_initialize_update_at(storage, block_height, block_time)?;
 
             trace!(
                 "Migration {} updated at height {block_height}: active? {}",
@@ -0,0 +1,132 @@
+use many_migration::{
+    InnerMigration, KeyRange, KeyRangeProgress, KeyRangeStorage, MigrationConfig, MigrationSet,
+};
+use std::collections::BTreeMap;
+
+#[derive(Default)]
+struct Storage {
+    entries: BTreeMap<Vec<u8>, Vec<u8>>,
+    progress: BTreeMap<String, KeyRangeProgress>,
+}
+
+impl Storage {
+    fn range<'a>(&'a self, range: &KeyRange) -> impl Iterator<Item = (&'a Vec<u8>, &'a Vec<u8>)> {
+        let end = range.end.map(|end| end.to_vec());
+        self.entries
+            .range(range.start.to_vec()..)
+            .take_while(move |(key, _)| end.as_ref().map_or(true, |end| *key < end))
+    }
+}
+
+impl KeyRangeStorage<Storage, String> for Storage {
+    fn count_entries(storage: &Storage, range: &KeyRange) -> Result<u64, String> {
+        Ok(storage.range(range).count() as u64)
+    }
+
+    fn entries(
+        storage: &Storage,
+        range: &KeyRange,
+        after: Option<&[u8]>,
+        limit: u64,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>, String> {
+        Ok(storage
+            .range(range)
+            .filter(|(key, _)| after.map_or(true, |after| key.as_slice() > after))
+            .take(limit as usize)
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect())
+    }
+
+    fn put_entries(storage: &mut Storage, entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<(), String> {
+        storage.entries.extend(entries);
+        Ok(())
+    }
+
+    fn progress(storage: &Storage, name: &str) -> Result<Option<KeyRangeProgress>, String> {
+        Ok(storage.progress.get(name).cloned())
+    }
+
+    fn set_progress(
+        storage: &mut Storage,
+        name: &str,
+        progress: &KeyRangeProgress,
+    ) -> Result<(), String> {
+        storage.progress.insert(name.to_string(), progress.clone());
+        Ok(())
+    }
+}
+
+/// Double the values of the even keys.
+fn double_even(key: &[u8], value: &[u8]) -> Option<Vec<u8>> {
+    (key.last()? % 2 == 0).then(|| vec![value[0] * 2])
+}
+
+static MIGRATIONS: [InnerMigration<Storage, String>; 1] =
+    [InnerMigration::new_key_range_hotfix::<Storage>(
+        KeyRange {
+            start: b"/a/",
+            end: Some(b"/a0"),
+        },
+        double_even,
+        "Double",
+        "Double the even values of /a/",
+    )];
+
+fn storage() -> Storage {
+    let mut storage = Storage::default();
+    for i in 0..10u8 {
+        storage.entries.insert(vec![b'/', b'a', b'/', i], vec![i]);
+        storage.entries.insert(vec![b'/', b'b', b'/', i], vec![i]);
+    }
+    storage
+}
+
+fn load(height: u64) -> MigrationSet<'static, Storage, String> {
+    let config: MigrationConfig = serde_json::from_str(
+        r#"{ "migrations": [ { "name": "Double", "block_height": 2, "batch_size": 4 } ] }"#,
+    )
+    .unwrap();
+    MigrationSet::load(&MIGRATIONS, config, height).unwrap()
+}
+
+#[test]
+fn batches() {
+    let mut storage = storage();
+    let mut migrations = load(0);
+
+    migrations.update_at_height(&mut storage, 1).unwrap();
+    assert!(storage.progress.is_empty());
+
+    migrations.update_at_height(&mut storage, 2).unwrap();
+    let progress = &storage.progress["Double"];
+    assert_eq!((progress.processed, progress.total), (4, 10));
+    assert_eq!(storage.entries[&vec![b'/', b'a', b'/', 2]], vec![4]);
+    assert_eq!(storage.entries[&vec![b'/', b'a', b'/', 4]], vec![4]);
+
+    for height in 3..6 {
+        migrations.update_at_height(&mut storage, height).unwrap();
+    }
+    let progress = &storage.progress["Double"];
+    assert!(progress.done);
+    assert_eq!(progress.processed, 10);
+    assert_eq!(progress.percent(), 100.);
+
+    for i in 0..10u8 {
+        let doubled = if i % 2 == 0 { i * 2 } else { i };
+        assert_eq!(storage.entries[&vec![b'/', b'a', b'/', i]], vec![doubled]);
+        assert_eq!(storage.entries[&vec![b'/', b'b', b'/', i]], vec![i]);
+    }
+}
+
+#[test]
+fn resume() {
+    let mut storage = storage();
+    load(0).update_at_height(&mut storage, 2).unwrap();
+
+    // After a restart, the hotfix goes on from the last key rewritten.
+    let mut migrations = load(2);
+    migrations.update_at_height(&mut storage, 3).unwrap();
+    assert_eq!(storage.progress["Double"].processed, 8);
+    assert_eq!(storage.entries[&vec![b'/', b'a', b'/', 6]], vec![12]);
+    assert_eq!(storage.entries[&vec![b'/', b'a', b'/', 2]], vec![4]);
+}
@@ -2,7 +2,8 @@
 
 use linkme::distributed_slice;
 use many_migration::{
-    InnerMigration, Metadata, Migration, MigrationConfig, MigrationSet, MigrationType,
+    Canary, InnerMigration, Metadata, Migration, MigrationConfig, MigrationSet, MigrationType,
+    Rollout,
 };
 use serde_json::Value;
 use std::collections::{BTreeMap, HashMap};
@@ -505,3 +506,184 @@ fn strict_config_many() {
         r#"Migration Config is missing migrations ["C", "D", "E", "F"]"#.to_string()
     );
 }
+
+#[test]
+fn upgrade_time_config() {
+    let config: MigrationConfig =
+        serde_json::from_str(r#"{ "migrations": [ { "name": "A", "upgrade_time": 1000 } ] }"#)
+            .unwrap();
+    assert_eq!(config, [(&A, Metadata::enabled_at_time(1000))].into());
+
+    // The configuration round-trips without a block height.
+    let json = serde_json::to_string(&config).unwrap();
+    assert!(!json.contains("block_height"));
+    assert_eq!(
+        serde_json::from_str::<MigrationConfig>(&json).unwrap(),
+        config
+    );
+
+    for content in [
+        r#"{ "migrations": [ { "name": "A", "block_height": 1, "upgrade_time": 1000 } ] }"#,
+        r#"{ "migrations": [ { "name": "A" } ] }"#,
+        r#"{ "migrations": [ { "name": "A", "upgrade_time": 1000, "upper_block_height": 5 } ] }"#,
+    ] {
+        assert!(serde_json::from_str::<MigrationConfig>(content).is_err());
+    }
+
+    assert_eq!(
+        MigrationSet::load(
+            &SOME_MANY_RS_MIGRATIONS,
+            [(&D, Metadata::enabled_at_time(1000))].into(),
+            0,
+        )
+        .unwrap_err(),
+        "Hotfix migration 'D' can only activate at a block height".to_string(),
+    );
+}
+
+#[test]
+fn upgrade_time() {
+    let mut migration_set = MigrationSet::load(
+        &SOME_MANY_RS_MIGRATIONS,
+        [(&C, Metadata::enabled_at_time(1000))].into(),
+        0,
+    )
+    .unwrap();
+
+    let mut storage = Storage::new();
+    storage.insert(StorageKey::Counter, 0);
+
+    // Heights do not matter, nor blocks without a time.
+    migration_set
+        .update_at(&mut storage, 100, Some(999))
+        .unwrap();
+    migration_set.update_at(&mut storage, 101, None).unwrap();
+    assert!(!migration_set.is_active(&C));
+    assert!(!storage.contains_key(&StorageKey::Init));
+
+    migration_set
+        .update_at(&mut storage, 102, Some(1005))
+        .unwrap();
+    assert!(migration_set.is_active(&C));
+    assert_eq!(storage[&StorageKey::Init], 1);
+    assert_eq!(storage[&StorageKey::Counter], 0);
+
+    migration_set
+        .update_at(&mut storage, 103, Some(1010))
+        .unwrap();
+    assert_eq!(storage[&StorageKey::Counter], 1);
+}
+
+#[test]
+fn upgrade_time_load() {
+    let config: MigrationConfig = [(&C, Metadata::enabled_at_time(1000))].into();
+    let mut storage = Storage::new();
+    storage.insert(StorageKey::Counter, 0);
+
+    let migration_set =
+        MigrationSet::load_at(&SOME_MANY_RS_MIGRATIONS, config.clone(), 10, Some(1000)).unwrap();
+    assert!(migration_set.is_active(&C));
+
+    // When the time of the last block is unknown, a migration past its
+    // upgrade time is not initialized again.
+    let mut migration_set = MigrationSet::load(&SOME_MANY_RS_MIGRATIONS, config, 10).unwrap();
+    assert!(!migration_set.is_active(&C));
+    migration_set
+        .update_at(&mut storage, 11, Some(2000))
+        .unwrap();
+    assert!(migration_set.is_active(&C));
+    assert!(!storage.contains_key(&StorageKey::Init));
+    assert_eq!(storage[&StorageKey::Counter], 1);
+}
+
+fn canary(nodes: &[&str], percentage: Option<u8>) -> Metadata {
+    Metadata {
+        rollout: Rollout::Canary(Canary {
+            nodes: nodes.iter().map(|n| n.to_string()).collect(),
+            percentage,
+        }),
+        ..Metadata::enabled(1)
+    }
+}
+
+#[test]
+fn canary_config() {
+    let config: MigrationConfig = serde_json::from_str(
+        r#"{ "migrations": [ {
+            "name": "A",
+            "block_height": 1,
+            "rollout": { "canary": { "nodes": ["maa"], "percentage": 10 } }
+        } ] }"#,
+    )
+    .unwrap();
+    assert_eq!(config, [(&A, canary(&["maa"], Some(10)))].into());
+
+    let json = serde_json::to_string(&config).unwrap();
+    assert_eq!(
+        serde_json::from_str::<MigrationConfig>(&json).unwrap(),
+        config
+    );
+
+    // Migrations rolled out on the whole network are serialized as before.
+    let network: MigrationConfig = [(&A, Metadata::enabled(1))].into();
+    assert!(!serde_json::to_string(&network).unwrap().contains("rollout"));
+
+    assert!(serde_json::from_str::<MigrationConfig>(
+        r#"{ "migrations": [ {
+            "name": "A",
+            "block_height": 1,
+            "rollout": { "canary": { "percentage": 101 } }
+        } ] }"#,
+    )
+    .is_err());
+}
+
+#[test]
+fn canary_nodes() {
+    let config: MigrationConfig =
+        [(&A, canary(&["canary"], None)), (&B, Metadata::enabled(1))].into();
+    let load = |config: MigrationConfig| MigrationSet::load(&SOME_MANY_RS_MIGRATIONS, config, 0);
+
+    let set = load(config.clone().with_node("canary")).unwrap();
+    assert!(set.is_enabled(&A));
+    assert!(set.is_enabled(&B));
+
+    // Nodes which are not canaries, or whose identity is unknown.
+    for config in [config.clone().with_node("other"), config] {
+        let mut set = load(config).unwrap();
+        assert!(!set.is_enabled(&A));
+        assert!(set.is_enabled(&B));
+
+        let mut storage = Storage::new();
+        storage.insert(StorageKey::Counter, 0);
+        set.update_at_height(&mut storage, 1).unwrap();
+        assert!(!set.is_active(&A));
+        assert!(!storage.contains_key(&StorageKey::Init));
+    }
+}
+
+#[test]
+fn canary_percentage() {
+    let nodes: Vec<String> = (0..1000).map(|i| format!("node{i}")).collect();
+    let canaries = |name: &str, percentage| -> Vec<&String> {
+        let rollout = canary(&[], Some(percentage)).rollout;
+        nodes
+            .iter()
+            .filter(|node| rollout.includes(name, Some(node)))
+            .collect()
+    };
+
+    assert!(canaries("A", 0).is_empty());
+    assert_eq!(canaries("A", 100).len(), nodes.len());
+    assert_eq!(canaries("A", 10).len(), 102);
+
+    // Buckets are stable, and differ between migrations.
+    assert_eq!(Canary::bucket("A", "node0"), 48);
+    assert_eq!(Canary::bucket("B", "node0"), 1);
+    assert_ne!(canaries("A", 50), canaries("B", 50));
+
+    // Listed nodes are canaries whatever their bucket.
+    let rollout = canary(&["node0"], Some(0)).rollout;
+    assert!(rollout.includes("A", Some("node0")));
+    assert!(!rollout.includes("A", Some("node1")));
+}
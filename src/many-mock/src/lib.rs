@@ -3,8 +3,11 @@ use std::fmt;
 
 use serde::{de::Visitor, Deserialize, Deserializer, Serialize};
 
+pub mod scenario;
 pub mod server;
 
+pub use scenario::{parse_scenario, Scenario};
+
 pub type MockEntries = BTreeMap<String, Vec<u8>>;
 
 #[derive(Serialize, Deserialize, Debug)]
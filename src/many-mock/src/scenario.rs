@@ -0,0 +1,200 @@
+//! Scenarios of the mock server, to simulate flows of several calls: steps
+//! answering in order, or depending on the previous calls, with injected
+//! errors and latency.
+//!
+//! A scenario is a TOML file, or a JSON file of the same structure:
+//!
+//! ```toml
+//! [[steps]]
+//! method = "ledger.balance"
+//! response = '{0: {}}'
+//! times = 1
+//!
+//! [[steps]]
+//! method = "ledger.send"
+//! error = { code = -10001, message = "Try again later." }
+//! delay_ms = 500
+//!
+//! [[steps]]
+//! method = "ledger.balance"
+//! response = '{0: {"mfx": 10}}'
+//! after = ["ledger.send"]
+//! ```
+//!
+//! Responses are in CBOR diagnostic notation, like the entries of a
+//! mockfile.
+use many_error::{ManyError, ManyErrorCode};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::time::Duration;
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Scenario {
+    /// Whether the steps must be called in the order they are listed. The
+    /// steps of an ordered scenario answer a single call by default.
+    #[serde(default)]
+    pub ordered: bool,
+
+    pub steps: Vec<Step>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Step {
+    pub method: String,
+
+    /// The response, in CBOR diagnostic notation.
+    #[serde(default, deserialize_with = "deserialize_diag")]
+    pub response: Option<Vec<u8>>,
+
+    /// The error to return instead of a response.
+    pub error: Option<StepError>,
+
+    /// How long to wait before answering, in milliseconds.
+    #[serde(default)]
+    pub delay_ms: u64,
+
+    /// The methods that must have been called before the step answers.
+    #[serde(default)]
+    pub after: Vec<String>,
+
+    /// How many calls the step answers. By default, any number of calls, or
+    /// a single one in an ordered scenario.
+    pub times: Option<u64>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct StepError {
+    pub code: i64,
+    pub message: Option<String>,
+    #[serde(default)]
+    pub arguments: BTreeMap<String, String>,
+}
+
+impl From<&StepError> for ManyError {
+    fn from(error: &StepError) -> Self {
+        ManyError::new(
+            ManyErrorCode::from(error.code),
+            error.message.clone(),
+            error.arguments.clone(),
+        )
+    }
+}
+
+impl Step {
+    pub fn delay(&self) -> Duration {
+        Duration::from_millis(self.delay_ms)
+    }
+
+    pub fn data(&self) -> Result<Vec<u8>, ManyError> {
+        match &self.error {
+            Some(error) => Err(error.into()),
+            None => Ok(self.response.clone().unwrap_or_default()),
+        }
+    }
+}
+
+fn deserialize_diag<'de, D>(d: D) -> Result<Option<Vec<u8>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Option::<String>::deserialize(d)?
+        .map(|value| {
+            cbor_diag::parse_diag(value)
+                .map(|data| data.to_bytes())
+                .map_err(|e| serde::de::Error::custom(format!("Deserialization error: {e:?}")))
+        })
+        .transpose()
+}
+
+impl Scenario {
+    /// Read a scenario file, as JSON if its extension is `.json` and as TOML
+    /// otherwise.
+    pub fn read(path: &Path) -> Result<Self, String> {
+        let contents =
+            std::fs::read_to_string(path).map_err(|e| format!("Could not read {path:?}: {e}"))?;
+        let scenario: Self = if path.extension().map_or(false, |ext| ext == "json") {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            toml::from_str(&contents).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Invalid scenario {path:?}: {e}"))?;
+
+        if let Some(step) = scenario
+            .steps
+            .iter()
+            .find(|step| step.response.is_some() == step.error.is_some())
+        {
+            return Err(format!(
+                "Invalid scenario {path:?}: the step of {} needs either a response or an error",
+                step.method
+            ));
+        }
+        Ok(scenario)
+    }
+
+    /// Whether some step of the scenario answers `method`.
+    pub fn has_method(&self, method: &str) -> bool {
+        self.steps.iter().any(|step| step.method == method)
+    }
+
+    fn times(&self, step: &Step) -> u64 {
+        step.times
+            .unwrap_or(if self.ordered { 1 } else { u64::MAX })
+    }
+}
+
+/// Parses the scenario file at `scenario_arg`, e.g. as a CLI argument.
+pub fn parse_scenario(scenario_arg: &str) -> Result<Scenario, String> {
+    Scenario::read(Path::new(scenario_arg))
+}
+
+/// The calls a scenario received so far.
+#[derive(Debug, Default)]
+pub struct ScenarioState {
+    /// The number of calls of each method.
+    calls: BTreeMap<String, u64>,
+
+    /// The number of calls each step answered.
+    answered: Vec<u64>,
+
+    /// The first step of an ordered scenario that can still answer.
+    next: usize,
+}
+
+impl ScenarioState {
+    pub fn new(scenario: &Scenario) -> Self {
+        Self {
+            answered: vec![0; scenario.steps.len()],
+            ..Default::default()
+        }
+    }
+
+    fn can_answer(&self, scenario: &Scenario, index: usize, method: &str) -> bool {
+        let step = &scenario.steps[index];
+        step.method == method
+            && self.answered[index] < scenario.times(step)
+            && step.after.iter().all(|m| self.calls.contains_key(m))
+    }
+
+    /// The step answering a call of `method`, if any. The call is recorded
+    /// either way, for the conditions of the next steps.
+    pub fn answer<'a>(&mut self, scenario: &'a Scenario, method: &str) -> Option<&'a Step> {
+        let index = if scenario.ordered {
+            while self.next < scenario.steps.len()
+                && self.answered[self.next] >= scenario.times(&scenario.steps[self.next])
+            {
+                self.next += 1;
+            }
+            Some(self.next)
+                .filter(|&i| i < scenario.steps.len() && self.can_answer(scenario, i, method))
+        } else {
+            (0..scenario.steps.len()).find(|&i| self.can_answer(scenario, i, method))
+        };
+
+        *self.calls.entry(method.to_string()).or_default() += 1;
+        let index = index?;
+        self.answered[index] += 1;
+        Some(&scenario.steps[index])
+    }
+}
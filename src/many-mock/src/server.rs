@@ -8,8 +8,12 @@ use many_identity_webauthn::WebAuthnVerifier;
 use many_modules::base;
 use many_protocol::{ManyUrl, ResponseMessage};
 use many_server::transport::LowLevelManyRequestHandler;
+use std::collections::BTreeSet;
 use std::fmt::Debug;
+use std::sync::Mutex;
+use std::time::Duration;
 
+use crate::scenario::{Scenario, ScenarioState};
 use crate::MockEntries;
 
 #[derive(Debug)]
@@ -17,6 +21,9 @@ pub struct ManyMockServer<I: Identity> {
     mock_entries: MockEntries,
     identity: I,
     verifier: (AnonymousVerifier, CoseKeyVerifier, WebAuthnVerifier),
+
+    /// A scenario answering its methods before the mock entries.
+    scenario: Option<(Scenario, Mutex<ScenarioState>)>,
 }
 
 impl<I: Identity> ManyMockServer<I> {
@@ -35,6 +42,30 @@ impl<I: Identity> ManyMockServer<I> {
             mock_entries,
             identity,
             verifier,
+            scenario: None,
+        }
+    }
+
+    pub fn with_scenario(mut self, scenario: Scenario) -> Self {
+        let state = ScenarioState::new(&scenario);
+        self.scenario = Some((scenario, Mutex::new(state)));
+        self
+    }
+
+    /// The delay and response of the scenario to a call, if it has steps
+    /// for this method.
+    fn scenario_answer(&self, method: &str) -> Option<(Duration, Result<Vec<u8>, ManyError>)> {
+        let (scenario, state) = self.scenario.as_ref()?;
+        let mut state = state.lock().unwrap();
+        match state.answer(scenario, method) {
+            Some(step) => Some((step.delay(), step.data())),
+            None if scenario.has_method(method) => Some((
+                Duration::ZERO,
+                Err(ManyError::unknown(format!(
+                    "Unexpected call to {method} in the scenario."
+                ))),
+            )),
+            None => None,
         }
     }
 }
@@ -46,13 +77,22 @@ impl<I: Identity + Debug + Send + Sync> LowLevelManyRequestHandler for ManyMockS
         let id = &self.identity;
 
         let message = request.map_err(|_| "Error processing the request".to_string())?;
-        let response = self
-            .mock_entries
-            .get(&message.method)
-            .ok_or_else(|| "No mock entry for that".to_string())?;
+        let data = match self.scenario_answer(&message.method) {
+            Some((delay, data)) => {
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                data
+            }
+            None => Ok(self
+                .mock_entries
+                .get(&message.method)
+                .ok_or_else(|| "No mock entry for that".to_string())?
+                .clone()),
+        };
         let response = ResponseMessage {
             from: id.address(),
-            data: Ok(response.clone()),
+            data,
             ..Default::default()
         };
         many_protocol::encode_cose_sign1_from_response(response, id).map_err(|e| e.to_string())
@@ -61,7 +101,11 @@ impl<I: Identity + Debug + Send + Sync> LowLevelManyRequestHandler for ManyMockS
 
 impl<I: Identity> base::BaseModuleBackend for ManyMockServer<I> {
     fn endpoints(&self) -> Result<base::Endpoints, ManyError> {
-        Ok(base::Endpoints(self.mock_entries.keys().cloned().collect()))
+        let mut endpoints: BTreeSet<String> = self.mock_entries.keys().cloned().collect();
+        if let Some((scenario, _)) = &self.scenario {
+            endpoints.extend(scenario.steps.iter().map(|step| step.method.clone()));
+        }
+        Ok(base::Endpoints(endpoints))
     }
 
     fn status(&self) -> Result<base::Status, ManyError> {
@@ -76,6 +120,7 @@ impl<I: Identity> base::BaseModuleBackend for ManyMockServer<I> {
             extras: Default::default(),
             server_version: None,
             timeout: None,
+            module_versions: Default::default(),
         })
     }
 }
@@ -8,3 +8,12 @@ Scenario: The server should answer with a JSON
 Scenario: The server should answer with a string
   Given I request "simplefield"
   Then it should be "hello"
+
+Scenario: The server should answer according to the previous calls
+  Given I request "wallet.balance"
+  Then "amount" should be 10
+  Then requesting "wallet.balance" should fail with code -1
+  Then requesting "wallet.send" should fail with code -10001
+  Given I request "wallet.send"
+  Given I request "wallet.balance"
+  Then "amount" should be 5
@@ -35,13 +35,21 @@ impl MockWorld {
             .find(|&p| Path::new(p).exists())
             .expect("Test mock file not found");
 
+        let tmp = format!("{}/tests/testscenario.toml", env!("CARGO_MANIFEST_DIR"));
+        let scenario = [tmp.as_ref(), "src/many-mock/tests/testscenario.toml"]
+            .into_iter()
+            .find(|&p| Path::new(p).exists())
+            .expect("Test scenario not found");
+
         let mocktree = many_mock::parse_mockfile(mockfile).unwrap();
+        let scenario = many_mock::parse_scenario(scenario).unwrap();
         let key = AnonymousIdentity;
 
         let many = ManyServer::simple("integration", key.clone(), AcceptAllVerifier, None);
         {
             let mut many = many.lock().unwrap();
-            let mock_server = ManyMockServer::new(mocktree, None, key.clone());
+            let mock_server =
+                ManyMockServer::new(mocktree, None, key.clone()).with_scenario(scenario);
             many.set_fallback_module(mock_server);
         }
         let mut server = HttpServer::new(many);
@@ -71,6 +79,13 @@ async fn make_request(w: &mut MockWorld, method: String) {
     w.response = Some(response);
 }
 
+#[then(regex = r#"requesting "(.*)" should fail with code (-?\d+)"#)]
+async fn request_error(w: &mut MockWorld, method: String, code: i64) {
+    let result = w.client.call(method, ()).await.unwrap();
+    let error = result.data.expect_err("Should have failed");
+    assert_eq!(i64::from(error.code()), code);
+}
+
 #[allow(clippy::needless_pass_by_ref_mut)]
 #[then(regex = "it should be (.*)")]
 async fn full_value(w: &mut MockWorld, value: String) {
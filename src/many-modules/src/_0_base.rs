@@ -3,6 +3,7 @@ use coset::{CborSerializable, CoseKey};
 use derive_builder::Builder;
 use many_identity::Address;
 use many_macros::many_module;
+use many_protocol::ModuleVersions;
 use many_types::attributes::AttributeSet;
 use many_types::cbor::CborAny;
 use minicbor::data::Type;
@@ -21,6 +22,50 @@ pub struct Endpoints(#[n(0)] pub BTreeSet<String>);
 // TODO: Move this in it's own file, like other modules
 pub type HeartbeatReturn = EmptyReturn;
 
+pub type HealthReturn = EmptyReturn;
+
+/// The result of a readiness check. `reason` tells why a failed check failed.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ReadyCheck {
+    #[n(0)]
+    pub ok: bool,
+
+    #[n(1)]
+    pub reason: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ReadyReturn {
+    /// Whether all the checks passed.
+    #[n(0)]
+    pub ready: bool,
+
+    /// The checks of the server, by name.
+    #[n(1)]
+    pub checks: BTreeMap<String, ReadyCheck>,
+}
+
+impl ReadyReturn {
+    pub fn from_checks(checks: BTreeMap<String, Result<(), String>>) -> Self {
+        let checks: BTreeMap<String, ReadyCheck> = checks
+            .into_iter()
+            .map(|(name, result)| {
+                let check = ReadyCheck {
+                    ok: result.is_ok(),
+                    reason: result.err(),
+                };
+                (name, check)
+            })
+            .collect();
+        Self {
+            ready: checks.values().all(|c| c.ok),
+            checks,
+        }
+    }
+}
+
 #[derive(Clone, Debug, Builder)]
 pub struct Status {
     pub version: u8,
@@ -35,6 +80,10 @@ pub struct Status {
     #[builder(setter(into, strip_option), default)]
     pub timeout: Option<u64>,
 
+    /// The versions of the modules, by attribute ID.
+    #[builder(default)]
+    pub module_versions: ModuleVersions,
+
     #[builder(default)]
     pub extras: BTreeMap<String, CborAny>,
 }
@@ -73,6 +122,10 @@ impl<C> Encode<C> for Status {
             e.u8(7)?.encode(timeout)?;
         }
 
+        if !self.module_versions.0.is_empty() {
+            e.u8(8)?.encode(&self.module_versions)?;
+        }
+
         for (k, v) in &self.extras {
             e.str(k.as_str())?.encode(v)?;
         }
@@ -111,6 +164,7 @@ impl<'b, C> Decode<'b, C> for Status {
                         4 => builder.attributes(d.decode()?),
                         5 => builder.server_version(d.decode::<String>()?),
                         7 => builder.timeout(d.decode::<u64>()?),
+                        8 => builder.module_versions(d.decode()?),
                         _ => &mut builder,
                     };
                 }
@@ -143,6 +197,19 @@ pub trait BaseModuleBackend: Send {
         Ok(HeartbeatReturn {})
     }
     fn status(&self) -> Result<Status, ManyError>;
+
+    /// Liveness: whether the server answers at all.
+    fn health(&self) -> Result<HealthReturn, ManyError> {
+        Ok(HealthReturn {})
+    }
+
+    /// Readiness: whether the server can serve requests, e.g. its storage is
+    /// open and its blockchain still makes blocks. Unlike the other methods,
+    /// a server that is not ready answers with the checks that failed
+    /// instead of an error.
+    fn ready(&self) -> Result<ReadyReturn, ManyError> {
+        Ok(ReadyReturn::from_checks(BTreeMap::new()))
+    }
 }
 
 #[cfg(test)]
@@ -171,6 +238,10 @@ mod tests {
             }]),
             server_version: Some("1.0.0".to_string()),
             timeout: Some(300),
+            module_versions: ModuleVersions(BTreeMap::from([(
+                0,
+                many_protocol::ModuleVersion::new(2).with_oldest(1),
+            )])),
             extras: BTreeMap::new(),
         };
         mock.expect_status()
@@ -187,6 +258,7 @@ mod tests {
         assert_eq!(status.attributes, results.attributes);
         assert_eq!(status.server_version, results.server_version);
         assert_eq!(status.timeout, results.timeout);
+        assert_eq!(status.module_versions, results.module_versions);
 
         let results = Status::from_bytes(&status.to_bytes().unwrap()).unwrap();
         assert_eq!(status.version, results.version);
@@ -196,6 +268,7 @@ mod tests {
         assert_eq!(status.attributes, results.attributes);
         assert_eq!(status.server_version, results.server_version);
         assert_eq!(status.timeout, results.timeout);
+        assert_eq!(status.module_versions, results.module_versions);
     }
 
     #[test]
@@ -226,4 +299,20 @@ mod tests {
         let _: HeartbeatReturn =
             minicbor::decode(&call_module(1, &module, "heartbeat", "null").unwrap()).unwrap();
     }
+
+    #[test]
+    fn ready() {
+        let mut mock = MockBaseModuleBackend::new();
+        let ready = ReadyReturn::from_checks(BTreeMap::from([
+            ("abci".to_string(), Ok(())),
+            ("height".to_string(), Err("No new block".to_string())),
+        ]));
+        assert!(!ready.ready);
+        mock.expect_ready().times(1).return_const(Ok(ready.clone()));
+        let module = super::BaseModule::new(Arc::new(Mutex::new(mock)));
+        let results: ReadyReturn =
+            minicbor::decode(&call_module(1, &module, "ready", "null").unwrap()).unwrap();
+
+        assert_eq!(results, ready);
+    }
 }
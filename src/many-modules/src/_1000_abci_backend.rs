@@ -8,6 +8,11 @@ use std::collections::BTreeMap;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+mod gas;
+mod ordering;
+pub use gas::*;
+pub use ordering::*;
+
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
 pub struct EndpointInfo {
@@ -26,6 +31,18 @@ pub struct AbciInit {
     /// endpoints as it can't know if they change the state or not.
     #[n(0)]
     pub endpoints: BTreeMap<String, EndpointInfo>,
+
+    /// The gas cost of the endpoints, metered per block by the frontend.
+    /// Endpoints without a cost cost [`GasCost::DEFAULT`].
+    #[n(1)]
+    pub gas: Option<BTreeMap<String, GasCost>>,
+
+    /// The maximum gas the transactions of a block can use. It is part of
+    /// the state of the application, so every validator uses the same
+    /// limit. It is set at genesis and cannot change. Blocks are not limited
+    /// without it.
+    #[n(2)]
+    pub block_gas_limit: Option<u64>,
 }
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
@@ -55,6 +72,37 @@ pub struct AbciCommitInfo {
     pub hash: ByteVec,
 }
 
+/// A block proposal, as seen by the ABCI frontend. The transactions are the
+/// encoded envelopes, in the order they appear in the proposal.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AbciProposal {
+    #[n(0)]
+    pub txs: Vec<ByteVec>,
+
+    #[n(1)]
+    pub height: u64,
+
+    #[n(2)]
+    pub time: Option<u64>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PrepareProposalReturn {
+    /// The transactions to propose, in order. Transactions that were not part
+    /// of the proposal are ignored by the frontend.
+    #[n(0)]
+    pub txs: Vec<ByteVec>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ProcessProposalReturn {
+    #[n(0)]
+    pub accept: bool,
+}
+
 pub type InitChainReturn = EmptyReturn;
 pub type BeginBlockReturn = EmptyReturn;
 pub type EndBlockReturn = EmptyReturn;
@@ -75,6 +123,22 @@ pub trait ManyAbciModuleBackend: std::fmt::Debug + Send + Sync {
     /// Called at Genesis of the Tendermint blockchain.
     fn init_chain(&mut self) -> Result<InitChainReturn, ManyError>;
 
+    /// Called when this node proposes a block. The application can re-order or
+    /// remove transactions. This must not change the state of the application.
+    fn prepare_proposal(&self, proposal: AbciProposal) -> Result<PrepareProposalReturn, ManyError> {
+        Ok(PrepareProposalReturn { txs: proposal.txs })
+    }
+
+    /// Called when a block is proposed by another node. The application can
+    /// reject the proposal. This must be deterministic and must not change the
+    /// state of the application.
+    fn process_proposal(
+        &self,
+        _proposal: AbciProposal,
+    ) -> Result<ProcessProposalReturn, ManyError> {
+        Ok(ProcessProposalReturn { accept: true })
+    }
+
     /// Called at the start of a block.
     fn begin_block(&mut self, _info: AbciBlock) -> Result<BeginBlockReturn, ManyError> {
         Ok(BeginBlockReturn {})
@@ -103,6 +167,8 @@ mod tests {
     fn init() {
         let init = AbciInit {
             endpoints: BTreeMap::from([("Foo".to_string(), EndpointInfo { is_command: false })]),
+            gas: Some(BTreeMap::from([("Foo".to_string(), GasCost::new(1, 2))])),
+            block_gas_limit: Some(1_000_000),
         };
         let mut mock = MockManyAbciModuleBackend::new();
         mock.expect_init().times(1).return_const(Ok(init.clone()));
@@ -163,6 +229,64 @@ mod tests {
         assert_eq!(abci_info, info);
     }
 
+    #[test]
+    fn prepare_proposal() {
+        let proposal = AbciProposal {
+            txs: vec![vec![1u8].into(), vec![2u8].into()],
+            height: 2,
+            time: Some(1),
+        };
+        let mut mock = MockManyAbciModuleBackend::new();
+        mock.expect_prepare_proposal()
+            .with(predicate::eq(proposal.clone()))
+            .times(1)
+            .returning(|p| {
+                Ok(PrepareProposalReturn {
+                    txs: p.txs.into_iter().rev().collect(),
+                })
+            });
+        let module = super::AbciModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: PrepareProposalReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "abci.prepareProposal",
+                minicbor::to_vec(proposal).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.txs, vec![vec![2u8].into(), vec![1u8].into()]);
+    }
+
+    #[test]
+    fn process_proposal() {
+        let proposal = AbciProposal {
+            txs: vec![vec![1u8].into()],
+            height: 2,
+            time: None,
+        };
+        let mut mock = MockManyAbciModuleBackend::new();
+        mock.expect_process_proposal()
+            .with(predicate::eq(proposal.clone()))
+            .times(1)
+            .returning(|_| Ok(ProcessProposalReturn { accept: false }));
+        let module = super::AbciModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: ProcessProposalReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "abci.processProposal",
+                minicbor::to_vec(proposal).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(!result.accept);
+    }
+
     #[test]
     fn end_block() {
         let mut mock = MockManyAbciModuleBackend::new();
@@ -0,0 +1,204 @@
+use super::ordering::decode;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+/// The gas an endpoint costs to execute, as a function of the size of its
+/// envelope. Costs only depend on the transaction, so every node meters a
+/// block the same way.
+#[derive(Clone, Copy, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GasCost {
+    #[n(0)]
+    pub base: u64,
+
+    #[n(1)]
+    pub per_byte: u64,
+}
+
+impl GasCost {
+    /// The cost of endpoints that do not declare one, and of transactions
+    /// that cannot be decoded.
+    pub const DEFAULT: GasCost = GasCost {
+        base: 1_000,
+        per_byte: 1,
+    };
+
+    pub const fn new(base: u64, per_byte: u64) -> Self {
+        Self { base, per_byte }
+    }
+
+    /// The cost of a transaction of `size` bytes.
+    pub fn of(&self, size: usize) -> u64 {
+        self.base
+            .saturating_add(self.per_byte.saturating_mul(size as u64))
+    }
+}
+
+impl Default for GasCost {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+/// The gas used by the transactions of a block, against a block gas limit.
+/// Without a limit, transactions are metered but never rejected. The limit
+/// is set at genesis and read once when the frontend starts.
+#[derive(Clone, Debug, Default)]
+pub struct BlockGasMeter {
+    costs: BTreeMap<String, GasCost>,
+    limit: Option<u64>,
+    used: u64,
+}
+
+impl BlockGasMeter {
+    pub fn new(costs: BTreeMap<String, GasCost>, limit: Option<u64>) -> Self {
+        Self {
+            costs,
+            limit,
+            used: 0,
+        }
+    }
+
+    pub fn limit(&self) -> Option<u64> {
+        self.limit
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used
+    }
+
+    /// The cost of a transaction, from the cost declared by its method.
+    pub fn cost_of(&self, tx: &[u8]) -> u64 {
+        decode(tx)
+            .and_then(|message| self.costs.get(&message.method).copied())
+            .unwrap_or_default()
+            .of(tx.len())
+    }
+
+    /// Whether a transaction could fit in an empty block.
+    pub fn fits_in_block(&self, tx: &[u8]) -> bool {
+        self.limit.map_or(true, |limit| self.cost_of(tx) <= limit)
+    }
+
+    /// Start metering a new block.
+    pub fn reset(&mut self) {
+        self.used = 0;
+    }
+
+    /// Add the cost of a transaction to the block. Returns the cost, or
+    /// `None` without changing the gas used if it would go over the limit.
+    pub fn consume(&mut self, tx: &[u8]) -> Option<u64> {
+        let cost = self.cost_of(tx);
+        let used = self.used.saturating_add(cost);
+        if self.limit.map_or(false, |limit| used > limit) {
+            return None;
+        }
+        self.used = used;
+        Some(cost)
+    }
+
+    /// Keep the transactions of a proposal that fit in the block gas limit,
+    /// in order. Transactions that do not fit are skipped, so smaller ones
+    /// after them can still be included.
+    pub fn fit<T: AsRef<[u8]>>(&self, txs: Vec<T>) -> Vec<T> {
+        let Some(limit) = self.limit else {
+            return txs;
+        };
+        let mut used: u64 = 0;
+        txs.into_iter()
+            .filter(|tx| {
+                let total = used.saturating_add(self.cost_of(tx.as_ref()));
+                let fits = total <= limit;
+                if fits {
+                    used = total;
+                }
+                fits
+            })
+            .collect()
+    }
+
+    /// Whether the transactions of a proposal fit in the block gas limit.
+    pub fn fits<T: AsRef<[u8]>>(&self, txs: &[T]) -> bool {
+        self.limit.map_or(true, |limit| {
+            txs.iter()
+                .try_fold(0u64, |used, tx| {
+                    Some(used.saturating_add(self.cost_of(tx.as_ref()))).filter(|u| *u <= limit)
+                })
+                .is_some()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::{CborSerializable, CoseSign1Builder};
+    use many_protocol::RequestMessage;
+
+    fn tx(method: &str, size: usize) -> Vec<u8> {
+        let message = RequestMessage {
+            method: method.to_string(),
+            data: vec![0; size],
+            ..Default::default()
+        };
+        CoseSign1Builder::new()
+            .payload(message.to_bytes().unwrap())
+            .build()
+            .to_vec()
+            .unwrap()
+    }
+
+    fn meter(limit: Option<u64>) -> BlockGasMeter {
+        BlockGasMeter::new(
+            BTreeMap::from([("kvstore.put".to_string(), GasCost::new(10_000, 10))]),
+            limit,
+        )
+    }
+
+    #[test]
+    fn cost_of() {
+        let meter = meter(None);
+        let put = tx("kvstore.put", 100);
+        let send = tx("ledger.send", 100);
+        assert_eq!(meter.cost_of(&put), 10_000 + 10 * put.len() as u64);
+        assert_eq!(meter.cost_of(&send), 1_000 + send.len() as u64);
+        assert_eq!(meter.cost_of(&[1, 2, 3]), 1_003);
+    }
+
+    #[test]
+    fn consume() {
+        let put = tx("kvstore.put", 100);
+        let mut meter = meter(Some(meter(None).cost_of(&put) * 2));
+        assert!(meter.fits_in_block(&put));
+        assert!(meter.consume(&put).is_some());
+        assert!(meter.consume(&put).is_some());
+        assert_eq!(meter.consume(&put), None);
+        assert_eq!(meter.used(), meter.limit().unwrap());
+
+        meter.reset();
+        assert_eq!(meter.used(), 0);
+        assert!(meter.consume(&put).is_some());
+
+        let huge = tx("kvstore.put", 10_000);
+        assert!(!meter.fits_in_block(&huge));
+    }
+
+    #[test]
+    fn fit() {
+        let put = tx("kvstore.put", 100);
+        let send = tx("ledger.send", 100);
+        let unlimited = meter(None);
+        let limited = meter(Some(unlimited.cost_of(&put) + unlimited.cost_of(&send)));
+
+        let txs = vec![put.clone(), put.clone(), send.clone(), send.clone()];
+        assert!(!limited.fits(&txs));
+        let fitted = limited.fit(txs);
+        assert_eq!(fitted, vec![put, send]);
+        assert!(limited.fits(&fitted));
+
+        // Without a limit, everything fits.
+        let txs = vec![tx("kvstore.put", 10_000); 10];
+        assert!(unlimited.fits(&txs));
+        assert_eq!(unlimited.fit(txs.clone()), txs);
+    }
+}
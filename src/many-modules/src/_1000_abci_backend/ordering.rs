@@ -0,0 +1,102 @@
+use coset::{CborSerializable, CoseSign1};
+use many_protocol::RequestMessage;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+/// The order in which the transactions of a block are executed. Validators
+/// order their proposals with it and reject proposals that are not ordered,
+/// so every node executes the transactions of a block in the same order.
+///
+/// Transactions that cannot be decoded are always executed last, in the order
+/// of the proposal.
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+pub enum TxOrdering {
+    /// The order of the proposal, i.e. in which the proposer received them.
+    #[default]
+    #[n(0)]
+    Arrival,
+
+    /// By priority class of their method, lowest class first, then in the
+    /// order of the proposal. Methods without a class are executed after
+    /// the others.
+    #[n(1)]
+    Priority(#[n(0)] BTreeMap<String, u64>),
+}
+
+pub(super) fn decode(tx: &[u8]) -> Option<RequestMessage> {
+    let envelope = CoseSign1::from_slice(tx).ok()?;
+    RequestMessage::try_from(&envelope).ok()
+}
+
+impl TxOrdering {
+    /// Order the transactions of a proposal.
+    pub fn order(&self, txs: &[ByteVec]) -> Vec<ByteVec> {
+        match self {
+            TxOrdering::Arrival => txs.to_vec(),
+            TxOrdering::Priority(classes) => {
+                let mut keyed: Vec<(bool, u64, &ByteVec)> = txs
+                    .iter()
+                    .map(|tx| match decode(tx) {
+                        Some(m) => (false, *classes.get(&m.method).unwrap_or(&u64::MAX), tx),
+                        None => (true, 0, tx),
+                    })
+                    .collect();
+                // The sort is stable, so each class keeps the proposal order.
+                keyed.sort_by_key(|(undecodable, class, _)| (*undecodable, *class));
+                keyed.into_iter().map(|(_, _, tx)| tx.clone()).collect()
+            }
+        }
+    }
+
+    /// Whether the transactions of a proposal are in order.
+    pub fn is_ordered(&self, txs: &[ByteVec]) -> bool {
+        self.order(txs) == txs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::CoseSign1Builder;
+    use many_identity::testing::identity;
+
+    fn tx(from: u32, nonce: u8, method: &str) -> ByteVec {
+        let message = RequestMessage {
+            from: Some(identity(from)),
+            method: method.to_string(),
+            nonce: Some(vec![nonce]),
+            ..Default::default()
+        };
+        CoseSign1Builder::new()
+            .payload(message.to_bytes().unwrap())
+            .build()
+            .to_vec()
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn priority() {
+        let ordering = TxOrdering::Priority(BTreeMap::from([
+            ("ledger.send".to_string(), 1),
+            ("tokens.mint".to_string(), 0),
+        ]));
+        let txs = vec![
+            tx(1, 0, "kvstore.put"),
+            tx(1, 1, "ledger.send"),
+            tx(2, 0, "tokens.mint"),
+            tx(2, 1, "ledger.send"),
+        ];
+        assert_eq!(
+            ordering.order(&txs),
+            vec![
+                txs[2].clone(),
+                txs[1].clone(),
+                txs[3].clone(),
+                txs[0].clone()
+            ]
+        );
+        assert!(TxOrdering::Arrival.is_ordered(&txs));
+    }
+}
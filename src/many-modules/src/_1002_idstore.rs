@@ -5,13 +5,17 @@ use many_macros::many_module;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+mod credentials;
 pub mod errors;
 mod get;
+mod rotate;
 mod store;
 pub mod types;
 
+pub use credentials::*;
 pub use errors::*;
 pub use get::*;
+pub use rotate::*;
 pub use store::*;
 pub use types::*;
 
@@ -25,6 +29,36 @@ pub trait IdStoreModuleBackend: Send {
         args: GetFromRecallPhraseArgs,
     ) -> Result<GetReturns, ManyError>;
     fn get_from_address(&self, args: GetFromAddressArgs) -> Result<GetReturns, ManyError>;
+
+    /// Replace the key of an identity, keeping its address. The continuity
+    /// proof is signed by both the current key and the new key.
+    fn rotate_key(&mut self, args: RotateKeyArgs) -> Result<RotateKeyReturns, ManyError>;
+    fn get_key_rotation(
+        &self,
+        args: GetKeyRotationArgs,
+    ) -> Result<GetKeyRotationReturns, ManyError>;
+
+    #[many(check_webauthn, deny_anonymous)]
+    fn add_credential(
+        &mut self,
+        sender: &Address,
+        args: AddCredentialArgs,
+    ) -> Result<AddCredentialReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn revoke_credential(
+        &mut self,
+        sender: &Address,
+        args: RevokeCredentialArgs,
+    ) -> Result<RevokeCredentialReturns, ManyError>;
+    fn list_credentials(
+        &self,
+        args: ListCredentialsArgs,
+    ) -> Result<ListCredentialsReturns, ManyError>;
+    fn get_from_credential_id(
+        &self,
+        args: GetFromCredentialIdArgs,
+    ) -> Result<GetFromCredentialIdReturns, ManyError>;
 }
 
 #[cfg(test)]
@@ -148,4 +182,36 @@ mod tests {
         assert_eq!(get_returns.cred_id, ret.cred_id);
         assert_eq!(get_returns.public_key, ret.public_key);
     }
+
+    #[test]
+    fn list_credentials() {
+        let id = generate_random_ed25519_identity();
+        let data = ListCredentialsArgs(id.address());
+        let ret = ListCredentialsReturns {
+            credentials: vec![CredentialInfo {
+                cred_id: CredentialId(ByteVec::from(Vec::from([1u8; 16]))),
+                public_key: PublicKey(ByteVec::from(id.public_key().to_vec().unwrap())),
+                revoked: false,
+            }],
+        };
+        let mut mock: MockIdStoreModuleBackend = MockIdStoreModuleBackend::new();
+        mock.expect_list_credentials()
+            .with(predicate::eq(data.clone()))
+            .times(1)
+            .return_const(Ok(ret.clone()));
+
+        let module = super::IdStoreModule::new(Arc::new(Mutex::new(mock)));
+        let list_returns: ListCredentialsReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "idstore.listCredentials",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(list_returns, ret);
+    }
 }
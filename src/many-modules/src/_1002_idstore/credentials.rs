@@ -0,0 +1,71 @@
+use super::types::{CredentialId, PublicKey};
+use crate::Acknowledgment;
+use many_identity::Address;
+use minicbor::{Decode, Encode};
+
+/// Register another WebAuthn credential for the sender, e.g. a security key
+/// in addition to a phone. Any credential of an identity can sign for it.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AddCredentialArgs {
+    #[n(0)]
+    pub cred_id: CredentialId,
+
+    #[n(1)]
+    pub public_key: PublicKey,
+}
+
+pub type AddCredentialReturns = Acknowledgment;
+
+/// Revoke a credential of the sender. The last credential of an identity
+/// cannot be revoked.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RevokeCredentialArgs {
+    #[n(0)]
+    pub cred_id: CredentialId,
+}
+
+pub type RevokeCredentialReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListCredentialsArgs(#[n(0)] pub Address);
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CredentialInfo {
+    #[n(0)]
+    pub cred_id: CredentialId,
+
+    #[n(1)]
+    pub public_key: PublicKey,
+
+    #[n(2)]
+    pub revoked: bool,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListCredentialsReturns {
+    /// The credentials of the identity, starting with the one it was stored
+    /// with, if any.
+    #[n(0)]
+    pub credentials: Vec<CredentialInfo>,
+}
+
+/// Find the identity of a discoverable credential (passkey), for which
+/// authenticators only return the credential ID.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetFromCredentialIdArgs(#[n(0)] pub CredentialId);
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetFromCredentialIdReturns {
+    #[n(0)]
+    pub address: Address,
+
+    #[n(1)]
+    pub public_key: PublicKey,
+}
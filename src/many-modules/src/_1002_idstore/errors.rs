@@ -7,5 +7,8 @@ define_attribute_many_error!(
         3: pub fn invalid_address(addr) => "The identity '{addr}' is invalid.",
         4: pub fn invalid_credential_id(cred_id) => "The credential ID '{cred_id}' is invalid.",
         5: pub fn recall_phrase_generation_failed() => "The recall phrase generation failed.",
+        6: pub fn key_already_used(key) => "The key '{key}' already signs for an identity or was rotated.",
+        7: pub fn credential_not_found(cred_id) => "The identity has no credential with the ID '{cred_id}'.",
+        8: pub fn last_credential() => "The last credential of an identity cannot be revoked.",
     }
 );
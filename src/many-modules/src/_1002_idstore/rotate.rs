@@ -0,0 +1,30 @@
+use crate::Acknowledgment;
+use many_identity::rotation::{ContinuityProof, KeyRotation};
+use many_identity::Address;
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RotateKeyArgs {
+    #[n(0)]
+    pub proof: ContinuityProof,
+}
+
+pub type RotateKeyReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetKeyRotationArgs(#[n(0)] pub Address);
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetKeyRotationReturns {
+    /// The latest rotation of the identity, if its key was ever rotated.
+    #[n(0)]
+    pub rotation: Option<KeyRotation>,
+
+    /// The identity the address signs for, if it is the current key of a
+    /// rotated identity.
+    #[n(1)]
+    pub identity: Option<Address>,
+}
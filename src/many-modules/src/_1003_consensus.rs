@@ -0,0 +1,170 @@
+use many_error::{define_attribute_many_error, ManyError};
+use many_identity::Address;
+use many_macros::many_module;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+define_attribute_many_error!(
+    attribute 1003 => {
+        1: pub fn not_an_operator(address) => "Address {address} is not allowed to query consensus data.",
+    }
+);
+
+pub type NetInfoArgs = crate::EmptyArg;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PeerInfo {
+    #[n(0)]
+    pub node_id: String,
+
+    #[n(1)]
+    pub moniker: String,
+
+    #[n(2)]
+    pub remote_ip: String,
+
+    #[n(3)]
+    pub is_outbound: bool,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct NetInfoReturns {
+    #[n(0)]
+    pub listening: bool,
+
+    #[n(1)]
+    pub listeners: Vec<String>,
+
+    #[n(2)]
+    pub peers: Vec<PeerInfo>,
+}
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ValidatorsArgs {
+    /// The height to get the validator set at. Defaults to the latest block.
+    #[n(0)]
+    pub height: Option<u64>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ValidatorInfo {
+    /// The consensus address of the validator.
+    #[n(0)]
+    pub address: ByteVec,
+
+    #[n(1)]
+    pub public_key: ByteVec,
+
+    #[n(2)]
+    pub voting_power: u64,
+
+    #[n(3)]
+    pub proposer_priority: i64,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ValidatorsReturns {
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub validators: Vec<ValidatorInfo>,
+}
+
+pub type StateArgs = crate::EmptyArg;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StateReturns {
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub round: u32,
+
+    /// The step of the round, as numbered by the consensus engine.
+    #[n(2)]
+    pub step: i8,
+
+    /// The consensus address of the proposer of this round.
+    #[n(3)]
+    pub proposer: ByteVec,
+}
+
+/// Consensus data of the blockchain the server is a frontend to, for
+/// operators.
+#[many_module(name = ConsensusModule, id = 1003, namespace = consensus, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait ConsensusModuleBackend: Send {
+    #[many(deny_anonymous)]
+    fn net_info(&self, sender: &Address, args: NetInfoArgs) -> Result<NetInfoReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn validators(
+        &self,
+        sender: &Address,
+        args: ValidatorsArgs,
+    ) -> Result<ValidatorsReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn state(&self, sender: &Address, args: StateArgs) -> Result<StateReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::call_module_cbor;
+    use many_identity::testing::identity;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn validators() {
+        let args = ValidatorsArgs { height: Some(5) };
+        let mut mock = MockConsensusModuleBackend::new();
+        mock.expect_validators()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| {
+                Ok(ValidatorsReturns {
+                    height: 5,
+                    validators: vec![ValidatorInfo {
+                        address: vec![1; 20].into(),
+                        public_key: vec![2; 32].into(),
+                        voting_power: 10,
+                        proposer_priority: -3,
+                    }],
+                })
+            });
+        let module = super::ConsensusModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: ValidatorsReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "consensus.validators",
+                minicbor::to_vec(args.clone()).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.height, 5);
+        assert_eq!(result.validators[0].voting_power, 10);
+
+        // Anonymous cannot query consensus data.
+        assert!(call_module_cbor(
+            0,
+            &module,
+            "consensus.validators",
+            minicbor::to_vec(args).unwrap()
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,92 @@
+use many_error::{define_attribute_many_error, ManyError};
+use many_macros::many_module;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+define_attribute_many_error!(
+    attribute 1004 => {
+        1: pub fn height_not_committed(height) => "Block {height} is not committed yet.",
+        2: pub fn not_enough_voting_power(height) => "The commit of block {height} does not have enough voting power.",
+    }
+);
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetArgs {
+    /// The height of the block. Defaults to the latest block with a
+    /// canonical commit.
+    #[n(0)]
+    pub height: Option<u64>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct GetReturns {
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub block_hash: ByteVec,
+
+    /// 32 bytes of entropy, see the limits of the module.
+    #[n(2)]
+    pub value: ByteVec,
+
+    /// The consensus addresses of the validators whose commit signatures
+    /// the value was derived from, sorted.
+    #[n(3)]
+    pub signers: Vec<ByteVec>,
+}
+
+/// Entropy for each block of the blockchain the server is a frontend to.
+///
+/// The value of a block is the SHA-256 of its height, its hash and the
+/// signatures of the validators that committed it, sorted by validator.
+/// Every node returns the same value, and anyone with the commit of the
+/// block can verify it. It is not known before the block is committed by
+/// more than two thirds of the voting power.
+///
+/// It is not a verifiable random function, and validators can bias it: the
+/// proposer chooses the transactions and thus the block hash, the last
+/// validators to sign can see the other signatures and withhold theirs, and
+/// the next proposer chooses which signatures above the quorum are part of
+/// the commit. It should not be used where validators would gain from
+/// biasing it.
+#[many_module(name = RandomModule, id = 1004, namespace = random, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait RandomModuleBackend: Send {
+    fn get(&self, args: GetArgs) -> Result<GetReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::call_module_cbor;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn get() {
+        let args = GetArgs { height: Some(5) };
+        let returns = GetReturns {
+            height: 5,
+            block_hash: vec![1; 32].into(),
+            value: vec![2; 32].into(),
+            signers: vec![vec![3; 20].into()],
+        };
+        let mut mock = MockRandomModuleBackend::new();
+        mock.expect_get()
+            .with(eq(args.clone()))
+            .times(1)
+            .return_const(Ok(returns.clone()));
+        let module = super::RandomModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: GetReturns = minicbor::decode(
+            &call_module_cbor(0, &module, "random.get", minicbor::to_vec(args).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result, returns);
+    }
+}
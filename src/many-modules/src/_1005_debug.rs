@@ -0,0 +1,105 @@
+use many_error::ManyError;
+use many_macros::many_module;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StateHashesArgs {
+    /// Only break down the keys starting with this prefix, e.g.
+    /// `/balances/`. Defaults to the whole store.
+    #[n(0)]
+    pub prefix: Option<ByteVec>,
+}
+
+/// The digest of the keys of a subtree of the store.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct SubtreeHash {
+    #[n(0)]
+    pub hash: ByteVec,
+
+    #[n(1)]
+    pub keys: u64,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StateHashesReturns {
+    /// The height of the last committed block.
+    #[n(0)]
+    pub height: u64,
+
+    /// The app hash of the last committed block.
+    #[n(1)]
+    pub app_hash: ByteVec,
+
+    /// The subtrees under the prefix, keyed by their own prefix: the prefix
+    /// followed by the next segment of the keys, up to and including its
+    /// `/`. Keys without another `/` are their own subtree.
+    #[n(2)]
+    pub subtrees: BTreeMap<ByteVec, SubtreeHash>,
+}
+
+/// Endpoints to debug a server, only added when the server is started with
+/// them enabled.
+///
+/// The app hash is the root of a single Merk tree, whose shape depends on
+/// the order keys were written in, so it cannot be split in subsystems.
+/// Instead, the hash of a subtree is the SHA3-256 of the Merk hashes of its
+/// key/value pairs, in key order. Two servers with the same keys and values
+/// under a prefix return the same hash, whatever their history. Comparing
+/// the hashes of two validators shows which subsystem diverged, then which
+/// of its keys when called again with its prefix.
+#[many_module(name = DebugModule, id = 1005, namespace = debug, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait DebugModuleBackend: Send {
+    fn state_hashes(&self, args: StateHashesArgs) -> Result<StateHashesReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::call_module_cbor;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn state_hashes() {
+        let args = StateHashesArgs {
+            prefix: Some(b"/balances/".to_vec().into()),
+        };
+        let returns = StateHashesReturns {
+            height: 3,
+            app_hash: vec![1; 32].into(),
+            subtrees: BTreeMap::from([(
+                b"/balances/abc/".to_vec().into(),
+                SubtreeHash {
+                    hash: vec![2; 32].into(),
+                    keys: 4,
+                },
+            )]),
+        };
+        let mut mock = MockDebugModuleBackend::new();
+        mock.expect_state_hashes()
+            .with(eq(args.clone()))
+            .times(1)
+            .return_const(Ok(returns.clone()));
+        let module = super::DebugModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: StateHashesReturns = minicbor::decode(
+            &call_module_cbor(
+                0,
+                &module,
+                "debug.stateHashes",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result, returns);
+    }
+}
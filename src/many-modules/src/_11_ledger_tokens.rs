@@ -1,9 +1,11 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_error::ManyError;
 use many_identity::Address;
 use many_macros::many_module;
+use many_protocol::context::Context;
 use many_types::{cbor_type_decl, ledger, AttributeRelatedIndex, Memo};
 use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
 
 pub mod extended_info;
 
@@ -15,6 +17,7 @@ cbor_type_decl!(
         3 => maximum_supply: Option<ledger::TokenAmount>,
         4 => extended_info: Option<extended_info::TokenExtendedInfo>,
         5 => memo: Option<Memo>,
+        6 => dust: Option<DustConfig>,
     }
 
     pub struct TokenCreateReturns {
@@ -29,6 +32,7 @@ cbor_type_decl!(
     pub struct TokenInfoReturns {
         0 => info: ledger::TokenInfo,
         1 => extended_info: extended_info::TokenExtendedInfo,
+        2 => rules: Option<TokenRules>,
     }
 
     pub struct TokenUpdateArgs {
@@ -38,6 +42,7 @@ cbor_type_decl!(
         3 => decimals: Option<u64>,
         4 => owner: Option<ledger::TokenMaybeOwner>,
         5 => memo: Option<Memo>,
+        6 => dust: Option<DustConfig>,
     }
 
     pub struct TokenAddExtendedInfoArgs {
@@ -51,13 +56,113 @@ cbor_type_decl!(
         1 => extended_info: Vec<AttributeRelatedIndex>, // TODO: This thing should be of at least length 1
         2 => memo: Option<Memo>,
     }
+
+    pub struct TokenSupplyAtArgs {
+        0 => symbol: ledger::Symbol,
+        1 => height: Option<u64>,
+    }
+
+    pub struct TokenSupplyAtReturns {
+        0 => total: ledger::TokenAmount,
+        1 => circulating: ledger::TokenAmount,
+        2 => burned: ledger::TokenAmount,
+    }
 );
 
-pub type TokenUpdateReturns = EmptyReturn;
-pub type TokenAddExtendedInfoReturns = EmptyReturn;
-pub type TokenRemoveExtendedInfoReturns = EmptyReturn;
+/// Send tokens that unlock over a schedule.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TokenGrantVestingArgs {
+    #[n(0)]
+    pub symbol: ledger::Symbol,
+
+    #[n(1)]
+    pub to: Address,
+
+    #[n(2)]
+    pub amount: ledger::TokenAmount,
+
+    #[n(3)]
+    pub schedule: ledger::VestingSchedule,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+}
+
+/// Rules checked on every transfer of a token. Rules are data, set by the
+/// owner of the token.
+#[derive(Clone, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TokenRules {
+    /// If set, only these addresses can send or receive the token.
+    #[n(0)]
+    pub whitelist: Option<BTreeSet<Address>>,
+
+    /// The maximum amount of a single transfer.
+    #[n(1)]
+    pub max_per_transfer: Option<ledger::TokenAmount>,
+
+    /// Refuse all transfers of the token.
+    #[n(2)]
+    pub paused: bool,
+}
+
+impl TokenRules {
+    /// Whether no rule restricts transfers.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// What happens to a transfer that would leave the balance of the sender
+/// below the dust threshold.
+#[derive(Clone, Copy, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(index_only)]
+pub enum DustPolicy {
+    /// The transfer fails.
+    #[default]
+    #[n(0)]
+    Reject,
+
+    /// The remainder is sent along with the transfer, emptying the balance.
+    #[n(1)]
+    Sweep,
+}
 
-#[many_module(name = LedgerTokensModule, id = 11, namespace = tokens, many_modules_crate = crate)]
+/// The minimum balance of a token. Balances are either empty or at least
+/// the threshold, and empty balances are removed from the storage. A zero
+/// threshold removes the minimum.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct DustConfig {
+    #[n(0)]
+    pub threshold: ledger::TokenAmount,
+
+    #[n(1)]
+    pub policy: DustPolicy,
+}
+
+/// Replace the transfer rules of a token. Empty rules remove them.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TokenUpdateRulesArgs {
+    #[n(0)]
+    pub symbol: ledger::Symbol,
+
+    #[n(1)]
+    pub rules: TokenRules,
+
+    #[n(2)]
+    pub memo: Option<Memo>,
+}
+
+pub type TokenUpdateReturns = Acknowledgment;
+pub type TokenAddExtendedInfoReturns = Acknowledgment;
+pub type TokenRemoveExtendedInfoReturns = Acknowledgment;
+pub type TokenGrantVestingReturns = Acknowledgment;
+pub type TokenUpdateRulesReturns = Acknowledgment;
+
+#[many_module(name = LedgerTokensModule, id = 11, namespace = tokens, many_modules_crate = crate, client = true)]
 #[cfg_attr(test, mockall::automock)]
 pub trait LedgerTokensModuleBackend: Send {
     #[many(deny_anonymous)]
@@ -67,7 +172,14 @@ pub trait LedgerTokensModuleBackend: Send {
         args: TokenCreateArgs,
     ) -> Result<TokenCreateReturns, ManyError>;
 
-    fn info(&self, sender: &Address, args: TokenInfoArgs) -> Result<TokenInfoReturns, ManyError>;
+    /// The information of a token. Its information, extended information
+    /// and rules can be proven against the state hash.
+    fn info(
+        &self,
+        sender: &Address,
+        args: TokenInfoArgs,
+        context: Context,
+    ) -> Result<TokenInfoReturns, ManyError>;
 
     #[many(deny_anonymous)]
     fn update(
@@ -89,6 +201,31 @@ pub trait LedgerTokensModuleBackend: Send {
         sender: &Address,
         args: TokenRemoveExtendedInfoArgs,
     ) -> Result<TokenRemoveExtendedInfoReturns, ManyError>;
+
+    /// The supply of a token at the end of the block of a height, or at the
+    /// latest height if none. Burned tokens include those sent to the illegal
+    /// address.
+    fn supply_at(
+        &self,
+        sender: &Address,
+        args: TokenSupplyAtArgs,
+    ) -> Result<TokenSupplyAtReturns, ManyError>;
+
+    /// Send tokens from the balance of the sender, which the destination can
+    /// only spend as they unlock.
+    #[many(deny_anonymous)]
+    fn grant_vesting(
+        &mut self,
+        sender: &Address,
+        args: TokenGrantVestingArgs,
+    ) -> Result<TokenGrantVestingReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn update_rules(
+        &mut self,
+        sender: &Address,
+        args: TokenUpdateRulesArgs,
+    ) -> Result<TokenUpdateRulesReturns, ManyError>;
 }
 
 #[cfg(test)]
@@ -116,6 +253,7 @@ mod tests {
             maximum_supply: None,
             extended_info: None,
             memo: None,
+            dust: None,
         };
         let info = TokenInfo {
             symbol: Default::default(),
@@ -152,11 +290,15 @@ mod tests {
             decimals: None,
             owner: None,
             memo: None,
+            dust: Some(DustConfig {
+                threshold: 10u32.into(),
+                policy: DustPolicy::Sweep,
+            }),
         };
         mock.expect_update()
             .with(eq(identity(1)), eq(data.clone()))
             .times(1)
-            .returning(|_, _| Ok(TokenUpdateReturns {}));
+            .returning(|_, _| Ok(TokenUpdateReturns::default()));
         let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
 
         let update_returns: TokenUpdateReturns = minicbor::decode(
@@ -165,7 +307,7 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(update_returns, TokenUpdateReturns {});
+        assert_eq!(update_returns, TokenUpdateReturns::default());
     }
 
     #[test]
@@ -182,7 +324,7 @@ mod tests {
         mock.expect_add_extended_info()
             .with(eq(identity(1)), eq(data.clone()))
             .times(1)
-            .returning(|_, _| Ok(TokenAddExtendedInfoReturns {}));
+            .returning(|_, _| Ok(TokenAddExtendedInfoReturns::default()));
         let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
 
         let add_ext_info_returns: TokenAddExtendedInfoReturns = minicbor::decode(
@@ -196,7 +338,7 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(add_ext_info_returns, TokenAddExtendedInfoReturns {});
+        assert_eq!(add_ext_info_returns, TokenAddExtendedInfoReturns::default());
     }
 
     #[test]
@@ -210,7 +352,7 @@ mod tests {
         mock.expect_remove_extended_info()
             .with(eq(identity(1)), eq(data.clone()))
             .times(1)
-            .returning(|_, _| Ok(TokenRemoveExtendedInfoReturns {}));
+            .returning(|_, _| Ok(TokenRemoveExtendedInfoReturns::default()));
         let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
 
         let rm_ext_info_returns: TokenRemoveExtendedInfoReturns = minicbor::decode(
@@ -224,6 +366,107 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(rm_ext_info_returns, TokenRemoveExtendedInfoReturns {});
+        assert_eq!(
+            rm_ext_info_returns,
+            TokenRemoveExtendedInfoReturns::default()
+        );
+    }
+
+    #[test]
+    fn supply_at() {
+        let mut mock = MockLedgerTokensModuleBackend::new();
+        let data = TokenSupplyAtArgs {
+            symbol: Default::default(),
+            height: Some(10),
+        };
+        let supply = TokenSupplyAtReturns {
+            total: 1000u64.into(),
+            circulating: 900u64.into(),
+            burned: 100u64.into(),
+        };
+        mock.expect_supply_at()
+            .with(eq(identity(1)), eq(data.clone()))
+            .times(1)
+            .return_const(Ok(supply.clone()));
+        let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
+
+        let supply_returns: TokenSupplyAtReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "tokens.supplyAt",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(supply_returns, supply);
+    }
+
+    #[test]
+    fn grant_vesting() {
+        let mut mock = MockLedgerTokensModuleBackend::new();
+        let data = TokenGrantVestingArgs {
+            symbol: Default::default(),
+            to: identity(2),
+            amount: 1000u32.into(),
+            schedule: ledger::VestingSchedule {
+                start: many_types::Timestamp::new(100).unwrap(),
+                cliff: 10,
+                duration: 100,
+            },
+            memo: None,
+        };
+        mock.expect_grant_vesting()
+            .with(eq(identity(1)), eq(data.clone()))
+            .times(1)
+            .returning(|_, _| Ok(TokenGrantVestingReturns::default()));
+        let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
+
+        let returns: TokenGrantVestingReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "tokens.grantVesting",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(returns, TokenGrantVestingReturns::default());
+    }
+
+    #[test]
+    fn update_rules() {
+        let mut mock = MockLedgerTokensModuleBackend::new();
+        let data = TokenUpdateRulesArgs {
+            symbol: Default::default(),
+            rules: TokenRules {
+                whitelist: Some(BTreeSet::from([identity(1), identity(2)])),
+                max_per_transfer: Some(100u32.into()),
+                paused: false,
+            },
+            memo: None,
+        };
+        mock.expect_update_rules()
+            .with(eq(identity(1)), eq(data.clone()))
+            .times(1)
+            .returning(|_, _| Ok(TokenUpdateRulesReturns::default()));
+        let module = super::LedgerTokensModule::new(Arc::new(Mutex::new(mock)));
+
+        let returns: TokenUpdateRulesReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "tokens.updateRules",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(returns, TokenUpdateRulesReturns::default());
     }
 }
@@ -1,4 +1,4 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_error::ManyError;
 use many_identity::Address;
 use many_macros::many_module;
@@ -24,7 +24,7 @@ cbor_type_decl!(
     }
 );
 
-pub type TokenMintReturns = EmptyReturn;
+pub type TokenMintReturns = Acknowledgment;
 
 #[many_module(name = LedgerMintBurnModule, id = 12, namespace = tokens, many_modules_crate = crate)]
 #[cfg_attr(test, mockall::automock)]
@@ -60,7 +60,7 @@ mod tests {
         mock.expect_mint()
             .with(eq(identity(1)), eq(data.clone()))
             .times(1)
-            .returning(|_, _| Ok(TokenMintReturns {}));
+            .returning(|_, _| Ok(TokenMintReturns::default()));
         let module = super::LedgerMintBurnModule::new(Arc::new(Mutex::new(mock)));
 
         let update_returns: TokenMintReturns = minicbor::decode(
@@ -68,7 +68,7 @@ mod tests {
         )
         .unwrap();
 
-        assert_eq!(update_returns, TokenMintReturns {});
+        assert_eq!(update_returns, TokenMintReturns::default());
     }
 
     #[test]
@@ -18,6 +18,20 @@ pub trait KvStoreTransferModuleBackend: Send {
         sender: &Address,
         args: TransferArgs,
     ) -> Result<TransferReturn, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn offer_transfer(
+        &mut self,
+        sender: &Address,
+        args: OfferTransferArgs,
+    ) -> Result<OfferTransferReturn, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn accept_transfer(
+        &mut self,
+        sender: &Address,
+        args: AcceptTransferArgs,
+    ) -> Result<AcceptTransferReturn, ManyError>;
 }
 
 #[cfg(test)]
@@ -40,7 +54,7 @@ mod tests {
         mock.expect_transfer()
             .with(eq(identity(1)), eq(data.clone()))
             .times(1)
-            .returning(|_sender, _args| Ok(TransferReturn {}));
+            .returning(|_sender, _args| Ok(TransferReturn::default()));
         let module = super::KvStoreTransferModule::new(Arc::new(Mutex::new(mock)));
 
         let _: TransferReturn = minicbor::decode(
@@ -54,4 +68,67 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn offer_transfer() {
+        let data = OfferTransferArgs {
+            key: ByteVec::from(vec![1]),
+            alternative_owner: None,
+            new_owner: identity(2),
+            expiration: Some(many_types::Timestamp::new(1000).unwrap()),
+        };
+
+        let mut mock = MockKvStoreTransferModuleBackend::new();
+        mock.expect_offer_transfer()
+            .with(eq(identity(1)), eq(data.clone()))
+            .times(1)
+            .returning(|_sender, _args| Ok(OfferTransferReturn::default()));
+        let module = super::KvStoreTransferModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: OfferTransferReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.offerTransfer",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn accept_transfer() {
+        let data = AcceptTransferArgs {
+            key: ByteVec::from(vec![1]),
+            alternative_owner: None,
+        };
+
+        let mut mock = MockKvStoreTransferModuleBackend::new();
+        mock.expect_accept_transfer()
+            .with(eq(identity(2)), eq(data.clone()))
+            .times(1)
+            .returning(|_sender, _args| Ok(AcceptTransferReturn::default()));
+        let module = super::KvStoreTransferModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: AcceptTransferReturn = minicbor::decode(
+            &call_module_cbor(
+                2,
+                &module,
+                "kvstore.acceptTransfer",
+                minicbor::to_vec(data.clone()).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        // Anonymous cannot accept offers.
+        assert!(call_module_cbor(
+            0,
+            &module,
+            "kvstore.acceptTransfer",
+            minicbor::to_vec(data).unwrap()
+        )
+        .is_err());
+    }
 }
@@ -1,6 +1,7 @@
 use crate::events::AddressContainer;
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_identity::Address;
+use many_types::Timestamp;
 use minicbor::bytes::ByteVec;
 use minicbor::{Decode, Encode};
 use std::collections::BTreeSet;
@@ -26,4 +27,39 @@ impl AddressContainer for TransferArgs {
     }
 }
 
-pub type TransferReturn = EmptyReturn;
+pub type TransferReturn = Acknowledgment;
+
+/// Offer the ownership of a key to a new owner, who needs to accept it
+/// before it is transferred. A new offer replaces the previous one.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct OfferTransferArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+
+    #[n(2)]
+    pub new_owner: Address,
+
+    /// The offer cannot be accepted after this time. Offers without an
+    /// expiration stay valid until the key changes owner.
+    #[n(3)]
+    pub expiration: Option<Timestamp>,
+}
+
+pub type OfferTransferReturn = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AcceptTransferArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// Accept an offer made to an account the sender has a role in.
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+pub type AcceptTransferReturn = Acknowledgment;
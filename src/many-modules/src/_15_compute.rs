@@ -4,13 +4,23 @@ use many_macros::many_module;
 
 pub mod close;
 pub mod deploy;
+pub mod deploy_template;
+pub mod escrow;
+pub mod estimate;
+pub mod exec;
 pub mod info;
 pub mod list;
+pub mod logs;
 
 pub use close::*;
 pub use deploy::*;
+pub use deploy_template::*;
+pub use escrow::*;
+pub use estimate::*;
+pub use exec::*;
 pub use info::*;
 pub use list::*;
+pub use logs::*;
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
@@ -23,8 +33,33 @@ pub trait ComputeModuleBackend: Send {
     #[many(deny_anonymous)]
     fn deploy(&mut self, sender: &Address, args: DeployArgs) -> Result<DeployReturns, ManyError>;
 
+    /// Deploy from a template of the server, rendered to an SDL with the
+    /// parameters given.
+    #[many(deny_anonymous)]
+    fn deploy_template(
+        &mut self,
+        sender: &Address,
+        args: DeployTemplateArgs,
+    ) -> Result<DeployTemplateReturns, ManyError>;
+
     #[many(deny_anonymous)]
     fn close(&mut self, sender: &Address, args: CloseArgs) -> Result<CloseReturns, ManyError>;
 
     fn list(&self, sender: &Address, args: ListArgs) -> Result<ListReturns, ManyError>;
+
+    fn estimate(&self, sender: &Address, args: EstimateArgs) -> Result<EstimateReturns, ManyError>;
+
+    /// The logs of a deployment of the sender.
+    #[many(deny_anonymous)]
+    fn logs(&self, sender: &Address, args: LogsArgs) -> Result<LogsReturns, ManyError>;
+
+    /// Run a command in a deployment of the sender, through the lease shell
+    /// of its provider.
+    #[many(deny_anonymous)]
+    fn exec(&self, sender: &Address, args: ExecArgs) -> Result<ExecReturns, ManyError>;
+
+    /// The funds of an owner in the escrow of the server, when deployments
+    /// are funded from a MANY ledger. Funds are added by sending tokens to
+    /// the escrow account on the ledger.
+    fn escrow(&self, sender: &Address, args: EscrowArgs) -> Result<EscrowReturns, ManyError>;
 }
@@ -1,7 +1,7 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use minicbor::{Decode, Encode};
 
-pub type CloseReturns = EmptyReturn;
+pub type CloseReturns = Acknowledgment;
 
 #[derive(Clone, Decode, Encode)]
 #[cbor(map)]
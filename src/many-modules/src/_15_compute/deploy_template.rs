@@ -0,0 +1,25 @@
+use crate::compute::{DeployArgs, DeployReturns};
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Decode, Encode)]
+#[cbor(map)]
+pub struct DeployTemplateArgs {
+    /// The name of a template of the server.
+    #[n(0)]
+    pub template: String,
+
+    /// The image, port, resources and region of the deployment.
+    #[n(1)]
+    pub deployment: DeployArgs,
+
+    /// The environment variables of the service.
+    #[n(2)]
+    pub env: BTreeMap<String, String>,
+
+    /// Values for the other parameters of the template.
+    #[n(3)]
+    pub parameters: BTreeMap<String, String>,
+}
+
+pub type DeployTemplateReturns = DeployReturns;
@@ -0,0 +1,55 @@
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Timestamp;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Default, Decode, Encode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct EscrowArgs {
+    /// The owner of the funds. By default, the sender.
+    #[n(0)]
+    pub owner: Option<Address>,
+}
+
+/// The part of the escrow funding a deployment, deposited on Akash.
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cbor(map)]
+pub struct DeploymentEscrow {
+    /// The price per block of the lease, in `uakt`.
+    #[n(0)]
+    pub price: f64,
+
+    /// The total deposited in the Akash escrow of the deployment, in `uakt`.
+    #[n(1)]
+    pub deposited: u64,
+
+    /// The total taken from the escrow balance of the owner for the
+    /// deposits.
+    #[n(2)]
+    pub charged: TokenAmount,
+
+    /// When the first deposit was made, to estimate what the lease spent.
+    #[n(3)]
+    pub since: Timestamp,
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cbor(map)]
+pub struct EscrowReturns {
+    /// The ledger account the funds are sent to.
+    #[n(0)]
+    pub account: Address,
+
+    /// The symbol of the funds.
+    #[n(1)]
+    pub symbol: Symbol,
+
+    /// The funds of the owner not deposited in a deployment yet.
+    #[n(2)]
+    pub balance: TokenAmount,
+
+    /// The open deployments of the owner funded by the escrow, by `dseq`.
+    #[n(3)]
+    pub deployments: BTreeMap<u64, DeploymentEscrow>,
+}
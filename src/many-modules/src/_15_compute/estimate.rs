@@ -0,0 +1,32 @@
+use crate::compute::DeployArgs;
+use minicbor::{Decode, Encode};
+
+/// The deployment to estimate, as it would be given to `compute.deploy`.
+pub type EstimateArgs = DeployArgs;
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq)]
+#[cbor(map)]
+pub struct EstimateReturns {
+    /// The denomination of all prices.
+    #[n(0)]
+    pub denom: String,
+
+    /// The estimated price per block.
+    #[n(1)]
+    pub price: f64,
+
+    /// The range of prices per block of the bids the estimate is based on.
+    #[n(2)]
+    pub min_price: f64,
+
+    #[n(3)]
+    pub max_price: f64,
+
+    /// The estimated cost of running the deployment for a month.
+    #[n(4)]
+    pub monthly_cost: f64,
+
+    /// The number of bids the estimate is based on.
+    #[n(5)]
+    pub bids: u64,
+}
@@ -0,0 +1,32 @@
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct ExecArgs {
+    #[n(0)]
+    pub dseq: u64,
+
+    /// The program to run in the container of the deployment, and its
+    /// arguments.
+    #[n(1)]
+    pub command: Vec<String>,
+
+    /// The service of the deployment. By default, its only service.
+    #[n(2)]
+    pub service: Option<String>,
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct ExecReturns {
+    /// The exit code of the command, if it exited normally.
+    #[n(0)]
+    pub exit_code: Option<i32>,
+
+    #[n(1)]
+    pub stdout: ByteVec,
+
+    #[n(2)]
+    pub stderr: ByteVec,
+}
@@ -0,0 +1,33 @@
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct LogsArgs {
+    #[n(0)]
+    pub dseq: u64,
+
+    /// The service of the deployment. By default, its only service.
+    #[n(1)]
+    pub service: Option<String>,
+
+    /// The maximum number of lines to return.
+    #[n(2)]
+    pub count: Option<u64>,
+
+    /// The number of most recent lines to skip, to page back through the
+    /// logs.
+    #[n(3)]
+    pub skip: Option<u64>,
+}
+
+#[derive(Clone, Debug, Decode, Encode, PartialEq, Eq)]
+#[cbor(map)]
+pub struct LogsReturns {
+    /// The lines of the logs, oldest first.
+    #[n(0)]
+    pub lines: Vec<String>,
+
+    /// Whether there are older lines than the ones returned.
+    #[n(1)]
+    pub more: bool,
+}
@@ -83,7 +83,7 @@ mod tests {
         mock.expect_remove()
             .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
             .times(1)
-            .returning(|_sender, _args| Ok(RemoveReturns {}));
+            .returning(|_sender, _args| Ok(RemoveReturns::default()));
         let module = super::WebCommandsModule::new(Arc::new(Mutex::new(mock)));
 
         let _: RemoveReturns = minicbor::decode(
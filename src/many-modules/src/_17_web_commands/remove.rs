@@ -1,4 +1,4 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_identity::Address;
 use many_types::Memo;
 use minicbor::{Decode, Encode};
@@ -16,4 +16,4 @@ pub struct RemoveArgs {
     pub memo: Option<Memo>,
 }
 
-pub type RemoveReturns = EmptyReturn;
+pub type RemoveReturns = Acknowledgment;
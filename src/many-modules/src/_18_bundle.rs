@@ -0,0 +1,505 @@
+use crate::{ManyModule, ManyModuleInfo};
+use async_trait::async_trait;
+use coset::CoseSign1;
+use many_error::{define_attribute_many_error, ManyError};
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::attributes::Attribute;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, Mutex};
+use tokio::sync::RwLock;
+
+#[cfg(test)]
+use mockall::automock;
+
+pub const BUNDLE_MODULE_ATTRIBUTE: Attribute = Attribute::id(18);
+
+define_attribute_many_error!(
+    attribute 18 => {
+        1: pub fn empty_bundle() => "A bundle must contain at least one message.",
+        2: pub fn nested_bundle() => "Bundles cannot contain other bundles.",
+        3: pub fn message_failed(index, error)
+            => "Message {index} of the bundle failed, no message was applied: {error}",
+    }
+);
+
+/// A single message of a bundle. It is executed with the same sender,
+/// destination and timestamp as the bundle envelope.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BundleMessage {
+    #[n(0)]
+    pub method: String,
+
+    #[n(1)]
+    pub data: ByteVec,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ExecuteArgs {
+    #[n(0)]
+    pub messages: Vec<BundleMessage>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ExecuteReturns {
+    /// The CBOR encoded return value of each message, in order.
+    #[n(0)]
+    pub results: Vec<ByteVec>,
+}
+
+/// A state backend that supports reverting all changes made since the start
+/// of a bundle.
+#[cfg_attr(test, automock)]
+pub trait BundleModuleBackend: Send {
+    fn begin_bundle(&mut self) -> Result<(), ManyError>;
+    fn commit_bundle(&mut self) -> Result<(), ManyError>;
+    fn rollback_bundle(&mut self) -> Result<(), ManyError>;
+}
+
+tokio::task_local! {
+    /// Set on the task executing the messages of a bundle.
+    static IN_BUNDLE: ();
+}
+
+/// Keeps requests from interleaving with bundles. A bundle holds the gate
+/// exclusively from its start to its commit or rollback, while the modules
+/// wrapped by [`BundleGate::module`] hold it shared for each request. A
+/// request thus never sees the changes of a bundle before they are
+/// committed, and never writes between two of its messages. The messages of
+/// the bundle itself go through.
+#[derive(Clone, Debug, Default)]
+pub struct BundleGate(Arc<RwLock<()>>);
+
+impl BundleGate {
+    pub fn module<M: ManyModule>(&self, module: M) -> GatedModule<M> {
+        GatedModule {
+            gate: self.clone(),
+            module,
+        }
+    }
+}
+
+/// A module whose requests wait for the bundles executing, see [`BundleGate`].
+#[derive(Debug)]
+pub struct GatedModule<M: ManyModule> {
+    gate: BundleGate,
+    module: M,
+}
+
+#[async_trait]
+impl<M: ManyModule> ManyModule for GatedModule<M> {
+    fn info(&self) -> &ManyModuleInfo {
+        self.module.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.module.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        if IN_BUNDLE.try_with(|_| ()).is_ok() {
+            return self.module.execute(message).await;
+        }
+        let _shared = self.gate.0.read().await;
+        self.module.execute(message).await
+    }
+}
+
+/// The arguments of `base.composite`, the same as a bundle.
+pub type CompositeArgs = ExecuteArgs;
+
+/// The per-step results of `base.composite`, the same as a bundle.
+pub type CompositeReturns = ExecuteReturns;
+
+/// A meta-module that executes an ordered list of messages atomically. The
+/// messages are routed to the `modules` given at construction.
+///
+/// It serves both `bundle.execute` and `base.composite`, which behave the
+/// same. Neither can be nested in the other. No other request executes while
+/// a bundle does, as long as the modules share its [`BundleGate`].
+pub struct BundleModule<T: BundleModuleBackend> {
+    backend: Arc<Mutex<T>>,
+    modules: Vec<Arc<dyn ManyModule + Send>>,
+    gate: BundleGate,
+    info: ManyModuleInfo,
+}
+
+impl<T: BundleModuleBackend> Debug for BundleModule<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("BundleModule")
+    }
+}
+
+impl<T: BundleModuleBackend> BundleModule<T> {
+    pub fn new(backend: Arc<Mutex<T>>, modules: Vec<Arc<dyn ManyModule + Send>>) -> Self {
+        Self {
+            backend,
+            modules,
+            gate: BundleGate::default(),
+            info: ManyModuleInfo {
+                name: "BundleModule".to_string(),
+                attribute: Some(BUNDLE_MODULE_ATTRIBUTE),
+                endpoints: vec!["bundle.execute".to_string(), "base.composite".to_string()],
+                version: Default::default(),
+            },
+        }
+    }
+
+    /// Share the gate of the modules requests are executed by.
+    pub fn with_gate(mut self, gate: BundleGate) -> Self {
+        self.gate = gate;
+        self
+    }
+
+    fn decode_args(message: &RequestMessage) -> Result<ExecuteArgs, ManyError> {
+        let args: ExecuteArgs = minicbor::decode(&message.data)
+            .map_err(|e| ManyError::deserialization_error(e.to_string()))?;
+        if args.messages.is_empty() {
+            return Err(empty_bundle());
+        }
+        Ok(args)
+    }
+
+    /// Build the request of a bundled message and find the module it's routed to.
+    fn route(
+        &self,
+        message: &RequestMessage,
+        inner: &BundleMessage,
+    ) -> Result<(RequestMessage, Arc<dyn ManyModule + Send>), ManyError> {
+        if self.info.endpoints.contains(&inner.method) {
+            return Err(nested_bundle());
+        }
+
+        let module = self
+            .modules
+            .iter()
+            .find(|m| m.info().endpoints.contains(&inner.method))
+            .cloned()
+            .ok_or_else(|| ManyError::invalid_method_name(inner.method.clone()))?;
+
+        let request = RequestMessage {
+            method: inner.method.clone(),
+            data: inner.data.to_vec(),
+            attributes: Default::default(),
+            ..message.clone()
+        };
+        Ok((request, module))
+    }
+
+    async fn execute_messages(
+        &self,
+        message: &RequestMessage,
+        args: ExecuteArgs,
+    ) -> Result<ExecuteReturns, ManyError> {
+        let mut results = Vec::with_capacity(args.messages.len());
+        for (index, inner) in args.messages.iter().enumerate() {
+            let data = match self.route(message, inner) {
+                Ok((request, module)) => module.execute(request).await.and_then(|r| r.data),
+                Err(e) => Err(e),
+            };
+            results.push(data.map_err(|e| message_failed(index, e))?.into());
+        }
+        Ok(ExecuteReturns { results })
+    }
+}
+
+#[async_trait]
+impl<T: BundleModuleBackend> ManyModule for BundleModule<T> {
+    fn info(&self) -> &ManyModuleInfo {
+        &self.info
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        let args = Self::decode_args(message)?;
+        for inner in &args.messages {
+            let (request, module) = self.route(message, inner)?;
+            module.validate(&request, envelope)?;
+        }
+        Ok(())
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let args = Self::decode_args(&message)?;
+
+        let _exclusive = self.gate.0.write().await;
+        self.backend.lock().unwrap().begin_bundle()?;
+        let result = IN_BUNDLE
+            .scope((), self.execute_messages(&message, args))
+            .await;
+        {
+            let mut backend = self.backend.lock().unwrap();
+            if result.is_ok() {
+                backend.commit_bundle()?;
+            } else {
+                backend.rollback_bundle()?;
+            }
+        }
+
+        let data =
+            minicbor::to_vec(result?).map_err(|e| ManyError::serialization_error(e.to_string()))?;
+        Ok(ResponseMessage::from_request(
+            &message,
+            &message.to,
+            Ok(data),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ledger::{LedgerCommandsModule, MockLedgerCommandsModuleBackend, SendArgs};
+    use crate::testutils::call_module_cbor;
+    use crate::Acknowledgment;
+    use many_identity::testing::identity;
+    use many_types::ledger::TokenAmount;
+
+    fn send_message(amount: u32) -> BundleMessage {
+        BundleMessage {
+            method: "ledger.send".to_string(),
+            data: minicbor::to_vec(SendArgs {
+                from: None,
+                to: identity(2),
+                amount: TokenAmount::from(amount),
+                symbol: identity(3),
+                memo: None,
+                decimal_amount: None,
+            })
+            .unwrap()
+            .into(),
+        }
+    }
+
+    fn setup(mut backend: MockBundleModuleBackend) -> BundleModule<MockBundleModuleBackend> {
+        let mut ledger = MockLedgerCommandsModuleBackend::new();
+        ledger.expect_send().returning(|_, args| {
+            if args.amount.is_zero() {
+                Err(ManyError::unknown("zero"))
+            } else {
+                Ok(Acknowledgment::default())
+            }
+        });
+        backend.expect_begin_bundle().times(1).returning(|| Ok(()));
+
+        BundleModule::new(
+            Arc::new(Mutex::new(backend)),
+            vec![Arc::new(LedgerCommandsModule::new(Arc::new(Mutex::new(
+                ledger,
+            ))))],
+        )
+    }
+
+    #[test]
+    fn execute() {
+        let mut backend = MockBundleModuleBackend::new();
+        backend.expect_commit_bundle().times(1).returning(|| Ok(()));
+        let module = setup(backend);
+
+        let args = ExecuteArgs {
+            messages: vec![send_message(1), send_message(2)],
+        };
+        let returns: ExecuteReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "bundle.execute",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(returns.results.len(), 2);
+    }
+
+    #[test]
+    fn composite() {
+        let mut backend = MockBundleModuleBackend::new();
+        backend.expect_commit_bundle().times(1).returning(|| Ok(()));
+        let module = setup(backend);
+
+        let args = CompositeArgs {
+            messages: vec![send_message(1), send_message(2), send_message(3)],
+        };
+        let returns: CompositeReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "base.composite",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(returns.results.len(), 3);
+    }
+
+    #[test]
+    fn execute_rollback() {
+        let mut backend = MockBundleModuleBackend::new();
+        backend
+            .expect_rollback_bundle()
+            .times(1)
+            .returning(|| Ok(()));
+        let module = setup(backend);
+
+        let args = ExecuteArgs {
+            messages: vec![send_message(1), send_message(0), send_message(2)],
+        };
+        let result = call_module_cbor(
+            1,
+            &module,
+            "bundle.execute",
+            minicbor::to_vec(args).unwrap(),
+        );
+        assert_eq!(result.unwrap_err().code(), message_failed("", "").code());
+    }
+
+    /// A module whose messages wait for a signal before answering.
+    #[derive(Debug)]
+    struct PauseModule {
+        signal: async_channel::Receiver<()>,
+        info: ManyModuleInfo,
+    }
+
+    #[async_trait]
+    impl ManyModule for PauseModule {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.info
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            self.signal.recv().await.unwrap();
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(minicbor::to_vec(()).unwrap()),
+            ))
+        }
+    }
+
+    #[test]
+    fn requests_wait_for_bundles() {
+        let mut backend = MockBundleModuleBackend::new();
+        backend.expect_commit_bundle().times(1).returning(|| Ok(()));
+        let mut ledger = MockLedgerCommandsModuleBackend::new();
+        ledger
+            .expect_send()
+            .returning(|_, _| Ok(Acknowledgment::default()));
+        backend.expect_begin_bundle().times(1).returning(|| Ok(()));
+
+        let (signal, receiver) = async_channel::bounded(1);
+        let gate = BundleGate::default();
+        let pause = gate.module(PauseModule {
+            signal: receiver,
+            info: ManyModuleInfo {
+                name: "PauseModule".to_string(),
+                attribute: None,
+                endpoints: vec!["test.pause".to_string()],
+                version: Default::default(),
+            },
+        });
+        let ledger = gate.module(LedgerCommandsModule::new(Arc::new(Mutex::new(ledger))));
+        let module =
+            BundleModule::new(Arc::new(Mutex::new(backend)), vec![Arc::new(pause)]).with_gate(gate);
+
+        let args = ExecuteArgs {
+            messages: vec![BundleMessage {
+                method: "test.pause".to_string(),
+                data: minicbor::to_vec(()).unwrap().into(),
+            }],
+        };
+        let request = |method: &str, data: Vec<u8>| {
+            RequestMessage::default()
+                .with_method(method.to_string())
+                .with_data(data)
+                .with_from(identity(1))
+        };
+        let bundle = module.execute(request("bundle.execute", minicbor::to_vec(args).unwrap()));
+        let send = ledger.execute(request("ledger.send", send_message(1).data.to_vec()));
+
+        smol::block_on(async {
+            smol::pin!(bundle);
+            smol::pin!(send);
+
+            // The bundle holds the gate while its message waits, so the
+            // request to the other module waits too.
+            assert!(smol::future::poll_once(&mut bundle).await.is_none());
+            assert!(smol::future::poll_once(&mut send).await.is_none());
+
+            signal.send(()).await.unwrap();
+            assert!(smol::future::poll_once(&mut send).await.is_none());
+            assert!(bundle.await.unwrap().data.is_ok());
+            assert!(send.await.unwrap().data.is_ok());
+        });
+    }
+
+    #[test]
+    fn invalid_bundles() {
+        let module =
+            BundleModule::new(Arc::new(Mutex::new(MockBundleModuleBackend::new())), vec![]);
+
+        let empty = ExecuteArgs { messages: vec![] };
+        assert_eq!(
+            call_module_cbor(
+                1,
+                &module,
+                "bundle.execute",
+                minicbor::to_vec(empty).unwrap()
+            )
+            .unwrap_err()
+            .code(),
+            empty_bundle().code()
+        );
+
+        let nested = ExecuteArgs {
+            messages: vec![BundleMessage {
+                method: "bundle.execute".to_string(),
+                data: ByteVec::from(vec![]),
+            }],
+        };
+        assert_eq!(
+            call_module_cbor(
+                1,
+                &module,
+                "bundle.execute",
+                minicbor::to_vec(nested).unwrap()
+            )
+            .unwrap_err()
+            .code(),
+            nested_bundle().code()
+        );
+
+        let nested_composite = ExecuteArgs {
+            messages: vec![BundleMessage {
+                method: "base.composite".to_string(),
+                data: ByteVec::from(vec![]),
+            }],
+        };
+        assert_eq!(
+            call_module_cbor(
+                1,
+                &module,
+                "bundle.execute",
+                minicbor::to_vec(nested_composite).unwrap()
+            )
+            .unwrap_err()
+            .code(),
+            nested_bundle().code()
+        );
+
+        let unknown = ExecuteArgs {
+            messages: vec![send_message(1)],
+        };
+        assert!(call_module_cbor(
+            1,
+            &module,
+            "bundle.execute",
+            minicbor::to_vec(unknown).unwrap()
+        )
+        .is_err());
+    }
+}
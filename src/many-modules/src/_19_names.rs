@@ -0,0 +1,211 @@
+use crate::Acknowledgment;
+use many_error::{define_attribute_many_error, ManyError};
+use many_identity::Address;
+use many_macros::many_module;
+use many_types::attributes::Attribute;
+use many_types::Memo;
+use minicbor::{Decode, Encode};
+use std::str::FromStr;
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+pub const NAMES_MODULE_ATTRIBUTE: Attribute = Attribute::id(19);
+
+/// The maximum length of a name, in bytes.
+pub const MAXIMUM_NAME_LENGTH: usize = 64;
+
+define_attribute_many_error!(
+    attribute 19 => {
+        1: pub fn invalid_name(name) => "Invalid name '{name}'.",
+        2: pub fn name_already_registered(name) => "The name '{name}' is already registered.",
+        3: pub fn name_not_found(name) => "The name '{name}' is not registered.",
+        4: pub fn not_name_owner(name) => "Only the owner of the name '{name}' can do this.",
+    }
+);
+
+/// Check that a name can be registered. Names are 1 to 64 characters of
+/// lowercase ASCII letters, digits, `-` and `.`, must start with a letter or
+/// a digit, and cannot be a textual address.
+pub fn validate_name(name: &str) -> Result<(), ManyError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAXIMUM_NAME_LENGTH
+        && name.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '.')
+        && Address::from_str(name).is_err();
+
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid_name(name))
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RegisterArgs {
+    #[n(0)]
+    pub name: String,
+
+    /// The address the name resolves to. Defaults to the sender.
+    #[n(1)]
+    pub address: Option<Address>,
+
+    #[n(2)]
+    pub memo: Option<Memo>,
+}
+
+pub type RegisterReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ResolveArgs {
+    #[n(0)]
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ResolveReturns {
+    #[n(0)]
+    pub address: Address,
+
+    #[n(1)]
+    pub owner: Address,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TransferArgs {
+    #[n(0)]
+    pub name: String,
+
+    /// The new owner of the name. The name will resolve to this address.
+    #[n(1)]
+    pub new_owner: Address,
+
+    #[n(2)]
+    pub memo: Option<Memo>,
+}
+
+pub type TransferReturns = Acknowledgment;
+
+#[many_module(name = NamesModule, id = 19, namespace = names, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait NamesModuleBackend: Send {
+    #[many(deny_anonymous)]
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: RegisterArgs,
+    ) -> Result<RegisterReturns, ManyError>;
+
+    fn resolve(&self, args: ResolveArgs) -> Result<ResolveReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn transfer(
+        &mut self,
+        sender: &Address,
+        args: TransferArgs,
+    ) -> Result<TransferReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{call_module, call_module_cbor};
+    use many_identity::testing::identity;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn names() {
+        assert!(validate_name("alice").is_ok());
+        assert!(validate_name("our-team.2").is_ok());
+
+        assert!(validate_name("").is_err());
+        assert!(validate_name("Alice").is_err());
+        assert!(validate_name("-alice").is_err());
+        assert!(validate_name("alice bob").is_err());
+        assert!(validate_name(&"a".repeat(MAXIMUM_NAME_LENGTH + 1)).is_err());
+        assert!(validate_name(&identity(1).to_string()).is_err());
+    }
+
+    #[test]
+    fn register() {
+        let mut mock = MockNamesModuleBackend::new();
+        let args = RegisterArgs {
+            name: "alice".to_string(),
+            address: None,
+            memo: None,
+        };
+        mock.expect_register()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| Ok(RegisterReturns::default()));
+        let module = super::NamesModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: RegisterReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "names.register",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result, RegisterReturns::default());
+
+        // Anonymous cannot register names.
+        assert!(call_module(0, &module, "names.register", r#"{ 0: "bob" }"#).is_err());
+    }
+
+    #[test]
+    fn resolve() {
+        let mut mock = MockNamesModuleBackend::new();
+        mock.expect_resolve()
+            .with(eq(ResolveArgs {
+                name: "alice".to_string(),
+            }))
+            .times(1)
+            .returning(|_| {
+                Ok(ResolveReturns {
+                    address: identity(2),
+                    owner: identity(1),
+                })
+            });
+        let module = super::NamesModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: ResolveReturns = minicbor::decode(
+            &call_module(0, &module, "names.resolve", r#"{ 0: "alice" }"#).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.address, identity(2));
+        assert_eq!(result.owner, identity(1));
+    }
+
+    #[test]
+    fn transfer() {
+        let mut mock = MockNamesModuleBackend::new();
+        let args = TransferArgs {
+            name: "alice".to_string(),
+            new_owner: identity(2),
+            memo: None,
+        };
+        mock.expect_transfer()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| Ok(TransferReturns::default()));
+        let module = super::NamesModule::new(Arc::new(Mutex::new(mock)));
+
+        assert!(call_module_cbor(
+            1,
+            &module,
+            "names.transfer",
+            minicbor::to_vec(args).unwrap()
+        )
+        .is_ok());
+    }
+}
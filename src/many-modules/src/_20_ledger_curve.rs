@@ -0,0 +1,350 @@
+use crate::ledger::extended_info::TokenExtendedInfo;
+use crate::ledger::TokenCreateReturns;
+use many_error::{define_attribute_many_error, ManyError};
+use many_identity::Address;
+use many_macros::many_module;
+use many_types::attributes::Attribute;
+use many_types::ledger::{Symbol, TokenAmount, TokenInfoSummary, TokenMaybeOwner};
+use many_types::Memo;
+use minicbor::{Decode, Encode};
+use num_bigint::BigUint;
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+pub const LEDGER_CURVE_MODULE_ATTRIBUTE: Attribute = Attribute::id(20);
+
+define_attribute_many_error!(
+    attribute 20 => {
+        1: pub fn invalid_curve(reason) => "Invalid bonding curve: {reason}.",
+        2: pub fn not_a_curve_token(symbol) => "The token {symbol} is not issued on a bonding curve.",
+        3: pub fn invalid_reserve(symbol) => "The token {symbol} cannot be used as a reserve.",
+        4: pub fn cost_over_maximum(cost, maximum) => "Buying would cost {cost}, which is over the maximum of {maximum}.",
+        5: pub fn proceeds_under_minimum(proceeds, minimum) => "Selling would return {proceeds}, which is under the minimum of {minimum}.",
+        6: pub fn supply_follows_curve(symbol) => "The supply of {symbol} can only change by buying or selling on its curve.",
+    }
+);
+
+/// A linear price curve: `price(supply) = base_price + slope * supply / scale`.
+/// All values are in the smallest unit of the tokens.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct LinearCurve {
+    #[n(0)]
+    pub base_price: TokenAmount,
+
+    #[n(1)]
+    pub slope: TokenAmount,
+
+    /// Divides the slope, to allow for fractional slopes. Cannot be zero.
+    #[n(2)]
+    pub scale: TokenAmount,
+}
+
+/// A bonding curve, giving the price, in reserve tokens, of a unit of a token
+/// depending on its circulating supply.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+pub enum BondingCurve {
+    #[n(0)]
+    Linear(#[n(0)] LinearCurve),
+}
+
+impl BondingCurve {
+    pub fn validate(&self) -> Result<(), ManyError> {
+        match self {
+            BondingCurve::Linear(curve) => {
+                if curve.scale.is_zero() {
+                    return Err(invalid_curve("the scale cannot be zero"));
+                }
+                if curve.base_price.is_zero() && curve.slope.is_zero() {
+                    return Err(invalid_curve("the price cannot always be zero"));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The price of the next token unit at the given supply (rounded down).
+    pub fn spot_price(&self, supply: &TokenAmount) -> TokenAmount {
+        match self {
+            BondingCurve::Linear(LinearCurve {
+                base_price,
+                slope,
+                scale,
+            }) => {
+                let slope: &BigUint = slope.as_ref();
+                let scale: &BigUint = scale.as_ref();
+                (base_price.as_ref() + slope * supply.as_ref() / scale).into()
+            }
+        }
+    }
+
+    /// The reserve amount needed to buy `amount` tokens when `supply` tokens
+    /// are circulating. Rounded up, so the reserve always covers sales.
+    pub fn buy_cost(&self, supply: &TokenAmount, amount: &TokenAmount) -> TokenAmount {
+        let (numerator, denominator) = self.area(supply, amount);
+        ((numerator + &denominator - 1u8) / denominator).into()
+    }
+
+    /// The reserve amount returned when selling `amount` tokens out of a
+    /// circulating `supply`. Rounded down. The amount must not be over the
+    /// supply.
+    pub fn sell_proceeds(&self, supply: &TokenAmount, amount: &TokenAmount) -> TokenAmount {
+        let (numerator, denominator) = self.area(&(supply - amount), amount);
+        (numerator / denominator).into()
+    }
+
+    /// The area under the curve between `start` and `start + amount`, as a
+    /// fraction.
+    fn area(&self, start: &TokenAmount, amount: &TokenAmount) -> (BigUint, BigUint) {
+        match self {
+            BondingCurve::Linear(LinearCurve {
+                base_price,
+                slope,
+                scale,
+            }) => {
+                let (start, amount): (&BigUint, &BigUint) = (start.as_ref(), amount.as_ref());
+                let denominator: BigUint = scale.as_ref() * 2u8;
+
+                // base * a + slope * ((s + a)^2 - s^2) / (2 * scale)
+                let numerator = base_price.as_ref() * amount * &denominator
+                    + slope.as_ref() * amount * (start * 2u8 + amount);
+                (numerator, denominator)
+            }
+        }
+    }
+}
+
+/// Create a token whose supply can only be changed by buying from or selling
+/// to its bonding curve. The initial supply is zero.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TokenCreateWithCurveArgs {
+    #[n(0)]
+    pub summary: TokenInfoSummary,
+
+    #[n(1)]
+    pub owner: Option<TokenMaybeOwner>,
+
+    #[n(2)]
+    pub maximum_supply: Option<TokenAmount>,
+
+    #[n(3)]
+    pub extended_info: Option<TokenExtendedInfo>,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+
+    /// The token used to buy and sell the new token.
+    #[n(5)]
+    pub reserve: Symbol,
+
+    #[n(6)]
+    pub curve: BondingCurve,
+}
+
+pub type TokenCreateWithCurveReturns = TokenCreateReturns;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CurveInfoArgs {
+    #[n(0)]
+    pub symbol: Symbol,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CurveInfoReturns {
+    #[n(0)]
+    pub reserve: Symbol,
+
+    #[n(1)]
+    pub curve: BondingCurve,
+
+    /// The amount of reserve tokens held by the curve.
+    #[n(2)]
+    pub pool: TokenAmount,
+
+    #[n(3)]
+    pub supply: TokenAmount,
+
+    /// The price of the next unit of the token.
+    #[n(4)]
+    pub price: TokenAmount,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BuyArgs {
+    #[n(0)]
+    pub symbol: Symbol,
+
+    #[n(1)]
+    pub amount: TokenAmount,
+
+    /// Fail if buying would cost more than this amount of reserve tokens.
+    #[n(2)]
+    pub maximum_cost: Option<TokenAmount>,
+
+    #[n(3)]
+    pub memo: Option<Memo>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BuyReturns {
+    #[n(0)]
+    pub cost: TokenAmount,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct SellArgs {
+    #[n(0)]
+    pub symbol: Symbol,
+
+    #[n(1)]
+    pub amount: TokenAmount,
+
+    /// Fail if selling would return less than this amount of reserve tokens.
+    #[n(2)]
+    pub minimum_proceeds: Option<TokenAmount>,
+
+    #[n(3)]
+    pub memo: Option<Memo>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct SellReturns {
+    #[n(0)]
+    pub proceeds: TokenAmount,
+}
+
+#[many_module(name = LedgerCurveModule, id = 20, namespace = tokens, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait LedgerCurveModuleBackend: Send {
+    #[many(deny_anonymous)]
+    fn create_with_curve(
+        &mut self,
+        sender: &Address,
+        args: TokenCreateWithCurveArgs,
+    ) -> Result<TokenCreateWithCurveReturns, ManyError>;
+
+    fn curve_info(&self, args: CurveInfoArgs) -> Result<CurveInfoReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn buy(&mut self, sender: &Address, args: BuyArgs) -> Result<BuyReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn sell(&mut self, sender: &Address, args: SellArgs) -> Result<SellReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{call_module, call_module_cbor};
+    use many_identity::testing::identity;
+    use std::sync::{Arc, Mutex};
+
+    fn linear(base_price: u64, slope: u64, scale: u64) -> BondingCurve {
+        BondingCurve::Linear(LinearCurve {
+            base_price: base_price.into(),
+            slope: slope.into(),
+            scale: scale.into(),
+        })
+    }
+
+    #[test]
+    fn validate() {
+        assert!(linear(1, 0, 1).validate().is_ok());
+        assert!(linear(0, 1, 1).validate().is_ok());
+        assert!(linear(1, 1, 0).validate().is_err());
+        assert!(linear(0, 0, 1).validate().is_err());
+    }
+
+    #[test]
+    fn pricing() {
+        // price(s) = 10 + s / 2
+        let curve = linear(10, 1, 2);
+        assert_eq!(curve.spot_price(&0u64.into()), 10u64);
+        assert_eq!(curve.spot_price(&100u64.into()), 60u64);
+
+        // 10 * 100 + (100^2 - 0) / 4
+        assert_eq!(curve.buy_cost(&0u64.into(), &100u64.into()), 3500u64);
+        assert_eq!(curve.sell_proceeds(&100u64.into(), &100u64.into()), 3500u64);
+
+        // Buying then selling in several steps never returns more than paid.
+        let cost =
+            curve.buy_cost(&0u64.into(), &3u64.into()) + curve.buy_cost(&3u64.into(), &4u64.into());
+        let proceeds = curve.sell_proceeds(&7u64.into(), &5u64.into())
+            + curve.sell_proceeds(&2u64.into(), &2u64.into());
+        assert!(proceeds <= cost);
+    }
+
+    #[test]
+    fn buy() {
+        let mut mock = MockLedgerCurveModuleBackend::new();
+        let args = BuyArgs {
+            symbol: identity(100),
+            amount: 10u64.into(),
+            maximum_cost: None,
+            memo: None,
+        };
+        mock.expect_buy()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| {
+                Ok(BuyReturns {
+                    cost: 100u64.into(),
+                })
+            });
+        let module = super::LedgerCurveModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: BuyReturns = minicbor::decode(
+            &call_module_cbor(1, &module, "tokens.buy", minicbor::to_vec(args).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.cost, 100u64);
+
+        // Anonymous cannot buy tokens.
+        assert!(call_module(0, &module, "tokens.buy", "{}").is_err());
+    }
+
+    #[test]
+    fn curve_info() {
+        let mut mock = MockLedgerCurveModuleBackend::new();
+        mock.expect_curve_info()
+            .with(eq(CurveInfoArgs {
+                symbol: identity(100),
+            }))
+            .times(1)
+            .returning(|_| {
+                Ok(CurveInfoReturns {
+                    reserve: identity(101),
+                    curve: linear(10, 1, 2),
+                    pool: 3500u64.into(),
+                    supply: 100u64.into(),
+                    price: 60u64.into(),
+                })
+            });
+        let module = super::LedgerCurveModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: CurveInfoReturns = minicbor::decode(
+            &call_module_cbor(
+                0,
+                &module,
+                "tokens.curveInfo",
+                minicbor::to_vec(CurveInfoArgs {
+                    symbol: identity(100),
+                })
+                .unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.reserve, identity(101));
+        assert_eq!(result.curve, linear(10, 1, 2));
+        assert_eq!(result.price, 60u64);
+    }
+}
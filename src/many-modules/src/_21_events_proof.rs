@@ -0,0 +1,75 @@
+use crate::events::EventLog;
+use many_error::ManyError;
+use many_identity::Address;
+use many_macros::many_module;
+use many_protocol::context::Context;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ProveArgs {
+    /// The first height of the segment.
+    #[n(0)]
+    pub start: u64,
+
+    /// The last height of the segment, inclusive.
+    #[n(1)]
+    pub end: u64,
+}
+
+#[derive(Debug, Encode, Decode)]
+#[cbor(map)]
+pub struct ProveReturns {
+    /// The running hash before the first event of the segment, if known.
+    #[n(0)]
+    pub previous: Option<ByteVec>,
+
+    /// The events logged between the two heights, in order.
+    #[n(1)]
+    pub events: Vec<EventLog>,
+}
+
+impl ProveReturns {
+    /// Verify that the events form an unbroken chain, starting from the
+    /// `previous` hash if there is one. Returns the running hash after the
+    /// last event, to be compared with the one anchored in the state.
+    pub fn verify(&self) -> Result<Option<ByteVec>, ManyError> {
+        let mut previous = self.previous.clone();
+        for event in &self.events {
+            let chain_hash = event
+                .chain_hash
+                .as_ref()
+                .ok_or_else(|| ManyError::unknown("Event is not chained."))?;
+            if let Some(p) = &previous {
+                if &event.chain(p)? != chain_hash {
+                    return Err(ManyError::unknown(format!(
+                        "Event {} does not follow the previous event.",
+                        hex::encode(&event.id)
+                    )));
+                }
+            }
+            previous = Some(chain_hash.clone());
+        }
+        Ok(previous)
+    }
+}
+
+/// Proofs of the integrity of the event log. Servers that implement it chain
+/// every event to the previous one with a running hash, so removing or
+/// reordering events breaks the chain.
+#[many_module(name = EventsProofModule, id = 21, namespace = events, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait EventsProofModuleBackend: Send {
+    /// The events logged between two heights. If a proof is requested, it
+    /// covers these events, the event before them and the head of the chain.
+    fn prove(
+        &self,
+        sender: &Address,
+        args: ProveArgs,
+        context: Context,
+    ) -> Result<ProveReturns, ManyError>;
+}
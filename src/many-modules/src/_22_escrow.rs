@@ -0,0 +1,388 @@
+use crate::events::AddressContainer;
+use crate::Acknowledgment;
+use many_error::{define_attribute_many_error, ManyError};
+use many_identity::Address;
+use many_macros::many_module;
+use many_types::attributes::Attribute;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{Memo, Timestamp};
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeSet;
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+pub const ESCROW_MODULE_ATTRIBUTE: Attribute = Attribute::id(22);
+
+define_attribute_many_error!(
+    attribute 22 => {
+        1: pub fn escrow_not_found(id) => "Escrow {id} not found.",
+        2: pub fn invalid_escrow_condition(reason) => "Invalid escrow condition: {reason}.",
+        3: pub fn cannot_release(reason) => "The escrow cannot be released: {reason}.",
+        4: pub fn cannot_refund(reason) => "The escrow cannot be refunded: {reason}.",
+    }
+);
+
+/// The hash of a preimage, for [`EscrowCondition::HashPreimage`].
+pub fn hash_preimage(preimage: &[u8]) -> ByteVec {
+    Sha3_256::digest(preimage).to_vec().into()
+}
+
+/// What releases the funds of an escrow to its recipient, aside from the
+/// depositor releasing them.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+pub enum EscrowCondition {
+    /// Anyone can release the funds once this time is reached.
+    #[n(0)]
+    Time(#[n(0)] Timestamp),
+
+    /// The arbiter can release the funds, or refund them to the depositor.
+    #[n(1)]
+    Arbiter(#[n(0)] Address),
+
+    /// Anyone revealing a value whose SHA3-256 hash is this can release the
+    /// funds.
+    #[n(2)]
+    HashPreimage(#[n(0)] ByteVec),
+}
+
+impl EscrowCondition {
+    pub fn validate(&self, depositor: &Address, recipient: &Address) -> Result<(), ManyError> {
+        match self {
+            EscrowCondition::Time(_) => {}
+            EscrowCondition::Arbiter(arbiter) => {
+                if arbiter.is_anonymous() || arbiter.is_illegal() {
+                    return Err(invalid_escrow_condition(format!(
+                        "{arbiter} cannot be an arbiter"
+                    )));
+                }
+                if arbiter == depositor || arbiter == recipient {
+                    return Err(invalid_escrow_condition(
+                        "the arbiter cannot be a party of the escrow",
+                    ));
+                }
+            }
+            EscrowCondition::HashPreimage(hash) => {
+                if hash.len() != Sha3_256::output_size() {
+                    return Err(invalid_escrow_condition(format!(
+                        "the hash must be {} bytes",
+                        Sha3_256::output_size()
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl AddressContainer for EscrowCondition {
+    fn addresses(&self) -> BTreeSet<Address> {
+        match self {
+            EscrowCondition::Arbiter(arbiter) => BTreeSet::from([*arbiter]),
+            _ => BTreeSet::new(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct EscrowInfo {
+    #[n(0)]
+    pub depositor: Address,
+
+    #[n(1)]
+    pub recipient: Address,
+
+    #[n(2)]
+    pub symbol: Symbol,
+
+    #[n(3)]
+    pub amount: TokenAmount,
+
+    #[n(4)]
+    pub condition: EscrowCondition,
+
+    /// When the depositor can take the funds back, if ever.
+    #[n(5)]
+    pub refund_after: Option<Timestamp>,
+
+    #[n(6)]
+    pub created: Timestamp,
+}
+
+impl EscrowInfo {
+    /// Check that `sender` can release the funds to the recipient at `now`.
+    pub fn can_release(
+        &self,
+        sender: &Address,
+        now: Timestamp,
+        preimage: Option<&[u8]>,
+    ) -> Result<(), ManyError> {
+        if sender == &self.depositor {
+            return Ok(());
+        }
+        match &self.condition {
+            EscrowCondition::Time(time) if now >= *time => Ok(()),
+            EscrowCondition::Time(time) => Err(cannot_release(format!(
+                "the funds are locked until {}",
+                time.secs()
+            ))),
+            EscrowCondition::Arbiter(arbiter) if sender == arbiter => Ok(()),
+            EscrowCondition::Arbiter(_) => {
+                Err(cannot_release("only the depositor or the arbiter can"))
+            }
+            EscrowCondition::HashPreimage(hash) => match preimage {
+                Some(preimage) if &hash_preimage(preimage) == hash => Ok(()),
+                Some(_) => Err(cannot_release("the preimage does not match the hash")),
+                None => Err(cannot_release("a preimage is needed")),
+            },
+        }
+    }
+
+    /// Check that `sender` can return the funds to the depositor at `now`.
+    /// The recipient can always refuse the funds.
+    pub fn can_refund(&self, sender: &Address, now: Timestamp) -> Result<(), ManyError> {
+        if sender == &self.recipient {
+            return Ok(());
+        }
+        if let EscrowCondition::Arbiter(arbiter) = &self.condition {
+            if sender == arbiter {
+                return Ok(());
+            }
+        }
+        if sender == &self.depositor {
+            return match self.refund_after {
+                Some(after) if now >= after => Ok(()),
+                Some(after) => Err(cannot_refund(format!(
+                    "the funds are locked until {}",
+                    after.secs()
+                ))),
+                None => Err(cannot_refund("the escrow has no refund time")),
+            };
+        }
+        Err(cannot_refund("only the parties of the escrow can"))
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CreateArgs {
+    #[n(0)]
+    pub recipient: Address,
+
+    #[n(1)]
+    pub symbol: Symbol,
+
+    #[n(2)]
+    pub amount: TokenAmount,
+
+    #[n(3)]
+    pub condition: EscrowCondition,
+
+    /// When the depositor can take the funds back. Without it, only the
+    /// recipient (or the arbiter) can refund the escrow.
+    #[n(4)]
+    pub refund_after: Option<Timestamp>,
+
+    #[n(5)]
+    pub memo: Option<Memo>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CreateReturns {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoArgs {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoReturns {
+    #[n(0)]
+    pub escrow: EscrowInfo,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ReleaseArgs {
+    #[n(0)]
+    pub id: ByteVec,
+
+    /// The preimage of the hash of a [`EscrowCondition::HashPreimage`].
+    #[n(1)]
+    pub preimage: Option<ByteVec>,
+}
+
+pub type ReleaseReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RefundArgs {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+pub type RefundReturns = Acknowledgment;
+
+/// Funds locked by a depositor until they are released to a recipient, or
+/// refunded to the depositor.
+#[many_module(name = EscrowModule, id = 22, namespace = escrow, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait EscrowModuleBackend: Send {
+    #[many(deny_anonymous)]
+    fn create(&mut self, sender: &Address, args: CreateArgs) -> Result<CreateReturns, ManyError>;
+
+    fn info(&self, args: InfoArgs) -> Result<InfoReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn release(&mut self, sender: &Address, args: ReleaseArgs)
+        -> Result<ReleaseReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn refund(&mut self, sender: &Address, args: RefundArgs) -> Result<RefundReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{call_module, call_module_cbor};
+    use many_identity::testing::identity;
+    use std::sync::{Arc, Mutex};
+
+    fn escrow(condition: EscrowCondition) -> EscrowInfo {
+        EscrowInfo {
+            depositor: identity(1),
+            recipient: identity(2),
+            symbol: identity(100),
+            amount: TokenAmount::from(10u64),
+            condition,
+            refund_after: Some(Timestamp::new(2_000).unwrap()),
+            created: Timestamp::new(1_000).unwrap(),
+        }
+    }
+
+    #[test]
+    fn conditions() {
+        let (depositor, recipient) = (identity(1), identity(2));
+        assert!(EscrowCondition::Arbiter(identity(3))
+            .validate(&depositor, &recipient)
+            .is_ok());
+        assert!(EscrowCondition::Arbiter(recipient)
+            .validate(&depositor, &recipient)
+            .is_err());
+        assert!(EscrowCondition::Arbiter(Address::anonymous())
+            .validate(&depositor, &recipient)
+            .is_err());
+        assert!(EscrowCondition::HashPreimage(hash_preimage(b"secret"))
+            .validate(&depositor, &recipient)
+            .is_ok());
+        assert!(EscrowCondition::HashPreimage(vec![1, 2, 3].into())
+            .validate(&depositor, &recipient)
+            .is_err());
+    }
+
+    #[test]
+    fn release() {
+        let now = Timestamp::new(1_500).unwrap();
+        let later = Timestamp::new(3_000).unwrap();
+
+        let timed = escrow(EscrowCondition::Time(Timestamp::new(2_500).unwrap()));
+        assert!(timed.can_release(&identity(1), now, None).is_ok());
+        assert!(timed.can_release(&identity(2), now, None).is_err());
+        assert!(timed.can_release(&identity(2), later, None).is_ok());
+
+        let arbitrated = escrow(EscrowCondition::Arbiter(identity(3)));
+        assert!(arbitrated.can_release(&identity(3), now, None).is_ok());
+        assert!(arbitrated.can_release(&identity(2), later, None).is_err());
+
+        let hashed = escrow(EscrowCondition::HashPreimage(hash_preimage(b"secret")));
+        assert!(hashed
+            .can_release(&identity(4), now, Some(b"secret"))
+            .is_ok());
+        assert!(hashed
+            .can_release(&identity(4), now, Some(b"guess"))
+            .is_err());
+        assert!(hashed.can_release(&identity(4), now, None).is_err());
+    }
+
+    #[test]
+    fn refund() {
+        let now = Timestamp::new(1_500).unwrap();
+        let later = Timestamp::new(3_000).unwrap();
+
+        let arbitrated = escrow(EscrowCondition::Arbiter(identity(3)));
+        assert!(arbitrated.can_refund(&identity(2), now).is_ok());
+        assert!(arbitrated.can_refund(&identity(3), now).is_ok());
+        assert!(arbitrated.can_refund(&identity(1), now).is_err());
+        assert!(arbitrated.can_refund(&identity(1), later).is_ok());
+        assert!(arbitrated.can_refund(&identity(4), later).is_err());
+
+        let locked = EscrowInfo {
+            refund_after: None,
+            ..arbitrated
+        };
+        assert!(locked.can_refund(&identity(1), later).is_err());
+    }
+
+    #[test]
+    fn create() {
+        let mut mock = MockEscrowModuleBackend::new();
+        let args = CreateArgs {
+            recipient: identity(2),
+            symbol: identity(100),
+            amount: TokenAmount::from(10u64),
+            condition: EscrowCondition::Arbiter(identity(3)),
+            refund_after: None,
+            memo: None,
+        };
+        mock.expect_create()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| Ok(CreateReturns { id: vec![1].into() }));
+        let module = super::EscrowModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: CreateReturns = minicbor::decode(
+            &call_module_cbor(1, &module, "escrow.create", minicbor::to_vec(args).unwrap())
+                .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.id, ByteVec::from(vec![1]));
+    }
+
+    #[test]
+    fn info() {
+        let mut mock = MockEscrowModuleBackend::new();
+        mock.expect_info()
+            .with(eq(InfoArgs { id: vec![1].into() }))
+            .times(1)
+            .returning(|_| {
+                Ok(InfoReturns {
+                    escrow: escrow(EscrowCondition::Arbiter(identity(3))),
+                })
+            });
+        let module = super::EscrowModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: InfoReturns =
+            minicbor::decode(&call_module(0, &module, "escrow.info", "{ 0: h'01' }").unwrap())
+                .unwrap();
+        assert_eq!(result.escrow.recipient, identity(2));
+    }
+
+    #[test]
+    fn release_refund_deny_anonymous() {
+        let mock = MockEscrowModuleBackend::new();
+        let module = super::EscrowModule::new(Arc::new(Mutex::new(mock)));
+
+        assert!(call_module(0, &module, "escrow.release", "{ 0: h'01' }").is_err());
+        assert!(call_module(0, &module, "escrow.refund", "{ 0: h'01' }").is_err());
+    }
+}
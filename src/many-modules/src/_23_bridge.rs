@@ -0,0 +1,347 @@
+use crate::Acknowledgment;
+use many_error::{define_attribute_many_error, ManyError};
+use many_identity::Address;
+use many_macros::many_module;
+use many_types::attributes::Attribute;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{Memo, Timestamp};
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+pub const BRIDGE_MODULE_ATTRIBUTE: Attribute = Attribute::id(23);
+
+define_attribute_many_error!(
+    attribute 23 => {
+        1: pub fn unknown_counterparty(chain) => "Unknown bridge counterparty {chain}.",
+        2: pub fn unknown_anchor(chain, height) => "No state hash of {chain} is anchored at height {height}.",
+        3: pub fn conflicting_anchor(chain, height) => "A different state hash of {chain} is already anchored at height {height}.",
+        4: pub fn invalid_bridge_proof(reason) => "Invalid bridge proof: {reason}.",
+        5: pub fn transfer_not_found(id) => "Bridge transfer {id} not found.",
+        6: pub fn transfer_already_claimed(id) => "Bridge transfer {id} was already claimed.",
+        7: pub fn unsupported_symbol(symbol, reason) => "Symbol {symbol} cannot be bridged: {reason}.",
+        8: pub fn not_a_relayer(address) => "{address} is not a relayer of this counterparty.",
+    }
+);
+
+/// How the tokens of a transfer left their ledger.
+#[derive(Copy, Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(index_only)]
+pub enum TransferKind {
+    /// Native tokens locked on the source ledger, to be minted as wrapped
+    /// tokens on the destination.
+    #[n(0)]
+    Lock,
+
+    /// Wrapped tokens burnt on the source ledger, to be released from the
+    /// locked native tokens of the destination.
+    #[n(1)]
+    Burn,
+}
+
+/// A transfer out of a ledger. It is stored in the state of the source
+/// ledger, so the destination can verify it with a proof against a state
+/// hash of the source.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BridgeTransfer {
+    #[n(0)]
+    pub kind: TransferKind,
+
+    /// The identity of the source ledger.
+    #[n(1)]
+    pub source: Address,
+
+    /// The identity of the destination ledger.
+    #[n(2)]
+    pub destination: Address,
+
+    #[n(3)]
+    pub sender: Address,
+
+    #[n(4)]
+    pub recipient: Address,
+
+    /// The symbol of the tokens on the ledger they are native to, i.e. the
+    /// source of a lock, or the destination of a burn.
+    #[n(5)]
+    pub native_symbol: Symbol,
+
+    #[n(6)]
+    pub amount: TokenAmount,
+
+    #[n(7)]
+    pub created: Timestamp,
+}
+
+/// Another ledger this ledger can exchange tokens with.
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct Counterparty {
+    /// The identities allowed to anchor state hashes of the counterparty.
+    #[n(0)]
+    pub relayers: BTreeSet<Address>,
+
+    /// The wrapped symbol minted on this ledger for each native symbol of
+    /// the counterparty.
+    #[n(1)]
+    pub wrapped: BTreeMap<Symbol, Symbol>,
+}
+
+impl Counterparty {
+    /// The native symbol of the counterparty wrapped by a symbol of this
+    /// ledger, if any.
+    pub fn native_symbol(&self, wrapped: &Symbol) -> Option<Symbol> {
+        self.wrapped
+            .iter()
+            .find_map(|(native, w)| (w == wrapped).then_some(*native))
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoArgs {
+    #[n(0)]
+    pub chain: Address,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoReturns {
+    #[n(0)]
+    pub counterparty: Counterparty,
+
+    /// The last height of the counterparty with an anchored state hash.
+    #[n(1)]
+    pub anchored_height: Option<u64>,
+
+    /// The native tokens of this ledger locked for the counterparty, which
+    /// can be released by burning their wrapped tokens there. They are held
+    /// by the address of the counterparty on this ledger.
+    #[n(2)]
+    pub locked: BTreeMap<Symbol, TokenAmount>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RegisterArgs {
+    #[n(0)]
+    pub chain: Address,
+
+    #[n(1)]
+    pub relayers: BTreeSet<Address>,
+
+    /// The wrapped symbols of this ledger, by native symbol of the
+    /// counterparty. They need to exist on this ledger.
+    #[n(2)]
+    pub wrapped: BTreeMap<Symbol, Symbol>,
+}
+
+pub type RegisterReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct LockArgs {
+    #[n(0)]
+    pub destination: Address,
+
+    #[n(1)]
+    pub recipient: Address,
+
+    #[n(2)]
+    pub symbol: Symbol,
+
+    #[n(3)]
+    pub amount: TokenAmount,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TransferReturns {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+pub type LockReturns = TransferReturns;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BurnArgs {
+    #[n(0)]
+    pub destination: Address,
+
+    #[n(1)]
+    pub recipient: Address,
+
+    /// The wrapped symbol, on this ledger.
+    #[n(2)]
+    pub symbol: Symbol,
+
+    #[n(3)]
+    pub amount: TokenAmount,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+}
+
+pub type BurnReturns = TransferReturns;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TransferArgs {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+/// A transfer with the Merk proof of its record against the state hash of the
+/// source ledger at the last committed height.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TransferInfo {
+    #[n(0)]
+    pub transfer: BridgeTransfer,
+
+    #[n(1)]
+    pub height: u64,
+
+    #[n(2)]
+    pub hash: ByteVec,
+
+    #[n(3)]
+    pub proof: ByteVec,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AnchorArgs {
+    #[n(0)]
+    pub chain: Address,
+
+    #[n(1)]
+    pub height: u64,
+
+    /// The state hash of the counterparty at that height.
+    #[n(2)]
+    pub hash: ByteVec,
+}
+
+pub type AnchorReturns = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ClaimArgs {
+    #[n(0)]
+    pub source: Address,
+
+    /// The height of the anchored state hash the proof is against.
+    #[n(1)]
+    pub height: u64,
+
+    #[n(2)]
+    pub id: ByteVec,
+
+    #[n(3)]
+    pub proof: ByteVec,
+}
+
+pub type ClaimReturns = Acknowledgment;
+
+/// Token transfers between ledgers. Native tokens are locked on their ledger
+/// and minted as wrapped tokens on the other one; wrapped tokens are burnt to
+/// release the native tokens they stand for.
+///
+/// A ledger trusts the relayers of a counterparty to anchor its state hashes,
+/// and verifies every transfer it credits with a proof against one of them.
+#[many_module(name = BridgeModule, id = 23, namespace = bridge, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait BridgeModuleBackend: Send {
+    fn info(&self, args: InfoArgs) -> Result<InfoReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: RegisterArgs,
+    ) -> Result<RegisterReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn lock(&mut self, sender: &Address, args: LockArgs) -> Result<LockReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn burn(&mut self, sender: &Address, args: BurnArgs) -> Result<BurnReturns, ManyError>;
+
+    fn transfer(&self, args: TransferArgs) -> Result<TransferInfo, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn anchor(&mut self, sender: &Address, args: AnchorArgs) -> Result<AnchorReturns, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn claim(&mut self, sender: &Address, args: ClaimArgs) -> Result<ClaimReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::{call_module, call_module_cbor};
+    use many_identity::testing::identity;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn native_symbol() {
+        let counterparty = Counterparty {
+            wrapped: BTreeMap::from([(identity(100), identity(200))]),
+            ..Default::default()
+        };
+        assert_eq!(
+            counterparty.native_symbol(&identity(200)),
+            Some(identity(100))
+        );
+        assert_eq!(counterparty.native_symbol(&identity(100)), None);
+    }
+
+    #[test]
+    fn lock() {
+        let mut mock = MockBridgeModuleBackend::new();
+        let args = LockArgs {
+            destination: identity(10),
+            recipient: identity(2),
+            symbol: identity(100),
+            amount: TokenAmount::from(10u64),
+            memo: None,
+        };
+        mock.expect_lock()
+            .with(eq(identity(1)), eq(args.clone()))
+            .times(1)
+            .returning(|_, _| Ok(TransferReturns { id: vec![1].into() }));
+        let module = super::BridgeModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: LockReturns = minicbor::decode(
+            &call_module_cbor(1, &module, "bridge.lock", minicbor::to_vec(args).unwrap()).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.id, ByteVec::from(vec![1]));
+    }
+
+    #[test]
+    fn commands_deny_anonymous() {
+        let mock = MockBridgeModuleBackend::new();
+        let module = super::BridgeModule::new(Arc::new(Mutex::new(mock)));
+
+        let claim = ClaimArgs {
+            source: identity(10),
+            height: 1,
+            id: vec![1].into(),
+            proof: vec![].into(),
+        };
+        assert!(
+            call_module_cbor(0, &module, "bridge.claim", minicbor::to_vec(claim).unwrap()).is_err()
+        );
+        assert!(call_module(0, &module, "bridge.burn", "{}").is_err());
+    }
+}
@@ -0,0 +1,193 @@
+use many_error::{define_attribute_many_error, ManyError};
+use many_macros::many_module;
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use sha3::{Digest, Sha3_256};
+use std::collections::BTreeMap;
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+define_attribute_many_error!(
+    attribute 24 => {
+        1: pub fn unknown_migration(name) => "Unknown migration '{name}'.",
+    }
+);
+
+/// A migration compiled in the server, with its configuration if the
+/// server has one for it.
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct MigrationInfo {
+    #[n(0)]
+    pub name: String,
+
+    #[n(1)]
+    pub description: String,
+
+    /// `Regular`, `Hotfix`, `KeyRangeHotfix` or `Trigger`.
+    #[n(2)]
+    pub kind: String,
+
+    /// Whether the migration is in the configuration of the server. The
+    /// other fields are unset if it is not.
+    #[n(3)]
+    pub configured: bool,
+
+    #[n(4)]
+    pub enabled: bool,
+
+    #[n(5)]
+    pub active: bool,
+
+    /// The height the migration activates at, unless it activates at a time.
+    #[n(6)]
+    pub block_height: Option<u64>,
+
+    #[n(7)]
+    pub upper_block_height: Option<u64>,
+
+    #[n(8)]
+    pub upgrade_time: Option<Timestamp>,
+
+    #[n(9)]
+    pub issue: Option<String>,
+
+    /// The extra parameters of the migration, as JSON.
+    #[n(10)]
+    pub extra: BTreeMap<String, String>,
+
+    /// The canary rollout of the migration, as JSON, unless it is rolled
+    /// out on the whole network. Canary migrations are only enabled on
+    /// their canary nodes.
+    #[n(11)]
+    pub rollout: Option<String>,
+}
+
+/// The hash of a migration plan, the SHA3-256 of the migrations without
+/// their `active` state. Servers with the same migrations and
+/// configuration have the same hash, whatever their height.
+pub fn plan_hash(migrations: &[MigrationInfo]) -> ByteVec {
+    let plan: Vec<MigrationInfo> = migrations
+        .iter()
+        .map(|m| MigrationInfo {
+            active: false,
+            ..m.clone()
+        })
+        .collect();
+    Sha3_256::digest(minicbor::to_vec(plan).expect("Could not encode migrations"))
+        .to_vec()
+        .into()
+}
+
+pub type ListArgs = crate::EmptyArg;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListReturns {
+    /// The height of the state the migrations are active at.
+    #[n(0)]
+    pub height: u64,
+
+    /// All the migrations of the server, ordered by name.
+    #[n(1)]
+    pub migrations: Vec<MigrationInfo>,
+
+    /// See [`plan_hash`].
+    #[n(2)]
+    pub plan_hash: ByteVec,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoArgs {
+    #[n(0)]
+    pub name: String,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct InfoReturns {
+    #[n(0)]
+    pub height: u64,
+
+    #[n(1)]
+    pub migration: MigrationInfo,
+}
+
+/// The migration registry of a server and its configuration, so operators
+/// can check that the nodes of a network run the same migrations.
+#[many_module(name = MigrationsModule, id = 24, namespace = migrations, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait MigrationsModuleBackend: Send {
+    fn list(&self, args: ListArgs) -> Result<ListReturns, ManyError>;
+
+    fn info(&self, args: InfoArgs) -> Result<InfoReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::call_module_cbor;
+    use std::sync::{Arc, Mutex};
+
+    fn migration(active: bool) -> MigrationInfo {
+        MigrationInfo {
+            name: "Token Migration".to_string(),
+            description: "Move the ledger to tokens".to_string(),
+            kind: "Regular".to_string(),
+            configured: true,
+            enabled: true,
+            active,
+            block_height: Some(20),
+            extra: BTreeMap::from([("symbol".to_string(), "\"MFX\"".to_string())]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn info() {
+        let args = InfoArgs {
+            name: "Token Migration".to_string(),
+        };
+        let mut mock = MockMigrationsModuleBackend::new();
+        mock.expect_info()
+            .with(eq(args.clone()))
+            .times(1)
+            .returning(|_| {
+                Ok(InfoReturns {
+                    height: 30,
+                    migration: migration(true),
+                })
+            });
+        let module = super::MigrationsModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: InfoReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "migrations.info",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.height, 30);
+        assert_eq!(result.migration, migration(true));
+    }
+
+    #[test]
+    fn plan_hash_ignores_activation() {
+        assert_eq!(
+            plan_hash(&[migration(false)]),
+            plan_hash(&[migration(true)])
+        );
+
+        let disabled = MigrationInfo {
+            enabled: false,
+            ..migration(false)
+        };
+        assert_ne!(plan_hash(&[migration(false)]), plan_hash(&[disabled]));
+    }
+}
@@ -0,0 +1,73 @@
+use many_error::ManyError;
+use many_macros::many_module;
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[cfg(test)]
+use mockall::{automock, predicate::*};
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ChallengeArgs {
+    /// The origin of the application the challenge will be signed from, as
+    /// in the `clientData` of WebAuthn.
+    #[n(0)]
+    pub origin: String,
+}
+
+/// A nonce to add to the WebAuthn challenge of an envelope. It can be used
+/// once, until it expires.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ChallengeReturns {
+    #[n(0)]
+    pub nonce: ByteVec,
+
+    #[n(1)]
+    pub expires: Timestamp,
+}
+
+/// Challenges for the WebAuthn envelopes sent to the server, if it only
+/// accepts the envelopes signed over a challenge it issued.
+#[many_module(name = WebAuthnModule, id = 25, namespace = webauthn, many_modules_crate = crate)]
+#[cfg_attr(test, automock)]
+pub trait WebAuthnModuleBackend: Send {
+    fn challenge(&self, args: ChallengeArgs) -> Result<ChallengeReturns, ManyError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testutils::call_module_cbor;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn challenge() {
+        let args = ChallengeArgs {
+            origin: "https://localhost:3000".to_string(),
+        };
+        let returns = ChallengeReturns {
+            nonce: vec![1; 32].into(),
+            expires: Timestamp::new(1_000_120).unwrap(),
+        };
+        let mut mock = MockWebAuthnModuleBackend::new();
+        mock.expect_challenge()
+            .with(eq(args.clone()))
+            .times(1)
+            .return_const(Ok(returns.clone()));
+        let module = super::WebAuthnModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: ChallengeReturns = minicbor::decode(
+            &call_module_cbor(
+                0,
+                &module,
+                "webauthn.challenge",
+                minicbor::to_vec(args).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result, returns);
+    }
+}
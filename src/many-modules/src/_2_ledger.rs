@@ -7,10 +7,16 @@ use mockall::{automock, predicate::*};
 
 mod balance;
 mod info;
+mod list_balances;
+mod statement;
+mod vesting_info;
 
 pub use balance::*;
 pub use info::*;
+pub use list_balances::*;
 use many_identity::Address;
+pub use statement::*;
+pub use vesting_info::*;
 
 define_attribute_many_error!(
     attribute 2 => {
@@ -23,7 +29,7 @@ define_attribute_many_error!(
     }
 );
 
-#[many_module(name = LedgerModule, id = 2, namespace = ledger, many_modules_crate = crate)]
+#[many_module(name = LedgerModule, id = 2, namespace = ledger, many_modules_crate = crate, client = true)]
 #[cfg_attr(test, automock)]
 pub trait LedgerModuleBackend: Send {
     fn info(
@@ -38,6 +44,31 @@ pub trait LedgerModuleBackend: Send {
         args: BalanceArgs,
         context: Context,
     ) -> Result<BalanceReturns, ManyError>;
+
+    /// The balances of an account in every symbol of the ledger, a page at
+    /// a time.
+    fn list_balances(
+        &self,
+        sender: &Address,
+        args: ListBalancesArgs,
+        context: Context,
+    ) -> Result<ListBalancesReturns, ManyError>;
+
+    /// The transactions of an account in a symbol, with its balance after
+    /// each of them.
+    fn statement(
+        &self,
+        sender: &Address,
+        args: StatementArgs,
+    ) -> Result<StatementReturns, ManyError>;
+
+    /// The tokens of an account that unlock over a schedule, and how much is
+    /// still locked.
+    fn vesting_info(
+        &self,
+        sender: &Address,
+        args: VestingInfoArgs,
+    ) -> Result<VestingInfoReturns, ManyError>;
 }
 
 #[cfg(test)]
@@ -130,4 +161,126 @@ mod tests {
             BTreeMap::from([(*SYMBOL, TokenAmount::from(123u16))])
         );
     }
+
+    #[test]
+    fn list_balances() {
+        let data = ListBalancesArgs {
+            count: Some(1),
+            include_zero: Some(true),
+            ..Default::default()
+        };
+        let returns = ListBalancesReturns {
+            balances: vec![SymbolBalance {
+                symbol: *SYMBOL,
+                amount: TokenAmount::zero(),
+                locked: None,
+            }],
+            next: Some(*SYMBOL),
+        };
+        let mut mock = MockLedgerModuleBackend::new();
+        mock.expect_list_balances()
+            .with(
+                predicate::eq(identity(1)),
+                predicate::eq(data.clone()),
+                predicate::always(),
+            )
+            .times(1)
+            .return_const(Ok(returns.clone()));
+        let module = super::LedgerModule::new(Arc::new(Mutex::new(mock)));
+
+        let list_returns: ListBalancesReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.listBalances",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(list_returns, returns);
+    }
+
+    #[test]
+    fn statement() {
+        let data = StatementArgs {
+            account: Some(identity(2)),
+            symbol: *SYMBOL,
+            height_range: None,
+            date_range: None,
+            count: Some(1),
+            after: None,
+        };
+        let returns = StatementReturns {
+            account: identity(2),
+            symbol: *SYMBOL,
+            height: 3,
+            opening_balance: TokenAmount::zero(),
+            closing_balance: TokenAmount::from(10u16),
+            entries: vec![StatementEntry {
+                id: crate::events::EventId::from(1u64),
+                time: many_types::Timestamp::new(1_000).unwrap(),
+                kind: crate::events::EventKind::Send,
+                credit: TokenAmount::from(10u16),
+                debit: TokenAmount::zero(),
+                counterparty: Some(identity(1)),
+                balance: TokenAmount::from(10u16),
+                memo: None,
+            }],
+            next: Some(crate::events::EventId::from(1u64)),
+        };
+        let mut mock = MockLedgerModuleBackend::new();
+        mock.expect_statement()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .return_const(Ok(returns.clone()));
+        let module = super::LedgerModule::new(Arc::new(Mutex::new(mock)));
+
+        let statement_returns: StatementReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.statement",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(statement_returns, returns);
+    }
+
+    #[test]
+    fn vesting_info() {
+        let data = VestingInfoArgs {
+            account: Some(identity(2)),
+            symbols: None,
+        };
+        let vesting = BTreeMap::from([(
+            *SYMBOL,
+            VestingInfo {
+                grants: vec![],
+                locked: TokenAmount::from(10u16),
+            },
+        )]);
+        let mut mock = MockLedgerModuleBackend::new();
+        mock.expect_vesting_info()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .return_const(Ok(VestingInfoReturns {
+                vesting: vesting.clone(),
+            }));
+        let module = super::LedgerModule::new(Arc::new(Mutex::new(mock)));
+
+        let returns: VestingInfoReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.vestingInfo",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(returns.vesting, vesting);
+    }
 }
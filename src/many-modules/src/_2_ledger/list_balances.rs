@@ -0,0 +1,54 @@
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListBalancesArgs {
+    #[n(0)]
+    pub account: Option<Address>,
+
+    /// The maximum number of balances returned. The server may return
+    /// fewer.
+    #[n(1)]
+    pub count: Option<u64>,
+
+    /// Only list the symbols after this one, e.g. the `next` of the previous
+    /// page.
+    #[n(2)]
+    pub after: Option<Symbol>,
+
+    /// Whether to list the symbols the account holds none of.
+    #[n(3)]
+    pub include_zero: Option<bool>,
+
+    /// Whether to return the amounts locked by vesting grants.
+    #[n(4)]
+    pub include_locked: Option<bool>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct SymbolBalance {
+    #[n(0)]
+    pub symbol: Symbol,
+
+    #[n(1)]
+    pub amount: TokenAmount,
+
+    /// The part of the amount that cannot be spent yet, if requested.
+    #[n(2)]
+    pub locked: Option<TokenAmount>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListBalancesReturns {
+    /// The balances, ordered by symbol.
+    #[n(0)]
+    pub balances: Vec<SymbolBalance>,
+
+    /// The symbol to list the next page after, if there are more balances.
+    #[n(1)]
+    pub next: Option<Symbol>,
+}
@@ -0,0 +1,92 @@
+use crate::events::{EventId, EventKind};
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{CborRange, Memo, Timestamp};
+use minicbor::{Decode, Encode};
+
+/// The statement of an account in a symbol, a page at a time. The ranges
+/// are combined, and the page starts after the `next` of the previous one.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StatementArgs {
+    #[n(0)]
+    pub account: Option<Address>,
+
+    #[n(1)]
+    pub symbol: Symbol,
+
+    /// The heights of the blocks whose transactions are listed.
+    #[n(2)]
+    pub height_range: Option<CborRange<u64>>,
+
+    #[n(3)]
+    pub date_range: Option<CborRange<Timestamp>>,
+
+    /// The maximum number of entries returned. The server may return fewer.
+    #[n(4)]
+    pub count: Option<u64>,
+
+    #[n(5)]
+    pub after: Option<EventId>,
+}
+
+/// A change of the balance of the account, from an event of the ledger.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StatementEntry {
+    #[n(0)]
+    pub id: EventId,
+
+    #[n(1)]
+    pub time: Timestamp,
+
+    #[n(2)]
+    pub kind: EventKind,
+
+    #[n(3)]
+    pub credit: TokenAmount,
+
+    #[n(4)]
+    pub debit: TokenAmount,
+
+    /// The other side of the transaction, if there is one.
+    #[n(5)]
+    pub counterparty: Option<Address>,
+
+    /// The balance after the entry.
+    #[n(6)]
+    pub balance: TokenAmount,
+
+    #[n(7)]
+    pub memo: Option<Memo>,
+}
+
+/// The entries are ordered by event ID. The statement is at the state of
+/// `height`, and it is signed with the rest of the response.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct StatementReturns {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub symbol: Symbol,
+
+    #[n(2)]
+    pub height: u64,
+
+    /// The balance before the first entry of the page.
+    #[n(3)]
+    pub opening_balance: TokenAmount,
+
+    /// The balance after the last entry of the page.
+    #[n(4)]
+    pub closing_balance: TokenAmount,
+
+    #[n(5)]
+    pub entries: Vec<StatementEntry>,
+
+    /// The event to list the next page after, if there are more entries.
+    #[n(6)]
+    pub next: Option<EventId>,
+}
@@ -0,0 +1,35 @@
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount, VestingGrant};
+use many_types::VecOrSingle;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct VestingInfoArgs {
+    #[n(0)]
+    pub account: Option<Address>,
+
+    #[n(1)]
+    pub symbols: Option<VecOrSingle<Symbol>>,
+}
+
+/// The vesting grants of an account in a symbol.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct VestingInfo {
+    #[n(0)]
+    pub grants: Vec<VestingGrant>,
+
+    /// The amount of the balance that cannot be spent yet.
+    #[n(1)]
+    pub locked: TokenAmount,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct VestingInfoReturns {
+    /// The symbols that have grants.
+    #[n(0)]
+    pub vesting: BTreeMap<Symbol, VestingInfo>,
+}
@@ -1,5 +1,5 @@
-use crate::_3_kvstore::list::ListArgs;
-use crate::kvstore::list::ListReturns;
+use crate::_3_kvstore::list::{ListArgs, ListByOwnerArgs};
+use crate::kvstore::list::{ListByOwnerReturns, ListReturns};
 use many_error::ManyError;
 use many_identity::Address;
 use many_macros::many_module;
@@ -8,10 +8,12 @@ use minicbor::{decode, encode};
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+pub mod acl;
 pub mod get;
 pub mod info;
 pub mod list;
 pub mod query;
+pub use acl::*;
 pub use get::*;
 pub use info::*;
 pub use query::*;
@@ -23,6 +25,13 @@ pub trait KvStoreModuleBackend: Send {
     fn get(&self, sender: &Address, args: GetArgs) -> Result<GetReturns, ManyError>;
     fn query(&self, sender: &Address, args: QueryArgs) -> Result<QueryReturns, ManyError>;
     fn list(&self, sender: &Address, args: ListArgs) -> Result<ListReturns, ManyError>;
+    fn list_by_owner(
+        &self,
+        sender: &Address,
+        args: ListByOwnerArgs,
+    ) -> Result<ListByOwnerReturns, ManyError>;
+    fn batch_get(&self, sender: &Address, args: BatchGetArgs)
+        -> Result<BatchGetReturns, ManyError>;
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -152,6 +161,36 @@ mod tests {
         assert_eq!(get_returns.value, Some(ByteVec::from(vec![1, 2, 3, 4])));
     }
 
+    #[test]
+    fn batch_get() {
+        let data = BatchGetArgs {
+            keys: vec![ByteVec::from(vec![1]), ByteVec::from(vec![2])],
+        };
+        let mut mock = MockKvStoreModuleBackend::new();
+        mock.expect_batch_get()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_id, _args| {
+                Ok(BatchGetReturns {
+                    values: vec![Some(ByteVec::from(vec![3])), None],
+                })
+            });
+        let module = super::KvStoreModule::new(Arc::new(Mutex::new(mock)));
+
+        let returns: BatchGetReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.batchGet",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(returns.values, vec![Some(ByteVec::from(vec![3])), None]);
+    }
+
     #[test]
     fn query() {
         let data = QueryArgs {
@@ -166,6 +205,7 @@ mod tests {
                     owner: identity(666),
                     disabled: None,
                     previous_owner: None,
+                    acl: None,
                 })
             });
         let module = super::KvStoreModule::new(Arc::new(Mutex::new(mock)));
@@ -195,6 +235,40 @@ mod tests {
         assert_eq!(list_returns.keys, vec![vec![1].into(), vec![2].into()]);
     }
 
+    #[test]
+    fn list_by_owner() {
+        let data = ListByOwnerArgs {
+            owner: identity(2),
+            count: Some(2),
+            after: Some(vec![1].into()),
+        };
+        let mut mock = MockKvStoreModuleBackend::new();
+        mock.expect_list_by_owner()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_id, _args| {
+                Ok(ListByOwnerReturns {
+                    keys: vec![vec![2].into(), vec![3].into()],
+                    next: Some(vec![3].into()),
+                })
+            });
+        let module = super::KvStoreModule::new(Arc::new(Mutex::new(mock)));
+
+        let returns: ListByOwnerReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.listByOwner",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(returns.keys, vec![vec![2].into(), vec![3].into()]);
+        assert_eq!(returns.next, Some(vec![3].into()));
+    }
+
     #[test]
     fn key_filter_type_from_str() {
         let key_filter_type = KeyFilterType::from_str("owner:maa").unwrap();
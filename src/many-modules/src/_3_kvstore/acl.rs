@@ -0,0 +1,65 @@
+use many_identity::Address;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
+
+/// Who can read the value of a key.
+#[derive(Clone, Copy, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(index_only)]
+pub enum ReadAccess {
+    /// Anyone.
+    #[default]
+    #[n(0)]
+    Public,
+
+    /// Only the owner and the writers of the key.
+    #[n(1)]
+    Restricted,
+}
+
+/// The access control list of a key, set by its owner. Keys without one are
+/// readable by anyone and writable by their owner only.
+#[derive(Clone, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct KeyAcl {
+    #[n(0)]
+    pub read: ReadAccess,
+
+    /// The identities that can put values at the key, besides its owner.
+    /// They do not own the key, so they cannot disable it, transfer it or
+    /// change its ACL.
+    #[n(1)]
+    pub writers: BTreeSet<Address>,
+}
+
+impl KeyAcl {
+    pub fn can_read(&self, owner: &Address, sender: &Address) -> bool {
+        self.read == ReadAccess::Public || self.can_write(owner, sender)
+    }
+
+    pub fn can_write(&self, owner: &Address, sender: &Address) -> bool {
+        owner == sender || self.writers.contains(sender)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+
+    #[test]
+    fn restricted() {
+        let acl = KeyAcl {
+            read: ReadAccess::Restricted,
+            writers: BTreeSet::from([identity(2)]),
+        };
+        assert!(acl.can_read(&identity(1), &identity(1)));
+        assert!(acl.can_read(&identity(1), &identity(2)));
+        assert!(!acl.can_read(&identity(1), &identity(3)));
+        assert!(acl.can_write(&identity(1), &identity(2)));
+        assert!(!acl.can_write(&identity(1), &identity(3)));
+
+        let public = KeyAcl::default();
+        assert!(public.can_read(&identity(1), &identity(3)));
+        assert!(!public.can_write(&identity(1), &identity(3)));
+    }
+}
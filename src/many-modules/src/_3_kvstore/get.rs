@@ -14,3 +14,18 @@ pub struct GetReturns {
     #[n(0)]
     pub value: Option<ByteVec>,
 }
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BatchGetArgs {
+    #[n(0)]
+    pub keys: Vec<ByteVec>,
+}
+
+/// The values of the keys, in the same order.
+#[derive(Clone, Debug, Encode, Decode)]
+#[cbor(map)]
+pub struct BatchGetReturns {
+    #[n(0)]
+    pub values: Vec<Option<ByteVec>>,
+}
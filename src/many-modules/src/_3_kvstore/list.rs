@@ -1,4 +1,5 @@
 use crate::kvstore::KeyFilterType;
+use many_identity::Address;
 use many_types::SortOrder;
 use minicbor::bytes::ByteVec;
 use minicbor::{Decode, Encode};
@@ -22,3 +23,32 @@ pub struct ListReturns {
     #[n(0)]
     pub keys: Vec<ByteVec>,
 }
+
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListByOwnerArgs {
+    #[n(0)]
+    pub owner: Address,
+
+    /// The maximum number of keys to return.
+    #[n(1)]
+    pub count: Option<u64>,
+
+    /// Only return the keys after this one, the `next` key of the previous
+    /// page.
+    #[n(2)]
+    pub after: Option<ByteVec>,
+}
+
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ListByOwnerReturns {
+    /// The keys owned by the address, in ascending order, including the
+    /// disabled ones.
+    #[n(0)]
+    pub keys: Vec<ByteVec>,
+
+    /// The key to list the next page after, if there are more keys.
+    #[n(1)]
+    pub next: Option<ByteVec>,
+}
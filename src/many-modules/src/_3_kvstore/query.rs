@@ -1,3 +1,4 @@
+use super::KeyAcl;
 use many_error::Reason;
 use many_identity::Address;
 use many_types::Either;
@@ -22,4 +23,8 @@ pub struct QueryReturns {
 
     #[n(2)]
     pub previous_owner: Option<Address>,
+
+    /// The ACL set by the owner of the key, if any.
+    #[n(3)]
+    pub acl: Option<KeyAcl>,
 }
@@ -20,15 +20,27 @@ use mockall::{automock, predicate::*};
 
 mod info;
 mod list;
+mod predicate;
+mod types;
 
 pub use info::*;
 pub use list::*;
+pub use predicate::*;
+pub use types::*;
 
 #[many_module(name = EventsModule, id = 4, namespace = events, many_modules_crate = crate)]
 #[cfg_attr(test, automock)]
 pub trait EventsModuleBackend: Send {
     fn info(&self, args: InfoArgs) -> Result<InfoReturn, ManyError>;
     fn list(&self, args: ListArgs) -> Result<ListReturns, ManyError>;
+
+    /// The schemas of the kinds of events of this version of the server.
+    fn types(&self, _args: TypesArgs) -> Result<TypesReturn, ManyError> {
+        use strum::IntoEnumIterator;
+        Ok(TypesReturn {
+            types: EventKind::iter().map(|kind| kind.schema()).collect(),
+        })
+    }
 }
 
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
@@ -145,6 +157,10 @@ pub struct EventFilter {
 
     pub date_range: Option<CborRange<Timestamp>>,
 
+    /// A compound predicate the events also need to match. It is only
+    /// encoded if set, so older servers still decode the other filters.
+    pub predicate: Option<EventPredicate>,
+
     pub events_filter_attribute_specific:
         BTreeMap<EventFilterAttributeSpecificIndex, EventFilterAttributeSpecific>,
 }
@@ -155,17 +171,23 @@ impl<C> Encode<C> for EventFilter {
         e: &mut Encoder<W>,
         _: &mut C,
     ) -> Result<(), encode::Error<W::Error>> {
-        e.map(5 + self.events_filter_attribute_specific.len() as u64)?
-            .u8(0)?
-            .encode(&self.account)?
-            .u8(1)?
-            .encode(&self.kind)?
-            .u8(2)?
-            .encode(&self.symbol)?
-            .u8(3)?
-            .encode(&self.id_range)?
-            .u8(4)?
-            .encode(self.date_range)?;
+        e.map(
+            5 + self.predicate.is_some() as u64
+                + self.events_filter_attribute_specific.len() as u64,
+        )?
+        .u8(0)?
+        .encode(&self.account)?
+        .u8(1)?
+        .encode(&self.kind)?
+        .u8(2)?
+        .encode(&self.symbol)?
+        .u8(3)?
+        .encode(&self.id_range)?
+        .u8(4)?
+        .encode(self.date_range)?;
+        if let Some(predicate) = &self.predicate {
+            e.u8(5)?.encode(predicate)?;
+        }
         for (key, value) in self.events_filter_attribute_specific.iter() {
             e.encode(key)?.encode(value)?;
         }
@@ -183,6 +205,7 @@ impl<'b, C> Decode<'b, C> for EventFilter {
         let mut symbol = None;
         let mut id_range = None;
         let mut date_range = None;
+        let mut predicate = None;
         let mut events_filter_attribute_specific = BTreeMap::new();
         for _ in 0..len.unwrap_or_default() {
             use minicbor::data::Type;
@@ -195,6 +218,7 @@ impl<'b, C> Decode<'b, C> for EventFilter {
                         2 => symbol = d.decode()?,
                         3 => id_range = d.decode()?,
                         4 => date_range = d.decode()?,
+                        5 => predicate = d.decode()?,
                         i => return Err(Error::message(format!("Unknown key {i}"))),
                     }
                 }
@@ -211,6 +235,7 @@ impl<'b, C> Decode<'b, C> for EventFilter {
             symbol,
             id_range,
             date_range,
+            predicate,
             events_filter_attribute_specific,
         })
     }
@@ -421,6 +446,25 @@ macro_rules! define_event_info_memo {
     };
 }
 
+/// Return the field of an event with a given name. Fields are given twice, as
+/// the first one is matched with the name and the second one is returned.
+macro_rules! define_event_info_pick_field {
+    (symbol) => {};
+    (symbol symbol $name: ident, $( $f_: ident $name_: ident, )*) => {
+        return Some($name)
+    };
+    (symbol $f_: ident $name_: ident, $( $f: ident $name: ident, )*) => {
+        define_event_info_pick_field!(symbol $( $f $name, )*)
+    };
+    (amount) => {};
+    (amount amount $name: ident, $( $f_: ident $name_: ident, )*) => {
+        return Some($name)
+    };
+    (amount $f_: ident $name_: ident, $( $f: ident $name: ident, )*) => {
+        define_event_info_pick_field!(amount $( $f $name, )*)
+    };
+}
+
 macro_rules! define_event_info_addresses_trait {
     (@field $set: ident) => {};
     (@field $set: ident $name: ident id $(,)? $( $name_: ident $( $tag_: ident )*, )* ) => {
@@ -480,6 +524,30 @@ macro_rules! define_event_info {
             fn is_about(&self, id: Address) -> bool {
                 self.addresses().contains(&id)
             }
+
+            /// The symbol of the tokens the event is about, if any.
+            pub fn symbol(&self) -> Option<&Symbol> {
+                match self {
+                    $( EventInfo :: $name { $( $fname, )* } => {
+                        $( let _ = $fname; )*
+                        define_event_info_pick_field!(symbol $( $fname $fname, )*);
+                    } )*
+                }
+
+                None
+            }
+
+            /// The amount of tokens the event moves, if it has a single one.
+            pub fn amount(&self) -> Option<&TokenAmount> {
+                match self {
+                    $( EventInfo :: $name { $( $fname, )* } => {
+                        $( let _ = $fname; )*
+                        define_event_info_pick_field!(amount $( $fname $fname, )*);
+                    } )*
+                }
+
+                None
+            }
         }
 
         define_event_info_addresses_trait!( $( $name { $( $fname $( $( $tag )* )?, )* } )* );
@@ -696,10 +764,27 @@ macro_rules! define_multisig_event {
     }
 }
 
+macro_rules! define_event_schema {
+    ( $( $name: ident { $( $idx: literal | $fname: ident : $type: ty $([ $( $tag: ident )* ])?, )* }, )* ) => {
+        impl EventKind {
+            /// The fields of the events of this kind.
+            pub fn schema(&self) -> EventTypeSchema {
+                let fields: &[(u32, &str, &str, &[&str])] = match self {
+                    $( EventKind :: $name => &[
+                        $( ($idx, stringify!($fname), stringify!($type), &[ $( $( stringify!($tag) ),* )? ]), )*
+                    ], )*
+                };
+                EventTypeSchema::new(*self, fields)
+            }
+        }
+    };
+}
+
 macro_rules! define_event {
     ( $( [ $index: literal $(, $sub: literal )* ] $name: ident $(($method_arg: ty $([ $( $struct_tag: ident )* ])? ))? { $( $idx: literal | $fname: ident : $type: ty $([ $($tag: ident)* ])?, )* }, )* ) => {
         define_event_kind!( $( [ $index $(, $sub )* ] $name { $( $idx | $fname : $type, )* }, )* );
         define_event_info!( $( $name { $( $idx | $fname : $type $([ $( $tag )* ])?, )* }, )* );
+        define_event_schema!( $( $name { $( $idx | $fname : $type $([ $( $tag )* ])?, )* }, )* );
 
         define_multisig_event!( $( $name $(: $method_arg $([ $( $struct_tag )* ])? )?, )* );
     }
@@ -714,6 +799,86 @@ define_event! {
         4     | amount:                 TokenAmount,
         5     | memo:                   Option<Memo>                           [ memo ],
     },
+    [6, 1]      SendScheduled {
+        1     | token:                  ByteVec,
+        2     | from:                   Address                                [ id ],
+        3     | to:                     Address                                [ id ],
+        4     | symbol:                 Symbol                                 [ id ],
+        5     | amount:                 TokenAmount,
+        6     | height:                 Option<u64>,
+        7     | time:                   Option<Timestamp>,
+        8     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [6, 2]      ScheduledSendExecuted {
+        1     | token:                  ByteVec,
+        2     | from:                   Address                                [ id ],
+        3     | to:                     Address                                [ id ],
+        4     | error:                  Option<ManyError>,
+    },
+    [6, 3]      ScheduledSendCancelled {
+        1     | token:                  ByteVec,
+        2     | from:                   Address                                [ id ],
+        3     | canceller:              Address                                [ id ],
+    },
+    [6, 4]      PullAuthorized {
+        1     | account:                Address                                [ id ],
+        2     | payee:                  Address                                [ id ],
+        3     | symbol:                 Symbol                                 [ id ],
+        4     | amount:                 TokenAmount,
+        5     | period_in_secs:         u64,
+    },
+    [6, 5]      PullRevoked {
+        1     | account:                Address                                [ id ],
+        2     | payee:                  Address                                [ id ],
+        3     | symbol:                 Symbol                                 [ id ],
+        4     | revoker:                Address                                [ id ],
+    },
+    [6, 6]      PullPayment {
+        1     | account:                Address                                [ id ],
+        2     | payee:                  Address                                [ id ],
+        3     | symbol:                 Symbol                                 [ id ],
+        4     | amount:                 TokenAmount,
+        5     | period:                 u64,
+        6     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [6, 7]      SwapCreate {
+        1     | id:                     ByteVec,
+        2     | creator:                Address                                [ id ],
+        3     | counterparty:           Address                                [ id ],
+        4     | give_symbol:            Symbol                                 [ id ],
+        5     | give_amount:            TokenAmount,
+        6     | want_symbol:            Symbol                                 [ id ],
+        7     | want_amount:            TokenAmount,
+        8     | expiration:             Timestamp,
+        9     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [6, 8]      SwapAccept {
+        1     | id:                     ByteVec,
+        2     | creator:                Address                                [ id ],
+        3     | counterparty:           Address                                [ id ],
+    },
+    [6, 9]      SwapCancel {
+        1     | id:                     ByteVec,
+        2     | creator:                Address                                [ id ],
+        3     | canceller:              Address                                [ id ],
+    },
+    [6, 10]     TransferPrepared {
+        1     | from:                   Address                                [ id ],
+        2     | to:                     Address                                [ id ],
+        3     | symbol:                 Symbol                                 [ id ],
+        4     | amount:                 TokenAmount,
+        5     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [6, 11]     TransferCommitted {
+        1     | id:                     ByteVec,
+        2     | from:                   Address                                [ id ],
+        3     | to:                     Address                                [ id ],
+    },
+    [6, 12]     TransferAborted {
+        1     | id:                     ByteVec,
+        2     | from:                   Address                                [ id ],
+        3     | to:                     Address                                [ id ],
+    },
     [7, 0]      KvStorePut (crate::kvstore::PutArgs) {
         1     | key:                    ByteVec,
         2     | value:                  ByteVec,
@@ -723,6 +888,11 @@ define_event! {
         1     | key:                    ByteVec,
         2     | reason:                 Option<Reason<u64>>,
     },
+    [7, 2]      KvStoreSetAcl (crate::kvstore::SetAclArgs) {
+        1     | key:                    ByteVec,
+        2     | owner:                  Address                                [ id ],
+        3     | acl:                    crate::kvstore::KeyAcl,
+    },
     [9, 0]      AccountCreate (crate::account::CreateArgs [ addresses ]) {
         1     | account:                Address                                [ id ],
         2     | description:            Option<String>,
@@ -794,6 +964,27 @@ define_event! {
         2     | token:                  ByteVec,
         3     | time:                   Timestamp,
     },
+    [9, 1, 7]   AccountMultisigComment {
+        1     | account:                Address                                [ id ],
+        2     | token:                  ByteVec,
+        3     | author:                 Address                                [ id ],
+        4     | comment:                u64,
+        5     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [9, 1, 8]   AccountDelegate {
+        1     | account:                Address                                [ id ],
+        2     | delegator:              Address                                [ id ],
+        3     | delegate:               Address                                [ id ],
+        4     | start:                  Option<Timestamp>,
+        5     | end:                    Timestamp,
+        6     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [9, 1, 9]   AccountRevokeDelegation {
+        1     | account:                Address                                [ id ],
+        2     | delegator:              Address                                [ id ],
+        3     | delegate:               Address                                [ id ],
+        4     | memo:                   Option<Memo>                           [ memo ],
+    },
     [11, 0]     TokenCreate (module::ledger::TokenCreateArgs) {
         1     | summary:                ledger::TokenInfoSummary,
         2     | symbol:                 Address                                [ id ],
@@ -821,6 +1012,11 @@ define_event! {
         2     | extended_info:          Vec<AttributeRelatedIndex>,
         3     | memo:                   Option<Memo>                           [ memo ],
     },
+    [11, 4]     TokenUpdateRules (module::ledger::TokenUpdateRulesArgs) {
+        1     | symbol:                 Address                                [ id ],
+        2     | rules:                  module::ledger::TokenRules,
+        3     | memo:                   Option<Memo>                           [ memo ],
+    },
     [12, 0]     TokenMint (module::ledger::TokenMintArgs) {
         1     | symbol:                 Address                                [ id ],
         2     | distribution:           ledger::LedgerTokensAddressMap         [ id ],
@@ -836,6 +1032,12 @@ define_event! {
         2     | owner:                  Address                                [ id ],
         3     | new_owner:              Address                                [ id ],
     },
+    [13, 1]     KvStoreTransferOffer {
+        1     | key:                    ByteVec,
+        2     | owner:                  Address                                [ id ],
+        3     | new_owner:              Address                                [ id ],
+        4     | expiration:             Option<Timestamp>,
+    },
     [17, 0]     WebDeploy (module::web::DeployArgs) {
         1     | owner:                  Address                                [ id ],
         2     | site_name:              String,
@@ -857,6 +1059,77 @@ define_event! {
         5     | memo:                   Option<Memo>                           [ memo ],
         6     | domain:                 Option<String>,
     },
+    [19, 0]     NameRegister {
+        1     | name:                   String,
+        2     | owner:                  Address                                [ id ],
+        3     | address:                Address                                [ id ],
+        4     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [19, 1]     NameTransfer {
+        1     | name:                   String,
+        2     | owner:                  Address                                [ id ],
+        3     | new_owner:              Address                                [ id ],
+        4     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [20, 0]     TokenBuy {
+        1     | symbol:                 Address                                [ id ],
+        2     | buyer:                  Address                                [ id ],
+        3     | amount:                 TokenAmount,
+        4     | cost:                   TokenAmount,
+        5     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [20, 1]     TokenSell {
+        1     | symbol:                 Address                                [ id ],
+        2     | seller:                 Address                                [ id ],
+        3     | amount:                 TokenAmount,
+        4     | proceeds:               TokenAmount,
+        5     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [22, 0]     EscrowCreate {
+        1     | id:                     ByteVec,
+        2     | depositor:              Address                                [ id ],
+        3     | recipient:              Address                                [ id ],
+        4     | symbol:                 Symbol                                 [ id ],
+        5     | amount:                 TokenAmount,
+        6     | condition:              module::escrow::EscrowCondition        [ id ],
+        7     | refund_after:           Option<Timestamp>,
+        8     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [22, 1]     EscrowRelease {
+        1     | id:                     ByteVec,
+        2     | recipient:              Address                                [ id ],
+        3     | releaser:               Address                                [ id ],
+    },
+    [22, 2]     EscrowRefund {
+        1     | id:                     ByteVec,
+        2     | depositor:              Address                                [ id ],
+        3     | refunder:               Address                                [ id ],
+    },
+    [23, 0]     BridgeLock {
+        1     | id:                     ByteVec,
+        2     | sender:                 Address                                [ id ],
+        3     | destination:            Address                                [ id ],
+        4     | recipient:              Address,
+        5     | symbol:                 Symbol                                 [ id ],
+        6     | amount:                 TokenAmount,
+        7     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [23, 1]     BridgeBurn {
+        1     | id:                     ByteVec,
+        2     | sender:                 Address                                [ id ],
+        3     | destination:            Address                                [ id ],
+        4     | recipient:              Address,
+        5     | symbol:                 Symbol                                 [ id ],
+        6     | amount:                 TokenAmount,
+        7     | memo:                   Option<Memo>                           [ memo ],
+    },
+    [23, 2]     BridgeClaim {
+        1     | source:                 Address                                [ id ],
+        2     | id:                     ByteVec,
+        3     | recipient:              Address                                [ id ],
+        4     | symbol:                 Symbol                                 [ id ],
+        5     | amount:                 TokenAmount,
+    },
 }
 
 /// An Event that happened on the server and that is part of the log.
@@ -871,9 +1144,30 @@ pub struct EventLog {
 
     #[n(2)]
     pub content: EventInfo,
+
+    /// The running hash of the event log, up to and including this event.
+    /// Only set once the server chains its events.
+    #[n(3)]
+    pub chain_hash: Option<ByteVec>,
 }
 
+/// The running hash before the first chained event.
+pub const EVENT_CHAIN_GENESIS: [u8; 32] = [0; 32];
+
 impl EventLog {
+    /// The running hash of the event log after this event, given the running
+    /// hash before it. The `chain_hash` of the event itself is not hashed.
+    pub fn chain(&self, previous: &[u8]) -> Result<ByteVec, ManyError> {
+        use sha3::{Digest, Sha3_256};
+
+        let bytes = minicbor::to_vec((&self.id, &self.time, &self.content))
+            .map_err(ManyError::serialization_error)?;
+        let mut hasher = Sha3_256::new();
+        hasher.update(previous);
+        hasher.update(bytes);
+        Ok(hasher.finalize().to_vec().into())
+    }
+
     pub fn kind(&self) -> EventKind {
         EventKind::from(&self.content)
     }
@@ -986,6 +1280,7 @@ mod test {
                 amount: Default::default(),
                 symbol: Default::default(),
                 memo: None,
+                decimal_amount: None,
             })),
             token: None,
             threshold: 0,
@@ -1014,6 +1309,7 @@ mod test {
                 amount: Default::default(),
                 symbol: Default::default(),
                 memo: None,
+                decimal_amount: None,
             })),
             threshold: None,
             timeout_in_secs: None,
@@ -1072,6 +1368,14 @@ mod test {
             },
             [],
         );
+        check(
+            EventInfo::KvStoreSetAcl {
+                key: vec![].into(),
+                owner: i0,
+                acl: Default::default(),
+            },
+            [i0],
+        );
         check(
             EventInfo::AccountCreate {
                 account: i0,
@@ -1179,6 +1483,7 @@ mod test {
                 amount: Default::default(),
                 symbol: Default::default(),
                 memo: None,
+                decimal_amount: None,
             })),
             token: None,
             threshold: 0,
@@ -1205,6 +1510,7 @@ mod test {
                 amount: Default::default(),
                 symbol: Default::default(),
                 memo: None,
+                decimal_amount: None,
             })),
             token: None,
             threshold: 0,
@@ -1272,6 +1578,7 @@ mod test {
                     amount: Default::default(),
                     symbol: Default::default(),
                     memo: None,
+                    decimal_amount: None,
                 }),
             );
             let bytes = minicbor::to_vec(&event).expect("Could not serialize");
@@ -1284,6 +1591,7 @@ mod test {
                 amount: Default::default(),
                 symbol: Default::default(),
                 memo: None,
+                decimal_amount: None,
             }));
             let bytes = minicbor::to_vec(&event).expect("Could not serialize");
             let map: BTreeMap<CborAny, CborAny> = minicbor::decode(&bytes).unwrap();
@@ -1314,6 +1622,7 @@ mod test {
                         symbol: identity(4),
                         amount: amount.into(),
                         memo: None,
+                        decimal_amount: None,
                     })),
                 );
             }
@@ -1334,6 +1643,7 @@ mod test {
                                     symbol: identity(4),
                                     amount: amount.into(),
                                     memo: None,
+                                    decimal_amount: None,
                                 })),
                                 threshold: None,
                                 timeout_in_secs: None,
@@ -1414,6 +1724,7 @@ mod tests {
                             amount: TokenAmount::from(1000u64),
                             memo: None,
                         },
+                        chain_hash: None,
                     }],
                 })
             });
@@ -1428,6 +1739,52 @@ mod tests {
         assert_eq!(list_returns.events.len(), 1);
     }
 
+    #[test]
+    fn types() {
+        let mut mock = MockEventsModuleBackend::new();
+        mock.expect_types().times(1).returning(|_args| {
+            Ok(TypesReturn {
+                types: vec![EventKind::Send.schema()],
+            })
+        });
+        let module = super::EventsModule::new(Arc::new(Mutex::new(mock)));
+
+        let types_returns: TypesReturn =
+            minicbor::decode(&call_module(1, &module, "events.types", "null").unwrap()).unwrap();
+        assert_eq!(types_returns.types, vec![EventKind::Send.schema()]);
+    }
+
+    #[test]
+    fn schema() {
+        let schema = EventKind::Send.schema();
+        assert_eq!(schema.name, "send");
+        let fields: Vec<_> = schema
+            .fields
+            .iter()
+            .map(|f| (f.index, f.name.as_str(), f.type_name.as_str(), f.optional))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (1, "from", "Address", false),
+                (2, "to", "Address", false),
+                (3, "symbol", "Symbol", false),
+                (4, "amount", "TokenAmount", false),
+                (5, "memo", "Option<Memo>", true),
+            ]
+        );
+
+        let schema = EventKind::TokenCreate.schema();
+        assert_eq!(schema.fields[2].type_name, "Option<TokenMaybeOwner>");
+        assert_eq!(schema.fields[5].type_name, "Option<TokenExtendedInfo>");
+
+        let encoded = minicbor::to_vec(&schema).unwrap();
+        assert_eq!(
+            minicbor::decode::<EventTypeSchema>(&encoded).unwrap(),
+            schema
+        );
+    }
+
     #[test]
     fn encode_decode() {
         let event = hex::decode(
@@ -1459,11 +1816,21 @@ f756e742077697468204944207b69647d20756e6b6e6f776e2e02a162696478326d61666\
             symbol: None,
             id_range: None,
             date_range: None,
+            predicate: None,
             events_filter_attribute_specific: BTreeMap::from([(state_key, pending_state)]),
         };
         let encoded = minicbor::to_vec(&event_filter).unwrap();
         let decoded: EventFilter = minicbor::decode(&encoded).unwrap();
 
         assert_eq!(decoded, event_filter);
+
+        let event_filter = EventFilter {
+            predicate: Some(EventPredicate::Kind(VecOrSingle(vec![EventKind::Send]))),
+            ..event_filter
+        };
+        let encoded = minicbor::to_vec(&event_filter).unwrap();
+        let decoded: EventFilter = minicbor::decode(&encoded).unwrap();
+
+        assert_eq!(decoded, event_filter);
     }
 }
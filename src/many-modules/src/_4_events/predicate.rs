@@ -0,0 +1,202 @@
+use crate::events::{EventKind, EventLog};
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{CborRange, Timestamp, VecOrSingle};
+use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
+
+/// A condition on an event that servers can index events by. Every event has
+/// its kind as a term, and one term per address and symbol it is about.
+#[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
+pub enum EventTerm {
+    Kind(EventKind),
+    Address(Address),
+    Symbol(Symbol),
+}
+
+impl EventLog {
+    /// The terms of the event, see [`EventTerm`].
+    pub fn terms(&self) -> BTreeSet<EventTerm> {
+        use crate::events::AddressContainer;
+
+        let mut terms = BTreeSet::from([EventTerm::Kind(self.kind())]);
+        terms.extend(self.content.addresses().into_iter().map(EventTerm::Address));
+        terms.extend(self.content.symbol().cloned().map(EventTerm::Symbol));
+        terms
+    }
+}
+
+/// A compound filter on events, evaluated by the server.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+pub enum EventPredicate {
+    /// Events matching all the predicates. An empty list matches everything.
+    #[n(0)]
+    All(#[n(0)] Vec<EventPredicate>),
+
+    /// Events matching any of the predicates. An empty list matches nothing.
+    #[n(1)]
+    Any(#[n(0)] Vec<EventPredicate>),
+
+    #[n(2)]
+    Kind(#[n(0)] VecOrSingle<EventKind>),
+
+    /// Events about any of the addresses.
+    #[n(3)]
+    Address(#[n(0)] VecOrSingle<Address>),
+
+    #[n(4)]
+    Symbol(#[n(0)] VecOrSingle<Symbol>),
+
+    /// Events moving an amount of tokens in the range. Events without a
+    /// single amount (e.g. mints to multiple addresses) do not match.
+    #[n(5)]
+    Amount(#[n(0)] CborRange<TokenAmount>),
+
+    #[n(6)]
+    Date(#[n(0)] CborRange<Timestamp>),
+}
+
+impl EventPredicate {
+    pub fn matches(&self, event: &EventLog) -> bool {
+        match self {
+            EventPredicate::All(predicates) => predicates.iter().all(|p| p.matches(event)),
+            EventPredicate::Any(predicates) => predicates.iter().any(|p| p.matches(event)),
+            EventPredicate::Kind(VecOrSingle(kinds)) => kinds.contains(&event.kind()),
+            EventPredicate::Address(VecOrSingle(addresses)) => {
+                addresses.iter().any(|a| event.is_about(*a))
+            }
+            EventPredicate::Symbol(VecOrSingle(symbols)) => event
+                .content
+                .symbol()
+                .map_or(false, |symbol| symbols.contains(symbol)),
+            EventPredicate::Amount(range) => event
+                .content
+                .amount()
+                .map_or(false, |amount| range.contains(amount)),
+            EventPredicate::Date(range) => range.contains(&event.time),
+        }
+    }
+
+    /// Terms such that every event matching the predicate has at least one
+    /// of them, so an index of the terms can be used instead of going through
+    /// the whole log. Returns `None` if there are no such terms, e.g. for
+    /// amount or date ranges.
+    pub fn terms(&self) -> Option<BTreeSet<EventTerm>> {
+        match self {
+            // Any of the predicates narrows down the events, so use the one
+            // with the fewest terms.
+            EventPredicate::All(predicates) => predicates
+                .iter()
+                .filter_map(EventPredicate::terms)
+                .min_by_key(BTreeSet::len),
+            EventPredicate::Any(predicates) => {
+                predicates.iter().try_fold(BTreeSet::new(), |mut acc, p| {
+                    acc.extend(p.terms()?);
+                    Some(acc)
+                })
+            }
+            EventPredicate::Kind(VecOrSingle(kinds)) => {
+                Some(kinds.iter().copied().map(EventTerm::Kind).collect())
+            }
+            EventPredicate::Address(VecOrSingle(addresses)) => {
+                Some(addresses.iter().copied().map(EventTerm::Address).collect())
+            }
+            EventPredicate::Symbol(VecOrSingle(symbols)) => {
+                Some(symbols.iter().copied().map(EventTerm::Symbol).collect())
+            }
+            EventPredicate::Amount(_) | EventPredicate::Date(_) => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::events::{EventId, EventInfo};
+    use many_identity::testing::identity;
+    use std::ops::Bound;
+
+    fn send(amount: u64) -> EventLog {
+        EventLog {
+            id: EventId::from(1u64),
+            time: Timestamp::new(1_000).unwrap(),
+            content: EventInfo::Send {
+                from: identity(1),
+                to: identity(2),
+                symbol: identity(100),
+                amount: TokenAmount::from(amount),
+                memo: None,
+            },
+            chain_hash: None,
+        }
+    }
+
+    fn amount_range(start: u64, end: u64) -> EventPredicate {
+        EventPredicate::Amount(CborRange {
+            start: Bound::Included(TokenAmount::from(start)),
+            end: Bound::Excluded(TokenAmount::from(end)),
+        })
+    }
+
+    #[test]
+    fn matches() {
+        let event = send(50);
+        let about = |i| EventPredicate::Address(VecOrSingle(vec![identity(i)]));
+
+        assert!(EventPredicate::All(vec![]).matches(&event));
+        assert!(!EventPredicate::Any(vec![]).matches(&event));
+        assert!(about(2).matches(&event));
+        assert!(!about(3).matches(&event));
+        assert!(EventPredicate::Symbol(VecOrSingle(vec![identity(100)])).matches(&event));
+        assert!(amount_range(10, 100).matches(&event));
+        assert!(!amount_range(60, 100).matches(&event));
+
+        assert!(EventPredicate::All(vec![about(1), amount_range(10, 100)]).matches(&event));
+        assert!(!EventPredicate::All(vec![about(3), amount_range(10, 100)]).matches(&event));
+        assert!(EventPredicate::Any(vec![about(3), amount_range(10, 100)]).matches(&event));
+    }
+
+    #[test]
+    fn terms() {
+        let about = |i: &[u32]| {
+            EventPredicate::Address(VecOrSingle(i.iter().map(|i| identity(*i)).collect()))
+        };
+
+        assert_eq!(amount_range(0, 1).terms(), None);
+        assert_eq!(
+            EventPredicate::All(vec![about(&[1, 2]), about(&[3]), amount_range(0, 1)]).terms(),
+            Some(BTreeSet::from([EventTerm::Address(identity(3))]))
+        );
+        assert_eq!(
+            EventPredicate::Any(vec![about(&[1]), about(&[3])]).terms(),
+            Some(BTreeSet::from([
+                EventTerm::Address(identity(1)),
+                EventTerm::Address(identity(3))
+            ]))
+        );
+        assert_eq!(
+            EventPredicate::Any(vec![about(&[1]), amount_range(0, 1)]).terms(),
+            None
+        );
+
+        // Terms of the events are a superset of the terms of the predicates
+        // they match.
+        let event = send(50);
+        assert!(about(&[2]).terms().unwrap().is_subset(&event.terms()));
+        assert!(event.terms().contains(&EventTerm::Kind(EventKind::Send)));
+        assert!(event.terms().contains(&EventTerm::Symbol(identity(100))));
+    }
+
+    #[test]
+    fn encode_decode() {
+        let predicate = EventPredicate::Any(vec![
+            EventPredicate::Kind(VecOrSingle(vec![EventKind::Send])),
+            EventPredicate::All(vec![amount_range(1, 2)]),
+        ]);
+        let bytes = minicbor::to_vec(&predicate).unwrap();
+        assert_eq!(
+            minicbor::decode::<EventPredicate>(&bytes).unwrap(),
+            predicate
+        );
+    }
+}
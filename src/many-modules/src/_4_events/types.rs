@@ -0,0 +1,80 @@
+use crate::events::EventKind;
+use crate::EmptyArg;
+use minicbor::{Decode, Encode};
+
+pub type TypesArgs = EmptyArg;
+
+/// A field of an event, the value at its index in the map of the event.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct EventFieldSchema {
+    #[n(0)]
+    pub index: u32,
+
+    #[n(1)]
+    pub name: String,
+
+    /// The type of the value, e.g. `Address` or `Option<Timestamp>`. `Option`
+    /// values are encoded as `null` when unset.
+    #[n(2)]
+    pub type_name: String,
+
+    /// Whether the field is left out of the map when unset.
+    #[n(3)]
+    pub optional: bool,
+}
+
+/// The layout of the events of a kind. An event is a map with its kind at
+/// index 0 and its fields at their index.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct EventTypeSchema {
+    #[n(0)]
+    pub kind: EventKind,
+
+    #[n(1)]
+    pub name: String,
+
+    #[n(2)]
+    pub fields: Vec<EventFieldSchema>,
+}
+
+impl EventTypeSchema {
+    /// The schema of a kind, from the index, name, type and tags of its
+    /// fields.
+    pub(crate) fn new(kind: EventKind, fields: &[(u32, &str, &str, &[&str])]) -> Self {
+        Self {
+            kind,
+            name: kind.to_string(),
+            fields: fields
+                .iter()
+                .map(|(index, name, ty, tags)| EventFieldSchema {
+                    index: *index,
+                    name: name.to_string(),
+                    type_name: type_name(ty),
+                    optional: tags.contains(&"memo"),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A Rust type without spaces nor module paths, e.g. `Option<TokenMaybeOwner>`
+/// for `Option < ledger :: TokenMaybeOwner >`.
+fn type_name(ty: &str) -> String {
+    let ty: String = ty.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut parts: Vec<&str> = ty.split("::").collect();
+    let last = parts.pop().unwrap_or_default();
+    parts
+        .into_iter()
+        .map(|part| part.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_'))
+        .chain([last])
+        .collect()
+}
+
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct TypesReturn {
+    #[n(0)]
+    pub types: Vec<EventTypeSchema>,
+}
@@ -1,7 +1,9 @@
 pub mod get_info;
 pub mod info;
 pub mod query;
+pub mod register;
 pub mod types;
+pub mod unregister;
 pub use get_info::*;
 pub use info::*;
 use many_error::ManyError;
@@ -9,7 +11,9 @@ use many_identity::Address;
 use many_macros::many_module;
 use many_protocol::context::Context;
 pub use query::*;
+pub use register::*;
 pub use types::*;
+pub use unregister::*;
 
 #[cfg(test)]
 use mockall::{automock, predicate::*};
@@ -35,6 +39,23 @@ pub trait DataModuleBackend: Send {
         args: DataQueryArgs,
         context: Context,
     ) -> Result<DataQueryReturns, ManyError>;
+
+    /// Register a metric at a new index, computed by the server from then on.
+    /// Only privileged identities can register metrics.
+    #[many(deny_anonymous)]
+    fn register(
+        &mut self,
+        sender: &Address,
+        args: DataRegisterArgs,
+    ) -> Result<DataRegisterReturns, ManyError>;
+
+    /// Remove a metric registered with `register`.
+    #[many(deny_anonymous)]
+    fn unregister(
+        &mut self,
+        sender: &Address,
+        args: DataUnregisterArgs,
+    ) -> Result<DataUnregisterReturns, ManyError>;
 }
 
 #[cfg(test)]
@@ -150,4 +171,28 @@ mod tests {
         let b = nzatc_value.try_into().unwrap();
         assert_eq!(a, b);
     }
+
+    #[test]
+    fn register() {
+        let args = DataRegisterArgs {
+            index: DataIndex::new(0).with_index(3).with_index(0),
+            metric: DataMetric {
+                shortname: "eventCount".into(),
+                r#type: DataType::Counter,
+                source: DataSource::Events,
+                aggregation: DataAggregation::Count,
+            },
+        };
+
+        let mut mock = MockDataModuleBackend::new();
+        mock.expect_register()
+            .with(
+                predicate::eq(many_identity::testing::identity(1)),
+                predicate::eq(args.clone()),
+            )
+            .times(1)
+            .returning(|_, _| Ok(DataRegisterReturns::default()));
+        let module = super::DataModule::new(Arc::new(Mutex::new(mock)));
+        call_module_cbor(1, &module, "data.register", minicbor::to_vec(args).unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,13 @@
+use crate::data::{DataIndex, DataMetric};
+use crate::Acknowledgment;
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+pub struct DataRegisterArgs {
+    #[n(0)]
+    pub index: DataIndex,
+    #[n(1)]
+    pub metric: DataMetric,
+}
+
+pub type DataRegisterReturns = Acknowledgment;
@@ -1,3 +1,4 @@
+use many_types::ledger::Symbol;
 use many_types::AttributeRelatedIndex;
 use minicbor::{Decode, Encode};
 use num_bigint::BigInt;
@@ -12,7 +13,7 @@ pub enum DataType {
     Gauge,
 }
 
-#[derive(Clone, Decode, Encode, Debug)]
+#[derive(Clone, Decode, Encode, Debug, PartialEq)]
 pub enum DataValue {
     #[n(0)]
     Counter(#[n(0)] DataValueTypeCounter),
@@ -33,7 +34,7 @@ impl TryFrom<DataValue> for BigInt {
 
 pub type DataValueTypeCounter = u64;
 
-#[derive(Clone, Decode, Encode, Debug)]
+#[derive(Clone, Decode, Encode, Debug, PartialEq)]
 pub enum DataValueTypeGauge {
     #[n(0)]
     Int(#[n(0)] i64),
@@ -81,3 +82,49 @@ pub struct DataInfo {
     #[n(1)]
     pub shortname: String,
 }
+
+/// The values a metric aggregates.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+pub enum DataSource {
+    /// The balances of the accounts in a token.
+    #[n(0)]
+    Balances(#[n(0)] Symbol),
+    /// The events logged by the ledger. Only `Count` applies to them.
+    #[n(1)]
+    Events,
+}
+
+#[derive(Clone, Copy, Debug, Decode, Encode, Eq, PartialEq)]
+pub enum DataAggregation {
+    #[n(0)]
+    Count,
+    #[n(1)]
+    NonZeroCount,
+    #[n(2)]
+    Sum,
+    #[n(3)]
+    Max,
+}
+
+/// A metric registered at runtime, computed by the ledger at every block.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct DataMetric {
+    #[n(0)]
+    pub shortname: String,
+    #[n(1)]
+    pub r#type: DataType,
+    #[n(2)]
+    pub source: DataSource,
+    #[n(3)]
+    pub aggregation: DataAggregation,
+}
+
+impl DataMetric {
+    pub fn info(&self) -> DataInfo {
+        DataInfo {
+            r#type: self.r#type.clone(),
+            shortname: self.shortname.clone(),
+        }
+    }
+}
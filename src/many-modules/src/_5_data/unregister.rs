@@ -0,0 +1,11 @@
+use crate::data::DataIndex;
+use crate::Acknowledgment;
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+pub struct DataUnregisterArgs {
+    #[n(0)]
+    pub index: DataIndex,
+}
+
+pub type DataUnregisterReturns = Acknowledgment;
@@ -5,20 +5,66 @@ use many_macros::many_module;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+mod pull;
+mod schedule;
 mod send;
+mod swap;
 
+pub use pull::*;
+pub use schedule::*;
 pub use send::*;
+pub use swap::*;
 
-#[many_module(name = LedgerCommandsModule, id = 6, namespace = ledger, many_modules_crate = crate)]
+#[many_module(name = LedgerCommandsModule, id = 6, namespace = ledger, many_modules_crate = crate, client = true)]
 #[cfg_attr(test, automock)]
 pub trait LedgerCommandsModuleBackend: Send {
     fn send(&mut self, sender: &Address, args: SendArgs) -> Result<SendReturns, ManyError>;
+    fn schedule(
+        &mut self,
+        sender: &Address,
+        args: ScheduleArgs,
+    ) -> Result<ScheduleReturns, ManyError>;
+    fn cancel_schedule(
+        &mut self,
+        sender: &Address,
+        args: CancelScheduleArgs,
+    ) -> Result<CancelScheduleReturns, ManyError>;
+    fn authorize_pull(
+        &mut self,
+        sender: &Address,
+        args: AuthorizePullArgs,
+    ) -> Result<AuthorizePullReturns, ManyError>;
+    fn revoke_pull(
+        &mut self,
+        sender: &Address,
+        args: RevokePullArgs,
+    ) -> Result<RevokePullReturns, ManyError>;
+    fn pull_payment(
+        &mut self,
+        sender: &Address,
+        args: PullPaymentArgs,
+    ) -> Result<PullPaymentReturns, ManyError>;
+    fn create_swap(
+        &mut self,
+        sender: &Address,
+        args: CreateSwapArgs,
+    ) -> Result<CreateSwapReturns, ManyError>;
+    fn accept_swap(
+        &mut self,
+        sender: &Address,
+        args: AcceptSwapArgs,
+    ) -> Result<AcceptSwapReturns, ManyError>;
+    fn cancel_swap(
+        &mut self,
+        sender: &Address,
+        args: CancelSwapArgs,
+    ) -> Result<CancelSwapReturns, ManyError>;
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::testutils::call_module_cbor;
+    use crate::testutils::{call_module_cbor, ModuleTransport};
     use many_identity::testing::identity;
     use many_identity::Address;
     use many_types::ledger::TokenAmount;
@@ -37,12 +83,13 @@ mod tests {
             symbol: Address::from_str("mqbfbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wiaaaaqnz")
                 .unwrap(),
             memo: None,
+            decimal_amount: None,
         };
         let mut mock = MockLedgerCommandsModuleBackend::new();
         mock.expect_send()
             .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
             .times(1)
-            .returning(|_, _| Ok(SendReturns {}));
+            .returning(|_, _| Ok(SendReturns::default()));
         let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
 
         let _: SendReturns = minicbor::decode(
@@ -50,4 +97,159 @@ mod tests {
         )
         .unwrap();
     }
+
+    #[test]
+    fn schedule() {
+        let data = ScheduleArgs {
+            send: SendArgs {
+                from: None,
+                to: identity(2),
+                amount: TokenAmount::from(512u16),
+                symbol: Address::anonymous(),
+                memo: None,
+                decimal_amount: None,
+            },
+            height: Some(10),
+            time: None,
+        };
+        let mut mock = MockLedgerCommandsModuleBackend::new();
+        mock.expect_schedule()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_, _| {
+                Ok(ScheduleReturns {
+                    token: vec![1, 2, 3].into(),
+                })
+            });
+        let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: ScheduleReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.schedule",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.token.as_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn cancel_schedule() {
+        let data = CancelScheduleArgs {
+            token: vec![1, 2, 3].into(),
+        };
+        let mut mock = MockLedgerCommandsModuleBackend::new();
+        mock.expect_cancel_schedule()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_, _| Ok(CancelScheduleReturns::default()));
+        let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: CancelScheduleReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.cancelSchedule",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn pull_payment() {
+        let data = PullPaymentArgs {
+            account: identity(2),
+            symbol: Address::anonymous(),
+            amount: TokenAmount::from(100u16),
+            memo: None,
+        };
+        let mut mock = MockLedgerCommandsModuleBackend::new();
+        mock.expect_pull_payment()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_, _| Ok(PullPaymentReturns::default()));
+        let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: PullPaymentReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.pullPayment",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn create_swap() {
+        let data = CreateSwapArgs {
+            counterparty: identity(2),
+            give_symbol: Address::anonymous(),
+            give_amount: TokenAmount::from(100u16),
+            want_symbol: identity(3),
+            want_amount: TokenAmount::from(50u16),
+            expiration: many_types::Timestamp::new(1_000).unwrap(),
+            memo: None,
+        };
+        let mut mock = MockLedgerCommandsModuleBackend::new();
+        mock.expect_create_swap()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_, _| {
+                Ok(CreateSwapReturns {
+                    id: vec![1, 2, 3].into(),
+                })
+            });
+        let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: CreateSwapReturns = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "ledger.createSwap",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert_eq!(result.id.as_slice(), &[1, 2, 3]);
+    }
+
+    #[test]
+    fn client() {
+        let data = SendArgs {
+            from: None,
+            to: identity(2),
+            amount: TokenAmount::from(512u16),
+            symbol: Address::anonymous(),
+            memo: None,
+            decimal_amount: None,
+        };
+        let mut mock = MockLedgerCommandsModuleBackend::new();
+        mock.expect_send()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_, _| Ok(SendReturns::default()));
+        mock.expect_cancel_schedule()
+            .times(1)
+            .returning(|_, _| Err(ManyError::unknown("cancel")));
+        let module = super::LedgerCommandsModule::new(Arc::new(Mutex::new(mock)));
+        let client = LedgerCommandsModuleClient::new(ModuleTransport(1, module));
+
+        let result = smol::block_on(client.send(data)).unwrap();
+        assert_eq!(result, SendReturns::default());
+
+        let err = smol::block_on(client.cancel_schedule(CancelScheduleArgs {
+            token: vec![1, 2, 3].into(),
+        }))
+        .unwrap_err();
+        assert_eq!(err, ManyError::unknown("cancel"));
+    }
 }
@@ -0,0 +1,87 @@
+use crate::events::AddressContainer;
+use crate::Acknowledgment;
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::Memo;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
+
+/// Authorize `payee` to pull up to `amount` of `symbol` from `account` every
+/// `period_in_secs` seconds. The account needs the pull payments feature, and
+/// the sender must be one of its owners. Authorizing a payee again replaces
+/// its previous authorization and starts a new period.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AuthorizePullArgs {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub payee: Address,
+
+    #[n(2)]
+    pub symbol: Symbol,
+
+    #[n(3)]
+    pub amount: TokenAmount,
+
+    #[n(4)]
+    pub period_in_secs: u64,
+}
+
+impl AddressContainer for AuthorizePullArgs {
+    fn addresses(&self) -> BTreeSet<Address> {
+        BTreeSet::from([self.account, self.payee])
+    }
+}
+
+pub type AuthorizePullReturns = Acknowledgment;
+
+/// Remove the authorization of `payee` to pull `symbol` from `account`. Either
+/// an owner of the account or the payee can revoke it.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RevokePullArgs {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub payee: Address,
+
+    #[n(2)]
+    pub symbol: Symbol,
+}
+
+impl AddressContainer for RevokePullArgs {
+    fn addresses(&self) -> BTreeSet<Address> {
+        BTreeSet::from([self.account, self.payee])
+    }
+}
+
+pub type RevokePullReturns = Acknowledgment;
+
+/// Pull `amount` of `symbol` from `account` to the sender, within what is
+/// left of the sender's authorization for the current period.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PullPaymentArgs {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub symbol: Symbol,
+
+    #[n(2)]
+    pub amount: TokenAmount,
+
+    #[n(3)]
+    pub memo: Option<Memo>,
+}
+
+impl AddressContainer for PullPaymentArgs {
+    fn addresses(&self) -> BTreeSet<Address> {
+        BTreeSet::from([self.account])
+    }
+}
+
+pub type PullPaymentReturns = Acknowledgment;
@@ -0,0 +1,45 @@
+use crate::events::AddressContainer;
+use crate::ledger::SendArgs;
+use crate::Acknowledgment;
+use many_identity::Address;
+use many_types::Timestamp;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
+
+/// Schedule a send to be executed once the chain reaches a block height or a
+/// block time. Exactly one of `height` or `time` must be specified.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ScheduleArgs {
+    #[n(0)]
+    pub send: SendArgs,
+
+    #[n(1)]
+    pub height: Option<u64>,
+
+    #[n(2)]
+    pub time: Option<Timestamp>,
+}
+
+impl AddressContainer for ScheduleArgs {
+    fn addresses(&self) -> BTreeSet<Address> {
+        self.send.addresses()
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ScheduleReturns {
+    #[n(0)]
+    pub token: ByteVec,
+}
+
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CancelScheduleArgs {
+    #[n(0)]
+    pub token: ByteVec,
+}
+
+pub type CancelScheduleReturns = Acknowledgment;
@@ -1,5 +1,5 @@
 use crate::events::AddressContainer;
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_identity::Address;
 use many_types::{ledger, Memo};
 use minicbor::{Decode, Encode};
@@ -22,9 +22,15 @@ pub struct SendArgs {
 
     #[n(4)]
     pub memo: Option<Memo>,
+
+    /// The amount in whole tokens, e.g. `1.5`. If present, it must be exactly
+    /// `amount` in the decimals of the symbol, and cannot be more precise than
+    /// the symbol.
+    #[n(5)]
+    pub decimal_amount: Option<ledger::DecimalAmount>,
 }
 
-pub type SendReturns = EmptyReturn;
+pub type SendReturns = Acknowledgment;
 
 impl AddressContainer for SendArgs {
     fn addresses(&self) -> BTreeSet<Address> {
@@ -0,0 +1,72 @@
+use crate::events::AddressContainer;
+use crate::Acknowledgment;
+use many_identity::Address;
+use many_types::ledger::{Symbol, TokenAmount};
+use many_types::{Memo, Timestamp};
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeSet;
+
+/// Offer to exchange `give_amount` of `give_symbol` for `want_amount` of
+/// `want_symbol` with `counterparty`, until `expiration`. The funds given are
+/// locked until the counterparty accepts the swap or either side cancels it.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CreateSwapArgs {
+    #[n(0)]
+    pub counterparty: Address,
+
+    #[n(1)]
+    pub give_symbol: Symbol,
+
+    #[n(2)]
+    pub give_amount: TokenAmount,
+
+    #[n(3)]
+    pub want_symbol: Symbol,
+
+    #[n(4)]
+    pub want_amount: TokenAmount,
+
+    #[n(5)]
+    pub expiration: Timestamp,
+
+    #[n(6)]
+    pub memo: Option<Memo>,
+}
+
+impl AddressContainer for CreateSwapArgs {
+    fn addresses(&self) -> BTreeSet<Address> {
+        BTreeSet::from([self.counterparty])
+    }
+}
+
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CreateSwapReturns {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+/// Accept a swap, sending what its creator wants in exchange for what it
+/// gives, in the same transaction. Only the counterparty can accept a swap,
+/// before it expires.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct AcceptSwapArgs {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+pub type AcceptSwapReturns = Acknowledgment;
+
+/// Cancel a swap, returning the locked funds to its creator. Either side can
+/// cancel a swap, expired or not.
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CancelSwapArgs {
+    #[n(0)]
+    pub id: ByteVec,
+}
+
+pub type CancelSwapReturns = Acknowledgment;
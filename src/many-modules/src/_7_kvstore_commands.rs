@@ -5,10 +5,16 @@ use many_macros::many_module;
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+mod batch;
 mod disable;
 mod put;
+mod put_cas;
+mod set_acl;
+pub use batch::*;
 pub use disable::*;
 pub use put::*;
+pub use put_cas::*;
+pub use set_acl::*;
 
 #[many_module(name = KvStoreCommandsModule, id = 7, namespace = kvstore, many_modules_crate = crate)]
 #[cfg_attr(test, automock)]
@@ -16,8 +22,28 @@ pub trait KvStoreCommandsModuleBackend: Send {
     #[many(deny_anonymous)]
     fn put(&mut self, sender: &Address, args: PutArgs) -> Result<PutReturn, ManyError>;
 
+    #[many(deny_anonymous)]
+    fn put_cas(&mut self, sender: &Address, args: PutCasArgs) -> Result<PutCasReturn, ManyError>;
+
     #[many(deny_anonymous)]
     fn disable(&mut self, sender: &Address, args: DisableArgs) -> Result<DisableReturn, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn batch_put(
+        &mut self,
+        sender: &Address,
+        args: BatchPutArgs,
+    ) -> Result<BatchPutReturn, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn batch_delete(
+        &mut self,
+        sender: &Address,
+        args: BatchDeleteArgs,
+    ) -> Result<BatchDeleteReturn, ManyError>;
+
+    #[many(deny_anonymous)]
+    fn set_acl(&mut self, sender: &Address, args: SetAclArgs) -> Result<SetAclReturn, ManyError>;
 }
 
 #[cfg(test)]
@@ -41,7 +67,7 @@ mod tests {
         mock.expect_put()
             .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
             .times(1)
-            .returning(|_sender, _args| Ok(PutReturn {}));
+            .returning(|_sender, _args| Ok(PutReturn::default()));
         let module = super::KvStoreCommandsModule::new(Arc::new(Mutex::new(mock)));
 
         let _: PutReturn = minicbor::decode(
@@ -50,6 +76,104 @@ mod tests {
         .unwrap();
     }
 
+    #[test]
+    fn put_cas() {
+        let data = PutCasArgs {
+            value: ByteVec::from(vec![2]),
+            alternative_owner: None,
+        };
+
+        let mut mock = MockKvStoreCommandsModuleBackend::new();
+        mock.expect_put_cas()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_sender, args| {
+                Ok(PutCasReturn {
+                    key: content_key(&args.value),
+                    references: 1,
+                })
+            });
+        let module = super::KvStoreCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let result: PutCasReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.putCas",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+        assert!(is_content_key(&result.key));
+        assert_eq!(result.key, content_key(&[2]));
+    }
+
+    #[test]
+    fn batch_put() {
+        let data = BatchPutArgs {
+            entries: vec![
+                BatchPutEntry {
+                    key: ByteVec::from(vec![1]),
+                    value: ByteVec::from(vec![2]),
+                },
+                BatchPutEntry {
+                    key: ByteVec::from(vec![3]),
+                    value: ByteVec::from(vec![4]),
+                },
+            ],
+            alternative_owner: None,
+        };
+
+        let mut mock = MockKvStoreCommandsModuleBackend::new();
+        mock.expect_batch_put()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_sender, _args| Ok(BatchPutReturn::default()));
+        let module = super::KvStoreCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: BatchPutReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.batchPut",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn set_acl() {
+        let data = SetAclArgs {
+            key: ByteVec::from(vec![1]),
+            acl: crate::kvstore::KeyAcl {
+                read: crate::kvstore::ReadAccess::Restricted,
+                writers: [identity(2)].into(),
+            },
+            alternative_owner: None,
+        };
+
+        let mut mock = MockKvStoreCommandsModuleBackend::new();
+        mock.expect_set_acl()
+            .with(predicate::eq(identity(1)), predicate::eq(data.clone()))
+            .times(1)
+            .returning(|_sender, _args| Ok(SetAclReturn::default()));
+        let module = super::KvStoreCommandsModule::new(Arc::new(Mutex::new(mock)));
+
+        let _: SetAclReturn = minicbor::decode(
+            &call_module_cbor(
+                1,
+                &module,
+                "kvstore.setAcl",
+                minicbor::to_vec(data).unwrap(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    }
+
     #[test]
     fn disable() {
         let data = DisableArgs {
@@ -65,7 +189,7 @@ mod tests {
                 predicate::eq(data.clone()),
             )
             .times(1)
-            .returning(|_sender, _args| Ok(DisableReturn {}));
+            .returning(|_sender, _args| Ok(DisableReturn::default()));
         let module = super::KvStoreCommandsModule::new(Arc::new(Mutex::new(mock)));
 
         let _: DisableReturn = minicbor::decode(
@@ -0,0 +1,49 @@
+use super::put::{decode_key, decode_value};
+use crate::Acknowledgment;
+use many_error::Reason;
+use many_identity::Address;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BatchPutEntry {
+    #[n(0)]
+    #[cbor(decode_with = "decode_key")]
+    pub key: ByteVec,
+
+    #[n(1)]
+    #[cbor(decode_with = "decode_value")]
+    pub value: ByteVec,
+}
+
+/// Put all the entries, or none of them if any of them cannot be put. Keys
+/// must be unique.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BatchPutArgs {
+    #[n(0)]
+    pub entries: Vec<BatchPutEntry>,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+pub type BatchPutReturn = Acknowledgment;
+
+/// Disable all the keys, like `kvstore.disable`, or none of them if any of
+/// them cannot be disabled. Keys must be unique.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct BatchDeleteArgs {
+    #[n(0)]
+    pub keys: Vec<ByteVec>,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+
+    #[n(2)]
+    pub reason: Option<Reason<u64>>,
+}
+
+pub type BatchDeleteReturn = Acknowledgment;
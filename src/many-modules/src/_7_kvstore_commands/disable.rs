@@ -1,4 +1,4 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_error::Reason;
 use many_identity::Address;
 use minicbor::bytes::ByteVec;
@@ -17,4 +17,4 @@ pub struct DisableArgs {
     pub reason: Option<Reason<u64>>,
 }
 
-pub type DisableReturn = EmptyReturn;
+pub type DisableReturn = Acknowledgment;
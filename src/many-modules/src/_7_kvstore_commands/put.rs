@@ -1,4 +1,4 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_identity::Address;
 use minicbor::bytes::ByteVec;
 use minicbor::data::Type;
@@ -25,7 +25,10 @@ pub struct PutArgs {
 }
 
 /// Data decoder. Check if the key is less than or equal to the maximum allowed size
-fn decode_key<C>(d: &mut minicbor::Decoder, _: &mut C) -> Result<ByteVec, minicbor::decode::Error> {
+pub(super) fn decode_key<C>(
+    d: &mut minicbor::Decoder,
+    _: &mut C,
+) -> Result<ByteVec, minicbor::decode::Error> {
     match d.datatype()? {
         Type::Bytes => {
             let data = d.bytes()?;
@@ -41,7 +44,7 @@ fn decode_key<C>(d: &mut minicbor::Decoder, _: &mut C) -> Result<ByteVec, minicb
 }
 
 /// Data decoder. Check if the value is less than or equal to the maximum allowed size
-fn decode_value<C>(
+pub(super) fn decode_value<C>(
     d: &mut minicbor::Decoder,
     _: &mut C,
 ) -> Result<ByteVec, minicbor::decode::Error> {
@@ -112,4 +115,4 @@ mod tests {
     }
 }
 
-pub type PutReturn = EmptyReturn;
+pub type PutReturn = Acknowledgment;
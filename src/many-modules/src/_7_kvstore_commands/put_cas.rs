@@ -0,0 +1,46 @@
+use super::put::decode_value;
+use many_identity::Address;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+use sha3::{Digest, Sha3_256};
+
+/// The prefix of the keys of content-addressed values. Those keys cannot be
+/// written with `kvstore.put`.
+pub const CAS_KEY_PREFIX: &[u8] = b"/cas/";
+
+/// The key of a content-addressed value, the SHA3-256 of the value after
+/// [`CAS_KEY_PREFIX`].
+pub fn content_key(value: &[u8]) -> ByteVec {
+    [CAS_KEY_PREFIX, Sha3_256::digest(value).as_slice()]
+        .concat()
+        .into()
+}
+
+pub fn is_content_key(key: &[u8]) -> bool {
+    key.starts_with(CAS_KEY_PREFIX)
+}
+
+/// Put a value at the key derived from its content. A value put by several
+/// owners is stored once, and stays readable until all of them disabled its
+/// key.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PutCasArgs {
+    #[n(0)]
+    #[cbor(decode_with = "decode_value")]
+    pub value: ByteVec,
+
+    #[n(1)]
+    pub alternative_owner: Option<Address>,
+}
+
+#[derive(Clone, Debug, Default, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PutCasReturn {
+    #[n(0)]
+    pub key: ByteVec,
+
+    /// The number of owners referencing the value.
+    #[n(1)]
+    pub references: u64,
+}
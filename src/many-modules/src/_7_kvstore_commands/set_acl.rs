@@ -0,0 +1,22 @@
+use crate::kvstore::KeyAcl;
+use crate::Acknowledgment;
+use many_identity::Address;
+use minicbor::bytes::ByteVec;
+use minicbor::{Decode, Encode};
+
+/// Set the ACL of a key, replacing its previous ACL. Only the owner of the
+/// key can set it.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct SetAclArgs {
+    #[n(0)]
+    pub key: ByteVec,
+
+    #[n(1)]
+    pub acl: KeyAcl,
+
+    #[n(2)]
+    pub alternative_owner: Option<Address>,
+}
+
+pub type SetAclReturn = Acknowledgment;
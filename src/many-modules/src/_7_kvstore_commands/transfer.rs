@@ -1,4 +1,4 @@
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_identity::Address;
 use minicbor::bytes::ByteVec;
 use minicbor::{Decode, Encode};
@@ -16,4 +16,4 @@ pub struct TransferArgs {
     pub new_owner: Address,
 }
 
-pub type TransferReturn = EmptyReturn;
+pub type TransferReturn = Acknowledgment;
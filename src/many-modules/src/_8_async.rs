@@ -1,6 +1,6 @@
 use crate::ResponseMessage;
 use coset::{CborSerializable, CoseSign1};
-use many_error::ManyError;
+use many_error::{define_attribute_many_error, ManyError};
 use many_identity::Address;
 use many_macros::many_module;
 use minicbor::data::Type;
@@ -10,6 +10,14 @@ use minicbor::{Decode, Decoder, Encode, Encoder};
 #[cfg(test)]
 use mockall::{automock, predicate::*};
 
+define_attribute_many_error!(
+    attribute 8 => {
+        1: pub fn unknown_token(token) => "Unknown async token {token}.",
+        2: pub fn not_done(token) => "The response of async token {token} is not ready.",
+        3: pub fn expired_token(token) => "Async token {token} expired.",
+    }
+);
+
 /// An AsyncToken which is returned when the server does not have an immediate
 /// response.
 #[derive(Clone, Eq, PartialEq)]
@@ -128,6 +136,23 @@ pub enum StatusReturn {
 }
 
 impl StatusReturn {
+    /// The response of a done request, or an error saying why there is none.
+    pub fn into_response(self, token: &AsyncToken) -> Result<ResponseMessage, ManyError> {
+        let token = || hex::encode(token);
+        match self {
+            StatusReturn::Unknown => Err(unknown_token(token())),
+            StatusReturn::Queued | StatusReturn::Processing => Err(not_done(token())),
+            StatusReturn::Expired => Err(expired_token(token())),
+            StatusReturn::Done { response } => ResponseMessage::from_bytes(
+                response
+                    .payload
+                    .as_ref()
+                    .ok_or_else(|| ManyError::deserialization_error("Empty payload."))?,
+            )
+            .map_err(ManyError::deserialization_error),
+        }
+    }
+
     fn variant(&self) -> u8 {
         match self {
             StatusReturn::Unknown => 0,
@@ -227,10 +252,47 @@ impl<'b, C> Decode<'b, C> for StatusReturn {
     }
 }
 
+#[derive(Debug, Clone, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct ResultArgs {
+    #[n(0)]
+    pub token: AsyncToken,
+}
+
+/// The result of a deferred request, as it would have been returned by the
+/// request itself. Its CBOR is the data of the deferred response.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResultReturn(pub Vec<u8>);
+
+impl ResultReturn {
+    /// The data of a response, failing with its error if it has one.
+    pub fn from_response(response: ResponseMessage) -> Result<Self, ManyError> {
+        response.data.map(Self)
+    }
+}
+
+impl<C> Encode<C> for ResultReturn {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>, _: &mut C) -> Result<(), Error<W::Error>> {
+        e.writer_mut().write_all(&self.0).map_err(Error::write)
+    }
+}
+
+impl<'b, C> Decode<'b, C> for ResultReturn {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        let start = d.position();
+        d.skip()?;
+        Ok(Self(d.input()[start..d.position()].to_vec()))
+    }
+}
+
 #[many_module(name = AsyncModule, id = 8, namespace = async, many_modules_crate = crate)]
 #[cfg_attr(test, automock)]
 pub trait AsyncModuleBackend: Send {
     fn status(&self, sender: &Address, args: StatusArgs) -> Result<StatusReturn, ManyError>;
+
+    /// The result of a deferred request once it is done. Fails with the
+    /// error of the request, or if it is not done.
+    fn result(&self, sender: &Address, args: ResultArgs) -> Result<ResultReturn, ManyError>;
 }
 
 #[cfg(test)]
@@ -285,6 +347,60 @@ mod tests {
         }
     }
 
+    #[test]
+    fn result() {
+        let data = ResultArgs {
+            token: AsyncToken::from(vec![11, 12, 13]),
+        };
+        let result = ResultReturn(minicbor::to_vec(("hello", 42u8)).unwrap());
+        let mut mock = MockAsyncModuleBackend::new();
+        mock.expect_result()
+            .with(
+                predicate::eq(many_identity::testing::identity(1)),
+                predicate::eq(data.clone()),
+            )
+            .times(1)
+            .return_const(Ok(result.clone()));
+        let module = super::AsyncModule::new(Arc::new(Mutex::new(mock)));
+
+        let bytes =
+            call_module_cbor(1, &module, "async.result", minicbor::to_vec(data).unwrap()).unwrap();
+        assert_eq!(bytes, result.0);
+        let (text, n): (String, u8) = minicbor::decode(&bytes).unwrap();
+        assert_eq!((text.as_str(), n), ("hello", 42));
+    }
+
+    #[test]
+    fn into_response() {
+        let token = AsyncToken::from(vec![1]);
+        assert_eq!(
+            StatusReturn::Processing
+                .into_response(&token)
+                .unwrap_err()
+                .code(),
+            not_done("").code()
+        );
+        assert_eq!(
+            StatusReturn::Expired
+                .into_response(&token)
+                .unwrap_err()
+                .code(),
+            expired_token("").code()
+        );
+
+        let response = ResponseMessage {
+            data: Ok(vec![1, 2, 3]),
+            ..Default::default()
+        };
+        let done = StatusReturn::Done {
+            response: Box::new(
+                encode_cose_sign1_from_response(response, &AnonymousIdentity).unwrap(),
+            ),
+        };
+        let result = ResultReturn::from_response(done.into_response(&token).unwrap()).unwrap();
+        assert_eq!(result.0, vec![1, 2, 3]);
+    }
+
     #[test]
     fn async_attr() {
         let v = vec![1, 2, 3, 4];
@@ -1,10 +1,11 @@
 use crate::events::AddressContainer;
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_error::{ManyError, Reason};
 use many_identity::Address;
 use many_macros::many_module;
 use many_protocol::context::Context;
 use many_types::{Either, VecOrSingle};
+use minicbor::bytes::ByteVec;
 use minicbor::{decode, encode, Decode, Decoder, Encode, Encoder};
 use std::collections::{BTreeMap, BTreeSet};
 use std::str::FromStr;
@@ -41,6 +42,7 @@ pub enum Role {
     CanTokensUpdate,
     CanTokensAddExtendedInfo,
     CanTokensRemoveExtendedInfo,
+    Observer,
 }
 
 impl PartialEq<&str> for Role {
@@ -195,6 +197,7 @@ impl Account {
             description,
             roles,
             features,
+            ..
         }: CreateArgs,
     ) -> Self {
         // Add the sender as owner role.
@@ -283,6 +286,29 @@ impl Account {
     pub fn feature<F: features::TryCreateFeature>(&self) -> Option<F> {
         self.features.get::<F>().ok()
     }
+
+    /// Whether the method ACL of the account allows an ID to call a method
+    /// on its behalf.
+    pub fn allows_method(&self, id: &Address, method: &str) -> bool {
+        self.feature::<features::method_acl::MethodAclAccountFeature>()
+            .map_or(false, |acl| acl.allows(id, method))
+    }
+}
+
+/// Verify that an ID can call a method on behalf of an account, either
+/// because the method ACL of the account allows it, or because it has one
+/// of the roles. Modules acting on behalf of accounts should use this
+/// instead of checking the roles themselves.
+pub fn verify_account_role<R: TryInto<Role> + std::fmt::Display + Copy>(
+    account: &Account,
+    id: &Address,
+    method: &str,
+    roles: impl IntoIterator<Item = R>,
+) -> Result<(), ManyError> {
+    if account.allows_method(id, method) {
+        return Ok(());
+    }
+    account.needs_role(id, roles)
 }
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
@@ -296,8 +322,18 @@ pub struct CreateArgs {
 
     #[n(2)]
     pub features: features::FeatureSet,
+
+    /// A value chosen by the client to make the creation idempotent. Creating
+    /// an account again with the same nonce returns the account created the
+    /// first time, so wallets can retry a creation without creating
+    /// duplicates. Nonces are scoped to the sender.
+    #[n(3)]
+    pub nonce: Option<ByteVec>,
 }
 
+/// The longest nonce of [`CreateArgs`], in bytes.
+pub const MAX_CREATE_NONCE_SIZE: usize = 64;
+
 impl AddressContainer for CreateArgs {
     fn addresses(&self) -> BTreeSet<Address> {
         self.roles.addresses()
@@ -327,7 +363,7 @@ impl AddressContainer for SetDescriptionArgs {
     }
 }
 
-pub type SetDescriptionReturn = EmptyReturn;
+pub type SetDescriptionReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -378,7 +414,7 @@ impl AddressContainer for AddRolesArgs {
     }
 }
 
-pub type AddRolesReturn = EmptyReturn;
+pub type AddRolesReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -398,7 +434,7 @@ impl AddressContainer for RemoveRolesArgs {
     }
 }
 
-pub type RemoveRolesReturn = EmptyReturn;
+pub type RemoveRolesReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -436,7 +472,7 @@ impl AddressContainer for DisableArgs {
     }
 }
 
-pub type DisableReturn = EmptyReturn;
+pub type DisableReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -459,7 +495,7 @@ impl AddressContainer for AddFeaturesArgs {
     }
 }
 
-pub type AddFeaturesReturn = EmptyReturn;
+pub type AddFeaturesReturn = Acknowledgment;
 
 #[many_module(name = AccountModule, id = 9, namespace = account, many_modules_crate = crate)]
 #[cfg_attr(test, mockall::automock)]
@@ -552,7 +588,7 @@ mod module_tests {
                     .get_mut(&args.account)
                     .ok_or_else(|| errors::unknown_account(args.account))?;
                 account.description = Some(args.description);
-                Ok(EmptyReturn)
+                Ok(Acknowledgment::default())
             }
         });
         mock.expect_info().returning({
@@ -590,7 +626,7 @@ mod module_tests {
                 account_map.needs_role(&args.account, sender, [Role::Owner])?;
                 account_map.remove(&args.account).map_or_else(
                     || Err(errors::unknown_account(args.account)),
-                    |_| Ok(EmptyReturn),
+                    |_| Ok(Acknowledgment::default()),
                 )
             }
         });
@@ -714,6 +750,7 @@ fn needs_role() {
             description: None,
             roles: None,
             features: Default::default(),
+            nonce: None,
         },
     );
     assert!(account.needs_role(&owner, [Role::Owner]).is_ok());
@@ -734,6 +771,7 @@ fn remove_empty_role() {
             description: None,
             roles: None,
             features: Default::default(),
+            nonce: None,
         },
     );
     assert!(!account.has_role(&identity(1), Role::CanMultisigSubmit));
@@ -743,3 +781,29 @@ fn remove_empty_role() {
     account.remove_role(&identity(1), Role::CanMultisigSubmit);
     assert!(!account.roles.contains_key(&identity(1)));
 }
+
+#[test]
+fn verify_method_acl() {
+    use features::method_acl::MethodAclAccountFeature;
+    use features::FeatureInfo;
+    use many_identity::testing::identity;
+
+    let owner = identity(0);
+    let acl = MethodAclAccountFeature::new(BTreeMap::from([(
+        identity(1),
+        BTreeSet::from(["kvstore.put".to_string()]),
+    )]));
+    let account = Account::create(
+        &owner,
+        CreateArgs {
+            description: None,
+            roles: None,
+            features: features::FeatureSet::from_iter([acl.as_feature()]),
+            nonce: None,
+        },
+    );
+
+    assert!(verify_account_role(&account, &identity(1), "kvstore.put", [Role::Owner]).is_ok());
+    assert!(verify_account_role(&account, &identity(1), "kvstore.disable", [Role::Owner]).is_err());
+    assert!(verify_account_role(&account, &owner, "kvstore.disable", [Role::Owner]).is_ok());
+}
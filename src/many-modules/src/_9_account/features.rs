@@ -7,7 +7,10 @@ use std::collections::BTreeSet;
 
 pub mod kvstore;
 pub mod ledger;
+pub mod method_acl;
 pub mod multisig;
+pub mod observers;
+pub mod pull_payments;
 pub mod tokens;
 
 pub type FeatureId = u32;
@@ -0,0 +1,120 @@
+/// See feature `_6_account_method_acl`.
+use crate::account::features::{Feature, FeatureId, TryCreateFeature};
+use crate::account::Role;
+use many_error::ManyError;
+use many_identity::Address;
+use many_types::cbor::CborAny;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Whether a method ACL pattern matches a method name. A pattern is either
+/// a method name (`kvstore.put`), all the methods of a namespace
+/// (`kvstore.*`) or all methods (`*`).
+pub fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some("") => true,
+        Some(namespace) if namespace.ends_with('.') => method.starts_with(namespace),
+        _ => pattern == method,
+    }
+}
+
+fn is_valid_pattern(pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(namespace) => namespace.is_empty() || namespace.ends_with('.'),
+        None => !pattern.is_empty() && !pattern.contains('*'),
+    }
+}
+
+/// Grants identities the permission to call some methods on behalf of the
+/// account, without giving them a role. The argument of the feature maps
+/// each identity to the method patterns it can call.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct MethodAclAccountFeature {
+    pub acl: BTreeMap<Address, BTreeSet<String>>,
+}
+
+impl MethodAclAccountFeature {
+    pub fn new(acl: BTreeMap<Address, BTreeSet<String>>) -> Self {
+        Self { acl }
+    }
+
+    /// Whether `id` can call `method` on behalf of the account.
+    pub fn allows(&self, id: &Address, method: &str) -> bool {
+        self.acl.get(id).map_or(false, |patterns| {
+            patterns.iter().any(|p| method_matches(p, method))
+        })
+    }
+}
+
+impl TryCreateFeature for MethodAclAccountFeature {
+    const ID: FeatureId = 6;
+
+    fn try_create(f: &Feature) -> Result<Self, ManyError> {
+        let [argument] = f.arguments().as_slice() else {
+            return Err(ManyError::invalid_attribute_arguments());
+        };
+
+        // Round-trip the argument to decode the addresses.
+        let bytes = minicbor::to_vec(argument).map_err(ManyError::serialization_error)?;
+        let acl: BTreeMap<Address, BTreeSet<String>> =
+            minicbor::decode(&bytes).map_err(|_| ManyError::invalid_attribute_arguments())?;
+        if !acl.values().flatten().all(|p| is_valid_pattern(p)) {
+            return Err(ManyError::invalid_attribute_arguments());
+        }
+
+        Ok(Self { acl })
+    }
+}
+
+impl super::FeatureInfo for MethodAclAccountFeature {
+    fn as_feature(&self) -> Feature {
+        let argument = minicbor::to_vec(&self.acl)
+            .ok()
+            .and_then(|bytes| minicbor::decode::<CborAny>(&bytes).ok())
+            .unwrap_or(CborAny::Map(BTreeMap::new()));
+        Feature::with_id(Self::ID).with_argument(argument)
+    }
+
+    fn roles() -> BTreeSet<Role> {
+        BTreeSet::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::features::FeatureInfo;
+    use many_identity::testing::identity;
+
+    #[test]
+    fn patterns() {
+        assert!(method_matches("kvstore.put", "kvstore.put"));
+        assert!(!method_matches("kvstore.put", "kvstore.putCas"));
+        assert!(method_matches("kvstore.*", "kvstore.disable"));
+        assert!(!method_matches("kvstore.*", "kvstoreX.put"));
+        assert!(method_matches("*", "ledger.send"));
+
+        for pattern in ["kvstore.put", "kvstore.*", "*"] {
+            assert!(is_valid_pattern(pattern));
+        }
+        for pattern in ["", "kvstore*", "kv*.put", "*.put"] {
+            assert!(!is_valid_pattern(pattern));
+        }
+    }
+
+    #[test]
+    fn feature_round_trip() {
+        let feature = MethodAclAccountFeature::new(BTreeMap::from([(
+            identity(1),
+            BTreeSet::from(["kvstore.put".to_string(), "ledger.*".to_string()]),
+        )]));
+        let parsed = MethodAclAccountFeature::try_create(&feature.as_feature()).unwrap();
+        assert_eq!(parsed, feature);
+        assert!(parsed.allows(&identity(1), "ledger.send"));
+        assert!(!parsed.allows(&identity(1), "kvstore.disable"));
+        assert!(!parsed.allows(&identity(2), "kvstore.put"));
+
+        let invalid = Feature::with_id(MethodAclAccountFeature::ID)
+            .with_argument(CborAny::String("kvstore.put".to_string()));
+        assert!(MethodAclAccountFeature::try_create(&invalid).is_err());
+    }
+}
@@ -2,7 +2,7 @@ use crate::account::features::{Feature, FeatureId, TryCreateFeature};
 use crate::account::Role;
 use crate::events::{AccountMultisigTransaction, AddressContainer};
 use crate::ledger::SendArgs;
-use crate::EmptyReturn;
+use crate::Acknowledgment;
 use many_error::ManyError;
 use many_identity::Address;
 use many_macros::many_module;
@@ -24,6 +24,10 @@ pub mod errors {
             102: pub fn transaction_type_unsupported() => "This transaction is not supported.",
             103: pub fn cannot_execute_transaction() => "This transaction cannot be executed yet.",
             104: pub fn transaction_expired_or_withdrawn() => "This transaction expired or was withdrawn.",
+            105: pub fn comment_cannot_be_found() => "The comment replied to cannot be found.",
+            106: pub fn invalid_delegation_window() => "The delegation needs to end after it starts, and in the future.",
+            107: pub fn cannot_delegate_to_self() => "An address cannot delegate to itself.",
+            108: pub fn delegation_cannot_be_found() => "The delegation cannot be found.",
         }
     );
 }
@@ -166,6 +170,7 @@ impl SubmitTransactionArgs {
                 symbol,
                 amount,
                 memo,
+                decimal_amount: None,
             })),
             threshold: None,
             timeout_in_secs: None,
@@ -278,6 +283,30 @@ pub struct InfoReturn {
 
     #[n(9)]
     pub memo: Option<Memo>,
+
+    /// The comments of the approvers, in the order they were made. Absent
+    /// until the first comment.
+    #[n(10)]
+    pub comments: Option<Vec<MultisigComment>>,
+}
+
+/// A comment on a multisig transaction. Comments are identified by their
+/// index in the list of comments of the transaction.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct MultisigComment {
+    #[n(0)]
+    pub author: Address,
+
+    #[n(1)]
+    pub timestamp: Timestamp,
+
+    #[n(2)]
+    pub memo: Memo,
+
+    /// The index of the comment this one replies to, if any.
+    #[n(3)]
+    pub reply_to: Option<u64>,
 }
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
@@ -302,7 +331,7 @@ impl AddressContainer for SetDefaultsArgs {
     }
 }
 
-pub type SetDefaultsReturn = EmptyReturn;
+pub type SetDefaultsReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -311,7 +340,7 @@ pub struct ApproveArgs {
     pub token: ByteVec,
 }
 
-pub type ApproveReturn = EmptyReturn;
+pub type ApproveReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -320,7 +349,7 @@ pub struct RevokeArgs {
     pub token: ByteVec,
 }
 
-pub type RevokeReturn = EmptyReturn;
+pub type RevokeReturn = Acknowledgment;
 
 #[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
 #[cbor(map)]
@@ -336,7 +365,103 @@ pub struct WithdrawArgs {
     pub token: ByteVec,
 }
 
-pub type WithdrawReturn = EmptyReturn;
+pub type WithdrawReturn = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CommentArgs {
+    #[n(0)]
+    pub token: ByteVec,
+
+    #[n(1)]
+    pub memo: Memo,
+
+    #[n(2)]
+    pub reply_to: Option<u64>,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct CommentReturn {
+    #[n(0)]
+    pub id: u64,
+}
+
+/// The approval power of an approver of an account, delegated to another
+/// address for a time window. The delegate can approve, revoke and, if the
+/// delegator is an owner, execute the transactions of the account on behalf
+/// of the delegator.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct Delegation {
+    #[n(0)]
+    pub delegator: Address,
+
+    #[n(1)]
+    pub delegate: Address,
+
+    /// When the delegation starts. It starts when it is made if unset.
+    #[n(2)]
+    pub start: Option<Timestamp>,
+
+    /// When the delegation ends, excluded.
+    #[n(3)]
+    pub end: Timestamp,
+}
+
+impl Delegation {
+    pub fn is_active(&self, now: Timestamp) -> bool {
+        self.start.map_or(true, |start| start <= now) && now < self.end
+    }
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct DelegateArgs {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub delegate: Address,
+
+    #[n(2)]
+    pub start: Option<Timestamp>,
+
+    #[n(3)]
+    pub end: Timestamp,
+
+    #[n(4)]
+    pub memo: Option<Memo>,
+}
+
+pub type DelegateReturn = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct RevokeDelegationArgs {
+    #[n(0)]
+    pub account: Address,
+
+    #[n(1)]
+    pub memo: Option<Memo>,
+}
+
+pub type RevokeDelegationReturn = Acknowledgment;
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct DelegationsArgs {
+    #[n(0)]
+    pub account: Address,
+}
+
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct DelegationsReturn {
+    /// The delegations of the account that did not end, by delegator.
+    #[n(0)]
+    pub delegations: Vec<Delegation>,
+}
 
 #[many_module(name = AccountMultisigModule, namespace = account, many_modules_crate = crate)]
 pub trait AccountMultisigModuleBackend: Send {
@@ -371,4 +496,30 @@ pub trait AccountMultisigModuleBackend: Send {
         sender: &Address,
         args: WithdrawArgs,
     ) -> Result<WithdrawReturn, ManyError>;
+    fn multisig_comment(
+        &mut self,
+        sender: &Address,
+        args: CommentArgs,
+    ) -> Result<CommentReturn, ManyError>;
+
+    /// Delegate the approval power of the sender for an account, replacing
+    /// its previous delegation for the account.
+    fn delegate(
+        &mut self,
+        sender: &Address,
+        args: DelegateArgs,
+    ) -> Result<DelegateReturn, ManyError>;
+
+    /// Revoke the delegation of the sender for an account.
+    fn revoke_delegation(
+        &mut self,
+        sender: &Address,
+        args: RevokeDelegationArgs,
+    ) -> Result<RevokeDelegationReturn, ManyError>;
+
+    fn delegations(
+        &self,
+        sender: &Address,
+        args: DelegationsArgs,
+    ) -> Result<DelegationsReturn, ManyError>;
 }
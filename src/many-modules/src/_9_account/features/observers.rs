@@ -0,0 +1,29 @@
+/// See feature `_5_account_observers`.
+use crate::account::features::{Feature, FeatureId, TryCreateFeature};
+use crate::account::Role;
+use many_error::ManyError;
+use std::collections::BTreeSet;
+
+/// Restricts the queries about the account (its info and roles, balances,
+/// events and multisig transactions) to the identities that have a role on
+/// it. The `observer` role grants access to those queries without allowing
+/// any command, e.g. for auditors.
+pub struct ObserversAccountFeature;
+
+impl TryCreateFeature for ObserversAccountFeature {
+    const ID: FeatureId = 5;
+
+    fn try_create(_: &Feature) -> Result<Self, ManyError> {
+        Ok(Self)
+    }
+}
+
+impl super::FeatureInfo for ObserversAccountFeature {
+    fn as_feature(&self) -> Feature {
+        Feature::with_id(Self::ID)
+    }
+
+    fn roles() -> BTreeSet<Role> {
+        BTreeSet::from([Role::Observer])
+    }
+}
@@ -0,0 +1,28 @@
+/// See feature `_4_account_pull_payments`.
+use crate::account::features::{Feature, FeatureId, TryCreateFeature};
+use crate::account::Role;
+use many_error::ManyError;
+use std::collections::BTreeSet;
+
+/// Allows the owners of the account to authorize payees to pull a limited
+/// amount of tokens from the account every period, e.g. for subscriptions.
+/// Payees do not need any role on the account.
+pub struct PullPaymentsAccountFeature;
+
+impl TryCreateFeature for PullPaymentsAccountFeature {
+    const ID: FeatureId = 4;
+
+    fn try_create(_: &Feature) -> Result<Self, ManyError> {
+        Ok(Self)
+    }
+}
+
+impl super::FeatureInfo for PullPaymentsAccountFeature {
+    fn as_feature(&self) -> Feature {
+        Feature::with_id(Self::ID)
+    }
+
+    fn roles() -> BTreeSet<Role> {
+        BTreeSet::new()
+    }
+}
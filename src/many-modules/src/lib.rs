@@ -5,6 +5,8 @@ use async_trait::async_trait;
 use many_error::ManyError;
 use many_protocol::{RequestMessage, ResponseMessage};
 use many_types::attributes::Attribute;
+use minicbor::bytes::ByteVec;
+use minicbor::data::Type;
 use minicbor::encode::{Error, Write};
 use minicbor::{Decoder, Encoder};
 use std::fmt::Debug;
@@ -26,17 +28,26 @@ macro_rules! reexport_module {
 reexport_module!(
     base: _0_base;
     blockchain: _1_blockchain;
-    ledger: _2_ledger + _6_ledger_commands + _11_ledger_tokens + _12_ledger_mintburn;
-    events: _4_events;
+    ledger: _2_ledger + _6_ledger_commands + _11_ledger_tokens + _12_ledger_mintburn + _20_ledger_curve;
+    events: _4_events + _21_events_proof;
     data: _5_data;
     kvstore: _3_kvstore + _7_kvstore_commands + _13_kvstore_transfer;
     r#async: _8_async;
     account: _9_account;
     compute: _15_compute;
     web: _16_web + _17_web_commands;
+    bundle: _18_bundle;
+    names: _19_names;
+    escrow: _22_escrow;
+    bridge: _23_bridge;
+    migrations: _24_migrations;
+    webauthn: _25_webauthn;
     abci_backend: _1000_abci_backend;
     abci_frontend: _1001_abci_frontend;
     idstore: _1002_idstore;
+    consensus: _1003_consensus;
+    random: _1004_random;
+    debug: _1005_debug;
 );
 
 /// The specification says that some methods returns nothing (e.g. void or unit).
@@ -62,6 +73,102 @@ impl<'b, C> minicbor::Decode<'b, C> for EmptyReturn {
     }
 }
 
+/// The return value of commands that have no other result. It gives clients
+/// enough information to confirm and look up the effects of their command
+/// without a follow-up query.
+///
+/// An empty acknowledgment is encoded as a null, like [EmptyReturn], so servers
+/// that predate acknowledgments are compatible with it. Clients that still
+/// decode these returns as [EmptyReturn] will skip the acknowledgment.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Acknowledgment {
+    /// The hash of the request, if known by the server.
+    pub request_hash: Option<ByteVec>,
+
+    /// The height of the block this command is expected to be part of.
+    pub height: Option<u64>,
+
+    /// The IDs of the events logged by this command.
+    pub events: Vec<events::EventId>,
+}
+
+impl Acknowledgment {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_request_hash(mut self, hash: impl Into<ByteVec>) -> Self {
+        self.request_hash = Some(hash.into());
+        self
+    }
+
+    pub fn with_height(mut self, height: u64) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    pub fn with_events(mut self, events: impl IntoIterator<Item = events::EventId>) -> Self {
+        self.events.extend(events);
+        self
+    }
+}
+
+impl<C> minicbor::Encode<C> for Acknowledgment {
+    fn encode<W: Write>(&self, e: &mut Encoder<W>, _: &mut C) -> Result<(), Error<W::Error>> {
+        // An empty acknowledgment is encoded the same way as an EmptyReturn.
+        if self == &Self::default() {
+            e.null()?;
+            return Ok(());
+        }
+
+        let len = u64::from(self.request_hash.is_some())
+            + u64::from(self.height.is_some())
+            + u64::from(!self.events.is_empty());
+        e.map(len)?;
+        if let Some(hash) = &self.request_hash {
+            e.u8(0)?.encode(hash)?;
+        }
+        if let Some(height) = self.height {
+            e.u8(1)?.u64(height)?;
+        }
+        if !self.events.is_empty() {
+            e.u8(2)?.encode(&self.events)?;
+        }
+        Ok(())
+    }
+}
+
+impl<'b, C> minicbor::Decode<'b, C> for Acknowledgment {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        let mut ack = Self::default();
+        if d.datatype()? == Type::Null {
+            d.skip()?;
+            return Ok(ack);
+        }
+
+        let len = d.map()?;
+        let mut i = 0;
+        loop {
+            match len {
+                Some(len) if i >= len => break,
+                None if d.datatype()? == Type::Break => {
+                    d.skip()?;
+                    break;
+                }
+                _ => {}
+            }
+            match d.u8()? {
+                0 => ack.request_hash = Some(d.decode()?),
+                1 => ack.height = Some(d.u64()?),
+                2 => ack.events = d.decode()?,
+                _ => d.skip()?,
+            }
+            i += 1;
+        }
+        Ok(ack)
+    }
+}
+
 #[derive(Debug, Eq, PartialEq)]
 pub struct EmptyArg;
 
@@ -91,6 +198,9 @@ pub struct ManyModuleInfo {
 
     /// The endpoints that this module exports.
     pub endpoints: Vec<String>,
+
+    /// The versions of the endpoints supported by this module.
+    pub version: many_protocol::ModuleVersion,
 }
 
 /// A module ran by an many-server server.
@@ -114,13 +224,37 @@ pub trait ManyModule: Sync + Send + Debug {
     async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError>;
 }
 
+/// A way to call the endpoints of a MANY server, used by the typed clients
+/// generated for modules declared with `#[many_module(client = true)]`.
+#[async_trait]
+pub trait ModuleClientTransport: Sync + Send {
+    /// Call an endpoint with its CBOR encoded argument, and return the CBOR
+    /// encoded result.
+    async fn call_endpoint(&self, method: &str, argument: Vec<u8>) -> Result<Vec<u8>, ManyError>;
+}
+
 #[cfg(test)]
 pub(crate) mod testutils {
-    use crate::ManyModule;
+    use crate::{ManyModule, ModuleClientTransport};
+    use async_trait::async_trait;
     use many_error::ManyError;
     use many_identity::testing::identity;
     use many_protocol::RequestMessage;
 
+    /// A client transport calling a module directly, as `identity(key)`.
+    pub struct ModuleTransport<M: ManyModule>(pub u32, pub M);
+
+    #[async_trait]
+    impl<M: ManyModule> ModuleClientTransport for ModuleTransport<M> {
+        async fn call_endpoint(
+            &self,
+            method: &str,
+            argument: Vec<u8>,
+        ) -> Result<Vec<u8>, ManyError> {
+            call_module_cbor(self.0, &self.1, method, argument)
+        }
+    }
+
     pub fn call_module(
         key: u32,
         module: &'_ impl ManyModule,
@@ -166,3 +300,45 @@ pub(crate) mod testutils {
         response.data
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn acknowledgment_empty_is_null() {
+        let bytes = minicbor::to_vec(Acknowledgment::default()).unwrap();
+        assert_eq!(bytes, minicbor::to_vec(EmptyReturn).unwrap());
+        assert_eq!(
+            minicbor::decode::<Acknowledgment>(&bytes).unwrap(),
+            Acknowledgment::default()
+        );
+    }
+
+    #[test]
+    fn acknowledgment_roundtrip() {
+        let ack = Acknowledgment::new()
+            .with_request_hash(vec![1, 2, 3])
+            .with_height(42)
+            .with_events([events::EventId::from(1u64), events::EventId::from(2u64)]);
+        let bytes = minicbor::to_vec(&ack).unwrap();
+        assert_eq!(minicbor::decode::<Acknowledgment>(&bytes).unwrap(), ack);
+
+        // Older clients still decode the acknowledgment as an empty return.
+        assert_eq!(
+            minicbor::decode::<EmptyReturn>(&bytes).unwrap(),
+            EmptyReturn
+        );
+    }
+
+    #[test]
+    fn acknowledgment_skips_unknown_keys() {
+        let bytes = cbor_diag::parse_diag("{_ 1: 5, 99: \"unknown\"}")
+            .unwrap()
+            .to_bytes();
+        assert_eq!(
+            minicbor::decode::<Acknowledgment>(&bytes).unwrap(),
+            Acknowledgment::new().with_height(5)
+        );
+    }
+}
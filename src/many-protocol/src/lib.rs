@@ -6,9 +6,11 @@ use many_identity::{Address, Identity, Verifier};
 pub mod context;
 pub mod request;
 pub mod response;
+pub mod version;
 
 pub use request::{RequestMessage, RequestMessageBuilder};
 pub use response::{ResponseMessage, ResponseMessageBuilder};
+pub use version::{ModuleVersion, ModuleVersions};
 
 pub type ManyUrl = url::Url;
 
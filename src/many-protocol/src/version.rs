@@ -0,0 +1,110 @@
+//! Versions of modules, so servers can change the arguments or returns of a
+//! module without breaking older clients.
+//!
+//! A server advertises in its status, for each attribute, the range of
+//! versions of the module it still supports. A client negotiates the highest
+//! version both sides support before using the module, so the server can keep
+//! serving the older version to clients until they upgrade.
+use many_error::ManyError;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// The versions of a module supported by a server, from `oldest` to
+/// `latest`. Modules of servers that do not advertise a version are at
+/// version 1.
+#[derive(Clone, Copy, Debug, Decode, Encode, Eq, PartialEq, Hash)]
+#[cbor(map)]
+pub struct ModuleVersion {
+    #[n(0)]
+    pub latest: u32,
+
+    #[n(1)]
+    pub oldest: u32,
+}
+
+impl Default for ModuleVersion {
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+impl ModuleVersion {
+    pub const fn new(latest: u32) -> Self {
+        Self {
+            latest,
+            oldest: latest,
+        }
+    }
+
+    /// Keep supporting the versions since `oldest`.
+    pub const fn with_oldest(self, oldest: u32) -> Self {
+        Self { oldest, ..self }
+    }
+
+    pub fn supports(&self, version: u32) -> bool {
+        (self.oldest..=self.latest).contains(&version)
+    }
+
+    /// The highest version supported by both this and a client supporting
+    /// the `supported` versions, if any.
+    pub fn negotiate(&self, supported: &RangeInclusive<u32>) -> Option<u32> {
+        let highest = self.latest.min(*supported.end());
+        (highest >= self.oldest.max(*supported.start())).then_some(highest)
+    }
+}
+
+impl Display for ModuleVersion {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.oldest == self.latest {
+            write!(f, "{}", self.latest)
+        } else {
+            write!(f, "{}-{}", self.oldest, self.latest)
+        }
+    }
+}
+
+/// The versions of the modules of a server, by attribute ID, as found in its
+/// status.
+#[derive(Clone, Debug, Default, Decode, Encode, Eq, PartialEq)]
+#[cbor(transparent)]
+pub struct ModuleVersions(#[n(0)] pub BTreeMap<u32, ModuleVersion>);
+
+impl ModuleVersions {
+    /// The versions of the module of an attribute. Modules without a version
+    /// are at version 1, e.g. on servers that predate versioning.
+    pub fn get(&self, attribute: u32) -> ModuleVersion {
+        self.0.get(&attribute).copied().unwrap_or_default()
+    }
+
+    /// Negotiate the version of the module of an attribute to use, given the
+    /// `supported` versions of the client. Fails if no version is supported
+    /// by both.
+    pub fn negotiate(
+        &self,
+        attribute: u32,
+        supported: RangeInclusive<u32>,
+    ) -> Result<u32, ManyError> {
+        let server = self.get(attribute);
+        server.negotiate(&supported).ok_or_else(|| {
+            ManyError::incompatible_module_version(
+                attribute,
+                server,
+                ModuleVersion::new(*supported.end()).with_oldest(*supported.start()),
+            )
+        })
+    }
+}
+
+impl Display for ModuleVersions {
+    /// Formatted as `attribute@versions`, e.g. `2@1-2, 4@1`.
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let versions: Vec<String> = self
+            .0
+            .iter()
+            .map(|(attribute, version)| format!("{attribute}@{version}"))
+            .collect();
+        f.write_str(&versions.join(", "))
+    }
+}
@@ -0,0 +1,391 @@
+//! Deferred responses, for the commands taking longer than clients can wait
+//! on a request, e.g. compute deployments.
+//!
+//! A [`DeferredModule`] executes the deferred methods of the module it wraps
+//! in the background, and responds right away with an empty payload and an
+//! [`ASYNC`] attribute. Clients poll the token of the attribute with the
+//! `async.status` and `async.result` endpoints, served by the
+//! [`AsyncModule`] of the [`DeferredResponses`]:
+//!
+//! ```ignore
+//! let responses = DeferredResponses::new(identity.clone());
+//! server
+//!     .add_module(DeferredModule::new(module, ["compute.deploy"], responses.clone()))
+//!     .add_module(AsyncModule::new(Arc::new(Mutex::new(responses))));
+//! ```
+//!
+//! Deferring needs a Tokio runtime; without one, the methods are executed
+//! before responding. The middlewares of the server see the empty response,
+//! not the deferred one.
+//!
+//! [`ASYNC`]: many_modules::r#async::attributes::ASYNC
+//! [`AsyncModule`]: many_modules::r#async::AsyncModule
+use async_trait::async_trait;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_identity::{Address, Identity};
+use many_modules::r#async::attributes::AsyncAttribute;
+use many_modules::r#async::{
+    AsyncModuleBackend, AsyncToken, ResultArgs, ResultReturn, StatusArgs, StatusReturn,
+};
+use many_modules::{ManyModule, ManyModuleInfo};
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Debug, Formatter};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long the responses are kept once done, by default.
+pub const DEFAULT_DEFERRED_TTL: Duration = Duration::from_secs(600);
+
+enum State {
+    Queued,
+    Processing,
+    Done {
+        response: Box<CoseSign1>,
+        at: SystemTime,
+    },
+}
+
+/// A deferred request. Only its sender can poll its token.
+struct Deferred {
+    sender: Address,
+    state: State,
+}
+
+/// The responses of the deferred requests of a server, signed with its
+/// identity. Cloning it shares the responses.
+#[derive(Clone)]
+pub struct DeferredResponses {
+    identity: Arc<dyn Identity>,
+    ttl: Duration,
+    deferred: Arc<Mutex<BTreeMap<Vec<u8>, Deferred>>>,
+    count: Arc<AtomicU64>,
+}
+
+impl Debug for DeferredResponses {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeferredResponses")
+            .field("ttl", &self.ttl)
+            .field("deferred", &self.deferred.lock().unwrap().len())
+            .finish()
+    }
+}
+
+fn secs_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Tokens start with the time they were issued at, to tell the tokens of the
+/// responses not kept anymore from the unknown ones.
+fn issued_at(token: &[u8]) -> Option<u64> {
+    Some(u64::from_be_bytes(token.get(..8)?.try_into().ok()?))
+}
+
+impl DeferredResponses {
+    pub fn new(identity: impl Identity + 'static) -> Self {
+        Self {
+            identity: Arc::new(identity),
+            ttl: DEFAULT_DEFERRED_TTL,
+            deferred: Default::default(),
+            count: Default::default(),
+        }
+    }
+
+    /// Keep the responses for `ttl` once done. Their tokens expire after.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn remove_expired(&self, deferred: &mut BTreeMap<Vec<u8>, Deferred>) {
+        let now = SystemTime::now();
+        deferred.retain(|_, d| match d.state {
+            State::Done { at, .. } => at + self.ttl > now,
+            _ => true,
+        });
+    }
+
+    fn queue(&self, sender: Address) -> AsyncToken {
+        let mut token = secs_since_epoch(SystemTime::now()).to_be_bytes().to_vec();
+        token.extend(self.count.fetch_add(1, Ordering::Relaxed).to_be_bytes());
+
+        let mut deferred = self.deferred.lock().unwrap();
+        self.remove_expired(&mut deferred);
+        deferred.insert(
+            token.clone(),
+            Deferred {
+                sender,
+                state: State::Queued,
+            },
+        );
+        token.into()
+    }
+
+    fn set_state(&self, token: &[u8], state: State) {
+        if let Some(d) = self.deferred.lock().unwrap().get_mut(token) {
+            d.state = state;
+        }
+    }
+
+    fn finish(&self, token: &[u8], mut response: ResponseMessage) {
+        response.from = self.identity.address();
+        match many_protocol::encode_cose_sign1_from_response(response, &self.identity) {
+            Ok(envelope) => self.set_state(
+                token,
+                State::Done {
+                    response: Box::new(envelope),
+                    at: SystemTime::now(),
+                },
+            ),
+            Err(e) => {
+                tracing::error!("Could not sign deferred response: {e}");
+                self.deferred.lock().unwrap().remove(token);
+            }
+        }
+    }
+}
+
+impl AsyncModuleBackend for DeferredResponses {
+    fn status(&self, sender: &Address, args: StatusArgs) -> Result<StatusReturn, ManyError> {
+        let mut deferred = self.deferred.lock().unwrap();
+        self.remove_expired(&mut deferred);
+
+        let status = match deferred.get(args.token.as_ref()) {
+            Some(d) if &d.sender == sender => match &d.state {
+                State::Queued => StatusReturn::Queued,
+                State::Processing => StatusReturn::Processing,
+                State::Done { response, .. } => StatusReturn::Done {
+                    response: response.clone(),
+                },
+            },
+            _ => match issued_at(args.token.as_ref()) {
+                Some(issued)
+                    if issued + self.ttl.as_secs() <= secs_since_epoch(SystemTime::now()) =>
+                {
+                    StatusReturn::Expired
+                }
+                _ => StatusReturn::Unknown,
+            },
+        };
+        Ok(status)
+    }
+
+    fn result(&self, sender: &Address, args: ResultArgs) -> Result<ResultReturn, ManyError> {
+        let status = self.status(
+            sender,
+            StatusArgs {
+                token: args.token.clone(),
+            },
+        )?;
+        ResultReturn::from_response(status.into_response(&args.token)?)
+    }
+}
+
+/// A module whose deferred methods are executed in the background. See the
+/// [module documentation](self).
+#[derive(Debug)]
+pub struct DeferredModule<M> {
+    inner: Arc<M>,
+    methods: BTreeSet<String>,
+    responses: DeferredResponses,
+}
+
+impl<M: ManyModule + 'static> DeferredModule<M> {
+    pub fn new(
+        inner: M,
+        methods: impl IntoIterator<Item = impl ToString>,
+        responses: DeferredResponses,
+    ) -> Self {
+        Self {
+            inner: Arc::new(inner),
+            methods: methods.into_iter().map(|m| m.to_string()).collect(),
+            responses,
+        }
+    }
+}
+
+#[async_trait]
+impl<M: ManyModule + 'static> ManyModule for DeferredModule<M> {
+    fn info(&self) -> &ManyModuleInfo {
+        self.inner.info()
+    }
+
+    fn validate(&self, message: &RequestMessage, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.inner.validate(message, envelope)
+    }
+
+    async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+        let handle = match tokio::runtime::Handle::try_current() {
+            Ok(handle) if self.methods.contains(&message.method) => handle,
+            _ => return self.inner.execute(message).await,
+        };
+
+        let token = self.responses.queue(message.from.unwrap_or_default());
+        let (inner, responses, request) =
+            (self.inner.clone(), self.responses.clone(), message.clone());
+        let t = token.clone();
+        // Modules block while they execute, so they get a thread of their own.
+        handle.clone().spawn_blocking(move || {
+            responses.set_state(t.as_ref(), State::Processing);
+            let response = handle
+                .block_on(inner.execute(request.clone()))
+                .unwrap_or_else(|e| ResponseMessage::error(request.to, request.id, e));
+            responses.finish(t.as_ref(), response);
+        });
+
+        Ok(
+            ResponseMessage::from_request(&message, &message.to, Ok(vec![]))
+                .with_attribute(AsyncAttribute::new(token).into()),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+    use many_identity::AnonymousIdentity;
+
+    #[derive(Debug)]
+    struct SlowEchoModule(ManyModuleInfo);
+
+    #[async_trait]
+    impl ManyModule for SlowEchoModule {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.0
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            std::thread::sleep(Duration::from_millis(200));
+            if message.data.is_empty() {
+                return Err(ManyError::unknown("empty"));
+            }
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(message.data.clone()),
+            ))
+        }
+    }
+
+    fn module(responses: &DeferredResponses) -> DeferredModule<SlowEchoModule> {
+        DeferredModule::new(
+            SlowEchoModule(ManyModuleInfo {
+                name: "SlowEchoModule".to_string(),
+                attribute: None,
+                endpoints: vec!["echo.slow".to_string(), "echo.now".to_string()],
+                version: Default::default(),
+            }),
+            ["echo.slow"],
+            responses.clone(),
+        )
+    }
+
+    fn request(method: &str, data: Vec<u8>) -> RequestMessage {
+        RequestMessage::default()
+            .with_method(method.to_string())
+            .with_from(identity(1))
+            .with_data(data)
+    }
+
+    async fn wait(responses: &DeferredResponses, token: &AsyncToken) -> StatusReturn {
+        for _ in 0..50 {
+            let status = responses
+                .status(
+                    &identity(1),
+                    StatusArgs {
+                        token: token.clone(),
+                    },
+                )
+                .unwrap();
+            if !matches!(status, StatusReturn::Queued | StatusReturn::Processing) {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        panic!("The deferred request never finished");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deferred() {
+        let responses = DeferredResponses::new(AnonymousIdentity);
+        let module = module(&responses);
+
+        let response = module
+            .execute(request("echo.slow", vec![1, 2, 3]))
+            .await
+            .unwrap();
+        assert_eq!(response.data, Ok(vec![]));
+        let token = response.attributes.get::<AsyncAttribute>().unwrap().token;
+        let args = ResultArgs {
+            token: token.clone(),
+        };
+        assert_eq!(
+            responses
+                .result(&identity(1), args.clone())
+                .unwrap_err()
+                .code(),
+            many_modules::r#async::not_done("").code()
+        );
+
+        assert!(matches!(
+            wait(&responses, &token).await,
+            StatusReturn::Done { .. }
+        ));
+        assert_eq!(
+            responses.result(&identity(1), args.clone()).unwrap().0,
+            vec![1, 2, 3]
+        );
+
+        // Other senders cannot see the response.
+        assert!(matches!(
+            responses
+                .status(&identity(2), StatusArgs { token })
+                .unwrap(),
+            StatusReturn::Unknown
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deferred_errors() {
+        let responses = DeferredResponses::new(AnonymousIdentity);
+        let module = module(&responses);
+
+        // Methods not deferred respond right away.
+        let response = module.execute(request("echo.now", vec![1])).await.unwrap();
+        assert_eq!(response.data, Ok(vec![1]));
+
+        // The result fails with the error of the request.
+        let response = module.execute(request("echo.slow", vec![])).await.unwrap();
+        let token = response.attributes.get::<AsyncAttribute>().unwrap().token;
+        assert!(matches!(
+            wait(&responses, &token).await,
+            StatusReturn::Done { .. }
+        ));
+        let err = responses
+            .result(&identity(1), ResultArgs { token })
+            .unwrap_err();
+        assert_eq!(err.code(), ManyError::unknown("").code());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn deferred_expired() {
+        let responses = DeferredResponses::new(AnonymousIdentity).with_ttl(Duration::ZERO);
+        let module = module(&responses);
+
+        let response = module.execute(request("echo.slow", vec![1])).await.unwrap();
+        let token = response.attributes.get::<AsyncAttribute>().unwrap().token;
+        assert!(matches!(
+            wait(&responses, &token).await,
+            StatusReturn::Expired
+        ));
+        let err = responses
+            .result(&identity(1), ResultArgs { token })
+            .unwrap_err();
+        assert_eq!(err.code(), many_modules::r#async::expired_token("").code());
+    }
+}
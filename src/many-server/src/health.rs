@@ -0,0 +1,152 @@
+//! Liveness and readiness probes. The `health` and `ready` methods of the
+//! base module are also served over HTTP, at `GET /health` and `GET /ready`,
+//! so orchestrators can probe a server without signing MANY requests.
+//!
+//! `GET /health` always answers `200 OK` while the server runs. `GET /ready`
+//! answers `200 OK` if all the readiness checks of the server pass, and
+//! `503 Service Unavailable` otherwise, with one line per check in the body.
+use many_modules::base::ReadyReturn;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A readiness check, returning why the server is not ready if it fails.
+pub type ReadinessCheck = Arc<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// The plain text body of a `GET /ready` response.
+pub fn ready_body(ready: &ReadyReturn) -> String {
+    ready
+        .checks
+        .iter()
+        .map(|(name, check)| {
+            let status = match &check.reason {
+                _ if check.ok => "ok",
+                Some(reason) => reason,
+                None => "failed",
+            };
+            format!("{name}: {status}\n")
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct HeightState {
+    height: Option<u64>,
+    advanced_at: Instant,
+    error: Option<String>,
+}
+
+/// Follows the height of a blockchain polled by the server, for checks that
+/// it is connected to it and that it still makes blocks.
+#[derive(Clone, Debug)]
+pub struct HeightWatcher {
+    max_block_age: Duration,
+    state: Arc<Mutex<HeightState>>,
+}
+
+impl HeightWatcher {
+    /// The height is not advancing when no block was observed for longer
+    /// than `max_block_age`.
+    pub fn new(max_block_age: Duration) -> Self {
+        Self {
+            max_block_age,
+            state: Arc::new(Mutex::new(HeightState {
+                height: None,
+                advanced_at: Instant::now(),
+                error: None,
+            })),
+        }
+    }
+
+    /// Record the height returned by a successful poll.
+    pub fn observe(&self, height: u64) {
+        self.observe_at(height, Instant::now());
+    }
+
+    fn observe_at(&self, height: u64, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if state.height != Some(height) {
+            state.height = Some(height);
+            state.advanced_at = now;
+        }
+        state.error = None;
+    }
+
+    /// Record a failed poll.
+    pub fn fail(&self, error: impl ToString) {
+        self.state.lock().unwrap().error = Some(error.to_string());
+    }
+
+    /// Whether the last poll succeeded.
+    pub fn connected(&self) -> Result<(), String> {
+        let state = self.state.lock().unwrap();
+        match (&state.error, state.height) {
+            (Some(error), _) => Err(error.clone()),
+            (None, None) => Err("Not connected yet".to_string()),
+            (None, Some(_)) => Ok(()),
+        }
+    }
+
+    /// Whether a new block was observed recently.
+    pub fn advancing(&self) -> Result<(), String> {
+        self.advancing_at(Instant::now())
+    }
+
+    fn advancing_at(&self, now: Instant) -> Result<(), String> {
+        let state = self.state.lock().unwrap();
+        let age = now.saturating_duration_since(state.advanced_at);
+        if age <= self.max_block_age {
+            return Ok(());
+        }
+        match state.height {
+            Some(height) => Err(format!(
+                "No new block since height {height}, {}s ago",
+                age.as_secs()
+            )),
+            None => Err(format!("No block in {}s", age.as_secs())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn height_watcher() {
+        let watcher = HeightWatcher::new(Duration::from_secs(10));
+        let start = Instant::now();
+        assert!(watcher.connected().is_err());
+        assert!(watcher.advancing_at(start).is_ok());
+
+        watcher.observe_at(5, start);
+        assert!(watcher.connected().is_ok());
+
+        // The same height does not count as advancing.
+        watcher.observe_at(5, start + Duration::from_secs(8));
+        assert!(watcher
+            .advancing_at(start + Duration::from_secs(10))
+            .is_ok());
+        assert_eq!(
+            watcher.advancing_at(start + Duration::from_secs(11)),
+            Err("No new block since height 5, 11s ago".to_string())
+        );
+
+        watcher.observe_at(6, start + Duration::from_secs(12));
+        assert!(watcher
+            .advancing_at(start + Duration::from_secs(13))
+            .is_ok());
+
+        watcher.fail("Connection refused");
+        assert_eq!(watcher.connected(), Err("Connection refused".to_string()));
+    }
+
+    #[test]
+    fn body() {
+        let ready = ReadyReturn::from_checks(BTreeMap::from([
+            ("abci".to_string(), Ok(())),
+            ("storage".to_string(), Err("Closed".to_string())),
+        ]));
+        assert_eq!(ready_body(&ready), "abci: ok\nstorage: Closed\n");
+    }
+}
@@ -1,8 +1,14 @@
+pub mod deferred;
+pub mod health;
+pub mod middleware;
+pub mod reload;
 pub mod server;
+pub mod telemetry;
 pub mod transport;
 pub mod validator;
 
 pub use many_error::ManyError;
 pub use many_identity::Address;
+pub use middleware::Middleware;
 pub use server::ManyServer;
 pub use validator::RequestValidator;
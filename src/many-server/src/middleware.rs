@@ -0,0 +1,92 @@
+//! Hooks around the execution of the modules of a [`ManyServer`].
+//!
+//! Unlike a [`RequestValidator`], a middleware can change the request it
+//! receives, and sees the result of the module executing it. Middlewares are
+//! added to a server for the methods matching a glob, e.g. `ledger.*`, and
+//! wrap each other in the order they were added:
+//!
+//! ```text
+//! a.before -> b.before -> module.execute -> b.after -> a.after
+//! ```
+//!
+//! They only run for the methods implemented by the modules of the server, not
+//! the requests forwarded to its fallback module.
+//!
+//! [`ManyServer`]: crate::ManyServer
+//! [`RequestValidator`]: crate::RequestValidator
+use crate::validator::policy::glob_matches;
+use async_trait::async_trait;
+use many_error::ManyError;
+use many_modules::ManyModule;
+use many_protocol::{RequestMessage, ResponseMessage};
+use std::sync::Arc;
+
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Called before the module executes a request, with the request returned
+    /// by the previous middleware. Failing skips the module and the following
+    /// middlewares, and the error is passed to the `after` hook of the previous
+    /// ones.
+    async fn before(&self, request: RequestMessage) -> Result<RequestMessage, ManyError> {
+        Ok(request)
+    }
+
+    /// Called after the module executed a request, with the result returned by
+    /// the next middleware. The request is the one returned by `before`.
+    async fn after(
+        &self,
+        _request: &RequestMessage,
+        result: Result<ResponseMessage, ManyError>,
+    ) -> Result<ResponseMessage, ManyError> {
+        result
+    }
+}
+
+/// The middlewares added to a server, with the glob of the methods they run
+/// for.
+#[derive(Clone, Default)]
+pub(crate) struct MiddlewareChain(Vec<(String, Arc<dyn Middleware>)>);
+
+impl MiddlewareChain {
+    pub fn push(&mut self, glob: String, middleware: Arc<dyn Middleware>) {
+        self.0.push((glob, middleware));
+    }
+
+    /// The middlewares running for a method, in order.
+    pub fn for_method(&self, method: &str) -> Vec<Arc<dyn Middleware>> {
+        self.0
+            .iter()
+            .filter(|(glob, _)| glob_matches(glob, method))
+            .map(|(_, middleware)| middleware.clone())
+            .collect()
+    }
+}
+
+/// Execute a request with a module, wrapped in middlewares.
+pub(crate) async fn execute(
+    middlewares: &[Arc<dyn Middleware>],
+    module: &dyn ManyModule,
+    mut request: RequestMessage,
+) -> Result<ResponseMessage, ManyError> {
+    let mut entered = 0;
+    let mut failure = None;
+    for middleware in middlewares {
+        match middleware.before(request.clone()).await {
+            Ok(r) => request = r,
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+        entered += 1;
+    }
+
+    let mut result = match failure {
+        Some(e) => Err(e),
+        None => module.execute(request.clone()).await,
+    };
+    for middleware in middlewares[..entered].iter().rev() {
+        result = middleware.after(&request, result).await;
+    }
+    result
+}
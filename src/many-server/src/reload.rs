@@ -0,0 +1,194 @@
+//! Configuration reloaded while the server runs, e.g. when it receives a
+//! SIGHUP, without dropping its connections.
+//!
+//! The parts of the configuration that can be reloaded are kept in a
+//! [`Reloadable`] shared with the modules and validators using them. A
+//! [`ConfigReloader`] reads them again from their sources and replaces them.
+use crate::RequestValidator;
+use coset::CoseSign1;
+use many_error::ManyError;
+use many_protocol::RequestMessage;
+use std::fmt::{Debug, Formatter};
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// A value that can be replaced while it is in use. Clones share the same
+/// value.
+pub struct Reloadable<T>(Arc<RwLock<Arc<T>>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(value))))
+    }
+
+    /// The current value. It is not affected by later reloads.
+    pub fn get(&self) -> Arc<T> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap() = Arc::new(value);
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T: Default> Default for Reloadable<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T: Debug> Debug for Reloadable<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Reloadable").field(&self.get()).finish()
+    }
+}
+
+/// A validator that can be replaced, e.g. a policy read from a file. Only
+/// validators that do not track the executed messages can be reloaded.
+impl<V: RequestValidator> RequestValidator for Reloadable<V> {
+    fn validate_envelope(&self, envelope: &CoseSign1) -> Result<(), ManyError> {
+        self.get().validate_envelope(envelope)
+    }
+
+    fn validate_request(&self, request: &RequestMessage) -> Result<(), ManyError> {
+        self.get().validate_request(request)
+    }
+}
+
+type ReloadFn = Box<dyn Fn() -> Result<(), String> + Send + Sync>;
+
+/// The outcome of a reload.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct ReloadReport {
+    /// The parts of the configuration reloaded.
+    pub reloaded: Vec<String>,
+
+    /// The parts of the configuration that could not be reloaded, with the
+    /// reason. They keep their previous value.
+    pub failed: Vec<(String, String)>,
+}
+
+impl ReloadReport {
+    pub fn is_ok(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+/// Reloads the parts of the configuration, each from its own source.
+#[derive(Default)]
+pub struct ConfigReloader {
+    parts: Vec<(String, ReloadFn)>,
+}
+
+impl Debug for ConfigReloader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.parts.iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+impl ConfigReloader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a part of the configuration, reloaded by `reload`.
+    pub fn add(
+        &mut self,
+        name: impl ToString,
+        reload: impl Fn() -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.parts.push((name.to_string(), Box::new(reload)));
+        self
+    }
+
+    /// Add a part of the configuration read by `read` into `value`.
+    pub fn add_reloadable<T: Send + Sync + 'static>(
+        &mut self,
+        name: impl ToString,
+        value: &Reloadable<T>,
+        read: impl Fn() -> Result<T, String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        let value = value.clone();
+        self.add(name, move || read().map(|new| value.set(new)))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts.is_empty()
+    }
+
+    /// Reload every part of the configuration, then log the outcome as a
+    /// `config.reload` audit event.
+    pub fn reload(&self) -> ReloadReport {
+        let mut report = ReloadReport::default();
+        for (name, reload) in &self.parts {
+            match reload() {
+                Ok(()) => report.reloaded.push(name.clone()),
+                Err(e) => report.failed.push((name.clone(), e)),
+            }
+        }
+
+        if report.is_ok() {
+            info!(
+                target: "audit",
+                event = "config.reload",
+                reloaded = ?report.reloaded,
+            );
+        } else {
+            warn!(
+                target: "audit",
+                event = "config.reload",
+                reloaded = ?report.reloaded,
+                failed = ?report.failed,
+            );
+        }
+        report
+    }
+
+    /// Reload the configuration every time the process receives a SIGHUP,
+    /// from a background thread.
+    pub fn reload_on_sighup(self) -> std::io::Result<()> {
+        let mut signals = signal_hook::iterator::Signals::new([signal_hook::consts::SIGHUP])?;
+        std::thread::Builder::new()
+            .name("config-reload".to_string())
+            .spawn(move || {
+                for _ in signals.forever() {
+                    info!("Received SIGHUP, reloading the configuration");
+                    self.reload();
+                }
+            })?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reload() {
+        let value = Reloadable::new(1);
+        let before = value.get();
+
+        let mut reloader = ConfigReloader::new();
+        reloader
+            .add_reloadable("value", &value, || Ok(2))
+            .add("broken", || Err("Invalid file".to_string()));
+
+        let report = reloader.reload();
+        assert_eq!(report.reloaded, vec!["value".to_string()]);
+        assert_eq!(
+            report.failed,
+            vec![("broken".to_string(), "Invalid file".to_string())]
+        );
+        assert_eq!(*value.get(), 2);
+        assert_eq!(*before, 1);
+    }
+}
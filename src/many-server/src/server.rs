@@ -1,3 +1,6 @@
+use crate::health::ReadinessCheck;
+use crate::middleware::{self, Middleware, MiddlewareChain};
+use crate::telemetry;
 use crate::transport::LowLevelManyRequestHandler;
 use crate::RequestValidator;
 use async_trait::async_trait;
@@ -5,13 +8,17 @@ use coset::{CoseKey, CoseSign1};
 use many_error::ManyError;
 use many_identity::{Identity, Verifier};
 use many_modules::{base, ManyModule, ManyModuleInfo};
-use many_protocol::{RequestMessage, ResponseMessage};
+use many_protocol::{ModuleVersion, ModuleVersions, RequestMessage, ResponseMessage};
 use many_types::attributes::Attribute;
+use many_types::deadline::{Deadline, DEADLINE};
+use many_types::node_state::{NodeState, NODE_STATE};
 use std::cell::RefCell;
 use std::collections::{BTreeMap, BTreeSet};
 use std::fmt::{Debug, Formatter};
+use std::future::Future;
 use std::sync::{Arc, Mutex};
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
+use tracing::{Instrument, Span};
 
 trait ManyServerFallback: LowLevelManyRequestHandler + base::BaseModuleBackend {}
 
@@ -22,19 +29,30 @@ pub struct ManyModuleList {}
 
 pub const MANYSERVER_DEFAULT_TIMEOUT: u64 = 300;
 
+type NodeStateFn = Arc<dyn Fn() -> Result<NodeState, ManyError> + Send + Sync>;
+
 pub struct ManyServer {
     modules: Vec<Arc<dyn ManyModule + Send>>,
     method_cache: BTreeSet<String>,
-    identity: Box<dyn Identity>,
-    identity_verifier: Box<dyn Verifier>,
+    identity: Arc<dyn Identity>,
+    identity_verifier: Arc<dyn Verifier + Sync>,
     validator: RefCell<Box<dyn RequestValidator + Send>>,
+    middlewares: MiddlewareChain,
     public_key: Option<CoseKey>,
     name: String,
     version: Option<String>,
     timeout: u64,
+    enforce_deadlines: bool,
     fallback: Option<Arc<dyn ManyServerFallback + Send + 'static>>,
+    readiness_checks: BTreeMap<String, ReadinessCheck>,
+
+    /// Held from the validation of a request to the end of its execution, so
+    /// validators see every request executed before, e.g. the request cache
+    /// rejecting a copy of an envelope that is still executing.
+    execution: Arc<tokio::sync::Mutex<()>>,
 
     time_fn: Option<Arc<dyn Fn() -> Result<SystemTime, ManyError> + Send + Sync>>,
+    node_state_fn: Option<NodeStateFn>,
 }
 
 impl ManyServer {
@@ -52,7 +70,7 @@ impl ManyServer {
     pub fn simple(
         name: impl ToString,
         identity: impl Identity + 'static,
-        verifier: impl Verifier + 'static,
+        verifier: impl Verifier + Sync + 'static,
         version: Option<String>,
     ) -> Arc<Mutex<Self>> {
         let public_key = identity.public_key();
@@ -69,21 +87,26 @@ impl ManyServer {
     pub fn new<N: ToString>(
         name: N,
         identity: impl Identity + 'static,
-        verifier: impl Verifier + 'static,
+        verifier: impl Verifier + Sync + 'static,
         public_key: Option<CoseKey>,
     ) -> Arc<Mutex<Self>> {
         Arc::new(Mutex::new(Self {
             modules: vec![],
             name: name.to_string(),
-            identity: Box::new(identity),
-            identity_verifier: Box::new(verifier),
+            identity: Arc::new(identity),
+            identity_verifier: Arc::new(verifier),
             validator: RefCell::new(Box::new(())),
+            middlewares: Default::default(),
             public_key,
             timeout: MANYSERVER_DEFAULT_TIMEOUT,
+            enforce_deadlines: true,
             fallback: None,
+            readiness_checks: BTreeMap::new(),
+            execution: Default::default(),
             method_cache: Default::default(),
             version: None,
             time_fn: None,
+            node_state_fn: None,
         }))
     }
 
@@ -91,6 +114,15 @@ impl ManyServer {
         self.timeout = timeout_in_secs;
     }
 
+    /// Whether to reject the requests received after their deadline, and
+    /// cancel the ones still executing at their deadline. This is on by
+    /// default. Servers executing the transactions of a blockchain need to
+    /// turn it off, as nodes would not agree on which requests expired; the
+    /// deadline is checked when the transaction is submitted instead.
+    pub fn set_enforce_deadlines(&mut self, enforce: bool) {
+        self.enforce_deadlines = enforce;
+    }
+
     pub fn set_time_fn<T>(&mut self, time_fn: T)
     where
         T: Fn() -> Result<SystemTime, ManyError> + Send + Sync + 'static,
@@ -98,6 +130,16 @@ impl ManyServer {
         self.time_fn = Some(Arc::new(time_fn));
     }
 
+    /// Add the state of the node to the responses of the requests with the
+    /// [`NODE_STATE`] attribute. The state is read after the module executed
+    /// the request, so it reflects the changes of the request.
+    pub fn set_node_state_fn<T>(&mut self, node_state_fn: T)
+    where
+        T: Fn() -> Result<NodeState, ManyError> + Send + Sync + 'static,
+    {
+        self.node_state_fn = Some(Arc::new(node_state_fn));
+    }
+
     pub fn set_fallback_module<M>(&mut self, module: M) -> &mut Self
     where
         M: LowLevelManyRequestHandler + base::BaseModuleBackend + 'static,
@@ -106,6 +148,19 @@ impl ManyServer {
         self
     }
 
+    /// Add a check to the readiness of the server, served by the `ready`
+    /// method and at `GET /ready`. A check with the same name replaces the
+    /// previous one.
+    pub fn add_readiness_check(
+        &mut self,
+        name: impl ToString,
+        check: impl Fn() -> Result<(), String> + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.readiness_checks
+            .insert(name.to_string(), Arc::new(check));
+        self
+    }
+
     pub fn add_validator(
         &mut self,
         validator: impl RequestValidator + Send + 'static,
@@ -115,6 +170,18 @@ impl ManyServer {
         self
     }
 
+    /// Wrap the execution of the methods matching `glob` in a middleware,
+    /// where `*` matches any sequence of characters. See [`middleware`].
+    pub fn add_middleware(
+        &mut self,
+        glob: impl ToString,
+        middleware: impl Middleware + 'static,
+    ) -> &mut Self {
+        self.middlewares
+            .push(glob.to_string(), Arc::new(middleware));
+        self
+    }
+
     pub fn add_module<M>(&mut self, module: M) -> &mut Self
     where
         M: ManyModule + 'static,
@@ -171,6 +238,11 @@ impl ManyServer {
         }
     }
 
+    /// Returns the modules added to this server, in order.
+    pub fn modules(&self) -> Vec<Arc<dyn ManyModule + Send>> {
+        self.modules.clone()
+    }
+
     pub fn find_module(&self, message: &RequestMessage) -> Option<Arc<dyn ManyModule + Send>> {
         self.modules
             .iter()
@@ -206,6 +278,12 @@ impl base::BaseModuleBackend for ManyServer {
             .filter_map(|m| m.info().attribute.clone())
             .collect();
 
+        let mut module_versions: BTreeMap<u32, ModuleVersion> = self
+            .modules
+            .iter()
+            .filter_map(|m| Some((m.info().attribute.as_ref()?.id, m.info().version)))
+            .collect();
+
         let mut builder = base::StatusBuilder::default();
 
         builder
@@ -246,73 +324,165 @@ impl base::BaseModuleBackend for ManyServer {
             builder.name(fb_status.name).extras(fb_status.extras);
 
             attributes = attributes.into_iter().chain(fb_status.attributes).collect();
+            module_versions.extend(fb_status.module_versions.0);
         }
 
-        builder.attributes(attributes.into_iter().collect());
+        builder
+            .attributes(attributes.into_iter().collect())
+            .module_versions(ModuleVersions(module_versions));
 
         builder
             .build()
             .map_err(|x| ManyError::unknown(x.to_string()))
     }
+
+    fn ready(&self) -> Result<base::ReadyReturn, ManyError> {
+        let mut checks: BTreeMap<String, Result<(), String>> = self
+            .readiness_checks
+            .iter()
+            .map(|(name, check)| (name.clone(), check()))
+            .collect();
+
+        if let Some(fb) = &self.fallback {
+            for (name, check) in base::BaseModuleBackend::ready(fb.as_ref())?.checks {
+                let result = match check.reason {
+                    _ if check.ok => Ok(()),
+                    reason => Err(reason.unwrap_or_else(|| "failed".to_string())),
+                };
+                checks.entry(name).or_insert(result);
+            }
+        }
+
+        Ok(base::ReadyReturn::from_checks(checks))
+    }
 }
 
 #[async_trait]
 impl LowLevelManyRequestHandler for Arc<Mutex<ManyServer>> {
     async fn execute(&self, envelope: CoseSign1) -> Result<CoseSign1, String> {
-        let request = {
-            let this = self.lock().unwrap();
-            {
-                let validator = this.validator.borrow();
-
-                validator.validate_envelope(&envelope).and_then(|_| {
-                    many_protocol::decode_request_from_cose_sign1(
-                        &envelope,
-                        &this.identity_verifier,
-                    )
-                })
-            }
-        };
-        let mut id = None;
-
-        let response = {
-            let this = self.lock().unwrap();
-            let address = this.identity.address();
-
-            (|| {
-                let message = request?;
-
-                let now = this
-                    .time_fn
-                    .as_ref()
-                    .map_or_else(|| Ok(SystemTime::now()), |f| f())?;
-
-                this.validator.borrow().validate_request(&message)?;
-                message.validate_time(now, this.timeout)?;
+        let span = crate::request_span!("many_request");
+        execute_envelope(self, envelope, &span)
+            .instrument(span.clone())
+            .await
+    }
 
-                id = message.id;
+    fn ready(&self) -> base::ReadyReturn {
+        let result = base::BaseModuleBackend::ready(&*self.lock().unwrap());
+        result.unwrap_or_else(|e| {
+            base::ReadyReturn::from_checks(BTreeMap::from([(
+                "server".to_string(),
+                Err(e.to_string()),
+            )]))
+        })
+    }
+}
 
-                this.validate_id(&message)?;
+async fn execute_envelope(
+    server: &Arc<Mutex<ManyServer>>,
+    envelope: CoseSign1,
+    span: &Span,
+) -> Result<CoseSign1, String> {
+    // Signatures are verified and responses signed concurrently. Requests
+    // are validated and executed one at a time, from the validation of
+    // their envelope to `message_executed`.
+    let (verifier, identity, execution) = {
+        let this = server.lock().unwrap();
+        (
+            this.identity_verifier.clone(),
+            this.identity.clone(),
+            this.execution.clone(),
+        )
+    };
+    let request = many_protocol::decode_request_from_cose_sign1(&envelope, &verifier);
+    let address = identity.address();
+
+    let execution = execution.lock().await;
+    let request = {
+        let this = server.lock().unwrap();
+        let envelope_check = this.validator.borrow().validate_envelope(&envelope);
+        envelope_check.and_then(|_| request)
+    };
+    let mut id = None;
+
+    let response = {
+        let this = server.lock().unwrap();
+
+        (|| {
+            let message = request?;
+            telemetry::record_request(span, &message);
+
+            let now = this
+                .time_fn
+                .as_ref()
+                .map_or_else(|| Ok(SystemTime::now()), |f| f())?;
+
+            this.validator.borrow().validate_request(&message)?;
+            message.validate_time(now, this.timeout)?;
+            let remaining = if this.enforce_deadlines {
+                time_until_deadline(&message, now)?
+            } else {
+                None
+            };
+
+            id = message.id;
+
+            this.validate_id(&message)?;
+
+            let maybe_module = this.find_module(&message);
+            if let Some(ref m) = maybe_module {
+                m.validate(&message, &envelope)?;
+            };
+            let maybe_module = maybe_module.map(|m| {
+                let node_state_fn = this
+                    .node_state_fn
+                    .clone()
+                    .filter(|_| message.attributes.has_id(NODE_STATE.id));
+                (
+                    m,
+                    this.middlewares.for_method(&message.method),
+                    node_state_fn,
+                )
+            });
+
+            Ok((message, maybe_module, this.fallback.clone(), remaining))
+        })()
+        .map_err(|many_err| ResponseMessage::error(address, id, many_err))
+    };
 
-                let maybe_module = this.find_module(&message);
-                if let Some(ref m) = maybe_module {
-                    m.validate(&message, &envelope)?;
+    match response {
+        Ok((message, maybe_module, fallback, remaining)) => match (maybe_module, fallback) {
+            (Some((m, middlewares, node_state_fn)), _) => {
+                let result = until_deadline(
+                    remaining,
+                    middleware::execute(&middlewares, m.as_ref(), message.clone()),
+                )
+                .await
+                .unwrap_or_else(|| Err(ManyError::deadline_exceeded()));
+                let mut response = match result {
+                    Ok(response) => response,
+                    Err(many_err) => {
+                        if let Some(origin) = many_err.origin() {
+                            tracing::debug!(
+                                "{} failed with error from {origin}: {many_err}",
+                                message.method
+                            );
+                        }
+                        ResponseMessage::error(address, id, many_err)
+                    }
                 };
+                response.from = address;
+                if let Some(node_state_fn) = node_state_fn {
+                    match node_state_fn() {
+                        Ok(state) => {
+                            response.attributes.insert(state.into());
+                        }
+                        Err(e) => tracing::debug!("Could not read the node state: {e}"),
+                    }
+                }
+                telemetry::record_response(span, &response);
 
-                Ok((address, message, maybe_module, this.fallback.clone()))
-            })()
-            .map_err(|many_err| ResponseMessage::error(address, id, many_err))
-        };
-
-        match response {
-            Ok((address, message, maybe_module, fallback)) => match (maybe_module, fallback) {
-                (Some(m), _) => {
-                    let mut response = match m.execute(message.clone()).await {
-                        Ok(response) => response,
-                        Err(many_err) => ResponseMessage::error(address, id, many_err),
-                    };
-                    response.from = address;
-
-                    let this = self.lock().unwrap();
+                {
+                    let this = server.lock().unwrap();
                     let _ = this
                         .validator
                         .borrow_mut()
@@ -326,36 +496,82 @@ impl LowLevelManyRequestHandler for Arc<Mutex<ManyServer>> {
                                 and would need to revert to a previous block."
                             );
                         });
-                    many_protocol::encode_cose_sign1_from_response(response, &this.identity)
-                        .map_err(|e| e.to_string())
-                }
-                (None, Some(fb)) => {
-                    LowLevelManyRequestHandler::execute(fb.as_ref(), envelope).await
                 }
-                (None, None) => {
-                    let this = self.lock().unwrap();
-                    let identity = &this.identity;
-                    let address = identity.address();
-
-                    let response =
-                        ResponseMessage::error(address, id, ManyError::could_not_route_message());
-                    many_protocol::encode_cose_sign1_from_response(response, identity)
-                        .map_err(|e| e.to_string())
+                drop(execution);
+                many_protocol::encode_cose_sign1_from_response(response, &identity)
+                    .map_err(|e| e.to_string())
+            }
+            (None, Some(fb)) => {
+                // The fallback validates and executes the envelope itself.
+                drop(execution);
+                match until_deadline(
+                    remaining,
+                    LowLevelManyRequestHandler::execute(fb.as_ref(), envelope),
+                )
+                .await
+                {
+                    Some(result) => result,
+                    None => {
+                        let response =
+                            ResponseMessage::error(address, id, ManyError::deadline_exceeded());
+                        telemetry::record_response(span, &response);
+                        many_protocol::encode_cose_sign1_from_response(response, &identity)
+                            .map_err(|e| e.to_string())
+                    }
                 }
-            },
-            Err(response) => {
-                let this = self.lock().unwrap();
-                many_protocol::encode_cose_sign1_from_response(response, &this.identity)
+            }
+            (None, None) => {
+                drop(execution);
+                let response =
+                    ResponseMessage::error(address, id, ManyError::could_not_route_message());
+                telemetry::record_response(span, &response);
+                many_protocol::encode_cose_sign1_from_response(response, &identity)
                     .map_err(|e| e.to_string())
             }
+        },
+        Err(response) => {
+            drop(execution);
+            telemetry::record_response(span, &response);
+            many_protocol::encode_cose_sign1_from_response(response, &identity)
+                .map_err(|e| e.to_string())
         }
     }
 }
 
+/// The time left until the deadline of a request, if it has one. Fails if
+/// the deadline has passed.
+fn time_until_deadline(
+    message: &RequestMessage,
+    now: SystemTime,
+) -> Result<Option<Duration>, ManyError> {
+    let Some(attr) = message.attributes.get_attribute(DEADLINE.id) else {
+        return Ok(None);
+    };
+    Deadline::try_from(attr.clone())?
+        .remaining(now)?
+        .map(Some)
+        .ok_or_else(ManyError::deadline_exceeded)
+}
+
+/// Run a handler, cancelling it if it is still running after `remaining`.
+/// Handlers can only be cancelled while they wait, e.g. on another server,
+/// and only on a Tokio runtime. Returns `None` if the handler was cancelled.
+async fn until_deadline<T>(
+    remaining: Option<Duration>,
+    handler: impl Future<Output = T>,
+) -> Option<T> {
+    match remaining {
+        Some(remaining) if tokio::runtime::Handle::try_current().is_ok() => {
+            tokio::time::timeout(remaining, handler).await.ok()
+        }
+        _ => Some(handler.await),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use semver::{BuildMetadata, Prerelease, Version};
-    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
     use std::sync::RwLock;
     use std::time::Duration;
 
@@ -421,6 +637,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn status_module_versions() {
+        use many_modules::base::BaseModuleBackend;
+
+        let server = ManyServer::test(AnonymousIdentity);
+        let status = server.lock().unwrap().status().unwrap();
+        assert_eq!(status.module_versions.get(0), ModuleVersion::new(1));
+
+        let status: Status = minicbor::decode(&minicbor::to_vec(&status).unwrap()).unwrap();
+        assert_eq!(status.module_versions.negotiate(0, 1..=3), Ok(1));
+        assert!(status.module_versions.negotiate(0, 2..=3).is_err());
+
+        // Modules that are not advertised are at version 1.
+        assert_eq!(status.module_versions.negotiate(1000, 1..=2), Ok(1));
+    }
+
     #[test]
     fn validate_from_anonymous_fail() {
         let request: RequestMessage = RequestMessageBuilder::default()
@@ -637,4 +869,357 @@ mod tests {
             decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier).unwrap();
         assert!(response.data.is_err());
     }
+
+    fn create_request_with_deadline(method: &str, deadline: Option<Deadline>) -> CoseSign1 {
+        let mut builder = RequestMessageBuilder::default();
+        builder
+            .method(method.to_string())
+            .timestamp(Timestamp::now())
+            .nonce(vec![0]);
+        if let Some(deadline) = deadline {
+            builder.attributes(many_types::attributes::AttributeSet::from_iter([
+                deadline.into()
+            ]));
+        }
+        encode_cose_sign1_from_request(builder.build().unwrap(), &AnonymousIdentity).unwrap()
+    }
+
+    #[test]
+    fn server_rejects_expired_requests() {
+        let server = ManyServer::test(AnonymousIdentity);
+        let execute = |deadline| {
+            let response_e = smol::block_on(
+                server.execute(create_request_with_deadline("status", Some(deadline))),
+            )
+            .unwrap();
+            decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier)
+                .unwrap()
+                .data
+        };
+
+        let expired = Deadline(Timestamp::new(Timestamp::now().secs() - 1).unwrap());
+        assert_eq!(
+            execute(expired).unwrap_err().code(),
+            ManyError::deadline_exceeded().code()
+        );
+        assert!(execute(Deadline::after(Duration::from_secs(60)).unwrap()).is_ok());
+
+        server.lock().unwrap().set_enforce_deadlines(false);
+        assert!(execute(expired).is_ok());
+    }
+
+    #[test]
+    fn server_adds_node_state() {
+        let server = ManyServer::test(AnonymousIdentity);
+        let state = NodeState {
+            height: 3,
+            time: Timestamp::new(1_000).unwrap(),
+            app_hash: Some(vec![1; 32]),
+        };
+        {
+            let state = state.clone();
+            server
+                .lock()
+                .unwrap()
+                .set_node_state_fn(move || Ok(state.clone()));
+        }
+        let execute = |attributes: Vec<Attribute>| {
+            let request = RequestMessageBuilder::default()
+                .method("status".to_string())
+                .timestamp(Timestamp::now())
+                .attributes(many_types::attributes::AttributeSet::from_iter(attributes))
+                .build()
+                .unwrap();
+            let envelope = encode_cose_sign1_from_request(request, &AnonymousIdentity).unwrap();
+            let response_e = smol::block_on(server.execute(envelope)).unwrap();
+            decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier).unwrap()
+        };
+
+        // The state is only added to the responses of clients asking for it.
+        assert!(execute(vec![]).attributes.get::<NodeState>().is_err());
+        let response = execute(vec![NODE_STATE]);
+        assert!(response.data.is_ok());
+        assert_eq!(response.attributes.get::<NodeState>().unwrap(), state);
+    }
+
+    #[derive(Debug)]
+    struct SlowModule(ManyModuleInfo);
+
+    #[async_trait]
+    impl ManyModule for SlowModule {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.0
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(vec![]),
+            ))
+        }
+    }
+
+    #[tokio::test]
+    async fn server_cancels_at_deadline() {
+        let server = ManyServer::test(AnonymousIdentity);
+        server
+            .lock()
+            .unwrap()
+            .add_module(SlowModule(ManyModuleInfo {
+                name: "SlowModule".to_string(),
+                attribute: None,
+                endpoints: vec!["slow.run".to_string()],
+                version: Default::default(),
+            }));
+
+        let start = std::time::Instant::now();
+        let deadline = Deadline::after(Duration::from_secs(2)).unwrap();
+        let response_e = server
+            .execute(create_request_with_deadline("slow.run", Some(deadline)))
+            .await
+            .unwrap();
+        let response =
+            decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier).unwrap();
+        assert_eq!(
+            response.data.unwrap_err().code(),
+            ManyError::deadline_exceeded().code()
+        );
+        assert!(start.elapsed() < Duration::from_secs(30));
+    }
+
+    #[derive(Debug)]
+    struct CountingModule(ManyModuleInfo, Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl ManyModule for CountingModule {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.0
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            self.1.fetch_add(1, Ordering::Relaxed);
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(vec![]),
+            ))
+        }
+    }
+
+    /// Rejects the envelopes already executed, like the request cache.
+    struct ReplayValidator(Arc<Mutex<BTreeSet<Vec<u8>>>>);
+
+    impl RequestValidator for ReplayValidator {
+        fn validate_envelope(&self, envelope: &CoseSign1) -> Result<(), ManyError> {
+            if self
+                .0
+                .lock()
+                .unwrap()
+                .contains(envelope.payload.as_ref().unwrap())
+            {
+                Err(ManyError::duplicated_message())
+            } else {
+                Ok(())
+            }
+        }
+
+        fn message_executed(
+            &mut self,
+            envelope: &CoseSign1,
+            _response: &ResponseMessage,
+        ) -> Result<(), ManyError> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(envelope.payload.clone().unwrap());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn server_rejects_concurrent_duplicates() {
+        let server = ManyServer::test(AnonymousIdentity);
+        let executions = Arc::new(AtomicUsize::new(0));
+        server
+            .lock()
+            .unwrap()
+            .add_module(CountingModule(
+                ManyModuleInfo {
+                    name: "CountingModule".to_string(),
+                    attribute: None,
+                    endpoints: vec!["counting.run".to_string()],
+                    version: Default::default(),
+                },
+                executions.clone(),
+            ))
+            .add_validator(ReplayValidator(Default::default()));
+
+        // The copy of the envelope is validated while the first one executes.
+        let envelope = create_request_with_deadline("counting.run", None);
+        let (first, second) =
+            tokio::join!(server.execute(envelope.clone()), server.execute(envelope));
+        let mut results = [first, second].map(|response_e| {
+            decode_response_from_cose_sign1(&response_e.unwrap(), None, &AcceptAllVerifier)
+                .unwrap()
+                .data
+        });
+        results.sort_by_key(Result::is_err);
+
+        assert_eq!(executions.load(Ordering::Relaxed), 1);
+        assert!(results[0].is_ok());
+        assert_eq!(
+            results[1].as_ref().unwrap_err().code(),
+            ManyError::duplicated_message().code()
+        );
+    }
+
+    #[derive(Debug)]
+    struct EchoModule(ManyModuleInfo);
+
+    #[async_trait]
+    impl ManyModule for EchoModule {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.0
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(message.data.clone()),
+            ))
+        }
+    }
+
+    /// Appends its byte to the data of the requests, and denies the requests
+    /// already containing it. Records the results it sees.
+    struct TagMiddleware(u8, Arc<Mutex<Vec<(u8, Result<Vec<u8>, ManyError>)>>>);
+
+    #[async_trait]
+    impl Middleware for TagMiddleware {
+        async fn before(&self, mut request: RequestMessage) -> Result<RequestMessage, ManyError> {
+            if request.data.contains(&self.0) {
+                return Err(ManyError::unknown("denied by middleware"));
+            }
+            request.data.push(self.0);
+            Ok(request)
+        }
+
+        async fn after(
+            &self,
+            _request: &RequestMessage,
+            result: Result<ResponseMessage, ManyError>,
+        ) -> Result<ResponseMessage, ManyError> {
+            let data = result
+                .as_ref()
+                .map_err(Clone::clone)
+                .and_then(|r| r.data.clone());
+            self.1.lock().unwrap().push((self.0, data));
+            result
+        }
+    }
+
+    #[test]
+    fn server_runs_middlewares() {
+        let server = ManyServer::test(AnonymousIdentity);
+        let seen = Arc::new(Mutex::new(vec![]));
+        {
+            let mut server = server.lock().unwrap();
+            server.add_module(EchoModule(ManyModuleInfo {
+                name: "EchoModule".to_string(),
+                attribute: None,
+                endpoints: vec!["echo.run".to_string(), "echo.other".to_string()],
+                version: Default::default(),
+            }));
+            server
+                .add_middleware("echo.*", TagMiddleware(1, seen.clone()))
+                .add_middleware("echo.run", TagMiddleware(2, seen.clone()));
+        }
+
+        let execute = |method: &str, data: Vec<u8>| {
+            let request = RequestMessageBuilder::default()
+                .method(method.to_string())
+                .data(data)
+                .timestamp(Timestamp::now())
+                .build()
+                .unwrap();
+            let envelope = encode_cose_sign1_from_request(request, &AnonymousIdentity).unwrap();
+            let response_e = smol::block_on(server.execute(envelope)).unwrap();
+            decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier)
+                .unwrap()
+                .data
+        };
+
+        // Middlewares run in order before the module, and in reverse after.
+        assert_eq!(execute("echo.run", vec![0]), Ok(vec![0, 1, 2]));
+        assert_eq!(
+            std::mem::take(&mut *seen.lock().unwrap()),
+            vec![(2, Ok(vec![0, 1, 2])), (1, Ok(vec![0, 1, 2]))]
+        );
+
+        assert_eq!(execute("echo.other", vec![0]), Ok(vec![0, 1]));
+        assert_eq!(
+            std::mem::take(&mut *seen.lock().unwrap()),
+            vec![(1, Ok(vec![0, 1]))]
+        );
+
+        // A middleware denying a request skips the module, and the previous
+        // middlewares see the error.
+        let err = execute("echo.run", vec![2]).unwrap_err();
+        assert_eq!(err.code(), ManyError::unknown("").code());
+        assert_eq!(
+            std::mem::take(&mut *seen.lock().unwrap()),
+            vec![(1, Err(err))]
+        );
+    }
+
+    #[test]
+    fn server_readiness_checks() {
+        let server = ManyServer::test(AnonymousIdentity);
+        let storage_open = Arc::new(AtomicBool::new(true));
+        {
+            let storage_open = storage_open.clone();
+            server
+                .lock()
+                .unwrap()
+                .add_readiness_check("abci", || Ok(()))
+                .add_readiness_check("storage", move || {
+                    if storage_open.load(Ordering::Relaxed) {
+                        Ok(())
+                    } else {
+                        Err("Storage closed".to_string())
+                    }
+                });
+        }
+        assert!(server.ready().ready);
+
+        storage_open.store(false, Ordering::Relaxed);
+        let ready = server.ready();
+        assert!(!ready.ready);
+        assert!(ready.checks["abci"].ok);
+        assert_eq!(
+            ready.checks["storage"].reason,
+            Some("Storage closed".to_string())
+        );
+
+        // The same checks are served by the base module.
+        let request = RequestMessageBuilder::default()
+            .method("ready".to_string())
+            .timestamp(Timestamp::now())
+            .build()
+            .unwrap();
+        let envelope = encode_cose_sign1_from_request(request, &AnonymousIdentity).unwrap();
+        let response_e = smol::block_on(server.execute(envelope)).unwrap();
+        let response = decode_response_from_cose_sign1(&response_e, None, &AcceptAllVerifier)
+            .unwrap()
+            .data
+            .unwrap();
+        assert_eq!(
+            minicbor::decode::<base::ReadyReturn>(&response).unwrap(),
+            ready
+        );
+    }
 }
@@ -0,0 +1,207 @@
+//! Spans of MANY requests.
+//!
+//! Every request gets a span with its method, sender and result code. If the
+//! request has a [`TraceContext`] attribute, its trace ID is recorded as
+//! well and, with the `opentelemetry` feature, the span is reported as a
+//! child of the span of the client, so layers relaying the same envelope
+//! show up in a single trace.
+use many_protocol::{RequestMessage, ResponseMessage};
+use many_types::trace::TraceContext;
+use tracing::Span;
+
+/// Create the span of a request. Its fields are filled in by
+/// [`record_request`] and [`record_response`].
+#[macro_export]
+macro_rules! request_span {
+    ($name: literal) => {
+        ::tracing::info_span!(
+            $name,
+            otel.kind = "server",
+            method = ::tracing::field::Empty,
+            sender = ::tracing::field::Empty,
+            trace_id = ::tracing::field::Empty,
+            code = ::tracing::field::Empty,
+        )
+    };
+}
+
+pub fn record_request(span: &Span, message: &RequestMessage) {
+    span.record("method", message.method.as_str());
+    span.record(
+        "sender",
+        message.from.unwrap_or_default().to_string().as_str(),
+    );
+
+    if let Ok(trace) = message.attributes.get::<TraceContext>() {
+        span.record("trace_id", trace.trace_id_hex().as_str());
+        set_parent(span, &trace);
+    }
+}
+
+/// Record the result code of a request, 0 for a success.
+pub fn record_response(span: &Span, response: &ResponseMessage) {
+    let code: i64 = match &response.data {
+        Ok(_) => 0,
+        Err(e) => e.code().into(),
+    };
+    span.record("code", code);
+}
+
+#[cfg(feature = "opentelemetry")]
+fn set_parent(span: &Span, trace: &TraceContext) {
+    use opentelemetry::trace::{
+        SpanContext, SpanId, TraceContextExt, TraceFlags, TraceId, TraceState,
+    };
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    let parent = SpanContext::new(
+        TraceId::from_bytes(trace.trace_id),
+        SpanId::from_bytes(trace.span_id),
+        TraceFlags::SAMPLED,
+        true,
+        TraceState::default(),
+    );
+    span.set_parent(opentelemetry::Context::new().with_remote_span_context(parent));
+}
+
+#[cfg(not(feature = "opentelemetry"))]
+fn set_parent(_span: &Span, _trace: &TraceContext) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::LowLevelManyRequestHandler;
+    use crate::ManyServer;
+    use async_trait::async_trait;
+    use many_error::ManyError;
+    use many_identity::{AcceptAllVerifier, AnonymousIdentity};
+    use many_modules::{ManyModule, ManyModuleInfo};
+    use many_protocol::{
+        decode_response_from_cose_sign1, encode_cose_sign1_from_request, RequestMessageBuilder,
+    };
+    use many_types::attributes::AttributeSet;
+    use many_types::Timestamp;
+    use std::sync::{Arc, Mutex};
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Id, Record};
+    use tracing_subscriber::layer::{Context, SubscriberExt};
+    use tracing_subscriber::Layer;
+
+    /// Collects the trace IDs recorded in spans.
+    #[derive(Clone, Default)]
+    struct TraceIds(Arc<Mutex<Vec<String>>>);
+
+    impl Visit for TraceIds {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "trace_id" {
+                self.0.lock().unwrap().push(value.to_string());
+            }
+        }
+
+        fn record_debug(&mut self, _field: &Field, _value: &dyn std::fmt::Debug) {}
+    }
+
+    impl<S: tracing::Subscriber> Layer<S> for TraceIds {
+        fn on_record(&self, _span: &Id, values: &Record<'_>, _ctx: Context<'_, S>) {
+            values.record(&mut self.clone());
+        }
+    }
+
+    /// Answers with the trace ID of the span it executes in, as seen by
+    /// OpenTelemetry.
+    #[derive(Debug)]
+    struct CurrentTrace(ManyModuleInfo);
+
+    #[async_trait]
+    impl ManyModule for CurrentTrace {
+        fn info(&self) -> &ManyModuleInfo {
+            &self.0
+        }
+
+        async fn execute(&self, message: RequestMessage) -> Result<ResponseMessage, ManyError> {
+            Ok(ResponseMessage::from_request(
+                &message,
+                &message.to,
+                Ok(current_trace_id()),
+            ))
+        }
+    }
+
+    #[cfg(feature = "opentelemetry")]
+    fn current_trace_id() -> Vec<u8> {
+        use opentelemetry::trace::TraceContextExt;
+        use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+        Span::current()
+            .context()
+            .span()
+            .span_context()
+            .trace_id()
+            .to_bytes()
+            .to_vec()
+    }
+
+    #[cfg(not(feature = "opentelemetry"))]
+    fn current_trace_id() -> Vec<u8> {
+        vec![]
+    }
+
+    async fn execute(server: &Arc<Mutex<ManyServer>>, trace: Option<TraceContext>) -> Vec<u8> {
+        let mut builder = RequestMessageBuilder::default();
+        builder
+            .method("trace.current".to_string())
+            .timestamp(Timestamp::now())
+            .nonce(vec![0]);
+        if let Some(trace) = trace {
+            builder.attributes(AttributeSet::from_iter([trace.into()]));
+        }
+        let envelope =
+            encode_cose_sign1_from_request(builder.build().unwrap(), &AnonymousIdentity).unwrap();
+
+        let response = server.execute(envelope).await.unwrap();
+        decode_response_from_cose_sign1(&response, None, &AcceptAllVerifier)
+            .unwrap()
+            .data
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn trace_context_is_the_parent() {
+        let trace_ids = TraceIds::default();
+        let subscriber = tracing_subscriber::registry().with(trace_ids.clone());
+        #[cfg(feature = "opentelemetry")]
+        let subscriber = {
+            use opentelemetry::trace::TracerProvider;
+            let provider = opentelemetry::sdk::trace::TracerProvider::default();
+            subscriber.with(tracing_opentelemetry::layer().with_tracer(provider.tracer("test")))
+        };
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let server = ManyServer::test(AnonymousIdentity);
+        server
+            .lock()
+            .unwrap()
+            .add_module(CurrentTrace(ManyModuleInfo {
+                name: "CurrentTrace".to_string(),
+                attribute: None,
+                endpoints: vec!["trace.current".to_string()],
+                version: Default::default(),
+            }));
+
+        let trace = TraceContext::new([1; 16], [2; 8]);
+        let data = execute(&server, Some(trace)).await;
+        assert_eq!(*trace_ids.0.lock().unwrap(), vec![trace.trace_id_hex()]);
+        #[cfg(feature = "opentelemetry")]
+        assert_eq!(data, trace.trace_id.to_vec());
+        #[cfg(not(feature = "opentelemetry"))]
+        assert!(data.is_empty());
+
+        // Without a trace context, the span starts a new trace.
+        let data = execute(&server, None).await;
+        assert_eq!(trace_ids.0.lock().unwrap().len(), 1);
+        #[cfg(feature = "opentelemetry")]
+        assert_ne!(data, trace.trace_id.to_vec());
+        #[cfg(not(feature = "opentelemetry"))]
+        assert!(data.is_empty());
+    }
+}
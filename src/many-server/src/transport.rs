@@ -1,14 +1,25 @@
 use async_trait::async_trait;
 use coset::CoseSign1;
 use many_error::ManyError;
+use many_modules::base::ReadyReturn;
 use many_protocol::{RequestMessage, ResponseMessage};
 use std::fmt::Debug;
 
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod http;
+#[cfg(feature = "tower")]
+pub mod tower;
 
 #[async_trait]
 pub trait LowLevelManyRequestHandler: Send + Sync + Debug {
     async fn execute(&self, envelope: CoseSign1) -> Result<CoseSign1, String>;
+
+    /// Whether the handler can serve requests, for the transports to answer
+    /// readiness probes. See [`crate::health`].
+    fn ready(&self) -> ReadyReturn {
+        ReadyReturn::from_checks(Default::default())
+    }
 }
 
 /// A simpler version of the [ManyRequestHandler] which only deals with methods and payloads.
@@ -0,0 +1,299 @@
+//! A gRPC transport for the MANY protocol. The service is described in
+//! `proto/many.proto`; request and response envelopes are the same tagged
+//! COSE_Sign1 bytes sent over HTTP, wrapped in a `google.protobuf.BytesValue`.
+use crate::transport::LowLevelManyRequestHandler;
+use coset::{CoseSign1, TaggedCborSerializable};
+use futures::{Stream, StreamExt};
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tonic::codec::{ProstCodec, Streaming};
+use tonic::codegen::{empty_body, http, BoxFuture, Context, Poll, Service, StdError};
+use tonic::server::{Grpc, NamedService, StreamingService, UnaryService};
+use tonic::transport::{Certificate, Identity, Server, ServerTlsConfig};
+use tonic::Status;
+use tracing::info;
+
+/// Maximum of 5MB per envelope, like the HTTP transport.
+const MAX_MESSAGE_LEN: usize = 1024 * 1024 * 5;
+
+const CALL_PATH: &str = "/many.Many/Call";
+const CALL_STREAM_PATH: &str = "/many.Many/CallStream";
+
+type ResponseStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, Status>> + Send>>;
+
+async fn execute<E: LowLevelManyRequestHandler>(
+    executor: &E,
+    bytes: Vec<u8>,
+) -> Result<Vec<u8>, Status> {
+    tracing::debug!("request  len={}", bytes.len());
+    tracing::trace!("request  {}", hex::encode(&bytes));
+
+    let envelope = CoseSign1::from_tagged_slice(&bytes).map_err(|e| {
+        tracing::error!(r#"Error decoding envelope. Error description="{e}""#);
+        Status::invalid_argument(format!("Could not decode envelope: {e}"))
+    })?;
+
+    let bytes = executor
+        .execute(envelope)
+        .await
+        .and_then(|r| r.to_tagged_vec().map_err(|e| e.to_string()))
+        .map_err(|e| {
+            tracing::error!(r#"Error getting response. Error description="{e}""#);
+            Status::internal(e)
+        })?;
+    tracing::debug!("response len={}", bytes.len());
+    tracing::trace!("response {}", hex::encode(&bytes));
+
+    Ok(bytes)
+}
+
+struct Call<E>(Arc<E>);
+
+impl<E: LowLevelManyRequestHandler + 'static> UnaryService<Vec<u8>> for Call<E> {
+    type Response = Vec<u8>;
+    type Future = BoxFuture<tonic::Response<Vec<u8>>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Vec<u8>>) -> Self::Future {
+        let executor = Arc::clone(&self.0);
+        Box::pin(async move {
+            execute(executor.as_ref(), request.into_inner())
+                .await
+                .map(tonic::Response::new)
+        })
+    }
+}
+
+struct CallStream<E>(Arc<E>);
+
+impl<E: LowLevelManyRequestHandler + 'static> StreamingService<Vec<u8>> for CallStream<E> {
+    type Response = Vec<u8>;
+    type ResponseStream = ResponseStream;
+    type Future = BoxFuture<tonic::Response<ResponseStream>, Status>;
+
+    fn call(&mut self, request: tonic::Request<Streaming<Vec<u8>>>) -> Self::Future {
+        let executor = Arc::clone(&self.0);
+        // Envelopes are executed one at a time, so responses keep the order
+        // of the requests.
+        let responses = request.into_inner().then(move |bytes| {
+            let executor = Arc::clone(&executor);
+            async move { execute(executor.as_ref(), bytes?).await }
+        });
+        Box::pin(async move { Ok(tonic::Response::new(Box::pin(responses) as ResponseStream)) })
+    }
+}
+
+/// The `many.Many` gRPC service, forwarding envelopes to an executor.
+#[derive(Debug)]
+pub struct ManyGrpcService<E>(Arc<E>);
+
+impl<E> Clone for ManyGrpcService<E> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<E> NamedService for ManyGrpcService<E> {
+    const NAME: &'static str = "many.Many";
+}
+
+impl<E, B> Service<http::Request<B>> for ManyGrpcService<E>
+where
+    E: LowLevelManyRequestHandler + 'static,
+    B: tonic::codegen::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = std::convert::Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        let executor = Arc::clone(&self.0);
+        let mut grpc = Grpc::new(ProstCodec::<Vec<u8>, Vec<u8>>::default())
+            .max_decoding_message_size(MAX_MESSAGE_LEN);
+
+        match request.uri().path() {
+            CALL_PATH => Box::pin(async move { Ok(grpc.unary(Call(executor), request).await) }),
+            CALL_STREAM_PATH => {
+                Box::pin(async move { Ok(grpc.streaming(CallStream(executor), request).await) })
+            }
+            _ => Box::pin(async move {
+                Ok(http::Response::builder()
+                    .status(200)
+                    .header("grpc-status", tonic::Code::Unimplemented as i32)
+                    .header("content-type", "application/grpc")
+                    .body(empty_body())
+                    .unwrap())
+            }),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct GrpcServer<E: LowLevelManyRequestHandler> {
+    executor: Arc<E>,
+    tls: Option<ServerTlsConfig>,
+    term_signal: Arc<AtomicBool>,
+}
+
+impl<E: LowLevelManyRequestHandler + 'static> GrpcServer<E> {
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor: Arc::new(executor),
+            tls: None,
+            term_signal: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Serve over TLS with the given PEM certificate chain and private key.
+    /// If a client CA root certificate is given, clients need a certificate
+    /// signed by it to connect (mTLS).
+    pub fn with_tls(
+        mut self,
+        cert: impl AsRef<[u8]>,
+        key: impl AsRef<[u8]>,
+        client_ca: Option<impl AsRef<[u8]>>,
+    ) -> Self {
+        let mut tls = ServerTlsConfig::new().identity(Identity::from_pem(cert, key));
+        if let Some(ca) = client_ca {
+            tls = tls.client_ca_root(Certificate::from_pem(ca));
+        }
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Returns a mutable reference to an atomic bool. Set the bool to true to kill
+    /// the server.
+    pub fn term_signal(&mut self) -> Arc<AtomicBool> {
+        Arc::clone(&self.term_signal)
+    }
+
+    pub async fn bind(&self, addr: SocketAddr) -> Result<(), anyhow::Error> {
+        let mut builder = Server::builder();
+        if let Some(tls) = &self.tls {
+            builder = builder.tls_config(tls.clone())?;
+        }
+
+        let term_signal = Arc::clone(&self.term_signal);
+        builder
+            .add_service(ManyGrpcService(Arc::clone(&self.executor)))
+            .serve_with_shutdown(addr, async move {
+                while !term_signal.load(Ordering::Relaxed) {
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                info!("gRPC server shutting down gracefully...");
+            })
+            .await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::CoseSign1Builder;
+    use tonic::client::Grpc as GrpcClient;
+    use tonic::codegen::http::uri::PathAndQuery;
+    use tonic::Code;
+
+    /// Answers every envelope with itself.
+    #[derive(Debug)]
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl LowLevelManyRequestHandler for Echo {
+        async fn execute(&self, envelope: CoseSign1) -> Result<CoseSign1, String> {
+            Ok(envelope)
+        }
+    }
+
+    /// A client calling the service in process, without a transport.
+    fn client() -> GrpcClient<ManyGrpcService<Echo>> {
+        GrpcClient::new(ManyGrpcService(Arc::new(Echo)))
+    }
+
+    fn envelope(payload: &[u8]) -> Vec<u8> {
+        CoseSign1Builder::new()
+            .payload(payload.to_vec())
+            .build()
+            .to_tagged_vec()
+            .unwrap()
+    }
+
+    async fn call(bytes: Vec<u8>) -> Result<Vec<u8>, Status> {
+        let mut client = client();
+        client.ready().await.unwrap();
+        client
+            .unary(
+                tonic::Request::new(bytes),
+                PathAndQuery::from_static(CALL_PATH),
+                ProstCodec::default(),
+            )
+            .await
+            .map(tonic::Response::into_inner)
+    }
+
+    #[tokio::test]
+    async fn call_roundtrip() {
+        let envelope = envelope(b"hello");
+        assert_eq!(call(envelope.clone()).await.unwrap(), envelope);
+    }
+
+    #[tokio::test]
+    async fn call_invalid_envelope() {
+        let status = call(vec![1, 2, 3]).await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn call_stream_keeps_order() {
+        let requests = vec![envelope(b"first"), envelope(b"second"), vec![1, 2, 3]];
+
+        let mut client = client();
+        client.ready().await.unwrap();
+        let mut responses = client
+            .streaming(
+                tonic::Request::new(futures::stream::iter(requests.clone())),
+                PathAndQuery::from_static(CALL_STREAM_PATH),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap()
+            .into_inner();
+
+        assert_eq!(
+            responses.message().await.unwrap(),
+            Some(requests[0].clone())
+        );
+        assert_eq!(
+            responses.message().await.unwrap(),
+            Some(requests[1].clone())
+        );
+
+        // The invalid envelope ends the stream with its error.
+        let status = responses.message().await.unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn unknown_method() {
+        let mut client = client();
+        client.ready().await.unwrap();
+        let status = client
+            .unary::<_, Vec<u8>, _>(
+                tonic::Request::new(envelope(b"hello")),
+                PathAndQuery::from_static("/many.Many/Unknown"),
+                ProstCodec::default(),
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(status.code(), Code::Unimplemented);
+    }
+}
@@ -1,3 +1,4 @@
+use crate::health::ready_body;
 use crate::transport::LowLevelManyRequestHandler;
 use anyhow::anyhow;
 use coset::{CoseSign1, TaggedCborSerializable};
@@ -7,72 +8,270 @@ use std::net::ToSocketAddrs;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tiny_http::{Request, Response};
+use tiny_http::{Header, Method, Request, Response};
+use tokio::sync::Semaphore;
 use tracing::info;
 
 /// Maximum of 5MB per HTTP request.
 const READ_BUFFER_LEN: usize = 1024 * 1024 * 5;
 
-#[derive(Debug)]
-pub struct HttpServer<E: LowLevelManyRequestHandler> {
-    executor: E,
-    term_signal: Arc<AtomicBool>,
+/// Maximum number of requests handled at the same time, by default.
+pub const DEFAULT_HTTP_CONCURRENCY: usize = 64;
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+fn empty_response(status: u16) -> HttpResponse {
+    Response::empty(status).with_data(Cursor::new(vec![]), Some(0))
 }
 
-impl<E: LowLevelManyRequestHandler> HttpServer<E> {
-    pub fn new(executor: E) -> Self {
-        Self {
-            executor,
-            term_signal: Arc::new(AtomicBool::new(false)),
+/// The CORS policy of an [`HttpServer`], for browsers to call it from the
+/// pages of other origins. Preflight requests are answered by the server
+/// itself.
+#[derive(Clone, Debug, Default)]
+pub struct CorsConfig {
+    /// The origins allowed to call the server, e.g. `https://wallet.example`,
+    /// or any origin if it contains `*`.
+    pub allowed_origins: Vec<String>,
+
+    /// The request headers allowed, besides `Content-Type`.
+    pub allowed_headers: Vec<String>,
+
+    /// How long browsers can cache the answer to a preflight request.
+    pub max_age: Option<Duration>,
+}
+
+impl CorsConfig {
+    pub fn any_origin() -> Self {
+        Self::default().with_origin("*")
+    }
+
+    pub fn with_origin(mut self, origin: impl Into<String>) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    pub fn with_header(mut self, header: impl Into<String>) -> Self {
+        self.allowed_headers.push(header.into());
+        self
+    }
+
+    pub fn with_max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    /// The value of `Access-Control-Allow-Origin` for a request from
+    /// `origin`, if it is allowed.
+    fn allow_origin(&self, origin: &str) -> Option<&str> {
+        if self.allowed_origins.iter().any(|o| o == "*") {
+            Some("*")
+        } else {
+            self.allowed_origins
+                .iter()
+                .find(|o| o.trim_end_matches('/') == origin)
+                .map(|_| origin)
         }
     }
 
-    async fn handle_request(&self, request: &mut Request) -> Response<std::io::Cursor<Vec<u8>>> {
-        match request.body_length() {
-            Some(x) if x > READ_BUFFER_LEN => {
-                // This is a transport error, and as such an HTTP error.
-                // Return a "413: Content Too Large" error.
-                tracing::error!("413: Content Too Large : {x} bytes");
-                return Response::empty(413u16).with_data(Cursor::new(vec![]), Some(0));
+    /// The CORS headers of the response to `request`, or `None` if its origin
+    /// is not allowed. Requests without an origin do not come from a browser,
+    /// and need no headers.
+    fn headers(&self, request: &Request) -> Option<Vec<Header>> {
+        let Some(origin) = request
+            .headers()
+            .iter()
+            .find(|h| h.field.equiv("Origin"))
+            .map(|h| h.value.as_str())
+        else {
+            return Some(vec![]);
+        };
+        let allowed = self.allow_origin(origin)?;
+
+        let mut headers = vec![header("Access-Control-Allow-Origin", allowed)];
+        if allowed != "*" {
+            headers.push(header("Vary", "Origin"));
+        }
+        if request.method() == &Method::Options {
+            let allowed_headers = std::iter::once("Content-Type")
+                .chain(self.allowed_headers.iter().map(String::as_str))
+                .collect::<Vec<_>>()
+                .join(", ");
+            headers.push(header("Access-Control-Allow-Methods", "POST, OPTIONS"));
+            headers.push(header("Access-Control-Allow-Headers", &allowed_headers));
+            if let Some(max_age) = self.max_age {
+                headers.push(header(
+                    "Access-Control-Max-Age",
+                    &max_age.as_secs().to_string(),
+                ));
             }
-            _ => {}
         }
+        Some(headers)
+    }
+}
 
-        let mut v = Vec::new();
-        let _ = request.as_reader().read_to_end(&mut v);
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("Invalid header")
+}
 
-        let bytes = &v;
+/// Read and decode the envelope of a request.
+fn read_envelope(request: &mut Request) -> Result<CoseSign1, HttpResponse> {
+    match request.body_length() {
+        Some(x) if x > READ_BUFFER_LEN => {
+            // This is a transport error, and as such an HTTP error.
+            // Return a "413: Content Too Large" error.
+            tracing::error!("413: Content Too Large : {x} bytes");
+            return Err(empty_response(413));
+        }
+        _ => {}
+    }
 
-        tracing::debug!("request  len={}", bytes.len());
-        tracing::trace!("request  {}", hex::encode(bytes));
+    let mut v = Vec::new();
+    let _ = request.as_reader().read_to_end(&mut v);
 
-        let envelope = match CoseSign1::from_tagged_slice(bytes) {
-            Ok(cs) => cs,
-            Err(e) => {
-                tracing::error!(
-                    r#"Error decoding envelope. Error description="{}""#,
-                    e.to_string()
-                );
-                return Response::empty(500u16).with_data(Cursor::new(vec![]), Some(0));
-            }
-        };
+    let bytes = &v;
 
-        let response = self
-            .executor
-            .execute(envelope)
-            .await
-            .and_then(|r| r.to_tagged_vec().map_err(|e| e.to_string()));
-        let bytes = match response {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                tracing::error!(r#"Error getting response. Error description="{}""#, e);
-                return Response::empty(500u16).with_data(Cursor::new(vec![]), Some(0));
-            }
+    tracing::debug!("request  len={}", bytes.len());
+    tracing::trace!("request  {}", hex::encode(bytes));
+
+    CoseSign1::from_tagged_slice(bytes).map_err(|e| {
+        tracing::error!(
+            r#"Error decoding envelope. Error description="{}""#,
+            e.to_string()
+        );
+        empty_response(500)
+    })
+}
+
+async fn execute_envelope<E: LowLevelManyRequestHandler>(
+    executor: &E,
+    envelope: CoseSign1,
+) -> HttpResponse {
+    let response = executor
+        .execute(envelope)
+        .await
+        .and_then(|r| r.to_tagged_vec().map_err(|e| e.to_string()));
+    let bytes = match response {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!(r#"Error getting response. Error description="{}""#, e);
+            return empty_response(500);
+        }
+    };
+    tracing::debug!("response len={}", bytes.len());
+    tracing::trace!("response {}", hex::encode(&bytes));
+
+    Response::from_data(bytes)
+}
+
+/// Answer the liveness and readiness probes, at `GET /health` and
+/// `GET /ready`. See [`crate::health`].
+fn probe_response<E: LowLevelManyRequestHandler>(
+    executor: &E,
+    request: &Request,
+) -> Option<HttpResponse> {
+    if request.method() != &Method::Get {
+        return None;
+    }
+    match request.url().split('?').next() {
+        Some("/health") => Some(Response::from_string("ok\n")),
+        Some("/ready") => {
+            let ready = executor.ready();
+            let status = if ready.ready { 200 } else { 503 };
+            Some(Response::from_string(ready_body(&ready)).with_status_code(status))
+        }
+        _ => None,
+    }
+}
+
+fn with_headers(response: HttpResponse, headers: Vec<Header>) -> HttpResponse {
+    headers
+        .into_iter()
+        .fold(response, |response, header| response.with_header(header))
+}
+
+/// Read, execute and respond to a request. Reading and responding block on
+/// the connection, so they run on the blocking pool of the runtime.
+async fn handle_request<E: LowLevelManyRequestHandler>(
+    executor: &E,
+    cors: Option<&CorsConfig>,
+    mut request: Request,
+) {
+    let cors_headers = cors.map(|cors| cors.headers(&request));
+
+    // Preflight requests of browsers are answered without an envelope.
+    if cors.is_some() && request.method() == &Method::Options {
+        let response = match cors_headers.flatten() {
+            Some(headers) => with_headers(empty_response(204), headers),
+            None => empty_response(403),
         };
-        tracing::debug!("response len={}", bytes.len());
-        tracing::trace!("response {}", hex::encode(&bytes));
+        let _ = tokio::task::spawn_blocking(move || request.respond(response)).await;
+        return;
+    }
 
-        Response::from_data(bytes)
+    if let Some(response) = probe_response(executor, &request) {
+        let response = with_headers(response, cors_headers.flatten().unwrap_or_default());
+        let _ = tokio::task::spawn_blocking(move || request.respond(response)).await;
+        return;
+    }
+
+    let Ok((request, envelope)) = tokio::task::spawn_blocking(move || {
+        let envelope = read_envelope(&mut request);
+        (request, envelope)
+    })
+    .await
+    else {
+        return;
+    };
+
+    let response = match envelope {
+        Ok(envelope) => execute_envelope(executor, envelope).await,
+        Err(response) => response,
+    };
+    let response = with_headers(response, cors_headers.flatten().unwrap_or_default());
+
+    // If there's a transport error (e.g. connection closed) on the response itself,
+    // we don't actually care and just continue waiting for the next request.
+    let _ = tokio::task::spawn_blocking(move || request.respond(response)).await;
+}
+
+/// A MANY server over HTTP.
+///
+/// Requests are handled concurrently, up to a limit: each one is decoded and
+/// verified on its own task, and only waits for the other requests to execute
+/// (e.g. for the lock of the module it calls). This must run within a Tokio
+/// runtime.
+#[derive(Debug)]
+pub struct HttpServer<E: LowLevelManyRequestHandler> {
+    executor: Arc<E>,
+    term_signal: Arc<AtomicBool>,
+    concurrency: usize,
+    cors: Option<Arc<CorsConfig>>,
+}
+
+impl<E: LowLevelManyRequestHandler + 'static> HttpServer<E> {
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor: Arc::new(executor),
+            term_signal: Arc::new(AtomicBool::new(false)),
+            concurrency: DEFAULT_HTTP_CONCURRENCY,
+            cors: None,
+        }
+    }
+
+    /// Set the maximum number of requests handled at the same time. Further
+    /// requests wait in the listening queue. A concurrency of 1 handles
+    /// requests one after the other.
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    /// Answer the requests of browsers from other origins, following a CORS
+    /// policy. Without one, responses have no CORS headers, and browsers
+    /// only let pages of the same origin read them.
+    pub fn with_cors(mut self, cors: CorsConfig) -> Self {
+        self.cors = Some(Arc::new(cors));
+        self
     }
 
     /// Returns a mutable reference to an atomic bool. Set the bool to true to kill
@@ -82,15 +281,24 @@ impl<E: LowLevelManyRequestHandler> HttpServer<E> {
     }
 
     pub async fn bind<A: ToSocketAddrs>(&self, addr: A) -> Result<(), anyhow::Error> {
-        let server = tiny_http::Server::http(addr).map_err(|e| anyhow!("{}", e))?;
+        let server = Arc::new(tiny_http::Server::http(addr).map_err(|e| anyhow!("{}", e))?);
+        let slots = Arc::new(Semaphore::new(self.concurrency));
 
         loop {
-            if let Some(mut request) = server.recv_timeout(Duration::from_millis(100))? {
-                let response = self.handle_request(&mut request).await;
+            // Wait for a free slot before accepting a request.
+            let slot = slots.clone().acquire_owned().await?;
+            let s = server.clone();
+            let request =
+                tokio::task::spawn_blocking(move || s.recv_timeout(Duration::from_millis(100)))
+                    .await??;
 
-                // If there's a transport error (e.g. connection closed) on the response itself,
-                // we don't actually care and just continue waiting for the next request.
-                let _ = request.respond(response);
+            if let Some(request) = request {
+                let executor = self.executor.clone();
+                let cors = self.cors.clone();
+                tokio::spawn(async move {
+                    handle_request(executor.as_ref(), cors.as_deref(), request).await;
+                    drop(slot);
+                });
             }
 
             // Check for the term signal and break out.
@@ -100,6 +308,226 @@ impl<E: LowLevelManyRequestHandler> HttpServer<E> {
             }
         }
 
+        // Let the requests in flight finish.
+        drop(slots.acquire_many(self.concurrency as u32).await?);
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Mutex;
+    use std::time::Instant;
+
+    /// Takes `delay` to verify each envelope, then executes them one at a
+    /// time, like a server verifying signatures before locking its modules.
+    #[derive(Debug)]
+    struct SlowVerifier {
+        delay: Duration,
+        lock: Mutex<()>,
+    }
+
+    #[async_trait]
+    impl LowLevelManyRequestHandler for SlowVerifier {
+        async fn execute(&self, envelope: CoseSign1) -> Result<CoseSign1, String> {
+            std::thread::sleep(self.delay);
+            let _guard = self.lock.lock().unwrap();
+            Ok(envelope)
+        }
+    }
+
+    fn post(port: u16, body: &[u8]) -> Vec<u8> {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "POST / HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(body).unwrap();
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).unwrap();
+        let start = response
+            .windows(4)
+            .position(|w| w == b"\r\n\r\n")
+            .expect("Invalid HTTP response")
+            + 4;
+        response.split_off(start)
+    }
+
+    /// Send a request without a body, returning the head of the response.
+    fn head(port: u16, method: &str, headers: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "{method} / HTTP/1.1\r\nHost: localhost\r\n{headers}Content-Length: 0\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response.split("\r\n\r\n").next().unwrap().to_string()
+    }
+
+    /// Send a `GET` request, returning the whole response.
+    fn get(port: u16, path: &str) -> String {
+        let mut stream = TcpStream::connect(("127.0.0.1", port)).unwrap();
+        write!(
+            stream,
+            "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        response
+    }
+
+    /// Start a server on a free port, returning the port and its term signal.
+    fn serve(concurrency: usize, delay: Duration) -> (u16, Arc<AtomicBool>) {
+        serve_with(
+            HttpServer::new(SlowVerifier {
+                delay,
+                lock: Mutex::new(()),
+            })
+            .with_concurrency(concurrency),
+        )
+    }
+
+    fn serve_with(mut server: HttpServer<SlowVerifier>) -> (u16, Arc<AtomicBool>) {
+        let port = std::net::TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let term = server.term_signal();
+        tokio::spawn(async move { server.bind(("127.0.0.1", port)).await.unwrap() });
+        std::thread::sleep(Duration::from_millis(200));
+        (port, term)
+    }
+
+    /// Send a burst of `n` requests at once, returning how long it took for
+    /// all of them to be answered.
+    fn burst(port: u16, n: usize) -> Duration {
+        let envelope = CoseSign1::default().to_tagged_vec().unwrap();
+        let start = Instant::now();
+        let clients: Vec<_> = (0..n)
+            .map(|_| {
+                let envelope = envelope.clone();
+                std::thread::spawn(move || post(port, &envelope))
+            })
+            .collect();
+        for client in clients {
+            assert_eq!(client.join().unwrap(), envelope);
+        }
+        start.elapsed()
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn invalid_envelope() {
+        let (port, term) = serve(4, Duration::ZERO);
+        let response = tokio::task::spawn_blocking(move || post(port, b"not an envelope")).await;
+        assert!(response.unwrap().is_empty());
+        term.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn cors() {
+        let cors = CorsConfig::default()
+            .with_origin("https://wallet.example")
+            .with_header("X-Request-Id")
+            .with_max_age(Duration::from_secs(600));
+        let (port, term) = serve_with(
+            HttpServer::new(SlowVerifier {
+                delay: Duration::ZERO,
+                lock: Mutex::new(()),
+            })
+            .with_cors(cors),
+        );
+
+        let responses = tokio::task::spawn_blocking(move || {
+            [
+                head(port, "OPTIONS", "Origin: https://wallet.example\r\n"),
+                head(port, "OPTIONS", "Origin: https://evil.example\r\n"),
+                head(port, "POST", "Origin: https://wallet.example\r\n"),
+            ]
+        })
+        .await
+        .unwrap();
+
+        let preflight = &responses[0];
+        assert!(preflight.starts_with("HTTP/1.1 204"), "{preflight}");
+        assert!(preflight.contains("Access-Control-Allow-Origin: https://wallet.example"));
+        assert!(preflight.contains("Access-Control-Allow-Headers: Content-Type, X-Request-Id"));
+        assert!(preflight.contains("Access-Control-Max-Age: 600"));
+
+        assert!(responses[1].starts_with("HTTP/1.1 403"), "{}", responses[1]);
+
+        // The envelope is invalid, but the browser can still read the error.
+        let post = &responses[2];
+        assert!(post.starts_with("HTTP/1.1 500"), "{post}");
+        assert!(post.contains("Access-Control-Allow-Origin: https://wallet.example"));
+        assert!(!post.contains("Access-Control-Max-Age"));
+        term.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn probes() {
+        let (port, term) = serve(4, Duration::ZERO);
+        let [health, ready, other] = tokio::task::spawn_blocking(move || {
+            [
+                get(port, "/health"),
+                get(port, "/ready?verbose"),
+                get(port, "/"),
+            ]
+        })
+        .await
+        .unwrap();
+
+        assert!(health.starts_with("HTTP/1.1 200"), "{health}");
+        assert!(health.ends_with("\r\n\r\nok\n"), "{health}");
+        assert!(ready.starts_with("HTTP/1.1 200"), "{ready}");
+
+        // Other requests still need an envelope.
+        assert!(other.starts_with("HTTP/1.1 500"), "{other}");
+        term.store(true, Ordering::Relaxed);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn concurrent_requests() {
+        let delay = Duration::from_millis(100);
+        let (port, term) = serve(8, delay);
+
+        // Verifying 8 envelopes serially would take 800ms.
+        let elapsed = tokio::task::spawn_blocking(move || burst(port, 8))
+            .await
+            .unwrap();
+        assert!(elapsed < delay * 6, "took {elapsed:?}");
+        term.store(true, Ordering::Relaxed);
+    }
+
+    /// Compare the time to answer bursts of queries, serially and
+    /// concurrently. Run with
+    /// `cargo test -p many-server --release -- --ignored --nocapture burst`.
+    #[ignore]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn burst_benchmark() {
+        let delay = Duration::from_millis(5);
+        for concurrency in [1, 4, 16, DEFAULT_HTTP_CONCURRENCY] {
+            let (port, term) = serve(concurrency, delay);
+            let elapsed = tokio::task::spawn_blocking(move || burst(port, 256))
+                .await
+                .unwrap();
+            println!(
+                "concurrency {concurrency:>3}: 256 queries in {elapsed:?} ({:.0} queries/s)",
+                256.0 / elapsed.as_secs_f64()
+            );
+            term.store(true, Ordering::Relaxed);
+        }
+    }
+}
@@ -0,0 +1,174 @@
+//! A [`tower_service::Service`] answering MANY requests over HTTP, to mount
+//! a MANY endpoint in an existing hyper or axum application instead of
+//! running the [`HttpServer`](super::http::HttpServer) on its own port.
+//!
+//! With axum, for example:
+//!
+//! ```ignore
+//! let many = ManyServer::simple("my-app", identity, verifier, None);
+//! let app = axum::Router::new()
+//!     .route("/health", axum::routing::get(|| async { "ok" }))
+//!     .route_service("/api", ManyService::new(many));
+//! ```
+use crate::transport::LowLevelManyRequestHandler;
+use coset::{CoseSign1, TaggedCborSerializable};
+use futures::future::BoxFuture;
+use http::{Request, Response, StatusCode};
+use http_body::{Body as HttpBody, Limited};
+use hyper::Body;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower_service::Service;
+
+/// Maximum of 5MB per HTTP request, like the HTTP transport.
+const READ_BUFFER_LEN: usize = 1024 * 1024 * 5;
+
+fn empty_response(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .unwrap()
+}
+
+async fn handle_request<E, B>(executor: &E, request: Request<B>) -> Response<Body>
+where
+    E: LowLevelManyRequestHandler,
+    B: HttpBody,
+    B::Error: std::error::Error + Send + Sync + 'static,
+{
+    let bytes =
+        match hyper::body::to_bytes(Limited::new(request.into_body(), READ_BUFFER_LEN)).await {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                // This is a transport error, and as such an HTTP error.
+                tracing::error!(r#"Error reading request. Error description="{e}""#);
+                return if e.is::<http_body::LengthLimitError>() {
+                    empty_response(StatusCode::PAYLOAD_TOO_LARGE)
+                } else {
+                    empty_response(StatusCode::BAD_REQUEST)
+                };
+            }
+        };
+
+    tracing::debug!("request  len={}", bytes.len());
+    tracing::trace!("request  {}", hex::encode(&bytes));
+
+    let envelope = match CoseSign1::from_tagged_slice(&bytes) {
+        Ok(cs) => cs,
+        Err(e) => {
+            tracing::error!(r#"Error decoding envelope. Error description="{e}""#);
+            return empty_response(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    let response = executor
+        .execute(envelope)
+        .await
+        .and_then(|r| r.to_tagged_vec().map_err(|e| e.to_string()));
+    match response {
+        Ok(bytes) => {
+            tracing::debug!("response len={}", bytes.len());
+            tracing::trace!("response {}", hex::encode(&bytes));
+            Response::new(Body::from(bytes))
+        }
+        Err(e) => {
+            tracing::error!(r#"Error getting response. Error description="{e}""#);
+            empty_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// A service executing the envelope in the body of every request, whatever
+/// its method and path. Cloning the service shares the executor.
+#[derive(Debug)]
+pub struct ManyService<E> {
+    executor: Arc<E>,
+}
+
+impl<E: LowLevelManyRequestHandler> ManyService<E> {
+    pub fn new(executor: E) -> Self {
+        Self::from_arc(Arc::new(executor))
+    }
+
+    pub fn from_arc(executor: Arc<E>) -> Self {
+        Self { executor }
+    }
+}
+
+impl<E> Clone for ManyService<E> {
+    fn clone(&self) -> Self {
+        Self {
+            executor: Arc::clone(&self.executor),
+        }
+    }
+}
+
+impl<E, B> Service<Request<B>> for ManyService<E>
+where
+    E: LowLevelManyRequestHandler + 'static,
+    B: HttpBody + Send + 'static,
+    B::Data: Send,
+    B::Error: std::error::Error + Send + Sync + 'static,
+{
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let executor = Arc::clone(&self.executor);
+        Box::pin(async move { Ok(handle_request(executor.as_ref(), request).await) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use coset::CoseSign1Builder;
+
+    /// Answers every envelope with itself.
+    #[derive(Debug)]
+    struct Echo;
+
+    #[async_trait::async_trait]
+    impl LowLevelManyRequestHandler for Echo {
+        async fn execute(&self, envelope: CoseSign1) -> Result<CoseSign1, String> {
+            Ok(envelope)
+        }
+    }
+
+    fn call(body: Vec<u8>) -> (StatusCode, Vec<u8>) {
+        smol::block_on(async {
+            let response = ManyService::new(Echo)
+                .call(Request::new(Body::from(body)))
+                .await
+                .unwrap();
+            let status = response.status();
+            let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            (status, bytes.to_vec())
+        })
+    }
+
+    #[test]
+    fn execute() {
+        let envelope = CoseSign1Builder::new()
+            .payload(b"hello".to_vec())
+            .build()
+            .to_tagged_vec()
+            .unwrap();
+        assert_eq!(call(envelope.clone()), (StatusCode::OK, envelope));
+    }
+
+    #[test]
+    fn invalid_requests() {
+        assert_eq!(call(vec![1, 2, 3]).0, StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(
+            call(vec![0; READ_BUFFER_LEN + 1]).0,
+            StatusCode::PAYLOAD_TOO_LARGE
+        );
+    }
+}
@@ -2,6 +2,8 @@ use coset::CoseSign1;
 use many_error::ManyError;
 use many_protocol::{RequestMessage, ResponseMessage};
 
+pub mod policy;
+
 /// A trait for transforming a request.
 pub trait RequestValidator {
     /// Validate the envelope, prior to executing the message.
@@ -0,0 +1,301 @@
+//! A request validator that allows or denies methods depending on the sender.
+//!
+//! A policy is usually loaded from a JSON5 file, e.g.:
+//!
+//! ```json5
+//! {
+//!   rules: [
+//!     // Anyone can check the status of the server.
+//!     { from: "*", allow: ["status", "heartbeat", "endpoints"] },
+//!     // Anonymous users can only query the ledger.
+//!     { from: "anonymous", allow: ["ledger.*"], query_only: true },
+//!     // Any other sender can send tokens, but not mint them.
+//!     { from: "authenticated", allow: ["ledger.*", "tokens.*"], deny: ["tokens.mint"] },
+//!     // A specific address can do anything.
+//!     { from: "maffbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wijp", allow: ["*"] },
+//!   ],
+//! }
+//! ```
+use crate::RequestValidator;
+use many_error::ManyError;
+use many_identity::Address;
+use many_protocol::RequestMessage;
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The senders a rule applies to.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(try_from = "String")]
+pub enum AddressPattern {
+    /// `"*"`, any sender.
+    Any,
+
+    /// `"anonymous"`, requests without a sender.
+    Anonymous,
+
+    /// `"authenticated"`, any sender that is not anonymous.
+    Authenticated,
+
+    /// A single address.
+    Address(Address),
+}
+
+impl AddressPattern {
+    pub fn matches(&self, address: &Address) -> bool {
+        match self {
+            AddressPattern::Any => true,
+            AddressPattern::Anonymous => address.is_anonymous(),
+            AddressPattern::Authenticated => !address.is_anonymous(),
+            AddressPattern::Address(a) => a == address,
+        }
+    }
+}
+
+impl FromStr for AddressPattern {
+    type Err = ManyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "*" => Ok(AddressPattern::Any),
+            "anonymous" => Ok(AddressPattern::Anonymous),
+            "authenticated" => Ok(AddressPattern::Authenticated),
+            a => Address::from_str(a).map(AddressPattern::Address),
+        }
+    }
+}
+
+impl TryFrom<String> for AddressPattern {
+    type Error = ManyError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::from_str(&value)
+    }
+}
+
+impl Display for AddressPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AddressPattern::Any => f.write_str("*"),
+            AddressPattern::Anonymous => f.write_str("anonymous"),
+            AddressPattern::Authenticated => f.write_str("authenticated"),
+            AddressPattern::Address(a) => write!(f, "{a}"),
+        }
+    }
+}
+
+/// Whether a method name matches a glob, where `*` matches any sequence of
+/// characters (including dots).
+pub(crate) fn glob_matches(glob: &str, method: &str) -> bool {
+    let mut parts = glob.split('*');
+    // `split` always returns at least one part.
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = method.strip_prefix(first) else {
+        return false;
+    };
+
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+
+    // There was no `*` in the glob.
+    rest.is_empty()
+}
+
+/// Allows methods matching any of the `allow` globs and none of the `deny`
+/// globs to the senders matching `from`.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+pub struct PolicyRule {
+    pub from: AddressPattern,
+
+    #[serde(default)]
+    pub allow: Vec<String>,
+
+    #[serde(default)]
+    pub deny: Vec<String>,
+
+    /// Only allow the methods that are not commands. See
+    /// [`PolicyValidator::with_commands`].
+    #[serde(default)]
+    pub query_only: bool,
+}
+
+impl PolicyRule {
+    fn allows(&self, method: &str, is_command: bool) -> bool {
+        self.allow.iter().any(|g| glob_matches(g, method))
+            && !self.deny.iter().any(|g| glob_matches(g, method))
+            && !(self.query_only && is_command)
+    }
+}
+
+/// A list of rules. A request is allowed if any rule that applies to
+/// its sender allows its method, and denied otherwise.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+pub struct Policy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+impl Policy {
+    pub fn allows(&self, from: &Address, method: &str, is_command: bool) -> bool {
+        self.rules
+            .iter()
+            .any(|rule| rule.from.matches(from) && rule.allows(method, is_command))
+    }
+}
+
+/// A [`RequestValidator`] that denies the requests not allowed by a
+/// [`Policy`].
+#[derive(Clone, Debug)]
+pub struct PolicyValidator {
+    policy: Policy,
+    commands: Option<BTreeSet<String>>,
+}
+
+impl PolicyValidator {
+    pub fn new(policy: Policy) -> Self {
+        Self {
+            policy,
+            commands: None,
+        }
+    }
+
+    /// The names of the methods that are commands, used by the query-only rules.
+    /// Without it, query-only rules do not allow any method, since any of
+    /// them could be a command.
+    pub fn with_commands(mut self, commands: impl IntoIterator<Item = String>) -> Self {
+        self.commands = Some(commands.into_iter().collect());
+        self
+    }
+
+    fn is_command(&self, method: &str) -> bool {
+        self.commands
+            .as_ref()
+            .map_or(true, |commands| commands.contains(method))
+    }
+}
+
+impl RequestValidator for PolicyValidator {
+    fn validate_request(&self, request: &RequestMessage) -> Result<(), ManyError> {
+        let from = request.from();
+        if self
+            .policy
+            .allows(&from, &request.method, self.is_command(&request.method))
+        {
+            Ok(())
+        } else {
+            Err(ManyError::method_denied_by_policy(&request.method, from))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+
+    fn policy() -> Policy {
+        Policy {
+            rules: vec![
+                PolicyRule {
+                    from: AddressPattern::Any,
+                    allow: vec!["status".to_string()],
+                    deny: vec![],
+                    query_only: false,
+                },
+                PolicyRule {
+                    from: AddressPattern::Anonymous,
+                    allow: vec!["ledger.*".to_string()],
+                    deny: vec![],
+                    query_only: true,
+                },
+                PolicyRule {
+                    from: AddressPattern::Authenticated,
+                    allow: vec!["ledger.*".to_string(), "tokens.*".to_string()],
+                    deny: vec!["tokens.mint".to_string()],
+                    query_only: false,
+                },
+                PolicyRule {
+                    from: AddressPattern::Address(identity(1)),
+                    allow: vec!["*".to_string()],
+                    deny: vec![],
+                    query_only: false,
+                },
+            ],
+        }
+    }
+
+    fn validate(validator: &PolicyValidator, from: Address, method: &str) -> bool {
+        let request = RequestMessage::default()
+            .with_method(method.to_string())
+            .with_from(from);
+        validator.validate_request(&request).is_ok()
+    }
+
+    #[test]
+    fn globs() {
+        assert!(glob_matches("*", ""));
+        assert!(glob_matches("*", "ledger.send"));
+        assert!(glob_matches("ledger.*", "ledger.send"));
+        assert!(glob_matches("*.send", "ledger.send"));
+        assert!(glob_matches("l*g*.s*d", "ledger.send"));
+        assert!(glob_matches("ledger.send", "ledger.send"));
+        assert!(!glob_matches("ledger.send", "ledger.sender"));
+        assert!(!glob_matches("ledger.*", "tokens.mint"));
+        assert!(!glob_matches("*.info", "ledger.send"));
+    }
+
+    #[test]
+    fn anonymous() {
+        let anon = Address::anonymous();
+        let validator = PolicyValidator::new(policy())
+            .with_commands(["ledger.send".to_string(), "tokens.mint".to_string()]);
+
+        assert!(validate(&validator, anon, "status"));
+        assert!(validate(&validator, anon, "ledger.balance"));
+        assert!(!validate(&validator, anon, "ledger.send"));
+        assert!(!validate(&validator, anon, "tokens.info"));
+
+        // Without knowing the commands, query-only rules do not allow anything.
+        let validator = PolicyValidator::new(policy());
+        assert!(validate(&validator, anon, "status"));
+        assert!(!validate(&validator, anon, "ledger.balance"));
+    }
+
+    #[test]
+    fn authenticated() {
+        let validator = PolicyValidator::new(policy()).with_commands([]);
+
+        assert!(validate(&validator, identity(2), "ledger.send"));
+        assert!(validate(&validator, identity(2), "tokens.info"));
+        assert!(!validate(&validator, identity(2), "tokens.mint"));
+        assert!(!validate(&validator, identity(2), "kvstore.put"));
+
+        assert!(validate(&validator, identity(1), "tokens.mint"));
+        assert!(validate(&validator, identity(1), "kvstore.put"));
+    }
+
+    #[test]
+    fn deserialize() {
+        let policy: Policy = serde_json::from_str(
+            r#"{ "rules": [
+                { "from": "*", "allow": ["status"] },
+                { "from": "anonymous", "allow": ["ledger.*"], "query_only": true }
+            ] }"#,
+        )
+        .unwrap();
+        assert_eq!(policy.rules.len(), 2);
+        assert_eq!(policy.rules[0].from, AddressPattern::Any);
+        assert!(policy.rules[1].query_only);
+
+        assert!(serde_json::from_str::<Policy>(r#"{ "rules": [{ "from": "foo" }] }"#).is_err());
+    }
+}
@@ -0,0 +1,67 @@
+//! Sources of the current time for module backends.
+//!
+//! Backends read the time from a [`Clock`] instead of the system, so tests
+//! can control it with a [`TestClock`] and exercise expiration logic without
+//! sleeping.
+use crate::Timestamp;
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> Timestamp;
+}
+
+/// A clock reading the system time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::now()
+    }
+}
+
+/// A clock that only moves when told to. Clones share the same time, so a
+/// test can keep one to travel in time after handing another to a backend.
+#[derive(Clone, Debug)]
+pub struct TestClock(Arc<Mutex<Timestamp>>);
+
+impl TestClock {
+    pub fn new(time: Timestamp) -> Self {
+        Self(Arc::new(Mutex::new(time)))
+    }
+
+    pub fn set(&self, time: Timestamp) {
+        *self.0.lock().unwrap() = time;
+    }
+
+    /// Move the clock forward by `secs` seconds.
+    pub fn advance(&self, secs: u64) {
+        let mut time = self.0.lock().unwrap();
+        *time = *time + secs;
+    }
+}
+
+impl Clock for TestClock {
+    fn now(&self) -> Timestamp {
+        *self.0.lock().unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clock() {
+        let clock = TestClock::new(Timestamp::new(1_000).unwrap());
+        let shared = clock.clone();
+        assert_eq!(shared.now(), Timestamp::new(1_000).unwrap());
+
+        clock.advance(10);
+        assert_eq!(shared.now(), Timestamp::new(1_010).unwrap());
+
+        clock.set(Timestamp::new(5).unwrap());
+        assert_eq!(shared.now(), Timestamp::new(5).unwrap());
+    }
+}
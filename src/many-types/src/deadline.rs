@@ -0,0 +1,99 @@
+use crate::attributes::{Attribute, AttributeSet, TryFromAttributeSet};
+use crate::cbor::CborAny;
+use crate::Timestamp;
+use many_error::ManyError;
+use std::time::{Duration, SystemTime};
+
+/// The request attribute carrying the time after which the request must not
+/// execute anymore, e.g. because the user gave up waiting for it. Servers
+/// reject requests received after their deadline, so a stale envelope that
+/// is retried does not execute long after it was sent.
+pub const DEADLINE: Attribute = Attribute::id(5);
+
+/// The deadline of a request, in seconds since the epoch.
+#[derive(Copy, Clone, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub struct Deadline(pub Timestamp);
+
+impl Deadline {
+    /// The deadline `duration` from now.
+    pub fn after(duration: Duration) -> Result<Self, ManyError> {
+        Timestamp::from_system_time(SystemTime::now() + duration).map(Self)
+    }
+
+    pub fn is_expired(&self, now: SystemTime) -> Result<bool, ManyError> {
+        Ok(self.0.as_system_time()? <= now)
+    }
+
+    /// The time left until the deadline, if it has not passed yet.
+    pub fn remaining(&self, now: SystemTime) -> Result<Option<Duration>, ManyError> {
+        Ok(self
+            .0
+            .as_system_time()?
+            .duration_since(now)
+            .ok()
+            .filter(|d| !d.is_zero()))
+    }
+}
+
+impl From<Deadline> for Attribute {
+    fn from(deadline: Deadline) -> Attribute {
+        DEADLINE.with_argument(CborAny::Int(deadline.0.secs() as i64))
+    }
+}
+
+impl TryFrom<Attribute> for Deadline {
+    type Error = ManyError;
+
+    fn try_from(value: Attribute) -> Result<Self, Self::Error> {
+        if value.id != DEADLINE.id {
+            return Err(ManyError::invalid_attribute_id(value.id));
+        }
+
+        match value.into_arguments().as_slice() {
+            [CborAny::Int(secs)] => u64::try_from(*secs)
+                .map_err(|_| ManyError::invalid_attribute_arguments())
+                .and_then(Timestamp::new)
+                .map(Self),
+            _ => Err(ManyError::invalid_attribute_arguments()),
+        }
+    }
+}
+
+impl TryFromAttributeSet for Deadline {
+    fn try_from_set(set: &AttributeSet) -> Result<Self, ManyError> {
+        match set.get_attribute(DEADLINE.id) {
+            Some(attr) => Deadline::try_from(attr.clone()),
+            None => Err(ManyError::attribute_not_found(DEADLINE.id.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute() {
+        let deadline = Deadline(Timestamp::new(1_000).unwrap());
+        let set = AttributeSet::from_iter([Attribute::from(deadline)]);
+        assert_eq!(set.get::<Deadline>().unwrap(), deadline);
+
+        assert!(Deadline::try_from(DEADLINE.with_argument(CborAny::Int(-1))).is_err());
+        assert!(Deadline::try_from(DEADLINE).is_err());
+        assert!(AttributeSet::new().get::<Deadline>().is_err());
+    }
+
+    #[test]
+    fn expiration() {
+        let deadline = Deadline(Timestamp::new(1_000).unwrap());
+        let at = |secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs);
+
+        assert!(!deadline.is_expired(at(999)).unwrap());
+        assert_eq!(
+            deadline.remaining(at(999)).unwrap(),
+            Some(Duration::from_secs(1))
+        );
+        assert!(deadline.is_expired(at(1_000)).unwrap());
+        assert_eq!(deadline.remaining(at(1_001)).unwrap(), None);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::ledger::TokenAmount;
+use minicbor::data::Tag;
+use minicbor::{encode, Decode, Decoder, Encode, Encoder};
+use num_bigint::BigUint;
+use num_traits::{Num, Zero};
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// The maximum number of fractional digits of a [`FixedDecimal`].
+pub const MAXIMUM_DECIMAL_SCALE: u32 = 255;
+
+fn ten_pow(exponent: u32) -> BigUint {
+    BigUint::from(10u8).pow(exponent)
+}
+
+/// A non-negative decimal number `mantissa * 10^-scale`, with arbitrary
+/// precision. Arithmetic is exact, or rounded down when a result does not fit
+/// its scale, so it gives the same results on every node.
+///
+/// Equality compares the representation, so `1.5` and `1.50` are not equal;
+/// use [`FixedDecimal::cmp_value`] to compare values.
+///
+/// Encoded as a CBOR decimal fraction (tag 4), `[-scale, mantissa]`.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct FixedDecimal {
+    mantissa: TokenAmount,
+    scale: u32,
+}
+
+impl FixedDecimal {
+    /// The number `mantissa * 10^-scale`. The scale is capped to
+    /// [`MAXIMUM_DECIMAL_SCALE`].
+    pub fn new(mantissa: impl Into<TokenAmount>, scale: u32) -> Self {
+        Self {
+            mantissa: mantissa.into(),
+            scale: scale.min(MAXIMUM_DECIMAL_SCALE),
+        }
+    }
+
+    /// The value of an amount in the smallest unit of a symbol with
+    /// `decimals` decimals. Returns `None` if there are too many decimals.
+    pub fn from_token_amount(amount: TokenAmount, decimals: u64) -> Option<Self> {
+        let scale = u32::try_from(decimals)
+            .ok()
+            .filter(|scale| *scale <= MAXIMUM_DECIMAL_SCALE)?;
+        Some(Self {
+            mantissa: amount,
+            scale,
+        })
+    }
+
+    pub fn mantissa(&self) -> &TokenAmount {
+        &self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.mantissa.is_zero()
+    }
+
+    /// The number of significant fractional digits, without trailing zeros.
+    pub fn precision(&self) -> u32 {
+        let ten = BigUint::from(10u8);
+        let mut mantissa = self.mantissa.as_ref().clone();
+        let mut precision = self.scale;
+        if mantissa.is_zero() {
+            return 0;
+        }
+        while precision > 0 && (&mantissa % &ten).is_zero() {
+            mantissa /= &ten;
+            precision -= 1;
+        }
+        precision
+    }
+
+    /// The mantissa of this number at a greater or equal scale.
+    fn mantissa_at(&self, scale: u32) -> BigUint {
+        self.mantissa.as_ref() * ten_pow(scale - self.scale)
+    }
+
+    /// The same number at another scale, rounded down if the scale is
+    /// smaller.
+    pub fn round_down(&self, scale: u32) -> Self {
+        let scale = scale.min(MAXIMUM_DECIMAL_SCALE);
+        let mantissa = match scale.cmp(&self.scale) {
+            Ordering::Less => self.mantissa.as_ref() / ten_pow(self.scale - scale),
+            _ => self.mantissa_at(scale),
+        };
+        Self::new(mantissa, scale)
+    }
+
+    /// Compare the values of two numbers, regardless of their scales.
+    pub fn cmp_value(&self, other: &Self) -> Ordering {
+        let scale = self.scale.max(other.scale);
+        self.mantissa_at(scale).cmp(&other.mantissa_at(scale))
+    }
+
+    /// The sum of two numbers, at the greater of their scales.
+    pub fn checked_add(&self, rhs: &Self) -> Option<Self> {
+        let scale = self.scale.max(rhs.scale);
+        Some(Self::new(
+            self.mantissa_at(scale) + rhs.mantissa_at(scale),
+            scale,
+        ))
+    }
+
+    /// The difference of two numbers, at the greater of their scales.
+    /// Returns `None` if it would be negative.
+    pub fn checked_sub(&self, rhs: &Self) -> Option<Self> {
+        let scale = self.scale.max(rhs.scale);
+        let (lhs, rhs) = (self.mantissa_at(scale), rhs.mantissa_at(scale));
+        (lhs >= rhs).then(|| Self::new(lhs - rhs, scale))
+    }
+
+    /// The exact product of two numbers, whose scale is the sum of their
+    /// scales. Returns `None` if that scale is over [`MAXIMUM_DECIMAL_SCALE`].
+    pub fn checked_mul(&self, rhs: &Self) -> Option<Self> {
+        let scale = self
+            .scale
+            .checked_add(rhs.scale)
+            .filter(|scale| *scale <= MAXIMUM_DECIMAL_SCALE)?;
+        Some(Self::new(
+            self.mantissa.as_ref() * rhs.mantissa.as_ref(),
+            scale,
+        ))
+    }
+
+    /// The quotient of two numbers at the given scale, rounded down. Returns
+    /// `None` if dividing by zero.
+    pub fn checked_div(&self, rhs: &Self, scale: u32) -> Option<Self> {
+        if rhs.is_zero() {
+            return None;
+        }
+        let scale = scale.min(MAXIMUM_DECIMAL_SCALE);
+        // (a / 10^sa) / (b / 10^sb) = a * 10^(scale + sb - sa) / b / 10^scale
+        let (numerator, denominator) = match (scale + rhs.scale).checked_sub(self.scale) {
+            Some(shift) => (
+                self.mantissa.as_ref() * ten_pow(shift),
+                rhs.mantissa.as_ref().clone(),
+            ),
+            None => (
+                self.mantissa.as_ref().clone(),
+                rhs.mantissa.as_ref() * ten_pow(self.scale - scale - rhs.scale),
+            ),
+        };
+        Some(Self::new(numerator / denominator, scale))
+    }
+
+    /// Convert to the smallest unit of a symbol with `decimals` decimals.
+    /// Returns `None` if the amount is more precise than the symbol.
+    pub fn to_token_amount(&self, decimals: u64) -> Option<TokenAmount> {
+        if u64::from(self.precision()) > decimals {
+            return None;
+        }
+        // Only trailing zeros are removed, per the precision check.
+        self.to_token_amount_floor(decimals)
+    }
+
+    /// Convert to the smallest unit of a symbol with `decimals` decimals,
+    /// rounding down any extra precision.
+    pub fn to_token_amount_floor(&self, decimals: u64) -> Option<TokenAmount> {
+        let decimals = u32::try_from(decimals).ok()?;
+        Some(if decimals >= self.scale {
+            self.mantissa_at(decimals).into()
+        } else {
+            (self.mantissa.as_ref() / ten_pow(self.scale - decimals)).into()
+        })
+    }
+}
+
+impl From<TokenAmount> for FixedDecimal {
+    fn from(value: TokenAmount) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl From<u64> for FixedDecimal {
+    fn from(value: u64) -> Self {
+        Self::new(value, 0)
+    }
+}
+
+impl Display for FixedDecimal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let digits = self.mantissa.as_ref().to_str_radix(10);
+        let scale = self.scale as usize;
+        if scale == 0 {
+            return f.write_str(&digits);
+        }
+
+        let digits = format!("{digits:0>width$}", width = scale + 1);
+        let (integer, fraction) = digits.split_at(digits.len() - scale);
+        write!(f, "{integer}.{fraction}")
+    }
+}
+
+impl FromStr for FixedDecimal {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (integer, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let valid = |p: &str| p.chars().all(|c| c.is_ascii_digit());
+        if integer.is_empty() || !valid(integer) || !valid(fraction) {
+            return Err(format!("Invalid decimal amount: '{s}'."));
+        }
+        let scale = u32::try_from(fraction.len())
+            .ok()
+            .filter(|scale| *scale <= MAXIMUM_DECIMAL_SCALE)
+            .ok_or_else(|| format!("Too many decimals in '{s}'."))?;
+
+        let mantissa = BigUint::from_str_radix(&format!("{integer}{fraction}"), 10)
+            .map_err(|e| e.to_string())?;
+        Ok(Self {
+            mantissa: mantissa.into(),
+            scale,
+        })
+    }
+}
+
+impl<C> Encode<C> for FixedDecimal {
+    fn encode<W: encode::Write>(
+        &self,
+        e: &mut Encoder<W>,
+        _: &mut C,
+    ) -> Result<(), encode::Error<W::Error>> {
+        e.tag(Tag::Decimal)?
+            .array(2)?
+            .i64(-i64::from(self.scale))?
+            .encode(&self.mantissa)?;
+        Ok(())
+    }
+}
+
+impl<'b, C> Decode<'b, C> for FixedDecimal {
+    fn decode(d: &mut Decoder<'b>, _: &mut C) -> Result<Self, minicbor::decode::Error> {
+        if d.tag()? != Tag::Decimal {
+            return Err(minicbor::decode::Error::message("Invalid tag."));
+        }
+        if d.array()? != Some(2) {
+            return Err(minicbor::decode::Error::message(
+                "Decimal fractions must be an array of 2 elements.",
+            ));
+        }
+
+        let exponent = d.i64()?;
+        let mantissa: TokenAmount = d.decode()?;
+        if exponent.unsigned_abs() > u64::from(MAXIMUM_DECIMAL_SCALE) {
+            return Err(minicbor::decode::Error::message(
+                "Decimal exponent out of range.",
+            ));
+        }
+
+        Ok(if exponent >= 0 {
+            Self {
+                mantissa: (mantissa.as_ref() * ten_pow(exponent as u32)).into(),
+                scale: 0,
+            }
+        } else {
+            Self {
+                mantissa,
+                scale: exponent.unsigned_abs() as u32,
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn d(s: &str) -> FixedDecimal {
+        FixedDecimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn arithmetic() {
+        assert_eq!(d("1.5").checked_add(&d("0.25")), Some(d("1.75")));
+        assert_eq!(d("1.5").checked_sub(&d("0.25")), Some(d("1.25")));
+        assert_eq!(d("0.25").checked_sub(&d("1.5")), None);
+        assert_eq!(d("1.5").checked_mul(&d("0.25")), Some(d("0.375")));
+        assert_eq!(d("1").checked_div(&d("3"), 4), Some(d("0.3333")));
+        assert_eq!(d("0.0001").checked_div(&d("0.1"), 2), Some(d("0.00")));
+        assert_eq!(d("1").checked_div(&d("0.0"), 2), None);
+
+        let max = FixedDecimal::new(1u32, MAXIMUM_DECIMAL_SCALE);
+        assert_eq!(max.checked_mul(&d("0.1")), None);
+    }
+
+    #[test]
+    fn compare_and_round() {
+        assert_ne!(d("1.5"), d("1.50"));
+        assert_eq!(d("1.5").cmp_value(&d("1.50")), Ordering::Equal);
+        assert_eq!(d("1.49").cmp_value(&d("1.5")), Ordering::Less);
+
+        assert_eq!(d("1.999").round_down(2), d("1.99"));
+        assert_eq!(d("1.9").round_down(3), d("1.900"));
+    }
+
+    #[test]
+    fn token_amounts() {
+        let amount = FixedDecimal::from_token_amount(1_500_000_000u64.into(), 9).unwrap();
+        assert_eq!(amount.to_string(), "1.500000000");
+        assert_eq!(amount.to_token_amount(9), Some(1_500_000_000u64.into()));
+
+        // 0.5% interest on 1.5 tokens, in a symbol with 2 decimals.
+        let interest = amount.checked_mul(&d("0.005")).unwrap();
+        assert_eq!(interest.to_token_amount(2), None);
+        assert_eq!(interest.to_token_amount_floor(2), Some(0u64.into()));
+        assert_eq!(interest.to_token_amount_floor(4), Some(75u64.into()));
+    }
+}
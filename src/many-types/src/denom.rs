@@ -0,0 +1,263 @@
+//! Conversion of token amounts to a reference denomination (e.g. USD), from
+//! the prices reported by an oracle. This is meant for display and for
+//! checking limits expressed in the reference denomination, like spending
+//! limits or fee caps.
+use crate::decimal::FixedDecimal;
+use crate::identity::Address;
+use crate::ledger::TokenAmount;
+use crate::Timestamp;
+use many_error::ManyError;
+use minicbor::{Decode, Encode};
+use std::collections::BTreeMap;
+
+/// The price of one whole token of `symbol` in the reference denomination,
+/// as reported by an oracle at `timestamp`.
+#[derive(Clone, Debug, Encode, Decode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct PriceFeed {
+    #[n(0)]
+    pub symbol: Address,
+
+    /// The number of decimals of the token, to convert from its smallest unit.
+    #[n(1)]
+    pub decimals: u64,
+
+    #[n(2)]
+    pub price: FixedDecimal,
+
+    #[n(3)]
+    pub timestamp: Timestamp,
+}
+
+impl PriceFeed {
+    /// The value of an amount of the token, in its smallest unit. Returns
+    /// `None` if the result is too precise to be represented.
+    pub fn value_of(&self, amount: TokenAmount) -> Option<FixedDecimal> {
+        FixedDecimal::from_token_amount(amount, self.decimals)?.checked_mul(&self.price)
+    }
+
+    /// The largest amount of the token, in its smallest unit, worth at most
+    /// `value`. Returns `None` if the price is zero.
+    pub fn amount_for(&self, value: &FixedDecimal) -> Option<TokenAmount> {
+        let tokens = value.checked_div(&self.price, u32::try_from(self.decimals).ok()?)?;
+        tokens.to_token_amount_floor(self.decimals)
+    }
+
+    /// Whether the price is older than `max_age` seconds at `now`.
+    pub fn is_stale(&self, now: Timestamp, max_age: u64) -> bool {
+        now.secs().saturating_sub(self.timestamp.secs()) > max_age
+    }
+}
+
+/// The latest prices of tokens in a single reference denomination.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReferencePrices {
+    denomination: String,
+    feeds: BTreeMap<Address, PriceFeed>,
+    max_age: Option<u64>,
+}
+
+impl ReferencePrices {
+    /// Prices in the given denomination, e.g. `"USD"`.
+    pub fn new(denomination: impl ToString) -> Self {
+        Self {
+            denomination: denomination.to_string(),
+            feeds: BTreeMap::new(),
+            max_age: None,
+        }
+    }
+
+    /// Refuse to convert with prices older than `max_age` seconds.
+    pub fn with_max_age(mut self, max_age: u64) -> Self {
+        self.max_age = Some(max_age);
+        self
+    }
+
+    pub fn denomination(&self) -> &str {
+        &self.denomination
+    }
+
+    /// Add a price, unless a more recent price of the same symbol is
+    /// already known.
+    pub fn insert(&mut self, feed: PriceFeed) {
+        match self.feeds.get(&feed.symbol) {
+            Some(existing) if existing.timestamp > feed.timestamp => {}
+            _ => {
+                self.feeds.insert(feed.symbol, feed);
+            }
+        }
+    }
+
+    pub fn get(&self, symbol: &Address) -> Option<&PriceFeed> {
+        self.feeds.get(symbol)
+    }
+
+    fn feed(&self, symbol: &Address, now: Timestamp) -> Result<&PriceFeed, ManyError> {
+        let feed = self
+            .feeds
+            .get(symbol)
+            .ok_or_else(|| ManyError::unknown(format!("No price known for symbol {symbol}.")))?;
+        if let Some(max_age) = self.max_age {
+            if feed.is_stale(now, max_age) {
+                return Err(ManyError::unknown(format!(
+                    "The price of symbol {symbol} is older than {max_age} seconds."
+                )));
+            }
+        }
+        Ok(feed)
+    }
+
+    /// The value of an amount of a symbol in the reference denomination.
+    pub fn convert(
+        &self,
+        symbol: &Address,
+        amount: TokenAmount,
+        now: Timestamp,
+    ) -> Result<FixedDecimal, ManyError> {
+        self.feed(symbol, now)?
+            .value_of(amount)
+            .ok_or_else(|| ManyError::unknown("The converted value is too precise."))
+    }
+
+    /// The total value of amounts of multiple symbols.
+    pub fn convert_all<'a>(
+        &self,
+        amounts: impl IntoIterator<Item = (&'a Address, &'a TokenAmount)>,
+        now: Timestamp,
+    ) -> Result<FixedDecimal, ManyError> {
+        amounts
+            .into_iter()
+            .try_fold(FixedDecimal::default(), |total, (symbol, amount)| {
+                let value = self.convert(symbol, amount.clone(), now)?;
+                total
+                    .checked_add(&value)
+                    .ok_or_else(|| ManyError::unknown("The converted value is too precise."))
+            })
+    }
+
+    /// The value of an amount for display, rounded down to `scale` decimals
+    /// and followed by the denomination, e.g. `12.34 USD`.
+    pub fn display(
+        &self,
+        symbol: &Address,
+        amount: TokenAmount,
+        scale: u32,
+        now: Timestamp,
+    ) -> Result<String, ManyError> {
+        let value = self.convert(symbol, amount, now)?.round_down(scale);
+        Ok(format!("{value} {}", self.denomination))
+    }
+
+    /// Whether the total value of the amounts is within `limit`, in the
+    /// reference denomination.
+    pub fn within_limit<'a>(
+        &self,
+        amounts: impl IntoIterator<Item = (&'a Address, &'a TokenAmount)>,
+        limit: &FixedDecimal,
+        now: Timestamp,
+    ) -> Result<bool, ManyError> {
+        Ok(self.convert_all(amounts, now)?.cmp_value(limit).is_le())
+    }
+
+    /// The largest amount of a symbol, in its smallest unit, that can be
+    /// spent without going over `limit`.
+    pub fn amount_within(
+        &self,
+        symbol: &Address,
+        limit: &FixedDecimal,
+        now: Timestamp,
+    ) -> Result<TokenAmount, ManyError> {
+        self.feed(symbol, now)?
+            .amount_for(limit)
+            .ok_or_else(|| ManyError::unknown(format!("The price of symbol {symbol} is zero.")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use many_identity::testing::identity;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> FixedDecimal {
+        FixedDecimal::from_str(s).unwrap()
+    }
+
+    fn prices() -> ReferencePrices {
+        let mut prices = ReferencePrices::new("USD").with_max_age(60);
+        // 1 token of 9 decimals = 2.50 USD.
+        prices.insert(PriceFeed {
+            symbol: identity(1),
+            decimals: 9,
+            price: d("2.50"),
+            timestamp: Timestamp::new(1_000).unwrap(),
+        });
+        // 1 token of 2 decimals = 0.001 USD.
+        prices.insert(PriceFeed {
+            symbol: identity(2),
+            decimals: 2,
+            price: d("0.001"),
+            timestamp: Timestamp::new(1_000).unwrap(),
+        });
+        prices
+    }
+
+    #[test]
+    fn convert() {
+        let prices = prices();
+        let now = Timestamp::new(1_010).unwrap();
+
+        let value = prices
+            .convert(&identity(1), 1_500_000_000u64.into(), now)
+            .unwrap();
+        assert_eq!(value.cmp_value(&d("3.75")), std::cmp::Ordering::Equal);
+        assert_eq!(
+            prices
+                .display(&identity(1), 1_500_000_000u64.into(), 2, now)
+                .unwrap(),
+            "3.75 USD"
+        );
+        assert_eq!(
+            prices.display(&identity(2), 150u64.into(), 2, now).unwrap(),
+            "0.00 USD"
+        );
+
+        assert!(prices.convert(&identity(3), 1u64.into(), now).is_err());
+        let later = Timestamp::new(1_100).unwrap();
+        assert!(prices.convert(&identity(1), 1u64.into(), later).is_err());
+    }
+
+    #[test]
+    fn limits() {
+        let prices = prices();
+        let now = Timestamp::new(1_010).unwrap();
+        let a = TokenAmount::from(2_000_000_000u64);
+        let b = TokenAmount::from(100_000u64);
+
+        // 5 USD + 1 USD.
+        let spent = [(&identity(1), &a), (&identity(2), &b)];
+        assert!(prices.within_limit(spent, &d("6"), now).unwrap());
+        assert!(!prices.within_limit(spent, &d("5.99"), now).unwrap());
+
+        assert_eq!(
+            prices.amount_within(&identity(1), &d("1"), now).unwrap(),
+            400_000_000u64
+        );
+    }
+
+    #[test]
+    fn newer_prices() {
+        let mut prices = prices();
+        let feed = |price, timestamp| PriceFeed {
+            symbol: identity(1),
+            decimals: 9,
+            price: d(price),
+            timestamp: Timestamp::new(timestamp).unwrap(),
+        };
+
+        prices.insert(feed("1", 900));
+        assert_eq!(prices.get(&identity(1)).unwrap().price, d("2.50"));
+        prices.insert(feed("3", 1_001));
+        assert_eq!(prices.get(&identity(1)).unwrap().price, d("3"));
+    }
+}
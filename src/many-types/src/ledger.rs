@@ -1,14 +1,16 @@
-use crate::{cbor::CborNull, cbor_type_decl, Either, Percent};
+use crate::decimal::FixedDecimal;
+use crate::{cbor::CborNull, cbor_type_decl, Either, Percent, Timestamp};
 use many_identity::Address;
 use minicbor::data::{Tag, Type};
 use minicbor::{encode, Decode, Decoder, Encode, Encoder};
 use num_bigint::{BigInt, BigUint};
-use num_traits::{Num, ToPrimitive};
+use num_traits::{Num, ToPrimitive, Zero};
 use serde::de::Unexpected;
 use serde::{Deserialize, Serialize};
 use std::collections::BTreeMap;
 use std::fmt::{Display, Formatter};
 use std::ops::Shr;
+use std::str::FromStr;
 
 /// A Symbol is represented by a non-anonymous identity.
 pub type Symbol = Address;
@@ -25,6 +27,10 @@ pub struct TransactionFee {
     pub fixed: Option<TokenAmount>,
     #[n(1)]
     pub percent: Option<Percent>,
+
+    /// A decimal rate of the amount (e.g. `0.005` for 0.5%), rounded down.
+    #[n(2)]
+    pub rate: Option<FixedDecimal>,
 }
 
 impl TransactionFee {
@@ -36,6 +42,7 @@ impl TransactionFee {
     /// let fees = TransactionFee {
     ///   fixed: Some(1000u64.into()),
     ///   percent: Some(Percent::new(0, 0x800000)),
+    ///   rate: None,
     /// };
     /// let amount = TokenAmount::from(5000000u64);
     ///
@@ -48,6 +55,12 @@ impl TransactionFee {
         } else {
             TokenAmount::zero()
         };
+        if let Some(rate) = &self.rate {
+            fees += FixedDecimal::from(amount.clone())
+                .checked_mul(rate)
+                .and_then(|f| f.to_token_amount_floor(0))
+                .unwrap_or_default();
+        }
         fees
     }
 }
@@ -364,6 +377,13 @@ impl Serialize for TokenAmount {
     }
 }
 
+pub use crate::decimal::MAXIMUM_DECIMAL_SCALE;
+
+/// An amount of whole tokens with fractional digits (e.g. `1.5`), as opposed
+/// to a [`TokenAmount`] which counts the smallest unit of a symbol. Converting
+/// it needs the number of decimals of the symbol.
+pub type DecimalAmount = FixedDecimal;
+
 cbor_type_decl!(
     pub struct TokenInfo {
         0 => symbol: Symbol,
@@ -385,6 +405,73 @@ cbor_type_decl!(
     }
 );
 
+impl TokenInfoSummary {
+    /// The value of an amount of this token in whole tokens.
+    pub fn to_decimal(&self, amount: TokenAmount) -> Option<FixedDecimal> {
+        FixedDecimal::from_token_amount(amount, self.decimals)
+    }
+
+    /// Convert a number of whole tokens to an amount of this token. Returns
+    /// `None` if it is more precise than the decimals of the token.
+    pub fn from_decimal(&self, decimal: &FixedDecimal) -> Option<TokenAmount> {
+        decimal.to_token_amount(self.decimals)
+    }
+}
+
+/// When tokens granted to an account unlock. Nothing unlocks before the
+/// `cliff`, then tokens unlock linearly from `start` until everything is
+/// unlocked after `duration`. Both are in seconds after `start`.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct VestingSchedule {
+    #[n(0)]
+    pub start: Timestamp,
+
+    #[n(1)]
+    pub cliff: u64,
+
+    #[n(2)]
+    pub duration: u64,
+}
+
+impl VestingSchedule {
+    pub fn is_valid(&self) -> bool {
+        self.duration > 0 && self.cliff <= self.duration
+    }
+}
+
+/// Tokens of an account that unlock over a schedule.
+#[derive(Clone, Debug, Decode, Encode, Eq, PartialEq)]
+#[cbor(map)]
+pub struct VestingGrant {
+    #[n(0)]
+    pub amount: TokenAmount,
+
+    #[n(1)]
+    pub schedule: VestingSchedule,
+}
+
+impl VestingGrant {
+    /// The amount of the grant still locked at a time, rounded up.
+    pub fn locked_at(&self, now: Timestamp) -> TokenAmount {
+        let VestingSchedule {
+            start,
+            cliff,
+            duration,
+        } = self.schedule;
+        let elapsed = now.secs().saturating_sub(start.secs());
+        if now < start || elapsed < cliff {
+            self.amount.clone()
+        } else if elapsed >= duration {
+            TokenAmount::zero()
+        } else {
+            let remaining = BigUint::from(duration - elapsed);
+            let duration = BigUint::from(duration);
+            TokenAmount((&self.amount.0 * remaining + &duration - 1u8) / duration)
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -424,6 +511,65 @@ mod test {
         assert_ser_tokens(&token, &[Token::String("1208925819614629174706175")]);
     }
 
+    #[test]
+    fn decimal_amount() {
+        let a = DecimalAmount::from_str("1.50").unwrap();
+        assert_eq!(a, DecimalAmount::new(150u32, 2));
+        assert_eq!(a.to_string(), "1.50");
+        assert_eq!(a.precision(), 1);
+        assert_eq!(DecimalAmount::new(5u32, 3).to_string(), "0.005");
+        assert_eq!(DecimalAmount::new(0u32, 3).precision(), 0);
+
+        assert_eq!(a.to_token_amount(9), Some(1_500_000_000u64.into()));
+        assert_eq!(a.to_token_amount(1), Some(15u64.into()));
+        assert_eq!(a.to_token_amount(0), None);
+
+        assert!(DecimalAmount::from_str("1.").is_ok());
+        assert!(DecimalAmount::from_str(".5").is_err());
+        assert!(DecimalAmount::from_str("1.5.0").is_err());
+        assert!(DecimalAmount::from_str("-1").is_err());
+    }
+
+    #[test]
+    fn transaction_fee_rate() {
+        let fees = TransactionFee {
+            fixed: Some(10u64.into()),
+            percent: None,
+            rate: Some(DecimalAmount::from_str("0.005").unwrap()),
+        };
+        // 10 + 1999 * 0.005, rounded down.
+        assert_eq!(fees.calculate_fees(&1999u64.into()), 19u64);
+
+        let summary = TokenInfoSummary {
+            name: "Token".to_string(),
+            ticker: "TKN".to_string(),
+            decimals: 2,
+        };
+        let amount = summary.to_decimal(150u64.into()).unwrap();
+        assert_eq!(amount.to_string(), "1.50");
+        assert_eq!(summary.from_decimal(&amount), Some(150u64.into()));
+        assert_eq!(
+            summary.from_decimal(&DecimalAmount::from_str("0.001").unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn decimal_amount_cbor() {
+        let a = DecimalAmount::new(150u32, 2);
+        let bytes = minicbor::to_vec(&a).unwrap();
+        // 4([-2, 150])
+        assert_eq!(bytes, [0xc4, 0x82, 0x21, 0x18, 0x96]);
+        assert_eq!(minicbor::decode::<DecimalAmount>(&bytes).unwrap(), a);
+
+        // Positive exponents are folded into the mantissa: 4([2, 15]) == 1500.
+        let decoded: DecimalAmount = minicbor::decode(&[0xc4, 0x82, 0x02, 0x0f]).unwrap();
+        assert_eq!(decoded, DecimalAmount::new(1500u32, 0));
+
+        // Exponents are bounded.
+        assert!(minicbor::decode::<DecimalAmount>(&[0xc4, 0x82, 0x19, 0x01, 0x00, 0x01]).is_err());
+    }
+
     #[test]
     fn token_amount_ref() {
         let a = TokenAmount::from(12345u64);
@@ -449,4 +595,33 @@ mod test {
         d *= &b;
         assert_eq!(d, TokenAmount::from(2523930316u64));
     }
+
+    #[test]
+    fn vesting_locked_at() {
+        let grant = VestingGrant {
+            amount: TokenAmount::from(1000u32),
+            schedule: VestingSchedule {
+                start: Timestamp::new(100).unwrap(),
+                cliff: 25,
+                duration: 100,
+            },
+        };
+        let locked_at = |secs| grant.locked_at(Timestamp::new(secs).unwrap());
+
+        assert_eq!(locked_at(0), 1000u32);
+        assert_eq!(locked_at(124), 1000u32);
+        assert_eq!(locked_at(125), 750u32);
+        assert_eq!(locked_at(150), 500u32);
+        // Rounded up, so nothing unlocks early.
+        assert_eq!(
+            VestingGrant {
+                amount: TokenAmount::from(10u32),
+                ..grant.clone()
+            }
+            .locked_at(Timestamp::new(133).unwrap()),
+            7u32
+        );
+        assert_eq!(locked_at(200), 0u32);
+        assert_eq!(locked_at(1000), 0u32);
+    }
 }
@@ -10,14 +10,20 @@ use std::str::FromStr;
 pub mod attributes;
 pub mod blockchain;
 pub mod cbor;
+pub mod clock;
 pub mod compute;
+pub mod deadline;
+pub mod decimal;
+pub mod denom;
 pub mod either;
 pub mod identity {
     pub use many_identity::*;
 }
 pub mod ledger;
 pub mod memo;
+pub mod node_state;
 pub mod proof;
+pub mod trace;
 pub mod web;
 
 use attributes::AttributeId;
@@ -7,6 +7,17 @@ use std::borrow::Cow;
 
 const MEMO_DATA_DEFAULT_MAX_SIZE: usize = 4000; // 4kB
 
+/// The maximum length of the MIME type of a typed part.
+pub const MEMO_MIME_TYPE_MAX_LENGTH: usize = 127;
+
+/// The MIME type of the string parts of a memo.
+pub const MIME_TEXT: &str = "text/plain";
+
+/// The MIME type of the untyped bytestring parts of a memo.
+pub const MIME_OCTET_STREAM: &str = "application/octet-stream";
+
+pub const MIME_JSON: &str = "application/json";
+
 mod legacy;
 pub use legacy::Data as DataLegacy;
 pub use legacy::Memo as MemoLegacy;
@@ -15,6 +26,9 @@ pub use legacy::Memo as MemoLegacy;
 enum MemoInner<const MAX_LENGTH: usize> {
     String(String),
     ByteString(ByteVec),
+
+    /// Data with a MIME type, encoded as a map `{ 0: mime, 1: data }`.
+    Typed(String, ByteVec),
 }
 
 impl<const M: usize> MemoInner<M> {
@@ -24,6 +38,63 @@ impl<const M: usize> MemoInner<M> {
             _ => None,
         }
     }
+
+    fn typed(mime: String, data: Vec<u8>) -> Result<Self, ManyError> {
+        if data.len() > M {
+            return Err(ManyError::unknown(format!(
+                "Data size ({}) over limit ({})",
+                data.len(),
+                M
+            )));
+        }
+        if !is_mime_type(&mime) {
+            return Err(ManyError::unknown(format!("Invalid MIME type '{mime}'")));
+        }
+        if mime == MIME_JSON && std::str::from_utf8(&data).is_err() {
+            return Err(ManyError::unknown("JSON part is not valid UTF-8"));
+        }
+        Ok(Self::Typed(mime, data.into()))
+    }
+
+    fn part(&self) -> MemoPart<'_> {
+        match self {
+            Self::String(s) => MemoPart {
+                mime: MIME_TEXT,
+                data: s.as_bytes(),
+            },
+            Self::ByteString(b) => MemoPart {
+                mime: MIME_OCTET_STREAM,
+                data: b.as_slice(),
+            },
+            Self::Typed(mime, data) => MemoPart {
+                mime,
+                data: data.as_slice(),
+            },
+        }
+    }
+}
+
+/// Whether `mime` is a `type/subtype` MIME type, optionally with parameters
+/// (e.g. `text/csv; charset=utf-8`).
+fn is_mime_type(mime: &str) -> bool {
+    let is_token = |s: &str| {
+        !s.is_empty()
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c))
+    };
+    let essence = mime.split(';').next().unwrap_or_default().trim();
+    mime.len() <= MEMO_MIME_TYPE_MAX_LENGTH
+        && mime.is_ascii()
+        && !mime.contains(['\r', '\n'])
+        && matches!(essence.split_once('/'), Some((t, st)) if is_token(t) && is_token(st))
+}
+
+/// A part of a memo and its MIME type. String parts are `text/plain`, and
+/// untyped bytestrings are `application/octet-stream`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MemoPart<'a> {
+    pub mime: &'a str,
+    pub data: &'a [u8],
 }
 
 macro_rules! declare_try_from {
@@ -86,6 +157,9 @@ impl<C, const M: usize> Encode<C> for MemoInner<M> {
         match self {
             MemoInner::String(str) => e.str(str),
             MemoInner::ByteString(bstr) => e.bytes(bstr.as_slice()),
+            MemoInner::Typed(mime, data) => {
+                e.map(2)?.u8(0)?.str(mime)?.u8(1)?.bytes(data.as_slice())
+            }
         }
         .map(|_| ())
     }
@@ -96,6 +170,23 @@ impl<'b, C, const M: usize> Decode<'b, C> for MemoInner<M> {
         match d.datatype()? {
             Type::Bytes => Self::try_from(d.bytes()?.to_vec()).map_err(decode::Error::message),
             Type::String => Self::try_from(d.str()?).map_err(decode::Error::message),
+            Type::Map => {
+                let mut mime = None;
+                let mut data = None;
+                for _ in 0..d.map()?.ok_or(decode::Error::message("Indefinite map"))? {
+                    match d.u8()? {
+                        0 => mime = Some(d.str()?.to_string()),
+                        1 => data = Some(d.bytes()?.to_vec()),
+                        _ => return Err(decode::Error::message("Unknown key in memo part")),
+                    }
+                }
+                match (mime, data) {
+                    (Some(mime), Some(data)) => {
+                        Self::typed(mime, data).map_err(decode::Error::message)
+                    }
+                    _ => Err(decode::Error::message("Memo part needs a type and data")),
+                }
+            }
             // Type::BytesIndef => {}
             // Type::StringIndef => {}
             _ => Err(decode::Error::type_mismatch(Type::String)),
@@ -142,6 +233,28 @@ impl<const M: usize> Memo<M> {
         Ok(())
     }
 
+    /// Adds data with a MIME type at the end, e.g. `application/json`
+    /// remittance data. The data is limited in size like any other part.
+    pub fn push_typed(
+        &mut self,
+        mime: impl Into<String>,
+        data: impl Into<Vec<u8>>,
+    ) -> Result<(), ManyError> {
+        self.inner
+            .push(MemoInner::<M>::typed(mime.into(), data.into())?);
+        Ok(())
+    }
+
+    /// Adds a JSON document at the end, as an `application/json` part.
+    pub fn push_json(&mut self, json: impl Into<String>) -> Result<(), ManyError> {
+        self.push_typed(MIME_JSON, json.into())
+    }
+
+    /// Creates a memo with a single typed part.
+    pub fn typed(mime: impl Into<String>, data: impl Into<Vec<u8>>) -> Result<Self, ManyError> {
+        Ok(Self::from(MemoInner::<M>::typed(mime.into(), data.into())?))
+    }
+
     #[inline]
     pub fn len(&self) -> usize {
         self.inner.len()
@@ -157,13 +270,32 @@ impl<const M: usize> Memo<M> {
         self.inner.iter().filter_map(MemoInner::as_string)
     }
 
-    /// Returns an iterator over all bytestrings of the memo.
+    /// Returns an iterator over all untyped bytestrings of the memo.
     pub fn iter_bytes(&self) -> impl Iterator<Item = &[u8]> {
         self.inner.iter().filter_map(|inner| match inner {
-            MemoInner::String(_) => None,
             MemoInner::ByteString(bstr) => Some(bstr.as_slice()),
+            _ => None,
         })
     }
+
+    /// Returns an iterator over all parts of the memo, in order, with their
+    /// MIME types.
+    pub fn iter_parts(&self) -> impl Iterator<Item = MemoPart<'_>> {
+        self.inner.iter().map(MemoInner::part)
+    }
+
+    /// Returns an iterator over the data of the parts of a MIME type, e.g.
+    /// [`MIME_JSON`]. Parameters of the types of the parts are ignored.
+    pub fn iter_mime<'a>(&'a self, mime: &'a str) -> impl Iterator<Item = &'a [u8]> {
+        self.iter_parts()
+            .filter(move |part| {
+                part.mime
+                    .split(';')
+                    .next()
+                    .map_or(false, |essence| essence.trim().eq_ignore_ascii_case(mime))
+            })
+            .map(|part| part.data)
+    }
 }
 
 // This helps comparisons.
@@ -503,6 +635,63 @@ mod tests {
         );
     }
 
+    #[test]
+    fn typed_parts() {
+        let mut memo: Memo = Memo::try_from("Invoice 42").unwrap();
+        memo.push_json(r#"{"invoice":42}"#).unwrap();
+        memo.push_typed("image/png", vec![0x89, b'P', b'N', b'G'])
+            .unwrap();
+        memo.push_bytes(b"raw".to_vec()).unwrap();
+
+        let bytes = minicbor::to_vec(&memo).unwrap();
+        let decoded: Memo = minicbor::decode(&bytes).unwrap();
+        assert_eq!(decoded, memo);
+        assert_eq!(
+            decoded.iter_parts().map(|p| p.mime).collect::<Vec<_>>(),
+            &[MIME_TEXT, MIME_JSON, "image/png", MIME_OCTET_STREAM]
+        );
+        assert_eq!(
+            decoded.iter_mime(MIME_JSON).collect::<Vec<_>>(),
+            &[br#"{"invoice":42}"#.as_slice()]
+        );
+        assert_eq!(decoded.iter_str().count(), 1);
+        assert_eq!(decoded.iter_bytes().collect::<Vec<_>>(), &[b"raw"]);
+    }
+
+    #[test]
+    fn typed_part_decode() {
+        let cbor = r#" [ { 0: "application/json", 1: h'7b7d' } ] "#;
+        let bytes = cbor_diag::parse_diag(cbor).unwrap().to_bytes();
+        let memo = minicbor::decode::<Memo>(&bytes).unwrap();
+        assert_eq!(memo.iter_mime(MIME_JSON).next(), Some(b"{}".as_slice()));
+
+        for cbor in [
+            r#" [ { 0: "json", 1: h'00' } ] "#,
+            r#" [ { 0: "application/json", 1: h'ff' } ] "#,
+            r#" [ { 0: "text/plain" } ] "#,
+            r#" [ { 0: "text/plain", 1: h'00', 2: 0 } ] "#,
+        ] {
+            let bytes = cbor_diag::parse_diag(cbor).unwrap().to_bytes();
+            assert!(minicbor::decode::<Memo>(&bytes).is_err(), "{cbor}");
+        }
+    }
+
+    #[test]
+    fn typed_part_limits() {
+        let mut memo: Memo<10> = Memo::typed("text/csv; charset=utf-8", b"a,b".to_vec()).unwrap();
+        assert!(memo.push_typed("application/cbor", vec![0; 11]).is_err());
+        assert!(memo.push_typed("application/", vec![]).is_err());
+        assert!(memo
+            .push_typed(
+                format!("a/{}", "b".repeat(MEMO_MIME_TYPE_MAX_LENGTH)),
+                vec![]
+            )
+            .is_err());
+        assert!(memo.push_typed("text/plain\r\nX: y", vec![]).is_err());
+        assert_eq!(memo.len(), 1);
+        assert_eq!(memo.iter_mime("TEXT/CSV").count(), 1);
+    }
+
     #[test]
     fn memo_mut() {
         let mut memo: Memo = Memo::try_from("Hello World".to_string()).unwrap();
@@ -0,0 +1,103 @@
+use crate::attributes::{Attribute, AttributeSet, TryFromAttributeSet};
+use crate::cbor::CborAny;
+use crate::Timestamp;
+use many_error::ManyError;
+
+/// The attribute carrying the state of the node which answered a request.
+/// Clients opt in by adding it without arguments to their request, and
+/// servers which know their state add it with its arguments to the response.
+/// Comparing the heights of responses tells a client that a node lags behind
+/// the others, or that it did not apply a transaction it already saw yet.
+pub const NODE_STATE: Attribute = Attribute::id(6);
+
+/// The state of a node when it answered a request.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct NodeState {
+    /// The height of the last committed block.
+    pub height: u64,
+
+    /// The time of the node, e.g. the time of its last block.
+    pub time: Timestamp,
+
+    /// The app hash of the last committed block, if the node has one.
+    pub app_hash: Option<Vec<u8>>,
+}
+
+impl From<NodeState> for Attribute {
+    fn from(state: NodeState) -> Attribute {
+        Attribute::new(
+            NODE_STATE.id,
+            vec![
+                CborAny::Int(state.height as i64),
+                CborAny::Int(state.time.secs() as i64),
+                state.app_hash.map_or(CborAny::Null, CborAny::Bytes),
+            ],
+        )
+    }
+}
+
+impl TryFrom<Attribute> for NodeState {
+    type Error = ManyError;
+
+    fn try_from(value: Attribute) -> Result<Self, Self::Error> {
+        if value.id != NODE_STATE.id {
+            return Err(ManyError::invalid_attribute_id(value.id));
+        }
+
+        let (height, secs, app_hash) = match value.into_arguments().as_slice() {
+            [CborAny::Int(height), CborAny::Int(secs), CborAny::Bytes(hash)] => {
+                (*height, *secs, Some(hash.clone()))
+            }
+            [CborAny::Int(height), CborAny::Int(secs), CborAny::Null] => (*height, *secs, None),
+            _ => return Err(ManyError::invalid_attribute_arguments()),
+        };
+        Ok(Self {
+            height: u64::try_from(height).map_err(|_| ManyError::invalid_attribute_arguments())?,
+            time: u64::try_from(secs)
+                .map_err(|_| ManyError::invalid_attribute_arguments())
+                .and_then(Timestamp::new)?,
+            app_hash,
+        })
+    }
+}
+
+impl TryFromAttributeSet for NodeState {
+    fn try_from_set(set: &AttributeSet) -> Result<Self, ManyError> {
+        match set.get_attribute(NODE_STATE.id) {
+            Some(attr) => NodeState::try_from(attr.clone()),
+            None => Err(ManyError::attribute_not_found(NODE_STATE.id.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute() {
+        let state = NodeState {
+            height: 12,
+            time: Timestamp::new(1_000).unwrap(),
+            app_hash: Some(vec![1; 32]),
+        };
+        let set = AttributeSet::from_iter([Attribute::from(state.clone())]);
+        assert_eq!(set.get::<NodeState>().unwrap(), state);
+
+        let state = NodeState {
+            app_hash: None,
+            ..state
+        };
+        let set = AttributeSet::from_iter([Attribute::from(state.clone())]);
+        assert_eq!(set.get::<NodeState>().unwrap(), state);
+
+        // The attribute of requests has no arguments.
+        assert!(NodeState::try_from(NODE_STATE).is_err());
+        assert!(NodeState::try_from(Attribute::new(
+            NODE_STATE.id,
+            vec![CborAny::Int(-1), CborAny::Int(1_000), CborAny::Null],
+        ))
+        .is_err());
+        assert!(AttributeSet::new().get::<NodeState>().is_err());
+    }
+}
@@ -9,6 +9,8 @@ use {
     },
 };
 
+pub mod cache;
+
 pub const PROOF: Attribute = Attribute::id(3);
 
 #[derive(Clone, Debug, Eq, From, Into, PartialEq)]
@@ -0,0 +1,155 @@
+//! A cache of the proofs of the keys of a store, so the proofs of keys that
+//! are often requested are not computed again until the store changes.
+use crate::ProofOperation;
+use lru::LruCache;
+use many_error::ManyError;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// The number of proofs kept by default.
+pub const DEFAULT_PROOF_CACHE_CAPACITY: usize = 1024;
+
+/// The keys proven together, at a height.
+type CacheKey = (u64, Vec<Vec<u8>>);
+
+/// The number of proofs served from the cache and computed, since the cache
+/// was created.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ProofCacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+/// Proofs by height and keys, least recently used first out. The store must
+/// invalidate the cache whenever it changes, since the height alone does not
+/// change until a block is committed.
+#[derive(Debug)]
+pub struct ProofCache {
+    proofs: Mutex<LruCache<CacheKey, Vec<ProofOperation>>>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+impl Default for ProofCache {
+    fn default() -> Self {
+        Self::new(NonZeroUsize::new(DEFAULT_PROOF_CACHE_CAPACITY).unwrap())
+    }
+}
+
+impl ProofCache {
+    pub fn new(capacity: NonZeroUsize) -> Self {
+        Self {
+            proofs: Mutex::new(LruCache::new(capacity)),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The proof of `keys` at `height`, computed by `prove` if it is not
+    /// cached. The keys are sorted first, so the order in which they are
+    /// requested does not matter. Failed proofs are not cached.
+    pub fn get_or_prove(
+        &self,
+        height: u64,
+        keys: impl IntoIterator<Item = Vec<u8>>,
+        prove: impl FnOnce(&[Vec<u8>]) -> Result<Vec<ProofOperation>, ManyError>,
+    ) -> Result<Vec<ProofOperation>, ManyError> {
+        let mut keys: Vec<Vec<u8>> = keys.into_iter().collect();
+        keys.sort();
+        keys.dedup();
+        let key = (height, keys);
+
+        if let Some(proof) = self.proofs.lock().unwrap().get(&key) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            return Ok(proof.clone());
+        }
+
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let proof = prove(&key.1)?;
+        self.proofs.lock().unwrap().put(key, proof.clone());
+        Ok(proof)
+    }
+
+    /// Remove every proof, e.g. after the store changed.
+    pub fn invalidate(&self) {
+        self.proofs.lock().unwrap().clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.proofs.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn stats(&self) -> ProofCacheStats {
+        ProofCacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proof(operations: &mut u32) -> Result<Vec<ProofOperation>, ManyError> {
+        *operations += 1;
+        Ok(vec![ProofOperation::NodeHash(vec![1, 2, 3])])
+    }
+
+    #[test]
+    fn hits_and_misses() {
+        let cache = ProofCache::default();
+        let mut computed = 0;
+
+        let first = cache
+            .get_or_prove(1, [b"b".to_vec(), b"a".to_vec()], |_| proof(&mut computed))
+            .unwrap();
+        let second = cache
+            .get_or_prove(1, [b"a".to_vec(), b"b".to_vec()], |_| proof(&mut computed))
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(computed, 1);
+
+        // Another height is another proof.
+        cache
+            .get_or_prove(2, [b"a".to_vec(), b"b".to_vec()], |_| proof(&mut computed))
+            .unwrap();
+        assert_eq!(computed, 2);
+        assert_eq!(cache.stats(), ProofCacheStats { hits: 1, misses: 2 });
+
+        cache.invalidate();
+        assert!(cache.is_empty());
+        cache
+            .get_or_prove(1, [b"a".to_vec()], |_| proof(&mut computed))
+            .unwrap();
+        assert_eq!(computed, 3);
+    }
+
+    #[test]
+    fn errors_are_not_cached() {
+        let cache = ProofCache::default();
+        assert!(cache
+            .get_or_prove(1, [b"a".to_vec()], |_| Err(ManyError::unknown("Failed")))
+            .is_err());
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn least_recently_used_first_out() {
+        let cache = ProofCache::new(NonZeroUsize::new(2).unwrap());
+        let mut computed = 0;
+        for key in [b"a", b"b", b"a", b"c", b"a"] {
+            cache
+                .get_or_prove(1, [key.to_vec()], |_| proof(&mut computed))
+                .unwrap();
+        }
+        // "b" was dropped for "c", "a" was kept.
+        assert_eq!(computed, 3);
+        assert_eq!(cache.len(), 2);
+    }
+}
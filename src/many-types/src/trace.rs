@@ -0,0 +1,107 @@
+use crate::attributes::{Attribute, AttributeSet, TryFromAttributeSet};
+use crate::cbor::CborAny;
+use many_error::ManyError;
+
+/// The request attribute carrying a trace context. Servers relaying a request
+/// (e.g. a gateway to the ABCI, to the ledger) keep the envelope, so they all
+/// report their span of the request in the same trace.
+pub const TRACE: Attribute = Attribute::id(4);
+
+/// A W3C trace context, i.e. the ID of a trace and of the parent span of the
+/// request in it.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub struct TraceContext {
+    pub trace_id: [u8; 16],
+    pub span_id: [u8; 8],
+}
+
+impl TraceContext {
+    pub fn new(trace_id: [u8; 16], span_id: [u8; 8]) -> Self {
+        Self { trace_id, span_id }
+    }
+
+    /// The trace ID in hexadecimal, as displayed by tracing backends.
+    pub fn trace_id_hex(&self) -> String {
+        hex::encode(self.trace_id)
+    }
+}
+
+impl From<TraceContext> for Attribute {
+    fn from(trace: TraceContext) -> Attribute {
+        TRACE
+            .with_argument(CborAny::Bytes(trace.trace_id.to_vec()))
+            .with_argument(CborAny::Bytes(trace.span_id.to_vec()))
+    }
+}
+
+impl TryFrom<Attribute> for TraceContext {
+    type Error = ManyError;
+
+    fn try_from(value: Attribute) -> Result<Self, Self::Error> {
+        if value.id != TRACE.id {
+            return Err(ManyError::invalid_attribute_id(value.id));
+        }
+
+        match value.into_arguments().as_slice() {
+            [CborAny::Bytes(trace_id), CborAny::Bytes(span_id)] => {
+                let trace_id: [u8; 16] = trace_id
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ManyError::invalid_attribute_arguments())?;
+                let span_id: [u8; 8] = span_id
+                    .as_slice()
+                    .try_into()
+                    .map_err(|_| ManyError::invalid_attribute_arguments())?;
+
+                // All-zero IDs are invalid in W3C trace contexts.
+                if trace_id == [0; 16] || span_id == [0; 8] {
+                    return Err(ManyError::invalid_attribute_arguments());
+                }
+                Ok(Self { trace_id, span_id })
+            }
+            _ => Err(ManyError::invalid_attribute_arguments()),
+        }
+    }
+}
+
+impl TryFromAttributeSet for TraceContext {
+    fn try_from_set(set: &AttributeSet) -> Result<Self, ManyError> {
+        match set.get_attribute(TRACE.id) {
+            Some(attr) => TraceContext::try_from(attr.clone()),
+            None => Err(ManyError::attribute_not_found(TRACE.id.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn attribute() {
+        let trace = TraceContext::new([1; 16], [2; 8]);
+        let set = AttributeSet::from_iter([Attribute::from(trace)]);
+        assert_eq!(set.get::<TraceContext>().unwrap(), trace);
+        assert_eq!(trace.trace_id_hex(), "01".repeat(16));
+    }
+
+    #[test]
+    fn invalid() {
+        let invalid = [
+            TRACE.with_argument(CborAny::Bytes(vec![1; 16])),
+            TRACE
+                .with_argument(CborAny::Bytes(vec![1; 15]))
+                .with_argument(CborAny::Bytes(vec![2; 8])),
+            TRACE
+                .with_argument(CborAny::Bytes(vec![0; 16]))
+                .with_argument(CborAny::Bytes(vec![2; 8])),
+            TRACE
+                .with_argument(CborAny::Int(1))
+                .with_argument(CborAny::Bytes(vec![2; 8])),
+        ];
+        for attr in invalid {
+            assert!(TraceContext::try_from(attr).is_err());
+        }
+        assert!(AttributeSet::new().get::<TraceContext>().is_err());
+    }
+}
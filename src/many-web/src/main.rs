@@ -1,8 +1,9 @@
 use clap::Parser;
+use many_cli_helpers::identity::read_pem;
 use many_cli_helpers::CommonCliFlags;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, Identity};
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity_dsa::CoseKeyVerifier;
 use many_identity_webauthn::WebAuthnVerifier;
 use many_modules::{abci_backend, events, kvstore, web};
 use many_protocol::ManyUrl;
@@ -119,8 +120,7 @@ fn main() {
         state = None;
     }
 
-    let pem = std::fs::read_to_string(pem).expect("Could not read PEM file.");
-    let key = CoseKeyIdentity::from_pem(pem).expect("Could not generate identity from PEM file.");
+    let key = read_pem(pem).expect("Could not generate identity from PEM file.");
     info!(address = key.address().to_string().as_str());
 
     let state = state.map(|state| {
@@ -179,6 +179,7 @@ fn main() {
 
         if abci {
             s.set_timeout(u64::MAX);
+            s.set_enforce_deadlines(false);
             s.add_module(abci_backend::AbciModule::new(module));
         }
 
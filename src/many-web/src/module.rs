@@ -4,14 +4,15 @@ use base64::{engine::general_purpose, Engine as _};
 use many_error::ManyError;
 use many_identity::Address;
 use many_modules::abci_backend::{
-    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, InitChainReturn,
-    ManyAbciModuleBackend,
+    AbciBlock, AbciCommitInfo, AbciInfo, AbciInit, BeginBlockReturn, EndpointInfo, GasCost,
+    InitChainReturn, ManyAbciModuleBackend,
 };
 use many_modules::kvstore::{GetArgs, GetReturns, KvStoreModuleBackend, QueryArgs, QueryReturns};
 use many_modules::web::{
     DeployArgs, DeployReturns, InfoArg, InfoReturns, ListArgs, ListReturns, RemoveArgs,
     RemoveReturns, UpdateArgs, UpdateReturns, WebCommandsModuleBackend, WebModuleBackend,
 };
+use many_types::clock::Clock;
 use many_types::web::{WebDeploymentInfo, WebDeploymentSource};
 use many_types::Timestamp;
 use sha2::Digest;
@@ -73,6 +74,11 @@ impl WebModuleImpl {
 
         Ok(Self { storage })
     }
+
+    /// Replace the clock used outside of blocks.
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.storage.set_clock(clock);
+    }
 }
 
 // This module is always supported, but will only be added when created using an ABCI
@@ -93,7 +99,13 @@ impl ManyAbciModuleBackend for WebModuleImpl {
                 // Events
                 ("events.info".to_string(), EndpointInfo { is_command: false }),
                 ("events.list".to_string(), EndpointInfo { is_command: false }),
+                ("events.types".to_string(), EndpointInfo { is_command: false }),
             ]),
+            gas: Some(BTreeMap::from([
+                ("web.deploy".to_string(), GasCost::new(50_000, 10)),
+                ("web.update".to_string(), GasCost::new(50_000, 10)),
+            ])),
+            block_gas_limit: None,
         })
     }
 
@@ -337,7 +349,7 @@ impl WebCommandsModuleBackend for WebModuleImpl {
 
         let site_name = _transform_site_name(site_name);
         self.storage.remove_website(sender, site_name, memo)?;
-        Ok(RemoveReturns {})
+        Ok(RemoveReturns::default())
     }
 
     fn update(&mut self, sender: &Address, args: UpdateArgs) -> Result<UpdateReturns, ManyError> {
@@ -466,6 +478,24 @@ impl KvStoreModuleBackend for WebModuleImpl {
     ) -> Result<many_modules::kvstore::list::ListReturns, ManyError> {
         Err(ManyError::unknown("Unimplemented"))
     }
+
+    // We do not expose this endpoint
+    fn list_by_owner(
+        &self,
+        _sender: &Address,
+        _args: many_modules::kvstore::list::ListByOwnerArgs,
+    ) -> Result<many_modules::kvstore::list::ListByOwnerReturns, ManyError> {
+        Err(ManyError::unknown("Unimplemented"))
+    }
+
+    // We do not expose this endpoint
+    fn batch_get(
+        &self,
+        _sender: &Address,
+        _args: many_modules::kvstore::BatchGetArgs,
+    ) -> Result<many_modules::kvstore::BatchGetReturns, ManyError> {
+        Err(ManyError::unknown("Unimplemented"))
+    }
 }
 
 #[cfg(test)]
@@ -5,6 +5,7 @@ use many_error::ManyError;
 use many_identity::Address;
 use many_modules::abci_backend::AbciCommitInfo;
 use many_modules::events::{EventId, EventInfo};
+use many_types::clock::{Clock, SystemClock};
 use many_types::web::{WebDeploymentFilter, WebDeploymentInfo};
 use many_types::{Memo, SortOrder, Timestamp};
 use merk::{BatchEntry, Op};
@@ -48,6 +49,7 @@ pub struct WebStorage {
     latest_event_id: EventId,
     current_time: Option<Timestamp>,
     current_hash: Option<Vec<u8>>,
+    clock: Box<dyn Clock>,
     #[allow(dead_code)]
     next_subresource: u32,
     #[allow(dead_code)]
@@ -66,8 +68,12 @@ impl WebStorage {
         self.current_time = Some(time);
     }
     #[inline]
+    pub fn set_clock(&mut self, clock: impl Clock + 'static) {
+        self.clock = Box::new(clock);
+    }
+    #[inline]
     pub fn now(&self) -> Timestamp {
-        self.current_time.unwrap_or_else(Timestamp::now)
+        self.current_time.unwrap_or_else(|| self.clock.now())
     }
 
     #[inline]
@@ -119,6 +125,7 @@ impl WebStorage {
             persistent_store,
             blockchain,
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             latest_event_id,
             next_subresource,
@@ -159,6 +166,7 @@ impl WebStorage {
             persistent_store,
             blockchain,
             current_time: None,
+            clock: Box::new(SystemClock),
             current_hash: None,
             latest_event_id,
             next_subresource: 0,
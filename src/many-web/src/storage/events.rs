@@ -52,6 +52,7 @@ impl WebStorage {
             id: self.new_event_id(),
             time: self.now(),
             content,
+            chain_hash: None,
         };
 
         self.persistent_store
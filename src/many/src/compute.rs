@@ -0,0 +1,719 @@
+use anyhow::anyhow;
+use clap::Parser;
+use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::output::{CommandOutput, OutputFormat};
+use many_client::ManyClient;
+use many_identity::{Address, AnonymousIdentity, Identity};
+use many_modules::compute;
+use many_types::compute::{ByteUnits, ComputeListFilter, ComputeStatus, DeploymentMeta, Region};
+use many_types::SortOrder;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+#[derive(clap::ArgEnum, Clone, Debug)]
+enum StatusFilter {
+    Deployed,
+    Closed,
+}
+
+#[derive(Parser)]
+pub struct ComputeOpt {
+    /// The compute server to connect to.
+    #[clap(long, default_value = "http://localhost:8000")]
+    server: url::Url,
+
+    /// The identity of the server (an identity string), or anonymous if you don't know it.
+    #[clap(long, default_value_t)]
+    server_id: Address,
+
+    /// A PEM file for the identity. If this is omitted, anonymous will be used.
+    #[clap(long)]
+    pem: Option<PathBuf>,
+
+    #[clap(subcommand)]
+    subcommand: ComputeCommand,
+}
+
+#[derive(Parser)]
+enum ComputeCommand {
+    /// Deploy a single service described by an SDL file.
+    Deploy(DeployOpt),
+
+    /// Deploy a single service from a template of the server, e.g.
+    /// `many compute deploy-template default --image nginx:latest`.
+    DeployTemplate(DeployTemplateOpt),
+
+    /// List deployments.
+    List(ListOpt),
+
+    /// Show the status of a deployment.
+    Status(StatusOpt),
+
+    /// Show the logs of a deployment.
+    Logs(LogsOpt),
+
+    /// Run a command in a deployment, e.g. `many compute exec 1234 -- ls -l`.
+    Exec(ExecOpt),
+
+    /// Close a deployment.
+    Close(DeploymentOpt),
+
+    /// Estimate the price of deploying an SDL file, without deploying it.
+    /// The server must implement `compute.estimate`.
+    Estimate(DeployOpt),
+
+    /// Show the funds in the escrow of the server, for servers funding
+    /// deployments from a MANY ledger. Funds are added by sending tokens to
+    /// the escrow account shown.
+    Escrow(EscrowOpt),
+}
+
+#[derive(Parser)]
+struct DeployOpt {
+    /// The SDL file describing the deployment. Only the first service and
+    /// its compute profile are used.
+    sdl: PathBuf,
+}
+
+#[derive(Parser)]
+struct DeployTemplateOpt {
+    /// The name of the template.
+    template: String,
+
+    /// The container image of the service.
+    #[clap(long)]
+    image: String,
+
+    /// The port exposed by the service.
+    #[clap(long, default_value_t = 80)]
+    port: u16,
+
+    /// The number of CPU units.
+    #[clap(long, default_value_t = 1)]
+    cpu: u64,
+
+    /// The memory of the service, e.g. `512Mi`.
+    #[clap(long, default_value = "512Mi")]
+    memory: String,
+
+    /// The storage of the service, e.g. `1Gi`.
+    #[clap(long, default_value = "1Gi")]
+    storage: String,
+
+    #[clap(long, default_value = "us-west")]
+    region: String,
+
+    /// An environment variable of the service, as `NAME=value`. Multiple
+    /// occurences of this argument can be given.
+    #[clap(long = "env", value_name = "NAME=VALUE")]
+    env: Vec<String>,
+
+    /// A value for a parameter of the template, as `name=value`. Multiple
+    /// occurences of this argument can be given.
+    #[clap(long = "param", value_name = "NAME=VALUE")]
+    params: Vec<String>,
+}
+
+#[derive(Parser)]
+struct ListOpt {
+    /// Only list the deployments of this owner. By default, list the
+    /// deployments of the identity used.
+    #[clap(long)]
+    owner: Option<Address>,
+
+    /// List the deployments of all owners.
+    #[clap(long, conflicts_with("owner"))]
+    all: bool,
+
+    /// Only list deployments with this status.
+    #[clap(long, arg_enum)]
+    status: Option<StatusFilter>,
+
+    /// Order of the deployments.
+    #[clap(long)]
+    order: Option<SortOrder>,
+}
+
+#[derive(Parser)]
+struct StatusOpt {
+    /// The deployment sequence number.
+    dseq: u64,
+
+    /// The owner of the deployment. By default, the identity used.
+    #[clap(long)]
+    owner: Option<Address>,
+}
+
+#[derive(Parser)]
+struct EscrowOpt {
+    /// The owner of the funds. By default, the identity used.
+    #[clap(long)]
+    owner: Option<Address>,
+}
+
+#[derive(Parser)]
+struct DeploymentOpt {
+    /// The deployment sequence number.
+    dseq: u64,
+}
+
+#[derive(Parser)]
+struct LogsOpt {
+    /// The deployment sequence number.
+    dseq: u64,
+
+    /// The service of the deployment. By default, its only service.
+    #[clap(long)]
+    service: Option<String>,
+
+    /// The number of lines to show. The server may return less.
+    #[clap(long)]
+    count: Option<u64>,
+
+    /// Skip this number of most recent lines, to page back through the logs.
+    #[clap(long)]
+    skip: Option<u64>,
+}
+
+#[derive(Parser)]
+struct ExecOpt {
+    /// The deployment sequence number.
+    dseq: u64,
+
+    /// The service of the deployment. By default, its only service.
+    #[clap(long)]
+    service: Option<String>,
+
+    /// The program to run and its arguments.
+    #[clap(required = true, last = true)]
+    command: Vec<String>,
+}
+
+fn yaml_str<'a>(value: &'a serde_yaml::Value, path: &str) -> Result<&'a str, anyhow::Error> {
+    value
+        .as_str()
+        .ok_or_else(|| anyhow!("SDL field `{path}` must be a string"))
+}
+
+fn yaml_u64(value: &serde_yaml::Value, path: &str) -> Result<u64, anyhow::Error> {
+    match value {
+        serde_yaml::Value::Number(n) => n.as_u64(),
+        serde_yaml::Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow!("SDL field `{path}` must be a positive integer"))
+}
+
+fn yaml_first<'a>(
+    value: &'a serde_yaml::Value,
+    path: &str,
+) -> Result<(&'a str, &'a serde_yaml::Value), anyhow::Error> {
+    value
+        .as_mapping()
+        .and_then(|m| m.iter().next())
+        .and_then(|(k, v)| Some((k.as_str()?, v)))
+        .ok_or_else(|| anyhow!("SDL field `{path}` must have at least one entry"))
+}
+
+/// Parse a size like `512Mi` into its amount and unit.
+fn parse_size(size: &str) -> Result<(u64, ByteUnits), anyhow::Error> {
+    use ByteUnits::*;
+
+    let split = size
+        .find(|c: char| !c.is_ascii_digit())
+        .ok_or_else(|| anyhow!("Size `{size}` has no unit"))?;
+    let (amount, unit) = size.split_at(split);
+    let amount = amount
+        .parse()
+        .map_err(|_| anyhow!("Invalid size `{size}`"))?;
+    let unit = [K, KI, M, MI, G, GI, T, TI, P, PI, E, EI]
+        .into_iter()
+        .find(|u| u.to_string() == unit)
+        .ok_or_else(|| anyhow!("Unknown unit `{unit}` in size `{size}`"))?;
+    Ok((amount, unit))
+}
+
+fn parse_region(region: &str) -> Result<Region, anyhow::Error> {
+    [Region::UsEast, Region::UsWest]
+        .into_iter()
+        .find(|r| r.to_string() == region)
+        .ok_or_else(|| anyhow!("Unknown region `{region}`"))
+}
+
+/// Build the deployment arguments from an Akash SDL document.
+fn deploy_args_from_sdl(sdl: &str) -> Result<compute::DeployArgs, anyhow::Error> {
+    let sdl: serde_yaml::Value = serde_yaml::from_str(sdl)?;
+
+    let (name, service) = yaml_first(&sdl["services"], "services")?;
+    let image = yaml_str(&service["image"], "image")?.to_string();
+    let port = yaml_u64(&service["expose"][0]["port"], "expose.port")?;
+
+    let resources = &sdl["profiles"]["compute"][name]["resources"];
+    let num_cpu = yaml_u64(&resources["cpu"]["units"], "cpu.units")?;
+    let (num_memory, memory_type) =
+        parse_size(yaml_str(&resources["memory"]["size"], "memory.size")?)?;
+    let (num_storage, storage_type) =
+        parse_size(yaml_str(&resources["storage"]["size"], "storage.size")?)?;
+
+    let (_, placement) = yaml_first(&sdl["profiles"]["placement"], "placement")?;
+    let region = parse_region(yaml_str(
+        &placement["attributes"]["region"],
+        "placement.attributes.region",
+    )?)?;
+
+    Ok(compute::DeployArgs {
+        image,
+        port: port
+            .try_into()
+            .map_err(|_| anyhow!("Invalid port {port}"))?,
+        num_cpu,
+        num_memory,
+        memory_type,
+        num_storage,
+        storage_type,
+        region,
+    })
+}
+
+/// Parse `NAME=value` pairs, e.g. environment variables.
+fn parse_pairs(pairs: &[String]) -> Result<BTreeMap<String, String>, anyhow::Error> {
+    pairs
+        .iter()
+        .map(|pair| {
+            pair.split_once('=')
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .ok_or_else(|| anyhow!("Expected NAME=value, got `{pair}`"))
+        })
+        .collect()
+}
+
+fn deploy_template_args(
+    opt: &DeployTemplateOpt,
+) -> Result<compute::DeployTemplateArgs, anyhow::Error> {
+    let (num_memory, memory_type) = parse_size(&opt.memory)?;
+    let (num_storage, storage_type) = parse_size(&opt.storage)?;
+    Ok(compute::DeployTemplateArgs {
+        template: opt.template.clone(),
+        deployment: compute::DeployArgs {
+            image: opt.image.clone(),
+            port: opt.port,
+            num_cpu: opt.cpu,
+            num_memory,
+            memory_type,
+            num_storage,
+            storage_type,
+            region: parse_region(&opt.region)?,
+        },
+        env: parse_pairs(&opt.env)?,
+        parameters: parse_pairs(&opt.params)?,
+    })
+}
+
+fn read_sdl(path: &PathBuf) -> Result<compute::DeployArgs, anyhow::Error> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| anyhow!("Could not read {}: {e}", path.display()))?;
+    deploy_args_from_sdl(&content)
+}
+
+fn deployment_json(d: &DeploymentMeta) -> serde_json::Value {
+    json!({
+        "dseq": d.dseq,
+        "status": d.status.to_string(),
+        "image": d.image,
+        "provider": d.meta.as_ref().map(|m| &m.provider),
+        "host": d.meta.as_ref().and_then(|m| m.provider_info.host.as_ref()),
+        "port": d.meta.as_ref().map(|m| m.provider_info.port),
+        "external_port": d.meta.as_ref().map(|m| m.provider_info.external_port),
+        "protocol": d.meta.as_ref().map(|m| m.provider_info.protocol.to_string()),
+        "price": d.meta.as_ref().map(|m| m.price),
+    })
+}
+
+fn format_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{c:<width$}", width = *w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.trim_end().to_string()
+    };
+    let mut lines = vec![line(headers.to_vec())];
+    for row in &rows {
+        lines.push(line(row.iter().map(String::as_str).collect()));
+    }
+    lines.join("\n")
+}
+
+fn deployments_output(cbor: Vec<u8>, deployments: &[DeploymentMeta]) -> CommandOutput {
+    let list: Vec<_> = deployments.iter().map(deployment_json).collect();
+    CommandOutput::cbor(cbor)
+        .with_json(list.into())
+        .with_plain(format_table(
+            &["DSEQ", "STATUS", "IMAGE", "PROVIDER", "PRICE"],
+            deployments
+                .iter()
+                .map(|d| {
+                    vec![
+                        d.dseq.to_string(),
+                        d.status.to_string(),
+                        d.image.clone(),
+                        d.meta
+                            .as_ref()
+                            .map_or_else(|| "-".to_string(), |m| m.provider.clone()),
+                        d.meta
+                            .as_ref()
+                            .map_or_else(|| "-".to_string(), |m| m.price.to_string()),
+                    ]
+                })
+                .collect(),
+        ))
+}
+
+fn deployment_output(cbor: Vec<u8>, d: &DeploymentMeta) -> CommandOutput {
+    let value = deployment_json(d);
+    let rows = value
+        .as_object()
+        .unwrap()
+        .iter()
+        .map(|(k, v)| {
+            let v = match v {
+                serde_json::Value::Null => "-".to_string(),
+                serde_json::Value::String(s) => s.clone(),
+                v => v.to_string(),
+            };
+            vec![k.to_uppercase(), v]
+        })
+        .collect();
+    let plain = format_table(&["FIELD", "VALUE"], rows);
+    CommandOutput::cbor(cbor).with_json(value).with_plain(plain)
+}
+
+fn estimate_output(cbor: Vec<u8>, e: &compute::EstimateReturns) -> CommandOutput {
+    CommandOutput::cbor(cbor)
+        .with_json(json!({
+            "denom": e.denom,
+            "price": e.price,
+            "min_price": e.min_price,
+            "max_price": e.max_price,
+            "monthly_cost": e.monthly_cost,
+            "bids": e.bids,
+        }))
+        .with_plain(format_table(
+            &["FIELD", "VALUE"],
+            vec![
+                vec![
+                    "PRICE".to_string(),
+                    format!("{} {}/block", e.price, e.denom),
+                ],
+                vec![
+                    "RANGE".to_string(),
+                    format!("{}-{} {}/block", e.min_price, e.max_price, e.denom),
+                ],
+                vec![
+                    "MONTHLY".to_string(),
+                    format!("{:.0} {}", e.monthly_cost, e.denom),
+                ],
+                vec!["BIDS".to_string(), e.bids.to_string()],
+            ],
+        ))
+}
+
+fn escrow_output(cbor: Vec<u8>, e: &compute::EscrowReturns) -> CommandOutput {
+    let deployments: BTreeMap<String, serde_json::Value> = e
+        .deployments
+        .iter()
+        .map(|(dseq, d)| {
+            (
+                dseq.to_string(),
+                json!({
+                    "price": d.price,
+                    "deposited": d.deposited,
+                    "charged": d.charged.to_string(),
+                    "since": d.since.secs(),
+                }),
+            )
+        })
+        .collect();
+    let mut rows = vec![
+        vec!["ACCOUNT".to_string(), e.account.to_string()],
+        vec!["SYMBOL".to_string(), e.symbol.to_string()],
+        vec!["BALANCE".to_string(), e.balance.to_string()],
+    ];
+    rows.extend(e.deployments.iter().map(|(dseq, d)| {
+        vec![
+            format!("DEPLOYMENT {dseq}"),
+            format!("{} uakt deposited for {}", d.deposited, d.charged),
+        ]
+    }));
+    CommandOutput::cbor(cbor)
+        .with_json(json!({
+            "account": e.account.to_string(),
+            "symbol": e.symbol.to_string(),
+            "balance": e.balance.to_string(),
+            "deployments": deployments,
+        }))
+        .with_plain(format_table(&["FIELD", "VALUE"], rows))
+}
+
+async fn find_deployment(
+    client: &ManyClient<impl Identity>,
+    owner: Address,
+    dseq: u64,
+) -> Result<DeploymentMeta, ClientServerError> {
+    let response = client
+        .call_(
+            "compute.list",
+            compute::ListArgs {
+                owner: Some(owner),
+                order: None,
+                filter: Some(ComputeListFilter::All),
+            },
+        )
+        .await?;
+    let list: compute::ListReturns = minicbor::decode(&response)?;
+    list.deployments
+        .into_iter()
+        .find(|d| d.dseq == dseq)
+        .ok_or_else(|| anyhow!("Deployment {dseq} not found for {owner}").into())
+}
+
+pub async fn compute(opt: ComputeOpt, format: OutputFormat) -> Result<(), ClientServerError> {
+    let ComputeOpt {
+        server,
+        server_id,
+        pem,
+        subcommand,
+    } = opt;
+    let key: Box<dyn Identity> = match pem {
+        Some(p) => Box::new(read_pem(p)?),
+        None => Box::new(AnonymousIdentity),
+    };
+    let address = key.address();
+    let client = ManyClient::new(server, server_id, key).map_err(|e| anyhow!(e))?;
+
+    let output = match subcommand {
+        ComputeCommand::Deploy(o) => {
+            let args = read_sdl(&o.sdl)?;
+            let response = client.call_("compute.deploy", args).await?;
+            let compute::DeployReturns(deployment) = minicbor::decode(&response)?;
+            deployment_output(response, &deployment)
+        }
+        ComputeCommand::DeployTemplate(o) => {
+            let args = deploy_template_args(&o)?;
+            let response = client.call_("compute.deployTemplate", args).await?;
+            let compute::DeployReturns(deployment) = minicbor::decode(&response)?;
+            deployment_output(response, &deployment)
+        }
+        ComputeCommand::List(o) => {
+            let response = client
+                .call_(
+                    "compute.list",
+                    compute::ListArgs {
+                        owner: if o.all {
+                            None
+                        } else {
+                            Some(o.owner.unwrap_or(address))
+                        },
+                        order: o.order,
+                        filter: Some(match o.status {
+                            None => ComputeListFilter::All,
+                            Some(StatusFilter::Deployed) => {
+                                ComputeListFilter::Status(ComputeStatus::Deployed)
+                            }
+                            Some(StatusFilter::Closed) => {
+                                ComputeListFilter::Status(ComputeStatus::Closed)
+                            }
+                        }),
+                    },
+                )
+                .await?;
+            let list: compute::ListReturns = minicbor::decode(&response)?;
+            deployments_output(response, &list.deployments)
+        }
+        ComputeCommand::Status(o) => {
+            let deployment = find_deployment(&client, o.owner.unwrap_or(address), o.dseq).await?;
+            deployment_output(
+                minicbor::to_vec(&deployment).map_err(|e| anyhow!(e))?,
+                &deployment,
+            )
+        }
+        ComputeCommand::Logs(o) => {
+            let response = client
+                .call_(
+                    "compute.logs",
+                    compute::LogsArgs {
+                        dseq: o.dseq,
+                        service: o.service,
+                        count: o.count,
+                        skip: o.skip,
+                    },
+                )
+                .await?;
+            let logs: compute::LogsReturns = minicbor::decode(&response)?;
+            CommandOutput::cbor(response)
+                .with_json(json!({
+                    "lines": logs.lines,
+                    "more": logs.more,
+                }))
+                .with_plain(logs.lines.join("\n"))
+        }
+        ComputeCommand::Exec(o) => {
+            let response = client
+                .call_(
+                    "compute.exec",
+                    compute::ExecArgs {
+                        dseq: o.dseq,
+                        command: o.command,
+                        service: o.service,
+                    },
+                )
+                .await?;
+            let exec: compute::ExecReturns = minicbor::decode(&response)?;
+            // The exit code of the command is the one of the CLI, so the
+            // output is printed before exiting.
+            CommandOutput::cbor(response)
+                .with_json(json!({
+                    "exit_code": exec.exit_code,
+                    "stdout": String::from_utf8_lossy(&exec.stdout),
+                    "stderr": String::from_utf8_lossy(&exec.stderr),
+                }))
+                .with_raw_plain(exec.stdout.to_vec())
+                .print(format)?;
+            if format == OutputFormat::Plain {
+                use std::io::Write;
+                std::io::stderr()
+                    .write_all(&exec.stderr)
+                    .map_err(|e| anyhow!(e))?;
+            }
+            if exec.exit_code != Some(0) {
+                std::process::exit(exec.exit_code.unwrap_or(1));
+            }
+            return Ok(());
+        }
+        ComputeCommand::Close(o) => {
+            let response = client
+                .call_("compute.close", compute::CloseArgs { dseq: o.dseq })
+                .await?;
+            CommandOutput::cbor(response)
+                .with_json(json!({ "dseq": o.dseq, "closed": true }))
+                .with_plain(format!("Deployment {} closed.", o.dseq))
+        }
+        ComputeCommand::Estimate(o) => {
+            let args = read_sdl(&o.sdl)?;
+            let response = client.call_("compute.estimate", args).await?;
+            let estimate: compute::EstimateReturns = minicbor::decode(&response)?;
+            estimate_output(response, &estimate)
+        }
+        ComputeCommand::Escrow(o) => {
+            let response = client
+                .call_(
+                    "compute.escrow",
+                    compute::EscrowArgs {
+                        owner: Some(o.owner.unwrap_or(address)),
+                    },
+                )
+                .await?;
+            let escrow: compute::EscrowReturns = minicbor::decode(&response)?;
+            escrow_output(response, &escrow)
+        }
+    };
+
+    output.print(format).map_err(Into::into)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SDL: &str = r#"---
+version: "2.0"
+
+services:
+  web:
+    image: nginx:latest
+    expose:
+      - port: 80
+        to:
+          - global: true
+profiles:
+  compute:
+    web:
+      resources:
+        cpu:
+          units: 1
+        memory:
+          size: 512Mi
+        storage:
+          size: 1Gi
+  placement:
+    akash:
+      attributes:
+        host: akash
+        region: us-west
+deployment:
+  web:
+    akash:
+      profile: web
+      count: 1"#;
+
+    #[test]
+    fn sdl() {
+        let args = deploy_args_from_sdl(SDL).unwrap();
+        assert_eq!(args.image, "nginx:latest");
+        assert_eq!(args.port, 80);
+        assert_eq!(args.num_cpu, 1);
+        assert_eq!((args.num_memory, args.memory_type), (512, ByteUnits::MI));
+        assert_eq!((args.num_storage, args.storage_type), (1, ByteUnits::GI));
+        assert_eq!(args.region, Region::UsWest);
+    }
+
+    #[test]
+    fn sdl_invalid() {
+        assert!(deploy_args_from_sdl(&SDL.replace("512Mi", "512")).is_err());
+        assert!(deploy_args_from_sdl(&SDL.replace("us-west", "eu-north")).is_err());
+        assert!(deploy_args_from_sdl(&SDL.replace("port: 80", "port: 80000")).is_err());
+        assert!(deploy_args_from_sdl("services: {}").is_err());
+    }
+
+    #[test]
+    fn template_args() {
+        let opt = DeployTemplateOpt::parse_from([
+            "deploy-template",
+            "web",
+            "--image",
+            "nginx:latest",
+            "--memory",
+            "1Gi",
+            "--env",
+            "MODE=prod",
+            "--env",
+            "URL=http://a?b=c",
+            "--param",
+            "replicas=2",
+        ]);
+        let args = deploy_template_args(&opt).unwrap();
+        assert_eq!(args.template, "web");
+        assert_eq!(args.deployment.port, 80);
+        assert_eq!(
+            (args.deployment.num_memory, args.deployment.memory_type),
+            (1, ByteUnits::GI)
+        );
+        assert_eq!(args.env["URL"], "http://a?b=c");
+        assert_eq!(args.parameters["replicas"], "2");
+
+        let opt =
+            DeployTemplateOpt::parse_from(["deploy-template", "web", "--image", "a", "--env", "X"]);
+        assert!(deploy_template_args(&opt).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+//! Signing with an Ed25519 key held on a Ledger Nano device, through the
+//! MANY app of the device. The private key never leaves the device, and
+//! every message is approved on its screen.
+//!
+//! The app is driven with APDUs. Messages larger than an APDU are sent in
+//! chunks, the first one starting with the derivation path of the key.
+use anyhow::anyhow;
+use coset::{CoseKey, CoseSign1, CoseSign1Builder};
+use ledger_apdu::APDUCommand;
+use ledger_transport_hid::hidapi::HidApi;
+use ledger_transport_hid::TransportNativeHID;
+use many_error::ManyError;
+use many_identity::{cose, Address, Identity};
+use many_identity_dsa::ed25519::{eddsa_cose_key, Ed25519Verifier};
+use tracing::trace;
+
+/// The derivation path of the key used by default. All of its levels are
+/// hardened, as Ed25519 keys can only be derived that way.
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/1'/0'/0'/0'";
+
+const CLA: u8 = 0xE0;
+const INS_GET_PUBLIC_KEY: u8 = 0x02;
+const INS_SIGN: u8 = 0x03;
+
+/// The first chunk of a message, or the following ones.
+const P1_FIRST: u8 = 0x00;
+const P1_NEXT: u8 = 0x01;
+
+/// Whether more chunks follow.
+const P2_LAST: u8 = 0x00;
+const P2_MORE: u8 = 0x80;
+
+const MAX_CHUNK_SIZE: usize = 255;
+const HARDENED: u32 = 0x8000_0000;
+
+const SW_OK: u16 = 0x9000;
+const SW_DENIED: u16 = 0x6985;
+const SW_INS_NOT_SUPPORTED: u16 = 0x6D00;
+const SW_CLA_NOT_SUPPORTED: u16 = 0x6E00;
+
+/// Parse a derivation path like `m/44'/1'/0'`. Every level needs to be
+/// hardened.
+pub fn parse_derivation_path(path: &str) -> Result<Vec<u32>, anyhow::Error> {
+    let levels = path.strip_prefix("m/").unwrap_or(path);
+    levels
+        .split('/')
+        .map(|level| {
+            let index = level
+                .strip_suffix('\'')
+                .or_else(|| level.strip_suffix('h'))
+                .ok_or_else(|| anyhow!("Level {level:?} of {path:?} is not hardened"))?;
+            let index: u32 = index
+                .parse()
+                .map_err(|e| anyhow!("Invalid level {level:?} of {path:?}: {e}"))?;
+            if index >= HARDENED {
+                return Err(anyhow!("Level {level:?} of {path:?} is too large"));
+            }
+            Ok(index | HARDENED)
+        })
+        .collect()
+}
+
+fn encode_derivation_path(path: &[u32]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(1 + 4 * path.len());
+    bytes.push(path.len() as u8);
+    for level in path {
+        bytes.extend_from_slice(&level.to_be_bytes());
+    }
+    bytes
+}
+
+/// The chunks of a message to sign, after the derivation path.
+fn sign_chunks(path: &[u32], message: &[u8]) -> Vec<Vec<u8>> {
+    let mut first = encode_derivation_path(path);
+    let (head, tail) = message.split_at(message.len().min(MAX_CHUNK_SIZE - first.len()));
+    first.extend_from_slice(head);
+    std::iter::once(first)
+        .chain(tail.chunks(MAX_CHUNK_SIZE).map(<[u8]>::to_vec))
+        .collect()
+}
+
+/// An identity signing with the key of a Ledger device, at a derivation path.
+pub struct LedgerIdentity {
+    address: Address,
+    key: CoseKey,
+    path: Vec<u32>,
+    transport: TransportNativeHID,
+}
+
+impl LedgerIdentity {
+    /// Connect to the first Ledger device found, and get its public key at
+    /// `path`. The MANY app needs to be open on the device.
+    pub fn connect(path: &str) -> Result<Self, anyhow::Error> {
+        let path = parse_derivation_path(path)?;
+        let api = HidApi::new().map_err(|e| anyhow!("Could not access the USB devices: {e}"))?;
+        let transport = TransportNativeHID::new(&api)
+            .map_err(|e| anyhow!("Could not connect to a Ledger device: {e}"))?;
+
+        trace!("Getting the public key from the Ledger device");
+        let public_key = exchange(
+            &transport,
+            INS_GET_PUBLIC_KEY,
+            P1_FIRST,
+            P2_LAST,
+            encode_derivation_path(&path),
+        )?;
+        if public_key.len() != 32 {
+            return Err(anyhow!(
+                "Invalid Ed25519 public key length: {}",
+                public_key.len()
+            ));
+        }
+
+        let key = eddsa_cose_key(public_key, None);
+        let address = unsafe { cose::address_unchecked(&key) }?;
+        Ok(Self {
+            address,
+            key,
+            path,
+            transport,
+        })
+    }
+
+    /// Sign the bytes on the device, once they are approved on its screen.
+    fn sign(&self, bytes: &[u8]) -> Result<Vec<u8>, ManyError> {
+        let chunks = sign_chunks(&self.path, bytes);
+        let last = chunks.len() - 1;
+        let mut signature = vec![];
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let p1 = if i == 0 { P1_FIRST } else { P1_NEXT };
+            let p2 = if i == last { P2_LAST } else { P2_MORE };
+            signature = exchange(&self.transport, INS_SIGN, p1, p2, chunk)
+                .map_err(|e| ManyError::unknown(e.to_string()))?;
+        }
+
+        // A signature of another key, e.g. of another device, is useless.
+        Ed25519Verifier::from_key(&self.key)?
+            .verify_signature(&signature, bytes)
+            .map_err(|e| ManyError::unknown(format!("Invalid signature from the device: {e}")))?;
+        Ok(signature)
+    }
+}
+
+fn exchange(
+    transport: &TransportNativeHID,
+    ins: u8,
+    p1: u8,
+    p2: u8,
+    data: Vec<u8>,
+) -> Result<Vec<u8>, anyhow::Error> {
+    let answer = transport
+        .exchange(&APDUCommand {
+            cla: CLA,
+            ins,
+            p1,
+            p2,
+            data,
+        })
+        .map_err(|e| anyhow!("Could not communicate with the Ledger device: {e}"))?;
+
+    match answer.retcode() {
+        SW_OK => Ok(answer.data().to_vec()),
+        SW_DENIED => Err(anyhow!("The request was rejected on the Ledger device")),
+        SW_INS_NOT_SUPPORTED | SW_CLA_NOT_SUPPORTED => {
+            Err(anyhow!("Open the MANY app on the Ledger device"))
+        }
+        code => Err(anyhow!("The Ledger device returned error {code:#06x}")),
+    }
+}
+
+impl Identity for LedgerIdentity {
+    fn address(&self) -> Address {
+        self.address
+    }
+
+    fn public_key(&self) -> Option<CoseKey> {
+        Some(self.key.clone())
+    }
+
+    fn sign_1(&self, envelope: CoseSign1) -> Result<CoseSign1, ManyError> {
+        let mut envelope = cose::add_keyset_header(envelope, self)?;
+
+        // Add the algorithm and key id.
+        envelope.protected.header.alg =
+            Some(coset::Algorithm::Assigned(coset::iana::Algorithm::EdDSA));
+        envelope.protected.header.key_id = self.address.to_vec();
+
+        let builder = CoseSign1Builder::new()
+            .protected(envelope.protected.header)
+            .unprotected(envelope.unprotected);
+
+        let builder = if let Some(payload) = envelope.payload {
+            builder.payload(payload)
+        } else {
+            builder
+        };
+
+        trace!("Waiting for the approval of the message on the Ledger device");
+        Ok(builder
+            .try_create_signature(&[], |bytes| self.sign(bytes))?
+            .build())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derivation_path() {
+        assert_eq!(
+            parse_derivation_path("m/44'/1'/0h").unwrap(),
+            vec![44 | HARDENED, 1 | HARDENED, HARDENED]
+        );
+        assert_eq!(
+            parse_derivation_path(DEFAULT_DERIVATION_PATH)
+                .unwrap()
+                .len(),
+            5
+        );
+        assert!(parse_derivation_path("m/44'/1'/0").is_err());
+        assert!(parse_derivation_path("m/44'/x'").is_err());
+        assert!(parse_derivation_path("m/2147483648'").is_err());
+    }
+
+    #[test]
+    fn message_chunks() {
+        let path = parse_derivation_path("m/44'/1'/0'").unwrap();
+        let message: Vec<u8> = (0..600u32).map(|i| i as u8).collect();
+        let chunks = sign_chunks(&path, &message);
+
+        assert_eq!(
+            chunks.iter().map(Vec::len).collect::<Vec<_>>(),
+            vec![
+                MAX_CHUNK_SIZE,
+                MAX_CHUNK_SIZE,
+                600 + 13 - 2 * MAX_CHUNK_SIZE
+            ]
+        );
+        assert_eq!(&chunks[0][..13], encode_derivation_path(&path).as_slice());
+        assert_eq!(chunks.concat()[13..], message);
+
+        // An empty message is still sent, with the path.
+        assert_eq!(sign_chunks(&path, &[]), vec![encode_derivation_path(&path)]);
+    }
+}
@@ -3,14 +3,18 @@ use async_recursion::async_recursion;
 use base64::{engine::general_purpose, Engine as _};
 use clap::{ArgGroup, Parser};
 use coset::{CborSerializable, CoseSign1};
+use indicatif::{ProgressBar, ProgressStyle};
 use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::output::{CommandOutput, OutputFlags, OutputFormat};
+use many_client::verify::BulkVerifier;
 use many_client::ManyClient;
 use many_identity::verifiers::AnonymousVerifier;
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::{CoseKeyIdentity, CoseKeyVerifier};
+use many_identity_dsa::CoseKeyVerifier;
 use many_identity_hsm::{Hsm, HsmIdentity, HsmMechanismType, HsmSessionType, HsmUserType};
-use many_identity_webauthn::WebAuthnIdentity;
-use many_mock::{parse_mockfile, server::ManyMockServer, MockEntries};
+use many_identity_webauthn::{WebAuthnIdentity, WebAuthnVerifier};
+use many_mock::{parse_mockfile, parse_scenario, server::ManyMockServer, MockEntries, Scenario};
 use many_modules::r#async::attributes::AsyncAttribute;
 use many_modules::r#async::{StatusArgs, StatusReturn};
 use many_modules::{idstore, ledger};
@@ -19,10 +23,13 @@ use many_protocol::{
 };
 use many_server::transport::http::HttpServer;
 use many_server::ManyServer;
-use many_types::{attributes::Attribute, Timestamp};
+use many_types::attributes::{Attribute, AttributeSet};
+use many_types::deadline::Deadline;
+use many_types::Timestamp;
 use std::convert::TryFrom;
 use std::io::{stderr, IsTerminal};
 use std::net::SocketAddr;
+use std::num::NonZeroUsize;
 use std::path::PathBuf;
 use std::process;
 use std::sync::Arc;
@@ -30,11 +37,19 @@ use std::time::{Duration, SystemTime};
 use tracing::{error, info, trace};
 use url::Url;
 
+mod compute;
+mod hardware_wallet;
+mod shell;
+mod threshold;
+
 #[derive(Parser)]
 struct Opts {
     #[clap(flatten)]
     verbosity: many_cli_helpers::Verbosity,
 
+    #[clap(flatten)]
+    output_flags: OutputFlags,
+
     #[clap(subcommand)]
     subcommand: SubCommand,
 }
@@ -49,6 +64,9 @@ enum SubCommand {
     /// Display the textual ID of a public key located on an HSM.
     HsmId(HsmIdOpt),
 
+    /// Display the textual ID of a public key located on a Ledger device.
+    LedgerId(LedgerIdOpt),
+
     /// Display the textual ID of a webauthn key.
     WebauthnId(WebauthnIdOpt),
 
@@ -61,6 +79,20 @@ enum SubCommand {
 
     /// Get the token ID per string of a ledger's token.
     GetTokenId(GetTokenIdOpt),
+
+    /// Deploy and manage workloads on a compute server.
+    Compute(compute::ComputeOpt),
+
+    /// Start an interactive shell to call the methods of a server.
+    Shell(shell::ShellOpt),
+
+    /// Verify the signatures of request envelopes stored in a file, without
+    /// a server.
+    Verify(VerifyOpt),
+
+    /// Generate and inspect threshold Ed25519 keys, controlled jointly by
+    /// several signers.
+    Threshold(threshold::ThresholdOpt),
 }
 
 #[derive(Parser)]
@@ -88,6 +120,17 @@ struct HsmIdOpt {
     subid: Option<u32>,
 }
 
+#[derive(Parser)]
+struct LedgerIdOpt {
+    /// The derivation path of the key on the device. By default, uses
+    /// `m/44'/1'/0'/0'/0'`.
+    #[clap(long)]
+    path: Option<String>,
+
+    /// Allow to generate the identity with a specific subresource ID.
+    subid: Option<u32>,
+}
+
 #[derive(Parser)]
 struct WebauthnIdOpt {
     /// URL to the relying party (the MANY server implementing idstore).
@@ -180,6 +223,29 @@ struct MessageOpt {
     #[clap(long)]
     to: Option<Address>,
 
+    /// The public key file of a threshold group to sign the message with,
+    /// created by `many threshold keygen`.
+    #[clap(long, conflicts_with("pem"), requires("threshold_share"))]
+    threshold_group: Option<PathBuf>,
+
+    /// A share of the threshold group to sign with. Pass it as many times as
+    /// signers are needed to sign.
+    #[clap(long, requires("threshold_group"))]
+    threshold_share: Vec<PathBuf>,
+
+    /// Sign with the key of a Ledger device, approving the message on its
+    /// screen. The MANY app needs to be open on the device.
+    #[clap(
+        long,
+        conflicts_with_all(&["pem", "webauthn", "threshold_group", "module"])
+    )]
+    ledger: bool,
+
+    /// The derivation path of the key on the Ledger device. By default, uses
+    /// `m/44'/1'/0'/0'/0'`.
+    #[clap(long, requires("ledger"))]
+    ledger_path: Option<String>,
+
     /// HSM PKCS#11 module path
     #[clap(long, conflicts_with("pem"))]
     module: Option<PathBuf>,
@@ -203,6 +269,11 @@ struct MessageOpt {
     /// the specification for more information.
     #[clap(long)]
     proof: Option<bool>,
+
+    /// The number of seconds after its timestamp during which the message
+    /// can execute. Servers reject it, or cancel it, after this deadline.
+    #[clap(long)]
+    deadline: Option<u64>,
 }
 
 #[derive(Parser)]
@@ -223,6 +294,11 @@ struct ServerOpt {
     /// Default is mockfile.toml, gives an error if the file does not exist
     #[clap(long, short, value_parser = parse_mockfile)]
     mockfile: Option<MockEntries>,
+
+    /// The path to a scenario file (TOML or JSON) of steps answering the
+    /// calls, before the mockfile.
+    #[clap(long, value_parser = parse_scenario)]
+    scenario: Option<Scenario>,
 }
 
 #[derive(Parser)]
@@ -235,12 +311,23 @@ struct GetTokenIdOpt {
     symbol: String,
 }
 
+#[derive(Parser)]
+struct VerifyOpt {
+    /// A file containing one request envelope per line, encoded in hexadecimal
+    /// or base64.
+    path: PathBuf,
+
+    /// Number of threads verifying envelopes. Defaults to the number of CPUs.
+    #[clap(long)]
+    threads: Option<NonZeroUsize>,
+}
+
 #[async_recursion(?Send)]
 async fn show_response<'a>(
     response: &'a ResponseMessage,
     client: ManyClient<impl Identity + 'a>,
     r#async: bool,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let ResponseMessage {
         data, attributes, ..
     } = response;
@@ -289,7 +376,7 @@ async fn show_response<'a>(
                     StatusReturn::Expired => {
                         progress(".", true);
                         info!("Async token expired before we could check it.");
-                        return Ok(());
+                        return Ok(CommandOutput::cbor(vec![]));
                     }
                     _ => {
                         progress(".", false);
@@ -298,14 +385,29 @@ async fn show_response<'a>(
                 }
             }
         }
+        Ok(CommandOutput::cbor(vec![]))
     } else {
-        println!(
-            "{}",
-            cbor_diag::parse_bytes(&payload).unwrap().to_diag_pretty()
-        );
+        let diag = cbor_diag::parse_bytes(&payload).unwrap().to_diag_pretty();
+        Ok(CommandOutput::cbor(payload).with_plain(diag))
     }
+}
 
-    Ok(())
+/// The attributes of a request: whether it requests a proof, and its deadline
+/// in seconds after its timestamp, if any.
+fn request_attributes(
+    proof: bool,
+    deadline: Option<u64>,
+    timestamp: Option<SystemTime>,
+) -> Result<AttributeSet, many_error::ManyError> {
+    let mut attributes = AttributeSet::new();
+    if proof {
+        attributes.insert(Attribute::id(3));
+    }
+    if let Some(secs) = deadline {
+        let start = timestamp.map_or_else(|| Ok(Timestamp::now()), Timestamp::from_system_time)?;
+        attributes.insert(Deadline(Timestamp::new(start.secs() + secs)?).into());
+    }
+    Ok(attributes)
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -317,8 +419,8 @@ async fn message(
     data: Vec<u8>,
     timestamp: Option<SystemTime>,
     r#async: bool,
-    proof: bool,
-) -> Result<(), ClientServerError> {
+    attributes: AttributeSet,
+) -> Result<CommandOutput, ClientServerError> {
     let address = key.address();
     let client = ManyClient::new(s, to, key).unwrap();
 
@@ -333,15 +435,7 @@ async fn message(
         .method(method)
         .data(data)
         .nonce(nonce.to_vec())
-        .attributes(
-            if proof {
-                vec![Attribute::id(3)]
-            } else {
-                vec![]
-            }
-            .into_iter()
-            .collect(),
-        );
+        .attributes(attributes);
 
     if let Some(ts) = timestamp {
         builder.timestamp(Timestamp::from_system_time(ts)?);
@@ -362,7 +456,7 @@ async fn message_from_hex(
     key: impl Identity,
     hex: String,
     r#async: bool,
-) -> Result<(), ClientServerError> {
+) -> Result<CommandOutput, ClientServerError> {
     let client = ManyClient::new(s.clone(), to, key).unwrap();
 
     let data = hex::decode(hex).map_err(|e| anyhow!(e))?;
@@ -420,10 +514,18 @@ async fn create_webauthn_identity(
     .expect("Could not create Identity object")
 }
 
+fn print_output(output: CommandOutput, format: OutputFormat) {
+    if let Err(err) = output.print(format) {
+        error!("{err}");
+        process::exit(1);
+    }
+}
+
 #[tokio::main]
 async fn main() {
     let Opts {
         verbosity,
+        output_flags: OutputFlags { output: format },
         subcommand,
     } = Opts::parse();
     tracing_subscriber::fmt()
@@ -440,7 +542,7 @@ async fn main() {
                                 .with_subresource_id(subid)
                                 .expect("Invalid subresource id");
                         }
-                        println!("{i}")
+                        print_output(CommandOutput::address(&i), format)
                     }
                     Err(e) => {
                         error!("Identity did not parse: {:?}", e.to_string());
@@ -453,17 +555,17 @@ async fn main() {
                         .with_subresource_id(subid)
                         .expect("Invalid subresource id");
                 }
-                println!("{}", hex::encode(i.to_vec()));
-            } else if let Ok(pem_content) = std::fs::read_to_string(&o.arg) {
+                print_output(CommandOutput::text(hex::encode(i.to_vec())), format);
+            } else if let Ok(key) = read_pem(&o.arg) {
                 // Create the identity from the public key hash.
-                let mut i = CoseKeyIdentity::from_pem(pem_content).unwrap().address();
+                let mut i = key.address();
                 if let Some(subid) = o.subid {
                     i = i
                         .with_subresource_id(subid)
                         .expect("Invalid subresource id");
                 }
 
-                println!("{i}");
+                print_output(CommandOutput::address(&i), format);
             } else {
                 error!("Could not understand the argument.");
                 process::exit(2);
@@ -492,11 +594,32 @@ async fn main() {
                     .expect("Invalid subresource id");
             }
 
-            println!("{id}");
+            print_output(CommandOutput::address(&id), format);
+        }
+        SubCommand::LedgerId(o) => {
+            let path = o
+                .path
+                .as_deref()
+                .unwrap_or(hardware_wallet::DEFAULT_DERIVATION_PATH);
+            let mut id = match hardware_wallet::LedgerIdentity::connect(path) {
+                Ok(identity) => identity.address(),
+                Err(err) => {
+                    error!("{err}");
+                    process::exit(1);
+                }
+            };
+
+            if let Some(subid) = o.subid {
+                id = id
+                    .with_subresource_id(subid)
+                    .expect("Invalid subresource id");
+            }
+
+            print_output(CommandOutput::address(&id), format);
         }
         SubCommand::WebauthnId(o) => {
             let identity = create_webauthn_identity(o.rp, None, o.phrase, o.address, None).await;
-            println!("{}", identity.address());
+            print_output(CommandOutput::address(&identity.address()), format);
         }
         SubCommand::Message(o) => {
             let to_identity = o.to.unwrap_or_default();
@@ -533,9 +656,26 @@ async fn main() {
                     HsmIdentity::new(HsmMechanismType::ECDSA)
                         .expect("Unable to create CoseKeyIdentity from HSM"),
                 )
+            } else if o.ledger {
+                let path = o
+                    .ledger_path
+                    .as_deref()
+                    .unwrap_or(hardware_wallet::DEFAULT_DERIVATION_PATH);
+                match hardware_wallet::LedgerIdentity::connect(path) {
+                    Ok(identity) => Box::new(identity),
+                    Err(err) => {
+                        error!("{err}");
+                        process::exit(1);
+                    }
+                }
+            } else if let Some(group) = o.threshold_group {
+                Box::new(
+                    threshold::threshold_identity(&group, &o.threshold_share)
+                        .expect("Could not create the threshold identity"),
+                )
             } else if let Some(p) = o.pem {
                 // If `pem` is not provided, use anonymous and don't sign.
-                Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap())
+                Box::new(read_pem(p).unwrap())
             } else if o.webauthn {
                 let rp =
                     o.rp.as_ref()
@@ -554,6 +694,9 @@ async fn main() {
                 Box::new(AnonymousIdentity)
             };
 
+            let attributes = request_attributes(o.proof.unwrap_or_default(), o.deadline, timestamp)
+                .expect("Invalid deadline");
+
             if let Some(s) = o.server {
                 let result = if let Some(hex) = o.from_hex {
                     message_from_hex(s, to_identity, from_identity, hex, o.r#async).await
@@ -566,13 +709,13 @@ async fn main() {
                         data,
                         timestamp,
                         o.r#async,
-                        o.proof.unwrap_or_default(),
+                        attributes,
                     )
                     .await
                 };
 
                 match result {
-                    Ok(()) => {}
+                    Ok(output) => print_output(output, format),
                     Err(err) => {
                         error!("{err}");
                         std::process::exit(1);
@@ -586,14 +729,7 @@ async fn main() {
                     .to(to_identity)
                     .method(o.method.expect("--method is required"))
                     .data(data)
-                    .attributes(
-                        match o.proof {
-                            Some(false) | None => vec![],
-                            Some(true) => vec![Attribute::id(3)],
-                        }
-                        .into_iter()
-                        .collect(),
-                    );
+                    .attributes(attributes);
                 if let Some(ts) = timestamp {
                     builder.timestamp(Timestamp::from_system_time(ts).unwrap());
                 }
@@ -602,21 +738,19 @@ async fn main() {
 
                 let cose = encode_cose_sign1_from_request(message, &from_identity).unwrap();
                 let bytes = cose.to_vec().unwrap();
-                if o.hex {
-                    println!("{}", hex::encode(&bytes));
+                let encoded = if o.hex {
+                    hex::encode(&bytes)
                 } else if o.base64 {
-                    println!("{}", general_purpose::STANDARD.encode(&bytes));
+                    general_purpose::STANDARD.encode(&bytes)
                 } else {
                     panic!("Must specify one of hex, base64 or server...");
-                }
+                };
+                print_output(CommandOutput::text(encoded), format);
             }
         }
         SubCommand::Server(o) => {
-            let pem = std::fs::read_to_string(&o.pem).expect("Could not read PEM file.");
-            let key = Arc::new(
-                CoseKeyIdentity::from_pem(&pem)
-                    .expect("Could not generate identity from PEM file."),
-            );
+            let key =
+                Arc::new(read_pem(&o.pem).expect("Could not generate identity from PEM file."));
 
             let many = ManyServer::simple(
                 o.name,
@@ -625,9 +759,12 @@ async fn main() {
                 Some(std::env!("CARGO_PKG_VERSION").to_string()),
             );
             let mockfile = o.mockfile.unwrap_or_default();
-            if !mockfile.is_empty() {
+            if !mockfile.is_empty() || o.scenario.is_some() {
                 let mut many_locked = many.lock().unwrap();
-                let mock_server = ManyMockServer::new(mockfile, None, key);
+                let mut mock_server = ManyMockServer::new(mockfile, None, key);
+                if let Some(scenario) = o.scenario {
+                    mock_server = mock_server.with_scenario(scenario);
+                }
                 many_locked.set_fallback_module(mock_server);
             }
             HttpServer::new(many).bind(o.addr).await.unwrap();
@@ -661,7 +798,92 @@ async fn main() {
                 .ok_or_else(|| format!("Could not resolve symbol '{}'", &symbol))
                 .unwrap();
 
-            println!("{id}");
+            print_output(CommandOutput::address(&id), format);
+        }
+        SubCommand::Compute(o) => {
+            if let Err(err) = compute::compute(o, format).await {
+                error!("{err}");
+                process::exit(1);
+            }
+        }
+        SubCommand::Threshold(o) => {
+            if let Err(err) = threshold::threshold(o, format) {
+                error!("{err}");
+                process::exit(1);
+            }
+        }
+        SubCommand::Shell(o) => {
+            if let Err(err) = shell::shell(o).await {
+                error!("{err}");
+                process::exit(1);
+            }
+        }
+        SubCommand::Verify(o) => {
+            let content = std::fs::read_to_string(&o.path).expect("Could not read file.");
+            let envelopes = content
+                .lines()
+                .map(|line| line.trim())
+                .filter(|line| !line.is_empty())
+                .map(|line| {
+                    hex::decode(line)
+                        .or_else(|_| general_purpose::STANDARD.decode(line))
+                        .unwrap_or_default()
+                })
+                .collect::<Vec<_>>();
+
+            let mut verifier = BulkVerifier::new((
+                AnonymousVerifier,
+                CoseKeyVerifier,
+                WebAuthnVerifier::new(None),
+            ));
+            if let Some(threads) = o.threads {
+                verifier = verifier.with_threads(threads);
+            }
+
+            let progress = ProgressBar::new(envelopes.len() as u64).with_message("Verifying");
+            progress.set_style(
+                ProgressStyle::with_template("{msg} [{bar:40}] {pos}/{len}")
+                    .unwrap()
+                    .progress_chars("=> "),
+            );
+            let results =
+                verifier.verify_all(&envelopes, |p| progress.set_position(p.done() as u64));
+            progress.finish_and_clear();
+
+            let failures: Vec<_> = results
+                .iter()
+                .enumerate()
+                .filter_map(|(i, result)| result.as_ref().err().map(|e| (i + 1, e.to_string())))
+                .collect();
+            let failed = failures.len();
+
+            let mut plain: Vec<String> = failures
+                .iter()
+                .map(|(envelope, e)| format!("Envelope {envelope}: {e}"))
+                .collect();
+            plain.push(format!(
+                "Verified {} envelope(s), {failed} failed.",
+                results.len() - failed
+            ));
+            let json = serde_json::json!({
+                "verified": results.len() - failed,
+                "failed": failures
+                    .iter()
+                    .map(|(envelope, error)| serde_json::json!({ "envelope": envelope, "error": error }))
+                    .collect::<Vec<_>>(),
+            });
+            let output = CommandOutput::encode(&(results.len() - failed, &failures))
+                .map(|output| output.with_json(json).with_plain(plain.join("\n")));
+            match output {
+                Ok(output) => print_output(output, format),
+                Err(err) => {
+                    error!("{err}");
+                    process::exit(1);
+                }
+            }
+            if failed > 0 {
+                process::exit(1);
+            }
         }
     }
 }
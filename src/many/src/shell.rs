@@ -0,0 +1,388 @@
+use crate::show_response;
+use anyhow::anyhow;
+use clap::Parser;
+use many_cli_helpers::error::ClientServerError;
+use many_cli_helpers::identity::read_pem;
+use many_client::ManyClient;
+use many_identity::{Address, AnonymousIdentity, Identity};
+use many_modules::base;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::DefaultHistory;
+use rustyline::validate::Validator;
+use rustyline::Editor;
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, warn};
+
+const HELP: &str = "\
+Commands:
+  <method> [data]        Call a method, with its data in CBOR diagnostic notation,
+                         e.g. `ledger.balance {}`.
+  .identity              Show the identity used to sign messages.
+  .identity <pem>        Sign messages with the key of a PEM file.
+  .identity anonymous    Send anonymous messages.
+  .to <address>          Set the identity of the server messages are sent to.
+  .async                 Toggle waiting for the result of commands.
+  .methods               List the methods of the server.
+  .help                  Show this help.
+  .exit                  Leave the shell (or Ctrl-D).";
+
+/// The shell commands, completed along with the methods of the server.
+const SHELL_COMMANDS: &[&str] = &[".identity", ".to", ".async", ".methods", ".help", ".exit"];
+
+#[derive(Parser)]
+pub struct ShellOpt {
+    /// The server to connect to.
+    #[clap(default_value = "http://localhost:8000")]
+    server: url::Url,
+
+    /// The identity of the server (an identity string), or anonymous if you don't know it.
+    #[clap(long, default_value_t)]
+    server_id: Address,
+
+    /// A PEM file for the identity. By default, the identity selected in the
+    /// previous session, or anonymous.
+    #[clap(long)]
+    pem: Option<PathBuf>,
+
+    /// Show the async token of commands instead of waiting for their result.
+    #[clap(long)]
+    r#async: bool,
+}
+
+/// A line typed in the shell.
+#[derive(Debug, Eq, PartialEq)]
+enum Line<'a> {
+    Empty,
+    Call {
+        method: &'a str,
+        data: Option<&'a str>,
+    },
+    Identity(Option<&'a str>),
+    To(&'a str),
+    Async,
+    Methods,
+    Help,
+    Exit,
+}
+
+impl<'a> Line<'a> {
+    fn parse(line: &'a str) -> Result<Self, String> {
+        let line = line.trim();
+        let (head, rest) = match line.split_once(char::is_whitespace) {
+            Some((head, rest)) => (head, Some(rest.trim()).filter(|r| !r.is_empty())),
+            None => (line, None),
+        };
+        match (head, rest) {
+            ("", _) => Ok(Line::Empty),
+            (".identity", arg) => Ok(Line::Identity(arg)),
+            (".to", Some(address)) => Ok(Line::To(address)),
+            (".async", None) => Ok(Line::Async),
+            (".methods", None) => Ok(Line::Methods),
+            (".help", None) => Ok(Line::Help),
+            (".exit", None) => Ok(Line::Exit),
+            (command, _) if command.starts_with('.') => {
+                Err(format!("Invalid command '{line}', see `.help`."))
+            }
+            (method, data) => Ok(Line::Call { method, data }),
+        }
+    }
+}
+
+/// Completes the first word of a line with the shell commands and the
+/// methods of the server.
+struct ShellHelper {
+    methods: BTreeSet<String>,
+}
+
+impl ShellHelper {
+    fn candidates(&self, prefix: &str) -> Vec<String> {
+        SHELL_COMMANDS
+            .iter()
+            .map(|c| c.to_string())
+            .chain(self.methods.iter().cloned())
+            .filter(|c| c.starts_with(prefix))
+            .collect()
+    }
+}
+
+impl Completer for ShellHelper {
+    type Candidate = Pair;
+
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &rustyline::Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<Pair>)> {
+        let prefix = &line[..pos];
+        // Only the method (or command) is completed, not its data.
+        if prefix.contains(char::is_whitespace) {
+            return Ok((pos, vec![]));
+        }
+        let candidates = self
+            .candidates(prefix)
+            .into_iter()
+            .map(|c| Pair {
+                display: c.clone(),
+                replacement: c,
+            })
+            .collect();
+        Ok((0, candidates))
+    }
+}
+
+impl Hinter for ShellHelper {
+    type Hint = String;
+}
+
+impl Highlighter for ShellHelper {}
+
+impl Validator for ShellHelper {}
+
+impl rustyline::Helper for ShellHelper {}
+
+/// The directory where the history and the selected identity are kept
+/// between sessions.
+fn state_dir() -> Option<PathBuf> {
+    let dir = PathBuf::from(std::env::var_os("HOME")?).join(".many");
+    std::fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+fn load_identity(pem: Option<&PathBuf>) -> Result<Box<dyn Identity>, ClientServerError> {
+    match pem {
+        Some(p) => Ok(Box::new(read_pem(p)?)),
+        None => Ok(Box::new(AnonymousIdentity)),
+    }
+}
+
+struct Shell {
+    server: url::Url,
+    server_id: Address,
+    pem: Option<PathBuf>,
+    key: Arc<Box<dyn Identity>>,
+    r#async: bool,
+    state_dir: Option<PathBuf>,
+}
+
+impl Shell {
+    fn client(&self) -> Result<ManyClient<Arc<Box<dyn Identity>>>, ClientServerError> {
+        ManyClient::new(self.server.clone(), self.server_id, self.key.clone())
+            .map_err(|e| anyhow!(e).into())
+    }
+
+    async fn methods(&self) -> Result<BTreeSet<String>, ClientServerError> {
+        let response = self.client()?.call_("endpoints", ()).await?;
+        let base::Endpoints(methods) = minicbor::decode(&response)?;
+        Ok(methods)
+    }
+
+    /// Select the identity signing the messages, and remember it for the
+    /// next sessions.
+    fn set_identity(&mut self, pem: Option<PathBuf>) -> Result<(), ClientServerError> {
+        self.key = Arc::new(load_identity(pem.as_ref())?);
+        self.pem = pem;
+        if let Some(dir) = &self.state_dir {
+            let path = dir.join("shell_identity");
+            let result = match &self.pem {
+                Some(pem) => std::fs::canonicalize(pem)
+                    .and_then(|pem| std::fs::write(&path, pem.to_string_lossy().as_bytes())),
+                None => std::fs::remove_file(&path).or_else(|e| match e.kind() {
+                    std::io::ErrorKind::NotFound => Ok(()),
+                    _ => Err(e),
+                }),
+            };
+            if let Err(e) = result {
+                warn!("Could not save the identity: {e}");
+            }
+        }
+        Ok(())
+    }
+
+    async fn call(&self, method: &str, data: Option<&str>) -> Result<(), ClientServerError> {
+        let data = match data {
+            Some(data) => cbor_diag::parse_diag(data)
+                .map_err(|e| anyhow!("Invalid CBOR diagnostic notation: {e}"))?
+                .to_bytes(),
+            None => vec![],
+        };
+        let client = self.client()?;
+        let response = client.call_raw(method, &data).await?;
+        show_response(&response, client, self.r#async).await
+    }
+
+    /// Run a line, returning whether the shell should continue.
+    async fn run(
+        &mut self,
+        line: &str,
+        helper: Option<&mut ShellHelper>,
+    ) -> Result<bool, ClientServerError> {
+        match Line::parse(line).map_err(|e| anyhow!(e))? {
+            Line::Empty => {}
+            Line::Call { method, data } => self.call(method, data).await?,
+            Line::Identity(None) => {
+                let pem = self
+                    .pem
+                    .as_ref()
+                    .map_or_else(String::new, |p| format!(" ({})", p.display()));
+                println!("{}{pem}", self.key.address());
+            }
+            Line::Identity(Some("anonymous")) => self.set_identity(None)?,
+            Line::Identity(Some(pem)) => {
+                self.set_identity(Some(PathBuf::from(pem)))?;
+                println!("{}", self.key.address());
+            }
+            Line::To(address) => {
+                self.server_id = address.parse().map_err(|e| anyhow!("{e}"))?;
+            }
+            Line::Async => {
+                self.r#async = !self.r#async;
+                println!(
+                    "{}",
+                    if self.r#async {
+                        "Showing the async token of commands."
+                    } else {
+                        "Waiting for the result of commands."
+                    }
+                );
+            }
+            Line::Methods => {
+                let methods = self.methods().await?;
+                for method in &methods {
+                    println!("{method}");
+                }
+                if let Some(helper) = helper {
+                    helper.methods = methods;
+                }
+            }
+            Line::Help => println!("{HELP}"),
+            Line::Exit => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+pub async fn shell(opt: ShellOpt) -> Result<(), ClientServerError> {
+    let ShellOpt {
+        server,
+        server_id,
+        pem,
+        r#async,
+    } = opt;
+    let state_dir = state_dir();
+    let pem = pem.or_else(|| {
+        let path = state_dir.as_ref()?.join("shell_identity");
+        let pem = std::fs::read_to_string(path).ok()?;
+        Some(PathBuf::from(pem.trim()))
+    });
+
+    let mut shell = Shell {
+        server,
+        server_id,
+        key: Arc::new(load_identity(pem.as_ref())?),
+        pem,
+        r#async,
+        state_dir,
+    };
+
+    let methods = shell.methods().await.unwrap_or_else(|e| {
+        warn!("Could not list the methods of the server: {e}");
+        BTreeSet::new()
+    });
+    let mut editor: Editor<ShellHelper, DefaultHistory> = Editor::new().map_err(|e| anyhow!(e))?;
+    editor.set_helper(Some(ShellHelper { methods }));
+    let history = shell
+        .state_dir
+        .as_ref()
+        .map(|dir| dir.join("shell_history"));
+    if let Some(history) = &history {
+        // The history does not exist on the first session.
+        let _ = editor.load_history(history);
+    }
+
+    println!(
+        "Connected to {} as {}. Type `.help` for help.",
+        shell.server,
+        shell.key.address()
+    );
+    loop {
+        let line = match editor.readline("many> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(anyhow!(e).into()),
+        };
+        if !line.trim().is_empty() {
+            let _ = editor.add_history_entry(line.as_str());
+        }
+
+        match shell.run(&line, editor.helper_mut()).await {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => error!("{e}"),
+        }
+    }
+
+    if let Some(history) = &history {
+        if let Err(e) = editor.save_history(history) {
+            warn!("Could not save the history: {e}");
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Line::parse("  "), Ok(Line::Empty));
+        assert_eq!(
+            Line::parse("ledger.info"),
+            Ok(Line::Call {
+                method: "ledger.info",
+                data: None
+            })
+        );
+        assert_eq!(
+            Line::parse("ledger.balance  { 0: [\"mqbfbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wiaaaaqnz\"] } "),
+            Ok(Line::Call {
+                method: "ledger.balance",
+                data: Some("{ 0: [\"mqbfbahksdwaqeenayy2gxke32hgb7aq4ao4wt745lsfs6wiaaaaqnz\"] }")
+            })
+        );
+        assert_eq!(Line::parse(".identity"), Ok(Line::Identity(None)));
+        assert_eq!(
+            Line::parse(".identity  id1.pem"),
+            Ok(Line::Identity(Some("id1.pem")))
+        );
+        assert_eq!(Line::parse(".to maa"), Ok(Line::To("maa")));
+        assert_eq!(Line::parse(".exit"), Ok(Line::Exit));
+        assert!(Line::parse(".to").is_err());
+        assert!(Line::parse(".unknown").is_err());
+    }
+
+    #[test]
+    fn candidates() {
+        let helper = ShellHelper {
+            methods: BTreeSet::from([
+                "ledger.balance".to_string(),
+                "ledger.info".to_string(),
+                "status".to_string(),
+            ]),
+        };
+        assert_eq!(
+            helper.candidates("ledger."),
+            vec!["ledger.balance", "ledger.info"]
+        );
+        assert_eq!(helper.candidates(".i"), vec![".identity"]);
+        assert!(helper.candidates("kvstore").is_empty());
+        assert_eq!(helper.candidates("").len(), SHELL_COMMANDS.len() + 3);
+    }
+}
@@ -0,0 +1,101 @@
+use anyhow::anyhow;
+use clap::Parser;
+use many_cli_helpers::output::{CommandOutput, OutputFormat};
+use many_identity_dsa::threshold::frost::keys::PublicKeyPackage;
+use many_identity_dsa::threshold::{
+    generate_threshold_shares, group_address, ThresholdIdentity, ThresholdShare,
+};
+use serde_json::json;
+use std::path::{Path, PathBuf};
+
+/// The file of the public key of a group, in its output directory.
+const GROUP_FILE: &str = "group.pub";
+
+#[derive(Parser)]
+pub struct ThresholdOpt {
+    #[clap(subcommand)]
+    subcommand: ThresholdCommand,
+}
+
+#[derive(Parser)]
+enum ThresholdCommand {
+    /// Generate the shares of a new group key, jointly controlled by its
+    /// signers, and show the address of the group.
+    Keygen(KeygenOpt),
+
+    /// Show the address of a group from its public key file.
+    Id(IdOpt),
+}
+
+#[derive(Parser)]
+struct KeygenOpt {
+    /// The number of signers needed to sign.
+    #[clap(long)]
+    min_signers: u16,
+
+    /// The number of signers of the group.
+    #[clap(long)]
+    max_signers: u16,
+
+    /// The directory to write the public key of the group (`group.pub`) and
+    /// the share of each signer (`share-<n>.key`) to. Each share must be
+    /// given to its signer only, then removed.
+    #[clap(long)]
+    output: PathBuf,
+}
+
+#[derive(Parser)]
+struct IdOpt {
+    /// The public key file of the group.
+    group: PathBuf,
+}
+
+pub fn read_group(path: &Path) -> Result<PublicKeyPackage, anyhow::Error> {
+    PublicKeyPackage::deserialize(&std::fs::read(path)?)
+        .map_err(|e| anyhow!("Invalid group public key {path:?}: {e}"))
+}
+
+/// The identity of a group signing with shares held locally.
+pub fn threshold_identity(
+    group: &Path,
+    shares: &[PathBuf],
+) -> Result<ThresholdIdentity, anyhow::Error> {
+    let shares = shares
+        .iter()
+        .map(|path| Ok(ThresholdShare::from_bytes(&std::fs::read(path)?)?))
+        .collect::<Result<Vec<_>, anyhow::Error>>()?;
+    Ok(ThresholdIdentity::from_shares(read_group(group)?, shares)?)
+}
+
+fn keygen(opt: KeygenOpt) -> Result<CommandOutput, anyhow::Error> {
+    let (shares, group) = generate_threshold_shares(opt.min_signers, opt.max_signers)?;
+    let address = group_address(&group)?;
+
+    std::fs::create_dir_all(&opt.output)?;
+    std::fs::write(
+        opt.output.join(GROUP_FILE),
+        group
+            .serialize()
+            .map_err(|e| anyhow!("Could not encode the group public key: {e}"))?,
+    )?;
+    let mut files = Vec::new();
+    for (i, share) in shares.iter().enumerate() {
+        let path = opt.output.join(format!("share-{}.key", i + 1));
+        std::fs::write(&path, share.to_bytes()?)?;
+        files.push(path.display().to_string());
+    }
+
+    Ok(CommandOutput::address(&address).with_json(json!({
+        "address": address.to_string(),
+        "group": opt.output.join(GROUP_FILE).display().to_string(),
+        "shares": files,
+    })))
+}
+
+pub fn threshold(opt: ThresholdOpt, format: OutputFormat) -> Result<(), anyhow::Error> {
+    let output = match opt.subcommand {
+        ThresholdCommand::Keygen(o) => keygen(o)?,
+        ThresholdCommand::Id(o) => CommandOutput::address(&group_address(&read_group(&o.group)?)?),
+    };
+    output.print(format)
+}
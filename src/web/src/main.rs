@@ -1,23 +1,32 @@
 use clap::Parser;
+use indicatif::{HumanBytes, ProgressBar, ProgressStyle};
+use many_cli_helpers::identity::read_pem;
+use many_cli_helpers::output::{CommandOutput, OutputFlags};
 use many_client::client::blocking::ManyClient;
 use many_error::ManyError;
 use many_identity::{Address, AnonymousIdentity, Identity};
-use many_identity_dsa::CoseKeyIdentity;
-use many_modules::r#async::{StatusArgs, StatusReturn};
+use many_modules::web;
 use many_modules::web::ListArgs;
-use many_modules::{r#async, web};
-use many_protocol::ResponseMessage;
-use many_types::web::{WebDeploymentFilter, WebDeploymentSource};
+use many_types::web::{WebDeploymentFilter, WebDeploymentInfo, WebDeploymentSource};
 use many_types::{Memo, SortOrder};
-use std::path::PathBuf;
+use serde_json::json;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
 use std::time::Duration;
-use tracing::{debug, error, info};
+use tracing::{debug, error};
+
+/// How long to wait for the result of a command the server processes
+/// asynchronously.
+const WAIT_TIMEOUT: Duration = Duration::from_secs(60);
 
 #[derive(Debug, Parser)]
 struct Opts {
     #[clap(flatten)]
     common_flags: many_cli_helpers::CommonCliFlags,
 
+    #[clap(flatten)]
+    output_flags: OutputFlags,
+
     /// Many server URL to connect to.
     #[clap(default_value = "http://localhost:8000")]
     server: String,
@@ -48,6 +57,9 @@ enum SubCommand {
 
     /// Update website
     Update(UpdateOpt),
+
+    /// Show the information of a website
+    Info(InfoOpt),
 }
 
 #[derive(Debug, Parser)]
@@ -59,7 +71,8 @@ struct DeployOpt {
     #[clap(long)]
     site_description: Option<String>,
 
-    /// Site source
+    /// Site source, either a zip archive or a directory containing an
+    /// `index.html` file, which is zipped before being sent
     source: PathBuf,
 
     /// MANY address of the website owner
@@ -84,7 +97,8 @@ struct UpdateOpt {
     #[clap(long)]
     site_description: Option<String>,
 
-    /// Site source
+    /// Site source, either a zip archive or a directory containing an
+    /// `index.html` file, which is zipped before being sent
     source: PathBuf,
 
     /// MANY address of the website owner
@@ -128,11 +142,208 @@ struct ListOpt {
     #[clap(long)]
     filter: Option<Vec<WebDeploymentFilter>>,
 
+    /// Only list the websites of this owner
+    #[clap(long)]
+    owner: Option<Address>,
+
     /// Page
     #[clap(long)]
     page: Option<usize>,
 }
 
+#[derive(Debug, Parser)]
+struct InfoOpt {
+    /// Site name
+    site_name: String,
+
+    /// MANY address of the website owner. By default, the identity used
+    #[clap(long)]
+    owner: Option<Address>,
+}
+
+/// The name under which the server stores a website.
+fn normalize_site_name(site_name: &str) -> String {
+    site_name.to_lowercase().trim().replace(' ', "_")
+}
+
+/// Zip the content of a directory in memory. The directory must contain an
+/// `index.html` file at its root, like the server expects.
+fn zip_directory(dir: &Path) -> Result<Vec<u8>, ManyError> {
+    if !dir.join("index.html").is_file() {
+        return Err(ManyError::unknown(format!(
+            "{} does not contain an index.html",
+            dir.display()
+        )));
+    }
+
+    let entries = walkdir::WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(ManyError::unknown)?;
+    let total = entries
+        .iter()
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.metadata().map(|m| m.len()))
+        .sum::<Result<u64, _>>()
+        .map_err(ManyError::unknown)?;
+
+    let progress = ProgressBar::new(total).with_message("Archiving");
+    progress.set_style(
+        ProgressStyle::with_template("{msg} [{bar:40}] {bytes}/{total_bytes}")
+            .map_err(ManyError::unknown)?
+            .progress_chars("=> "),
+    );
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options =
+        zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    for entry in entries {
+        // Zip entries always use `/` as the separator.
+        let name = entry
+            .path()
+            .strip_prefix(dir)
+            .map_err(ManyError::unknown)?
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+
+        if entry.file_type().is_dir() {
+            zip.add_directory(name, options)
+                .map_err(ManyError::unknown)?;
+        } else if entry.file_type().is_file() {
+            zip.start_file(name, options).map_err(ManyError::unknown)?;
+            let content = std::fs::read(entry.path()).map_err(ManyError::unknown)?;
+            zip.write_all(&content).map_err(ManyError::unknown)?;
+            progress.inc(content.len() as u64);
+        }
+    }
+    progress.finish_and_clear();
+
+    Ok(zip.finish().map_err(ManyError::unknown)?.into_inner())
+}
+
+fn read_source(source: &Path) -> Result<WebDeploymentSource, ManyError> {
+    let bytes = if source.is_dir() {
+        zip_directory(source)?
+    } else {
+        std::fs::read(source)
+            .map_err(|e| ManyError::unknown(format!("Could not read {}: {e}", source.display())))?
+    };
+    Ok(WebDeploymentSource::Archive(bytes.into()))
+}
+
+fn upload_message(source: &WebDeploymentSource) -> String {
+    let WebDeploymentSource::Archive(bytes) = source;
+    format!("Uploading {}", HumanBytes(bytes.len() as u64))
+}
+
+/// Send a command, showing its progress, and wait for its result if the
+/// server returns it asynchronously.
+fn call_with_progress(
+    client: &ManyClient<impl Identity>,
+    method: &str,
+    argument: impl minicbor::Encode<()>,
+    message: String,
+) -> Result<Vec<u8>, ManyError> {
+    let progress = ProgressBar::new_spinner().with_message(message);
+    progress.enable_steady_tick(Duration::from_millis(100));
+    let response = client.call_and_wait(method, argument, WAIT_TIMEOUT);
+    progress.finish_and_clear();
+
+    let payload = response?.data?;
+    debug!("response: {}", hex::encode(&payload));
+    Ok(payload)
+}
+
+fn format_table(headers: &[&str], rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.len()).collect();
+    for row in &rows {
+        for (w, cell) in widths.iter_mut().zip(row) {
+            *w = (*w).max(cell.len());
+        }
+    }
+
+    let line = |cells: Vec<&str>| {
+        let line = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| format!("{c:<width$}", width = *w))
+            .collect::<Vec<_>>()
+            .join("  ");
+        line.trim_end().to_string()
+    };
+    let mut lines = vec![line(headers.to_vec())];
+    for row in &rows {
+        lines.push(line(row.iter().map(String::as_str).collect()));
+    }
+    lines.join("\n")
+}
+
+fn site_json(site: &WebDeploymentInfo) -> serde_json::Value {
+    json!({
+        "owner": site.owner.to_string(),
+        "site_name": site.site_name,
+        "site_description": site.site_description,
+        "url": site.url,
+        "domain": site.domain,
+    })
+}
+
+fn site_output(cbor: Vec<u8>, site: &WebDeploymentInfo) -> CommandOutput {
+    CommandOutput::cbor(cbor)
+        .with_json(site_json(site))
+        .with_plain(format_table(
+            &["FIELD", "VALUE"],
+            vec![
+                vec!["OWNER".to_string(), site.owner.to_string()],
+                vec!["SITE_NAME".to_string(), site.site_name.clone()],
+                vec![
+                    "SITE_DESCRIPTION".to_string(),
+                    site.site_description.clone().unwrap_or_else(|| "-".into()),
+                ],
+                vec![
+                    "URL".to_string(),
+                    site.url.clone().unwrap_or_else(|| "-".into()),
+                ],
+                vec![
+                    "DOMAIN".to_string(),
+                    site.domain.clone().unwrap_or_else(|| "-".into()),
+                ],
+            ],
+        ))
+}
+
+fn sites_output(cbor: Vec<u8>, list: &web::ListReturns) -> CommandOutput {
+    let sites: Vec<_> = list.deployments.iter().map(site_json).collect();
+    let table = format_table(
+        &["SITE_NAME", "OWNER", "URL", "DOMAIN"],
+        list.deployments
+            .iter()
+            .map(|site| {
+                vec![
+                    site.site_name.clone(),
+                    site.owner.to_string(),
+                    site.url.clone().unwrap_or_else(|| "-".into()),
+                    site.domain.clone().unwrap_or_else(|| "-".into()),
+                ]
+            })
+            .collect(),
+    );
+    CommandOutput::cbor(cbor)
+        .with_json(json!({
+            "total_count": list.total_count,
+            "deployments": sites,
+        }))
+        .with_plain(format!(
+            "{table}\n{} of {} website(s).",
+            list.deployments.len(),
+            list.total_count
+        ))
+}
+
 fn deploy(
     client: ManyClient<impl Identity>,
     site_name: String,
@@ -141,24 +352,21 @@ fn deploy(
     owner: Option<Address>,
     memo: Option<Memo>,
     domain: Option<String>,
-) -> Result<(), ManyError> {
-    // Read the source file
-    let source = std::fs::read(source).map_err(ManyError::unknown)?;
+) -> Result<CommandOutput, ManyError> {
+    let source = read_source(&source)?;
+    let message = upload_message(&source);
     let arguments = web::DeployArgs {
         owner,
         site_name,
         site_description,
-        source: WebDeploymentSource::Archive(source.into()),
+        source,
         memo,
         domain,
     };
-    let response = client.call("web.deploy", arguments)?;
-    let payload = wait_response(client, response)?;
-    println!(
-        "{}",
-        cbor_diag::parse_bytes(payload).unwrap().to_diag_pretty()
-    );
-    Ok(())
+    let payload = call_with_progress(&client, "web.deploy", arguments, message)?;
+    let web::DeployReturns { info } =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+    Ok(site_output(payload, &info))
 }
 
 fn update(
@@ -169,24 +377,21 @@ fn update(
     owner: Option<Address>,
     memo: Option<Memo>,
     domain: Option<String>,
-) -> Result<(), ManyError> {
-    // Read the source file
-    let source = std::fs::read(source).map_err(ManyError::unknown)?;
+) -> Result<CommandOutput, ManyError> {
+    let source = read_source(&source)?;
+    let message = upload_message(&source);
     let arguments = web::UpdateArgs {
         owner,
         site_name,
         site_description,
-        source: WebDeploymentSource::Archive(source.into()),
+        source,
         memo,
         domain,
     };
-    let response = client.call("web.update", arguments)?;
-    let payload = wait_response(client, response)?;
-    println!(
-        "{}",
-        cbor_diag::parse_bytes(payload).unwrap().to_diag_pretty()
-    );
-    Ok(())
+    let payload = call_with_progress(&client, "web.update", arguments, message)?;
+    let web::UpdateReturns { info } =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+    Ok(site_output(payload, &info))
 }
 
 fn remove(
@@ -194,19 +399,22 @@ fn remove(
     site_name: String,
     owner: Option<Address>,
     memo: Option<Memo>,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
+    let normalized = normalize_site_name(&site_name);
     let arguments = web::RemoveArgs {
         owner,
         site_name,
         memo,
     };
-    let response = client.call("web.remove", arguments)?;
-    let payload = wait_response(client, response)?;
-    println!(
-        "{}",
-        cbor_diag::parse_bytes(payload).unwrap().to_diag_pretty()
-    );
-    Ok(())
+    let payload = call_with_progress(
+        &client,
+        "web.remove",
+        arguments,
+        format!("Removing {normalized}"),
+    )?;
+    Ok(CommandOutput::cbor(payload)
+        .with_json(json!({ "site_name": normalized, "removed": true }))
+        .with_plain(format!("Website {normalized} removed.")))
 }
 
 fn list(
@@ -214,86 +422,74 @@ fn list(
     count: Option<usize>,
     order: Option<SortOrder>,
     filter: Option<Vec<WebDeploymentFilter>>,
+    owner: Option<Address>,
     page: Option<usize>,
-) -> Result<(), ManyError> {
+) -> Result<CommandOutput, ManyError> {
+    let filter = match (filter, owner) {
+        (filter, None) => filter,
+        (filter, Some(owner)) => Some(
+            filter
+                .into_iter()
+                .flatten()
+                .chain([WebDeploymentFilter::Owner(owner)])
+                .collect(),
+        ),
+    };
     let args = ListArgs {
         count,
         order,
         filter,
         page,
     };
-    let response = client.call("web.list", args)?;
-    let payload = wait_response(client, response)?;
-    println!(
-        "{}",
-        cbor_diag::parse_bytes(payload).unwrap().to_diag_pretty()
-    );
-    Ok(())
+    let payload = client.call_and_wait("web.list", args, WAIT_TIMEOUT)?.data?;
+    let list: web::ListReturns =
+        minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+    Ok(sites_output(payload, &list))
 }
 
-pub(crate) fn wait_response(
-    client: ManyClient<impl Identity>,
-    response: ResponseMessage,
-) -> Result<Vec<u8>, ManyError> {
-    let ResponseMessage {
-        data, attributes, ..
-    } = response;
-
-    let payload = data?;
-    debug!("response: {}", hex::encode(&payload));
-    if payload.is_empty() {
-        let attr = match attributes.get::<r#async::attributes::AsyncAttribute>() {
-            Ok(attr) => attr,
-            _ => {
-                info!("Empty payload.");
-                return Ok(Vec::new());
-            }
-        };
-        info!("Async token: {}", hex::encode(&attr.token));
-
-        let progress =
-            indicatif::ProgressBar::new_spinner().with_message("Waiting for async response");
-        progress.enable_steady_tick(Duration::from_millis(100));
-
-        // TODO: improve on this by using duration and thread and watchdog.
-        // Wait for the server for ~60 seconds by pinging it every second.
-        for _ in 0..60 {
-            let response = client.call(
-                "async.status",
-                StatusArgs {
-                    token: attr.token.clone(),
-                },
-            )?;
-            let status: StatusReturn =
-                minicbor::decode(&response.data?).map_err(ManyError::deserialization_error)?;
-            match status {
-                StatusReturn::Done { response } => {
-                    progress.finish();
-                    let response: ResponseMessage =
-                        minicbor::decode(&response.payload.ok_or_else(|| {
-                            ManyError::deserialization_error(
-                                "Empty payload. Expected ResponseMessage.",
-                            )
-                        })?)
-                        .map_err(ManyError::deserialization_error)?;
-                    return wait_response(client, response);
-                }
-                StatusReturn::Expired => {
-                    progress.finish();
-                    info!("Async token expired before we could check it.");
-                    return Ok(Vec::new());
-                }
-                _ => {
-                    std::thread::sleep(Duration::from_secs(1));
-                }
-            }
+/// Find a website of an owner, going through the pages of `web.list`.
+fn find_site(
+    client: &ManyClient<impl Identity>,
+    owner: Address,
+    site_name: &str,
+) -> Result<WebDeploymentInfo, ManyError> {
+    let site_name = normalize_site_name(site_name);
+    for page in 1.. {
+        let payload = client.call_(
+            "web.list",
+            ListArgs {
+                count: None,
+                order: None,
+                filter: Some(vec![WebDeploymentFilter::Owner(owner)]),
+                page: Some(page),
+            },
+        )?;
+        let list: web::ListReturns =
+            minicbor::decode(&payload).map_err(ManyError::deserialization_error)?;
+        if list.deployments.is_empty() {
+            break;
+        }
+        if let Some(site) = list
+            .deployments
+            .into_iter()
+            .find(|d| d.site_name == site_name)
+        {
+            return Ok(site);
         }
-        Err(ManyError::unknown(
-            "Transport timed out waiting for async result.",
-        ))
-    } else {
-        Ok(payload)
     }
+    Err(ManyError::unknown(format!(
+        "Website {site_name} not found for {owner}"
+    )))
+}
+
+fn info(
+    client: ManyClient<impl Identity>,
+    site_name: String,
+    owner: Address,
+) -> Result<CommandOutput, ManyError> {
+    let site = find_site(&client, owner, &site_name)?;
+    let cbor = minicbor::to_vec(&site).map_err(ManyError::serialization_error)?;
+    Ok(site_output(cbor, &site))
 }
 
 fn main() {
@@ -303,7 +499,7 @@ fn main() {
         server_id,
         subcommand,
         common_flags,
-        ..
+        output_flags,
     } = Opts::parse();
 
     common_flags.init_logging().unwrap();
@@ -312,8 +508,9 @@ fn main() {
 
     let key = pem.map_or_else(
         || Box::new(AnonymousIdentity) as Box<dyn Identity>,
-        |p| Box::new(CoseKeyIdentity::from_pem(std::fs::read_to_string(p).unwrap()).unwrap()),
+        |p| Box::new(read_pem(p).unwrap()),
     );
+    let address = key.address();
 
     let client = ManyClient::new(server, server_id, key).unwrap();
     let result = match subcommand {
@@ -342,8 +539,9 @@ fn main() {
             count,
             order,
             filter,
+            owner,
             page,
-        }) => list(client, count, order, filter, page),
+        }) => list(client, count, order, filter, owner, page),
         SubCommand::Update(UpdateOpt {
             site_name,
             site_description,
@@ -360,7 +558,15 @@ fn main() {
             memo,
             domain,
         ),
-    };
+        SubCommand::Info(InfoOpt { site_name, owner }) => {
+            info(client, site_name, owner.unwrap_or(address))
+        }
+    }
+    .and_then(|output| {
+        output
+            .print(output_flags.output)
+            .map_err(ManyError::unknown)
+    });
 
     if let Err(err) = result {
         error!(
@@ -373,3 +579,44 @@ fn main() {
         std::process::exit(1);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn site_name() {
+        assert_eq!(normalize_site_name("My Site"), "my_site");
+        assert_eq!(normalize_site_name("site"), "site");
+    }
+
+    #[test]
+    fn zip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("index.html"), "<html></html>").unwrap();
+        std::fs::create_dir(dir.path().join("assets")).unwrap();
+        std::fs::write(dir.path().join("assets").join("app.js"), "hello()").unwrap();
+
+        let bytes = zip_directory(dir.path()).unwrap();
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).unwrap();
+        let mut names: Vec<_> = archive.file_names().map(String::from).collect();
+        names.sort();
+        assert_eq!(names, vec!["assets/", "assets/app.js", "index.html"]);
+
+        let mut content = String::new();
+        archive
+            .by_name("assets/app.js")
+            .unwrap()
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "hello()");
+    }
+
+    #[test]
+    fn zip_requires_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("other.html"), "<html></html>").unwrap();
+        assert!(zip_directory(dir.path()).is_err());
+    }
+}